@@ -0,0 +1,263 @@
+use bulk_email_core::engine::validate_email;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::time::{Duration, Instant};
+
+/// 退信率样本不足时不告警，避免前几封失败就触发。
+const DEFAULT_MIN_ATTEMPTS: u32 = 20;
+
+/// 告警通知的发送方式：通过指定的发件账号把告警邮件发给操作人自己。
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct AlertEmail {
+    pub account_id: String,
+    pub to: String,
+}
+
+/// 发送任务的告警规则；未填写的规则不生效。
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct AlertSettings {
+    /// 失败（退信）占已尝试收件人的百分比超过该值时告警。
+    #[serde(default)]
+    pub bounce_rate_percent: Option<f64>,
+    /// 计算退信率前至少需要的尝试次数。
+    #[serde(default = "default_min_attempts")]
+    pub min_attempts: u32,
+    /// 任务超过该分钟数没有任何进展时告警。
+    #[serde(default)]
+    pub stall_minutes: Option<u32>,
+    /// 每日发送配额已用百分比达到该值时告警。
+    #[serde(default)]
+    pub quota_percent: Option<u32>,
+    /// 为空时只发送桌面通知。
+    #[serde(default)]
+    pub email: Option<AlertEmail>,
+}
+
+impl Default for AlertSettings {
+    fn default() -> Self {
+        AlertSettings {
+            bounce_rate_percent: None,
+            min_attempts: DEFAULT_MIN_ATTEMPTS,
+            stall_minutes: None,
+            quota_percent: None,
+            email: None,
+        }
+    }
+}
+
+fn default_min_attempts() -> u32 {
+    DEFAULT_MIN_ATTEMPTS
+}
+
+impl AlertSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(rate) = self.bounce_rate_percent {
+            if !(rate > 0.0 && rate <= 100.0) {
+                return Err("退信率阈值必须在 0 到 100 之间".to_string());
+            }
+        }
+        if self.stall_minutes == Some(0) {
+            return Err("停滞告警时间必须大于 0 分钟，不启用请留空".to_string());
+        }
+        if let Some(percent) = self.quota_percent {
+            if percent == 0 || percent > 100 {
+                return Err("配额告警阈值必须在 1 到 100 之间".to_string());
+            }
+        }
+        if let Some(email) = &self.email {
+            if email.account_id.trim().is_empty() {
+                return Err("请选择发送告警邮件的账号".to_string());
+            }
+            validate_email(&email.to, "告警收件邮箱")?;
+        }
+        Ok(())
+    }
+
+    fn enabled(&self) -> bool {
+        self.bounce_rate_percent.is_some() || self.stall_minutes.is_some() || self.quota_percent.is_some()
+    }
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertKind {
+    BounceRate,
+    Stalled,
+    Quota,
+    /// 金丝雀邮件进入了垃圾邮件箱；任务配置了金丝雀即生效。
+    CanarySpam,
+}
+
+/// 一次触发的告警，同时作为 `alert` 事件发给前端。
+#[derive(Serialize, Clone, Debug)]
+pub struct Alert {
+    pub kind: AlertKind,
+    pub job_id: String,
+    pub message: String,
+}
+
+impl Alert {
+    pub fn subject(&self) -> String {
+        format!("[Bulk Email Sender] 告警：{}", self.message)
+    }
+
+    pub fn to_event(&self) -> Value {
+        json!({
+            "type": "alert",
+            "kind": self.kind,
+            "job_id": self.job_id,
+            "message": self.message,
+        })
+    }
+}
+
+/// 单个任务的告警状态：每条规则在一个任务内只触发一次。
+pub struct AlertMonitor {
+    settings: AlertSettings,
+    job_id: String,
+    sent: u32,
+    failed: u32,
+    last_activity: Instant,
+    finished: bool,
+    fired: Vec<AlertKind>,
+}
+
+impl AlertMonitor {
+    pub fn new(settings: AlertSettings, now: Instant) -> Self {
+        AlertMonitor {
+            settings,
+            job_id: String::new(),
+            sent: 0,
+            failed: 0,
+            last_activity: now,
+            finished: false,
+            fired: Vec::new(),
+        }
+    }
+
+    pub fn settings(&self) -> &AlertSettings {
+        &self.settings
+    }
+
+    /// 任务是否仍需要停滞检查。
+    pub fn watching(&self) -> bool {
+        self.settings.enabled() && !self.finished
+    }
+
+    /// 记录一条任务事件；任何事件（包括等待倒计时）都视为任务仍有进展。
+    pub fn observe(&mut self, event: &Value, now: Instant) -> Option<Alert> {
+        self.last_activity = now;
+        if let Some(job_id) = event["job_id"].as_str() {
+            self.job_id = job_id.to_string();
+        }
+        match event["type"].as_str() {
+            Some("recipient_sent") => self.sent += 1,
+            Some("recipient_failed") => self.failed += 1,
+            Some("job_finished" | "job_cancelled" | "job_failed" | "error") => self.finished = true,
+            Some("canary_placement") if event["placement"] == "spam" => {
+                let email = event["email"].as_str().unwrap_or_default();
+                let folder = event["folder"].as_str().unwrap_or("垃圾邮件");
+                return self.fire(
+                    AlertKind::CanarySpam,
+                    format!("金丝雀邮箱 {email} 收到的第 {} 轮邮件进入了「{folder}」", event["round"]),
+                );
+            }
+            _ => return None,
+        }
+        let threshold = self.settings.bounce_rate_percent?;
+        let attempts = self.sent + self.failed;
+        if attempts < self.settings.min_attempts.max(1) {
+            return None;
+        }
+        let rate = f64::from(self.failed) * 100.0 / f64::from(attempts);
+        if rate <= threshold {
+            return None;
+        }
+        self.fire(
+            AlertKind::BounceRate,
+            format!("退信率 {rate:.1}% 超过阈值 {threshold}%（{} / {attempts} 封失败）", self.failed),
+        )
+    }
+
+    /// 按当日已发送量与每日上限检查配额。
+    pub fn observe_quota(&mut self, used: u32, limit: u32) -> Option<Alert> {
+        let threshold = self.settings.quota_percent?;
+        if limit == 0 || u64::from(used) * 100 < u64::from(limit) * u64::from(threshold) {
+            return None;
+        }
+        self.fire(
+            AlertKind::Quota,
+            format!("今日发送配额已用 {used} / {limit}（达到 {threshold}% 告警线）"),
+        )
+    }
+
+    pub fn check_stalled(&mut self, now: Instant) -> Option<Alert> {
+        let minutes = self.settings.stall_minutes?;
+        if self.finished || now.duration_since(self.last_activity) < Duration::from_secs(u64::from(minutes) * 60) {
+            return None;
+        }
+        self.fire(AlertKind::Stalled, format!("发送任务已超过 {minutes} 分钟没有进展"))
+    }
+
+    fn fire(&mut self, kind: AlertKind, message: String) -> Option<Alert> {
+        if self.fired.contains(&kind) {
+            return None;
+        }
+        self.fired.push(kind);
+        Some(Alert {
+            kind,
+            job_id: self.job_id.clone(),
+            message,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AlertKind, AlertMonitor, AlertSettings};
+    use serde_json::json;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn fires_each_rule_once_per_job() {
+        let settings = AlertSettings {
+            bounce_rate_percent: Some(10.0),
+            min_attempts: 10,
+            stall_minutes: Some(5),
+            quota_percent: Some(90),
+            email: None,
+        };
+        assert!(settings.validate().is_ok());
+        let start = Instant::now();
+        let mut monitor = AlertMonitor::new(settings, start);
+        let sent = json!({ "type": "recipient_sent", "job_id": "job-1" });
+        let failed = json!({ "type": "recipient_failed", "job_id": "job-1" });
+        for _ in 0..8 {
+            assert!(monitor.observe(&sent, start).is_none());
+        }
+        assert!(monitor.observe(&failed, start).is_none());
+        let alert = monitor.observe(&failed, start).expect("bounce rate alert");
+        assert_eq!(alert.kind, AlertKind::BounceRate);
+        assert_eq!(alert.job_id, "job-1");
+        assert!(monitor.observe(&failed, start).is_none());
+
+        assert!(monitor.observe_quota(89, 100).is_none());
+        assert_eq!(monitor.observe_quota(90, 100).expect("quota alert").kind, AlertKind::Quota);
+
+        assert!(monitor.check_stalled(start + Duration::from_secs(299)).is_none());
+        assert!(monitor.check_stalled(start + Duration::from_secs(300)).is_some());
+        assert!(monitor.check_stalled(start + Duration::from_secs(900)).is_none());
+
+        monitor.observe(&json!({ "type": "job_finished" }), start);
+        assert!(!monitor.watching());
+        let spam = json!({ "type": "canary_placement", "email": "me@163.com", "round": 3, "placement": "spam" });
+        assert_eq!(monitor.observe(&spam, start).expect("canary alert").kind, AlertKind::CanarySpam);
+        assert!(monitor.observe(&spam, start).is_none());
+        assert!(AlertSettings {
+            bounce_rate_percent: Some(0.0),
+            ..AlertSettings::default()
+        }
+        .validate()
+        .is_err());
+    }
+}
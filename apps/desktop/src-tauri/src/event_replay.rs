@@ -0,0 +1,147 @@
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, VecDeque};
+
+/// 每个任务保留的最近事件数；更早的事件仍可从任务日志查看。
+const MAX_EVENTS_PER_JOB: usize = 1000;
+/// 保留事件的任务数；超出时丢弃最早登记的任务。
+const MAX_BUFFERED_JOBS: usize = 20;
+/// 每秒刷新的倒计时事件只推送、不缓存，补发时没有意义且会挤掉收件人结果。
+const TRANSIENT_EVENTS: [&str; 2] = ["inter_send_wait", "scheduled_wait"];
+
+/// `get_job_events` 的结果：`since_seq` 之后缓存的事件与最新的进度快照。
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+pub struct JobEvents {
+    pub events: Vec<Value>,
+    /// 最近一次收件人结果事件附带的进度（见 `SendProgress`）。
+    pub progress: Option<Value>,
+    /// 任务最新事件的序号；界面下次从这里继续拉取。
+    pub latest_seq: u64,
+    /// `since_seq` 之后有事件已被挤出缓存，界面需要以 `progress` 为准。
+    pub truncated: bool,
+}
+
+#[derive(Default)]
+struct JobBuffer {
+    next_seq: u64,
+    events: VecDeque<Value>,
+    progress: Option<Value>,
+}
+
+/// 按任务缓存最近推送的 `worker-event`：界面刷新或重新打开窗口后用 `get_job_events` 补上错过的事件。
+/// 推送的每个事件都带任务内递增的 `seq`，补发与实时事件可按序号去重。
+#[derive(Default)]
+pub struct EventReplay {
+    jobs: BTreeMap<String, JobBuffer>,
+    /// 任务登记的先后顺序，用于淘汰最早的任务。
+    order: VecDeque<String>,
+}
+
+impl EventReplay {
+    /// 为事件编号并缓存；没有 `job_id` 的事件原样推送。
+    pub fn record(&mut self, event: &mut Value) {
+        let Some(job_id) = event["job_id"].as_str().map(str::to_string) else {
+            return;
+        };
+        if !self.jobs.contains_key(&job_id) {
+            if self.order.len() >= MAX_BUFFERED_JOBS {
+                if let Some(oldest) = self.order.pop_front() {
+                    self.jobs.remove(&oldest);
+                }
+            }
+            self.order.push_back(job_id.clone());
+        }
+        let buffer = self.jobs.entry(job_id).or_default();
+        buffer.next_seq += 1;
+        event["seq"] = json!(buffer.next_seq);
+        if !event["progress"].is_null() {
+            buffer.progress = Some(event["progress"].clone());
+        }
+        if TRANSIENT_EVENTS.contains(&event["type"].as_str().unwrap_or_default()) {
+            return;
+        }
+        if buffer.events.len() >= MAX_EVENTS_PER_JOB {
+            buffer.events.pop_front();
+        }
+        buffer.events.push_back(event.clone());
+    }
+
+    /// 同一任务 ID 重新提交（续发）时清空上一次运行缓存的事件与进度；序号继续递增，界面的去重不受影响。
+    pub fn restart(&mut self, job_id: &str) {
+        if let Some(buffer) = self.jobs.get_mut(job_id) {
+            buffer.events.clear();
+            buffer.progress = None;
+        }
+    }
+
+    /// 返回任务中序号大于 `since_seq` 的缓存事件；任务没有缓存时返回 `None`。
+    pub fn since(&self, job_id: &str, since_seq: u64) -> Option<JobEvents> {
+        let buffer = self.jobs.get(job_id)?;
+        let first_seq = buffer.events.front().and_then(|event| event["seq"].as_u64()).unwrap_or(buffer.next_seq + 1);
+        Some(JobEvents {
+            events: buffer
+                .events
+                .iter()
+                .filter(|event| event["seq"].as_u64().is_some_and(|seq| seq > since_seq))
+                .cloned()
+                .collect(),
+            progress: buffer.progress.clone(),
+            latest_seq: buffer.next_seq,
+            truncated: buffer.events.len() == MAX_EVENTS_PER_JOB && first_seq > since_seq + 1,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EventReplay, MAX_BUFFERED_JOBS, MAX_EVENTS_PER_JOB};
+    use serde_json::json;
+
+    #[test]
+    fn replays_events_after_a_sequence_number() {
+        let mut replay = EventReplay::default();
+        let mut started = json!({ "type": "job_started", "job_id": "job-a", "total": 2 });
+        replay.record(&mut started);
+        assert_eq!(started["seq"], 1);
+        replay.record(&mut json!({ "type": "inter_send_wait", "job_id": "job-a", "remaining_sec": 3 }));
+        let progress = json!({ "sent": 1, "processed": 1 });
+        replay.record(&mut json!({ "type": "recipient_sent", "job_id": "job-a", "progress": progress }));
+        replay.record(&mut json!({ "type": "job_started", "job_id": "job-b", "total": 1 }));
+
+        let events = replay.since("job-a", 0).expect("job-a buffered");
+        let seqs: Vec<_> = events.events.iter().map(|event| event["seq"].as_u64().unwrap_or_default()).collect();
+        assert_eq!(seqs, [1, 3]);
+        assert_eq!((events.latest_seq, events.truncated), (3, false));
+        assert_eq!(events.progress, Some(progress));
+        assert!(replay.since("job-a", 3).expect("job-a buffered").events.is_empty());
+        replay.restart("job-a");
+        let mut resumed = json!({ "type": "job_started", "job_id": "job-a", "total": 1 });
+        replay.record(&mut resumed);
+        let events = replay.since("job-a", 0).expect("job-a buffered");
+        assert_eq!((events.events, events.progress), (vec![resumed], None));
+        assert_eq!(replay.since("job-b", 0).map(|events| events.latest_seq), Some(1));
+        assert!(replay.since("job-c", 0).is_none());
+
+        let mut unscoped = json!({ "type": "alert" });
+        replay.record(&mut unscoped);
+        assert!(unscoped.get("seq").is_none());
+    }
+
+    #[test]
+    fn drops_the_oldest_events_and_jobs() {
+        let mut replay = EventReplay::default();
+        for _ in 0..MAX_EVENTS_PER_JOB + 5 {
+            replay.record(&mut json!({ "type": "recipient_sent", "job_id": "job-0" }));
+        }
+        let events = replay.since("job-0", 2).expect("job-0 buffered");
+        assert_eq!(events.events.len(), MAX_EVENTS_PER_JOB);
+        assert!(events.truncated);
+        assert!(!replay.since("job-0", 5).expect("job-0 buffered").truncated);
+
+        for index in 1..=MAX_BUFFERED_JOBS {
+            replay.record(&mut json!({ "type": "job_started", "job_id": format!("job-{index}") }));
+        }
+        assert!(replay.since("job-0", 0).is_none());
+        assert!(replay.since("job-1", 0).is_some());
+    }
+}
@@ -0,0 +1,401 @@
+use crate::scheduler::save_private;
+use bulk_email_core::campaign_log::CampaignSummary;
+use chrono::{Local, SecondsFormat};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::fs;
+use std::path::Path;
+
+/// 任务列表中显示的摘要，不含发送参数（其中有 SMTP 密码）。
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct JobSummary {
+    pub job_id: String,
+    pub subject: String,
+    pub recipients: usize,
+    pub engine: String,
+    pub queued_at: String,
+    /// 定时发送的时间（UTC，RFC 3339）。
+    pub scheduled_at: Option<String>,
+}
+
+impl JobSummary {
+    pub fn from_payload(job_id: &str, payload: &Value) -> Self {
+        JobSummary {
+            job_id: job_id.to_string(),
+            subject: payload.pointer("/template/subject").and_then(Value::as_str).unwrap_or_default().to_string(),
+            recipients: payload["recipients"].as_array().map_or(0, Vec::len),
+            engine: payload["engine"].as_str().unwrap_or("python").to_string(),
+            queued_at: Local::now().to_rfc3339_opts(SecondsFormat::Secs, false),
+            scheduled_at: None,
+        }
+    }
+}
+
+pub struct QueuedJob {
+    pub summary: JobSummary,
+    pub payload: Value,
+}
+
+/// 同时运行的任务数上限。
+pub const MAX_CONCURRENT_JOBS: usize = 8;
+
+/// 任务队列设置，保存在 `AppSettings` 中。
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct JobQueueSettings {
+    /// 同时运行的任务数；超出的任务排队等待。开启共享数据目录（发送锁同一时间只能由一个任务持有）
+    /// 或设置了每日发送上限时始终为 1。
+    pub max_concurrent: usize,
+}
+
+impl Default for JobQueueSettings {
+    fn default() -> Self {
+        JobQueueSettings { max_concurrent: 1 }
+    }
+}
+
+impl JobQueueSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_concurrent == 0 || self.max_concurrent > MAX_CONCURRENT_JOBS {
+            return Err(format!("同时运行的任务数必须在 1 到 {MAX_CONCURRENT_JOBS} 之间"));
+        }
+        Ok(())
+    }
+}
+
+/// 正在运行的任务与等待中的任务；运行数达到上限时新任务排队，有任务结束后按顺序启动下一个。
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct JobList {
+    pub running: Vec<JobSummary>,
+    pub queued: Vec<JobSummary>,
+    /// 尚未到发送时间的定时任务，按时间先后排列。
+    pub scheduled: Vec<JobSummary>,
+}
+
+#[derive(Default)]
+pub struct JobQueue {
+    running: Vec<JobSummary>,
+    pending: VecDeque<QueuedJob>,
+    max_running: usize,
+}
+
+impl JobQueue {
+    pub fn set_max_running(&mut self, max_running: usize) {
+        self.max_running = max_running;
+    }
+
+    /// 运行数已达上限或已有任务等待时，新任务需要排队（保持提交顺序）。
+    pub fn is_busy(&self) -> bool {
+        self.running.len() >= self.max_running.max(1) || !self.pending.is_empty()
+    }
+
+    /// 是否有任务在运行或等待；应用运行时 / worker 更新前据此判断能否立即应用。
+    #[cfg(not(feature = "native-only"))]
+    pub fn is_active(&self) -> bool {
+        !self.running.is_empty() || !self.pending.is_empty()
+    }
+
+    pub fn contains(&self, job_id: &str) -> bool {
+        self.is_running(job_id) || self.pending.iter().any(|job| job.summary.job_id == job_id)
+    }
+
+    pub fn is_running(&self, job_id: &str) -> bool {
+        self.running.iter().any(|job| job.job_id == job_id)
+    }
+
+    /// 加入队尾，返回排队位置（从 1 开始）。
+    pub fn push(&mut self, job: QueuedJob) -> usize {
+        self.pending.push_back(job);
+        self.pending.len()
+    }
+
+    /// 从等待队列中移除任务（取消排队中的任务）。
+    pub fn remove_pending(&mut self, job_id: &str) -> Option<QueuedJob> {
+        let index = self.pending.iter().position(|job| job.summary.job_id == job_id)?;
+        self.pending.remove(index)
+    }
+
+    /// 运行数未达上限时取出下一个等待的任务。
+    pub fn pop_ready(&mut self) -> Option<QueuedJob> {
+        if self.running.len() >= self.max_running.max(1) {
+            return None;
+        }
+        self.pending.pop_front()
+    }
+
+    pub fn running(&self) -> &[JobSummary] {
+        &self.running
+    }
+
+    pub fn start(&mut self, job: JobSummary) {
+        self.running.push(job);
+    }
+
+    pub fn finish(&mut self, job_id: &str) -> Option<JobSummary> {
+        let index = self.running.iter().position(|job| job.job_id == job_id)?;
+        Some(self.running.remove(index))
+    }
+
+    pub fn list(&self) -> JobList {
+        JobList {
+            running: self.running.clone(),
+            queued: self.pending.iter().map(|job| job.summary.clone()).collect(),
+            scheduled: Vec::new(),
+        }
+    }
+
+    /// 任务在队列中的状态：运行中，或等待中及其排队位置（从 1 开始）。
+    pub fn status(&self, job_id: &str) -> Option<(&JobSummary, JobState)> {
+        if let Some(job) = self.running.iter().find(|job| job.job_id == job_id) {
+            return Some((job, JobState::Running));
+        }
+        let position = self.pending.iter().position(|job| job.summary.job_id == job_id)?;
+        Some((&self.pending[position].summary, JobState::Queued { position: position + 1 }))
+    }
+
+    /// 按给定顺序把等待中的任务移到队首，未列出的任务保持原有顺序排在其后。
+    pub fn reorder(&mut self, job_ids: &[String]) -> Result<(), String> {
+        for (index, job_id) in job_ids.iter().enumerate() {
+            if !self.pending.iter().any(|job| &job.summary.job_id == job_id) {
+                return Err(format!("等待中的任务不存在: {job_id}"));
+            }
+            if job_ids[..index].contains(job_id) {
+                return Err(format!("任务重复出现: {job_id}"));
+            }
+        }
+        let mut front = Vec::with_capacity(job_ids.len());
+        for job_id in job_ids {
+            if let Some(index) = self.pending.iter().position(|job| &job.summary.job_id == job_id) {
+                front.extend(self.pending.remove(index));
+            }
+        }
+        for job in front.into_iter().rev() {
+            self.pending.push_front(job);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum JobState {
+    Running,
+    Queued { position: usize },
+    Scheduled,
+}
+
+/// 单个任务的状态与进度（来自任务日志）。
+#[derive(Serialize, Clone, Debug)]
+pub struct JobStatus {
+    #[serde(flatten)]
+    pub summary: JobSummary,
+    #[serde(flatten)]
+    pub state: JobState,
+    pub total: Option<u64>,
+    pub processed: usize,
+    pub sent: usize,
+    pub failed: usize,
+}
+
+impl JobStatus {
+    pub fn new(summary: JobSummary, state: JobState, progress: Option<&CampaignSummary>) -> Self {
+        JobStatus {
+            summary,
+            state,
+            total: progress.and_then(|progress| progress.total),
+            processed: progress.map_or(0, |progress| progress.sent + progress.failed + progress.skipped),
+            sent: progress.map_or(0, |progress| progress.sent),
+            failed: progress.map_or(0, |progress| progress.failed),
+        }
+    }
+}
+
+/// 运行中与排队中任务的发送参数（含 SMTP 密码，文件仅本机用户可读），任务进入队列时写入、正常结束或取消后删除。
+/// 应用或 worker 崩溃后留下的条目即中断的任务；逐个收件人的结果已由任务日志随发送写入。
+#[derive(Deserialize, Serialize, Clone, Default)]
+pub struct JobJournal {
+    #[serde(default)]
+    pub jobs: Vec<JournalEntry>,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct JournalEntry {
+    pub job_id: String,
+    pub queued_at: String,
+    pub payload: Value,
+}
+
+impl JobJournal {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        if !path.exists() {
+            return Ok(JobJournal::default());
+        }
+        let text = fs::read_to_string(path).map_err(|err| format!("读取任务记录失败: {err}"))?;
+        serde_json::from_str(&text).map_err(|err| format!("任务记录文件格式错误: {err}"))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        save_private(path, self, "任务记录")
+    }
+
+    /// 记录任务；同一任务再次提交（如续发）时替换原条目。
+    pub fn record(&mut self, job_id: &str, payload: Value) {
+        self.remove(job_id);
+        self.jobs.push(JournalEntry {
+            job_id: job_id.to_string(),
+            queued_at: Local::now().to_rfc3339_opts(SecondsFormat::Secs, false),
+            payload,
+        });
+    }
+
+    pub fn remove(&mut self, job_id: &str) -> Option<JournalEntry> {
+        let index = self.jobs.iter().position(|job| job.job_id == job_id)?;
+        Some(self.jobs.remove(index))
+    }
+}
+
+/// 中断的任务：提交时的参数摘要与任务日志中已处理的进度。
+#[derive(Serialize, Clone, Debug)]
+pub struct InterruptedJob {
+    #[serde(flatten)]
+    pub summary: JobSummary,
+    /// 已处理（发送成功、失败或跳过）的收件人数，即中断时的位置。
+    pub processed: usize,
+    pub sent: usize,
+    pub failed: usize,
+    /// 最后一条任务日志的时间；任务尚未开始时为空。
+    pub last_event_at: Option<String>,
+}
+
+impl InterruptedJob {
+    pub fn new(entry: &JournalEntry, progress: Option<&CampaignSummary>) -> Self {
+        InterruptedJob {
+            summary: JobSummary {
+                queued_at: entry.queued_at.clone(),
+                ..JobSummary::from_payload(&entry.job_id, &entry.payload)
+            },
+            processed: progress.map_or(0, |progress| progress.sent + progress.failed + progress.skipped),
+            sent: progress.map_or(0, |progress| progress.sent),
+            failed: progress.map_or(0, |progress| progress.failed),
+            last_event_at: progress
+                .and_then(|progress| progress.ended_at)
+                .map(|at| at.with_timezone(&Local).to_rfc3339_opts(SecondsFormat::Secs, false)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{InterruptedJob, JobJournal, JobQueue, JobState, JobSummary, QueuedJob};
+    use bulk_email_core::campaign_log::CampaignSummary;
+    use serde_json::json;
+
+    fn queued(job_id: &str) -> QueuedJob {
+        let payload = json!({ "template": { "subject": "招生咨询" }, "recipients": [{ "email": "a@pku.edu.cn" }] });
+        QueuedJob {
+            summary: JobSummary::from_payload(job_id, &payload),
+            payload,
+        }
+    }
+
+    #[test]
+    fn queues_and_reorders_jobs() {
+        let mut queue = JobQueue::default();
+        assert!(!queue.is_busy());
+        queue.start(queued("job-a").summary);
+        assert_eq!(queue.push(queued("job-b")), 1);
+        assert_eq!(queue.push(queued("job-c")), 2);
+        assert_eq!(queue.push(queued("job-d")), 3);
+        assert!(queue.contains("job-a") && queue.contains("job-d"));
+
+        queue.reorder(&["job-d".to_string(), "job-c".to_string()]).expect("reorder");
+        let list = queue.list();
+        let order: Vec<&str> = list.queued.iter().map(|job| job.job_id.as_str()).collect();
+        assert_eq!(order, ["job-d", "job-c", "job-b"]);
+        assert_eq!(list.running.first().map(|job| job.recipients), Some(1));
+        assert_eq!(list.queued[0].subject, "招生咨询");
+        assert!(queue.reorder(&["job-a".to_string()]).is_err());
+        assert_eq!(queue.list().queued.len(), 3);
+        assert_eq!(queue.status("job-c").map(|(_, state)| state), Some(JobState::Queued { position: 2 }));
+
+        assert!(queue.remove_pending("job-c").is_some() && queue.remove_pending("job-a").is_none());
+        assert!(!queue.contains("job-c"));
+
+        assert!(queue.pop_ready().is_none());
+        assert!(queue.finish("job-a").is_some());
+        assert_eq!(queue.pop_ready().map(|job| job.summary.job_id), Some("job-d".to_string()));
+    }
+
+    #[test]
+    fn runs_several_jobs_up_to_the_limit() {
+        let mut queue = JobQueue::default();
+        queue.set_max_running(2);
+        queue.start(queued("job-a").summary);
+        assert!(!queue.is_busy());
+        queue.start(queued("job-b").summary);
+        assert!(queue.is_busy());
+        queue.push(queued("job-c"));
+        assert!(queue.pop_ready().is_none());
+
+        assert!(queue.finish("job-a").is_some() && queue.finish("job-a").is_none());
+        let next = queue.pop_ready().expect("capacity freed");
+        queue.start(next.summary);
+        let running: Vec<&str> = queue.running().iter().map(|job| job.job_id.as_str()).collect();
+        assert_eq!(running, ["job-b", "job-c"]);
+        assert_eq!(queue.status("job-c").map(|(_, state)| state), Some(JobState::Running));
+    }
+
+    #[test]
+    fn lowering_the_limit_holds_pending_jobs_until_running_ones_finish() {
+        let mut queue = JobQueue::default();
+        queue.set_max_running(3);
+        for job_id in ["job-a", "job-b", "job-c"] {
+            queue.start(queued(job_id).summary);
+        }
+        queue.push(queued("job-d"));
+        queue.push(queued("job-e"));
+
+        // 调低上限不影响已经在运行的任务，排队的任务等到运行数低于新上限才启动。
+        queue.set_max_running(1);
+        assert!(queue.is_busy() && queue.pop_ready().is_none());
+        assert!(queue.is_running("job-c") && !queue.is_running("job-d"));
+        assert!(queue.finish("job-a").is_some() && queue.finish("job-b").is_some());
+        assert!(!queue.is_running("job-a") && queue.contains("job-d"));
+        assert!(queue.pop_ready().is_none());
+        assert_eq!(queue.running().len(), 1);
+
+        assert!(queue.finish("job-c").is_some());
+        let next = queue.pop_ready().expect("below the limit");
+        assert_eq!(next.summary.job_id, "job-d");
+        queue.start(next.summary);
+        assert!(queue.pop_ready().is_none());
+        assert_eq!(queue.status("job-e").map(|(_, state)| state), Some(JobState::Queued { position: 1 }));
+
+        queue.set_max_running(2);
+        assert_eq!(queue.pop_ready().map(|job| job.summary.job_id), Some("job-e".to_string()));
+    }
+
+    #[test]
+    fn journals_jobs_until_they_end() {
+        let mut journal = JobJournal::default();
+        journal.record("job-a", queued("job-a").payload);
+        journal.record("job-b", queued("job-b").payload);
+        journal.record("job-a", json!({ "recipients": [] }));
+        let order: Vec<&str> = journal.jobs.iter().map(|job| job.job_id.as_str()).collect();
+        assert_eq!(order, ["job-b", "job-a"]);
+        assert!(journal.remove("job-a").is_some() && journal.remove("job-a").is_none());
+
+        let events = [
+            json!({ "type": "job_started", "job_id": "job-b", "total": 3, "at": "2024-03-01T09:00:00Z" }),
+            json!({ "type": "recipient_sent", "job_id": "job-b", "at": "2024-03-01T09:00:05Z" }),
+            json!({ "type": "recipient_failed", "job_id": "job-b", "error": "550", "at": "2024-03-01T09:00:09Z" }),
+        ];
+        let progress = CampaignSummary::from_events("job-b", &events);
+        let interrupted = InterruptedJob::new(&journal.jobs[0], Some(&progress));
+        assert_eq!((interrupted.processed, interrupted.sent, interrupted.failed), (2, 1, 1));
+        assert_eq!(interrupted.summary.subject, "招生咨询");
+        assert!(interrupted.last_event_at.is_some());
+        assert!(InterruptedJob::new(&journal.jobs[0], None).last_event_at.is_none());
+    }
+}
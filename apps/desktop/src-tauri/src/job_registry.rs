@@ -0,0 +1,129 @@
+use bulk_email_core::throttle::Throttle;
+use std::collections::BTreeMap;
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, Mutex};
+
+/// 控制一个运行中任务所需的句柄。
+pub enum JobControl {
+    /// 原生引擎在后台线程中运行，通过取消标志与任务级限速控制。
+    Native {
+        cancel: Arc<AtomicBool>,
+        /// 任务级限速，`update_job_throttle` 修改后从下一封邮件起生效。
+        throttle: Arc<Mutex<Throttle>>,
+    },
+    /// 每个任务一个 Python worker 进程。
+    #[cfg(not(feature = "native-only"))]
+    Worker {
+        child: std::process::Child,
+        /// worker 的请求通道（标准输入或本地套接字），任务结束前保持打开，用于发送控制消息；任务结束后置空。
+        requests: Option<Box<dyn std::io::Write + Send>>,
+        /// 启动握手时 worker 报告的协议范围与功能，发送控制消息前据此检查。
+        hello: bulk_email_core::worker_protocol::WorkerHello,
+    },
+}
+
+/// 运行中任务的控制句柄，按任务 ID 索引。任务占用运行位置时预留，启动完成后登记，线程或 worker 结束、
+/// 推进任务队列时移除。
+#[derive(Default)]
+pub struct JobRegistry {
+    jobs: BTreeMap<String, JobControl>,
+    /// 已预留、尚未登记控制句柄的任务（启动 worker 与握手期间）；值为这期间收到的取消请求（是否强制）。
+    starting: BTreeMap<String, Option<bool>>,
+}
+
+impl JobRegistry {
+    /// 任务占用运行位置后、释放队列锁启动之前预留，启动期间收到的取消请求记在预留上。
+    pub fn reserve(&mut self, job_id: &str) {
+        self.starting.insert(job_id.to_string(), None);
+    }
+
+    /// 登记启动完成的任务，返回启动期间收到的取消请求，由调用方随即转交任务。
+    /// 预留已被移除（任务在登记前就已结束或回滚）时不登记，丢弃控制句柄。
+    pub fn insert(&mut self, job_id: &str, control: JobControl) -> Option<bool> {
+        let cancel = self.starting.remove(job_id)?;
+        self.jobs.insert(job_id.to_string(), control);
+        cancel
+    }
+
+    /// 记下启动中任务的取消请求；任务不在启动中时返回 `false`。强制取消不会被之后的普通取消覆盖。
+    pub fn cancel_starting(&mut self, job_id: &str, force: bool) -> bool {
+        let Some(cancel) = self.starting.get_mut(job_id) else {
+            return false;
+        };
+        *cancel = Some(force || cancel.unwrap_or(false));
+        true
+    }
+
+    pub fn get(&self, job_id: &str) -> Option<&JobControl> {
+        self.jobs.get(job_id)
+    }
+
+    pub fn get_mut(&mut self, job_id: &str) -> Option<&mut JobControl> {
+        self.jobs.get_mut(job_id)
+    }
+
+    /// 移除任务的控制句柄或预留。
+    pub fn remove(&mut self, job_id: &str) -> Option<JobControl> {
+        self.starting.remove(job_id);
+        self.jobs.remove(job_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{JobControl, JobRegistry};
+    use bulk_email_core::throttle::Throttle;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    fn native(cancel: &Arc<AtomicBool>) -> JobControl {
+        JobControl::Native {
+            cancel: Arc::clone(cancel),
+            throttle: Arc::new(Mutex::new(Throttle::default())),
+        }
+    }
+
+    #[test]
+    fn registers_reserved_jobs_until_removed() {
+        let cancel = Arc::new(AtomicBool::new(false));
+        let mut jobs = JobRegistry::default();
+        jobs.reserve("job-a");
+        assert!(jobs.get("job-a").is_none());
+
+        assert_eq!(jobs.insert("job-a", native(&cancel)), None);
+        let Some(JobControl::Native { cancel: registered, .. }) = jobs.get_mut("job-a") else {
+            panic!("job-a is registered");
+        };
+        registered.store(true, Ordering::SeqCst);
+        assert!(cancel.load(Ordering::SeqCst));
+
+        assert!(jobs.remove("job-a").is_some());
+        assert!(jobs.get("job-a").is_none() && jobs.remove("job-a").is_none());
+        assert!(!jobs.cancel_starting("job-a", false));
+    }
+
+    #[test]
+    fn drops_controls_of_jobs_removed_while_starting() {
+        let cancel = Arc::new(AtomicBool::new(false));
+        let mut jobs = JobRegistry::default();
+        jobs.reserve("job-a");
+        assert!(jobs.remove("job-a").is_none());
+        assert_eq!(jobs.insert("job-a", native(&cancel)), None);
+        assert!(jobs.get("job-a").is_none() && !jobs.cancel_starting("job-a", false));
+        assert_eq!(Arc::strong_count(&cancel), 1);
+    }
+
+    #[test]
+    fn hands_over_cancels_received_while_starting() {
+        let cancel = Arc::new(AtomicBool::new(false));
+        let mut jobs = JobRegistry::default();
+        assert!(!jobs.cancel_starting("job-a", false));
+        jobs.reserve("job-a");
+        jobs.reserve("job-b");
+        assert!(jobs.cancel_starting("job-a", true) && jobs.cancel_starting("job-a", false));
+        assert!(jobs.cancel_starting("job-b", false));
+        assert_eq!(jobs.insert("job-a", native(&cancel)), Some(true));
+        assert_eq!(jobs.insert("job-b", native(&cancel)), Some(false));
+        assert!(!jobs.cancel_starting("job-a", false));
+    }
+}
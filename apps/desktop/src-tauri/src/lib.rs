@@ -1,230 +1,2463 @@
-use lettre::transport::smtp::authentication::Credentials;
-use lettre::transport::smtp::client::{Tls, TlsParameters};
-use lettre::{SmtpTransport};
+mod alerts;
+mod event_replay;
+mod job_queue;
+mod job_registry;
+#[cfg(not(feature = "native-only"))]
+mod maintenance;
+mod progress;
+#[cfg(not(feature = "native-only"))]
+mod python_runtime;
+mod report_pdf;
+#[cfg(not(feature = "native-only"))]
+mod runtime_probe;
+mod scheduler;
+mod session;
+mod shared_dir;
+mod smtp_profiles;
+#[cfg(not(feature = "native-only"))]
+mod uv_installer;
+#[cfg(not(feature = "native-only"))]
+mod worker_env;
+
+use bulk_email_core::{
+    accounts::{
+        summarize, AccountRegistry, AccountRotation, RotationConfig, SmtpAccount, SmtpAccountSummary, UsageLedger,
+    },
+    attachments::{self, AttachmentCheckRequest, AttachmentReport, DEFAULT_MAX_TOTAL_BYTES},
+    campaign_log::{
+        self, unresolved_failures, CampaignLog, CampaignSummary, CampaignTimeline, CloneSelection, JobDetail,
+        JobHistoryEntry, TemplateOverrides, TimelineBucket,
+    },
+    clock::SystemClock,
+    concurrency::MAX_CONNECTIONS,
+    consent::{read_consent_file, ConsentImportSummary, ConsentPolicy, ConsentReport, ConsentStore},
+    database::{self, DatabaseSource},
+    dead_domains::{DeadDomainReport, DeadDomainStore, DeadDomainSummary},
+    disposable_domains::{DisposableDomains, DisposableReport, DEFAULT_DISPOSABLE_DOMAINS_URL},
+    email_syntax::{self, RecipientValidationReport, ValidationSettings},
+    enrichment::{self, EnrichmentCache, EnrichmentReport, EnrichmentSettings},
+    engine::{
+        default_job_id, normalize_copy_addresses, RecipientEntry, SendEngine, SendJob, SendOptions, SenderSlot,
+    },
+    environment::{usable_for_test, Environment},
+    imap_seed::{self, ImapSeedQuery, ImapSettings},
+    ldap::{self, LdapQuery, LdapSettings},
+    local_mta::LocalMtaTransport,
+    message_builder::{build_email_message, MessageContent},
+    message_size::{measure_message_sizes, MessageSizeReport, OversizeAction},
+    net_policy::{http_client, NetworkPolicy},
+    provider_policy::{self, builtin_rules, detect_provider, PolicyCampaign, PolicyReport, ProviderRules},
+    rcpt_probe::{self, RcptProbeOptions, RcptProbeReport, PROBE_WARNINGS},
+    recipient_filter::{self, ColumnFilter, RecipientFilterReport},
+    recipients_loader::{self, ColumnMapping, CsvOptions, RecipientExclusion},
+    record_notes::{RecordNote, RecordNotes},
+    send_plan::SendPlan,
+    sent_history::{
+        ClearFilter, ClearReport, PurgeReport, RecordStatus, RetentionPolicy, SendStats, SendStatsQuery, SentHistory,
+        SentRecordPage, SentRecordQuery,
+    },
+    sent_store::SentStore,
+    smime::{
+        certificate_emails, certificate_info, load_pkcs12, parse_certificate, RecipientCertStore, SmimeCertificateInfo,
+        SmimeContext, SmimeIdentity, SmimeSettings,
+    },
+    smtp_auth::AuthMechanism,
+    smtp_client::{build_mail_transport, probe_auth, MailTransport, NativeTransport, ProbeStage, SmtpPayload},
+    suppression::{self, SuppressionImportSummary, SuppressionList, SuppressionListing},
+    throttle::{load_history, save_history, unix_now, Throttle, ThrottleLimits, ThrottleStatus},
+    upload::{upload_attachment, UploadSettings},
+    worker_protocol::RequestBody,
+};
+use alerts::{Alert, AlertEmail, AlertMonitor, AlertSettings};
+use event_replay::{EventReplay, JobEvents};
+use job_queue::{
+    InterruptedJob, JobJournal, JobList, JobQueue, JobQueueSettings, JobState, JobStatus, JobSummary, QueuedJob,
+};
+use job_registry::{JobControl, JobRegistry};
+use progress::ProgressTracker;
+use scheduler::{
+    format_scheduled_at, parse_scheduled_at, RecurringCampaign, RecurringCampaignSummary, RecurringCampaigns, Schedule,
+    ScheduledJob,
+};
+use session::SessionState;
 use serde::{Deserialize, Serialize};
+use shared_dir::{break_lock, list_locks, ConflictGuard, DataDirLock, LockOwner, SharedDirSettings, UserIdentity};
+use smtp_profiles::{SmtpProfile, SmtpProfileStore};
 use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs::{self, File};
-use std::io::{BufRead, BufReader, Read, Write};
+use std::io::{BufRead, BufReader, Read};
 #[cfg(unix)]
 use std::os::unix::fs::PermissionsExt;
 use std::path::{Path, PathBuf};
-use std::process::{Child, Command, Stdio};
-use std::sync::Mutex;
-use std::time::Duration;
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
 use tauri::{AppHandle, Emitter, Manager, State};
 use walkdir::WalkDir;
-use zip::ZipArchive;
-use sha2::{Digest, Sha256};
 
+/// 任务事件按任务分频道推送：`worker-event:{job_id}`（见 `job_event_channel`）。
 const WORKER_EVENT_CHANNEL: &str = "worker-event";
-const RUNTIME_CONFIG_RELATIVE_PATH: &str = "runtime/python_runtime.json";
+/// 所有任务的生命周期事件（受理、排队、启动、结束与错误），同时也推送到各自任务的频道。
+const JOB_LIFECYCLE_CHANNEL: &str = "job-lifecycle";
+const JOB_LIFECYCLE_EVENTS: [&str; 8] = [
+    "job_accepted",
+    "job_queued",
+    "job_scheduled",
+    "job_started",
+    "job_finished",
+    "job_cancelled",
+    "job_failed",
+    "error",
+];
+const JOB_QUEUE_CHANNEL: &str = "job-queue";
+const ALERT_CHANNEL: &str = "alert";
+const RCPT_PROBE_CHANNEL: &str = "rcpt-probe";
+/// 发送任务停滞检查的间隔。
+const STALL_CHECK_INTERVAL: Duration = Duration::from_secs(30);
 const APP_SETTINGS_RELATIVE_PATH: &str = "settings/app_settings.json";
 const APP_DRAFT_RELATIVE_PATH: &str = "config/app_draft.json";
+const SESSION_STATE_RELATIVE_PATH: &str = "settings/session_state.json";
+const SMTP_ACCOUNTS_RELATIVE_PATH: &str = "config/smtp_accounts.json";
+const SMTP_ACCOUNT_USAGE_RELATIVE_PATH: &str = "records/smtp_account_usage.json";
+const SMTP_PROFILES_RELATIVE_PATH: &str = "config/smtp_profiles.json";
+const THROTTLE_HISTORY_RELATIVE_PATH: &str = "records/throttle_history.json";
+const DEAD_DOMAINS_RELATIVE_PATH: &str = "records/dead_domains.json";
+const DISPOSABLE_DOMAINS_RELATIVE_PATH: &str = "records/disposable_domains.txt";
+const CONSENT_RELATIVE_PATH: &str = "records/consent.json";
+const SUPPRESSION_RELATIVE_PATH: &str = "records/suppression.json";
+const CAMPAIGNS_RELATIVE_PATH: &str = "records/campaigns";
+const MAX_RESUME_SKIPPED_ROWS: usize = 200;
+/// 远程收件人名单的大小上限；名单地址由用户填写，超过时报错而不是把整个响应读进内存。
+const MAX_RECIPIENT_LIST_BYTES: u64 = 64 * 1024 * 1024;
+const RECORD_NOTES_RELATIVE_PATH: &str = "records/record_notes.json";
+const ENRICHMENT_CACHE_RELATIVE_PATH: &str = "records/enrichment_cache.json";
+const SMIME_RECIPIENTS_RELATIVE_PATH: &str = "config/smime_recipients.json";
+const COLUMN_MAPPINGS_RELATIVE_PATH: &str = "config/column_mappings.json";
+const SCHEDULED_JOBS_RELATIVE_PATH: &str = "config/scheduled_jobs.json";
+const RECURRING_CAMPAIGNS_RELATIVE_PATH: &str = "config/recurring_campaigns.json";
+const ACTIVE_JOBS_RELATIVE_PATH: &str = "config/active_jobs.json";
+/// 应用日志（与 `AppPaths::log_file` 相同），worker 的标准错误输出追加在这里。
+const APP_LOG_RELATIVE_PATH: &str = "logs/email_log.txt";
+const SMIME_IDENTITY_RELATIVE_PATH: &str = "smime/identity.p12";
 const DEFAULT_DATA_DIR_NAME: &str = "Bulk-Email-Sender";
 const SAMPLE_RECIPIENTS_RESOURCE_DIR: &str = "examples/recipients";
 const SAMPLE_RECIPIENT_JSON_FILE: &str = "recipients_sample.json";
 const SAMPLE_RECIPIENT_XLSX_FILE: &str = "recipients_sample.xlsx";
-const PYTHON_MIN_MAJOR: u32 = 3;
-const PYTHON_MIN_MINOR: u32 = 9;
 
 #[derive(Default)]
 struct WorkerState {
-    child: Mutex<Option<Child>>,
+    /// 运行中任务的控制句柄（worker 进程或原生引擎的取消标志），按任务 ID 索引。
+    /// 需要同时持有时按 `queue` → `jobs` → `events` 的顺序加锁。
+    jobs: Mutex<JobRegistry>,
+    /// 正在运行与排队等待的发送任务。
+    queue: Mutex<JobQueue>,
+    /// 尚未到发送时间的定时任务。
+    scheduled: Mutex<Schedule>,
+    /// 按 cron 表达式周期运行的任务。
+    recurring: Mutex<RecurringCampaigns>,
+    /// 运行中与排队中任务的落盘记录，用于崩溃后恢复。
+    journal: Mutex<JobJournal>,
+    throttle: Mutex<Option<Arc<Mutex<Throttle>>>>,
+    /// 最近推送的任务事件，供刷新后的界面补发。推送事件时可能已持有 `queue` 或 `jobs`，
+    /// 因此总是最后加锁，持有期间不再获取其他锁。
+    events: Mutex<EventReplay>,
+    /// 正在应用排队的运行时 / worker 更新，此时不接受新任务。
+    updating: AtomicBool,
+    /// 处理一问一答请求的常驻 worker，首次使用时启动，运行时或 worker 设置变更前结束。
+    #[cfg(not(feature = "native-only"))]
+    resident: Mutex<Option<bulk_email_core::worker_protocol::ResidentWorker>>,
+}
+
+/// CSV 由 Rust 直接解析，无需 Python 运行时；其他格式交给 worker。文件设置过列映射时按映射导入。
+/// CSV 的编码与分隔符默认自动识别，识别结果随事件返回；`csv` 用于识别有误时手动指定。
+/// `path` 也可以是指向 CSV / JSON 名单的 https 地址（团队在内部服务器维护统一名单），
+/// 下载后直接解析；没有可供发送的文件，因此额外返回完整的 `recipients`。
+#[tauri::command]
+async fn load_recipients(app: AppHandle, path: String, csv: Option<CsvOptions>) -> Result<Value, String> {
+    if is_remote_url(&path) {
+        let url = path.trim().to_string();
+        validate_remote_url_scheme(&url, "收件人名单地址")?;
+        let policy = read_app_settings(&app)?.network;
+        let loaded = tauri::async_runtime::spawn_blocking(move || {
+            let bytes = download_recipient_list(&policy, &url)?;
+            recipients_loader::load_recipients_from_bytes(&bytes, &csv.unwrap_or_default())
+        })
+        .await
+        .map_err(|err| format!("下载收件人名单失败: {err}"))??;
+        let mut event = loaded.to_event();
+        event["recipients"] = json!(loaded.recipients);
+        return Ok(event);
+    }
+    // 解析大文件与等待 worker 应答都可能阻塞较久，放到阻塞线程池，不占用异步运行时的线程。
+    tauri::async_runtime::spawn_blocking(move || {
+        let file = Path::new(path.trim());
+        if recipients_loader::is_native_format(file) {
+            let mapping = read_column_mappings(&app)?.remove(path.trim());
+            let loaded = recipients_loader::load_recipients_with(file, mapping.as_ref(), &csv.unwrap_or_default())?;
+            return Ok(loaded.to_event());
+        }
+        load_recipients_fallback(&app, path.trim())
+    })
+    .await
+    .map_err(|err| format!("读取收件人文件失败: {err}"))?
+}
+
+/// 其他格式（JSON、XLS）交给 Python worker 读取。
+#[cfg(not(feature = "native-only"))]
+fn load_recipients_fallback(app: &AppHandle, path: &str) -> Result<Value, String> {
+    use bulk_email_core::worker_protocol::worker_request;
+    let request = worker_request(RequestBody::LoadRecipients(json!({ "path": path })));
+    python_runtime::run_worker_request(request, app)
+}
+
+/// 不含 Python worker 时只能原生读取 JSON 名单。
+#[cfg(feature = "native-only")]
+fn load_recipients_fallback(_app: &AppHandle, path: &str) -> Result<Value, String> {
+    let file = Path::new(path);
+    if !file.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json")) {
+        return Err("此版本仅支持 CSV / XLSX / ODS / JSON 收件人文件".to_string());
+    }
+    let bytes = fs::read(file).map_err(|err| format!("读取收件人文件失败: {err}"))?;
+    let loaded = recipients_loader::load_recipients_from_bytes(&bytes, &CsvOptions::default())?;
+    Ok(loaded.to_event())
+}
+
+#[derive(Serialize)]
+struct ColumnMappingInfo {
+    headers: Vec<String>,
+    /// 该文件已保存的列映射。
+    mapping: Option<ColumnMapping>,
+}
+
+/// 读取收件人文件的表头与已保存的列映射，供前端配置映射。
+#[tauri::command]
+fn get_column_mapping(app: AppHandle, path: String, csv: Option<CsvOptions>) -> Result<ColumnMappingInfo, String> {
+    let headers = recipients_loader::read_headers(Path::new(path.trim()), &csv.unwrap_or_default())?;
+    let mapping = read_column_mappings(&app)?.remove(path.trim());
+    Ok(ColumnMappingInfo { headers, mapping })
+}
+
+#[derive(Deserialize)]
+struct ColumnMappingPayload {
+    path: String,
+    /// 为空时清除映射，恢复按表头别名识别。
+    #[serde(default)]
+    mapping: Option<ColumnMapping>,
+    #[serde(default)]
+    csv: CsvOptions,
+}
+
+/// 设置收件人文件的列映射：校验必填字段与表头后按映射重新导入并保存；
+/// 之后加载该文件、流式发送与模板变量检查都使用同一映射。
+#[tauri::command]
+fn set_column_mapping(app: AppHandle, payload: ColumnMappingPayload) -> Result<Value, String> {
+    let path = payload.path.trim();
+    let file = Path::new(path);
+    if !recipients_loader::is_native_format(file) {
+        return Err("列映射仅支持 CSV / Excel / ODS 文件".to_string());
+    }
+    let loaded = recipients_loader::load_recipients_with(file, payload.mapping.as_ref(), &payload.csv)?;
+    let mut mappings = read_column_mappings(&app)?;
+    match &payload.mapping {
+        Some(mapping) => mappings.insert(path.to_string(), mapping.clone()),
+        None => mappings.remove(path),
+    };
+    let text = serde_json::to_string_pretty(&mappings).map_err(|err| err.to_string())?;
+    fs::write(resolve_data_file(&app, COLUMN_MAPPINGS_RELATIVE_PATH)?, text)
+        .map_err(|err| format!("写入列映射失败: {err}"))?;
+    let mut event = loaded.to_event();
+    event["column_mapping"] = json!(payload.mapping);
+    Ok(event)
+}
+
+/// 按文件路径保存的列映射。
+fn read_column_mappings(app: &AppHandle) -> Result<BTreeMap<String, ColumnMapping>, String> {
+    let path = resolve_data_file(app, COLUMN_MAPPINGS_RELATIVE_PATH)?;
+    if !path.exists() {
+        return Ok(BTreeMap::new());
+    }
+    let text = fs::read_to_string(path).map_err(|err| format!("读取列映射失败: {err}"))?;
+    serde_json::from_str(&text).map_err(|err| format!("列映射格式错误: {err}"))
+}
+
+#[derive(Deserialize)]
+struct LdapQueryPayload {
+    ldap: LdapSettings,
+    #[serde(flatten)]
+    query: LdapQuery,
+}
+
+/// 按过滤器查询 LDAP / Active Directory，把条目按属性映射导入为收件人（映射的其他属性作为补充字段）。
+/// 结果与文件导入同形，已移除抑制列表中的地址，并额外返回完整的 `recipients`。
+#[tauri::command]
+async fn ldap_query_recipients(app: AppHandle, payload: LdapQueryPayload) -> Result<Value, String> {
+    if read_app_settings(&app)?.network.strict_local {
+        return Err("已开启仅本地模式，不能连接 LDAP 服务器".to_string());
+    }
+    let mut result = tauri::async_runtime::spawn_blocking(move || ldap::query_recipients(&payload.ldap, &payload.query))
+        .await
+        .map_err(|err| format!("LDAP 查询任务失败: {err}"))??;
+    let loaded = &mut result.recipients;
+    let (recipients, suppressed) = filter_suppressed(&app, std::mem::take(&mut loaded.recipients))?;
+    loaded.recipients = recipients;
+    loaded.stats.valid_rows = loaded.recipients.len();
+    let mut event = loaded.to_event();
+    event["recipients"] = json!(loaded.recipients);
+    event["suppressed"] = json!(suppressed);
+    event["directory_entries"] = json!(result.entries);
+    event["truncated"] = json!(result.truncated);
+    Ok(event)
+}
+
+/// 执行 SQLite / Postgres 上的 SELECT 查询，把结果按列名导入为收件人（CRM 中的名单无需手动导出）。
+/// 结果与 LDAP 查询同形，已移除抑制列表中的地址；仅本地模式下只允许查询本机 SQLite 文件。
+#[tauri::command]
+async fn load_recipients_from_database(app: AppHandle, payload: DatabaseSource) -> Result<Value, String> {
+    payload.validate()?;
+    if read_app_settings(&app)?.network.strict_local && !payload.is_sqlite() {
+        return Err("已开启仅本地模式，不能连接数据库服务器".to_string());
+    }
+    let mut result = tauri::async_runtime::spawn_blocking(move || database::query_recipients(&payload))
+        .await
+        .map_err(|err| format!("数据库查询任务失败: {err}"))??;
+    let loaded = &mut result.recipients;
+    let (recipients, suppressed) = filter_suppressed(&app, std::mem::take(&mut loaded.recipients))?;
+    loaded.recipients = recipients;
+    loaded.stats.valid_rows = loaded.recipients.len();
+    let mut event = loaded.to_event();
+    event["recipients"] = json!(loaded.recipients);
+    event["suppressed"] = json!(suppressed);
+    event["database_rows"] = json!(result.rows);
+    event["truncated"] = json!(result.truncated);
+    Ok(event)
+}
+
+/// 从粘贴的文本导入收件人（每行一个地址，或带表头的制表符分隔表格），结果与文件导入同形，
+/// 并已移除抑制列表中的地址；没有可供发送的文件，因此额外返回完整的 `recipients`。
+#[tauri::command]
+fn load_recipients_from_text(app: AppHandle, text: String) -> Result<Value, String> {
+    let mut loaded = recipients_loader::load_recipients_from_text(&text)?;
+    let (recipients, suppressed) = filter_suppressed(&app, std::mem::take(&mut loaded.recipients))?;
+    loaded.recipients = recipients;
+    loaded.stats.valid_rows = loaded.recipients.len();
+    let mut event = loaded.to_event();
+    event["recipients"] = json!(loaded.recipients);
+    event["suppressed"] = json!(suppressed);
+    Ok(event)
+}
+
+#[derive(Deserialize)]
+struct ImapSeedPayload {
+    imap: ImapSettings,
+    #[serde(flatten)]
+    query: ImapSeedQuery,
+}
+
+/// 搜索邮箱（如最近 90 天发给 support@ 的邮件），把去重后的发件人作为收件人导入。
+/// 结果与文件导入同形（`recipients_loaded`），并已移除抑制列表中的地址；
+/// 没有可供发送的文件，因此额外返回完整的 `recipients`。
+#[tauri::command]
+async fn load_recipients_from_imap(app: AppHandle, payload: ImapSeedPayload) -> Result<Value, String> {
+    if read_app_settings(&app)?.network.strict_local {
+        return Err("已开启仅本地模式，不能连接 IMAP 服务器".to_string());
+    }
+    let mut result =
+        tauri::async_runtime::spawn_blocking(move || imap_seed::load_recipients_from_imap(&payload.imap, &payload.query))
+            .await
+            .map_err(|err| format!("IMAP 搜索任务失败: {err}"))??;
+    let loaded = &mut result.recipients;
+    let (recipients, suppressed) = filter_suppressed(&app, std::mem::take(&mut loaded.recipients))?;
+    loaded.recipients = recipients;
+    loaded.stats.valid_rows = loaded.recipients.len();
+    let mut event = loaded.to_event();
+    event["recipients"] = json!(loaded.recipients);
+    event["suppressed"] = json!(suppressed);
+    event["matched_messages"] = json!(result.matched);
+    event["scanned_messages"] = json!(result.scanned);
+    Ok(event)
+}
+
+#[derive(Deserialize)]
+struct FilterRecipientsPayload {
+    path: String,
+    #[serde(default)]
+    filters: Vec<ColumnFilter>,
+    /// 为 true 时满足任一条件即可，默认需满足全部条件。
+    #[serde(default)]
+    match_any: bool,
+}
+
+/// 按列值筛选收件人（如只保留 `region == "EU"`），不必修改表格即可分段发送。
+#[tauri::command]
+fn filter_recipients(payload: FilterRecipientsPayload) -> Result<RecipientFilterReport, String> {
+    let table = recipients_loader::load_table(Path::new(payload.path.trim()))?;
+    recipient_filter::filter_table(&table, &payload.filters, payload.match_any)
+}
+
+/// 发送前检查附件：是否存在、大小与 MIME 类型，以及是否超过配置或服务器的大小上限。
+#[tauri::command]
+fn validate_attachments(payload: AttachmentCheckRequest) -> Result<AttachmentReport, String> {
+    Ok(attachments::validate_attachments(&payload))
+}
+
+#[derive(Deserialize)]
+struct SendPlanPayload {
+    #[serde(default)]
+    recipients: Vec<RecipientEntry>,
+    #[serde(default)]
+    options: SendOptions,
+}
+
+/// 预览原生引擎的发送顺序与间隔；带上 `job_started` 中的 `seed` 可复现某次任务的计划。
+#[tauri::command]
+fn preview_send_plan(payload: SendPlanPayload) -> SendPlan {
+    SendPlan::build(&payload.options, &payload.recipients)
+}
+
+/// 按发件服务商的规则模拟任务（每日投递数、单封收件人数、附件大小），在服务商拒信前提示。
+/// `rules` 用于自建或未内置的服务商；为空时按 SMTP 主机与发件人域名识别。
+#[tauri::command]
+fn simulate_policy_compliance(
+    app: AppHandle,
+    state: State<'_, WorkerState>,
+    mut payload: Value,
+    rules: Option<ProviderRules>,
+) -> Result<PolicyReport, String> {
+    normalize_copy_recipients(&mut payload)?;
+    let campaign: PolicyCampaign =
+        serde_json::from_value(payload).map_err(|err| format!("发送参数格式错误: {err}"))?;
+    let builtin = builtin_rules();
+    let rules = rules
+        .as_ref()
+        .or_else(|| detect_provider(&builtin, &campaign.smtp.host, &campaign.sender.email));
+    let sent_last_day = {
+        let throttle = shared_throttle(&app, &state)?;
+        let mut throttle = throttle
+            .lock()
+            .map_err(|_| "failed to acquire throttle lock".to_string())?;
+        throttle.status(unix_now()).sent_last_day as u64
+    };
+    Ok(provider_policy::simulate(rules, &campaign, sent_last_day))
+}
+
+/// 估算每位收件人渲染后的邮件大小，标记超过上限（任务配置、服务商规则或服务器 SIZE 上限）的收件人。
+#[tauri::command]
+fn preflight_message_sizes(mut payload: Value) -> Result<MessageSizeReport, String> {
+    normalize_copy_recipients(&mut payload)?;
+    let job = SendJob::from_payload(payload)?;
+    measure_message_sizes(&job, oversize_limit(&job))
+}
+
+/// `test_smtp` 的失败：`stage` 区分连接（网络、TLS 与配置检查）与认证（账号或密码错误）。
+#[derive(Serialize)]
+struct SmtpTestFailure {
+    stage: ProbeStage,
+    error: String,
+}
+
+impl From<String> for SmtpTestFailure {
+    fn from(error: String) -> Self {
+        SmtpTestFailure {
+            stage: ProbeStage::Connect,
+            error,
+        }
+    }
+}
+
+/// 连接、认证并发送 NOOP，确认账号密码真正可用；成功时附带服务器在 EHLO 中声明的扩展（`capabilities`），
+/// 关闭了证书校验时在 `warnings` 中提示风险。
+/// 失败时返回带 `stage`（`connect` / `auth`）的错误，界面据此区分网络问题与账号密码错误。
+#[tauri::command]
+async fn test_smtp(app: AppHandle, payload: SmtpPayload) -> Result<Value, SmtpTestFailure> {
+    read_app_settings(&app)?.network.check_smtp_host(&payload.host)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        // Retry once after 2 s: some SMTP servers (e.g. 126.com) apply a
+        // cold-start delay on the first connection and temporarily reject it.
+        let mut last_err: Option<String> = None;
+        for attempt in 0..2u32 {
+            match probe_auth(&payload) {
+                Ok(probe) => {
+                    return Ok(json!({
+                        "type": "smtp_test_succeeded",
+                        "offered_mechanisms": probe.auth.offered_mechanisms,
+                        "auth_mechanism": probe.auth.auth_mechanism.name(),
+                        "capabilities": probe.capabilities,
+                        "tls_probe": probe.tls_probe,
+                        "warnings": payload.tls_warning().into_iter().collect::<Vec<_>>(),
+                    }))
+                }
+                // 认证失败不重试：重复提交错误的密码可能触发服务器锁定账号。
+                Err(failure) if failure.stage == ProbeStage::Auth => {
+                    return Err(SmtpTestFailure {
+                        stage: failure.stage,
+                        error: format!("SMTP 认证失败: {}", failure.message),
+                    });
+                }
+                Err(failure) => {
+                    last_err = Some(format!("SMTP 连接失败: {}", failure.message));
+                    if attempt == 0 {
+                        std::thread::sleep(Duration::from_secs(2));
+                    }
+                }
+            }
+        }
+        Err(SmtpTestFailure::from(last_err.unwrap_or_default()))
+    })
+    .await
+    .map_err(|e| format!("SMTP test task failed: {e}"))?
+}
+
+/// 没有任务时立即启动；已有任务运行或排队时放入任务队列（返回 `job_queued`），
+/// 前面的任务结束后按顺序自动启动。带 `scheduled_at` 时保存为定时任务（返回 `job_scheduled`）。
+#[tauri::command]
+fn start_send(
+    app: AppHandle,
+    state: State<'_, WorkerState>,
+    mut payload: Value,
+) -> Result<Value, String> {
+    if state.updating.load(Ordering::SeqCst) {
+        return Err("正在应用运行时更新，请稍后再试".to_string());
+    }
+    check_send_payload(&app, &payload)?;
+    let job_id = assign_job_id(&mut payload)?;
+    let scheduled_at = payload
+        .as_object_mut()
+        .and_then(|fields| fields.remove("scheduled_at"))
+        .filter(|value| !value.is_null());
+    if let Some(scheduled_at) = scheduled_at {
+        return schedule_job(&app, &state, job_id, payload, &scheduled_at);
+    }
+    submit_job(&app, &state, job_id, payload)
+}
+
+/// 检查发送引擎与 SMTP 主机是否允许使用。
+fn check_send_payload(app: &AppHandle, payload: &Value) -> Result<(), String> {
+    #[cfg(feature = "native-only")]
+    if payload.get("engine").and_then(Value::as_str) != Some("native") {
+        return Err("此版本仅包含原生发送引擎（engine: native）".to_string());
+    }
+    let local_mta = payload.get("local_mta").is_some_and(|value| !value.is_null());
+    if let Some(host) = payload.pointer("/smtp/host").and_then(Value::as_str).filter(|_| !local_mta) {
+        read_app_settings(app)?.network.check_smtp_host(host)?;
+    }
+    Ok(())
+}
+
+/// 延后发送的任务提前检查参数，避免到时才发现无法发送。
+fn probe_deferred_payload(payload: &Value) -> Result<(), String> {
+    if payload.get("engine").and_then(Value::as_str) == Some("native") {
+        let mut probe = payload.clone();
+        normalize_copy_recipients(&mut probe)?;
+        SendJob::from_payload(probe)?;
+    }
+    Ok(())
+}
+
+/// 立即启动或放入任务队列。立即启动时先在队列锁内占用运行位置，释放锁后再启动（worker 启动与握手可能较慢）。
+fn submit_job(app: &AppHandle, state: &WorkerState, job_id: String, payload: Value) -> Result<Value, String> {
+    {
+        let mut queue = state
+            .queue
+            .lock()
+            .map_err(|_| "failed to acquire job queue lock".to_string())?;
+        if queue.contains(&job_id) {
+            return Err(format!("任务 {job_id} 已在运行或排队中"));
+        }
+        // 续发沿用原任务 ID，上一次运行缓存的事件不再补发。
+        if let Ok(mut replay) = state.events.lock() {
+            replay.restart(&job_id);
+        }
+        let summary = JobSummary::from_payload(&job_id, &payload);
+        let _ = update_journal(app, state, |journal| journal.record(&job_id, payload.clone()));
+        queue.set_max_running(job_concurrency_limit(app));
+        if queue.is_busy() {
+            let position = queue.push(QueuedJob { summary, payload });
+            let _ = app.emit(JOB_QUEUE_CHANNEL, queue.list());
+            return Ok(json!({ "type": "job_queued", "job_id": job_id, "position": position }));
+        }
+        state
+            .jobs
+            .lock()
+            .map_err(|_| "failed to acquire job registry lock".to_string())?
+            .reserve(&job_id);
+        queue.start(summary);
+        let _ = app.emit(JOB_QUEUE_CHANNEL, queue.list());
+    }
+    start_reserved_job(app, state, &job_id, payload)
+}
+
+/// 启动已占用运行位置的任务，调用时不持有队列锁。启动失败时移除任务记录并释放运行位置，
+/// 期间排进队列的任务随之按顺序启动。
+fn start_reserved_job(app: &AppHandle, state: &WorkerState, job_id: &str, payload: Value) -> Result<Value, String> {
+    launch_job(app, state, payload).inspect_err(|_| {
+        let _ = update_journal(app, state, |journal| {
+            journal.remove(job_id);
+        });
+        advance_job_queue(app, job_id);
+    })
+}
+
+/// 登记启动完成的任务；启动期间收到的取消请求在登记后转交任务。
+fn register_job(app: &AppHandle, state: &WorkerState, job_id: &str, control: JobControl) -> Result<(), String> {
+    let cancel = state
+        .jobs
+        .lock()
+        .map_err(|_| "failed to acquire job registry lock".to_string())?
+        .insert(job_id, control);
+    match cancel {
+        Some(force) => cancel_running_job(app, state, job_id, force),
+        None => Ok(()),
+    }
+}
+
+/// 保存定时任务（应用重启后仍然有效），到时由调度线程提交到任务队列。
+fn schedule_job(
+    app: &AppHandle,
+    state: &WorkerState,
+    job_id: String,
+    payload: Value,
+    scheduled_at: &Value,
+) -> Result<Value, String> {
+    let at = parse_scheduled_at(scheduled_at.as_str().ok_or("定时发送时间必须是字符串")?)?;
+    if at <= chrono::Utc::now() {
+        return Err("定时发送时间必须晚于当前时间".to_string());
+    }
+    probe_deferred_payload(&payload)?;
+    let mut schedule = state
+        .scheduled
+        .lock()
+        .map_err(|_| "failed to acquire schedule lock".to_string())?;
+    let queued = state
+        .queue
+        .lock()
+        .map_err(|_| "failed to acquire job queue lock".to_string())?
+        .contains(&job_id);
+    if queued || schedule.contains(&job_id) {
+        return Err(format!("任务 {job_id} 已在运行、排队或定时等待中"));
+    }
+    let scheduled_at = format_scheduled_at(at);
+    schedule.add(ScheduledJob {
+        job_id: job_id.clone(),
+        scheduled_at: scheduled_at.clone(),
+        payload,
+    });
+    schedule.save(&resolve_data_file(app, SCHEDULED_JOBS_RELATIVE_PATH)?)?;
+    Ok(json!({ "type": "job_scheduled", "job_id": job_id, "scheduled_at": scheduled_at }))
+}
+
+/// 每秒检查一次定时任务：到时提交到任务队列；未到时发送 `scheduled_wait` 倒计时事件
+/// （最后一分钟每秒一次，之前每分钟一次）。启动时先载入保存的定时任务，错过的任务立即提交。
+/// 周期任务同样在这里检查，每次到时提交一次独立的运行。
+fn spawn_send_scheduler(app: AppHandle) {
+    std::thread::spawn(move || {
+        let state = app.state::<WorkerState>();
+        let saved = resolve_data_file(&app, SCHEDULED_JOBS_RELATIVE_PATH).and_then(|path| Schedule::load(&path));
+        if let (Ok(saved), Ok(mut schedule)) = (saved, state.scheduled.lock()) {
+            *schedule = saved;
+        }
+        let saved =
+            resolve_data_file(&app, RECURRING_CAMPAIGNS_RELATIVE_PATH).and_then(|path| RecurringCampaigns::load(&path));
+        if let (Ok(saved), Ok(mut recurring)) = (saved, state.recurring.lock()) {
+            *recurring = saved;
+        }
+        // 每个定时任务上次倒计时事件的剩余分钟数；按分钟数变化判断，检查间隔漂移时不会漏掉某一分钟。
+        let mut emitted_minutes: HashMap<String, i64> = HashMap::new();
+        loop {
+            let now = chrono::Utc::now();
+            let due = {
+                let Ok(mut schedule) = state.scheduled.lock() else {
+                    break;
+                };
+                let due = schedule.take_due(now);
+                if !due.is_empty() {
+                    if let Ok(path) = resolve_data_file(&app, SCHEDULED_JOBS_RELATIVE_PATH) {
+                        let _ = schedule.save(&path);
+                    }
+                }
+                emitted_minutes.retain(|job_id, _| schedule.jobs.iter().any(|job| &job.job_id == job_id));
+                for job in &schedule.jobs {
+                    let remaining = job.due_at().map_or(0, |at| (at - now).num_seconds().max(0));
+                    let minute = remaining / 60;
+                    if remaining <= 60 || emitted_minutes.get(&job.job_id) != Some(&minute) {
+                        emitted_minutes.insert(job.job_id.clone(), minute);
+                        let event = json!({
+                            "type": "scheduled_wait",
+                            "job_id": job.job_id,
+                            "scheduled_at": job.scheduled_at,
+                            "remaining_sec": remaining,
+                        });
+                        emit_worker_event(&app, event);
+                    }
+                }
+                due
+            };
+            let occurrences = {
+                let Ok(mut recurring) = state.recurring.lock() else {
+                    break;
+                };
+                let occurrences = recurring.take_due(now);
+                if !occurrences.is_empty() {
+                    if let Ok(path) = resolve_data_file(&app, RECURRING_CAMPAIGNS_RELATIVE_PATH) {
+                        let _ = recurring.save(&path);
+                    }
+                }
+                occurrences
+            };
+            let jobs = due.into_iter().map(|job| (job.job_id, job.payload)).chain(occurrences);
+            for (job_id, payload) in jobs {
+                let event = submit_job(&app, &state, job_id.clone(), payload)
+                    .unwrap_or_else(|error| json!({ "type": "error", "job_id": job_id, "error": error }));
+                emit_worker_event(&app, event);
+            }
+            std::thread::sleep(Duration::from_secs(1));
+        }
+    });
+}
+
+/// 排队前确定任务 ID，以便列出与调整顺序：续发沿用原任务 ID，否则使用参数中的 ID 或生成默认 ID。
+fn assign_job_id(payload: &mut Value) -> Result<String, String> {
+    let given = |key: &str| {
+        payload
+            .get(key)
+            .and_then(Value::as_str)
+            .map(str::trim)
+            .filter(|id| !id.is_empty())
+            .map(str::to_string)
+    };
+    let job_id = given("resume_campaign")
+        .or_else(|| given("job_id"))
+        .unwrap_or_else(|| default_job_id(payload.pointer("/options/seed").and_then(Value::as_u64)));
+    let Some(fields) = payload.as_object_mut() else {
+        return Err("发送参数必须是 JSON 对象".to_string());
+    };
+    fields.insert("job_id".to_string(), json!(job_id));
+    Ok(job_id)
+}
+
+/// 任务的线程结束后调用：释放它的运行位置，并在同时运行数未达上限时按顺序启动排队的任务；
+/// 启动失败的任务以 `error` 事件通知界面。
+/// 任务日志中没有结束事件（worker 崩溃）的任务保留在任务记录中，之后可用 `recover_jobs` 续发。
+fn advance_job_queue(app: &AppHandle, finished: &str) {
+    let state = app.state::<WorkerState>();
+    let ready = {
+        let Ok(mut queue) = state.queue.lock() else {
+            return;
+        };
+        if queue.finish(finished).is_some() {
+            let events = campaign_log(app).and_then(|log| log.read(finished)).ok().flatten();
+            let ended =
+                events.is_some_and(|events| CampaignSummary::from_events(finished, &events).status != "incomplete");
+            if ended {
+                let _ = update_journal(app, &state, |journal| {
+                    journal.remove(finished);
+                });
+            }
+        }
+        let Ok(mut jobs) = state.jobs.lock() else {
+            return;
+        };
+        jobs.remove(finished);
+        queue.set_max_running(job_concurrency_limit(app));
+        let mut ready = Vec::new();
+        while let Some(job) = queue.pop_ready() {
+            jobs.reserve(&job.summary.job_id);
+            ready.push((job.summary.job_id.clone(), job.payload));
+            queue.start(job.summary);
+        }
+        let _ = app.emit(JOB_QUEUE_CHANNEL, queue.list());
+        ready
+    };
+    for (job_id, payload) in ready {
+        match start_reserved_job(app, &state, &job_id, payload) {
+            Ok(accepted) => emit_worker_event(app, accepted),
+            Err(error) => emit_worker_event(app, json!({ "type": "error", "job_id": job_id, "error": error })),
+        }
+    }
+}
+
+/// 同时运行的任务数：开启共享数据目录时为 1（发送锁同一时间只能由一个任务持有）；设置了每日上限时也为 1
+/// （Python worker 的任务只在启动时按剩余额度截断收件人，多个任务同时运行会超出上限）；否则按任务队列设置。
+fn job_concurrency_limit(app: &AppHandle) -> usize {
+    match read_app_settings(app) {
+        Ok(settings) if !settings.shared.enabled && settings.throttle.per_day.is_none() => settings.jobs.max_concurrent,
+        _ => 1,
+    }
+}
+
+/// 修改并保存任务记录；记录失败不影响发送。
+fn update_journal(app: &AppHandle, state: &WorkerState, update: impl FnOnce(&mut JobJournal)) -> Result<(), String> {
+    let mut journal = state
+        .journal
+        .lock()
+        .map_err(|_| "failed to acquire job journal lock".to_string())?;
+    update(&mut journal);
+    journal.save(&resolve_data_file(app, ACTIVE_JOBS_RELATIVE_PATH)?)
+}
+
+/// 上次运行时中断（应用或 worker 崩溃）的任务及其进度，启动后由界面询问是否续发。
+#[tauri::command]
+fn list_interrupted_jobs(app: AppHandle, state: State<'_, WorkerState>) -> Result<Vec<InterruptedJob>, String> {
+    let entries = state
+        .journal
+        .lock()
+        .map_err(|_| "failed to acquire job journal lock".to_string())?
+        .jobs
+        .clone();
+    let queue = state
+        .queue
+        .lock()
+        .map_err(|_| "failed to acquire job queue lock".to_string())?;
+    let log = campaign_log(&app)?;
+    Ok(entries
+        .iter()
+        .filter(|entry| !queue.contains(&entry.job_id))
+        .map(|entry| {
+            let progress = log
+                .read(&entry.job_id)
+                .ok()
+                .flatten()
+                .map(|events| CampaignSummary::from_events(&entry.job_id, &events));
+            InterruptedJob::new(entry, progress.as_ref())
+        })
+        .collect())
+}
+
+/// 续发 `job_ids` 中的中断任务（跳过发送记录中已送达的收件人），其余中断任务放弃。
+/// 每个任务返回 `job_accepted` / `job_queued`，无法续发时返回 `error`。
+#[tauri::command]
+fn recover_jobs(app: AppHandle, state: State<'_, WorkerState>, job_ids: Vec<String>) -> Result<Vec<Value>, String> {
+    let interrupted = list_interrupted_jobs(app.clone(), state.clone())?;
+    if let Some(job_id) = job_ids
+        .iter()
+        .find(|job_id| !interrupted.iter().any(|job| &job.summary.job_id == *job_id))
+    {
+        return Err(format!("中断的任务不存在: {job_id}"));
+    }
+    let mut resumed = Vec::new();
+    update_journal(&app, &state, |journal| {
+        for job in &interrupted {
+            if let Some(entry) = journal.remove(&job.summary.job_id) {
+                if job_ids.contains(&entry.job_id) {
+                    resumed.push((entry, job.last_event_at.is_some()));
+                }
+            }
+        }
+    })?;
+    let mut results = Vec::with_capacity(resumed.len());
+    for (mut entry, started) in resumed {
+        if started {
+            entry.payload["resume_campaign"] = json!(entry.job_id);
+        }
+        let job_id = entry.job_id.clone();
+        results.push(
+            submit_job(&app, &state, entry.job_id, entry.payload)
+                .unwrap_or_else(|error| json!({ "type": "error", "job_id": job_id, "error": error })),
+        );
+    }
+    Ok(results)
+}
+
+#[tauri::command]
+fn list_jobs(state: State<'_, WorkerState>) -> Result<JobList, String> {
+    job_list(&state)
+}
+
+fn job_list(state: &WorkerState) -> Result<JobList, String> {
+    let mut list = state
+        .queue
+        .lock()
+        .map_err(|_| "failed to acquire job queue lock".to_string())?
+        .list();
+    list.scheduled = state
+        .scheduled
+        .lock()
+        .map_err(|_| "failed to acquire schedule lock".to_string())?
+        .jobs
+        .iter()
+        .map(ScheduledJob::summary)
+        .collect();
+    Ok(list)
+}
+
+/// 取消尚未到发送时间的定时任务。
+#[tauri::command]
+fn cancel_scheduled_job(app: AppHandle, state: State<'_, WorkerState>, job_id: String) -> Result<JobList, String> {
+    {
+        let mut schedule = state
+            .scheduled
+            .lock()
+            .map_err(|_| "failed to acquire schedule lock".to_string())?;
+        if schedule.remove(job_id.trim()).is_none() {
+            return Err(format!("定时任务不存在: {job_id}"));
+        }
+        schedule.save(&resolve_data_file(&app, SCHEDULED_JOBS_RELATIVE_PATH)?)?;
+    }
+    job_list(&state)
+}
+
+#[tauri::command]
+fn list_recurring_campaigns(state: State<'_, WorkerState>) -> Result<Vec<RecurringCampaignSummary>, String> {
+    let now = chrono::Utc::now();
+    Ok(state
+        .recurring
+        .lock()
+        .map_err(|_| "failed to acquire recurring campaigns lock".to_string())?
+        .campaigns
+        .iter()
+        .map(|campaign| campaign.summary(now))
+        .collect())
+}
+
+/// 新增或修改周期任务（`id` 为空时新增），保存后由调度线程按 cron 表达式运行。
+#[tauri::command]
+fn save_recurring_campaign(
+    app: AppHandle,
+    state: State<'_, WorkerState>,
+    mut campaign: RecurringCampaign,
+) -> Result<RecurringCampaignSummary, String> {
+    check_send_payload(&app, &campaign.payload)?;
+    probe_deferred_payload(&campaign.payload)?;
+    if let Some(fields) = campaign.payload.as_object_mut() {
+        fields.remove("job_id");
+    }
+    let now = chrono::Utc::now();
+    let mut recurring = state
+        .recurring
+        .lock()
+        .map_err(|_| "failed to acquire recurring campaigns lock".to_string())?;
+    let id = recurring.upsert(campaign, now)?;
+    recurring.save(&resolve_data_file(&app, RECURRING_CAMPAIGNS_RELATIVE_PATH)?)?;
+    recurring
+        .campaigns
+        .iter()
+        .find(|campaign| campaign.id == id)
+        .map(|campaign| campaign.summary(now))
+        .ok_or_else(|| format!("周期任务不存在: {id}"))
+}
+
+/// 删除周期任务；已提交的运行不受影响。
+#[tauri::command]
+fn delete_recurring_campaign(
+    app: AppHandle,
+    state: State<'_, WorkerState>,
+    id: String,
+) -> Result<Vec<RecurringCampaignSummary>, String> {
+    {
+        let mut recurring = state
+            .recurring
+            .lock()
+            .map_err(|_| "failed to acquire recurring campaigns lock".to_string())?;
+        if recurring.remove(id.trim()).is_none() {
+            return Err(format!("周期任务不存在: {id}"));
+        }
+        recurring.save(&resolve_data_file(&app, RECURRING_CAMPAIGNS_RELATIVE_PATH)?)?;
+    }
+    list_recurring_campaigns(state)
+}
+
+/// 调整等待中任务的顺序：`job_ids` 中的任务按给定顺序排到队首。
+#[tauri::command]
+fn reorder_jobs(app: AppHandle, state: State<'_, WorkerState>, job_ids: Vec<String>) -> Result<JobList, String> {
+    let mut queue = state
+        .queue
+        .lock()
+        .map_err(|_| "failed to acquire job queue lock".to_string())?;
+    queue.reorder(&job_ids)?;
+    let list = queue.list();
+    let _ = app.emit(JOB_QUEUE_CHANNEL, list.clone());
+    Ok(list)
+}
+
+/// 启动一个任务：应用抑制列表、同意策略与限速后交给 Python worker 或原生引擎。
+fn launch_job(app: &AppHandle, state: &WorkerState, mut payload: Value) -> Result<Value, String> {
+    let app = app.clone();
+    #[cfg(not(feature = "native-only"))]
+    let native = payload.get("engine").and_then(Value::as_str) == Some("native");
+    #[cfg(not(feature = "native-only"))]
+    let local_mta = payload.get("local_mta").is_some_and(|value| !value.is_null());
+    normalize_copy_recipients(&mut payload)?;
+    // 在抑制列表、限额等过滤之前保存，复制任务时能还原完整的收件人。
+    let definition = payload.clone();
+    let resumed = apply_resume(&app, &mut payload)?;
+    let suppressed = apply_suppression_list(&app, &mut payload)?;
+    let consent_blocked = apply_consent_policy(&app, &mut payload)?;
+    check_campaign_environment(&app, &payload)?;
+    let settings = read_app_settings(&app)?;
+    let identity = UserIdentity::current(&settings.shared);
+    payload["operator"] = json!(identity.label());
+    let send_lock = if settings.shared.enabled {
+        Some(DataDirLock::acquire(&resolve_data_dir(&app)?, "send", &identity)?)
+    } else {
+        None
+    };
+    let throttle = shared_throttle(&app, state)?;
+    let throttled = {
+        let mut guard = throttle
+            .lock()
+            .map_err(|_| "failed to acquire throttle lock".to_string())?;
+        apply_throttle(&mut payload, &mut guard)?
+    };
+    let hooks = SendEventHooks::new(&app, throttle, send_lock, definition)?;
+
+    #[cfg(not(feature = "native-only"))]
+    if !native {
+        python_runtime::reject_native_only_options(&payload, local_mta)?;
+        let job_id = payload["job_id"].clone();
+        python_runtime::spawn_worker_job(app, payload, hooks)?;
+        return Ok(json!({
+            "type": "job_accepted",
+            "job_id": job_id,
+            "throttled_recipients": throttled,
+            "consent_blocked": consent_blocked,
+            "suppressed": suppressed,
+            "resumed": resumed,
+        }));
+    }
+    apply_saved_column_mapping(&app, &mut payload)?;
+    let exclusion = stream_exclusion(&app, &payload)?;
+    let mut job = SendJob::from_payload(payload)?;
+    let attachments_linked = apply_oversize_policy(&app, &mut job)?;
+    let job_id = job.job_id().to_string();
+    let control = spawn_native_job(app.clone(), job, hooks, exclusion)?;
+    register_job(&app, state, &job_id, control)?;
+    Ok(json!({
+        "type": "job_accepted",
+        "job_id": job_id,
+        "throttled_recipients": throttled,
+        "consent_blocked": consent_blocked,
+        "suppressed": suppressed,
+        "resumed": resumed,
+        "attachments_linked": attachments_linked,
+    }))
+}
+
+/// 快速发送单封事务性邮件（回复咨询、单独重发失败的收件人）：不占用任务队列、不等待发送间隔，
+/// 与正在进行的任务共享限速、账号每日上限与发送记录，超出限额时直接报错。始终使用原生发送引擎。
+#[tauri::command]
+async fn send_single(app: AppHandle, state: State<'_, WorkerState>, payload: Value) -> Result<Value, String> {
+    if state.updating.load(Ordering::SeqCst) {
+        return Err("正在应用运行时更新，请稍后再试".to_string());
+    }
+    let throttle = shared_throttle(&app, &state)?;
+    tauri::async_runtime::spawn_blocking(move || run_single_send(&app, throttle, payload))
+        .await
+        .map_err(|err| format!("快速发送失败: {err}"))?
+}
+
+/// 返回 `recipient_sent` 事件；事件同样交给发送观察者，计入限速、失效域名统计与任务事件日志。
+fn run_single_send(app: &AppHandle, throttle: Arc<Mutex<Throttle>>, mut payload: Value) -> Result<Value, String> {
+    let Some(fields) = payload.as_object_mut() else {
+        return Err("发送参数必须是 JSON 对象".to_string());
+    };
+    if fields.get("job_id").and_then(Value::as_str).is_none_or(|id| id.trim().is_empty()) {
+        let job_id = format!("single-{}", chrono::Local::now().format("%Y%m%d%H%M%S%3f"));
+        fields.insert("job_id".to_string(), json!(job_id));
+    }
+    normalize_copy_recipients(&mut payload)?;
+    apply_suppression_list(app, &mut payload)?;
+    apply_consent_policy(app, &mut payload)?;
+    check_campaign_environment(app, &payload)?;
+    let settings = read_app_settings(app)?;
+    payload["operator"] = json!(UserIdentity::current(&settings.shared).label());
+    let mut job = SendJob::from_payload(payload)?;
+    apply_oversize_policy(app, &mut job)?;
+
+    let usage_path = resolve_data_file(app, SMTP_ACCOUNT_USAGE_RELATIVE_PATH)?;
+    let mut ledger = UsageLedger::load(&usage_path);
+    let (slots, rotation) = native_slots(app, &job, &settings.network, &ledger)?;
+    let tracks_usage = rotation.is_some();
+    let sent_store = SentStore::open(&job.sent_store_path(), Some(&job.sent_store_text_path()))?
+        .with_operator(job.operator.clone())
+        .with_environment(job.environment);
+    let mut engine = SendEngine::new(slots, sent_store, Arc::new(AtomicBool::new(false)))
+        .with_throttle(Arc::clone(&throttle));
+    if let Some(rotation) = rotation {
+        engine = engine.with_rotation(rotation);
+    }
+    if let Some(smime) = smime_context(app, &job)? {
+        engine = engine.with_smime(smime);
+    }
+    let hooks = SendEventHooks::new(app, throttle, None, Value::Null)?;
+    let mut sent = Value::Null;
+    engine.send_single(&job, &mut |event| {
+        hooks.observe(&event);
+        if event["type"] == "recipient_sent" {
+            if let Some(account_id) = event["account_id"].as_str().filter(|_| tracks_usage) {
+                ledger.record(account_id);
+                let _ = ledger.save(&usage_path);
+            }
+            sent = event;
+        }
+    })?;
+    Ok(sent)
+}
+
+/// 取消指定任务：运行中的任务默认等当前邮件发送完成、发送记录写入后停止，
+/// `force` 时直接结束 Python worker 进程（原生引擎总是在两封邮件之间停止）；排队中的任务直接移出队列。
+/// 任务不在运行或排队中时返回错误；定时任务用 `cancel_scheduled_job` 取消。
+#[tauri::command]
+fn cancel_send(
+    app: AppHandle,
+    state: State<'_, WorkerState>,
+    job_id: String,
+    force: Option<bool>,
+) -> Result<(), String> {
+    let job_id = job_id.trim();
+    if !cancel_job(&app, &state, job_id, force.unwrap_or(false))? {
+        return Err(format!("没有运行或排队中的任务: {job_id}"));
+    }
+    Ok(())
+}
+
+/// 紧急停止：清空任务队列并取消全部运行中的任务，返回被取消的任务 ID。定时与周期任务不受影响。
+/// 某个任务取消失败时仍继续取消其余任务，最后汇总报错。
+#[tauri::command]
+fn cancel_all_jobs(app: AppHandle, state: State<'_, WorkerState>, force: Option<bool>) -> Result<Vec<String>, String> {
+    let list = state
+        .queue
+        .lock()
+        .map_err(|_| "failed to acquire job queue lock".to_string())?
+        .list();
+    // 先清空等待队列，避免运行中的任务结束后启动排队的任务。
+    let job_ids = list.queued.iter().chain(&list.running).map(|job| job.job_id.clone());
+    let mut cancelled = Vec::new();
+    let mut errors = Vec::new();
+    for job_id in job_ids {
+        match cancel_job(&app, &state, &job_id, force.unwrap_or(false)) {
+            Ok(true) => cancelled.push(job_id),
+            Ok(false) => {}
+            Err(error) => errors.push(format!("{job_id}: {error}")),
+        }
+    }
+    if !errors.is_empty() {
+        return Err(format!("部分任务取消失败：{}", errors.join("；")));
+    }
+    Ok(cancelled)
+}
+
+/// 取消运行中或排队中的任务；任务不存在（或已结束）时返回 `false`。
+fn cancel_job(app: &AppHandle, state: &WorkerState, job_id: &str, force: bool) -> Result<bool, String> {
+    let removed = {
+        let mut queue = state
+            .queue
+            .lock()
+            .map_err(|_| "failed to acquire job queue lock".to_string())?;
+        if queue.is_running(job_id) {
+            None
+        } else {
+            let Some(job) = queue.remove_pending(job_id) else {
+                return Ok(false);
+            };
+            let _ = app.emit(JOB_QUEUE_CHANNEL, queue.list());
+            Some(job)
+        }
+    };
+    // 主动取消的任务不再作为中断任务提示续发；日志写入失败不影响取消本身。
+    let _ = update_journal(app, state, |journal| {
+        journal.remove(job_id);
+    });
+    if let Some(job) = removed {
+        let event = json!({
+            "type": "job_cancelled",
+            "job_id": job_id,
+            "success": 0,
+            "failed": 0,
+            "skipped": 0,
+            "total": job.summary.recipients,
+        });
+        emit_worker_event(app, event);
+        return Ok(true);
+    }
+    cancel_running_job(app, state, job_id, force)?;
+    Ok(true)
+}
+
+/// 通知运行中的任务停止；任务不在运行时不做任何事。
+fn cancel_running_job(app: &AppHandle, state: &WorkerState, job_id: &str, force: bool) -> Result<(), String> {
+    let graceful = {
+        let mut jobs = state
+            .jobs
+            .lock()
+            .map_err(|_| "failed to acquire job registry lock".to_string())?;
+        match jobs.get_mut(job_id) {
+            Some(JobControl::Native { cancel, .. }) => {
+                cancel.store(true, Ordering::SeqCst);
+                false
+            }
+            #[cfg(not(feature = "native-only"))]
+            Some(JobControl::Worker { child, .. }) if force => {
+                child
+                    .kill()
+                    .map_err(|err| format!("failed to kill worker process: {err}"))?;
+                false
+            }
+            #[cfg(not(feature = "native-only"))]
+            Some(JobControl::Worker { .. }) => true,
+            // 仍在启动：登记后转交取消请求。
+            None => {
+                jobs.cancel_starting(job_id, force);
+                false
+            }
+        }
+    };
+    #[cfg(not(feature = "native-only"))]
+    if graceful {
+        return python_runtime::request_worker_cancel(app, job_id);
+    }
+    #[cfg(feature = "native-only")]
+    let _ = (app, force, graceful);
+    Ok(())
+}
+
+/// 调整正在发送的任务的限速，从下一封邮件起生效，无需取消后重新发送。
+/// 与全局限速同时生效；其中的间隔上下限覆盖任务原本的发送间隔，因此既可以放慢也可以加快。
+/// Python worker 的任务通过控制消息调整：速率上限换算为最小发送间隔。
+#[tauri::command]
+fn update_job_throttle(
+    app: AppHandle,
+    state: State<'_, WorkerState>,
+    job_id: String,
+    new_limits: ThrottleLimits,
+) -> Result<ThrottleLimits, String> {
+    new_limits.validate()?;
+    let job_id = job_id.trim();
+    let running = state
+        .queue
+        .lock()
+        .map_err(|_| "failed to acquire job queue lock".to_string())?
+        .is_running(job_id);
+    if !running {
+        return Err(format!("没有正在发送的任务: {job_id}"));
+    }
+    let native = {
+        let jobs = state
+            .jobs
+            .lock()
+            .map_err(|_| "failed to acquire job registry lock".to_string())?;
+        match jobs.get(job_id) {
+            Some(JobControl::Native { throttle, .. }) => {
+                throttle
+                    .lock()
+                    .map_err(|_| "failed to acquire throttle lock".to_string())?
+                    .set_limits(new_limits.clone());
+                true
+            }
+            _ => false,
+        }
+    };
+    if !native && !send_worker_control(&app, job_id, RequestBody::Throttle(json!(new_limits)))? {
+        return Err(format!("没有正在发送的任务: {job_id}"));
+    }
+    let event = json!({ "type": "job_throttle_updated", "job_id": job_id, "limits": new_limits });
+    emit_worker_event(&app, event);
+    Ok(new_limits)
+}
+
+/// 暂停正在发送的任务：当前邮件发送完成、发送记录写入后停在下一位收件人之前，worker 随后输出 `job_paused`。
+/// 暂停中的任务仍占用运行名额，可以继续或取消。仅支持 Python worker 发送引擎。
+#[tauri::command]
+fn pause_job(app: AppHandle, state: State<'_, WorkerState>, job_id: String) -> Result<(), String> {
+    control_worker_job(&app, &state, &job_id, RequestBody::Pause)
+}
+
+/// 继续已暂停的任务，worker 随后输出 `job_resumed`。
+#[tauri::command]
+fn resume_job(app: AppHandle, state: State<'_, WorkerState>, job_id: String) -> Result<(), String> {
+    control_worker_job(&app, &state, &job_id, RequestBody::Resume)
+}
+
+fn control_worker_job(app: &AppHandle, state: &WorkerState, job_id: &str, body: RequestBody) -> Result<(), String> {
+    let job_id = job_id.trim();
+    let running = state
+        .queue
+        .lock()
+        .map_err(|_| "failed to acquire job queue lock".to_string())?
+        .is_running(job_id);
+    if !running {
+        return Err(format!("没有正在发送的任务: {job_id}"));
+    }
+    if !send_worker_control(app, job_id, body)? {
+        return Err("暂停与继续仅支持 Python worker 发送引擎".to_string());
+    }
+    Ok(())
+}
+
+/// 把控制消息写给任务的 worker；任务不是由 worker 执行（原生引擎或 native-only 构建）或已经结束时返回 `false`。
+#[cfg(not(feature = "native-only"))]
+fn send_worker_control(app: &AppHandle, job_id: &str, body: RequestBody) -> Result<bool, String> {
+    python_runtime::send_worker_control(app, job_id, body)
+}
+
+#[cfg(feature = "native-only")]
+fn send_worker_control(_app: &AppHandle, _job_id: &str, _body: RequestBody) -> Result<bool, String> {
+    Ok(false)
+}
+
+/// 推送一条任务事件：编号后存入 `WorkerState::events`（界面刷新后可用 `get_job_events` 补发），
+/// 推送到任务自己的频道；生命周期事件与没有任务 ID 的事件同时推送到 `JOB_LIFECYCLE_CHANNEL`。
+fn emit_worker_event(app: &AppHandle, mut event: Value) {
+    if let Ok(mut replay) = app.state::<WorkerState>().events.lock() {
+        replay.record(&mut event);
+    }
+    let lifecycle = JOB_LIFECYCLE_EVENTS.contains(&event["type"].as_str().unwrap_or_default());
+    match event["job_id"].as_str() {
+        Some(job_id) => {
+            let _ = app.emit(&job_event_channel(job_id), &event);
+            if lifecycle {
+                let _ = app.emit(JOB_LIFECYCLE_CHANNEL, event);
+            }
+        }
+        None => {
+            let _ = app.emit(JOB_LIFECYCLE_CHANNEL, event);
+        }
+    }
+}
+
+/// 任务的事件频道。事件名只允许字母、数字与 `-/:_`，任务 ID 中的其他字符替换为 `_`（界面按同样规则订阅）。
+fn job_event_channel(job_id: &str) -> String {
+    let job_id: String = job_id
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || "-/:_".contains(c) { c } else { '_' })
+        .collect();
+    format!("{WORKER_EVENT_CHANNEL}:{job_id}")
+}
+
+/// 任务中序号大于 `since_seq` 的缓存事件与最新进度，界面刷新或重新打开后据此接上运行中的任务。
+/// 任务还没有推送过事件（或缓存已淘汰）时返回空结果。
+#[tauri::command]
+fn get_job_events(state: State<'_, WorkerState>, job_id: String, since_seq: Option<u64>) -> Result<JobEvents, String> {
+    Ok(state
+        .events
+        .lock()
+        .map_err(|_| "failed to acquire event buffer lock".to_string())?
+        .since(job_id.trim(), since_seq.unwrap_or(0))
+        .unwrap_or_default())
+}
+
+/// 单个任务的状态（运行中、排队中或定时等待）与任务日志中的进度。
+#[tauri::command]
+fn get_job_status(app: AppHandle, state: State<'_, WorkerState>, job_id: String) -> Result<JobStatus, String> {
+    let job_id = job_id.trim();
+    let queued = state
+        .queue
+        .lock()
+        .map_err(|_| "failed to acquire job queue lock".to_string())?
+        .status(job_id)
+        .map(|(summary, job_state)| (summary.clone(), job_state));
+    let (summary, job_state) = match queued {
+        Some(found) => found,
+        None => state
+            .scheduled
+            .lock()
+            .map_err(|_| "failed to acquire schedule lock".to_string())?
+            .jobs
+            .iter()
+            .find(|job| job.job_id == job_id)
+            .map(|job| (job.summary(), JobState::Scheduled))
+            .ok_or_else(|| format!("任务不存在: {job_id}"))?,
+    };
+    let progress = campaign_log(&app)?
+        .read(job_id)?
+        .map(|events| CampaignSummary::from_events(job_id, &events));
+    Ok(JobStatus::new(summary, job_state, progress.as_ref()))
+}
+
+/// 清除发送记录：不带条件时删除全部成功记录文件；带条件时只删除匹配的成功记录与失败事件。有任务正在发送时拒绝。
+#[tauri::command]
+fn clear_sent_records(
+    app: AppHandle,
+    state: State<'_, WorkerState>,
+    filter: Option<ClearFilter>,
+) -> Result<ClearReport, String> {
+    let paths = resolve_app_paths(&app)?;
+    with_idle_sent_store(&state, "清除发送记录", || {
+        if let Some(filter) = filter {
+            return sent_history(&app)?.clear(Some(Path::new(&paths.sent_store_text_file)), &filter);
+        }
+        let mut removed_sent = 0;
+        sent_history(&app)?.for_each(Some(RecordStatus::Sent), None, |_, _| removed_sent += 1)?;
+        for target in [&paths.sent_store_file, &paths.sent_store_text_file] {
+            let file = PathBuf::from(target);
+            if file.exists() {
+                fs::remove_file(&file)
+                    .map_err(|err| format!("failed to remove sent records: {err}"))?;
+            }
+        }
+        Ok(ClearReport {
+            removed_sent,
+            removed_failed: 0,
+        })
+    })
+}
+
+/// 分页查询发送历史（成功记录与各任务的失败事件），按时间倒序。
+#[tauri::command]
+async fn query_sent_records(app: AppHandle, query: SentRecordQuery) -> Result<SentRecordPage, String> {
+    let history = sent_history(&app)?;
+    tauri::async_runtime::spawn_blocking(move || history.query(&query))
+        .await
+        .map_err(|err| format!("查询发送历史失败: {err}"))?
+}
+
+/// 发送历史汇总：每日发送量、成功率、按 SMTP 错误分类的失败与平均发送速度。
+#[tauri::command]
+async fn get_send_stats(app: AppHandle, query: Option<SendStatsQuery>) -> Result<SendStats, String> {
+    let history = sent_history(&app)?;
+    tauri::async_runtime::spawn_blocking(move || history.stats(&query.unwrap_or_default()))
+        .await
+        .map_err(|err| format!("统计发送历史失败: {err}"))?
+}
+
+#[tauri::command]
+fn get_sent_record_retention(app: AppHandle) -> Result<RetentionPolicy, String> {
+    Ok(read_app_settings(&app)?.retention)
+}
+
+#[tauri::command]
+fn set_sent_record_retention(app: AppHandle, payload: RetentionPolicy) -> Result<RetentionPolicy, String> {
+    payload.validate()?;
+    let mut settings = read_app_settings(&app)?;
+    settings.retention = payload;
+    write_app_settings(&app, &settings)?;
+    Ok(payload)
+}
+
+/// 按保留策略清理发送记录与过期的任务日志；`policy` 为空时使用已保存的策略。有任务正在发送时拒绝。
+#[tauri::command]
+async fn purge_sent_records(app: AppHandle, policy: Option<RetentionPolicy>) -> Result<PurgeReport, String> {
+    let policy = match policy {
+        Some(policy) => policy,
+        None => read_app_settings(&app)?.retention,
+    };
+    tauri::async_runtime::spawn_blocking(move || purge_sent_history(&app, &policy))
+        .await
+        .map_err(|err| format!("清理发送记录失败: {err}"))?
+}
+
+fn purge_sent_history(app: &AppHandle, policy: &RetentionPolicy) -> Result<PurgeReport, String> {
+    let text_path = PathBuf::from(resolve_app_paths(app)?.sent_store_text_file);
+    with_idle_sent_store(&app.state::<WorkerState>(), "清理发送记录", || {
+        sent_history(app)?.purge(Some(&text_path), policy, chrono::Utc::now())
+    })
+}
+
+/// 改写发送记录（清理、删除）期间持有任务队列锁，并在有任务运行时拒绝：
+/// 运行中的任务在改写期间追加的记录会丢失，削弱防重复发送；持锁期间也不会有新任务启动。
+fn with_idle_sent_store<T>(
+    state: &WorkerState,
+    action: &str,
+    rewrite: impl FnOnce() -> Result<T, String>,
+) -> Result<T, String> {
+    let queue = state
+        .queue
+        .lock()
+        .map_err(|_| "failed to acquire job queue lock".to_string())?;
+    if !queue.running().is_empty() {
+        return Err(format!("有任务正在发送，请等任务结束后再{action}"));
+    }
+    rewrite()
+}
+
+fn sent_history(app: &AppHandle) -> Result<SentHistory, String> {
+    let paths = resolve_app_paths(app)?;
+    let campaigns_dir = resolve_data_dir(app)?.join(CAMPAIGNS_RELATIVE_PATH);
+    Ok(SentHistory::new(Path::new(&paths.sent_store_file), &campaigns_dir))
+}
+
+#[derive(Deserialize)]
+struct CampaignReportPayload {
+    campaign_id: String,
+    path: String,
+    /// 嵌入报告的中文 TrueType 字体；缺省时在系统字体目录中查找。
+    font_path: Option<String>,
+}
+
+/// 导出任务结果的 PDF 报告。优先使用任务事件日志；早期任务没有日志时退回到发送记录中的成功明细。
+#[tauri::command]
+fn export_campaign_report_pdf(app: AppHandle, payload: CampaignReportPayload) -> Result<String, String> {
+    let campaign_id = payload.campaign_id.trim();
+    if campaign_id.is_empty() {
+        return Err("任务 ID 不能为空".to_string());
+    }
+    let output = PathBuf::from(payload.path.trim());
+    if output.as_os_str().is_empty() {
+        return Err("报告保存路径不能为空".to_string());
+    }
+    let events = match campaign_log(&app)?.read(campaign_id)? {
+        Some(events) => events,
+        None => sent_records_as_events(&resolve_app_paths(&app)?.sent_store_file, campaign_id)?,
+    };
+    if events.is_empty() {
+        return Err(format!("没有找到任务 {campaign_id} 的发送记录"));
+    }
+    let summary = CampaignSummary::from_events(campaign_id, &events);
+    let font = report_pdf::resolve_report_font(payload.font_path.as_deref())?;
+    report_pdf::write_campaign_report(&summary, &font, &output)?;
+    Ok(output.to_string_lossy().to_string())
+}
+
+#[derive(Deserialize)]
+struct CampaignTimelinePayload {
+    campaign_id: String,
+    bucket: TimelineBucket,
+}
+
+/// 按分钟 / 小时统计任务的发送、失败与重试次数，界面据此绘制时间线，无需读取全部记录。
+#[tauri::command]
+fn get_campaign_timeline(app: AppHandle, payload: CampaignTimelinePayload) -> Result<CampaignTimeline, String> {
+    let campaign_id = payload.campaign_id.trim();
+    let events = match campaign_log(&app)?.read(campaign_id)? {
+        Some(events) => events,
+        None => sent_records_as_events(&resolve_app_paths(&app)?.sent_store_file, campaign_id)?,
+    };
+    if events.is_empty() {
+        return Err(format!("没有找到任务 {campaign_id} 的发送记录"));
+    }
+    CampaignTimeline::from_events(campaign_id, &events, payload.bucket)
+}
+
+/// 全部任务的历史记录（开始时间、状态与结果统计），从新到旧排列，最多返回 `limit` 条。
+#[tauri::command]
+async fn list_job_history(app: AppHandle, limit: Option<usize>) -> Result<Vec<JobHistoryEntry>, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut history = campaign_log(&app)?.history()?;
+        if let Some(limit) = limit {
+            history.truncate(limit);
+        }
+        Ok(history)
+    })
+    .await
+    .map_err(|err| format!("读取任务历史失败: {err}"))?
+}
+
+/// 任务详情：结果汇总、失败原因与当时使用的发送参数（不含密码），用于审计历史任务的配置。
+#[tauri::command]
+fn get_job_detail(app: AppHandle, job_id: String) -> Result<JobDetail, String> {
+    let job_id = job_id.trim();
+    campaign_log(&app)?
+        .detail(job_id)?
+        .ok_or_else(|| format!("没有找到任务 {job_id} 的任务日志"))
+}
+
+#[derive(Deserialize)]
+struct RequeueFailedPayload {
+    campaign_id: String,
+    /// 新任务的发送参数（账号、模板、节奏等），收件人由原任务的失败记录替换。
+    send: Value,
+}
+
+/// 把任务中最终失败的收件人作为一个新任务重新发送，免去前端逐条收集失败记录。
+#[tauri::command]
+fn requeue_failed_recipients(
+    app: AppHandle,
+    state: State<'_, WorkerState>,
+    payload: RequeueFailedPayload,
+) -> Result<Value, String> {
+    let campaign_id = payload.campaign_id.trim().to_string();
+    let events = campaign_log(&app)?
+        .read(&campaign_id)?
+        .ok_or_else(|| format!("没有找到任务 {campaign_id} 的事件日志"))?;
+    let failures = unresolved_failures(&events);
+    if failures.is_empty() {
+        return Err(format!("任务 {campaign_id} 没有需要重新发送的失败收件人"));
+    }
+    let Value::Object(mut send) = payload.send else {
+        return Err("发送参数必须是 JSON 对象".to_string());
+    };
+    let requeued = failures.len();
+    send.insert("recipients".to_string(), json!(failures));
+    send.remove("recipients_file");
+    send.remove("job_id");
+    let mut accepted = start_send(app, state, Value::Object(send))?;
+    accepted["requeued"] = json!(requeued);
+    accepted["requeued_from"] = json!(campaign_id);
+    Ok(accepted)
+}
+
+#[derive(Deserialize)]
+struct ResendFailedPayload {
+    campaign_id: String,
+    #[serde(default)]
+    template_overrides: TemplateOverrides,
+    /// 保存的任务参数不含密码，重发时需重新提供发件账号密码。
+    #[serde(default)]
+    smtp_password: Option<String>,
+    #[serde(default)]
+    confirm_production: bool,
+}
+
+/// 按原任务保存的参数重发其最终失败的收件人，可修改主题 / 正文或去掉导致拒收的附件；
+/// 新任务在统计中以 `resend_of` 关联原任务。
+#[tauri::command]
+fn resend_failed(app: AppHandle, state: State<'_, WorkerState>, payload: ResendFailedPayload) -> Result<Value, String> {
+    let campaign_id = payload.campaign_id.trim().to_string();
+    let log = campaign_log(&app)?;
+    let events = log
+        .read(&campaign_id)?
+        .ok_or_else(|| format!("没有找到任务 {campaign_id} 的事件日志"))?;
+    let definition = log
+        .read_definition(&campaign_id)?
+        .ok_or_else(|| format!("任务 {campaign_id} 没有保存发送参数，请使用重新发送失败收件人"))?;
+    let mut recipients = campaign_log::select_recipients(&events, Some(&definition), CloneSelection::Failed);
+    let sent_store = SentStore::open(Path::new(&resolve_app_paths(&app)?.sent_store_file), None)?;
+    recipients.retain(|recipient| !sent_store.is_sent(&recipient.email));
+    if recipients.is_empty() {
+        return Err(format!("任务 {campaign_id} 没有需要重新发送的失败收件人"));
+    }
+    let resent = recipients.len();
+    let mut send = campaign_log::resend_definition(&campaign_id, definition, recipients, &payload.template_overrides)?;
+    if let (Some(password), Some(smtp)) = (payload.smtp_password, send["smtp"].as_object_mut()) {
+        smtp.insert("password".to_string(), json!(password));
+    }
+    if payload.confirm_production {
+        send["confirm_production"] = json!(true);
+    }
+    let mut accepted = start_send(app, state, send)?;
+    accepted["resent"] = json!(resent);
+    accepted["resend_of"] = json!(campaign_id);
+    Ok(accepted)
+}
+
+#[derive(Deserialize)]
+struct CloneCampaignPayload {
+    id: String,
+    recipients: CloneSelection,
+}
+
+#[derive(Serialize)]
+struct CampaignClone {
+    source_id: String,
+    selection: CloneSelection,
+    /// 可直接编辑后发送的任务参数；旧任务没有保存参数时只含收件人。
+    send: Value,
+    recipients: usize,
+    /// 已在抑制列表中而被移除的收件人数。
+    suppressed: usize,
+    /// 失败后已由其他任务发送成功而被移除的收件人数。
+    already_sent: usize,
+}
+
+/// 复制历史任务：沿用原任务的模板与参数，收件人按范围（失败 / 全部）筛选，
+/// 并移除抑制列表中的地址；复制失败收件人时还会排除之后已发送成功的地址。
+#[tauri::command]
+fn clone_campaign(app: AppHandle, payload: CloneCampaignPayload) -> Result<CampaignClone, String> {
+    let source_id = payload.id.trim().to_string();
+    if source_id.is_empty() {
+        return Err("请选择要复制的任务".to_string());
+    }
+    let log = campaign_log(&app)?;
+    let paths = resolve_app_paths(&app)?;
+    let events = match log.read(&source_id)? {
+        Some(events) => events,
+        None => sent_records_as_events(&paths.sent_store_file, &source_id)?,
+    };
+    let definition = log.read_definition(&source_id)?;
+    if events.is_empty() && definition.is_none() {
+        return Err(format!("没有找到任务 {source_id} 的发送记录"));
+    }
+    let recipients = campaign_log::select_recipients(&events, definition.as_ref(), payload.recipients);
+
+    let (mut recipients, suppressed) = filter_suppressed(&app, recipients)?;
+    let mut already_sent = 0;
+    if payload.recipients == CloneSelection::Failed {
+        let sent_store = SentStore::open(Path::new(&paths.sent_store_file), None)?;
+        let before = recipients.len();
+        recipients.retain(|recipient| !sent_store.is_sent(&recipient.email));
+        already_sent = before - recipients.len();
+    }
+    if recipients.is_empty() {
+        return Err(format!("任务 {source_id} 中没有符合条件的收件人"));
+    }
+
+    let mut send = match definition {
+        Some(Value::Object(send)) => send,
+        _ => serde_json::Map::new(),
+    };
+    send.remove("recipients_file");
+    send.remove("resend_of");
+    send.insert("recipients".to_string(), json!(recipients));
+    Ok(CampaignClone {
+        source_id,
+        selection: payload.recipients,
+        send: Value::Object(send),
+        recipients: recipients.len(),
+        suppressed,
+        already_sent,
+    })
+}
+
+#[derive(Deserialize)]
+struct SuppressRecipientsPayload {
+    emails: Vec<String>,
+    /// 原因代码，按导入时的规则归一（bounce / complaint / unsubscribe / invalid / manual）。
+    reason: Option<String>,
+    /// 来源说明，通常是记录所属的任务 ID。
+    campaign_id: Option<String>,
+}
+
+/// 把选中的收件人一次性加入抑制列表（单次读写）。
+#[tauri::command]
+fn suppress_recipients(app: AppHandle, payload: SuppressRecipientsPayload) -> Result<SuppressionImportSummary, String> {
+    if payload.emails.is_empty() {
+        return Err("请至少选择一个收件人".to_string());
+    }
+    let reason = suppression::normalize_reason(payload.reason.as_deref().unwrap_or_default());
+    let source = payload.campaign_id.unwrap_or_default();
+    update_shared_file(&app, SUPPRESSION_RELATIVE_PATH, |store_path, guard| {
+        let mut list = SuppressionList::load(store_path)?;
+        let summary = list.suppress_emails(&payload.emails, &reason, source.trim());
+        if summary.imported > 0 {
+            guard.ensure_unchanged()?;
+            list.save(store_path)?;
+        }
+        Ok(summary)
+    })
+}
+
+#[derive(Deserialize)]
+struct AnnotateRecordsPayload {
+    campaign_id: String,
+    emails: Vec<String>,
+    /// 备注内容；为空时删除这些记录的备注。
+    note: String,
+}
+
+/// 为任务中的一批收件人记录写入同一条备注，任一地址无效时整批不写入。
+#[tauri::command]
+fn annotate_records(app: AppHandle, payload: AnnotateRecordsPayload) -> Result<usize, String> {
+    let operator = UserIdentity::current(&read_app_settings(&app)?.shared).label();
+    update_shared_file(&app, RECORD_NOTES_RELATIVE_PATH, |store_path, guard| {
+        let mut notes = RecordNotes::load(store_path)?;
+        let changed = notes.annotate(&payload.campaign_id, &payload.emails, &payload.note, &operator)?;
+        guard.ensure_unchanged()?;
+        notes.save(store_path)?;
+        Ok(changed)
+    })
+}
+
+#[tauri::command]
+fn list_record_notes(app: AppHandle, campaign_id: String) -> Result<Vec<RecordNote>, String> {
+    let notes = RecordNotes::load(&resolve_data_file(&app, RECORD_NOTES_RELATIVE_PATH)?)?;
+    Ok(notes.notes_for(&campaign_id))
+}
+
+/// 把发送记录中属于该任务的行转换为 `recipient_sent` 事件。
+fn sent_records_as_events(store_path: &str, job_id: &str) -> Result<Vec<Value>, String> {
+    let path = Path::new(store_path);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let file = File::open(path).map_err(|err| format!("读取发送记录失败: {err}"))?;
+    let mut events = Vec::new();
+    for line in BufReader::new(file).lines() {
+        let line = line.map_err(|err| format!("读取发送记录失败: {err}"))?;
+        let Ok(mut record) = serde_json::from_str::<Value>(&line) else {
+            continue;
+        };
+        if record["job_id"].as_str() == Some(job_id) {
+            record["type"] = json!("recipient_sent");
+            events.push(record);
+        }
+    }
+    Ok(events)
+}
+
+/// 本版本与当前环境可用的功能，前端与外部集成据此调整界面，而不是调用后才发现失败。
+#[derive(Serialize)]
+struct Capabilities {
+    version: &'static str,
+    native_engine: bool,
+    python_worker: WorkerCapability,
+    imap: bool,
+    ldap: bool,
+    /// 可用的数据库收件人来源；strict_local 下只允许 SQLite。
+    databases: Vec<&'static str>,
+    smime: bool,
+    local_mta: bool,
+    /// 原生引擎单个任务可同时使用的 SMTP 连接数上限。
+    max_connections: usize,
+    smtp_auth_mechanisms: Vec<&'static str>,
+    /// 以下功能本版本未包含，字段保留供集成方判断。
+    tracking_server: bool,
+    oauth_providers: Vec<&'static str>,
+    cli_agent: bool,
+    strict_local: bool,
+}
+
+#[derive(Serialize)]
+struct WorkerCapability {
+    /// 是否编译了 Python worker（`native-only` 构建不包含）。
+    compiled: bool,
+    ready: bool,
+    message: String,
+}
+
+#[tauri::command]
+fn get_capabilities(app: AppHandle) -> Result<Capabilities, String> {
+    let strict_local = read_app_settings(&app)?.network.strict_local;
+    Ok(Capabilities {
+        version: env!("CARGO_PKG_VERSION"),
+        native_engine: true,
+        python_worker: worker_capability(&app),
+        imap: true,
+        ldap: true,
+        databases: if strict_local { vec!["sqlite"] } else { vec!["sqlite", "postgres"] },
+        smime: true,
+        local_mta: true,
+        max_connections: MAX_CONNECTIONS,
+        smtp_auth_mechanisms: [AuthMechanism::Plain, AuthMechanism::Login, AuthMechanism::CramMd5, AuthMechanism::Ntlm]
+            .map(AuthMechanism::name)
+            .to_vec(),
+        tracking_server: false,
+        oauth_providers: Vec::new(),
+        cli_agent: false,
+        strict_local,
+    })
+}
+
+#[cfg(not(feature = "native-only"))]
+fn worker_capability(app: &AppHandle) -> WorkerCapability {
+    let (ready, message) = python_runtime::runtime_readiness(app);
+    WorkerCapability {
+        compiled: true,
+        ready,
+        message,
+    }
+}
+
+#[cfg(feature = "native-only")]
+fn worker_capability(_app: &AppHandle) -> WorkerCapability {
+    WorkerCapability {
+        compiled: false,
+        ready: false,
+        message: "此版本仅包含原生发送引擎".to_string(),
+    }
+}
+
+#[tauri::command]
+fn get_app_paths(app: AppHandle) -> Result<AppPaths, String> {
+    resolve_app_paths(&app)
+}
+
+#[tauri::command]
+fn set_data_dir(app: AppHandle, path: String) -> Result<AppPaths, String> {
+    let mut settings = read_app_settings(&app)?;
+    let trimmed = path.trim();
+    if trimmed.is_empty() {
+        settings.data_dir = None;
+    } else {
+        settings.data_dir = Some(trimmed.to_string());
+    }
+    write_app_settings(&app, &settings)?;
+    resolve_app_paths(&app)
+}
+
+#[tauri::command]
+fn load_app_draft(app: AppHandle) -> Result<Value, String> {
+    let paths = resolve_app_paths(&app)?;
+    let draft_path = PathBuf::from(paths.app_draft_file);
+    if !draft_path.exists() {
+        return Ok(json!({}));
+    }
+    let text = fs::read_to_string(&draft_path)
+        .map_err(|err| format!("读取草稿配置失败: {err}"))?;
+    serde_json::from_str(&text).map_err(|err| format!("草稿配置格式错误: {err}"))
+}
+
+#[tauri::command]
+fn save_app_draft(app: AppHandle, payload: Value) -> Result<(), String> {
+    if !payload.is_object() {
+        return Err("草稿配置必须是 JSON 对象".to_string());
+    }
+    let paths = resolve_app_paths(&app)?;
+    let draft_path = PathBuf::from(paths.app_draft_file);
+    if let Some(parent) = draft_path.parent() {
+        fs::create_dir_all(parent).map_err(|err| format!("创建草稿配置目录失败: {err}"))?;
+    }
+    let text = serde_json::to_string_pretty(&payload).map_err(|err| err.to_string())?;
+    fs::write(draft_path, text).map_err(|err| format!("写入草稿配置失败: {err}"))
+}
+
+/// 读取上次的界面会话；已删除的发件账号与已不存在的收件人文件不再恢复。
+#[tauri::command]
+fn load_session_state(app: AppHandle) -> Result<SessionState, String> {
+    let mut state = SessionState::load(&session_state_path(&app)?)?;
+    if let Some(id) = &state.selected_profile {
+        let registry = AccountRegistry::load(&resolve_data_file(&app, SMTP_ACCOUNTS_RELATIVE_PATH)?)?;
+        if registry.find(id).is_none() {
+            state.selected_profile = None;
+        }
+    }
+    if state
+        .recipient_source
+        .as_ref()
+        .is_some_and(|source| !Path::new(&source.path).is_file())
+    {
+        state.recipient_source = None;
+    }
+    Ok(state)
+}
+
+#[tauri::command]
+fn save_session_state(app: AppHandle, payload: SessionState) -> Result<SessionState, String> {
+    let mut state = payload;
+    state.save(&session_state_path(&app)?)?;
+    Ok(state)
+}
+
+#[tauri::command]
+fn get_throttle_status(app: AppHandle, state: State<'_, WorkerState>) -> Result<ThrottleStatus, String> {
+    let throttle = shared_throttle(&app, &state)?;
+    let mut throttle = throttle
+        .lock()
+        .map_err(|_| "failed to acquire throttle lock".to_string())?;
+    Ok(throttle.status(unix_now()))
+}
+
+#[tauri::command]
+fn set_throttle_limits(
+    app: AppHandle,
+    state: State<'_, WorkerState>,
+    payload: ThrottleLimits,
+) -> Result<ThrottleStatus, String> {
+    payload.validate()?;
+    let mut settings = read_app_settings(&app)?;
+    settings.throttle = payload;
+    write_app_settings(&app, &settings)?;
+    get_throttle_status(app, state)
+}
+
+#[tauri::command]
+fn get_network_policy(app: AppHandle) -> Result<NetworkPolicy, String> {
+    Ok(read_app_settings(&app)?.network)
+}
+
+#[tauri::command]
+fn set_network_policy(app: AppHandle, payload: NetworkPolicy) -> Result<NetworkPolicy, String> {
+    let mut settings = read_app_settings(&app)?;
+    let proxy = payload
+        .proxy
+        .map(|proxy| proxy.trim().to_string())
+        .filter(|proxy| !proxy.is_empty());
+    if let Some(proxy) = &proxy {
+        reqwest::Proxy::all(proxy).map_err(|err| format!("代理地址无效: {err}"))?;
+    }
+    settings.network = NetworkPolicy {
+        strict_local: payload.strict_local,
+        allowed_smtp_host: payload
+            .allowed_smtp_host
+            .map(|host| host.trim().to_string())
+            .filter(|host| !host.is_empty()),
+        proxy,
+    };
+    write_app_settings(&app, &settings)?;
+    Ok(settings.network)
+}
+
+#[tauri::command]
+fn get_upload_settings(app: AppHandle) -> Result<UploadSettings, String> {
+    Ok(read_app_settings(&app)?.upload)
+}
+
+#[tauri::command]
+fn set_upload_settings(app: AppHandle, payload: UploadSettings) -> Result<UploadSettings, String> {
+    payload.validate()?;
+    let mut settings = read_app_settings(&app)?;
+    settings.upload = payload;
+    write_app_settings(&app, &settings)?;
+    Ok(settings.upload)
+}
+
+#[tauri::command]
+fn get_job_queue_settings(app: AppHandle) -> Result<JobQueueSettings, String> {
+    Ok(read_app_settings(&app)?.jobs)
+}
+
+/// 修改同时运行的任务数；已在运行的任务不受影响，之后有任务结束或提交新任务时按新上限启动排队的任务。
+#[tauri::command]
+fn set_job_queue_settings(app: AppHandle, payload: JobQueueSettings) -> Result<JobQueueSettings, String> {
+    payload.validate()?;
+    let mut settings = read_app_settings(&app)?;
+    settings.jobs = payload;
+    write_app_settings(&app, &settings)?;
+    Ok(settings.jobs)
+}
+
+#[tauri::command]
+fn get_enrichment_settings(app: AppHandle) -> Result<EnrichmentSettings, String> {
+    Ok(read_app_settings(&app)?.enrichment)
+}
+
+#[tauri::command]
+fn set_enrichment_settings(app: AppHandle, payload: EnrichmentSettings) -> Result<EnrichmentSettings, String> {
+    payload.validate()?;
+    let mut settings = read_app_settings(&app)?;
+    settings.enrichment = payload;
+    write_app_settings(&app, &settings)?;
+    Ok(settings.enrichment)
+}
+
+/// 调用配置的接口或命令为收件人补充字段（如域名 → 单位名称），结果写入缓存，
+/// 返回带 `fields` 的收件人列表，供模板以 `{company}` 等变量使用。
+#[tauri::command]
+async fn enrich_recipients(app: AppHandle, recipients: Vec<RecipientEntry>) -> Result<EnrichmentReport, String> {
+    let settings = read_app_settings(&app)?;
+    let cache_path = resolve_data_file(&app, ENRICHMENT_CACHE_RELATIVE_PATH)?;
+    let mut cache = EnrichmentCache::load(&cache_path)?;
+    let (report, cache) = tauri::async_runtime::spawn_blocking(move || {
+        let report = enrichment::enrich_recipients(recipients, &settings.enrichment, &mut cache, &SystemClock, |values| {
+            enrichment::lookup_batch(&settings.network, &settings.enrichment, values)
+        });
+        (report, cache)
+    })
+    .await
+    .map_err(|err| format!("联系人补充任务失败: {err}"))?;
+    // 部分批次失败时也保留已查到的结果。
+    update_shared_file(&app, ENRICHMENT_CACHE_RELATIVE_PATH, |path, guard| {
+        let mut stored = EnrichmentCache::load(path)?;
+        stored.merge(cache);
+        guard.ensure_unchanged()?;
+        stored.save(path)
+    })?;
+    report
+}
+
+#[tauri::command]
+fn import_suppression_list(app: AppHandle, path: String) -> Result<SuppressionImportSummary, String> {
+    update_shared_file(&app, SUPPRESSION_RELATIVE_PATH, |store_path, guard| {
+        let mut list = SuppressionList::load(store_path)?;
+        let summary = list.import_file(Path::new(path.trim()))?;
+        guard.ensure_unchanged()?;
+        list.save(store_path)?;
+        Ok(summary)
+    })
+}
+
+/// 导入 ESP 导出的 CSV（按表头识别邮箱 / 原因 / 时间列），邮箱列中的域名按整域抑制。
+#[tauri::command]
+fn import_suppression_csv(app: AppHandle, path: String) -> Result<SuppressionImportSummary, String> {
+    let is_csv = Path::new(path.trim())
+        .extension()
+        .and_then(|value| value.to_str())
+        .is_some_and(|value| value.eq_ignore_ascii_case("csv"));
+    if !is_csv {
+        return Err("请选择 .csv 文件".to_string());
+    }
+    import_suppression_list(app, path)
+}
+
+#[derive(Deserialize)]
+struct AddSuppressionPayload {
+    /// 邮箱地址或域名（`example.com` / `@example.com`）。
+    entries: Vec<String>,
+    reason: Option<String>,
+}
+
+/// 手动把地址或整个域名加入抑制列表，之后的发送任务会自动排除。
+#[tauri::command]
+fn add_to_suppression(app: AppHandle, payload: AddSuppressionPayload) -> Result<SuppressionImportSummary, String> {
+    if payload.entries.iter().all(|entry| entry.trim().is_empty()) {
+        return Err("请至少输入一个邮箱或域名".to_string());
+    }
+    let reason = suppression::normalize_reason(payload.reason.as_deref().unwrap_or_default());
+    update_shared_file(&app, SUPPRESSION_RELATIVE_PATH, |store_path, guard| {
+        let mut list = SuppressionList::load(store_path)?;
+        let summary = list.add_entries(&payload.entries, &reason, "manual");
+        if summary.imported > 0 {
+            guard.ensure_unchanged()?;
+            list.save(store_path)?;
+        }
+        Ok(summary)
+    })
+}
+
+#[derive(Deserialize)]
+struct ListSuppressionPayload {
+    query: Option<String>,
+    limit: Option<usize>,
+}
+
+#[tauri::command]
+fn list_suppression(app: AppHandle, payload: ListSuppressionPayload) -> Result<SuppressionListing, String> {
+    let list = SuppressionList::load(&resolve_data_file(&app, SUPPRESSION_RELATIVE_PATH)?)?;
+    Ok(list.listing(payload.query.as_deref().unwrap_or_default(), payload.limit.unwrap_or(200)))
+}
+
+#[tauri::command]
+fn export_suppression_list(app: AppHandle, path: String) -> Result<usize, String> {
+    let target = PathBuf::from(path.trim());
+    if target.as_os_str().is_empty() {
+        return Err("导出路径不能为空".to_string());
+    }
+    let list = SuppressionList::load(&resolve_data_file(&app, SUPPRESSION_RELATIVE_PATH)?)?;
+    list.export_file(&target)
+}
+
+#[tauri::command]
+fn import_consent_records(app: AppHandle, path: String) -> Result<ConsentImportSummary, String> {
+    let rows = read_consent_file(Path::new(path.trim()))?;
+    update_shared_file(&app, CONSENT_RELATIVE_PATH, |store_path, guard| {
+        let mut store = ConsentStore::load(store_path)?;
+        let summary = store.import(rows);
+        guard.ensure_unchanged()?;
+        store.save(store_path)?;
+        Ok(summary)
+    })
+}
+
+#[tauri::command]
+fn check_consent(app: AppHandle, recipients: Vec<RecipientEntry>) -> Result<ConsentReport, String> {
+    let store = ConsentStore::load(&resolve_data_file(&app, CONSENT_RELATIVE_PATH)?)?;
+    Ok(store.check(recipients))
+}
+
+#[derive(Deserialize)]
+struct RcptProbePayload {
+    recipients: Vec<RecipientEntry>,
+    options: RcptProbeOptions,
+    /// 调用方确认已向用户展示探测的局限与风险。
+    #[serde(default)]
+    acknowledge_risks: bool,
+}
+
+/// 可选的深度验证：连接收件域名的 MX，只发 MAIL FROM / RCPT TO 检查邮箱是否存在。
+/// 逐条结果通过 `rcpt-probe` 事件推送，完成后返回完整报告。
+#[tauri::command]
+async fn probe_recipients(app: AppHandle, payload: RcptProbePayload) -> Result<RcptProbeReport, String> {
+    if !payload.acknowledge_risks {
+        return Err(format!("请确认以下风险后重新提交（acknowledge_risks）：{}", PROBE_WARNINGS.join(" ")));
+    }
+    let network = read_app_settings(&app)?.network;
+    if network.strict_local {
+        return Err("已开启仅本地模式，不能连接收件域名的邮件服务器".to_string());
+    }
+    payload.options.validate()?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let client = http_client(&network, &payload.options.doh_url(), "MX 查询")?;
+        rcpt_probe::probe_recipients(&client, &payload.options, payload.recipients, |result| {
+            let _ = app.emit(RCPT_PROBE_CHANNEL, result);
+        })
+    })
+    .await
+    .map_err(|err| format!("RCPT 探测任务失败: {err}"))?
+}
+
+/// 发送前按 RFC 5321 / 5322 检查收件人邮箱语法，逐行给出有效 / 可疑 / 无效结论；角色邮箱标记为可疑。
+#[tauri::command]
+fn validate_recipients(app: AppHandle, recipients: Vec<RecipientEntry>) -> Result<RecipientValidationReport, String> {
+    let settings = read_app_settings(&app)?.validation;
+    Ok(email_syntax::validate_recipients(recipients, &settings))
+}
+
+#[tauri::command]
+fn get_validation_settings(app: AppHandle) -> Result<ValidationSettings, String> {
+    Ok(read_app_settings(&app)?.validation)
+}
+
+#[tauri::command]
+fn set_validation_settings(app: AppHandle, payload: ValidationSettings) -> Result<ValidationSettings, String> {
+    let mut settings = read_app_settings(&app)?;
+    settings.validation = ValidationSettings {
+        role_prefixes: payload
+            .role_prefixes
+            .iter()
+            .map(|prefix| prefix.trim().to_lowercase())
+            .filter(|prefix| !prefix.is_empty())
+            .collect(),
+    };
+    write_app_settings(&app, &settings)?;
+    Ok(settings.validation)
+}
+
+#[tauri::command]
+fn preflight_dead_domains(app: AppHandle, recipients: Vec<RecipientEntry>) -> Result<DeadDomainReport, String> {
+    let store = DeadDomainStore::load(&resolve_data_file(&app, DEAD_DOMAINS_RELATIVE_PATH)?)?;
+    Ok(store.preflight(recipients))
 }
 
-#[derive(Deserialize, Serialize)]
-struct SmtpPayload {
-    host: String,
-    port: u16,
-    username: String,
-    password: String,
-    use_ssl: bool,
-    use_starttls: bool,
-    timeout_sec: u32,
+#[tauri::command]
+fn list_dead_domains(app: AppHandle) -> Result<Vec<DeadDomainSummary>, String> {
+    let store = DeadDomainStore::load(&resolve_data_file(&app, DEAD_DOMAINS_RELATIVE_PATH)?)?;
+    Ok(store.dead_domains())
 }
 
 #[tauri::command]
-fn load_recipients(app: AppHandle, path: String) -> Result<Value, String> {
-    run_worker_request(json!({
-        "type": "load_recipients",
-        "protocol": 1,
-        "payload": { "path": path }
-    }), &app)
+fn forget_dead_domain(app: AppHandle, domain: String) -> Result<(), String> {
+    update_shared_file(&app, DEAD_DOMAINS_RELATIVE_PATH, |path, guard| {
+        let mut store = DeadDomainStore::load(path)?;
+        if !store.forget(&domain) {
+            return Err(format!("未找到该域名的退信记录: {domain}"));
+        }
+        guard.ensure_unchanged()?;
+        store.save(path)
+    })
+}
+
+#[derive(Serialize)]
+struct DisposableDomainsStatus {
+    /// `bundled`：内置列表；`downloaded`：已从更新地址下载。
+    source: &'static str,
+    domains: usize,
+    update_url: String,
+    updated_at: Option<String>,
 }
 
+/// 标记使用一次性邮箱（mailinator 等）的收件人，`kept` 为排除它们之后的列表。
 #[tauri::command]
-async fn test_smtp(payload: SmtpPayload) -> Result<Value, String> {
-    tauri::async_runtime::spawn_blocking(move || {
-        let creds = Credentials::new(payload.username.clone(), payload.password.clone());
-
-        let tls = if payload.use_ssl || payload.use_starttls {
-            let tls_params = TlsParameters::builder(payload.host.clone())
-                .build()
-                .map_err(|e| format!("TLS 配置失败: {e}"))?;
-            if payload.use_ssl {
-                Tls::Wrapper(tls_params)
-            } else {
-                Tls::Required(tls_params)
-            }
-        } else {
-            Tls::None
-        };
+fn check_disposable_recipients(app: AppHandle, recipients: Vec<RecipientEntry>) -> Result<DisposableReport, String> {
+    let list = DisposableDomains::load(&resolve_data_file(&app, DISPOSABLE_DOMAINS_RELATIVE_PATH)?)?;
+    Ok(list.check(recipients))
+}
 
-        let transport = SmtpTransport::builder_dangerous(&payload.host)
-            .port(payload.port)
-            .tls(tls)
-            .credentials(creds)
-            .timeout(Some(Duration::from_secs(payload.timeout_sec.into())))
-            .build();
+#[tauri::command]
+fn get_disposable_domains(app: AppHandle) -> Result<DisposableDomainsStatus, String> {
+    disposable_domains_status(&app)
+}
 
-        // Retry once after 2 s: some SMTP servers (e.g. 126.com) apply a
-        // cold-start delay on the first connection and temporarily reject it.
-        let mut last_err: Option<String> = None;
-        for attempt in 0..2u32 {
-            match transport.test_connection() {
-                Ok(_) => return Ok(json!({ "type": "smtp_test_succeeded" })),
-                Err(e) => {
-                    last_err = Some(format!("SMTP 连接失败: {e}"));
-                    if attempt == 0 {
-                        std::thread::sleep(Duration::from_secs(2));
-                    }
-                }
-            }
+/// 从更新地址下载一次性邮箱域名列表并覆盖本地副本；传入 `url` 时同时保存为新的更新地址。
+#[tauri::command]
+async fn update_disposable_domains(app: AppHandle, url: Option<String>) -> Result<DisposableDomainsStatus, String> {
+    let mut settings = read_app_settings(&app)?;
+    if let Some(url) = url.map(|url| url.trim().to_string()) {
+        if !url.is_empty() {
+            validate_remote_url_scheme(&url, "一次性邮箱域名列表地址")?;
         }
-        Err(last_err.unwrap())
+        settings.disposable_domains_url = Some(url).filter(|url| !url.is_empty());
+        write_app_settings(&app, &settings)?;
+    }
+    let url = disposable_domains_url(&settings);
+    let policy = settings.network;
+    let list = tauri::async_runtime::spawn_blocking(move || {
+        let text = http_client(&policy, &url, "一次性邮箱域名列表更新")?
+            .get(&url)
+            .send()
+            .map_err(|err| format!("下载一次性邮箱域名列表失败: {err}"))?
+            .error_for_status()
+            .map_err(|err| format!("一次性邮箱域名列表响应异常: {err}"))?
+            .text()
+            .map_err(|err| format!("读取一次性邮箱域名列表失败: {err}"))?;
+        DisposableDomains::parse_download(&text)
     })
     .await
-    .map_err(|e| format!("SMTP test task failed: {e}"))?
+    .map_err(|err| format!("更新一次性邮箱域名列表失败: {err}"))??;
+    update_shared_file(&app, DISPOSABLE_DOMAINS_RELATIVE_PATH, |path, _| {
+        fs::write(path, list.to_text()).map_err(|err| format!("写入一次性邮箱域名列表失败: {err}"))
+    })?;
+    disposable_domains_status(&app)
+}
+
+fn disposable_domains_url(settings: &AppSettings) -> String {
+    settings
+        .disposable_domains_url
+        .clone()
+        .unwrap_or_else(|| DEFAULT_DISPOSABLE_DOMAINS_URL.to_string())
+}
+
+fn disposable_domains_status(app: &AppHandle) -> Result<DisposableDomainsStatus, String> {
+    let path = resolve_data_file(app, DISPOSABLE_DOMAINS_RELATIVE_PATH)?;
+    let updated_at = fs::metadata(&path).and_then(|meta| meta.modified()).ok().map(|modified| {
+        chrono::DateTime::<chrono::Local>::from(modified)
+            .format("%Y-%m-%d %H:%M:%S")
+            .to_string()
+    });
+    Ok(DisposableDomainsStatus {
+        source: if updated_at.is_some() { "downloaded" } else { "bundled" },
+        domains: DisposableDomains::load(&path)?.len(),
+        update_url: disposable_domains_url(&read_app_settings(app)?),
+        updated_at,
+    })
 }
 
 #[tauri::command]
-fn start_send(
-    app: AppHandle,
-    state: State<'_, WorkerState>,
-    payload: Value,
-) -> Result<Value, String> {
-    let mut guard = state
-        .child
-        .lock()
-        .map_err(|_| "failed to acquire worker state lock".to_string())?;
+fn add_smtp_account(app: AppHandle, payload: SmtpAccount) -> Result<SmtpAccountSummary, String> {
+    let account = update_shared_file(&app, SMTP_ACCOUNTS_RELATIVE_PATH, |registry_path, guard| {
+        let mut registry = AccountRegistry::load(registry_path)?;
+        let account = registry.upsert(payload)?;
+        guard.ensure_unchanged()?;
+        registry.save(registry_path)?;
+        Ok(account)
+    })?;
+    let ledger = UsageLedger::load(&resolve_data_file(&app, SMTP_ACCOUNT_USAGE_RELATIVE_PATH)?);
+    Ok(summarize(&account, &ledger))
+}
 
-    if let Some(child) = guard.as_mut() {
-        if child
-            .try_wait()
-            .map_err(|err| err.to_string())?
-            .is_none()
-        {
-            return Err("another job is running".to_string());
-        }
-        *guard = None;
-    }
-
-    let mut command = worker_command(&app)?;
-    let mut child = command
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .stderr(Stdio::inherit())
-        .spawn()
-        .map_err(|err| format!("failed to spawn worker: {err}"))?;
-
-    let mut stdin = child
-        .stdin
-        .take()
-        .ok_or_else(|| "failed to open worker stdin".to_string())?;
-    let request = json!({
-        "type": "start_send",
-        "protocol": 1,
-        "payload": payload
-    });
-    writeln!(stdin, "{}", request)
-        .and_then(|_| stdin.flush())
-        .map_err(|err| format!("failed to write worker request: {err}"))?;
-    // Drop stdin to send EOF — the Python worker loop exits after the job thread finishes.
+#[tauri::command]
+fn list_smtp_accounts(app: AppHandle) -> Result<Vec<SmtpAccountSummary>, String> {
+    let registry = AccountRegistry::load(&resolve_data_file(&app, SMTP_ACCOUNTS_RELATIVE_PATH)?)?;
+    let ledger = UsageLedger::load(&resolve_data_file(&app, SMTP_ACCOUNT_USAGE_RELATIVE_PATH)?);
+    Ok(registry
+        .accounts
+        .iter()
+        .map(|account| summarize(account, &ledger))
+        .collect())
+}
 
-    let stdout = child
-        .stdout
-        .take()
-        .ok_or_else(|| "failed to open worker stdout".to_string())?;
+#[tauri::command]
+fn remove_smtp_account(app: AppHandle, id: String) -> Result<(), String> {
+    update_shared_file(&app, SMTP_ACCOUNTS_RELATIVE_PATH, |registry_path, guard| {
+        let mut registry = AccountRegistry::load(registry_path)?;
+        if !registry.remove(id.trim()) {
+            return Err(format!("发件账号不存在: {id}"));
+        }
+        guard.ensure_unchanged()?;
+        registry.save(registry_path)
+    })
+}
 
-    spawn_event_forwarder(app, stdout);
+/// 保存命名的 SMTP 服务器配置，密码写入系统钥匙串；未填写密码时保留该配置原有的密码。
+#[tauri::command]
+fn save_smtp_profile(app: AppHandle, name: String, smtp: SmtpPayload) -> Result<SmtpProfile, String> {
+    let name = update_shared_file(&app, SMTP_PROFILES_RELATIVE_PATH, |store_path, guard| {
+        let mut store = SmtpProfileStore::load(store_path)?;
+        let name = store.upsert(&name, &smtp)?;
+        guard.ensure_unchanged()?;
+        // 先写钥匙串：钥匙串不可用时配置文件保持不变。
+        if !smtp.password.is_empty() {
+            smtp_profiles::store_password(&name, &smtp.password)?;
+        }
+        store.save(store_path)?;
+        Ok(name)
+    })?;
+    let password = if smtp.password.is_empty() {
+        smtp_profiles::load_password(&name)?
+    } else {
+        smtp.password.clone()
+    };
+    Ok(SmtpProfile {
+        name,
+        smtp: SmtpPayload { password, ..smtp },
+    })
+}
 
-    let response = json!({ "type": "job_accepted" });
-    *guard = Some(child);
-    Ok(response)
+/// 列出命名的 SMTP 配置，密码从系统钥匙串读取后一并返回，界面选中配置即可直接测试或发送。
+/// 读取失败（如 Linux 上没有运行 Secret Service）时密码留空，由用户重新输入。
+#[tauri::command]
+fn list_smtp_profiles(app: AppHandle) -> Result<Vec<SmtpProfile>, String> {
+    let store = SmtpProfileStore::load(&resolve_data_file(&app, SMTP_PROFILES_RELATIVE_PATH)?)?;
+    Ok(store
+        .profiles
+        .into_iter()
+        .map(|mut profile| {
+            profile.smtp.password = smtp_profiles::load_password(&profile.name).unwrap_or_default();
+            profile
+        })
+        .collect())
 }
 
 #[tauri::command]
-fn cancel_send(state: State<'_, WorkerState>) -> Result<(), String> {
-    let mut guard = state
-        .child
-        .lock()
-        .map_err(|_| "failed to acquire worker state lock".to_string())?;
+fn delete_smtp_profile(app: AppHandle, name: String) -> Result<(), String> {
+    update_shared_file(&app, SMTP_PROFILES_RELATIVE_PATH, |store_path, guard| {
+        let mut store = SmtpProfileStore::load(store_path)?;
+        if !store.remove(&name) {
+            return Err(format!("SMTP 配置不存在: {}", name.trim()));
+        }
+        guard.ensure_unchanged()?;
+        store.save(store_path)?;
+        smtp_profiles::delete_password(name.trim())
+    })
+}
 
-    if let Some(child) = guard.as_mut() {
-        child
-            .kill()
-            .map_err(|err| format!("failed to kill worker process: {err}"))?;
-    }
+/// 校验 PKCS#12 证书与密码，不保存。
+#[tauri::command]
+fn validate_smime_certificate(path: String, password: String) -> Result<SmimeCertificateInfo, String> {
+    let bytes = fs::read(path.trim()).map_err(|err| format!("读取证书文件失败: {err}"))?;
+    let identity = load_pkcs12(&bytes, &password)?;
+    Ok(certificate_info(&identity.certificate))
+}
 
-    *guard = None;
-    Ok(())
+/// 导入签名证书：复制到应用数据目录，密码保存在本机设置中。
+#[tauri::command]
+fn import_smime_certificate(app: AppHandle, path: String, password: String) -> Result<SmimeCertificateInfo, String> {
+    let bytes = fs::read(path.trim()).map_err(|err| format!("读取证书文件失败: {err}"))?;
+    let identity = load_pkcs12(&bytes, &password)?;
+    let info = certificate_info(&identity.certificate);
+    let target = smime_identity_path(&app)?;
+    fs::write(&target, &bytes).map_err(|err| format!("保存证书失败: {err}"))?;
+    #[cfg(unix)]
+    fs::set_permissions(&target, fs::Permissions::from_mode(0o600))
+        .map_err(|err| format!("设置证书文件权限失败: {err}"))?;
+    let mut settings = read_app_settings(&app)?;
+    settings.smime = SmimeSettings {
+        pkcs12_path: Some(target.to_string_lossy().to_string()),
+        password,
+    };
+    write_app_settings(&app, &settings)?;
+    Ok(info)
 }
 
 #[tauri::command]
-fn clear_sent_records(app: AppHandle) -> Result<(), String> {
-    let paths = resolve_app_paths(&app)?;
-    for target in [paths.sent_store_file, paths.sent_store_text_file] {
-        let file = PathBuf::from(target);
-        if file.exists() {
-            fs::remove_file(&file)
-                .map_err(|err| format!("failed to remove sent records: {err}"))?;
+fn get_smime_certificate(app: AppHandle) -> Result<Option<SmimeCertificateInfo>, String> {
+    Ok(load_smime_identity(&app)?.map(|identity| certificate_info(&identity.certificate)))
+}
+
+#[tauri::command]
+fn remove_smime_certificate(app: AppHandle) -> Result<(), String> {
+    let mut settings = read_app_settings(&app)?;
+    if let Some(path) = settings.smime.pkcs12_path.take() {
+        let path = PathBuf::from(path);
+        if path.exists() {
+            fs::remove_file(&path).map_err(|err| format!("删除证书失败: {err}"))?;
         }
     }
-    Ok(())
+    settings.smime = SmimeSettings::default();
+    write_app_settings(&app, &settings)
 }
 
+/// 导入收件人公钥证书（PEM / DER），用于加密发给该邮箱的邮件。
 #[tauri::command]
-fn get_app_paths(app: AppHandle) -> Result<AppPaths, String> {
-    resolve_app_paths(&app)
+fn import_recipient_certificate(app: AppHandle, path: String) -> Result<SmimeCertificateInfo, String> {
+    let bytes = fs::read(path.trim()).map_err(|err| format!("读取证书文件失败: {err}"))?;
+    let certificate = parse_certificate(&bytes)?;
+    update_shared_file(&app, SMIME_RECIPIENTS_RELATIVE_PATH, |store_path, guard| {
+        let mut store = RecipientCertStore::load(store_path)?;
+        let info = store.import(&certificate)?;
+        guard.ensure_unchanged()?;
+        store.save(store_path)?;
+        Ok(info)
+    })
 }
 
 #[tauri::command]
-fn set_data_dir(app: AppHandle, path: String) -> Result<AppPaths, String> {
+fn list_recipient_certificates(app: AppHandle) -> Result<Vec<SmimeCertificateInfo>, String> {
+    let store = RecipientCertStore::load(&resolve_data_file(&app, SMIME_RECIPIENTS_RELATIVE_PATH)?)?;
+    Ok(store.list())
+}
+
+#[tauri::command]
+fn get_shared_dir_settings(app: AppHandle) -> Result<SharedDirSettings, String> {
+    Ok(read_app_settings(&app)?.shared)
+}
+
+#[tauri::command]
+fn set_shared_dir_settings(app: AppHandle, payload: SharedDirSettings) -> Result<AppPaths, String> {
     let mut settings = read_app_settings(&app)?;
-    let trimmed = path.trim();
-    if trimmed.is_empty() {
-        settings.data_dir = None;
-    } else {
-        settings.data_dir = Some(trimmed.to_string());
-    }
+    settings.shared = SharedDirSettings {
+        enabled: payload.enabled,
+        user_name: payload
+            .user_name
+            .map(|name| name.trim().to_string())
+            .filter(|name| !name.is_empty()),
+    };
     write_app_settings(&app, &settings)?;
     resolve_app_paths(&app)
 }
 
+/// 当前操作人（用户名@主机名），会写入发送记录。
 #[tauri::command]
-fn load_app_draft(app: AppHandle) -> Result<Value, String> {
-    let paths = resolve_app_paths(&app)?;
-    let draft_path = PathBuf::from(paths.app_draft_file);
-    if !draft_path.exists() {
-        return Ok(json!({}));
-    }
-    let text = fs::read_to_string(&draft_path)
-        .map_err(|err| format!("读取草稿配置失败: {err}"))?;
-    serde_json::from_str(&text).map_err(|err| format!("草稿配置格式错误: {err}"))
+fn get_current_user(app: AppHandle) -> Result<UserIdentity, String> {
+    Ok(UserIdentity::current(&read_app_settings(&app)?.shared))
 }
 
 #[tauri::command]
-fn save_app_draft(app: AppHandle, payload: Value) -> Result<(), String> {
-    if !payload.is_object() {
-        return Err("草稿配置必须是 JSON 对象".to_string());
-    }
-    let paths = resolve_app_paths(&app)?;
-    let draft_path = PathBuf::from(paths.app_draft_file);
-    if let Some(parent) = draft_path.parent() {
-        fs::create_dir_all(parent).map_err(|err| format!("创建草稿配置目录失败: {err}"))?;
-    }
-    let text = serde_json::to_string_pretty(&payload).map_err(|err| err.to_string())?;
-    fs::write(draft_path, text).map_err(|err| format!("写入草稿配置失败: {err}"))
+fn list_data_dir_locks(app: AppHandle) -> Result<Vec<LockOwner>, String> {
+    Ok(list_locks(&resolve_data_dir(&app)?))
+}
+
+#[tauri::command]
+fn break_data_dir_lock(app: AppHandle, name: String) -> Result<(), String> {
+    break_lock(&resolve_data_dir(&app)?, name.trim())
 }
 
 #[tauri::command]
@@ -275,23 +2508,39 @@ fn open_path(path: String) -> Result<(), String> {
     Ok(())
 }
 
-#[derive(Serialize, Default)]
-struct RuntimeStatus {
-    ready: bool,
-    source: String,
-    executable_path: Option<String>,
-    version: Option<String>,
-    message: String,
-}
-
-#[derive(Serialize, Deserialize, Default)]
-struct RuntimeConfig {
-    python_path: Option<String>,
-}
-
 #[derive(Serialize, Deserialize, Default)]
 struct AppSettings {
     data_dir: Option<String>,
+    /// 一次性邮箱域名列表的更新地址；为空时使用 `DEFAULT_DISPOSABLE_DOMAINS_URL`。
+    #[serde(default)]
+    disposable_domains_url: Option<String>,
+    #[serde(default)]
+    throttle: ThrottleLimits,
+    #[serde(default)]
+    network: NetworkPolicy,
+    #[cfg(not(feature = "native-only"))]
+    #[serde(default)]
+    worker: worker_env::WorkerSettings,
+    #[serde(default)]
+    shared: SharedDirSettings,
+    #[serde(default)]
+    smime: SmimeSettings,
+    #[serde(default)]
+    upload: UploadSettings,
+    #[cfg(not(feature = "native-only"))]
+    #[serde(default)]
+    maintenance: maintenance::MaintenanceSettings,
+    #[serde(default)]
+    alerts: AlertSettings,
+    #[serde(default)]
+    enrichment: EnrichmentSettings,
+    #[serde(default)]
+    validation: ValidationSettings,
+    /// 发送记录保留策略，启动时与 `purge_sent_records` 执行。
+    #[serde(default)]
+    retention: RetentionPolicy,
+    #[serde(default)]
+    jobs: JobQueueSettings,
 }
 
 #[derive(Serialize)]
@@ -303,743 +2552,715 @@ struct AppPaths {
     app_draft_file: String,
 }
 
-#[derive(Deserialize, Default)]
-struct RuntimeManifest {
-    bundles: Vec<RuntimeManifestBundle>,
-}
-
-#[derive(Deserialize, Clone)]
-struct RuntimeManifestBundle {
-    target: String,
-    url: String,
-    sha256: Option<String>,
-    urls: Option<Vec<String>>,
-}
-
-#[derive(Deserialize)]
-struct AutoInstallPayload {
-    manifest_url: Option<String>,
-    manifest_urls: Option<Vec<String>>,
-}
-
-#[tauri::command]
-fn get_runtime_status(app: AppHandle) -> Result<RuntimeStatus, String> {
-    Ok(resolve_runtime_status(&app))
-}
-
-#[tauri::command]
-fn set_runtime_python(app: AppHandle, path: String) -> Result<RuntimeStatus, String> {
-    let candidate = PathBuf::from(path.trim());
-    if !candidate.exists() {
-        return Err("指定的 Python 可执行文件不存在".to_string());
-    }
-
-    let version = probe_python_version(&candidate)
-        .ok_or_else(|| "指定路径不是可用的 Python 运行时".to_string())?;
-    if !is_supported_python_version(&version) {
-        return Err(format!(
-            "Python 版本过低，当前为 `{version}`，要求 >= {}.{}",
-            PYTHON_MIN_MAJOR, PYTHON_MIN_MINOR
-        ));
-    }
-
-    let mut config = read_runtime_config(&app)?;
-    config.python_path = Some(candidate.to_string_lossy().to_string());
-    write_runtime_config(&app, &config)?;
-
-    Ok(RuntimeStatus {
-        ready: true,
-        source: "configured".to_string(),
-        executable_path: Some(candidate.to_string_lossy().to_string()),
-        version: Some(version),
-        message: "Python 运行时已保存".to_string(),
-    })
-}
-
-#[tauri::command]
-fn clear_runtime_python(app: AppHandle) -> Result<RuntimeStatus, String> {
-    let mut config = read_runtime_config(&app)?;
-    config.python_path = None;
-    write_runtime_config(&app, &config)?;
-    Ok(resolve_runtime_status(&app))
-}
-
 #[tauri::command]
-fn install_runtime_from_archive(app: AppHandle, archive_path: String) -> Result<RuntimeStatus, String> {
-    let source_path = PathBuf::from(archive_path.trim());
-    if !source_path.exists() {
-        return Err("运行时压缩包不存在".to_string());
-    }
-
-    install_runtime_from_archive_internal(&app, &source_path, "archive")
-}
-
-#[tauri::command]
-fn auto_install_runtime(
-    app: AppHandle,
-    payload: Option<AutoInstallPayload>,
-) -> Result<RuntimeStatus, String> {
-    let payload = payload.unwrap_or(AutoInstallPayload {
-        manifest_url: None,
-        manifest_urls: None,
-    });
-    let manifest_sources = collect_manifest_sources(payload.manifest_url, payload.manifest_urls);
-    if manifest_sources.is_empty() {
-        return Err("未配置 runtime manifest 地址，请先填写 manifest URL".to_string());
-    }
-
-    let target = runtime_target_key(std::env::consts::OS, std::env::consts::ARCH);
-    let mut manifest_errors: Vec<String> = Vec::new();
-    let mut selected_bundle: Option<RuntimeManifestBundle> = None;
-
-    for source in &manifest_sources {
-        if let Err(err) = validate_remote_url_scheme(source, "manifest") {
-            manifest_errors.push(err);
-            continue;
-        }
-        match load_runtime_manifest(source) {
-            Ok(manifest) => {
-                if let Some(bundle) = select_manifest_bundle(&manifest, &target) {
-                    selected_bundle = Some(bundle.clone());
-                    break;
-                }
-                manifest_errors.push(format!("manifest `{source}` 未包含平台 `{target}`"));
-            }
-            Err(err) => {
-                manifest_errors.push(format!("manifest `{source}` 加载失败：{err}"));
-            }
-        }
-    }
-
-    let bundle = selected_bundle.ok_or_else(|| format!("自动安装失败：{}", manifest_errors.join(" | ")))?;
-
-    let runtime_root = runtime_root_dir(&app)?;
-    let download_dir = runtime_root.join("downloads");
-    fs::create_dir_all(&download_dir).map_err(|err| format!("创建下载目录失败: {err}"))?;
-    let archive_path = download_dir.join(format!("python-runtime-{target}.zip"));
-    let download_urls = resolve_bundle_download_urls(&bundle);
-    for url in &download_urls {
-        validate_remote_url_scheme(url, "runtime 包下载地址")?;
-    }
-    if download_urls.iter().any(|url| is_remote_url(url)) && !bundle_has_checksum(&bundle) {
-        return Err("远程 runtime 包必须提供 sha256 校验值".to_string());
-    }
-    let mut download_errors: Vec<String> = Vec::new();
-    let mut downloaded = false;
-    for url in download_urls {
-        match download_bundle_to_path(&url, &archive_path) {
-            Ok(_) => {
-                downloaded = true;
-                break;
-            }
-            Err(err) => download_errors.push(format!("`{url}` 下载失败：{err}")),
-        }
-    }
-    if !downloaded {
-        return Err(format!("runtime 包下载失败：{}", download_errors.join(" | ")));
-    }
-
-    if let Some(checksum) = &bundle.sha256 {
-        if let Err(err) = verify_sha256_checksum(&archive_path, checksum) {
-            let _ = fs::remove_file(&archive_path);
-            return Err(err);
-        }
-    }
-
-    install_runtime_from_archive_internal(&app, &archive_path, "download")
+fn get_alert_settings(app: AppHandle) -> Result<AlertSettings, String> {
+    Ok(read_app_settings(&app)?.alerts)
 }
 
-// ── uv / Python 自动安装常量 ───────────────────────────────────────────────
-const UV_INSTALL_RETRIES: u32 = 3;
-const UV_RETRY_SLEEP_SECS: u64 = 4;
-
-/// 自动探测并配置 Python 运行时：
-///   1. 查找已有 uv → 查找 / 安装 Python 3.11
-///   2. uv 不存在 → 自动安装 uv（带重试），再执行 1
-///   3. 全部失败 → 回退系统 python3 / python
 #[tauri::command]
-fn auto_detect_runtime(app: AppHandle) -> Result<RuntimeStatus, String> {
-    let mut uv_install_err: Option<String> = None;
-
-    let uv_opt = find_uv_executable().or_else(|| {
-        match install_uv() {
-            Ok(p) => Some(p),
-            Err(e) => { uv_install_err = Some(e); None }
-        }
-    });
-
-    if let Some(uv) = uv_opt {
-        // 1a. 查找 uv 已管理的 Python（任意 >=3.9）
-        if let Ok(out) = Command::new(&uv).args(["python", "find"]).output() {
-            if out.status.success() {
-                let p = String::from_utf8_lossy(&out.stdout).trim().to_string();
-                if !p.is_empty() {
-                    let c = PathBuf::from(&p);
-                    if let Some(ver) = probe_python_version(&c) {
-                        if is_supported_python_version(&ver) {
-                            return save_configured_runtime(&app, c, ver);
-                        }
-                    }
-                }
-            }
-        }
-
-        // 1b. 通过 uv 安装 Python 3.11，最多重试 UV_INSTALL_RETRIES 次
-        let mut py_ok = false;
-        for attempt in 1..=UV_INSTALL_RETRIES {
-            if let Ok(out) = Command::new(&uv).args(["python", "install", "3.11"]).output() {
-                if out.status.success() { py_ok = true; break; }
-            }
-            if attempt < UV_INSTALL_RETRIES {
-                std::thread::sleep(std::time::Duration::from_secs(UV_RETRY_SLEEP_SECS));
-            }
-        }
-        if py_ok {
-            if let Ok(out) = Command::new(&uv).args(["python", "find", "3.11"]).output() {
-                if out.status.success() {
-                    let p = String::from_utf8_lossy(&out.stdout).trim().to_string();
-                    if !p.is_empty() {
-                        let c = PathBuf::from(&p);
-                        if let Some(ver) = probe_python_version(&c) {
-                            if is_supported_python_version(&ver) {
-                                return save_configured_runtime(&app, c, ver);
-                            }
-                        }
-                    }
-                }
-            }
-        }
-        // uv python install 失败（如网络差），继续回退
-    }
-
-    // 2. 回退到系统 Python
-    #[cfg(target_os = "windows")]
-    let candidates = ["python", "python3"];
-    #[cfg(not(target_os = "windows"))]
-    let candidates = ["python3", "python"];
-
-    for name in candidates {
-        let exe = PathBuf::from(name);
-        if let Some(ver) = probe_python_version(&exe) {
-            if is_supported_python_version(&ver) {
-                return save_configured_runtime(&app, exe, ver);
-            }
-        }
-    }
-
-    // 3. 全部失败：给出有针对性的错误提示
-    let base = "未找到可用的 Python 运行时（需 ≥3.9）。";
-    let hint = "https://docs.astral.sh/uv/getting-started/installation/";
-    if let Some(uv_err) = uv_install_err {
-        Err(format!(
-            "{base}\n\n安装 uv 失败：{uv_err}\n\n建议：\n① 检查网络后点击「自动安装 Python」重试\n② 或访问 {hint} 手动安装 uv\n③ 或点击「选择 Python 文件」指定已有 Python"
-        ))
-    } else {
-        Err(format!(
-            "{base}\n\n建议：\n① 检查网络后点击「自动安装 Python」重试\n② 或访问 {hint} 安装 uv\n③ 或点击「选择 Python 文件」指定已有 Python"
-        ))
-    }
-}
-
-/// 查找已安装的 uv 可执行文件（PATH + 平台默认路径）。
-fn find_uv_executable() -> Option<PathBuf> {
-    // 优先 PATH
-    if Command::new("uv").arg("--version").output().map(|o| o.status.success()).unwrap_or(false) {
-        return Some(PathBuf::from("uv"));
-    }
-    for path in uv_default_paths() {
-        if path.exists()
-            && Command::new(&path).arg("--version").output().map(|o| o.status.success()).unwrap_or(false)
-        {
-            return Some(path);
-        }
-    }
-    None
-}
-
-/// 平台相关的 uv 默认安装位置。
-fn uv_default_paths() -> Vec<PathBuf> {
-    let mut paths: Vec<PathBuf> = Vec::new();
-    #[cfg(target_os = "windows")]
-    {
-        for var in ["USERPROFILE", "APPDATA", "LOCALAPPDATA"] {
-            if let Ok(base) = std::env::var(var) {
-                let b = PathBuf::from(base);
-                paths.push(b.join(".cargo").join("bin").join("uv.exe"));
-                paths.push(b.join(".local").join("bin").join("uv.exe"));
-                paths.push(b.join("uv").join("bin").join("uv.exe"));
-            }
-        }
-    }
-    #[cfg(not(target_os = "windows"))]
-    {
-        if let Ok(home) = std::env::var("HOME") {
-            let h = PathBuf::from(home);
-            paths.push(h.join(".local").join("bin").join("uv"));
-            paths.push(h.join(".cargo").join("bin").join("uv"));
-        }
-    }
-    paths
-}
-
-/// 通过官方脚本自动安装 uv（跨平台，带重试），成功后返回可执行路径。
-fn install_uv() -> Result<PathBuf, String> {
-    let mut last_err = String::new();
-
-    for attempt in 1..=UV_INSTALL_RETRIES {
-        let ok = {
-            #[cfg(target_os = "windows")]
-            {
-                Command::new("powershell")
-                    .args([
-                        "-NoProfile", "-ExecutionPolicy", "Bypass", "-Command",
-                        "irm https://astral.sh/uv/install.ps1 | iex",
-                    ])
-                    .stdout(std::process::Stdio::null())
-                    .stderr(std::process::Stdio::null())
-                    .status()
-                    .map(|s| s.success())
-                    .unwrap_or(false)
-            }
-            #[cfg(not(target_os = "windows"))]
-            {
-                Command::new("sh")
-                    .args(["-c", "curl -LsSf https://astral.sh/uv/install.sh | sh"])
-                    .stdout(std::process::Stdio::null())
-                    .stderr(std::process::Stdio::null())
-                    .status()
-                    .map(|s| s.success())
-                    .unwrap_or(false)
-            }
-        };
-
-        if ok {
-            // 安装成功，在默认路径中寻找
-            if Command::new("uv").arg("--version").output().map(|o| o.status.success()).unwrap_or(false) {
-                return Ok(PathBuf::from("uv"));
-            }
-            for p in uv_default_paths() {
-                if p.exists() { return Ok(p); }
-            }
-            return Err("uv 安装完成，但未找到可执行文件，请重启应用后重试。".to_string());
-        }
-
-        last_err = format!("第 {attempt} 次安装失败");
-        if attempt < UV_INSTALL_RETRIES {
-            std::thread::sleep(std::time::Duration::from_secs(UV_RETRY_SLEEP_SECS));
+fn set_alert_settings(app: AppHandle, payload: AlertSettings) -> Result<AlertSettings, String> {
+    payload.validate()?;
+    if let Some(email) = &payload.email {
+        let registry = AccountRegistry::load(&resolve_data_file(&app, SMTP_ACCOUNTS_RELATIVE_PATH)?)?;
+        if registry.find(&email.account_id).is_none() {
+            return Err(format!("发件账号不存在: {}", email.account_id));
         }
     }
-
-    Err(format!(
-        "{last_err}（共重试 {UV_INSTALL_RETRIES} 次）。\n请检查网络连接后重试，或手动安装：https://docs.astral.sh/uv/"
-    ))
-}
-
-fn save_configured_runtime(app: &AppHandle, path: PathBuf, version: String) -> Result<RuntimeStatus, String> {
-    let mut config = read_runtime_config(app)?;
-    config.python_path = Some(path.to_string_lossy().to_string());
-    write_runtime_config(app, &config)?;
-    Ok(RuntimeStatus {
-        ready: true,
-        source: "configured".to_string(),
-        executable_path: Some(path.to_string_lossy().to_string()),
-        version: Some(version),
-        message: "Python 运行时已就绪".to_string(),
-    })
-}
-
-fn install_runtime_from_archive_internal(
-    app: &AppHandle,
-    source_path: &Path,
-    source_label: &str,
-) -> Result<RuntimeStatus, String> {
-    if !source_path.exists() {
-        return Err("运行时压缩包不存在".to_string());
-    }
-
-    let runtime_root = runtime_root_dir(app)?;
-    fs::create_dir_all(&runtime_root).map_err(|err| format!("创建 runtime 根目录失败: {err}"))?;
-    let staging_dir = runtime_root.join("python_staging");
-    let active_dir = runtime_root.join("python");
-
-    extract_zip_archive(source_path, &staging_dir)?;
-
-    let staging_python = find_python_executable(&staging_dir)
-        .ok_or_else(|| "压缩包中未找到可用 Python 可执行文件".to_string())?;
-    let version = probe_python_version(&staging_python)
-        .ok_or_else(|| "解压后的 Python 运行时不可执行".to_string())?;
-    if !is_supported_python_version(&version) {
-        return Err(format!(
-            "压缩包中的 Python 版本过低，当前为 `{version}`，要求 >= {}.{}",
-            PYTHON_MIN_MAJOR, PYTHON_MIN_MINOR
-        ));
-    }
-
-    let relative_python = staging_python
-        .strip_prefix(&staging_dir)
-        .map_err(|err| format!("运行时路径解析失败: {err}"))?
-        .to_path_buf();
-
-    if active_dir.exists() {
-        fs::remove_dir_all(&active_dir).map_err(|err| format!("清理旧运行时目录失败: {err}"))?;
-    }
-    fs::rename(&staging_dir, &active_dir).map_err(|err| format!("启用新运行时失败: {err}"))?;
-    let active_python = active_dir.join(relative_python);
-
-    let mut config = read_runtime_config(app)?;
-    config.python_path = Some(active_python.to_string_lossy().to_string());
-    write_runtime_config(app, &config)?;
-
-    Ok(RuntimeStatus {
-        ready: true,
-        source: source_label.to_string(),
-        executable_path: Some(active_python.to_string_lossy().to_string()),
-        version: Some(version),
-        message: "运行时导入成功".to_string(),
-    })
+    let mut settings = read_app_settings(&app)?;
+    settings.alerts = payload;
+    write_app_settings(&app, &settings)?;
+    Ok(settings.alerts)
 }
 
-fn spawn_event_forwarder(app: AppHandle, stdout: impl std::io::Read + Send + 'static) {
-    std::thread::spawn(move || {
-        let reader = BufReader::new(stdout);
-        for line in reader.lines() {
-            match line {
-                Ok(raw) => {
-                    let parsed: Result<Value, _> = serde_json::from_str(&raw);
-                    match parsed {
-                        Ok(payload) => {
-                            let _ = app.emit(WORKER_EVENT_CHANNEL, payload);
-                        }
-                        Err(err) => {
-                            let _ = app.emit(
-                                WORKER_EVENT_CHANNEL,
-                                json!({ "type": "error", "error": format!("invalid worker payload: {err}") }),
-                            );
-                        }
-                    }
-                }
-                Err(err) => {
-                    let _ = app.emit(
-                        WORKER_EVENT_CHANNEL,
-                        json!({ "type": "error", "error": format!("worker stdout read failure: {err}") }),
-                    );
-                    break;
-                }
-            }
+/// 获取跨任务共享的限速器：首次调用时载入历史发送记录，每次都按最新设置刷新限额。
+fn shared_throttle(app: &AppHandle, state: &WorkerState) -> Result<Arc<Mutex<Throttle>>, String> {
+    let limits = read_app_settings(app)?.throttle;
+    let mut guard = state
+        .throttle
+        .lock()
+        .map_err(|_| "failed to acquire throttle lock".to_string())?;
+    let throttle = match guard.as_ref() {
+        Some(throttle) => Arc::clone(throttle),
+        None => {
+            let history = load_history(&resolve_data_file(app, THROTTLE_HISTORY_RELATIVE_PATH)?);
+            let throttle = Arc::new(Mutex::new(Throttle::new(limits.clone(), history)));
+            *guard = Some(Arc::clone(&throttle));
+            throttle
         }
-    });
+    };
+    throttle
+        .lock()
+        .map_err(|_| "failed to acquire throttle lock".to_string())?
+        .set_limits(limits);
+    Ok(throttle)
 }
 
-fn run_worker_request(request: Value, app: &AppHandle) -> Result<Value, String> {
-    let mut command = worker_command(app)?;
-    let mut child = command
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .stderr(Stdio::inherit())
-        .spawn()
-        .map_err(|err| format!("failed to spawn worker: {err}"))?;
-
-    {
-        // Take stdin out of child so it is dropped (closed) at end of scope.
-        // This lets the Python worker see EOF and exit its input loop.
-        let mut stdin = child
-            .stdin
-            .take()
-            .ok_or_else(|| "failed to open worker stdin".to_string())?;
-
-        writeln!(stdin, "{}", request)
-            .and_then(|_| stdin.flush())
-            .map_err(|err| format!("failed to write worker request: {err}"))?;
+/// 规范化任务中的抄送 / 密送地址；同时出现在抄送中的密送地址只保留抄送。
+fn normalize_copy_recipients(payload: &mut Value) -> Result<(), String> {
+    let read_list = |field: &str| -> Result<Vec<String>, String> {
+        match payload.get(field) {
+            None | Some(Value::Null) => Ok(Vec::new()),
+            Some(value) => serde_json::from_value(value.clone()).map_err(|err| format!("{field} 参数格式错误: {err}")),
+        }
+    };
+    let cc = normalize_copy_addresses(&read_list("cc")?, "cc")?;
+    let mut bcc = normalize_copy_addresses(&read_list("bcc")?, "bcc")?;
+    bcc.retain(|address| !cc.iter().any(|copy| copy.eq_ignore_ascii_case(address)));
+    if let Some(object) = payload.as_object_mut() {
+        object.insert("cc".to_string(), json!(cc));
+        object.insert("bcc".to_string(), json!(bcc));
     }
+    Ok(())
+}
 
-    let stdout = child
-        .stdout
-        .take()
-        .ok_or_else(|| "failed to open worker stdout".to_string())?;
-    let mut lines = BufReader::new(stdout).lines();
-
-    let first_line = lines
-        .next()
-        .ok_or_else(|| "worker returned empty response".to_string())?
-        .map_err(|err| format!("failed to read worker response: {err}"))?;
-
-    let payload: Value =
-        serde_json::from_str(&first_line).map_err(|err| format!("invalid worker response: {err}"))?;
-
-    let _ = child.wait();
-    Ok(payload)
+/// 从导入的收件人中移除抑制列表里的地址（含被抑制域名下的地址），返回保留的收件人与被移除的数量。
+fn filter_suppressed(app: &AppHandle, recipients: Vec<RecipientEntry>) -> Result<(Vec<RecipientEntry>, usize), String> {
+    let suppression = SuppressionList::load(&resolve_data_file(app, SUPPRESSION_RELATIVE_PATH)?)?;
+    let before = recipients.len();
+    let kept: Vec<RecipientEntry> =
+        recipients.into_iter().filter(|recipient| !suppression.is_suppressed(&recipient.email)).collect();
+    let suppressed = before - kept.len();
+    Ok((kept, suppressed))
 }
 
-fn worker_command(app: &AppHandle) -> Result<Command, String> {
-    let worker_script = resolve_worker_script(app)?;
-    let project_root = worker_script
-        .parent()
-        .map(Path::to_path_buf)
-        .unwrap_or_else(|| PathBuf::from("."));
-    let use_uv = project_root.join("pyproject.toml").exists();
-
-    if use_uv {
-        if let Some(project_python) = find_project_python(&project_root) {
-            let mut command = Command::new(project_python);
-            command.arg(&worker_script);
-            command.current_dir(&project_root);
-            command.env("PYTHONPATH", &project_root);
-            return Ok(command);
-        }
-
-        // Dev fallback: use "uv run python" to activate local project env.
-        if let Some(uv) = find_uv_executable() {
-            let mut command = Command::new(uv);
-            command.args(["run", "python"]);
-            command.arg(&worker_script);
-            command.current_dir(&project_root);
-            return Ok(command);
-        }
-    }
-
-    // Fallback: use the configured Python binary directly.
-    // Set CWD + PYTHONPATH so bulk_email_sender is importable; third-party deps
-    // (openpyxl) may be absent in base Python – xlsx loading will fail gracefully.
-    let runtime = resolve_python_runtime(app)
-        .ok_or_else(|| "未找到可用 Python 运行时，请先在客户端完成 Python 运行时设置".to_string())?;
-    let mut command = Command::new(runtime.executable_path);
-    command.arg(worker_script);
-    command.current_dir(&project_root);
-    command.env("PYTHONPATH", &project_root);
-    Ok(command)
-}
-
-fn find_project_python(project_root: &Path) -> Option<PathBuf> {
-    let candidates = if cfg!(target_os = "windows") {
-        vec![
-            project_root.join(".venv").join("Scripts").join("python.exe"),
-            project_root.join(".venv").join("python.exe"),
-        ]
-    } else {
-        vec![
-            project_root.join(".venv").join("bin").join("python3"),
-            project_root.join(".venv").join("bin").join("python"),
-        ]
+/// 从任务中移除抑制列表里的地址（含被抑制域名下的地址），返回被移除的数量。
+fn apply_suppression_list(app: &AppHandle, payload: &mut Value) -> Result<usize, String> {
+    let list = SuppressionList::load(&resolve_data_file(app, SUPPRESSION_RELATIVE_PATH)?)?;
+    if list.is_empty() {
+        return Ok(0);
+    }
+    let Some(recipients) = payload.get_mut("recipients").and_then(Value::as_array_mut) else {
+        return Ok(0);
     };
+    let before = recipients.len();
+    recipients.retain(|recipient| {
+        !recipient["email"]
+            .as_str()
+            .is_some_and(|email| list.is_suppressed(email))
+    });
+    if recipients.is_empty() && before > 0 {
+        return Err("所有收件人均在抑制列表中，无需发送".to_string());
+    }
+    Ok(before - recipients.len())
+}
 
-    for candidate in candidates {
-        if !candidate.exists() {
-            continue;
-        }
-        if let Some(version) = probe_python_version(&candidate) {
-            if is_supported_python_version(&version) {
-                return Some(candidate);
+/// 任务中断后续发：`resume_campaign` 指定原任务时沿用其任务 ID，并跳过发送记录中该任务已送达的地址，
+/// 避免崩溃后重新导入同一名单造成重复发送。返回跳过情况的汇总（逐行列出最多 `MAX_RESUME_SKIPPED_ROWS` 个）。
+fn apply_resume(app: &AppHandle, payload: &mut Value) -> Result<Option<Value>, String> {
+    let Some((campaign_id, delivered)) = resume_delivered(app, payload)? else {
+        return Ok(None);
+    };
+    payload["job_id"] = json!(campaign_id);
+    let mut skipped = 0;
+    let mut skipped_rows = Vec::new();
+    if let Some(recipients) = payload.get_mut("recipients").and_then(Value::as_array_mut) {
+        let mut row = 0;
+        recipients.retain(|recipient| {
+            row += 1;
+            let email = recipient["email"].as_str().unwrap_or_default();
+            if !delivered.contains(&email.trim().to_lowercase()) {
+                return true;
             }
+            skipped += 1;
+            if skipped_rows.len() < MAX_RESUME_SKIPPED_ROWS {
+                skipped_rows.push(json!({ "row": row, "email": email, "name": recipient["name"] }));
+            }
+            false
+        });
+        if recipients.is_empty() && skipped > 0 {
+            return Err(format!("任务 {campaign_id} 的收件人均已发送，无需续发"));
         }
     }
-    None
+    Ok(Some(json!({
+        "campaign_id": campaign_id,
+        "delivered_before": delivered.len(),
+        "skipped": skipped,
+        "skipped_rows": skipped_rows,
+    })))
+}
+
+/// `resume_campaign` 对应任务在发送记录中已送达的邮箱（小写）。
+fn resume_delivered(app: &AppHandle, payload: &Value) -> Result<Option<(String, HashSet<String>)>, String> {
+    let Some(campaign_id) = payload
+        .get("resume_campaign")
+        .and_then(Value::as_str)
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+    else {
+        return Ok(None);
+    };
+    let mut delivered = HashSet::new();
+    sent_history(app)?.for_each(Some(RecordStatus::Sent), Some(campaign_id), |_, record| {
+        delivered.insert(record.email.trim().to_lowercase());
+    })?;
+    Ok(Some((campaign_id.to_string(), delivered)))
 }
 
-fn resolve_worker_script(app: &AppHandle) -> Result<PathBuf, String> {
-    let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
-    let dev_candidates = vec![
-        manifest_dir.join("../../..").join("worker.py"),
-        manifest_dir.join("../..").join("worker.py"),
-        manifest_dir.join("worker.py"),
-    ];
-
-    for candidate in &dev_candidates {
-        if candidate.exists() {
-            return candidate
-                .canonicalize()
-                .or_else(|_| Ok(candidate.clone()));
+fn consent_policy(payload: &Value) -> Result<ConsentPolicy, String> {
+    match payload.get("consent_policy") {
+        Some(value) if !value.is_null() => {
+            serde_json::from_value(value.clone()).map_err(|err| format!("同意策略无效: {err}"))
         }
+        _ => Ok(ConsentPolicy::Off),
     }
+}
 
-    if let Ok(resource_dir) = app.path().resource_dir() {
-        let packaged_script = resource_dir.join("worker.py");
-        if packaged_script.exists() {
-            return Ok(packaged_script);
-        }
+/// 按任务的 `consent_policy` 过滤收件人：`require` 时移除没有同意记录的联系人，返回被阻止的数量。
+fn apply_consent_policy(app: &AppHandle, payload: &mut Value) -> Result<usize, String> {
+    if consent_policy(payload)? == ConsentPolicy::Off {
+        return Ok(0);
+    }
+    let store = ConsentStore::load(&resolve_data_file(app, CONSENT_RELATIVE_PATH)?)?;
+    let Some(recipients) = payload.get_mut("recipients").and_then(Value::as_array_mut) else {
+        return Ok(0);
+    };
+    let before = recipients.len();
+    recipients.retain(|recipient| {
+        recipient["email"]
+            .as_str()
+            .is_some_and(|email| store.has_consent(email))
+    });
+    if recipients.is_empty() && before > 0 {
+        return Err("所有收件人均缺少同意记录，已按同意策略阻止发送".to_string());
+    }
+    Ok(before - recipients.len())
+}
 
-        for entry in WalkDir::new(&resource_dir)
-            .max_depth(4)
-            .into_iter()
-            .filter_map(Result::ok)
-        {
-            if entry.file_type().is_file() && entry.file_name() == "worker.py" {
-                return Ok(entry.path().to_path_buf());
+/// 流式收件人未指定列映射时沿用该文件已保存的映射。
+fn apply_saved_column_mapping(app: &AppHandle, payload: &mut Value) -> Result<(), String> {
+    let Some(source) = payload.get_mut("recipient_source").filter(|value| value.is_object()) else {
+        return Ok(());
+    };
+    if source.get("column_mapping").is_some_and(|value| !value.is_null()) {
+        return Ok(());
+    }
+    let path = source["path"].as_str().unwrap_or_default().trim().to_string();
+    if let Some(mapping) = read_column_mappings(app)?.remove(&path) {
+        source["column_mapping"] = json!(mapping);
+    }
+    Ok(())
+}
+
+/// 流式收件人无法预先过滤，改为在读取时按抑制列表与同意策略排除；非流式任务返回 `None`。
+fn stream_exclusion(app: &AppHandle, payload: &Value) -> Result<Option<RecipientExclusion>, String> {
+    if payload.get("recipient_source").is_none_or(Value::is_null) {
+        return Ok(None);
+    }
+    let suppression = SuppressionList::load(&resolve_data_file(app, SUPPRESSION_RELATIVE_PATH)?)?;
+    let consent = match consent_policy(payload)? {
+        ConsentPolicy::Require => Some(ConsentStore::load(&resolve_data_file(app, CONSENT_RELATIVE_PATH)?)?),
+        ConsentPolicy::Off => None,
+    };
+    let delivered = resume_delivered(app, payload)?.map(|(_, delivered)| delivered).unwrap_or_default();
+    Ok(Some(Arc::new(move |email: &str| {
+        suppression.is_suppressed(email)
+            || consent.as_ref().is_some_and(|store| !store.has_consent(email))
+            || delivered.contains(&email.trim().to_lowercase())
+    })))
+}
+
+/// 校验任务的环境标签：测试任务只能使用测试 SMTP 配置或测试收件服务；
+/// 生产任务必须带有 `confirm_production: true`，否则返回包含收件人数与发件服务器的确认提示。
+fn check_campaign_environment(app: &AppHandle, payload: &Value) -> Result<(), String> {
+    let environment: Environment = match payload.get("environment") {
+        Some(value) if !value.is_null() => {
+            serde_json::from_value(value.clone()).map_err(|err| format!("任务环境标签无效: {err}"))?
+        }
+        _ => return Ok(()),
+    };
+    let profiles = campaign_profiles(app, payload)?;
+    if environment == Environment::Test {
+        for (name, label, host) in &profiles {
+            if !usable_for_test(*label, host) {
+                return Err(format!(
+                    "测试任务不能使用非测试的 SMTP 配置「{name}」（{host}），请改用标记为测试的配置或本地测试收件服务"
+                ));
             }
         }
+        return Ok(());
     }
-
-    let searched = dev_candidates
-        .iter()
-        .map(|path| path.to_string_lossy().to_string())
-        .collect::<Vec<String>>()
-        .join(" | ");
-    Err(format!("未找到 worker.py，请检查打包资源配置（已检查：{searched}）"))
+    if payload.get("confirm_production").and_then(Value::as_bool) == Some(true) {
+        return Ok(());
+    }
+    let hosts: Vec<&str> = profiles.iter().map(|(_, _, host)| host.as_str()).collect();
+    let audience = match payload.pointer("/recipient_source/path").and_then(Value::as_str) {
+        Some(path) => format!("收件人文件 {} 中的", path.trim()),
+        None => {
+            let recipients = payload.get("recipients").and_then(Value::as_array).map_or(0, Vec::len);
+            format!(" {recipients} 位")
+        }
+    };
+    Err(format!(
+        "【生产环境】即将通过 {} 向{audience}真实收件人发送邮件，请确认后重新提交（confirm_production）",
+        hosts.join("、")
+    ))
 }
 
-fn resolve_runtime_status(app: &AppHandle) -> RuntimeStatus {
-    if let Some(runtime) = resolve_python_runtime(app) {
-        let message = if runtime.source == "system" {
-            "检测到系统 Python，可直接使用".to_string()
-        } else {
-            "Python 运行时可用".to_string()
-        };
-        return RuntimeStatus {
-            ready: true,
-            source: runtime.source,
-            executable_path: Some(runtime.executable_path.to_string_lossy().to_string()),
-            version: Some(runtime.version),
-            message,
+/// 任务实际使用的 SMTP 配置：(名称, 环境标签, 主机)。
+fn campaign_profiles(app: &AppHandle, payload: &Value) -> Result<Vec<(String, Option<Environment>, String)>, String> {
+    let Some(rotation) = payload.get("rotation").filter(|value| !value.is_null()) else {
+        let label = match payload.pointer("/smtp/environment") {
+            Some(value) if !value.is_null() => Some(
+                serde_json::from_value(value.clone()).map_err(|err| format!("SMTP 配置的环境标签无效: {err}"))?,
+            ),
+            _ => None,
         };
+        let host = payload.pointer("/smtp/host").and_then(Value::as_str).unwrap_or_default();
+        return Ok(vec![("当前 SMTP 配置".to_string(), label, host.trim().to_string())]);
+    };
+    let config: RotationConfig =
+        serde_json::from_value(rotation.clone()).map_err(|err| format!("账号轮换配置格式错误: {err}"))?;
+    let registry = AccountRegistry::load(&resolve_data_file(app, SMTP_ACCOUNTS_RELATIVE_PATH)?)?;
+    config
+        .account_ids
+        .iter()
+        .map(|id| {
+            let account = registry.find(id).ok_or_else(|| format!("发件账号不存在: {id}"))?;
+            let name = if account.label.trim().is_empty() { &account.id } else { &account.label };
+            Ok((name.clone(), account.smtp.environment, account.smtp.host.clone()))
+        })
+        .collect()
+}
+
+/// 单封邮件的体积上限：任务显式配置优先，其次按发件服务商规则，最后使用默认值；
+/// 连接测试记录了服务器 SIZE 上限时不超过该上限。
+fn oversize_limit(job: &SendJob) -> u64 {
+    let limit = job
+        .oversize
+        .limit_bytes
+        .or_else(|| {
+            detect_provider(&builtin_rules(), &job.smtp.host, &job.sender.email)
+                .and_then(|rules| rules.max_attachment_bytes)
+        })
+        .unwrap_or(DEFAULT_MAX_TOTAL_BYTES);
+    match job.smtp.server_size_limit().filter(|_| job.local_mta.is_none()) {
+        Some(server) => limit.min(server),
+        None => limit,
+    }
+}
+
+/// `oversize.action = link` 时上传附件，超限的收件人改为收到下载链接；返回改为链接的收件人数量。
+fn apply_oversize_policy(app: &AppHandle, job: &mut SendJob) -> Result<usize, String> {
+    if job.oversize.action != OversizeAction::Link || job.attachments.is_empty() {
+        return Ok(0);
+    }
+    let report = measure_message_sizes(job, oversize_limit(job))?;
+    if report.oversize.is_empty() {
+        return Ok(0);
     }
-
-    RuntimeStatus {
-        ready: false,
-        source: "none".to_string(),
-        executable_path: None,
-        version: None,
-        message: "未检测到 Python 运行时，请导入运行时压缩包或手动指定可执行文件".to_string(),
+    let settings = read_app_settings(app)?;
+    job.attachment_links = job
+        .attachments
+        .iter()
+        .map(|path| upload_attachment(&settings.network, &settings.upload, Path::new(path)))
+        .collect::<Result<_, _>>()?;
+    job.linked_recipients = report.oversize.into_iter().collect();
+    Ok(job.linked_recipients.len())
+}
+
+/// 在任务启动前应用全局限速：提升发送间隔下限，并按 24 小时剩余额度截断收件人。
+/// 返回因额度不足而未纳入本次任务的收件人数量。
+fn apply_throttle(payload: &mut Value, throttle: &mut Throttle) -> Result<usize, String> {
+    let now = unix_now();
+    let mut throttled = 0;
+    if let Some(remaining) = throttle.remaining_today(now) {
+        if remaining == 0 {
+            return Err(format!(
+                "已达到每日发送上限（{} 封/24 小时），请稍后再试",
+                throttle.limits().per_day.unwrap_or_default()
+            ));
+        }
+        if let Some(recipients) = payload.get_mut("recipients").and_then(Value::as_array_mut) {
+            let keep = remaining as usize;
+            if recipients.len() > keep {
+                throttled = recipients.len() - keep;
+                recipients.truncate(keep);
+            }
+        }
     }
+    if let Some(options) = payload.get_mut("options").and_then(Value::as_object_mut) {
+        let min_delay = options.get("min_delay_sec").and_then(Value::as_u64).unwrap_or(0);
+        let max_delay = options.get("max_delay_sec").and_then(Value::as_u64).unwrap_or(min_delay);
+        let (low, high) = throttle.delay_bounds(min_delay, max_delay);
+        options.insert("min_delay_sec".to_string(), json!(low));
+        options.insert("max_delay_sec".to_string(), json!(high));
+    }
+    Ok(throttled)
+}
+
+/// 两种引擎共用的发送事件观察者：更新限速记录、失效域名统计与任务事件日志。
+struct SendEventHooks {
+    throttle: Arc<Mutex<Throttle>>,
+    history_path: PathBuf,
+    dead_domains: Mutex<DeadDomainStore>,
+    dead_domains_path: PathBuf,
+    /// 共享目录模式下的发送锁，任务结束（hooks 被丢弃）时释放。
+    send_lock: Option<DataDirLock>,
+    /// 任务的告警状态；停滞检查线程只持有弱引用，hooks 被丢弃后随之退出。
+    alerts: Arc<Mutex<AlertMonitor>>,
+    campaign_log: CampaignLog,
+    /// 任务的发送参数，收到 `job_started`（得知任务 ID）时写入任务日志目录。
+    definition: Mutex<Option<Value>>,
+    /// 重发任务对应的原任务，写入任务日志的 `job_started` 事件。
+    resend_of: Option<String>,
+    progress: Mutex<ProgressTracker>,
+    app: AppHandle,
 }
 
-struct PythonRuntime {
-    source: String,
-    executable_path: PathBuf,
-    version: String,
-}
-
-fn resolve_python_runtime(app: &AppHandle) -> Option<PythonRuntime> {
-    if let Ok(config) = read_runtime_config(app) {
-        if let Some(path) = config.python_path {
-            let configured = PathBuf::from(path);
-            if let Some(version) = probe_python_version(&configured) {
-                if is_supported_python_version(&version) {
-                    return Some(PythonRuntime {
-                        source: "configured".to_string(),
-                        executable_path: configured,
-                        version,
-                    });
+impl SendEventHooks {
+    fn new(
+        app: &AppHandle,
+        throttle: Arc<Mutex<Throttle>>,
+        send_lock: Option<DataDirLock>,
+        definition: Value,
+    ) -> Result<Self, String> {
+        let dead_domains_path = resolve_data_file(app, DEAD_DOMAINS_RELATIVE_PATH)?;
+        let settings = read_app_settings(app)?.alerts;
+        let watch_stall = settings.stall_minutes.is_some();
+        let alerts = Arc::new(Mutex::new(AlertMonitor::new(settings, Instant::now())));
+        if watch_stall {
+            spawn_stall_watchdog(app.clone(), Arc::downgrade(&alerts));
+        }
+        Ok(SendEventHooks {
+            throttle,
+            history_path: resolve_data_file(app, THROTTLE_HISTORY_RELATIVE_PATH)?,
+            dead_domains: Mutex::new(DeadDomainStore::load(&dead_domains_path)?),
+            dead_domains_path,
+            send_lock,
+            alerts,
+            campaign_log: campaign_log(app)?,
+            resend_of: definition["resend_of"].as_str().map(str::to_string),
+            definition: Mutex::new(Some(definition)),
+            progress: Mutex::new(ProgressTracker::default()),
+            app: app.clone(),
+        })
+    }
+
+    fn observe(&self, event: &Value) {
+        if let Some(lock) = &self.send_lock {
+            lock.heartbeat();
+        }
+        match (&self.resend_of, event["type"].as_str()) {
+            (Some(source_id), Some("job_started")) => {
+                let mut event = event.clone();
+                event["resend_of"] = json!(source_id);
+                let _ = self.campaign_log.record(&event);
+            }
+            _ => {
+                let _ = self.campaign_log.record(event);
+            }
+        }
+        if let (Some("job_started"), Some(job_id)) = (event["type"].as_str(), event["job_id"].as_str()) {
+            if let Some(definition) = self.definition.lock().ok().and_then(|mut definition| definition.take()) {
+                let _ = self.campaign_log.save_definition(job_id, &definition);
+            }
+        }
+        let email = event["email"].as_str().unwrap_or_default();
+        let mut quota = None;
+        match event["type"].as_str() {
+            Some("recipient_sent") => {
+                if let Ok(mut throttle) = self.throttle.lock() {
+                    let now = unix_now();
+                    throttle.record(now);
+                    let _ = save_history(&self.history_path, &throttle.history());
+                    quota = throttle
+                        .limits()
+                        .per_day
+                        .zip(throttle.remaining_today(now))
+                        .map(|(limit, remaining)| (limit - remaining, limit));
                 }
+                self.update_dead_domains(|store| store.record_success(email));
             }
+            Some("recipient_failed") => {
+                let error = event["error"].as_str().unwrap_or_default();
+                self.update_dead_domains(|store| store.record_failure(email, error));
+            }
+            _ => {}
+        }
+        self.check_alerts(event, quota);
+    }
+
+    /// 在收件人结果事件中附加进度（已处理 / 总数、每分钟发送数与预计完成时间），只发给界面，不写入任务日志。
+    fn attach_progress(&self, event: &mut Value) {
+        let progress = self
+            .progress
+            .lock()
+            .ok()
+            .and_then(|mut tracker| tracker.observe(event, chrono::Utc::now()));
+        if let Some(progress) = progress {
+            event["progress"] = json!(progress);
         }
     }
 
-    for candidate in ["python3", "python"] {
-        let executable = PathBuf::from(candidate);
-        if let Some(version) = probe_python_version(&executable) {
-            if is_supported_python_version(&version) {
-                return Some(PythonRuntime {
-                    source: "system".to_string(),
-                    executable_path: executable,
-                    version,
-                });
-            }
+    fn check_alerts(&self, event: &Value, quota: Option<(u32, u32)>) {
+        let Ok(mut monitor) = self.alerts.lock() else {
+            return;
+        };
+        let mut fired: Vec<Alert> = monitor.observe(event, Instant::now()).into_iter().collect();
+        if let Some((used, limit)) = quota {
+            fired.extend(monitor.observe_quota(used, limit));
+        }
+        let email = monitor.settings().email.clone();
+        drop(monitor);
+        for alert in fired {
+            dispatch_alert(&self.app, email.clone(), alert);
         }
     }
 
-    None
-}
-
-fn probe_python_version(path: &Path) -> Option<String> {
-    let output = Command::new(path).arg("--version").output().ok()?;
-    if !output.status.success() {
-        return None;
-    }
-    let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
-    let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
-    let line = if !stdout.is_empty() { stdout } else { stderr };
-    if line.is_empty() {
-        return None;
-    }
-    if parse_python_version(&line).is_none() {
-        return None;
+    fn update_dead_domains(&self, update: impl FnOnce(&mut DeadDomainStore) -> bool) {
+        if let Ok(mut store) = self.dead_domains.lock() {
+            if update(&mut store) {
+                let _ = store.save(&self.dead_domains_path);
+            }
+        }
     }
-    Some(line)
 }
 
-fn parse_python_version(line: &str) -> Option<(u32, u32, u32)> {
-    let normalized = line.trim();
-    let payload = normalized.strip_prefix("Python ")?;
-    let mut chunks = payload.split('.');
-    let major = chunks.next()?.parse::<u32>().ok()?;
-    let minor = chunks.next()?.parse::<u32>().ok()?;
-    let patch = chunks
-        .next()
-        .unwrap_or("0")
-        .split_whitespace()
-        .next()
-        .unwrap_or("0")
-        .parse::<u32>()
-        .ok()?;
-    Some((major, minor, patch))
+/// 每隔一段时间检查任务是否停滞；任务结束或 hooks 被丢弃后退出。
+fn spawn_stall_watchdog(app: AppHandle, monitor: Weak<Mutex<AlertMonitor>>) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(STALL_CHECK_INTERVAL);
+        let Some(monitor) = monitor.upgrade() else {
+            break;
+        };
+        let (alert, email) = {
+            let Ok(mut monitor) = monitor.lock() else {
+                break;
+            };
+            if !monitor.watching() {
+                break;
+            }
+            (monitor.check_stalled(Instant::now()), monitor.settings().email.clone())
+        };
+        if let Some(alert) = alert {
+            dispatch_alert(&app, email, alert);
+        }
+    });
 }
 
-fn is_supported_python_version(line: &str) -> bool {
-    let Some((major, minor, _patch)) = parse_python_version(line) else {
-        return false;
+/// 告警同时以桌面通知（`alert` 事件）和邮件发给操作人；邮件在后台线程发送，不阻塞任务。
+fn dispatch_alert(app: &AppHandle, email: Option<AlertEmail>, alert: Alert) {
+    let _ = app.emit(ALERT_CHANNEL, alert.to_event());
+    let Some(email) = email else {
+        return;
     };
-    major > PYTHON_MIN_MAJOR || (major == PYTHON_MIN_MAJOR && minor >= PYTHON_MIN_MINOR)
+    let app = app.clone();
+    std::thread::spawn(move || {
+        if let Err(err) = send_alert_email(&app, &email, &alert) {
+            let _ = app.emit(
+                ALERT_CHANNEL,
+                json!({ "type": "alert_email_failed", "job_id": alert.job_id, "error": err }),
+            );
+        }
+    });
 }
 
-fn runtime_target_key(os: &str, arch: &str) -> String {
-    format!("{os}-{arch}")
+fn send_alert_email(app: &AppHandle, email: &AlertEmail, alert: &Alert) -> Result<(), String> {
+    let registry = AccountRegistry::load(&resolve_data_file(app, SMTP_ACCOUNTS_RELATIVE_PATH)?)?;
+    let account = registry
+        .find(&email.account_id)
+        .ok_or_else(|| format!("发件账号不存在: {}", email.account_id))?;
+    read_app_settings(app)?.network.check_smtp_host(&account.smtp.host)?;
+    let body = format!(
+        "{}\n\n任务: {}\n时间: {}\n",
+        alert.message,
+        alert.job_id,
+        chrono::Local::now().format("%Y-%m-%d %H:%M:%S")
+    );
+    let message = build_email_message(&MessageContent {
+        sender_email: &account.sender.email,
+        sender_name: &account.sender.name,
+        recipient_email: &email.to,
+        subject: &alert.subject(),
+        body_text: &body,
+        body_html: None,
+        attachments: &[],
+        inline_images: &[],
+        headers: &BTreeMap::new(),
+        cc: &[],
+        bcc: &[],
+        smime: None,
+        return_path: None,
+        ascii_attachment_names: false,
+        message_id: None,
+        read_receipt_to: None,
+    })?;
+    build_mail_transport(&account.smtp, None)?
+        .send_message(&message)
+        .map_err(|err| format!("发送告警邮件失败: {}", err.message))
+}
+
+/// 在后台线程中运行原生引擎，事件与 Python worker 一样转发到 `worker-event` 通道。
+fn spawn_native_job(
+    app: AppHandle,
+    job: SendJob,
+    hooks: SendEventHooks,
+    exclusion: Option<RecipientExclusion>,
+) -> Result<JobControl, String> {
+    let usage_path = resolve_data_file(&app, SMTP_ACCOUNT_USAGE_RELATIVE_PATH)?;
+    let mut ledger = UsageLedger::load(&usage_path);
+    let network = read_app_settings(&app)?.network;
+    if job.readback.is_some() && network.strict_local {
+        return Err("已开启仅本地模式，不能连接 IMAP 服务器做回读验证".to_string());
+    }
+    let canary_imap = job
+        .canary
+        .as_ref()
+        .is_some_and(|canary| canary.mailboxes.iter().any(|mailbox| mailbox.imap.is_some()));
+    if canary_imap && network.strict_local {
+        return Err("已开启仅本地模式，不能连接 IMAP 服务器检查金丝雀邮件".to_string());
+    }
+    let (slots, rotation) = native_slots(&app, &job, &network, &ledger)?;
+    let connections = match &job.concurrency {
+        Some(concurrency) => (0..concurrency.connections)
+            .map(|_| build_mail_transport(&job.smtp, job.options.dsn.as_ref()))
+            .collect::<Result<Vec<_>, _>>()?,
+        None => Vec::new(),
+    };
+    let smime = smime_context(&app, &job)?;
+    let sent_store = SentStore::open(&job.sent_store_path(), Some(&job.sent_store_text_path()))?
+        .with_operator(job.operator.clone())
+        .with_environment(job.environment);
+    let cancel = Arc::new(AtomicBool::new(false));
+    let engine_cancel = Arc::clone(&cancel);
+    let job_throttle = Arc::new(Mutex::new(Throttle::default()));
+    let engine_throttle = Arc::clone(&job_throttle);
+    let tracks_usage = rotation.is_some();
+    std::thread::spawn(move || {
+        let mut engine = SendEngine::new(slots, sent_store, engine_cancel)
+            .with_throttle(Arc::clone(&hooks.throttle))
+            .with_job_throttle(engine_throttle)
+            .with_connections(connections);
+        if let Some(rotation) = rotation {
+            engine = engine.with_rotation(rotation);
+        }
+        if let Some(smime) = smime {
+            engine = engine.with_smime(smime);
+        }
+        if let Some(exclusion) = exclusion {
+            engine = engine.with_recipient_exclusion(exclusion);
+        }
+        engine.run(&job, &mut |mut event| {
+            hooks.observe(&event);
+            hooks.attach_progress(&mut event);
+            if event["type"] == "recipient_sent" {
+                if let Some(account_id) = event["account_id"].as_str().filter(|_| tracks_usage) {
+                    ledger.record(account_id);
+                    let _ = ledger.save(&usage_path);
+                }
+            }
+            emit_worker_event(&app, event);
+        });
+        // 先释放 hooks（其中的共享目录发送锁），下一个任务才能取得锁。
+        drop(hooks);
+        advance_job_queue(&app, job.job_id());
+    });
+    Ok(JobControl::Native {
+        cancel,
+        throttle: job_throttle,
+    })
 }
 
-fn collect_manifest_sources(
-    manifest_url: Option<String>,
-    manifest_urls: Option<Vec<String>>,
-) -> Vec<String> {
-    let mut ordered: Vec<String> = Vec::new();
-
-    if let Some(raw) = manifest_url {
-        for item in raw.split(['\n', ',', ';']) {
-            let trimmed = item.trim();
-            if !trimmed.is_empty() && !ordered.iter().any(|existing| existing == trimmed) {
-                ordered.push(trimmed.to_string());
-            }
+/// 原生引擎的发件账号：轮换时按账号登记表与今日用量创建，否则使用任务自身的发件人与 SMTP 配置。
+fn native_slots(
+    app: &AppHandle,
+    job: &SendJob,
+    network: &NetworkPolicy,
+    ledger: &UsageLedger,
+) -> Result<(Vec<SenderSlot<NativeTransport>>, Option<AccountRotation>), String> {
+    let transport_for = |smtp: &SmtpPayload| match &job.local_mta {
+        Some(local_mta) => LocalMtaTransport::new(local_mta).map(NativeTransport::Local),
+        None => {
+            network.check_smtp_host(&smtp.host)?;
+            build_mail_transport(smtp, job.options.dsn.as_ref())
         }
-    }
-
-    if let Some(list) = manifest_urls {
-        for item in list {
-            let trimmed = item.trim();
-            if !trimmed.is_empty() && !ordered.iter().any(|existing| existing == trimmed) {
-                ordered.push(trimmed.to_string());
+    };
+    // 本机 MTA 不经过 SMTP 服务器，服务器的 SIZE 上限不适用。
+    let size_limit_for = |smtp: &SmtpPayload| smtp.server_size_limit().filter(|_| job.local_mta.is_none());
+    match &job.rotation {
+        Some(config) => {
+            let registry = AccountRegistry::load(&resolve_data_file(app, SMTP_ACCOUNTS_RELATIVE_PATH)?)?;
+            let mut slots = Vec::new();
+            let mut caps = Vec::new();
+            let mut used = Vec::new();
+            for id in &config.account_ids {
+                let account = registry
+                    .find(id)
+                    .ok_or_else(|| format!("发件账号不存在: {id}"))?;
+                slots.push(SenderSlot {
+                    account_id: account.id.clone(),
+                    sender: account.sender.clone(),
+                    return_path: account.smtp.return_path_address()?,
+                    size_limit: size_limit_for(&account.smtp),
+                    transport: transport_for(&account.smtp)?,
+                });
+                caps.push(account.daily_cap);
+                used.push(ledger.sent_today(&account.id));
             }
+            Ok((slots, Some(AccountRotation::new(config.strategy, caps, used))))
+        }
+        None => {
+            let slot = SenderSlot {
+                account_id: "default".to_string(),
+                sender: job.sender.clone(),
+                return_path: job.smtp.return_path_address()?,
+                size_limit: size_limit_for(&job.smtp),
+                transport: transport_for(&job.smtp)?,
+            };
+            Ok((vec![slot], None))
         }
     }
-
-    ordered
 }
 
-fn select_manifest_bundle<'a>(
-    manifest: &'a RuntimeManifest,
-    target: &str,
-) -> Option<&'a RuntimeManifestBundle> {
-    manifest
-        .bundles
-        .iter()
-        .find(|item| item.target == target)
+fn smime_identity_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let path = app
+        .path()
+        .app_data_dir()
+        .map_err(|err| format!("无法获取应用数据目录: {err}"))?
+        .join(SMIME_IDENTITY_RELATIVE_PATH);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|err| format!("创建证书目录失败: {err}"))?;
+    }
+    Ok(path)
 }
 
-fn resolve_bundle_download_urls(bundle: &RuntimeManifestBundle) -> Vec<String> {
-    let mut urls = vec![bundle.url.trim().to_string()];
-    if let Some(extra) = &bundle.urls {
-        for item in extra {
-            let trimmed = item.trim();
-            if !trimmed.is_empty() && !urls.iter().any(|existing| existing == trimmed) {
-                urls.push(trimmed.to_string());
-            }
-        }
-    }
-    urls
+fn load_smime_identity(app: &AppHandle) -> Result<Option<SmimeIdentity>, String> {
+    let settings = read_app_settings(app)?.smime;
+    let Some(path) = settings.pkcs12_path else {
+        return Ok(None);
+    };
+    let bytes = fs::read(&path).map_err(|err| format!("读取 S/MIME 证书失败: {err}"))?;
+    load_pkcs12(&bytes, &settings.password).map(Some)
 }
 
-fn bundle_has_checksum(bundle: &RuntimeManifestBundle) -> bool {
-    bundle
-        .sha256
-        .as_ref()
-        .map(|value| !value.trim().is_empty())
-        .unwrap_or(false)
+/// 按任务的 S/MIME 开关加载签名身份与收件人证书。
+fn smime_context(app: &AppHandle, job: &SendJob) -> Result<Option<SmimeContext>, String> {
+    if !job.smime.enabled() {
+        return Ok(None);
+    }
+    let signer = if job.smime.sign {
+        if job.rotation.is_some() {
+            return Err("S/MIME 签名暂不支持多账号轮换".to_string());
+        }
+        let identity = load_smime_identity(app)?.ok_or_else(|| "尚未导入 S/MIME 签名证书".to_string())?;
+        let emails = certificate_emails(&identity.certificate);
+        if !emails.contains(&job.sender.email.trim().to_lowercase()) {
+            return Err(format!("签名证书的邮箱（{}）与发件邮箱不一致", emails.join("、")));
+        }
+        Some(identity)
+    } else {
+        None
+    };
+    let recipient_certs = if job.smime.encrypt {
+        RecipientCertStore::load(&resolve_data_file(app, SMIME_RECIPIENTS_RELATIVE_PATH)?)?.certificates()
+    } else {
+        Default::default()
+    };
+    Ok(Some(SmimeContext {
+        signer,
+        recipient_certs,
+        encrypt: job.smime.encrypt,
+    }))
 }
 
 fn is_remote_url(url: &str) -> bool {
@@ -1076,104 +3297,40 @@ fn is_localhost_http_url(url: &str) -> bool {
     host == "localhost" || host == "127.0.0.1" || host == "::1"
 }
 
-fn load_runtime_manifest(manifest_url: &str) -> Result<RuntimeManifest, String> {
-    let body = if manifest_url.starts_with("http://") || manifest_url.starts_with("https://") {
-        reqwest::blocking::get(manifest_url)
-            .map_err(|err| format!("下载 manifest 失败: {err}"))?
-            .error_for_status()
-            .map_err(|err| format!("manifest 响应异常: {err}"))?
-            .text()
-            .map_err(|err| format!("读取 manifest 内容失败: {err}"))?
-    } else if manifest_url.starts_with("file://") {
-        let path = manifest_url.trim_start_matches("file://");
-        fs::read_to_string(path).map_err(|err| format!("读取本地 manifest 失败: {err}"))?
-    } else {
-        fs::read_to_string(manifest_url).map_err(|err| format!("读取 manifest 失败: {err}"))?
-    };
-
-    serde_json::from_str::<RuntimeManifest>(&body).map_err(|err| format!("manifest JSON 格式错误: {err}"))
-}
-
-fn download_bundle_to_path(url: &str, destination: &Path) -> Result<(), String> {
-    if let Some(parent) = destination.parent() {
-        fs::create_dir_all(parent).map_err(|err| format!("创建下载目录失败: {err}"))?;
-    }
-
-    if url.starts_with("http://") || url.starts_with("https://") {
-        let mut response = reqwest::blocking::get(url)
-            .map_err(|err| format!("下载 runtime 包失败: {err}"))?
-            .error_for_status()
-            .map_err(|err| format!("runtime 包响应异常: {err}"))?;
-        let mut target = File::create(destination).map_err(|err| format!("创建下载文件失败: {err}"))?;
-        std::io::copy(&mut response, &mut target).map_err(|err| format!("写入下载文件失败: {err}"))?;
-        return Ok(());
-    }
-
-    let source_path = if url.starts_with("file://") {
-        PathBuf::from(url.trim_start_matches("file://"))
-    } else {
-        PathBuf::from(url)
-    };
-
-    if !source_path.exists() {
-        return Err("runtime 包地址无效，文件不存在".to_string());
-    }
-    fs::copy(source_path, destination).map_err(|err| format!("复制 runtime 包失败: {err}"))?;
-    Ok(())
-}
-
-fn verify_sha256_checksum(path: &Path, expected: &str) -> Result<(), String> {
-    let mut file = File::open(path).map_err(|err| format!("读取下载文件失败: {err}"))?;
-    let mut hasher = Sha256::new();
-    let mut buffer = [0_u8; 8192];
-    loop {
-        let size = file
-            .read(&mut buffer)
-            .map_err(|err| format!("读取下载文件失败: {err}"))?;
-        if size == 0 {
-            break;
-        }
-        hasher.update(&buffer[..size]);
-    }
-    let actual = format!("{:x}", hasher.finalize());
-    let expected_trimmed = expected.trim().to_lowercase();
-    if expected_trimmed.is_empty() {
-        return Ok(());
+fn download_recipient_list(policy: &NetworkPolicy, url: &str) -> Result<Vec<u8>, String> {
+    let too_large = || format!("收件人名单超过 {} MB 上限", MAX_RECIPIENT_LIST_BYTES / 1024 / 1024);
+    let response = http_client(policy, url, "收件人名单下载")?
+        .get(url)
+        .send()
+        .map_err(|err| format!("下载收件人名单失败: {err}"))?
+        .error_for_status()
+        .map_err(|err| format!("收件人名单响应异常: {err}"))?;
+    if response.content_length().is_some_and(|length| length > MAX_RECIPIENT_LIST_BYTES) {
+        return Err(too_large());
     }
-    if actual != expected_trimmed {
-        return Err(format!(
-            "runtime 包校验失败：期望 sha256={expected_trimmed}，实际 sha256={actual}"
-        ));
+    // 没有 Content-Length（分块传输）或声明的长度不可信时，多读一个字节判断是否超限。
+    let mut bytes = Vec::new();
+    response
+        .take(MAX_RECIPIENT_LIST_BYTES + 1)
+        .read_to_end(&mut bytes)
+        .map_err(|err| format!("读取收件人名单失败: {err}"))?;
+    if bytes.len() as u64 > MAX_RECIPIENT_LIST_BYTES {
+        return Err(too_large());
     }
-    Ok(())
+    Ok(bytes)
 }
 
-fn runtime_config_path(app: &AppHandle) -> Result<PathBuf, String> {
+/// 会话状态只属于本机界面，存放在应用数据目录而不是（可能共享的）数据目录。
+fn session_state_path(app: &AppHandle) -> Result<PathBuf, String> {
     let app_data_dir = app
         .path()
         .app_data_dir()
         .map_err(|err| format!("无法获取应用数据目录: {err}"))?;
-    let config_path = app_data_dir.join(RUNTIME_CONFIG_RELATIVE_PATH);
-    if let Some(parent) = config_path.parent() {
-        fs::create_dir_all(parent).map_err(|err| format!("无法创建运行时配置目录: {err}"))?;
-    }
-    Ok(config_path)
-}
-
-fn read_runtime_config(app: &AppHandle) -> Result<RuntimeConfig, String> {
-    let config_path = runtime_config_path(app)?;
-    if !config_path.exists() {
-        return Ok(RuntimeConfig::default());
+    let path = app_data_dir.join(SESSION_STATE_RELATIVE_PATH);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|err| format!("无法创建应用设置目录: {err}"))?;
     }
-
-    let text = fs::read_to_string(config_path).map_err(|err| format!("读取运行时配置失败: {err}"))?;
-    serde_json::from_str(&text).map_err(|err| format!("运行时配置格式错误: {err}"))
-}
-
-fn write_runtime_config(app: &AppHandle, config: &RuntimeConfig) -> Result<(), String> {
-    let config_path = runtime_config_path(app)?;
-    let text = serde_json::to_string_pretty(config).map_err(|err| err.to_string())?;
-    fs::write(config_path, text).map_err(|err| format!("写入运行时配置失败: {err}"))
+    Ok(path)
 }
 
 fn app_settings_path(app: &AppHandle) -> Result<PathBuf, String> {
@@ -1224,8 +3381,43 @@ fn resolve_data_dir(app: &AppHandle) -> Result<PathBuf, String> {
     Ok(data_dir)
 }
 
+/// 解析数据目录下的相对文件路径，并确保其父目录存在。
+fn resolve_data_file(app: &AppHandle, relative: &str) -> Result<PathBuf, String> {
+    let path = resolve_data_dir(app)?.join(relative);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|err| format!("创建目录失败: {err}"))?;
+    }
+    Ok(path)
+}
+
+fn campaign_log(app: &AppHandle) -> Result<CampaignLog, String> {
+    Ok(CampaignLog::new(&resolve_data_dir(app)?.join(CAMPAIGNS_RELATIVE_PATH)))
+}
+
+/// 对共享目录中的 JSON 存储做读-改-写：共享模式下先持有同名锁，保存前由调用方检查冲突。
+fn update_shared_file<T>(
+    app: &AppHandle,
+    relative: &str,
+    update: impl FnOnce(&Path, &ConflictGuard) -> Result<T, String>,
+) -> Result<T, String> {
+    let path = resolve_data_file(app, relative)?;
+    let shared = read_app_settings(app)?.shared;
+    let _lock = if shared.enabled {
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_else(|| relative.to_string());
+        Some(DataDirLock::acquire(&resolve_data_dir(app)?, &name, &UserIdentity::current(&shared))?)
+    } else {
+        None
+    };
+    let guard = ConflictGuard::new(&path);
+    update(&path, &guard)
+}
+
 fn resolve_app_paths(app: &AppHandle) -> Result<AppPaths, String> {
     let data_dir = resolve_data_dir(app)?;
+    let shared = read_app_settings(app)?.shared;
     let records_dir = data_dir.join("records");
     let logs_dir = data_dir.join("logs");
     let config_dir = data_dir.join("config");
@@ -1244,11 +3436,17 @@ fn resolve_app_paths(app: &AppHandle) -> Result<AppPaths, String> {
             .join("sent_records.txt")
             .to_string_lossy()
             .to_string(),
-        log_file: logs_dir.join("email_log.txt").to_string_lossy().to_string(),
-        app_draft_file: data_dir
-            .join(APP_DRAFT_RELATIVE_PATH)
-            .to_string_lossy()
-            .to_string(),
+        log_file: data_dir.join(APP_LOG_RELATIVE_PATH).to_string_lossy().to_string(),
+        // 共享目录中每位用户使用自己的草稿，避免互相覆盖（草稿含 SMTP 密码）。
+        app_draft_file: if shared.enabled {
+            data_dir
+                .join(APP_DRAFT_RELATIVE_PATH)
+                .with_file_name(format!("app_draft.{}.json", UserIdentity::current(&shared).file_slug()))
+        } else {
+            data_dir.join(APP_DRAFT_RELATIVE_PATH)
+        }
+        .to_string_lossy()
+        .to_string(),
     })
 }
 
@@ -1307,108 +3505,166 @@ fn resolve_sample_recipient_source_path(app: &AppHandle, file_name: &str) -> Opt
     None
 }
 
-fn runtime_root_dir(app: &AppHandle) -> Result<PathBuf, String> {
-    let root = app
-        .path()
-        .app_local_data_dir()
-        .map_err(|err| format!("无法获取本地运行时目录: {err}"))?
-        .join("runtime");
-    Ok(root)
-}
-
-fn extract_zip_archive(source: &Path, destination: &Path) -> Result<(), String> {
-    if destination.exists() {
-        fs::remove_dir_all(destination).map_err(|err| format!("清理临时目录失败: {err}"))?;
-    }
-    fs::create_dir_all(destination).map_err(|err| format!("创建临时目录失败: {err}"))?;
-
-    let file = File::open(source).map_err(|err| format!("打开压缩包失败: {err}"))?;
-    let mut archive = ZipArchive::new(file).map_err(|err| format!("读取压缩包失败: {err}"))?;
-    for index in 0..archive.len() {
-        let mut entry = archive
-            .by_index(index)
-            .map_err(|err| format!("解压失败: {err}"))?;
-        let Some(safe_name) = entry.enclosed_name().map(|path| path.to_owned()) else {
-            continue;
-        };
-        let output_path = destination.join(safe_name);
-
-        if entry.name().ends_with('/') {
-            fs::create_dir_all(&output_path).map_err(|err| format!("创建目录失败: {err}"))?;
-            continue;
-        }
-
-        if let Some(parent) = output_path.parent() {
-            fs::create_dir_all(parent).map_err(|err| format!("创建目录失败: {err}"))?;
-        }
-
-        let mut output_file =
-            File::create(&output_path).map_err(|err| format!("写入解压文件失败: {err}"))?;
-        std::io::copy(&mut entry, &mut output_file).map_err(|err| format!("写入解压文件失败: {err}"))?;
-
-        #[cfg(unix)]
-        if let Some(mode) = entry.unix_mode() {
-            let _ = fs::set_permissions(&output_path, fs::Permissions::from_mode(mode));
-        }
-    }
-    Ok(())
-}
-
-fn find_python_executable(root: &Path) -> Option<PathBuf> {
-    let mut candidates: Vec<PathBuf> = Vec::new();
-    for entry in WalkDir::new(root)
-        .into_iter()
-        .filter_map(Result::ok)
-    {
-        if !entry.file_type().is_file() {
-            continue;
-        }
-        let file_name = entry.file_name().to_string_lossy().to_lowercase();
-        if file_name == "python3"
-            || file_name == "python"
-            || file_name == "python.exe"
-        {
-            candidates.push(entry.path().to_path_buf());
-        }
-    }
-
-    candidates.sort_by_key(|path| {
-        let depth = path.components().count();
-        let file_name = path
-            .file_name()
-            .and_then(|name| name.to_str())
-            .unwrap_or("")
-            .to_lowercase();
-        let is_python3 = usize::from(file_name == "python3");
-        let is_bin = usize::from(path.components().any(|component| component.as_os_str() == "bin"));
-        (1 - is_python3, 1 - is_bin, depth)
-    });
-    candidates
-        .into_iter()
-        .find(|candidate| probe_python_version(candidate.as_path()).is_some())
-}
-
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_dialog::init())
         .manage(WorkerState::default())
+        .setup(|app| {
+            // 先载入上次留下的任务记录，定时任务提交时才不会覆盖它。
+            let saved =
+                resolve_data_file(app.handle(), ACTIVE_JOBS_RELATIVE_PATH).and_then(|path| JobJournal::load(&path));
+            if let (Ok(saved), Ok(mut journal)) = (saved, app.state::<WorkerState>().journal.lock()) {
+                *journal = saved;
+            }
+            spawn_send_scheduler(app.handle().clone());
+            let handle = app.handle().clone();
+            tauri::async_runtime::spawn_blocking(move || {
+                if let Ok(settings) = read_app_settings(&handle) {
+                    if settings.retention.is_enabled() {
+                        let _ = purge_sent_history(&handle, &settings.retention);
+                    }
+                }
+            });
+            #[cfg(not(feature = "native-only"))]
+            python_runtime::spawn_update_scheduler(app.handle().clone());
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             load_recipients,
+            get_column_mapping,
+            set_column_mapping,
+            load_recipients_from_imap,
+            load_recipients_from_text,
+            ldap_query_recipients,
+            load_recipients_from_database,
+            filter_recipients,
             test_smtp,
+            validate_attachments,
+            preview_send_plan,
+            simulate_policy_compliance,
+            preflight_message_sizes,
             start_send,
+            send_single,
             cancel_send,
-            get_runtime_status,
-            set_runtime_python,
-            clear_runtime_python,
-            install_runtime_from_archive,
-            auto_install_runtime,
-            auto_detect_runtime,
+            cancel_all_jobs,
+            update_job_throttle,
+            pause_job,
+            resume_job,
+            get_job_status,
+            get_job_events,
+            list_jobs,
+            reorder_jobs,
+            cancel_scheduled_job,
+            list_interrupted_jobs,
+            recover_jobs,
+            list_recurring_campaigns,
+            save_recurring_campaign,
+            delete_recurring_campaign,
+            #[cfg(not(feature = "native-only"))]
+            python_runtime::get_runtime_status,
+            #[cfg(not(feature = "native-only"))]
+            python_runtime::set_runtime_python,
+            #[cfg(not(feature = "native-only"))]
+            python_runtime::clear_runtime_python,
+            #[cfg(not(feature = "native-only"))]
+            python_runtime::probe_runtime_libraries,
+            #[cfg(not(feature = "native-only"))]
+            python_runtime::cleanup_worker_envs,
+            #[cfg(not(feature = "native-only"))]
+            python_runtime::set_uv_download_base,
+            #[cfg(not(feature = "native-only"))]
+            python_runtime::install_runtime_from_archive,
+            #[cfg(not(feature = "native-only"))]
+            python_runtime::auto_install_runtime,
+            #[cfg(not(feature = "native-only"))]
+            python_runtime::auto_detect_runtime,
+            #[cfg(not(feature = "native-only"))]
+            python_runtime::get_pending_updates,
+            #[cfg(not(feature = "native-only"))]
+            python_runtime::set_maintenance_settings,
+            get_alert_settings,
+            set_alert_settings,
+            #[cfg(not(feature = "native-only"))]
+            python_runtime::apply_pending_updates,
+            #[cfg(not(feature = "native-only"))]
+            python_runtime::discard_pending_updates,
             clear_sent_records,
+            get_sent_record_retention,
+            set_sent_record_retention,
+            purge_sent_records,
+            query_sent_records,
+            get_send_stats,
+            export_campaign_report_pdf,
+            clone_campaign,
+            get_campaign_timeline,
+            list_job_history,
+            get_job_detail,
+            requeue_failed_recipients,
+            resend_failed,
+            suppress_recipients,
+            annotate_records,
+            list_record_notes,
             get_app_paths,
+            get_capabilities,
             set_data_dir,
             load_app_draft,
             save_app_draft,
+            load_session_state,
+            save_session_state,
+            get_throttle_status,
+            set_throttle_limits,
+            get_network_policy,
+            set_network_policy,
+            get_upload_settings,
+            set_upload_settings,
+            get_job_queue_settings,
+            set_job_queue_settings,
+            get_enrichment_settings,
+            set_enrichment_settings,
+            enrich_recipients,
+            #[cfg(not(feature = "native-only"))]
+            python_runtime::get_worker_settings,
+            #[cfg(not(feature = "native-only"))]
+            python_runtime::set_worker_settings,
+            #[cfg(not(feature = "native-only"))]
+            python_runtime::warm_worker_cache,
+            #[cfg(not(feature = "native-only"))]
+            python_runtime::test_worker_command,
+            import_suppression_list,
+            import_suppression_csv,
+            add_to_suppression,
+            list_suppression,
+            export_suppression_list,
+            import_consent_records,
+            check_consent,
+            validate_recipients,
+            get_validation_settings,
+            set_validation_settings,
+            probe_recipients,
+            preflight_dead_domains,
+            list_dead_domains,
+            forget_dead_domain,
+            check_disposable_recipients,
+            get_disposable_domains,
+            update_disposable_domains,
+            add_smtp_account,
+            list_smtp_accounts,
+            remove_smtp_account,
+            save_smtp_profile,
+            list_smtp_profiles,
+            delete_smtp_profile,
+            validate_smime_certificate,
+            import_smime_certificate,
+            get_smime_certificate,
+            remove_smime_certificate,
+            import_recipient_certificate,
+            list_recipient_certificates,
+            get_shared_dir_settings,
+            set_shared_dir_settings,
+            get_current_user,
+            list_data_dir_locks,
+            break_data_dir_lock,
             open_path,
         ])
         .run(tauri::generate_context!())
@@ -1417,99 +3673,7 @@ pub fn run() {
 
 #[cfg(test)]
 mod tests {
-    use super::{
-        bundle_has_checksum, collect_manifest_sources, is_localhost_http_url, is_supported_python_version,
-        parse_python_version, resolve_bundle_download_urls, runtime_target_key, select_manifest_bundle,
-        validate_remote_url_scheme, RuntimeManifest, RuntimeManifestBundle,
-    };
-
-    #[test]
-    fn parses_python_version_line() {
-        let parsed = parse_python_version("Python 3.11.8");
-        assert_eq!(parsed, Some((3, 11, 8)));
-    }
-
-    #[test]
-    fn rejects_invalid_version_line() {
-        assert_eq!(parse_python_version("v3.11.8"), None);
-    }
-
-    #[test]
-    fn validates_supported_python_version() {
-        assert!(is_supported_python_version("Python 3.11.8"));
-        assert!(is_supported_python_version("Python 3.9.18"));
-        assert!(!is_supported_python_version("Python 3.8.18"));
-    }
-
-    #[test]
-    fn builds_runtime_target_key() {
-        assert_eq!(runtime_target_key("macos", "aarch64"), "macos-aarch64");
-        assert_eq!(runtime_target_key("windows", "x86_64"), "windows-x86_64");
-    }
-
-    #[test]
-    fn selects_bundle_by_target() {
-        let manifest = RuntimeManifest {
-            bundles: vec![
-                RuntimeManifestBundle {
-                    target: "macos-aarch64".to_string(),
-                    url: "https://cdn.example.com/mac.zip".to_string(),
-                    sha256: Some("abc".to_string()),
-                    urls: None,
-                },
-                RuntimeManifestBundle {
-                    target: "windows-x86_64".to_string(),
-                    url: "https://cdn.example.com/win.zip".to_string(),
-                    sha256: None,
-                    urls: None,
-                },
-            ],
-        };
-
-        let bundle = select_manifest_bundle(&manifest, "windows-x86_64").expect("bundle should exist");
-        assert_eq!(bundle.url, "https://cdn.example.com/win.zip");
-    }
-
-    #[test]
-    fn collects_manifest_sources_with_dedup() {
-        let sources = collect_manifest_sources(
-            Some("https://a.example.com/manifest.json, https://b.example.com/manifest.json".to_string()),
-            Some(vec![
-                "https://b.example.com/manifest.json".to_string(),
-                "https://c.example.com/manifest.json".to_string(),
-            ]),
-        );
-        assert_eq!(
-            sources,
-            vec![
-                "https://a.example.com/manifest.json".to_string(),
-                "https://b.example.com/manifest.json".to_string(),
-                "https://c.example.com/manifest.json".to_string(),
-            ]
-        );
-    }
-
-    #[test]
-    fn resolves_bundle_download_urls() {
-        let bundle = RuntimeManifestBundle {
-            target: "macos-aarch64".to_string(),
-            url: "https://primary.example.com/runtime.zip".to_string(),
-            sha256: None,
-            urls: Some(vec![
-                "https://mirror1.example.com/runtime.zip".to_string(),
-                "https://mirror2.example.com/runtime.zip".to_string(),
-            ]),
-        };
-        let urls = resolve_bundle_download_urls(&bundle);
-        assert_eq!(
-            urls,
-            vec![
-                "https://primary.example.com/runtime.zip".to_string(),
-                "https://mirror1.example.com/runtime.zip".to_string(),
-                "https://mirror2.example.com/runtime.zip".to_string(),
-            ]
-        );
-    }
+    use super::{is_localhost_http_url, job_event_channel, validate_remote_url_scheme};
 
     #[test]
     fn validates_remote_url_scheme() {
@@ -1530,21 +3694,8 @@ mod tests {
     }
 
     #[test]
-    fn checks_bundle_checksum_presence() {
-        let with_checksum = RuntimeManifestBundle {
-            target: "linux-x86_64".to_string(),
-            url: "https://example.com/runtime.zip".to_string(),
-            sha256: Some("abc123".to_string()),
-            urls: None,
-        };
-        let without_checksum = RuntimeManifestBundle {
-            target: "linux-x86_64".to_string(),
-            url: "https://example.com/runtime.zip".to_string(),
-            sha256: Some("   ".to_string()),
-            urls: None,
-        };
-
-        assert!(bundle_has_checksum(&with_checksum));
-        assert!(!bundle_has_checksum(&without_checksum));
+    fn job_event_channel_only_uses_allowed_characters() {
+        assert_eq!(job_event_channel("job-1a2b"), "worker-event:job-1a2b");
+        assert_eq!(job_event_channel("job/1 招生.v2"), "worker-event:job/1____v2");
     }
 }
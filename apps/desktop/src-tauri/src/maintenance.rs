@@ -0,0 +1,199 @@
+use crate::worker_env::WorkerSettings;
+use chrono::{Local, SecondsFormat};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// 发送任务进行中被推迟的运行时 / worker 变更，空闲时（或维护时段内）再应用。
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PendingUpdate {
+    SetRuntimePython {
+        path: String,
+    },
+    ClearRuntimePython,
+    InstallRuntimeArchive {
+        archive_path: String,
+    },
+    AutoInstallRuntime {
+        manifest_url: Option<String>,
+        manifest_urls: Option<Vec<String>>,
+    },
+    AutoDetectRuntime,
+    WorkerSettings {
+        settings: WorkerSettings,
+    },
+}
+
+impl PendingUpdate {
+    /// 同一目标的变更只保留最后一次：运行时变更互相覆盖，worker 设置互相覆盖。
+    fn target(&self) -> &'static str {
+        match self {
+            PendingUpdate::WorkerSettings { .. } => "worker",
+            _ => "runtime",
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct QueuedUpdate {
+    #[serde(flatten)]
+    pub update: PendingUpdate,
+    pub queued_at: String,
+}
+
+#[derive(Deserialize, Serialize, Clone, Default)]
+pub struct UpdateQueue {
+    #[serde(default)]
+    pub updates: Vec<QueuedUpdate>,
+}
+
+impl UpdateQueue {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        if !path.exists() {
+            return Ok(UpdateQueue::default());
+        }
+        let text = fs::read_to_string(path).map_err(|err| format!("读取待应用更新失败: {err}"))?;
+        serde_json::from_str(&text).map_err(|err| format!("待应用更新格式错误: {err}"))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let text = serde_json::to_string_pretty(self).map_err(|err| err.to_string())?;
+        fs::write(path, text).map_err(|err| format!("写入待应用更新失败: {err}"))
+    }
+
+    pub fn push(&mut self, update: PendingUpdate) {
+        self.updates.retain(|queued| queued.update.target() != update.target());
+        self.updates.push(QueuedUpdate {
+            update,
+            queued_at: Local::now().to_rfc3339_opts(SecondsFormat::Secs, false),
+        });
+    }
+}
+
+/// 每天的维护时段（本地时间 `HH:MM`）；结束早于开始时表示跨越午夜。
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct MaintenanceWindow {
+    pub start: String,
+    pub end: String,
+}
+
+impl MaintenanceWindow {
+    pub fn validate(&self) -> Result<(), String> {
+        let start = parse_clock(&self.start)?;
+        if start == parse_clock(&self.end)? {
+            return Err("维护时段的开始与结束时间不能相同".to_string());
+        }
+        Ok(())
+    }
+
+    /// `minute` 为当天零点起的分钟数。
+    pub fn contains(&self, minute: u32) -> bool {
+        let (Ok(start), Ok(end)) = (parse_clock(&self.start), parse_clock(&self.end)) else {
+            return false;
+        };
+        if start < end {
+            (start..end).contains(&minute)
+        } else {
+            minute >= start || minute < end
+        }
+    }
+}
+
+/// 未配置维护时段时，任务结束后立即应用排队的更新。
+#[derive(Deserialize, Serialize, Clone, Default, PartialEq, Debug)]
+pub struct MaintenanceSettings {
+    #[serde(default)]
+    pub window: Option<MaintenanceWindow>,
+}
+
+impl MaintenanceSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        match &self.window {
+            Some(window) => window.validate(),
+            None => Ok(()),
+        }
+    }
+
+    pub fn allows(&self, minute: u32) -> bool {
+        self.window.as_ref().is_none_or(|window| window.contains(minute))
+    }
+}
+
+/// 提供给界面的待应用更新状态。
+#[derive(Serialize, Clone)]
+pub struct PendingUpdatesStatus {
+    pub updates: Vec<QueuedUpdate>,
+    pub maintenance: MaintenanceSettings,
+    pub job_running: bool,
+    /// 没有任务运行且处于维护时段内，下一轮检查就会应用。
+    pub due: bool,
+}
+
+fn parse_clock(value: &str) -> Result<u32, String> {
+    let invalid = || format!("维护时间格式应为 HH:MM: {value}");
+    let (hour, minute) = value.trim().split_once(':').ok_or_else(invalid)?;
+    let hour: u32 = hour.parse().map_err(|_| invalid())?;
+    let minute: u32 = minute.parse().map_err(|_| invalid())?;
+    if hour > 23 || minute > 59 {
+        return Err(invalid());
+    }
+    Ok(hour * 60 + minute)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MaintenanceSettings, MaintenanceWindow, PendingUpdate, UpdateQueue};
+    use crate::worker_env::WorkerSettings;
+
+    #[test]
+    fn later_updates_replace_queued_ones_for_the_same_target() {
+        let mut queue = UpdateQueue::default();
+        queue.push(PendingUpdate::SetRuntimePython {
+            path: "/opt/python3.11".to_string(),
+        });
+        queue.push(PendingUpdate::WorkerSettings {
+            settings: WorkerSettings::default(),
+        });
+        queue.push(PendingUpdate::AutoDetectRuntime);
+        let kinds: Vec<&PendingUpdate> = queue.updates.iter().map(|queued| &queued.update).collect();
+        assert_eq!(
+            kinds,
+            [
+                &PendingUpdate::WorkerSettings {
+                    settings: WorkerSettings::default()
+                },
+                &PendingUpdate::AutoDetectRuntime
+            ]
+        );
+        let encoded = serde_json::to_value(&queue.updates[1]).expect("encode");
+        assert_eq!(encoded["kind"], "auto_detect_runtime");
+    }
+
+    #[test]
+    fn checks_maintenance_window() {
+        let overnight = MaintenanceWindow {
+            start: "22:30".to_string(),
+            end: "06:00".to_string(),
+        };
+        assert!(overnight.validate().is_ok());
+        assert!(overnight.contains(23 * 60));
+        assert!(overnight.contains(5 * 60 + 59));
+        assert!(!overnight.contains(6 * 60));
+        assert!(!overnight.contains(12 * 60));
+        let daytime = MaintenanceWindow {
+            start: "12:00".to_string(),
+            end: "13:00".to_string(),
+        };
+        assert!(daytime.contains(12 * 60 + 30));
+        assert!(!daytime.contains(13 * 60));
+        assert!(MaintenanceSettings::default().allows(12 * 60));
+        for (start, end) in [("24:00", "01:00"), ("1200", "13:00"), ("08:00", "08:00")] {
+            let window = MaintenanceWindow {
+                start: start.to_string(),
+                end: end.to_string(),
+            };
+            assert!(window.validate().is_err(), "{start}-{end} should be rejected");
+        }
+    }
+}
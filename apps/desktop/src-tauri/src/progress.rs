@@ -0,0 +1,117 @@
+use chrono::{DateTime, Duration, Local, SecondsFormat, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::VecDeque;
+
+/// 发送速率只按最近这段时间计算，任务中途调整间隔或遇到限速后预计时间能较快跟上。
+const RATE_WINDOW_SECS: i64 = 600;
+/// 至少有这么多次发送才计算速率，避免第一封就给出离谱的预计时间。
+const MIN_RATE_SAMPLES: usize = 2;
+
+/// 附加在收件人结果事件（`progress` 字段）上的任务进度。
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct SendProgress {
+    pub sent: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    /// 已处理（成功、失败或跳过）的收件人数。
+    pub processed: usize,
+    pub total: Option<u64>,
+    /// 最近 10 分钟内每分钟发送（成功或失败）的邮件数；样本不足时为空。
+    pub per_minute: Option<f64>,
+    /// 预计完成时间（本地时间，RFC 3339）。
+    pub eta: Option<String>,
+}
+
+/// 按任务事件累计进度；两种发送引擎的事件都经由 `SendEventHooks` 传入。
+#[derive(Default)]
+pub struct ProgressTracker {
+    total: Option<u64>,
+    started_at: Option<DateTime<Utc>>,
+    sent: usize,
+    failed: usize,
+    skipped: usize,
+    /// 速率窗口内每次发送的时间；跳过的收件人不耗时，不计入速率。
+    recent: VecDeque<DateTime<Utc>>,
+}
+
+impl ProgressTracker {
+    /// 累计一条事件；收件人结果事件返回更新后的进度，其他事件返回 `None`。
+    pub fn observe(&mut self, event: &Value, now: DateTime<Utc>) -> Option<SendProgress> {
+        match event["type"].as_str()? {
+            "job_started" => {
+                *self = ProgressTracker {
+                    total: event["total"].as_u64(),
+                    started_at: Some(now),
+                    ..ProgressTracker::default()
+                };
+                return None;
+            }
+            "recipient_sent" => self.sent += 1,
+            "recipient_failed" => self.failed += 1,
+            "recipient_skipped" => self.skipped += 1,
+            _ => return None,
+        }
+        let started_at = *self.started_at.get_or_insert(now);
+        if event["type"] != "recipient_skipped" {
+            self.recent.push_back(now);
+        }
+        let window_start = now - Duration::seconds(RATE_WINDOW_SECS);
+        while self.recent.front().is_some_and(|at| *at < window_start) {
+            self.recent.pop_front();
+        }
+        let processed = self.sent + self.failed + self.skipped;
+        let elapsed_min = (now - started_at.max(window_start)).num_milliseconds() as f64 / 60_000.0;
+        let per_minute = (self.recent.len() >= MIN_RATE_SAMPLES && elapsed_min > 0.0)
+            .then(|| self.recent.len() as f64 / elapsed_min);
+        let remaining = self.total.map(|total| total.saturating_sub(processed as u64));
+        let eta = per_minute.zip(remaining).map(|(rate, remaining)| {
+            let at = now + Duration::milliseconds((remaining as f64 / rate * 60_000.0) as i64);
+            at.with_timezone(&Local).to_rfc3339_opts(SecondsFormat::Secs, false)
+        });
+        Some(SendProgress {
+            sent: self.sent,
+            failed: self.failed,
+            skipped: self.skipped,
+            processed,
+            total: self.total,
+            per_minute: per_minute.map(|rate| (rate * 10.0).round() / 10.0),
+            eta,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ProgressTracker;
+    use chrono::{DateTime, Duration, Utc};
+    use serde_json::json;
+
+    #[test]
+    fn reports_rate_and_eta() {
+        let start: DateTime<Utc> = "2024-03-01T09:00:00Z".parse().expect("start");
+        let mut tracker = ProgressTracker::default();
+        assert!(tracker.observe(&json!({ "type": "job_started", "total": 13 }), start).is_none());
+
+        let skipped = tracker.observe(&json!({ "type": "recipient_skipped" }), start).expect("skipped");
+        assert_eq!((skipped.processed, skipped.per_minute), (1, None));
+        let first = tracker.observe(&json!({ "type": "recipient_sent" }), start + Duration::seconds(30));
+        assert!(first.expect("first").eta.is_none());
+        tracker.observe(&json!({ "type": "recipient_failed" }), start + Duration::seconds(60));
+        let progress = tracker
+            .observe(&json!({ "type": "recipient_sent" }), start + Duration::seconds(90))
+            .expect("progress");
+        assert_eq!((progress.sent, progress.failed, progress.processed, progress.total), (2, 1, 4, Some(13)));
+        // 1.5 分钟发送 3 封，每分钟 2 封；剩余 9 封约需 4.5 分钟。
+        assert_eq!(progress.per_minute, Some(2.0));
+        let eta: DateTime<Utc> = progress.eta.expect("eta").parse().expect("rfc3339");
+        assert_eq!(eta, start + Duration::seconds(90 + 270));
+
+        // 速率只看最近 10 分钟。
+        let later = tracker
+            .observe(&json!({ "type": "recipient_sent" }), start + Duration::minutes(30))
+            .expect("later");
+        assert_eq!(later.per_minute, None);
+        assert!(tracker.observe(&json!({ "type": "recipient_retry" }), start).is_none());
+    }
+}
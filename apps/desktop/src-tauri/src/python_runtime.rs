@@ -0,0 +1,1782 @@
+use crate::job_registry::JobControl;
+use crate::maintenance::{MaintenanceSettings, PendingUpdate, PendingUpdatesStatus, UpdateQueue};
+use crate::runtime_probe::{probe_system_libraries, MissingSystemLibrary};
+use crate::uv_installer::{
+    copy_with_progress, extract_uv_binary, parse_sha256_file, uv_archive_name, uv_target_triple,
+    DEFAULT_UV_DOWNLOAD_BASE,
+};
+use crate::worker_env::{
+    ephemeral_uv_args, read_locked_requirements, stale_worker_envs, worker_env_key, WorkerCommandOverride,
+    WorkerSettings, WORKER_REQUIREMENTS_FILE,
+};
+use crate::{
+    advance_job_queue, cancel_running_job, emit_worker_event, is_remote_url, read_app_settings, resolve_data_file,
+    validate_remote_url_scheme, write_app_settings, SendEventHooks, WorkerState, APP_LOG_RELATIVE_PATH,
+};
+use bulk_email_core::message_builder::{validate_custom_headers, validate_inline_images, InlineImage};
+use bulk_email_core::net_policy::{http_client, NetworkPolicy};
+use bulk_email_core::smime::SmimeOptions;
+use bulk_email_core::worker_ipc::{spawn_worker, SpawnedWorker};
+use bulk_email_core::worker_protocol::{
+    forward_framed_events, read_stderr_lines, run_worker_request_via, send_first_request, worker_request, Framing,
+    JobExitSummary, RequestBody, ResidentWorker, WorkerRequest, WorkerRequestError,
+};
+use chrono::Timelike;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+#[cfg(unix)]
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus};
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+use walkdir::WalkDir;
+use zip::ZipArchive;
+
+const RUNTIME_PROGRESS_CHANNEL: &str = "runtime-progress";
+/// 优雅取消后等待 worker 退出的时间（足够完成一次 SMTP 超时），超时后强制结束进程。
+const GRACEFUL_CANCEL_TIMEOUT: Duration = Duration::from_secs(60);
+/// worker 的事件输出结束后等待进程退出的时间，超时后强制结束，结束事件不会无限期延迟。
+const WORKER_EXIT_GRACE: Duration = Duration::from_secs(10);
+const PENDING_UPDATES_CHANNEL: &str = "pending-updates";
+const RUNTIME_CONFIG_RELATIVE_PATH: &str = "runtime/python_runtime.json";
+const PENDING_UPDATES_RELATIVE_PATH: &str = "runtime/pending_updates.json";
+const PYTHON_MIN_MAJOR: u32 = 3;
+const PYTHON_MIN_MINOR: u32 = 9;
+
+#[tauri::command]
+pub fn get_worker_settings(app: AppHandle) -> Result<WorkerSettings, String> {
+    Ok(read_app_settings(&app)?.worker)
+}
+
+#[tauri::command]
+pub fn set_worker_settings(app: AppHandle, payload: WorkerSettings) -> Result<WorkerSettings, String> {
+    payload.validate()?;
+    if defer_while_busy(&app, PendingUpdate::WorkerSettings { settings: payload.clone() })? {
+        return Ok(read_app_settings(&app)?.worker);
+    }
+    let mut settings = read_app_settings(&app)?;
+    settings.worker = payload;
+    write_app_settings(&app, &settings)?;
+    Ok(settings.worker)
+}
+
+/// 用自定义命令启动 worker 并发送一条探测消息，确认解释器可用且能正常应答协议。
+#[tauri::command]
+pub fn test_worker_command(app: AppHandle, payload: WorkerCommandOverride) -> Result<String, String> {
+    payload.validate()?;
+    let worker_script = resolve_worker_script(&app)?;
+    let command = override_worker_command(&payload, &worker_script);
+    let settings = read_app_settings(&app)?.worker;
+    let ping = worker_request(RequestBody::Ping);
+    let response = run_worker_request_via(command, ping, settings.transport, settings.request_timeout())
+        .map_err(|err| err.to_string())?;
+    match response.get("type").and_then(Value::as_str) {
+        Some(_) => Ok("自定义 worker 命令可用".to_string()),
+        None => Err(format!("worker 响应格式异常: {response}")),
+    }
+}
+
+/// 预热一次性环境的 uv 缓存，避免首次发送时才下载依赖。
+#[tauri::command]
+pub fn warm_worker_cache(app: AppHandle) -> Result<String, String> {
+    let worker_script = resolve_worker_script(&app)?;
+    let project_root = worker_script
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| PathBuf::from("."));
+    let mut command = ephemeral_worker_command(&app, &project_root)?
+        .ok_or_else(|| "未找到 uv 或 worker 依赖清单，无法预热依赖缓存".to_string())?;
+    let output = command
+        .args(["-c", "import json"])
+        .output()
+        .map_err(|err| format!("启动 uv 失败: {err}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        return Err(format!("预热依赖缓存失败: {stderr}"));
+    }
+    Ok("worker 依赖缓存已就绪".to_string())
+}
+
+#[derive(Serialize, Default)]
+pub struct RuntimeStatus {
+    ready: bool,
+    source: String,
+    executable_path: Option<String>,
+    version: Option<String>,
+    message: String,
+    /// 安装后自检发现缺失的系统库（ssl / sqlite3 / lzma 等）。
+    missing_libraries: Vec<MissingSystemLibrary>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct RuntimeConfig {
+    python_path: Option<String>,
+    /// uv 发布包下载前缀（内网镜像）；为空时使用 GitHub Releases。
+    #[serde(default)]
+    uv_download_base: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+struct RuntimeManifest {
+    bundles: Vec<RuntimeManifestBundle>,
+}
+
+#[derive(Deserialize, Clone)]
+struct RuntimeManifestBundle {
+    target: String,
+    url: String,
+    sha256: Option<String>,
+    urls: Option<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+pub struct AutoInstallPayload {
+    manifest_url: Option<String>,
+    manifest_urls: Option<Vec<String>>,
+}
+
+#[tauri::command]
+pub fn get_runtime_status(app: AppHandle) -> Result<RuntimeStatus, String> {
+    Ok(resolve_runtime_status(&app))
+}
+
+#[tauri::command]
+pub fn set_runtime_python(app: AppHandle, path: String) -> Result<RuntimeStatus, String> {
+    let candidate = PathBuf::from(path.trim());
+    if !candidate.exists() {
+        return Err("指定的 Python 可执行文件不存在".to_string());
+    }
+    if defer_while_busy(&app, PendingUpdate::SetRuntimePython { path: path.clone() })? {
+        return Ok(queued_runtime_status(&app));
+    }
+
+    let version = probe_python_version(&candidate)
+        .ok_or_else(|| "指定路径不是可用的 Python 运行时".to_string())?;
+    if !is_supported_python_version(&version) {
+        return Err(format!(
+            "Python 版本过低，当前为 `{version}`，要求 >= {}.{}",
+            PYTHON_MIN_MAJOR, PYTHON_MIN_MINOR
+        ));
+    }
+
+    let mut config = read_runtime_config(&app)?;
+    config.python_path = Some(candidate.to_string_lossy().to_string());
+    write_runtime_config(&app, &config)?;
+
+    Ok(with_library_probe(
+        RuntimeStatus {
+            ready: true,
+            source: "configured".to_string(),
+            executable_path: Some(candidate.to_string_lossy().to_string()),
+            version: Some(version),
+            message: "Python 运行时已保存".to_string(),
+            missing_libraries: Vec::new(),
+        },
+        &candidate,
+    ))
+}
+
+/// 对当前 Python 运行时重新做一次系统库自检。
+#[tauri::command]
+pub fn probe_runtime_libraries(app: AppHandle) -> Result<Vec<MissingSystemLibrary>, String> {
+    let runtime = resolve_python_runtime(&app).ok_or_else(|| "未检测到 Python 运行时".to_string())?;
+    let os_release = fs::read_to_string("/etc/os-release").ok();
+    probe_system_libraries(&runtime.executable_path, os_release.as_deref())
+}
+
+#[tauri::command]
+pub fn clear_runtime_python(app: AppHandle) -> Result<RuntimeStatus, String> {
+    if defer_while_busy(&app, PendingUpdate::ClearRuntimePython)? {
+        return Ok(queued_runtime_status(&app));
+    }
+    let mut config = read_runtime_config(&app)?;
+    config.python_path = None;
+    write_runtime_config(&app, &config)?;
+    Ok(resolve_runtime_status(&app))
+}
+
+/// 清理旧版本应用遗留的 worker 虚拟环境，返回已删除的目录。
+#[tauri::command]
+pub fn cleanup_worker_envs(app: AppHandle) -> Result<Vec<String>, String> {
+    let worker_script = resolve_worker_script(&app)?;
+    let project_root = worker_script
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| PathBuf::from("."));
+    let current_key = current_worker_env_key(&app, &project_root);
+    let mut removed = Vec::new();
+    for path in stale_worker_envs(&worker_envs_root(&app)?, &current_key)? {
+        fs::remove_dir_all(&path).map_err(|err| format!("删除 worker 环境失败 {}: {err}", path.display()))?;
+        removed.push(path.to_string_lossy().to_string());
+    }
+    Ok(removed)
+}
+
+/// 设置 uv 发布包的下载前缀（内网镜像），传空恢复默认。
+#[tauri::command]
+pub fn set_uv_download_base(app: AppHandle, url: Option<String>) -> Result<Option<String>, String> {
+    let url = url
+        .map(|url| url.trim().trim_end_matches('/').to_string())
+        .filter(|url| !url.is_empty());
+    if let Some(url) = &url {
+        validate_remote_url_scheme(url, "uv 下载地址")?;
+    }
+    let mut config = read_runtime_config(&app)?;
+    config.uv_download_base = url;
+    write_runtime_config(&app, &config)?;
+    Ok(config.uv_download_base)
+}
+
+#[tauri::command]
+pub fn install_runtime_from_archive(app: AppHandle, archive_path: String) -> Result<RuntimeStatus, String> {
+    let source_path = PathBuf::from(archive_path.trim());
+    if !source_path.exists() {
+        return Err("运行时压缩包不存在".to_string());
+    }
+    if defer_while_busy(&app, PendingUpdate::InstallRuntimeArchive { archive_path: archive_path.clone() })? {
+        return Ok(queued_runtime_status(&app));
+    }
+
+    install_runtime_from_archive_internal(&app, &source_path, "archive")
+}
+
+#[tauri::command]
+pub fn auto_install_runtime(
+    app: AppHandle,
+    payload: Option<AutoInstallPayload>,
+) -> Result<RuntimeStatus, String> {
+    let payload = payload.unwrap_or(AutoInstallPayload {
+        manifest_url: None,
+        manifest_urls: None,
+    });
+    let deferred = PendingUpdate::AutoInstallRuntime {
+        manifest_url: payload.manifest_url.clone(),
+        manifest_urls: payload.manifest_urls.clone(),
+    };
+    let manifest_sources = collect_manifest_sources(payload.manifest_url, payload.manifest_urls);
+    if manifest_sources.is_empty() {
+        return Err("未配置 runtime manifest 地址，请先填写 manifest URL".to_string());
+    }
+    if defer_while_busy(&app, deferred)? {
+        return Ok(queued_runtime_status(&app));
+    }
+    let policy = read_app_settings(&app)?.network;
+
+    let target = runtime_target_key(std::env::consts::OS, std::env::consts::ARCH);
+    let mut manifest_errors: Vec<String> = Vec::new();
+    let mut selected_bundle: Option<RuntimeManifestBundle> = None;
+
+    for source in &manifest_sources {
+        if let Err(err) = validate_remote_url_scheme(source, "manifest") {
+            manifest_errors.push(err);
+            continue;
+        }
+        match load_runtime_manifest(&policy, source) {
+            Ok(manifest) => {
+                if let Some(bundle) = select_manifest_bundle(&manifest, &target) {
+                    selected_bundle = Some(bundle.clone());
+                    break;
+                }
+                manifest_errors.push(format!("manifest `{source}` 未包含平台 `{target}`"));
+            }
+            Err(err) => {
+                manifest_errors.push(format!("manifest `{source}` 加载失败：{err}"));
+            }
+        }
+    }
+
+    let bundle = selected_bundle.ok_or_else(|| format!("自动安装失败：{}", manifest_errors.join(" | ")))?;
+
+    let runtime_root = runtime_root_dir(&app)?;
+    let download_dir = runtime_root.join("downloads");
+    fs::create_dir_all(&download_dir).map_err(|err| format!("创建下载目录失败: {err}"))?;
+    let archive_path = download_dir.join(format!("python-runtime-{target}.zip"));
+    let download_urls = resolve_bundle_download_urls(&bundle);
+    for url in &download_urls {
+        validate_remote_url_scheme(url, "runtime 包下载地址")?;
+    }
+    if download_urls.iter().any(|url| is_remote_url(url)) && !bundle_has_checksum(&bundle) {
+        return Err("远程 runtime 包必须提供 sha256 校验值".to_string());
+    }
+    let mut download_errors: Vec<String> = Vec::new();
+    let mut downloaded = false;
+    for url in download_urls {
+        match download_bundle_to_path(&policy, &url, &archive_path) {
+            Ok(_) => {
+                downloaded = true;
+                break;
+            }
+            Err(err) => download_errors.push(format!("`{url}` 下载失败：{err}")),
+        }
+    }
+    if !downloaded {
+        return Err(format!("runtime 包下载失败：{}", download_errors.join(" | ")));
+    }
+
+    if let Some(checksum) = &bundle.sha256 {
+        if let Err(err) = verify_sha256_checksum(&archive_path, checksum) {
+            let _ = fs::remove_file(&archive_path);
+            return Err(err);
+        }
+    }
+
+    install_runtime_from_archive_internal(&app, &archive_path, "download")
+}
+
+// ── uv / Python 自动安装常量 ───────────────────────────────────────────────
+const UV_INSTALL_RETRIES: u32 = 3;
+
+const UV_RETRY_SLEEP_SECS: u64 = 4;
+
+/// 自动探测并配置 Python 运行时：
+///   1. 查找已有 uv → 查找 / 安装 Python 3.11
+///   2. uv 不存在 → 自动安装 uv（带重试），再执行 1
+///   3. 全部失败 → 回退系统 python3 / python
+#[tauri::command]
+pub fn auto_detect_runtime(app: AppHandle) -> Result<RuntimeStatus, String> {
+    if defer_while_busy(&app, PendingUpdate::AutoDetectRuntime)? {
+        return Ok(queued_runtime_status(&app));
+    }
+    let mut uv_install_err: Option<String> = None;
+    // 仅本地模式下不联网安装 uv / Python，只使用本机已有的运行时。
+    let allows_downloads = read_app_settings(&app)?.network.allows_downloads();
+
+    let uv_opt = find_uv_executable(&app).or_else(|| {
+        if !allows_downloads {
+            return None;
+        }
+        match install_uv(&app) {
+            Ok(p) => Some(p),
+            Err(e) => { uv_install_err = Some(e); None }
+        }
+    });
+
+    if let Some(uv) = uv_opt {
+        // 1a. 查找 uv 已管理的 Python（任意 >=3.9）
+        if let Ok(out) = Command::new(&uv).args(["python", "find"]).output() {
+            if out.status.success() {
+                let p = String::from_utf8_lossy(&out.stdout).trim().to_string();
+                if !p.is_empty() {
+                    let c = PathBuf::from(&p);
+                    if let Some(ver) = probe_python_version(&c) {
+                        if is_supported_python_version(&ver) {
+                            return save_configured_runtime(&app, c, ver);
+                        }
+                    }
+                }
+            }
+        }
+
+        // 1b. 通过 uv 安装 Python 3.11，最多重试 UV_INSTALL_RETRIES 次
+        let mut py_ok = false;
+        for attempt in 1..=UV_INSTALL_RETRIES {
+            if !allows_downloads {
+                break;
+            }
+            if let Ok(out) = Command::new(&uv).args(["python", "install", "3.11"]).output() {
+                if out.status.success() { py_ok = true; break; }
+            }
+            if attempt < UV_INSTALL_RETRIES {
+                std::thread::sleep(std::time::Duration::from_secs(UV_RETRY_SLEEP_SECS));
+            }
+        }
+        if py_ok {
+            if let Ok(out) = Command::new(&uv).args(["python", "find", "3.11"]).output() {
+                if out.status.success() {
+                    let p = String::from_utf8_lossy(&out.stdout).trim().to_string();
+                    if !p.is_empty() {
+                        let c = PathBuf::from(&p);
+                        if let Some(ver) = probe_python_version(&c) {
+                            if is_supported_python_version(&ver) {
+                                return save_configured_runtime(&app, c, ver);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        // uv python install 失败（如网络差），继续回退
+    }
+
+    // 2. 回退到系统 Python
+    #[cfg(target_os = "windows")]
+    let candidates = ["python", "python3"];
+    #[cfg(not(target_os = "windows"))]
+    let candidates = ["python3", "python"];
+
+    for name in candidates {
+        let exe = PathBuf::from(name);
+        if let Some(ver) = probe_python_version(&exe) {
+            if is_supported_python_version(&ver) {
+                return save_configured_runtime(&app, exe, ver);
+            }
+        }
+    }
+
+    // 3. 全部失败：给出有针对性的错误提示
+    let base = "未找到可用的 Python 运行时（需 ≥3.9）。";
+    let hint = "https://docs.astral.sh/uv/getting-started/installation/";
+    if let Some(uv_err) = uv_install_err {
+        Err(format!(
+            "{base}\n\n安装 uv 失败：{uv_err}\n\n建议：\n① 检查网络后点击「自动安装 Python」重试\n② 或访问 {hint} 手动安装 uv\n③ 或点击「选择 Python 文件」指定已有 Python"
+        ))
+    } else {
+        Err(format!(
+            "{base}\n\n建议：\n① 检查网络后点击「自动安装 Python」重试\n② 或访问 {hint} 安装 uv\n③ 或点击「选择 Python 文件」指定已有 Python"
+        ))
+    }
+}
+
+#[tauri::command]
+pub fn get_pending_updates(app: AppHandle) -> Result<PendingUpdatesStatus, String> {
+    pending_updates_status(&app)
+}
+
+/// 设置维护时段；未设置时排队的更新在任务结束后立即应用。
+#[tauri::command]
+pub fn set_maintenance_settings(app: AppHandle, payload: MaintenanceSettings) -> Result<MaintenanceSettings, String> {
+    payload.validate()?;
+    let mut settings = read_app_settings(&app)?;
+    settings.maintenance = payload;
+    write_app_settings(&app, &settings)?;
+    Ok(settings.maintenance)
+}
+
+/// 立即应用排队的更新（忽略维护时段，但仍要求没有任务在运行）。
+#[tauri::command]
+pub fn apply_pending_updates(app: AppHandle) -> Result<PendingUpdatesStatus, String> {
+    apply_queued_updates(&app)?;
+    pending_updates_status(&app)
+}
+
+#[tauri::command]
+pub fn discard_pending_updates(app: AppHandle) -> Result<PendingUpdatesStatus, String> {
+    UpdateQueue::default().save(&pending_updates_path(&app)?)?;
+    pending_updates_status(&app)
+}
+
+/// 查找已安装的 uv 可执行文件（PATH + 平台默认路径 + 应用自行下载的位置）。
+fn find_uv_executable(app: &AppHandle) -> Option<PathBuf> {
+    // 优先 PATH
+    if Command::new("uv").arg("--version").output().map(|o| o.status.success()).unwrap_or(false) {
+        return Some(PathBuf::from("uv"));
+    }
+    uv_default_paths().into_iter().chain(managed_uv_path(app)).find(|path| {
+        path.exists()
+            && Command::new(path).arg("--version").output().map(|o| o.status.success()).unwrap_or(false)
+    })
+}
+
+/// 平台相关的 uv 默认安装位置。
+fn uv_default_paths() -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = Vec::new();
+    #[cfg(target_os = "windows")]
+    {
+        for var in ["USERPROFILE", "APPDATA", "LOCALAPPDATA"] {
+            if let Ok(base) = std::env::var(var) {
+                let b = PathBuf::from(base);
+                paths.push(b.join(".cargo").join("bin").join("uv.exe"));
+                paths.push(b.join(".local").join("bin").join("uv.exe"));
+                paths.push(b.join("uv").join("bin").join("uv.exe"));
+            }
+        }
+    }
+    #[cfg(not(target_os = "windows"))]
+    {
+        if let Ok(home) = std::env::var("HOME") {
+            let h = PathBuf::from(home);
+            paths.push(h.join(".local").join("bin").join("uv"));
+            paths.push(h.join(".cargo").join("bin").join("uv"));
+        }
+    }
+    paths
+}
+
+/// 通过应用的 HTTP 客户端（遵循代理与网络策略）下载 uv 发布包，校验 sha256 后解压到运行时目录。
+/// 下载地址可在运行时配置中改为内网镜像；过程通过 `runtime-progress` 事件报告进度。
+fn install_uv(app: &AppHandle) -> Result<PathBuf, String> {
+    let policy = read_app_settings(app)?.network;
+    let base = read_runtime_config(app)?
+        .uv_download_base
+        .map(|base| base.trim().trim_end_matches('/').to_string())
+        .filter(|base| !base.is_empty())
+        .unwrap_or_else(|| DEFAULT_UV_DOWNLOAD_BASE.to_string());
+    validate_remote_url_scheme(&base, "uv 下载地址")?;
+    let triple = uv_target_triple(std::env::consts::OS, std::env::consts::ARCH).ok_or_else(|| {
+        format!(
+            "当前平台 {}-{} 没有可用的 uv 发布包，请手动安装：https://docs.astral.sh/uv/",
+            std::env::consts::OS,
+            std::env::consts::ARCH
+        )
+    })?;
+    let archive_name = uv_archive_name(triple);
+    let url = format!("{base}/{archive_name}");
+    let uv_dir = runtime_root_dir(app)?.join("uv");
+    fs::create_dir_all(&uv_dir).map_err(|err| format!("创建 uv 目录失败: {err}"))?;
+    let archive_path = uv_dir.join(&archive_name);
+
+    let mut last_err = String::new();
+    for attempt in 1..=UV_INSTALL_RETRIES {
+        match download_uv_archive(app, &policy, &url, &archive_path) {
+            Ok(()) => {
+                last_err.clear();
+                break;
+            }
+            Err(err) => last_err = format!("第 {attempt} 次下载失败：{err}"),
+        }
+        if attempt < UV_INSTALL_RETRIES {
+            std::thread::sleep(std::time::Duration::from_secs(UV_RETRY_SLEEP_SECS));
+        }
+    }
+    if !last_err.is_empty() {
+        return Err(format!(
+            "{last_err}（共重试 {UV_INSTALL_RETRIES} 次）。\n请检查网络或代理设置后重试，或手动安装：https://docs.astral.sh/uv/"
+        ));
+    }
+
+    emit_runtime_progress(app, "verify", 0, None, "正在校验 uv 安装包");
+    let checksum_url = format!("{url}.sha256");
+    let checksum_text = http_client(&policy, &checksum_url, "uv 校验文件下载")?
+        .get(&checksum_url)
+        .send()
+        .and_then(|response| response.error_for_status())
+        .and_then(|response| response.text())
+        .map_err(|err| format!("下载 uv 校验文件失败: {err}"))?;
+    let checksum = parse_sha256_file(&checksum_text).ok_or_else(|| "uv 校验文件格式无效".to_string())?;
+    if let Err(err) = verify_sha256_checksum(&archive_path, &checksum) {
+        let _ = fs::remove_file(&archive_path);
+        return Err(err);
+    }
+
+    emit_runtime_progress(app, "extract", 0, None, "正在解压 uv");
+    let binary = extract_uv_binary(&archive_path, &uv_dir.join("bin"))?;
+    let _ = fs::remove_file(&archive_path);
+    emit_runtime_progress(app, "done", 0, None, "uv 安装完成");
+    Ok(binary)
+}
+
+fn download_uv_archive(app: &AppHandle, policy: &NetworkPolicy, url: &str, destination: &Path) -> Result<(), String> {
+    let mut response = http_client(policy, url, "uv 下载")?
+        .get(url)
+        .send()
+        .map_err(|err| format!("下载 uv 失败: {err}"))?
+        .error_for_status()
+        .map_err(|err| format!("uv 下载响应异常: {err}"))?;
+    let total_bytes = response.content_length();
+    copy_with_progress(&mut response, destination, total_bytes, &mut |downloaded, total| {
+        emit_runtime_progress(app, "download", downloaded, total, "正在下载 uv");
+    })?;
+    Ok(())
+}
+
+fn emit_runtime_progress(app: &AppHandle, stage: &str, downloaded_bytes: u64, total_bytes: Option<u64>, message: &str) {
+    let _ = app.emit(
+        RUNTIME_PROGRESS_CHANNEL,
+        json!({
+            "stage": stage,
+            "downloaded_bytes": downloaded_bytes,
+            "total_bytes": total_bytes,
+            "message": message,
+        }),
+    );
+}
+
+/// 应用自行下载的 uv 所在位置。
+fn managed_uv_path(app: &AppHandle) -> Option<PathBuf> {
+    let name = if cfg!(target_os = "windows") { "uv.exe" } else { "uv" };
+    runtime_root_dir(app).ok().map(|root| root.join("uv").join("bin").join(name))
+}
+
+fn save_configured_runtime(app: &AppHandle, path: PathBuf, version: String) -> Result<RuntimeStatus, String> {
+    let mut config = read_runtime_config(app)?;
+    config.python_path = Some(path.to_string_lossy().to_string());
+    write_runtime_config(app, &config)?;
+    Ok(with_library_probe(
+        RuntimeStatus {
+            ready: true,
+            source: "configured".to_string(),
+            executable_path: Some(path.to_string_lossy().to_string()),
+            version: Some(version),
+            message: "Python 运行时已就绪".to_string(),
+            missing_libraries: Vec::new(),
+        },
+        &path,
+    ))
+}
+
+fn install_runtime_from_archive_internal(
+    app: &AppHandle,
+    source_path: &Path,
+    source_label: &str,
+) -> Result<RuntimeStatus, String> {
+    if !source_path.exists() {
+        return Err("运行时压缩包不存在".to_string());
+    }
+
+    let runtime_root = runtime_root_dir(app)?;
+    fs::create_dir_all(&runtime_root).map_err(|err| format!("创建 runtime 根目录失败: {err}"))?;
+    let staging_dir = runtime_root.join("python_staging");
+    let active_dir = runtime_root.join("python");
+
+    extract_zip_archive(source_path, &staging_dir)?;
+
+    let staging_python = find_python_executable(&staging_dir)
+        .ok_or_else(|| "压缩包中未找到可用 Python 可执行文件".to_string())?;
+    let version = probe_python_version(&staging_python)
+        .ok_or_else(|| "解压后的 Python 运行时不可执行".to_string())?;
+    if !is_supported_python_version(&version) {
+        return Err(format!(
+            "压缩包中的 Python 版本过低，当前为 `{version}`，要求 >= {}.{}",
+            PYTHON_MIN_MAJOR, PYTHON_MIN_MINOR
+        ));
+    }
+
+    let relative_python = staging_python
+        .strip_prefix(&staging_dir)
+        .map_err(|err| format!("运行时路径解析失败: {err}"))?
+        .to_path_buf();
+
+    if active_dir.exists() {
+        fs::remove_dir_all(&active_dir).map_err(|err| format!("清理旧运行时目录失败: {err}"))?;
+    }
+    fs::rename(&staging_dir, &active_dir).map_err(|err| format!("启用新运行时失败: {err}"))?;
+    let active_python = active_dir.join(relative_python);
+
+    let mut config = read_runtime_config(app)?;
+    config.python_path = Some(active_python.to_string_lossy().to_string());
+    write_runtime_config(app, &config)?;
+
+    Ok(with_library_probe(
+        RuntimeStatus {
+            ready: true,
+            source: source_label.to_string(),
+            executable_path: Some(active_python.to_string_lossy().to_string()),
+            version: Some(version),
+            message: "运行时导入成功".to_string(),
+            missing_libraries: Vec::new(),
+        },
+        &active_python,
+    ))
+}
+
+/// 运行时就绪后做一次系统库自检；缺失时在状态消息中列出，而不是等到 worker 崩溃。
+fn with_library_probe(mut status: RuntimeStatus, python: &Path) -> RuntimeStatus {
+    let os_release = fs::read_to_string("/etc/os-release").ok();
+    match probe_system_libraries(python, os_release.as_deref()) {
+        Ok(missing) if !missing.is_empty() => {
+            let names = missing
+                .iter()
+                .map(|item| format!("{}（{}）", item.library, item.module))
+                .collect::<Vec<String>>()
+                .join("、");
+            status.message = format!("{}，但缺少系统库：{names}", status.message);
+            status.missing_libraries = missing;
+        }
+        Ok(_) => {}
+        Err(err) => status.message = format!("{}；{err}", status.message),
+    }
+    status
+}
+
+/// 转发任务 worker 的事件，输出结束后等待进程退出，发出带退出码与耗时的结束事件（见 `JobExitSummary`）。
+fn spawn_event_forwarder(
+    app: AppHandle,
+    job_id: String,
+    events: impl std::io::Read + Send + 'static,
+    framing: Framing,
+    hooks: SendEventHooks,
+    started: Instant,
+) {
+    std::thread::spawn(move || {
+        let mut exit = JobExitSummary::default();
+        forward_framed_events(events, framing, |event| {
+            let (payload, ends_job) = match event {
+                Ok(message) => {
+                    let mut payload = message.raw;
+                    hooks.observe(&payload);
+                    hooks.attach_progress(&mut payload);
+                    (payload, message.event.ends_job())
+                }
+                Err(error) => (error, true),
+            };
+            // 任务结束后关闭 worker 的请求通道，worker 读到 EOF 后退出。
+            if ends_job {
+                close_worker_requests(&app, &job_id);
+            }
+            if exit.observe(&payload) {
+                emit_worker_event(&app, payload);
+            }
+        });
+        let status = wait_worker_exit(&app, &job_id);
+        let event = exit.exit_event(&job_id, status.and_then(|status| status.code()), started.elapsed());
+        if event["type"] == "job_failed" {
+            hooks.observe(&event);
+        }
+        emit_worker_event(&app, event);
+        // worker 退出后释放 hooks 中的发送锁，再启动下一个排队的任务。
+        drop(hooks);
+        advance_job_queue(&app, &job_id);
+    });
+}
+
+/// 事件输出结束后等待任务的 worker 退出并回收进程；超过 `WORKER_EXIT_GRACE` 仍未退出时强制结束。
+/// 只在查询时短暂持有任务注册表的锁，取消线程仍可同时结束进程。
+fn wait_worker_exit(app: &AppHandle, job_id: &str) -> Option<ExitStatus> {
+    let started = Instant::now();
+    loop {
+        {
+            let state = app.state::<WorkerState>();
+            let mut jobs = state.jobs.lock().ok()?;
+            let Some(JobControl::Worker { child, .. }) = jobs.get_mut(job_id) else {
+                return None;
+            };
+            match child.try_wait() {
+                Ok(Some(status)) => return Some(status),
+                Ok(None) if started.elapsed() >= WORKER_EXIT_GRACE => {
+                    let _ = child.kill();
+                    return child.wait().ok();
+                }
+                Ok(None) => {}
+                Err(_) => return None,
+            }
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}
+
+/// Python worker 不支持的发送选项，遇到时提示改用原生发送引擎。
+pub fn reject_native_only_options(payload: &Value, local_mta: bool) -> Result<(), String> {
+    if payload.get("rotation").is_some_and(|value| !value.is_null()) {
+        return Err("发件账号轮换仅支持原生发送引擎（engine: native）".to_string());
+    }
+    if local_mta {
+        return Err("本机 MTA 投递仅支持原生发送引擎（engine: native）".to_string());
+    }
+    if payload.get("readback").is_some_and(|value| !value.is_null()) {
+        return Err("IMAP 回读验证仅支持原生发送引擎（engine: native）".to_string());
+    }
+    if payload.get("recipient_source").is_some_and(|value| !value.is_null()) {
+        return Err("流式读取收件人仅支持原生发送引擎（engine: native）".to_string());
+    }
+    if payload.get("canary").is_some_and(|value| !value.is_null()) {
+        return Err("金丝雀收件人仅支持原生发送引擎（engine: native）".to_string());
+    }
+    if payload.pointer("/smtp/auth_mechanism").and_then(Value::as_str) == Some("ntlm") {
+        return Err("NTLM 认证仅支持原生发送引擎（engine: native）".to_string());
+    }
+    let smime = payload
+        .get("smime")
+        .and_then(|value| serde_json::from_value::<SmimeOptions>(value.clone()).ok())
+        .unwrap_or_default();
+    if smime.enabled() {
+        return Err("S/MIME 签名与加密仅支持原生发送引擎（engine: native）".to_string());
+    }
+    if payload.pointer("/oversize/action").and_then(Value::as_str) == Some("link") {
+        return Err("超限附件改为下载链接仅支持原生发送引擎（engine: native）".to_string());
+    }
+    if payload.pointer("/options/ascii_attachment_names").and_then(Value::as_bool) == Some(true) {
+        return Err("ASCII 附件文件名仅支持原生发送引擎（engine: native）".to_string());
+    }
+    if payload.get("batch_bcc").is_some_and(|value| !value.is_null()) {
+        return Err("批量密送模式仅支持原生发送引擎（engine: native）".to_string());
+    }
+    if payload.pointer("/render/parallel").and_then(Value::as_bool) == Some(true) {
+        return Err("并行预渲染仅支持原生发送引擎（engine: native）".to_string());
+    }
+    if payload.get("concurrency").is_some_and(|value| !value.is_null()) {
+        return Err("多连接并发发送仅支持原生发送引擎（engine: native）".to_string());
+    }
+    if let Some(images) = payload.get("inline_images").filter(|value| !value.is_null()) {
+        let images: Vec<InlineImage> =
+            serde_json::from_value(images.clone()).map_err(|err| format!("内嵌图片参数格式错误: {err}"))?;
+        validate_inline_images(&images)?;
+    }
+    if let Some(headers) = payload.get("headers").filter(|value| !value.is_null()) {
+        let headers: BTreeMap<String, String> =
+            serde_json::from_value(headers.clone()).map_err(|err| format!("自定义邮件头格式错误: {err}"))?;
+        validate_custom_headers(&headers)?;
+    }
+    Ok(())
+}
+
+/// 启动 Python worker 执行发送任务并登记到任务注册表，事件经 `SendEventHooks` 转发到 `worker-event` 通道。
+pub fn spawn_worker_job(app: AppHandle, payload: Value, hooks: SendEventHooks) -> Result<(), String> {
+    let started = Instant::now();
+    let job_id = payload["job_id"].as_str().unwrap_or_default().to_string();
+    let transport = read_app_settings(&app)?.worker.transport;
+    let mut worker = spawn_worker(worker_command(&app)?, transport)?;
+    for (stream, reader) in std::mem::take(&mut worker.diagnostics) {
+        spawn_diagnostic_forwarder(app.clone(), job_id.clone(), stream, reader);
+    }
+
+    // 握手确认 worker 与应用协议兼容后才写入发送请求；不兼容时提示更新应用或 worker。
+    let request = worker_request(RequestBody::StartSend(payload));
+    let hello = match send_first_request(&mut worker, &request) {
+        Ok(hello) => hello,
+        Err(err) => {
+            let _ = worker.child.kill();
+            let _ = worker.child.wait();
+            return Err(err);
+        }
+    };
+    let framing = hello.framing;
+    let SpawnedWorker {
+        child,
+        requests,
+        events,
+        ..
+    } = worker;
+
+    // 保持请求通道打开以便发送控制消息；任务结束时由事件转发线程关闭，worker 随后退出。
+    // 先启动事件转发再转交启动期间收到的取消请求，取消后 worker 输出的 `job_cancelled` 才有人读取。
+    let control = JobControl::Worker {
+        child,
+        requests: Some(requests),
+        hello,
+    };
+    let cancel = app
+        .state::<WorkerState>()
+        .jobs
+        .lock()
+        .map_err(|_| "failed to acquire job registry lock".to_string())?
+        .insert(&job_id, control);
+    spawn_event_forwarder(app.clone(), job_id.clone(), events, framing, hooks, started);
+    if let Some(force) = cancel {
+        cancel_running_job(&app, &app.state::<WorkerState>(), &job_id, force)?;
+    }
+    Ok(())
+}
+
+/// 打包后的应用没有终端，worker 的诊断输出（标准错误中的 Python 异常堆栈，套接字模式下还有标准输出里的调试信息）
+/// 逐行以 `worker_diagnostic` 事件转发给界面，同时追加到应用日志，便于事后排查。
+fn spawn_diagnostic_forwarder(
+    app: AppHandle,
+    job_id: String,
+    stream: &'static str,
+    reader: impl std::io::Read + Send + 'static,
+) {
+    std::thread::spawn(move || {
+        let log_path = resolve_data_file(&app, APP_LOG_RELATIVE_PATH).ok();
+        read_stderr_lines(reader, |line| {
+            if let Some(path) = &log_path {
+                append_worker_log(path, &job_id, &line);
+            }
+            let event = json!({ "type": "worker_diagnostic", "job_id": job_id, "stream": stream, "line": line });
+            emit_worker_event(&app, event);
+        });
+    });
+}
+
+fn append_worker_log(path: &Path, job_id: &str, line: &str) {
+    let Ok(mut file) = fs::OpenOptions::new().create(true).append(true).open(path) else {
+        return;
+    };
+    let now = chrono::Local::now().format("%Y-%m-%d %H:%M:%S");
+    let _ = writeln!(file, "[{now}] [worker {job_id}] {line}");
+}
+
+fn close_worker_requests(app: &AppHandle, job_id: &str) {
+    if let Ok(mut jobs) = app.state::<WorkerState>().jobs.lock() {
+        if let Some(JobControl::Worker { requests, .. }) = jobs.get_mut(job_id) {
+            requests.take();
+        }
+    }
+}
+
+/// 向运行中任务的 worker 写一条控制消息（取消、暂停、继续、限速）。`start_send` 之后 worker 的请求通道保持打开，
+/// worker 在发送间隙处理这些消息。任务不是由 worker 执行或请求通道已关闭（任务已经结束）时返回 `false`。
+pub fn send_worker_control(app: &AppHandle, job_id: &str, body: RequestBody) -> Result<bool, String> {
+    let state = app.state::<WorkerState>();
+    let mut jobs = state
+        .jobs
+        .lock()
+        .map_err(|_| "failed to acquire job registry lock".to_string())?;
+    let Some(JobControl::Worker {
+        requests: Some(requests),
+        hello,
+        ..
+    }) = jobs.get_mut(job_id)
+    else {
+        return Ok(false);
+    };
+    hello.check_supports(&body)?;
+    hello
+        .framing
+        .write_message(requests, &worker_request(body).to_string())
+        .map_err(|err| format!("failed to write worker request: {err}"))?;
+    Ok(true)
+}
+
+/// 请求任务的 worker 在当前邮件发送完成、发送记录写入后停止（worker 随后输出 `job_cancelled`）。
+/// 超过 `GRACEFUL_CANCEL_TIMEOUT` 仍未退出时强制结束进程，并以 `error` 事件通知界面。
+pub fn request_worker_cancel(app: &AppHandle, job_id: &str) -> Result<(), String> {
+    if !send_worker_control(app, job_id, RequestBody::Cancel(json!({})))? {
+        return Ok(());
+    }
+    let app = app.clone();
+    let job_id = job_id.to_string();
+    std::thread::spawn(move || {
+        let started = std::time::Instant::now();
+        loop {
+            std::thread::sleep(Duration::from_secs(1));
+            let state = app.state::<WorkerState>();
+            let Ok(mut jobs) = state.jobs.lock() else {
+                return;
+            };
+            let Some(JobControl::Worker { child, .. }) = jobs.get_mut(&job_id) else {
+                return;
+            };
+            if !matches!(child.try_wait(), Ok(None)) {
+                return;
+            }
+            if started.elapsed() >= GRACEFUL_CANCEL_TIMEOUT {
+                let _ = child.kill();
+                let error = format!("worker 未在 {} 秒内停止，已强制结束", GRACEFUL_CANCEL_TIMEOUT.as_secs());
+                emit_worker_event(&app, json!({ "type": "error", "job_id": job_id, "error": error }));
+                return;
+            }
+        }
+    });
+    Ok(())
+}
+
+/// 一问一答的请求交给常驻 worker，省去每次启动 Python 解释器的时间（Windows 上需要 1–3 秒）。
+/// 常驻 worker 已退出或通信出错时丢弃它，换一个新启动的 worker 重试一次；超时不重试，
+/// 结束 worker 后把超时前收到的输出写入日志。
+pub fn run_worker_request(request: WorkerRequest, app: &AppHandle) -> Result<Value, String> {
+    let state = app.state::<WorkerState>();
+    let mut resident = state
+        .resident
+        .lock()
+        .map_err(|_| "failed to acquire resident worker lock".to_string())?;
+    if let Some(worker) = resident.as_mut() {
+        if worker.is_running() {
+            match worker.request(&request) {
+                Ok(response) => return Ok(response),
+                Err(WorkerRequestError::Failed(err)) => {
+                    append_resident_log(app, &format!("常驻 worker 请求失败，重新启动: {err}"))
+                }
+                Err(timeout) => {
+                    *resident = None;
+                    return Err(report_request_error(app, timeout));
+                }
+            }
+        }
+    }
+    // 先结束旧进程再启动新的。
+    *resident = None;
+    let started = start_resident_worker(app).map_err(|error| report_request_error(app, error))?;
+    let response = resident.insert(started).request(&request);
+    if response.is_err() {
+        *resident = None;
+    }
+    response.map_err(|error| report_request_error(app, error))
+}
+
+/// 超时错误连同超时前收到的输出写入日志（常驻 worker 的诊断输出已经逐行记录），返回给界面的错误信息。
+fn report_request_error(app: &AppHandle, error: WorkerRequestError) -> String {
+    let message = error.to_string();
+    if matches!(error, WorkerRequestError::Timeout { .. }) {
+        for line in message.lines() {
+            append_resident_log(app, line);
+        }
+    }
+    message
+}
+
+/// 常驻 worker 的诊断输出与重启记录在日志中使用的标识（代替任务 ID）。
+const RESIDENT_WORKER_ID: &str = "resident";
+
+fn start_resident_worker(app: &AppHandle) -> Result<ResidentWorker, WorkerRequestError> {
+    let settings = read_app_settings(app)?.worker;
+    let mut worker = spawn_worker(worker_command(app)?, settings.transport)?;
+    for (stream, reader) in std::mem::take(&mut worker.diagnostics) {
+        spawn_diagnostic_forwarder(app.clone(), RESIDENT_WORKER_ID.to_string(), stream, reader);
+    }
+    ResidentWorker::start(worker, settings.request_timeout())
+}
+
+fn append_resident_log(app: &AppHandle, line: &str) {
+    if let Ok(path) = resolve_data_file(app, APP_LOG_RELATIVE_PATH) {
+        append_worker_log(&path, RESIDENT_WORKER_ID, line);
+    }
+}
+
+/// 结束常驻 worker，下次请求时按新的运行时与 worker 设置重新启动（Windows 上也不再占用虚拟环境中的文件）。
+fn stop_resident_worker(app: &AppHandle) {
+    if let Ok(mut resident) = app.state::<WorkerState>().resident.lock() {
+        resident.take();
+    }
+}
+
+fn worker_command(app: &AppHandle) -> Result<Command, String> {
+    let worker_script = resolve_worker_script(app)?;
+    let project_root = worker_script
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| PathBuf::from("."));
+    let use_uv = project_root.join("pyproject.toml").exists();
+
+    let worker_settings = read_app_settings(app)?.worker;
+    if let Some(command_override) = &worker_settings.command_override {
+        return Ok(override_worker_command(command_override, &worker_script));
+    }
+
+    if worker_settings.ephemeral_uv {
+        if let Some(mut command) = ephemeral_worker_command(app, &project_root)? {
+            command.arg(&worker_script);
+            return Ok(command);
+        }
+    }
+
+    if use_uv {
+        // 按“应用版本 + worker 哈希”隔离的虚拟环境，升级不会破坏旧版本正在使用的环境。
+        let worker_env = current_worker_env_dir(app, &project_root)?;
+        if let Some(env_python) = find_venv_python(&worker_env) {
+            let mut command = Command::new(env_python);
+            command.arg(&worker_script);
+            command.current_dir(&project_root);
+            command.env("PYTHONPATH", &project_root);
+            return Ok(command);
+        }
+
+        if let Some(project_python) = find_venv_python(&project_root.join(".venv")) {
+            let mut command = Command::new(project_python);
+            command.arg(&worker_script);
+            command.current_dir(&project_root);
+            command.env("PYTHONPATH", &project_root);
+            return Ok(command);
+        }
+
+        // 首次运行：由 uv 在版本化目录中创建并同步环境。
+        if let Some(uv) = find_uv_executable(app) {
+            let mut command = Command::new(uv);
+            command.args(["run", "python"]);
+            command.arg(&worker_script);
+            command.current_dir(&project_root);
+            command.env("UV_PROJECT_ENVIRONMENT", &worker_env);
+            return Ok(command);
+        }
+    }
+
+    // Fallback: use the configured Python binary directly.
+    // Set CWD + PYTHONPATH so bulk_email_sender is importable. The worker has no
+    // third-party dependencies: recipient files are parsed natively in Rust.
+    let runtime = resolve_python_runtime(app)
+        .ok_or_else(|| "未找到可用 Python 运行时，请先在客户端完成 Python 运行时设置".to_string())?;
+    let mut command = Command::new(runtime.executable_path);
+    command.arg(worker_script);
+    command.current_dir(&project_root);
+    command.env("PYTHONPATH", &project_root);
+    Ok(command)
+}
+
+fn override_worker_command(command_override: &WorkerCommandOverride, worker_script: &Path) -> Command {
+    let mut command = Command::new(command_override.program.trim());
+    command.args(command_override.expanded_args(worker_script));
+    if let Some(project_root) = worker_script.parent() {
+        command.current_dir(project_root);
+        command.env("PYTHONPATH", project_root);
+    }
+    command.envs(&command_override.env);
+    command
+}
+
+/// 有 uv 且打包了锁定依赖清单时，构造 `uv run --no-project --with ... python` 命令；
+/// 仅本地模式下加 `--offline`，只使用已预热的缓存。
+fn ephemeral_worker_command(app: &AppHandle, project_root: &Path) -> Result<Option<Command>, String> {
+    let requirements_path = project_root.join(WORKER_REQUIREMENTS_FILE);
+    if !requirements_path.exists() {
+        return Ok(None);
+    }
+    let Some(uv) = find_uv_executable(app) else {
+        return Ok(None);
+    };
+    let requirements = read_locked_requirements(&requirements_path)?;
+    let offline = !read_app_settings(app)?.network.allows_downloads();
+    let mut command = Command::new(uv);
+    command.args(ephemeral_uv_args(&requirements, offline));
+    command.current_dir(project_root);
+    command.env("PYTHONPATH", project_root);
+    Ok(Some(command))
+}
+
+fn worker_envs_root(app: &AppHandle) -> Result<PathBuf, String> {
+    Ok(runtime_root_dir(app)?.join("worker-envs"))
+}
+
+fn current_worker_env_key(app: &AppHandle, project_root: &Path) -> String {
+    worker_env_key(&app.package_info().version.to_string(), project_root)
+}
+
+fn current_worker_env_dir(app: &AppHandle, project_root: &Path) -> Result<PathBuf, String> {
+    Ok(worker_envs_root(app)?.join(current_worker_env_key(app, project_root)))
+}
+
+fn find_venv_python(venv_dir: &Path) -> Option<PathBuf> {
+    let candidates = if cfg!(target_os = "windows") {
+        vec![
+            venv_dir.join("Scripts").join("python.exe"),
+            venv_dir.join("python.exe"),
+        ]
+    } else {
+        vec![
+            venv_dir.join("bin").join("python3"),
+            venv_dir.join("bin").join("python"),
+        ]
+    };
+
+    for candidate in candidates {
+        if !candidate.exists() {
+            continue;
+        }
+        if let Some(version) = probe_python_version(&candidate) {
+            if is_supported_python_version(&version) {
+                return Some(candidate);
+            }
+        }
+    }
+    None
+}
+
+fn resolve_worker_script(app: &AppHandle) -> Result<PathBuf, String> {
+    let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let dev_candidates = vec![
+        manifest_dir.join("../../..").join("worker.py"),
+        manifest_dir.join("../..").join("worker.py"),
+        manifest_dir.join("worker.py"),
+    ];
+
+    for candidate in &dev_candidates {
+        if candidate.exists() {
+            return candidate
+                .canonicalize()
+                .or_else(|_| Ok(candidate.clone()));
+        }
+    }
+
+    if let Ok(resource_dir) = app.path().resource_dir() {
+        let packaged_script = resource_dir.join("worker.py");
+        if packaged_script.exists() {
+            return Ok(packaged_script);
+        }
+
+        for entry in WalkDir::new(&resource_dir)
+            .max_depth(4)
+            .into_iter()
+            .filter_map(Result::ok)
+        {
+            if entry.file_type().is_file() && entry.file_name() == "worker.py" {
+                return Ok(entry.path().to_path_buf());
+            }
+        }
+    }
+
+    let searched = dev_candidates
+        .iter()
+        .map(|path| path.to_string_lossy().to_string())
+        .collect::<Vec<String>>()
+        .join(" | ");
+    Err(format!("未找到 worker.py，请检查打包资源配置（已检查：{searched}）"))
+}
+
+/// 运行时是否就绪及说明，供 `get_capabilities` 使用。
+pub fn runtime_readiness(app: &AppHandle) -> (bool, String) {
+    let status = resolve_runtime_status(app);
+    (status.ready, status.message)
+}
+
+fn resolve_runtime_status(app: &AppHandle) -> RuntimeStatus {
+    if let Some(runtime) = resolve_python_runtime(app) {
+        let message = if runtime.source == "system" {
+            "检测到系统 Python，可直接使用".to_string()
+        } else {
+            "Python 运行时可用".to_string()
+        };
+        return RuntimeStatus {
+            ready: true,
+            source: runtime.source,
+            executable_path: Some(runtime.executable_path.to_string_lossy().to_string()),
+            version: Some(runtime.version),
+            message,
+            missing_libraries: Vec::new(),
+        };
+    }
+
+    RuntimeStatus {
+        ready: false,
+        source: "none".to_string(),
+        executable_path: None,
+        version: None,
+        message: "未检测到 Python 运行时，请导入运行时压缩包或手动指定可执行文件".to_string(),
+        missing_libraries: Vec::new(),
+    }
+}
+
+struct PythonRuntime {
+    source: String,
+    executable_path: PathBuf,
+    version: String,
+}
+
+fn resolve_python_runtime(app: &AppHandle) -> Option<PythonRuntime> {
+    if let Ok(config) = read_runtime_config(app) {
+        if let Some(path) = config.python_path {
+            let configured = PathBuf::from(path);
+            if let Some(version) = probe_python_version(&configured) {
+                if is_supported_python_version(&version) {
+                    return Some(PythonRuntime {
+                        source: "configured".to_string(),
+                        executable_path: configured,
+                        version,
+                    });
+                }
+            }
+        }
+    }
+
+    for candidate in ["python3", "python"] {
+        let executable = PathBuf::from(candidate);
+        if let Some(version) = probe_python_version(&executable) {
+            if is_supported_python_version(&version) {
+                return Some(PythonRuntime {
+                    source: "system".to_string(),
+                    executable_path: executable,
+                    version,
+                });
+            }
+        }
+    }
+
+    None
+}
+
+fn probe_python_version(path: &Path) -> Option<String> {
+    let output = Command::new(path).arg("--version").output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    let line = if !stdout.is_empty() { stdout } else { stderr };
+    if line.is_empty() {
+        return None;
+    }
+    parse_python_version(&line)?;
+    Some(line)
+}
+
+fn parse_python_version(line: &str) -> Option<(u32, u32, u32)> {
+    let normalized = line.trim();
+    let payload = normalized.strip_prefix("Python ")?;
+    let mut chunks = payload.split('.');
+    let major = chunks.next()?.parse::<u32>().ok()?;
+    let minor = chunks.next()?.parse::<u32>().ok()?;
+    let patch = chunks
+        .next()
+        .unwrap_or("0")
+        .split_whitespace()
+        .next()
+        .unwrap_or("0")
+        .parse::<u32>()
+        .ok()?;
+    Some((major, minor, patch))
+}
+
+fn is_supported_python_version(line: &str) -> bool {
+    let Some((major, minor, _patch)) = parse_python_version(line) else {
+        return false;
+    };
+    major > PYTHON_MIN_MAJOR || (major == PYTHON_MIN_MAJOR && minor >= PYTHON_MIN_MINOR)
+}
+
+fn runtime_target_key(os: &str, arch: &str) -> String {
+    format!("{os}-{arch}")
+}
+
+fn collect_manifest_sources(
+    manifest_url: Option<String>,
+    manifest_urls: Option<Vec<String>>,
+) -> Vec<String> {
+    let mut ordered: Vec<String> = Vec::new();
+
+    if let Some(raw) = manifest_url {
+        for item in raw.split(['\n', ',', ';']) {
+            let trimmed = item.trim();
+            if !trimmed.is_empty() && !ordered.iter().any(|existing| existing == trimmed) {
+                ordered.push(trimmed.to_string());
+            }
+        }
+    }
+
+    if let Some(list) = manifest_urls {
+        for item in list {
+            let trimmed = item.trim();
+            if !trimmed.is_empty() && !ordered.iter().any(|existing| existing == trimmed) {
+                ordered.push(trimmed.to_string());
+            }
+        }
+    }
+
+    ordered
+}
+
+fn select_manifest_bundle<'a>(
+    manifest: &'a RuntimeManifest,
+    target: &str,
+) -> Option<&'a RuntimeManifestBundle> {
+    manifest
+        .bundles
+        .iter()
+        .find(|item| item.target == target)
+}
+
+fn resolve_bundle_download_urls(bundle: &RuntimeManifestBundle) -> Vec<String> {
+    let mut urls = vec![bundle.url.trim().to_string()];
+    if let Some(extra) = &bundle.urls {
+        for item in extra {
+            let trimmed = item.trim();
+            if !trimmed.is_empty() && !urls.iter().any(|existing| existing == trimmed) {
+                urls.push(trimmed.to_string());
+            }
+        }
+    }
+    urls
+}
+
+fn bundle_has_checksum(bundle: &RuntimeManifestBundle) -> bool {
+    bundle
+        .sha256
+        .as_ref()
+        .map(|value| !value.trim().is_empty())
+        .unwrap_or(false)
+}
+
+fn load_runtime_manifest(policy: &NetworkPolicy, manifest_url: &str) -> Result<RuntimeManifest, String> {
+    let body = if manifest_url.starts_with("http://") || manifest_url.starts_with("https://") {
+        http_client(policy, manifest_url, "runtime manifest")?
+            .get(manifest_url)
+            .send()
+            .map_err(|err| format!("下载 manifest 失败: {err}"))?
+            .error_for_status()
+            .map_err(|err| format!("manifest 响应异常: {err}"))?
+            .text()
+            .map_err(|err| format!("读取 manifest 内容失败: {err}"))?
+    } else if manifest_url.starts_with("file://") {
+        let path = manifest_url.trim_start_matches("file://");
+        fs::read_to_string(path).map_err(|err| format!("读取本地 manifest 失败: {err}"))?
+    } else {
+        fs::read_to_string(manifest_url).map_err(|err| format!("读取 manifest 失败: {err}"))?
+    };
+
+    serde_json::from_str::<RuntimeManifest>(&body).map_err(|err| format!("manifest JSON 格式错误: {err}"))
+}
+
+fn download_bundle_to_path(policy: &NetworkPolicy, url: &str, destination: &Path) -> Result<(), String> {
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent).map_err(|err| format!("创建下载目录失败: {err}"))?;
+    }
+
+    if url.starts_with("http://") || url.starts_with("https://") {
+        let mut response = http_client(policy, url, "runtime 包下载")?
+            .get(url)
+            .send()
+            .map_err(|err| format!("下载 runtime 包失败: {err}"))?
+            .error_for_status()
+            .map_err(|err| format!("runtime 包响应异常: {err}"))?;
+        let mut target = File::create(destination).map_err(|err| format!("创建下载文件失败: {err}"))?;
+        std::io::copy(&mut response, &mut target).map_err(|err| format!("写入下载文件失败: {err}"))?;
+        return Ok(());
+    }
+
+    let source_path = if url.starts_with("file://") {
+        PathBuf::from(url.trim_start_matches("file://"))
+    } else {
+        PathBuf::from(url)
+    };
+
+    if !source_path.exists() {
+        return Err("runtime 包地址无效，文件不存在".to_string());
+    }
+    fs::copy(source_path, destination).map_err(|err| format!("复制 runtime 包失败: {err}"))?;
+    Ok(())
+}
+
+fn verify_sha256_checksum(path: &Path, expected: &str) -> Result<(), String> {
+    let mut file = File::open(path).map_err(|err| format!("读取下载文件失败: {err}"))?;
+    let mut hasher = Sha256::new();
+    let mut buffer = [0_u8; 8192];
+    loop {
+        let size = file
+            .read(&mut buffer)
+            .map_err(|err| format!("读取下载文件失败: {err}"))?;
+        if size == 0 {
+            break;
+        }
+        hasher.update(&buffer[..size]);
+    }
+    let actual = format!("{:x}", hasher.finalize());
+    let expected_trimmed = expected.trim().to_lowercase();
+    if expected_trimmed.is_empty() {
+        return Ok(());
+    }
+    if actual != expected_trimmed {
+        return Err(format!(
+            "runtime 包校验失败：期望 sha256={expected_trimmed}，实际 sha256={actual}"
+        ));
+    }
+    Ok(())
+}
+
+/// 是否有发送任务（Python worker 或原生引擎）正在运行或排队等待。
+fn job_running(state: &WorkerState) -> Result<bool, String> {
+    let queue = state
+        .queue
+        .lock()
+        .map_err(|_| "failed to acquire job queue lock".to_string())?;
+    Ok(queue.is_active())
+}
+
+/// 有任务运行时把变更放入待应用队列并返回 `true`，调用方不再立即执行；
+/// 否则先结束常驻 worker，调用方随即应用变更。
+fn defer_while_busy(app: &AppHandle, update: PendingUpdate) -> Result<bool, String> {
+    if !job_running(&app.state::<WorkerState>())? {
+        stop_resident_worker(app);
+        return Ok(false);
+    }
+    let path = pending_updates_path(app)?;
+    let mut queue = UpdateQueue::load(&path)?;
+    queue.push(update);
+    queue.save(&path)?;
+    let _ = app.emit(PENDING_UPDATES_CHANNEL, pending_updates_status(app)?);
+    Ok(true)
+}
+
+fn queued_runtime_status(app: &AppHandle) -> RuntimeStatus {
+    RuntimeStatus {
+        message: "发送任务进行中，运行时变更已排队，将在任务结束后应用".to_string(),
+        ..resolve_runtime_status(app)
+    }
+}
+
+fn pending_updates_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let app_data_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|err| format!("无法获取应用数据目录: {err}"))?;
+    let path = app_data_dir.join(PENDING_UPDATES_RELATIVE_PATH);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|err| format!("无法创建运行时配置目录: {err}"))?;
+    }
+    Ok(path)
+}
+
+fn pending_updates_status(app: &AppHandle) -> Result<PendingUpdatesStatus, String> {
+    let maintenance = read_app_settings(app)?.maintenance;
+    let job_running = job_running(&app.state::<WorkerState>())?;
+    Ok(PendingUpdatesStatus {
+        updates: UpdateQueue::load(&pending_updates_path(app)?)?.updates,
+        due: !job_running && maintenance.allows(local_minute_of_day()),
+        maintenance,
+        job_running,
+    })
+}
+
+fn local_minute_of_day() -> u32 {
+    let now = chrono::Local::now();
+    now.hour() * 60 + now.minute()
+}
+
+/// 依次应用排队的更新；应用期间拒绝启动新任务。失败的更新不再重试，错误汇总后返回。
+fn apply_queued_updates(app: &AppHandle) -> Result<(), String> {
+    let state = app.state::<WorkerState>();
+    if state.updating.swap(true, Ordering::SeqCst) {
+        return Err("正在应用运行时更新".to_string());
+    }
+    let result = (|| {
+        if job_running(&state)? {
+            return Err("发送任务进行中，无法应用更新".to_string());
+        }
+        let path = pending_updates_path(app)?;
+        let queue = UpdateQueue::load(&path)?;
+        UpdateQueue::default().save(&path)?;
+        let errors: Vec<String> = queue
+            .updates
+            .into_iter()
+            .filter_map(|queued| apply_update(app, queued.update).err())
+            .collect();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("部分更新应用失败：{}", errors.join(" | ")))
+        }
+    })();
+    state.updating.store(false, Ordering::SeqCst);
+    if let Ok(status) = pending_updates_status(app) {
+        let _ = app.emit(PENDING_UPDATES_CHANNEL, status);
+    }
+    result
+}
+
+fn apply_update(app: &AppHandle, update: PendingUpdate) -> Result<(), String> {
+    let app = app.clone();
+    match update {
+        PendingUpdate::SetRuntimePython { path } => set_runtime_python(app, path).map(|_| ()),
+        PendingUpdate::ClearRuntimePython => clear_runtime_python(app).map(|_| ()),
+        PendingUpdate::InstallRuntimeArchive { archive_path } => install_runtime_from_archive(app, archive_path).map(|_| ()),
+        PendingUpdate::AutoInstallRuntime {
+            manifest_url,
+            manifest_urls,
+        } => auto_install_runtime(
+            app,
+            Some(AutoInstallPayload {
+                manifest_url,
+                manifest_urls,
+            }),
+        )
+        .map(|_| ()),
+        PendingUpdate::AutoDetectRuntime => auto_detect_runtime(app).map(|_| ()),
+        PendingUpdate::WorkerSettings { settings } => set_worker_settings(app, settings).map(|_| ()),
+    }
+}
+
+/// 每分钟检查一次：没有任务运行且处于维护时段内时应用排队的更新。
+pub fn spawn_update_scheduler(app: AppHandle) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(Duration::from_secs(60));
+        let due = pending_updates_status(&app).is_ok_and(|status| status.due && !status.updates.is_empty());
+        if due {
+            let _ = apply_queued_updates(&app);
+        }
+    });
+}
+
+fn runtime_config_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let app_data_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|err| format!("无法获取应用数据目录: {err}"))?;
+    let config_path = app_data_dir.join(RUNTIME_CONFIG_RELATIVE_PATH);
+    if let Some(parent) = config_path.parent() {
+        fs::create_dir_all(parent).map_err(|err| format!("无法创建运行时配置目录: {err}"))?;
+    }
+    Ok(config_path)
+}
+
+fn read_runtime_config(app: &AppHandle) -> Result<RuntimeConfig, String> {
+    let config_path = runtime_config_path(app)?;
+    if !config_path.exists() {
+        return Ok(RuntimeConfig::default());
+    }
+
+    let text = fs::read_to_string(config_path).map_err(|err| format!("读取运行时配置失败: {err}"))?;
+    serde_json::from_str(&text).map_err(|err| format!("运行时配置格式错误: {err}"))
+}
+
+fn write_runtime_config(app: &AppHandle, config: &RuntimeConfig) -> Result<(), String> {
+    let config_path = runtime_config_path(app)?;
+    let text = serde_json::to_string_pretty(config).map_err(|err| err.to_string())?;
+    fs::write(config_path, text).map_err(|err| format!("写入运行时配置失败: {err}"))
+}
+
+fn runtime_root_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let root = app
+        .path()
+        .app_local_data_dir()
+        .map_err(|err| format!("无法获取本地运行时目录: {err}"))?
+        .join("runtime");
+    Ok(root)
+}
+
+fn extract_zip_archive(source: &Path, destination: &Path) -> Result<(), String> {
+    if destination.exists() {
+        fs::remove_dir_all(destination).map_err(|err| format!("清理临时目录失败: {err}"))?;
+    }
+    fs::create_dir_all(destination).map_err(|err| format!("创建临时目录失败: {err}"))?;
+
+    let file = File::open(source).map_err(|err| format!("打开压缩包失败: {err}"))?;
+    let mut archive = ZipArchive::new(file).map_err(|err| format!("读取压缩包失败: {err}"))?;
+    for index in 0..archive.len() {
+        let mut entry = archive
+            .by_index(index)
+            .map_err(|err| format!("解压失败: {err}"))?;
+        let Some(safe_name) = entry.enclosed_name().map(|path| path.to_owned()) else {
+            continue;
+        };
+        let output_path = destination.join(safe_name);
+
+        if entry.name().ends_with('/') {
+            fs::create_dir_all(&output_path).map_err(|err| format!("创建目录失败: {err}"))?;
+            continue;
+        }
+
+        if let Some(parent) = output_path.parent() {
+            fs::create_dir_all(parent).map_err(|err| format!("创建目录失败: {err}"))?;
+        }
+
+        let mut output_file =
+            File::create(&output_path).map_err(|err| format!("写入解压文件失败: {err}"))?;
+        std::io::copy(&mut entry, &mut output_file).map_err(|err| format!("写入解压文件失败: {err}"))?;
+
+        #[cfg(unix)]
+        if let Some(mode) = entry.unix_mode() {
+            let _ = fs::set_permissions(&output_path, fs::Permissions::from_mode(mode));
+        }
+    }
+    Ok(())
+}
+
+fn find_python_executable(root: &Path) -> Option<PathBuf> {
+    let mut candidates: Vec<PathBuf> = Vec::new();
+    for entry in WalkDir::new(root)
+        .into_iter()
+        .filter_map(Result::ok)
+    {
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let file_name = entry.file_name().to_string_lossy().to_lowercase();
+        if file_name == "python3"
+            || file_name == "python"
+            || file_name == "python.exe"
+        {
+            candidates.push(entry.path().to_path_buf());
+        }
+    }
+
+    candidates.sort_by_key(|path| {
+        let depth = path.components().count();
+        let file_name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or("")
+            .to_lowercase();
+        let is_python3 = usize::from(file_name == "python3");
+        let is_bin = usize::from(path.components().any(|component| component.as_os_str() == "bin"));
+        (1 - is_python3, 1 - is_bin, depth)
+    });
+    candidates
+        .into_iter()
+        .find(|candidate| probe_python_version(candidate.as_path()).is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        bundle_has_checksum, collect_manifest_sources, is_supported_python_version, parse_python_version,
+        resolve_bundle_download_urls, runtime_target_key, select_manifest_bundle, RuntimeManifest, RuntimeManifestBundle,
+    };
+
+    #[test]
+    fn parses_python_version_line() {
+        let parsed = parse_python_version("Python 3.11.8");
+        assert_eq!(parsed, Some((3, 11, 8)));
+    }
+
+    #[test]
+    fn rejects_invalid_version_line() {
+        assert_eq!(parse_python_version("v3.11.8"), None);
+    }
+
+    #[test]
+    fn validates_supported_python_version() {
+        assert!(is_supported_python_version("Python 3.11.8"));
+        assert!(is_supported_python_version("Python 3.9.18"));
+        assert!(!is_supported_python_version("Python 3.8.18"));
+    }
+
+    #[test]
+    fn builds_runtime_target_key() {
+        assert_eq!(runtime_target_key("macos", "aarch64"), "macos-aarch64");
+        assert_eq!(runtime_target_key("windows", "x86_64"), "windows-x86_64");
+    }
+
+    #[test]
+    fn selects_bundle_by_target() {
+        let manifest = RuntimeManifest {
+            bundles: vec![
+                RuntimeManifestBundle {
+                    target: "macos-aarch64".to_string(),
+                    url: "https://cdn.example.com/mac.zip".to_string(),
+                    sha256: Some("abc".to_string()),
+                    urls: None,
+                },
+                RuntimeManifestBundle {
+                    target: "windows-x86_64".to_string(),
+                    url: "https://cdn.example.com/win.zip".to_string(),
+                    sha256: None,
+                    urls: None,
+                },
+            ],
+        };
+
+        let bundle = select_manifest_bundle(&manifest, "windows-x86_64").expect("bundle should exist");
+        assert_eq!(bundle.url, "https://cdn.example.com/win.zip");
+    }
+
+    #[test]
+    fn collects_manifest_sources_with_dedup() {
+        let sources = collect_manifest_sources(
+            Some("https://a.example.com/manifest.json, https://b.example.com/manifest.json".to_string()),
+            Some(vec![
+                "https://b.example.com/manifest.json".to_string(),
+                "https://c.example.com/manifest.json".to_string(),
+            ]),
+        );
+        assert_eq!(
+            sources,
+            vec![
+                "https://a.example.com/manifest.json".to_string(),
+                "https://b.example.com/manifest.json".to_string(),
+                "https://c.example.com/manifest.json".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn resolves_bundle_download_urls() {
+        let bundle = RuntimeManifestBundle {
+            target: "macos-aarch64".to_string(),
+            url: "https://primary.example.com/runtime.zip".to_string(),
+            sha256: None,
+            urls: Some(vec![
+                "https://mirror1.example.com/runtime.zip".to_string(),
+                "https://mirror2.example.com/runtime.zip".to_string(),
+            ]),
+        };
+        let urls = resolve_bundle_download_urls(&bundle);
+        assert_eq!(
+            urls,
+            vec![
+                "https://primary.example.com/runtime.zip".to_string(),
+                "https://mirror1.example.com/runtime.zip".to_string(),
+                "https://mirror2.example.com/runtime.zip".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn checks_bundle_checksum_presence() {
+        let with_checksum = RuntimeManifestBundle {
+            target: "linux-x86_64".to_string(),
+            url: "https://example.com/runtime.zip".to_string(),
+            sha256: Some("abc123".to_string()),
+            urls: None,
+        };
+        let without_checksum = RuntimeManifestBundle {
+            target: "linux-x86_64".to_string(),
+            url: "https://example.com/runtime.zip".to_string(),
+            sha256: Some("   ".to_string()),
+            urls: None,
+        };
+
+        assert!(bundle_has_checksum(&with_checksum));
+        assert!(!bundle_has_checksum(&without_checksum));
+    }
+}
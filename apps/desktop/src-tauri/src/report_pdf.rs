@@ -0,0 +1,329 @@
+use bulk_email_core::campaign_log::CampaignSummary;
+use chrono::{DateTime, Duration, Local, Utc};
+use printpdf::{Color, IndirectFontRef, Mm, PdfDocument, PdfDocumentReference, PdfLayerReference, Rect, Rgb};
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+const PAGE_WIDTH: f32 = 210.0;
+const PAGE_HEIGHT: f32 = 297.0;
+const MARGIN: f32 = 18.0;
+const CONTENT_WIDTH: f32 = PAGE_WIDTH - 2.0 * MARGIN;
+/// 时间线最多绘制的柱数，超出时自动放大时间桶。
+const MAX_TIMELINE_BUCKETS: i64 = 48;
+const BUCKET_MINUTES: [i64; 10] = [1, 5, 10, 15, 30, 60, 120, 360, 720, 1440];
+const MAX_TABLE_ROWS: usize = 12;
+
+/// 时间桶：(起点, 成功发送数)。
+type TimelineBucket = (DateTime<Utc>, usize);
+
+/// 内置 PDF 字体不含中文，需要嵌入独立的 TrueType 字体（`.ttf`；`.ttc` 字体集合无法直接嵌入）。
+const CJK_FONT_CANDIDATES: [&str; 8] = [
+    "C:\\Windows\\Fonts\\simhei.ttf",
+    "C:\\Windows\\Fonts\\Deng.ttf",
+    "C:\\Windows\\Fonts\\simkai.ttf",
+    "/System/Library/Fonts/Supplemental/Arial Unicode.ttf",
+    "/Library/Fonts/Arial Unicode.ttf",
+    "/usr/share/fonts/truetype/droid/DroidSansFallbackFull.ttf",
+    "/usr/share/fonts/truetype/wqy/wqy-microhei.ttf",
+    "/usr/share/fonts/truetype/arphic/uming.ttf",
+];
+
+/// 报告使用的字体：优先使用调用方指定的文件，否则在常见系统字体位置中查找。
+pub fn resolve_report_font(font_path: Option<&str>) -> Result<PathBuf, String> {
+    if let Some(path) = font_path.map(str::trim).filter(|path| !path.is_empty()) {
+        let path = PathBuf::from(path);
+        if !path.is_file() {
+            return Err(format!("字体文件不存在: {}", path.display()));
+        }
+        return Ok(path);
+    }
+    CJK_FONT_CANDIDATES
+        .iter()
+        .map(PathBuf::from)
+        .find(|path| path.is_file())
+        .ok_or_else(|| "未找到可用的中文字体，请指定一个 .ttf 字体文件（如 simhei.ttf）".to_string())
+}
+
+/// 逐行向下排版的页面游标，空间不足时自动换页。
+struct ReportWriter {
+    doc: PdfDocumentReference,
+    font: IndirectFontRef,
+    layer: PdfLayerReference,
+    y: f32,
+}
+
+impl ReportWriter {
+    fn new(title: &str, font_path: &Path) -> Result<Self, String> {
+        let (doc, page, layer) = PdfDocument::new(title, Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "content");
+        let font_file = File::open(font_path).map_err(|err| format!("读取字体文件失败: {err}"))?;
+        let font = doc
+            .add_external_font(font_file)
+            .map_err(|err| format!("字体文件无法嵌入 PDF: {err}"))?;
+        let layer = doc.get_page(page).get_layer(layer);
+        Ok(ReportWriter {
+            doc,
+            font,
+            layer,
+            y: PAGE_HEIGHT - MARGIN,
+        })
+    }
+
+    fn ensure_space(&mut self, height: f32) {
+        if self.y - height >= MARGIN {
+            return;
+        }
+        let (page, layer) = self.doc.add_page(Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "content");
+        self.layer = self.doc.get_page(page).get_layer(layer);
+        self.y = PAGE_HEIGHT - MARGIN;
+    }
+
+    fn text_at(&self, text: &str, size: f32, x: f32, y: f32) {
+        self.layer.set_fill_color(grey(0.1));
+        self.layer.use_text(text, size, Mm(x), Mm(y), &self.font);
+    }
+
+    fn line(&mut self, text: &str, size: f32) {
+        let height = size * 0.5;
+        self.ensure_space(height);
+        self.y -= height;
+        self.text_at(text, size, MARGIN, self.y);
+    }
+
+    fn heading(&mut self, text: &str) {
+        self.ensure_space(16.0);
+        self.y -= 6.0;
+        self.line(text, 13.0);
+        self.y -= 2.0;
+    }
+
+    fn rect(&self, color: Color, x: f32, y: f32, width: f32, height: f32) {
+        self.layer.set_fill_color(color);
+        self.layer.add_rect(Rect::new(Mm(x), Mm(y), Mm(x + width), Mm(y + height)));
+    }
+
+    /// 表格：第一行为表头，列宽按 `widths`（mm）排列。
+    fn table(&mut self, widths: &[f32], rows: &[Vec<String>]) {
+        for (index, row) in rows.iter().enumerate() {
+            self.ensure_space(7.0);
+            self.y -= 7.0;
+            if index == 0 {
+                self.rect(grey(0.9), MARGIN, self.y - 1.5, CONTENT_WIDTH, 6.5);
+            }
+            let mut x = MARGIN + 1.5;
+            for (cell, width) in row.iter().zip(widths) {
+                let max_chars = (*width / 2.6) as usize;
+                self.text_at(&truncate(cell, max_chars), 9.0, x, self.y);
+                x += width;
+            }
+        }
+    }
+
+    /// 横向条形图：每项一行，条长按最大值缩放。
+    fn bar_chart(&mut self, items: &[(&str, usize, Color)]) {
+        let max = items.iter().map(|(_, value, _)| *value).max().unwrap_or(0).max(1);
+        let bar_width = CONTENT_WIDTH - 45.0;
+        for (label, value, color) in items {
+            self.ensure_space(8.0);
+            self.y -= 8.0;
+            self.text_at(label, 10.0, MARGIN, self.y + 1.0);
+            let width = bar_width * (*value as f32) / (max as f32);
+            self.rect(color.clone(), MARGIN + 22.0, self.y, width.max(0.5), 5.0);
+            self.text_at(&value.to_string(), 9.0, MARGIN + 24.0 + width, self.y + 1.0);
+        }
+    }
+
+    /// 纵向柱状时间线。
+    fn timeline_chart(&mut self, buckets: &[TimelineBucket], bucket_minutes: i64) {
+        let chart_height = 45.0;
+        self.ensure_space(chart_height + 14.0);
+        let base = self.y - chart_height - 4.0;
+        let max = buckets.iter().map(|(_, count)| *count).max().unwrap_or(0).max(1);
+        let slot = CONTENT_WIDTH / buckets.len().max(1) as f32;
+        self.rect(grey(0.6), MARGIN, base, CONTENT_WIDTH, 0.3);
+        for (index, (_, count)) in buckets.iter().enumerate() {
+            let height = chart_height * (*count as f32) / (max as f32);
+            let x = MARGIN + slot * index as f32;
+            self.rect(accent(), x + slot * 0.15, base, slot * 0.7, height);
+        }
+        self.text_at(&format!("峰值 {max} 封 / {bucket_minutes} 分钟"), 8.0, MARGIN, self.y - 3.0);
+        if let (Some((first, _)), Some((last, _))) = (buckets.first(), buckets.last()) {
+            let format = "%m-%d %H:%M";
+            self.text_at(&local_time(first, format), 8.0, MARGIN, base - 5.0);
+            let end = local_time(&(*last + Duration::minutes(bucket_minutes)), format);
+            self.text_at(&end, 8.0, PAGE_WIDTH - MARGIN - 22.0, base - 5.0);
+        }
+        self.y = base - 8.0;
+    }
+
+    fn save(self, path: &Path) -> Result<(), String> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|err| format!("创建报告目录失败: {err}"))?;
+        }
+        let file = File::create(path).map_err(|err| format!("写入报告失败: {err}"))?;
+        self.doc
+            .save(&mut BufWriter::new(file))
+            .map_err(|err| format!("写入报告失败: {err}"))
+    }
+}
+
+/// 生成任务结果报告：概要、结果分布图、发送时间线、失败原因与失败域名。
+pub fn write_campaign_report(summary: &CampaignSummary, font_path: &Path, path: &Path) -> Result<(), String> {
+    let mut writer = ReportWriter::new(&format!("发送结果报告 {}", summary.job_id), font_path)?;
+    writer.line("发送结果报告", 20.0);
+    writer.y -= 2.0;
+    writer.line(&format!("任务: {}", summary.job_id), 10.0);
+    writer.line(&format!("生成时间: {}", Local::now().format("%Y-%m-%d %H:%M")), 10.0);
+
+    writer.heading("概要");
+    let attempted = summary.sent + summary.failed;
+    let success_rate = if attempted == 0 {
+        "-".to_string()
+    } else {
+        format!("{:.1}%", summary.sent as f64 * 100.0 / attempted as f64)
+    };
+    let duration = match (summary.started_at, summary.ended_at) {
+        (Some(start), Some(end)) => format_duration(end - start),
+        _ => "-".to_string(),
+    };
+    let optional_time = |value: Option<DateTime<Utc>>| {
+        value.map_or_else(|| "-".to_string(), |value| local_time(&value, "%Y-%m-%d %H:%M:%S"))
+    };
+    let rows = [
+        ("状态", status_label(&summary.status).to_string()),
+        ("开始时间", optional_time(summary.started_at)),
+        ("结束时间", optional_time(summary.ended_at)),
+        ("耗时", duration),
+        ("计划收件人", summary.total.map_or_else(|| "-".to_string(), |total| total.to_string())),
+        ("成功率", success_rate),
+        ("重试次数", summary.retries.to_string()),
+    ];
+    let mut table = vec![vec!["项目".to_string(), "值".to_string()]];
+    table.extend(rows.into_iter().map(|(label, value)| vec![label.to_string(), value]));
+    if let Some(source_id) = &summary.resend_of {
+        table.push(vec!["重发自".to_string(), source_id.clone()]);
+    }
+    writer.table(&[45.0, CONTENT_WIDTH - 45.0], &table);
+
+    writer.heading("结果分布");
+    writer.bar_chart(&[
+        ("成功", summary.sent, Color::Rgb(Rgb::new(0.22, 0.62, 0.35, None))),
+        ("失败", summary.failed, Color::Rgb(Rgb::new(0.82, 0.26, 0.24, None))),
+        ("跳过", summary.skipped, grey(0.6)),
+        ("延后", summary.deferred, Color::Rgb(Rgb::new(0.93, 0.62, 0.18, None))),
+    ]);
+
+    if let Some((buckets, bucket_minutes)) = timeline_buckets(&summary.sent_times) {
+        writer.heading("发送时间线（成功发送）");
+        writer.timeline_chart(&buckets, bucket_minutes);
+    }
+
+    writer.heading("失败原因");
+    if summary.failures.is_empty() {
+        writer.line("没有失败的收件人。", 10.0);
+    } else {
+        let mut table = vec![vec!["状态码".to_string(), "数量".to_string(), "示例".to_string()]];
+        table.extend(summary.failures.iter().take(MAX_TABLE_ROWS).map(|group| {
+            let class = if group.class == "other" { "其他" } else { &group.class };
+            vec![class.to_string(), group.count.to_string(), group.sample.clone()]
+        }));
+        writer.table(&[22.0, 18.0, CONTENT_WIDTH - 40.0], &table);
+    }
+
+    if !summary.failed_domains.is_empty() {
+        writer.heading("失败最多的收件域名");
+        let mut table = vec![vec!["域名".to_string(), "失败数".to_string()]];
+        table.extend(
+            summary
+                .failed_domains
+                .iter()
+                .take(MAX_TABLE_ROWS)
+                .map(|(domain, count)| vec![domain.clone(), count.to_string()]),
+        );
+        writer.table(&[CONTENT_WIDTH - 30.0, 30.0], &table);
+    }
+    writer.save(path)
+}
+
+/// 按时间跨度选择时间桶大小，返回每个桶的起点与发送数。
+fn timeline_buckets(times: &[DateTime<Utc>]) -> Option<(Vec<TimelineBucket>, i64)> {
+    let first = *times.iter().min()?;
+    let last = *times.iter().max()?;
+    let span = (last - first).num_minutes() + 1;
+    let bucket_minutes = BUCKET_MINUTES
+        .into_iter()
+        .find(|minutes| (span + minutes - 1) / minutes <= MAX_TIMELINE_BUCKETS)
+        .unwrap_or(1440);
+    let count = ((span + bucket_minutes - 1) / bucket_minutes).max(1) as usize;
+    let mut buckets: Vec<TimelineBucket> = (0..count)
+        .map(|index| (first + Duration::minutes(index as i64 * bucket_minutes), 0))
+        .collect();
+    for time in times {
+        let index = ((*time - first).num_minutes() / bucket_minutes) as usize;
+        buckets[index.min(count - 1)].1 += 1;
+    }
+    Some((buckets, bucket_minutes))
+}
+
+fn status_label(status: &str) -> &'static str {
+    match status {
+        "finished" => "已完成",
+        "cancelled" => "已取消",
+        _ => "未完成（仍在运行或异常中断）",
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.num_seconds().max(0);
+    match (seconds / 3600, seconds % 3600 / 60, seconds % 60) {
+        (0, 0, secs) => format!("{secs} 秒"),
+        (0, mins, secs) => format!("{mins} 分 {secs} 秒"),
+        (hours, mins, _) => format!("{hours} 小时 {mins} 分"),
+    }
+}
+
+fn local_time(value: &DateTime<Utc>, format: &str) -> String {
+    value.with_timezone(&Local).format(format).to_string()
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    let text = text.replace(['\r', '\n'], " ");
+    if text.chars().count() <= max_chars {
+        return text;
+    }
+    let mut truncated: String = text.chars().take(max_chars.saturating_sub(1)).collect();
+    truncated.push('…');
+    truncated
+}
+
+fn grey(level: f32) -> Color {
+    Color::Rgb(Rgb::new(level, level, level, None))
+}
+
+fn accent() -> Color {
+    Color::Rgb(Rgb::new(0.2, 0.45, 0.8, None))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_duration, timeline_buckets, truncate};
+    use chrono::{Duration, TimeZone, Utc};
+
+    #[test]
+    fn buckets_timeline_by_span() {
+        let start = Utc.with_ymd_and_hms(2026, 3, 1, 9, 0, 0).unwrap();
+        let times: Vec<_> = (0..30).map(|minute| start + Duration::minutes(minute)).collect();
+        let (buckets, minutes) = timeline_buckets(&times).expect("buckets");
+        assert_eq!((minutes, buckets.len()), (1, 30));
+
+        let times = [start, start + Duration::minutes(5 * 60), start + Duration::minutes(5 * 60 + 10)];
+        let (buckets, minutes) = timeline_buckets(&times).expect("buckets");
+        assert_eq!(minutes, 10);
+        assert_eq!(buckets.iter().map(|(_, count)| count).sum::<usize>(), 3);
+        assert_eq!(buckets.last().expect("last").1, 1);
+        assert!(timeline_buckets(&[]).is_none());
+
+        assert_eq!(format_duration(Duration::seconds(3725)), "1 小时 2 分");
+        assert_eq!(truncate("550 mailbox\nunavailable", 8), "550 mai…");
+    }
+}
@@ -0,0 +1,145 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::path::Path;
+use std::process::Command;
+
+/// 依赖系统动态库的标准库模块，以及对应的库名称。
+const PROBE_MODULES: [(&str, &str); 5] = [
+    ("ssl", "OpenSSL"),
+    ("sqlite3", "SQLite"),
+    ("lzma", "XZ / LZMA"),
+    ("zlib", "zlib"),
+    ("ctypes", "libffi"),
+];
+
+const PROBE_SCRIPT: &str = r#"
+import importlib, json, sys
+failed = {}
+for name in sys.argv[1:]:
+    try:
+        importlib.import_module(name)
+    except Exception as exc:
+        failed[name] = f"{type(exc).__name__}: {exc}"
+print(json.dumps(failed))
+"#;
+
+/// 运行时缺失的系统库，附带按发行版给出的安装命令。
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct MissingSystemLibrary {
+    pub module: String,
+    pub library: String,
+    pub error: String,
+    pub install_hint: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PackageManager {
+    Apt,
+    Dnf,
+    Apk,
+    Pacman,
+    Zypper,
+}
+
+/// 在目标 Python 中逐个导入 `ssl` / `sqlite3` / `lzma` 等模块，返回导入失败的项。
+pub fn probe_system_libraries(python: &Path, os_release: Option<&str>) -> Result<Vec<MissingSystemLibrary>, String> {
+    let output = Command::new(python)
+        .arg("-c")
+        .arg(PROBE_SCRIPT)
+        .args(PROBE_MODULES.iter().map(|(module, _)| module))
+        .output()
+        .map_err(|err| format!("运行时自检启动失败: {err}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        return Err(format!("运行时自检失败: {stderr}"));
+    }
+    let manager = os_release.and_then(detect_package_manager);
+    parse_probe_output(&String::from_utf8_lossy(&output.stdout), manager)
+}
+
+pub fn parse_probe_output(stdout: &str, manager: Option<PackageManager>) -> Result<Vec<MissingSystemLibrary>, String> {
+    let failed: Value = serde_json::from_str(stdout.trim()).map_err(|err| format!("运行时自检输出无法解析: {err}"))?;
+    let Some(failed) = failed.as_object() else {
+        return Err("运行时自检输出格式错误".to_string());
+    };
+    Ok(PROBE_MODULES
+        .iter()
+        .filter_map(|(module, library)| {
+            let error = failed.get(*module)?.as_str().unwrap_or_default().to_string();
+            Some(MissingSystemLibrary {
+                module: module.to_string(),
+                library: library.to_string(),
+                error,
+                install_hint: manager.map(|manager| install_command(manager, module)),
+            })
+        })
+        .collect())
+}
+
+/// 根据 `/etc/os-release` 的 `ID` / `ID_LIKE` 判断包管理器。
+pub fn detect_package_manager(os_release: &str) -> Option<PackageManager> {
+    let mut ids = Vec::new();
+    for line in os_release.lines() {
+        if let Some(value) = line.strip_prefix("ID=").or_else(|| line.strip_prefix("ID_LIKE=")) {
+            ids.extend(value.trim_matches('"').split_whitespace().map(str::to_lowercase));
+        }
+    }
+    ids.iter().find_map(|id| match id.as_str() {
+        "debian" | "ubuntu" => Some(PackageManager::Apt),
+        "fedora" | "rhel" | "centos" | "rocky" | "almalinux" => Some(PackageManager::Dnf),
+        "alpine" => Some(PackageManager::Apk),
+        "arch" | "manjaro" => Some(PackageManager::Pacman),
+        "suse" | "opensuse" | "opensuse-leap" | "opensuse-tumbleweed" | "sles" => Some(PackageManager::Zypper),
+        _ => None,
+    })
+}
+
+fn install_command(manager: PackageManager, module: &str) -> String {
+    // 与 PROBE_MODULES 顺序一致：ssl, sqlite3, lzma, zlib, ctypes。
+    let (command, packages) = match manager {
+        PackageManager::Apt => (
+            "sudo apt-get install -y",
+            ["libssl3", "libsqlite3-0", "liblzma5", "zlib1g", "libffi8"],
+        ),
+        PackageManager::Dnf => (
+            "sudo dnf install -y",
+            ["openssl-libs", "sqlite-libs", "xz-libs", "zlib", "libffi"],
+        ),
+        PackageManager::Apk => ("sudo apk add", ["libssl3", "sqlite-libs", "xz-libs", "zlib", "libffi"]),
+        PackageManager::Pacman => ("sudo pacman -S --needed", ["openssl", "sqlite", "xz", "zlib", "libffi"]),
+        PackageManager::Zypper => (
+            "sudo zypper install -y",
+            ["libopenssl3", "libsqlite3-0", "liblzma5", "libz1", "libffi8"],
+        ),
+    };
+    let index = PROBE_MODULES
+        .iter()
+        .position(|(name, _)| *name == module)
+        .unwrap_or_default();
+    format!("{command} {}", packages[index])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{detect_package_manager, parse_probe_output, PackageManager};
+
+    #[test]
+    fn maps_failed_imports_to_distro_packages() {
+        let os_release = "NAME=\"Rocky Linux\"\nID=\"rocky\"\nID_LIKE=\"rhel centos fedora\"\n";
+        let manager = detect_package_manager(os_release);
+        assert_eq!(manager, Some(PackageManager::Dnf));
+        assert_eq!(detect_package_manager("ID=ubuntu\nID_LIKE=debian\n"), Some(PackageManager::Apt));
+        assert_eq!(detect_package_manager("ID=nixos\n"), None);
+
+        let missing = parse_probe_output(
+            r#"{"ssl": "ImportError: libssl.so.3: cannot open shared object file", "lzma": "ModuleNotFoundError: No module named '_lzma'"}"#,
+            manager,
+        )
+        .expect("parse probe output");
+        assert_eq!(missing.len(), 2);
+        assert_eq!(missing[0].module, "ssl");
+        assert_eq!(missing[0].install_hint.as_deref(), Some("sudo dnf install -y openssl-libs"));
+        assert_eq!(missing[1].install_hint.as_deref(), Some("sudo dnf install -y xz-libs"));
+        assert!(parse_probe_output("{}", None).expect("empty output").is_empty());
+    }
+}
@@ -0,0 +1,370 @@
+use crate::job_queue::JobSummary;
+use chrono::{DateTime, Duration, Local, NaiveDateTime, SecondsFormat, TimeZone, Utc};
+use croner::Cron;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fs;
+#[cfg(unix)]
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+
+/// 等待定时发送的任务；发送参数原样保存（含 SMTP 密码，文件仅本机用户可读），到时后提交到任务队列。
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct ScheduledJob {
+    pub job_id: String,
+    /// UTC，RFC 3339。
+    pub scheduled_at: String,
+    pub payload: Value,
+}
+
+impl ScheduledJob {
+    pub fn due_at(&self) -> Option<DateTime<Utc>> {
+        DateTime::parse_from_rfc3339(&self.scheduled_at)
+            .ok()
+            .map(|at| at.with_timezone(&Utc))
+    }
+
+    pub fn summary(&self) -> JobSummary {
+        JobSummary {
+            scheduled_at: Some(self.scheduled_at.clone()),
+            ..JobSummary::from_payload(&self.job_id, &self.payload)
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Default)]
+pub struct Schedule {
+    #[serde(default)]
+    pub jobs: Vec<ScheduledJob>,
+}
+
+impl Schedule {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        if !path.exists() {
+            return Ok(Schedule::default());
+        }
+        let text = fs::read_to_string(path).map_err(|err| format!("读取定时任务失败: {err}"))?;
+        serde_json::from_str(&text).map_err(|err| format!("定时任务文件格式错误: {err}"))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        save_private(path, self, "定时任务")
+    }
+
+    pub fn contains(&self, job_id: &str) -> bool {
+        self.jobs.iter().any(|job| job.job_id == job_id)
+    }
+
+    /// 按发送时间排序插入。
+    pub fn add(&mut self, job: ScheduledJob) {
+        let index = self.jobs.partition_point(|queued| queued.due_at() <= job.due_at());
+        self.jobs.insert(index, job);
+    }
+
+    pub fn remove(&mut self, job_id: &str) -> Option<ScheduledJob> {
+        let index = self.jobs.iter().position(|job| job.job_id == job_id)?;
+        Some(self.jobs.remove(index))
+    }
+
+    /// 取出已到发送时间的任务（应用未运行期间错过的任务同样取出，启动后立即发送）。
+    pub fn take_due(&mut self, now: DateTime<Utc>) -> Vec<ScheduledJob> {
+        let (due, pending) = self
+            .jobs
+            .drain(..)
+            .partition(|job| job.due_at().is_none_or(|at| at <= now));
+        self.jobs = pending;
+        due
+    }
+}
+
+/// 解析定时发送时间：RFC 3339，或本地时间 `YYYY-MM-DD HH:MM[:SS]`（日期与时间之间也可用 `T`）。
+pub fn parse_scheduled_at(value: &str) -> Result<DateTime<Utc>, String> {
+    let value = value.trim();
+    if let Ok(at) = DateTime::parse_from_rfc3339(value) {
+        return Ok(at.with_timezone(&Utc));
+    }
+    let normalized = value.replacen('T', " ", 1);
+    let local = ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(&normalized, format).ok())
+        .ok_or_else(|| format!("无法识别的定时发送时间: {value}"))?;
+    Local
+        .from_local_datetime(&local)
+        .earliest()
+        .map(|at| at.with_timezone(&Utc))
+        .ok_or_else(|| format!("无法识别的定时发送时间: {value}"))
+}
+
+pub fn format_scheduled_at(at: DateTime<Utc>) -> String {
+    at.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// 应用未运行期间错过的周期运行，只补发这段时间内最近的一次。
+const RECURRING_CATCH_UP_HOURS: i64 = 24;
+
+/// 按 cron 表达式周期运行的群发任务；每次运行使用独立的任务 ID（`{id}-{本地时间 YYYYMMDD-HHMM}`），
+/// 发送记录按次分开，且每次都重新发送给全部收件人。
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct RecurringCampaign {
+    /// 为空时保存时自动生成。
+    #[serde(default)]
+    pub id: String,
+    #[serde(default)]
+    pub name: String,
+    /// 五段式 cron 表达式（分 时 日 月 周），按本地时间计算，如 `0 9 * * MON`。
+    pub cron: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    pub payload: Value,
+    /// 最近一次运行的计划时间（UTC，RFC 3339）。
+    #[serde(default)]
+    pub last_run: Option<String>,
+    #[serde(default)]
+    pub created_at: String,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// 周期任务列表中显示的摘要，不含发送参数。
+#[derive(Serialize, Clone, Debug)]
+pub struct RecurringCampaignSummary {
+    pub id: String,
+    pub name: String,
+    pub cron: String,
+    pub enabled: bool,
+    pub subject: String,
+    pub recipients: usize,
+    pub last_run: Option<String>,
+    pub next_run: Option<String>,
+}
+
+impl RecurringCampaign {
+    fn schedule(&self) -> Result<Cron, String> {
+        Cron::new(self.cron.trim())
+            .parse()
+            .map_err(|err| format!("cron 表达式无效（{}）: {err}", self.cron))
+    }
+
+    /// `after` 之后（不含）的下一次运行时间。
+    pub fn next_run(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let next = self
+            .schedule()
+            .ok()?
+            .find_next_occurrence(&after.with_timezone(&Local), false)
+            .ok()?;
+        Some(next.with_timezone(&Utc))
+    }
+
+    /// 已到运行时间且尚未运行的最近一次计划时间。
+    pub fn due_occurrence(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        if !self.enabled {
+            return None;
+        }
+        let parse = |value: &str| DateTime::parse_from_rfc3339(value).ok().map(|at| at.with_timezone(&Utc));
+        let since = self
+            .last_run
+            .as_deref()
+            .and_then(parse)
+            .or_else(|| parse(&self.created_at))
+            .unwrap_or(now)
+            .max(now - Duration::hours(RECURRING_CATCH_UP_HOURS));
+        let mut due = self.next_run(since).filter(|at| *at <= now)?;
+        while let Some(next) = self.next_run(due).filter(|at| *at <= now) {
+            due = next;
+        }
+        Some(due)
+    }
+
+    pub fn occurrence_job_id(&self, at: DateTime<Utc>) -> String {
+        format!("{}-{}", self.id, at.with_timezone(&Local).format("%Y%m%d-%H%M"))
+    }
+
+    /// 单次运行的发送参数：使用本次的任务 ID，并关闭“跳过已发送”。
+    pub fn occurrence_payload(&self, at: DateTime<Utc>) -> Value {
+        let mut payload = self.payload.clone();
+        if let Some(fields) = payload.as_object_mut() {
+            fields.remove("scheduled_at");
+            fields.remove("resume_campaign");
+            fields.insert("job_id".to_string(), json!(self.occurrence_job_id(at)));
+            let options = fields.entry("options").or_insert_with(|| json!({}));
+            if let Some(options) = options.as_object_mut() {
+                options.insert("skip_sent".to_string(), json!(false));
+            }
+        }
+        payload
+    }
+
+    pub fn summary(&self, now: DateTime<Utc>) -> RecurringCampaignSummary {
+        let job = JobSummary::from_payload(&self.id, &self.payload);
+        RecurringCampaignSummary {
+            id: self.id.clone(),
+            name: self.name.clone(),
+            cron: self.cron.clone(),
+            enabled: self.enabled,
+            subject: job.subject,
+            recipients: job.recipients,
+            last_run: self.last_run.clone(),
+            next_run: self.next_run(now).filter(|_| self.enabled).map(format_scheduled_at),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Default)]
+pub struct RecurringCampaigns {
+    #[serde(default)]
+    pub campaigns: Vec<RecurringCampaign>,
+}
+
+impl RecurringCampaigns {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        if !path.exists() {
+            return Ok(RecurringCampaigns::default());
+        }
+        let text = fs::read_to_string(path).map_err(|err| format!("读取周期任务失败: {err}"))?;
+        serde_json::from_str(&text).map_err(|err| format!("周期任务文件格式错误: {err}"))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        save_private(path, self, "周期任务")
+    }
+
+    /// 新增或替换同 ID 的周期任务，返回任务 ID；替换时保留创建时间与最近运行时间。
+    pub fn upsert(&mut self, mut campaign: RecurringCampaign, now: DateTime<Utc>) -> Result<String, String> {
+        campaign.schedule()?;
+        campaign.id = campaign.id.trim().to_string();
+        if campaign.id.is_empty() {
+            let stamp = now.with_timezone(&Local).format("%Y%m%d%H%M%S");
+            campaign.id = (1..)
+                .map(|n| if n == 1 { format!("recurring-{stamp}") } else { format!("recurring-{stamp}-{n}") })
+                .find(|id| !self.campaigns.iter().any(|existing| &existing.id == id))
+                .unwrap_or_default();
+        }
+        if !campaign.id.chars().all(|ch| ch.is_ascii_alphanumeric() || ch == '-' || ch == '_') {
+            return Err(format!("周期任务 ID 只能包含字母、数字、- 与 _: {}", campaign.id));
+        }
+        let id = campaign.id.clone();
+        match self.campaigns.iter_mut().find(|existing| existing.id == id) {
+            Some(existing) => {
+                campaign.created_at = existing.created_at.clone();
+                campaign.last_run = existing.last_run.clone();
+                *existing = campaign;
+            }
+            None => {
+                campaign.created_at = format_scheduled_at(now);
+                campaign.last_run = None;
+                self.campaigns.push(campaign);
+            }
+        }
+        Ok(id)
+    }
+
+    pub fn remove(&mut self, id: &str) -> Option<RecurringCampaign> {
+        let index = self.campaigns.iter().position(|campaign| campaign.id == id)?;
+        Some(self.campaigns.remove(index))
+    }
+
+    /// 取出本次到时的运行（任务 ID 与发送参数），并把对应任务的最近运行时间记为本次计划时间。
+    pub fn take_due(&mut self, now: DateTime<Utc>) -> Vec<(String, Value)> {
+        let mut due = Vec::new();
+        for campaign in &mut self.campaigns {
+            if let Some(at) = campaign.due_occurrence(now) {
+                campaign.last_run = Some(format_scheduled_at(at));
+                due.push((campaign.occurrence_job_id(at), campaign.occurrence_payload(at)));
+            }
+        }
+        due
+    }
+}
+
+/// 写入含发送参数（SMTP 密码）的文件，仅本机用户可读。
+pub fn save_private<T: Serialize>(path: &Path, value: &T, label: &str) -> Result<(), String> {
+    let text = serde_json::to_string_pretty(value).map_err(|err| err.to_string())?;
+    fs::write(path, text).map_err(|err| format!("写入{label}失败: {err}"))?;
+    #[cfg(unix)]
+    fs::set_permissions(path, fs::Permissions::from_mode(0o600))
+        .map_err(|err| format!("设置{label}文件权限失败: {err}"))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_scheduled_at, RecurringCampaign, RecurringCampaigns, Schedule, ScheduledJob};
+    use chrono::{DateTime, Duration, Utc};
+    use serde_json::json;
+
+    fn scheduled(job_id: &str, at: &str) -> ScheduledJob {
+        ScheduledJob {
+            job_id: job_id.to_string(),
+            scheduled_at: at.to_string(),
+            payload: json!({ "recipients": [] }),
+        }
+    }
+
+    #[test]
+    fn releases_jobs_when_due() {
+        let mut schedule = Schedule::default();
+        schedule.add(scheduled("job-late", "2024-03-02T09:00:00Z"));
+        schedule.add(scheduled("job-early", "2024-03-01T09:00:00Z"));
+        schedule.add(scheduled("job-later", "2024-03-03T09:00:00Z"));
+        let order: Vec<&str> = schedule.jobs.iter().map(|job| job.job_id.as_str()).collect();
+        assert_eq!(order, ["job-early", "job-late", "job-later"]);
+
+        let now: DateTime<Utc> = "2024-03-02T09:00:00Z".parse().expect("now");
+        let due: Vec<String> = schedule.take_due(now).into_iter().map(|job| job.job_id).collect();
+        assert_eq!(due, ["job-early", "job-late"]);
+        assert!(schedule.take_due(now).is_empty());
+        assert!(schedule.contains("job-later"));
+        assert!(schedule.remove("job-later").is_some());
+        assert!(schedule.jobs.is_empty());
+
+        let at = parse_scheduled_at("2024-03-01T08:00:00+08:00").expect("rfc3339");
+        assert_eq!(at, "2024-03-01T00:00:00Z".parse::<DateTime<Utc>>().expect("utc"));
+        let local = parse_scheduled_at("2024-03-01 08:00").expect("local");
+        assert_eq!(parse_scheduled_at("2024-03-01T08:00:00").expect("local seconds"), local);
+        assert!(local - at < Duration::days(1) && at - local < Duration::days(1));
+        assert!(parse_scheduled_at("tomorrow").is_err());
+    }
+
+    #[test]
+    fn runs_recurring_campaigns_once_per_occurrence() {
+        let created: DateTime<Utc> = "2024-03-01T00:00:30Z".parse().expect("created");
+        let mut campaigns = RecurringCampaigns::default();
+        let campaign = RecurringCampaign {
+            id: String::new(),
+            name: "每小时提醒".to_string(),
+            cron: "0 * * * *".to_string(),
+            enabled: true,
+            payload: json!({ "recipients": [], "options": { "skip_sent": true }, "scheduled_at": "x" }),
+            last_run: None,
+            created_at: String::new(),
+        };
+        let id = campaigns.upsert(campaign.clone(), created).expect("upsert");
+        assert!(id.starts_with("recurring-"));
+        assert!(campaigns.take_due(created).is_empty());
+
+        // 错过三次运行时只补发最近的一次。
+        let now = created + Duration::minutes(200);
+        let due = campaigns.take_due(now);
+        assert_eq!(due.len(), 1);
+        let (job_id, payload) = &due[0];
+        assert!(job_id.starts_with(&format!("{id}-202")));
+        assert_eq!(payload["job_id"], json!(job_id));
+        assert_eq!(payload["options"]["skip_sent"], json!(false));
+        assert!(payload.get("scheduled_at").is_none());
+        assert!(campaigns.take_due(now).is_empty());
+        assert_eq!(campaigns.take_due(now + Duration::hours(1)).len(), 1);
+
+        let last_run = campaigns.campaigns[0].last_run.clone();
+        let edited = RecurringCampaign { id: id.clone(), enabled: false, ..campaign.clone() };
+        campaigns.upsert(edited, now).expect("edit");
+        assert_eq!(campaigns.campaigns[0].last_run, last_run);
+        assert!(campaigns.take_due(now + Duration::hours(2)).is_empty());
+        assert!(campaigns.campaigns[0].summary(now).next_run.is_none());
+
+        let invalid = RecurringCampaign { cron: "61 * * * *".to_string(), ..campaign };
+        assert!(campaigns.upsert(invalid, now).is_err());
+        assert!(campaigns.remove(&id).is_some());
+    }
+}
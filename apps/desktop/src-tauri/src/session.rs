@@ -0,0 +1,145 @@
+use chrono::{Local, SecondsFormat};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// 会话状态文件的结构版本；结构变化时递增，旧版本文件按无效处理。
+pub const SESSION_SCHEMA_VERSION: u32 = 1;
+/// 活动草稿序列化后的大小上限，避免把整份收件人列表塞进会话状态。
+const MAX_DRAFT_BYTES: usize = 256 * 1024;
+const MAX_SCROLL_TOKEN_LEN: usize = 256;
+
+/// 上次加载的收件人来源：只记录文件引用，重新打开时由前端重新加载。
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct RecipientSource {
+    pub path: String,
+}
+
+/// 界面恢复所需的最小后端状态：选中的发件账号、收件人来源、活动草稿与滚动位置。
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Default)]
+#[serde(deny_unknown_fields)]
+pub struct SessionState {
+    #[serde(default)]
+    pub version: u32,
+    #[serde(default)]
+    pub selected_profile: Option<String>,
+    #[serde(default)]
+    pub recipient_source: Option<RecipientSource>,
+    /// 前端的活动草稿（JSON 对象，结构由前端决定）。
+    #[serde(default)]
+    pub campaign_draft: Option<Map<String, Value>>,
+    /// 前端自定义的滚动位置标记（如 `recipients:120`）。
+    #[serde(default)]
+    pub scroll_token: Option<String>,
+    /// 保存时间，由后端写入。
+    #[serde(default)]
+    pub saved_at: Option<String>,
+}
+
+impl SessionState {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.version != SESSION_SCHEMA_VERSION {
+            return Err(format!(
+                "会话状态版本不受支持: {}（当前为 {SESSION_SCHEMA_VERSION}）",
+                self.version
+            ));
+        }
+        if self.selected_profile.as_deref().is_some_and(|id| id.trim().is_empty()) {
+            return Err("会话状态中的发件账号不能为空字符串".to_string());
+        }
+        if self.recipient_source.as_ref().is_some_and(|source| source.path.trim().is_empty()) {
+            return Err("会话状态中的收件人文件路径不能为空".to_string());
+        }
+        if let Some(draft) = &self.campaign_draft {
+            let size = serde_json::to_vec(draft).map_err(|err| err.to_string())?.len();
+            if size > MAX_DRAFT_BYTES {
+                return Err(format!("活动草稿过大（{size} 字节），上限为 {MAX_DRAFT_BYTES} 字节"));
+            }
+        }
+        if self
+            .scroll_token
+            .as_ref()
+            .is_some_and(|token| token.len() > MAX_SCROLL_TOKEN_LEN)
+        {
+            return Err(format!("滚动位置标记不能超过 {MAX_SCROLL_TOKEN_LEN} 个字符"));
+        }
+        Ok(())
+    }
+
+    /// 读取会话状态；文件不存在时返回空状态（当前版本）。
+    /// 内容损坏或不符合结构时把原文件改名为 `.invalid` 备份，避免每次启动都失败。
+    pub fn load(path: &Path) -> Result<Self, String> {
+        if !path.exists() {
+            return Ok(SessionState {
+                version: SESSION_SCHEMA_VERSION,
+                ..SessionState::default()
+            });
+        }
+        let text = fs::read_to_string(path).map_err(|err| format!("读取会话状态失败: {err}"))?;
+        let parsed = serde_json::from_str::<SessionState>(&text)
+            .map_err(|err| format!("会话状态格式错误: {err}"))
+            .and_then(|state| state.validate().map(|()| state));
+        parsed.map_err(|err| {
+            let backup = invalid_backup_path(path);
+            match fs::rename(path, &backup) {
+                Ok(()) => format!("{err}，已备份为 {}", backup.display()),
+                Err(_) => err,
+            }
+        })
+    }
+
+    /// 先写临时文件再替换，应用在写入途中崩溃也不会留下半个文件。
+    pub fn save(&mut self, path: &Path) -> Result<(), String> {
+        self.validate()?;
+        self.saved_at = Some(Local::now().to_rfc3339_opts(SecondsFormat::Secs, false));
+        let text = serde_json::to_string_pretty(self).map_err(|err| err.to_string())?;
+        let staging = path.with_extension("json.tmp");
+        fs::write(&staging, text).map_err(|err| format!("写入会话状态失败: {err}"))?;
+        fs::rename(&staging, path).map_err(|err| format!("写入会话状态失败: {err}"))
+    }
+}
+
+fn invalid_backup_path(path: &Path) -> PathBuf {
+    path.with_extension("json.invalid")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RecipientSource, SessionState, SESSION_SCHEMA_VERSION};
+    use serde_json::json;
+
+    #[test]
+    fn round_trips_and_quarantines_invalid_state() {
+        let dir = std::env::temp_dir().join(format!("bes-session-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("temp dir");
+        let path = dir.join("session_state.json");
+        assert_eq!(SessionState::load(&path).expect("empty").version, SESSION_SCHEMA_VERSION);
+
+        let mut state = SessionState {
+            version: SESSION_SCHEMA_VERSION,
+            selected_profile: Some("acct-1".to_string()),
+            recipient_source: Some(RecipientSource {
+                path: "/tmp/teachers.xlsx".to_string(),
+            }),
+            campaign_draft: json!({ "subject": "招生咨询" }).as_object().cloned(),
+            scroll_token: Some("recipients:120".to_string()),
+            saved_at: None,
+        };
+        state.save(&path).expect("save");
+        let loaded = SessionState::load(&path).expect("load");
+        assert_eq!(loaded.selected_profile.as_deref(), Some("acct-1"));
+        assert_eq!(loaded.campaign_draft, state.campaign_draft);
+        assert!(loaded.saved_at.is_some());
+
+        std::fs::write(&path, r#"{"version": 1, "unknown_field": true}"#).expect("write invalid");
+        assert!(SessionState::load(&path).is_err());
+        assert!(!path.exists());
+        assert!(dir.join("session_state.json.invalid").exists());
+
+        state.version = SESSION_SCHEMA_VERSION + 1;
+        assert!(state.save(&path).is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
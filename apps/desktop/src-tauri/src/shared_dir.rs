@@ -0,0 +1,323 @@
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 锁文件所在的子目录。
+const LOCKS_DIR: &str = "locks";
+/// 超过该时长没有心跳的锁视为持有者已崩溃，可被接管。
+pub const LOCK_STALE_SECS: u64 = 30 * 60;
+/// 两次心跳写入之间的最小间隔，避免每封邮件都改写共享盘上的文件。
+const HEARTBEAT_INTERVAL_SECS: u64 = 60;
+
+/// 共享数据目录设置（保存在本机应用设置中，而不是共享目录里）。
+#[derive(Deserialize, Serialize, Clone, Default, PartialEq, Debug)]
+pub struct SharedDirSettings {
+    /// 数据目录位于团队共享盘：启用协作锁与按用户隔离的草稿。
+    #[serde(default)]
+    pub enabled: bool,
+    /// 记录中显示的用户名；为空时取系统登录名。
+    #[serde(default)]
+    pub user_name: Option<String>,
+}
+
+/// 当前操作人，写入发送记录与锁文件。
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct UserIdentity {
+    pub user: String,
+    pub host: String,
+}
+
+impl UserIdentity {
+    pub fn current(settings: &SharedDirSettings) -> Self {
+        let user = settings
+            .user_name
+            .as_deref()
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(str::to_string)
+            .or_else(|| env_value(&["USERNAME", "USER", "LOGNAME"]))
+            .unwrap_or_else(|| "unknown".to_string());
+        let host = env_value(&["COMPUTERNAME", "HOSTNAME"])
+            .or_else(|| {
+                fs::read_to_string("/etc/hostname")
+                    .ok()
+                    .map(|text| text.trim().to_string())
+                    .filter(|text| !text.is_empty())
+            })
+            .unwrap_or_else(|| "unknown-host".to_string());
+        UserIdentity { user, host }
+    }
+
+    pub fn label(&self) -> String {
+        format!("{}@{}", self.user, self.host)
+    }
+
+    /// 用于按用户区分文件名的安全片段。
+    pub fn file_slug(&self) -> String {
+        self.user
+            .chars()
+            .map(|ch| if ch.is_ascii_alphanumeric() || ch == '-' || ch == '_' { ch } else { '_' })
+            .collect()
+    }
+}
+
+/// 锁文件内容：持有者、进程号与最近一次心跳时间。
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct LockOwner {
+    pub name: String,
+    pub owner: UserIdentity,
+    pub pid: u32,
+    pub acquired_at: u64,
+    pub heartbeat_at: u64,
+    #[serde(default)]
+    pub stale: bool,
+}
+
+/// 共享目录中的协作锁：用 `create_new` 创建 `locks/<name>.lock`。
+/// 网络盘上的 flock 并不可靠，因此以锁文件 + 心跳的方式让多个实例互相避让。
+pub struct DataDirLock {
+    path: PathBuf,
+    owner: LockOwner,
+    last_heartbeat: Mutex<u64>,
+}
+
+impl DataDirLock {
+    pub fn acquire(data_dir: &Path, name: &str, identity: &UserIdentity) -> Result<Self, String> {
+        let path = lock_path(data_dir, name);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|err| format!("创建锁目录失败: {err}"))?;
+        }
+        let now = unix_now();
+        let owner = LockOwner {
+            name: name.to_string(),
+            owner: identity.clone(),
+            pid: std::process::id(),
+            acquired_at: now,
+            heartbeat_at: now,
+            stale: false,
+        };
+        let text = serde_json::to_string_pretty(&owner).map_err(|err| err.to_string())?;
+        for _ in 0..2 {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut file) => {
+                    file.write_all(text.as_bytes())
+                        .map_err(|err| format!("写入锁文件失败: {err}"))?;
+                    return Ok(DataDirLock {
+                        path,
+                        owner,
+                        last_heartbeat: Mutex::new(now),
+                    });
+                }
+                Err(err) if err.kind() == ErrorKind::AlreadyExists => {
+                    let holder = read_lock(&path, now);
+                    match holder {
+                        Some(holder) if !holder.stale => {
+                            return Err(format!(
+                                "{} 正在使用共享数据（{}），请稍后再试",
+                                holder.owner.label(),
+                                holder.name
+                            ));
+                        }
+                        // 持有者心跳超时或锁文件已损坏：接管后重试一次。
+                        _ => {
+                            let _ = fs::remove_file(&path);
+                        }
+                    }
+                }
+                Err(err) => return Err(format!("创建锁文件失败: {err}")),
+            }
+        }
+        Err(format!("无法获取共享数据锁: {name}"))
+    }
+
+    /// 刷新心跳；长时间运行的发送任务在收到事件时调用。
+    pub fn heartbeat(&self) {
+        let now = unix_now();
+        let Ok(mut last) = self.last_heartbeat.lock() else {
+            return;
+        };
+        if now.saturating_sub(*last) < HEARTBEAT_INTERVAL_SECS {
+            return;
+        }
+        *last = now;
+        let owner = LockOwner {
+            heartbeat_at: now,
+            ..self.owner.clone()
+        };
+        if let Ok(text) = serde_json::to_string_pretty(&owner) {
+            let _ = fs::write(&self.path, text);
+        }
+    }
+}
+
+impl Drop for DataDirLock {
+    fn drop(&mut self) {
+        // 只删除自己持有的锁：锁可能已因心跳超时被他人接管。
+        let current = read_lock(&self.path, unix_now());
+        if current.is_some_and(|holder| holder.owner == self.owner.owner && holder.pid == self.owner.pid) {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// 列出共享目录中的全部锁及其是否已超时。
+pub fn list_locks(data_dir: &Path) -> Vec<LockOwner> {
+    let Ok(entries) = fs::read_dir(data_dir.join(LOCKS_DIR)) else {
+        return Vec::new();
+    };
+    let now = unix_now();
+    let mut locks: Vec<LockOwner> = entries
+        .filter_map(Result::ok)
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "lock"))
+        .filter_map(|entry| read_lock(&entry.path(), now))
+        .collect();
+    locks.sort_by(|a, b| a.name.cmp(&b.name));
+    locks
+}
+
+/// 强制解除锁（持有者崩溃且不愿等待超时时使用）。
+pub fn break_lock(data_dir: &Path, name: &str) -> Result<(), String> {
+    let path = lock_path(data_dir, name);
+    if !path.exists() {
+        return Err(format!("锁不存在: {name}"));
+    }
+    fs::remove_file(path).map_err(|err| format!("解除锁失败: {err}"))
+}
+
+/// 读取文件时记下的修改时间与大小，写回前比较以发现他人的并发修改。
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct FileStamp {
+    len: u64,
+    modified_nanos: u128,
+}
+
+impl FileStamp {
+    pub fn capture(path: &Path) -> Option<Self> {
+        let metadata = fs::metadata(path).ok()?;
+        let modified_nanos = metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map(|duration| duration.as_nanos())
+            .unwrap_or_default();
+        Some(FileStamp {
+            len: metadata.len(),
+            modified_nanos,
+        })
+    }
+}
+
+/// 读-改-写期间的冲突检测：保存前调用 `ensure_unchanged`。
+pub struct ConflictGuard {
+    path: PathBuf,
+    stamp: Option<FileStamp>,
+}
+
+impl ConflictGuard {
+    pub fn new(path: &Path) -> Self {
+        ConflictGuard {
+            path: path.to_path_buf(),
+            stamp: FileStamp::capture(path),
+        }
+    }
+
+    pub fn ensure_unchanged(&self) -> Result<(), String> {
+        if FileStamp::capture(&self.path) != self.stamp {
+            let name = self
+                .path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+            return Err(format!("{name} 已被其他用户修改，请刷新后重试"));
+        }
+        Ok(())
+    }
+}
+
+fn lock_path(data_dir: &Path, name: &str) -> PathBuf {
+    let safe: String = name
+        .chars()
+        .map(|ch| if ch.is_ascii_alphanumeric() || ch == '-' || ch == '_' { ch } else { '_' })
+        .collect();
+    data_dir.join(LOCKS_DIR).join(format!("{safe}.lock"))
+}
+
+fn read_lock(path: &Path, now: u64) -> Option<LockOwner> {
+    let text = fs::read_to_string(path).ok()?;
+    let mut owner: LockOwner = serde_json::from_str(&text).ok()?;
+    owner.stale = now.saturating_sub(owner.heartbeat_at) > LOCK_STALE_SECS;
+    Some(owner)
+}
+
+fn env_value(names: &[&str]) -> Option<String> {
+    names
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .map(|value| value.trim().to_string())
+        .find(|value| !value.is_empty())
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{list_locks, ConflictGuard, DataDirLock, UserIdentity, LOCK_STALE_SECS};
+
+    #[test]
+    fn locks_are_exclusive_and_stale_locks_are_taken_over() {
+        let dir = std::env::temp_dir().join(format!("bes-shared-lock-{}", std::process::id()));
+        let alice = UserIdentity {
+            user: "alice".to_string(),
+            host: "PC-01".to_string(),
+        };
+        let bob = UserIdentity {
+            user: "bob".to_string(),
+            host: "PC-02".to_string(),
+        };
+
+        let held = DataDirLock::acquire(&dir, "send", &alice).expect("alice acquires");
+        let err = DataDirLock::acquire(&dir, "send", &bob).err().expect("bob is blocked");
+        assert!(err.contains("alice@PC-01"));
+        assert_eq!(list_locks(&dir).len(), 1);
+        drop(held);
+        assert!(list_locks(&dir).is_empty());
+
+        // 模拟 alice 崩溃后遗留的过期锁。
+        let crashed = DataDirLock::acquire(&dir, "send", &alice).expect("alice acquires again");
+        let path = dir.join("locks").join("send.lock");
+        let text = std::fs::read_to_string(&path).expect("read lock");
+        let mut owner: serde_json::Value = serde_json::from_str(&text).expect("lock json");
+        let expired = owner["heartbeat_at"].as_u64().unwrap_or_default() - LOCK_STALE_SECS - 1;
+        owner["heartbeat_at"] = serde_json::json!(expired);
+        std::fs::write(&path, owner.to_string()).expect("age lock");
+        std::mem::forget(crashed);
+        assert!(list_locks(&dir)[0].stale);
+        let taken = DataDirLock::acquire(&dir, "send", &bob).expect("bob takes over stale lock");
+        drop(taken);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn detects_concurrent_modification() {
+        let dir = std::env::temp_dir().join(format!("bes-shared-conflict-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("temp dir");
+        let path = dir.join("suppression.json");
+        std::fs::write(&path, "{}").expect("write store");
+        let untouched = ConflictGuard::new(&path);
+        assert!(untouched.ensure_unchanged().is_ok());
+
+        let guard = ConflictGuard::new(&path);
+        std::fs::write(&path, "{\"entries\": []}").expect("other user writes");
+        let result = guard.ensure_unchanged();
+        let _ = std::fs::remove_dir_all(&dir);
+        assert!(result.expect_err("conflict").contains("suppression.json"));
+    }
+}
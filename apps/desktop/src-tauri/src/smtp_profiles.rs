@@ -0,0 +1,155 @@
+use bulk_email_core::smtp_client::SmtpPayload;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// 系统钥匙串中的服务名；条目的账户名为 `smtp-profile:<配置名>`。
+const KEYRING_SERVICE: &str = "bulk-email-sender";
+const MAX_PROFILE_NAME_CHARS: usize = 64;
+
+/// 命名的 SMTP 服务器配置（如「工作」「个人」「中继」）。文件中不保存密码，密码存放在系统钥匙串。
+#[derive(Deserialize, Serialize, Clone)]
+pub struct SmtpProfile {
+    pub name: String,
+    pub smtp: SmtpPayload,
+}
+
+#[derive(Deserialize, Serialize, Default)]
+pub struct SmtpProfileStore {
+    pub profiles: Vec<SmtpProfile>,
+}
+
+impl SmtpProfileStore {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        if !path.exists() {
+            return Ok(SmtpProfileStore::default());
+        }
+        let text = fs::read_to_string(path).map_err(|err| format!("读取 SMTP 配置失败: {err}"))?;
+        serde_json::from_str(&text).map_err(|err| format!("SMTP 配置文件格式错误: {err}"))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let text = serde_json::to_string_pretty(self).map_err(|err| err.to_string())?;
+        fs::write(path, text).map_err(|err| format!("写入 SMTP 配置失败: {err}"))
+    }
+
+    /// 新增或按名称覆盖配置，返回规范化后的名称；保存到文件的副本清空密码。
+    pub fn upsert(&mut self, name: &str, smtp: &SmtpPayload) -> Result<String, String> {
+        let name = validate_profile_name(name)?;
+        if smtp.host.trim().is_empty() {
+            return Err("SMTP 主机不能为空".to_string());
+        }
+        if smtp.use_ssl && smtp.use_starttls {
+            return Err("SMTP 配置冲突：use_ssl 与 use_starttls 不能同时开启".to_string());
+        }
+        smtp.validate_envelope()?;
+        let profile = SmtpProfile {
+            name: name.clone(),
+            smtp: SmtpPayload {
+                password: String::new(),
+                ..smtp.clone()
+            },
+        };
+        match self.profiles.iter_mut().find(|item| item.name == name) {
+            Some(existing) => *existing = profile,
+            None => self.profiles.push(profile),
+        }
+        Ok(name)
+    }
+
+    pub fn remove(&mut self, name: &str) -> bool {
+        let before = self.profiles.len();
+        self.profiles.retain(|item| item.name != name.trim());
+        before != self.profiles.len()
+    }
+}
+
+fn validate_profile_name(name: &str) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("SMTP 配置名称不能为空".to_string());
+    }
+    if name.chars().count() > MAX_PROFILE_NAME_CHARS {
+        return Err(format!("SMTP 配置名称不能超过 {MAX_PROFILE_NAME_CHARS} 个字符"));
+    }
+    if name.chars().any(char::is_control) {
+        return Err("SMTP 配置名称不能包含控制字符".to_string());
+    }
+    Ok(name.to_string())
+}
+
+fn keyring_entry(name: &str) -> Result<keyring::Entry, String> {
+    keyring::Entry::new(KEYRING_SERVICE, &format!("smtp-profile:{name}"))
+        .map_err(|err| format!("访问系统钥匙串失败: {err}"))
+}
+
+/// 把配置的密码写入系统钥匙串。钥匙串不可用时返回错误，不会退回到明文保存。
+pub fn store_password(name: &str, password: &str) -> Result<(), String> {
+    keyring_entry(name)?
+        .set_password(password)
+        .map_err(|err| format!("保存密码到系统钥匙串失败: {err}"))
+}
+
+/// 从系统钥匙串读取配置的密码；没有保存过密码时返回空字符串。
+pub fn load_password(name: &str) -> Result<String, String> {
+    match keyring_entry(name)?.get_password() {
+        Ok(password) => Ok(password),
+        Err(keyring::Error::NoEntry) => Ok(String::new()),
+        Err(err) => Err(format!("从系统钥匙串读取密码失败: {err}")),
+    }
+}
+
+/// 删除系统钥匙串中配置的密码；本来就没有时视为成功。
+pub fn delete_password(name: &str) -> Result<(), String> {
+    match keyring_entry(name)?.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(err) => Err(format!("从系统钥匙串删除密码失败: {err}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SmtpProfileStore;
+    use bulk_email_core::smtp_client::SmtpPayload;
+
+    #[test]
+    fn saves_named_profiles_without_passwords() {
+        let dir = std::env::temp_dir().join(format!("bes-smtp-profiles-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("temp dir");
+        let path = dir.join("smtp_profiles.json");
+        let smtp = SmtpPayload {
+            host: "smtp.example.com".to_string(),
+            port: 465,
+            username: "me@example.com".to_string(),
+            password: "secret".to_string(),
+            use_ssl: true,
+            timeout_sec: 30,
+            ..SmtpPayload::default()
+        };
+
+        let mut store = SmtpProfileStore::load(&path).expect("empty store");
+        assert_eq!(store.upsert("  工作 ", &smtp).expect("saved"), "工作");
+        let relay = SmtpPayload {
+            host: "relay.example.com".to_string(),
+            port: 25,
+            use_ssl: false,
+            ..smtp.clone()
+        };
+        store.upsert("relay", &relay).expect("saved");
+        store.upsert("工作", &SmtpPayload { port: 587, ..smtp.clone() }).expect("overwritten");
+        store.save(&path).expect("written");
+
+        let text = std::fs::read_to_string(&path).expect("read back");
+        assert!(!text.contains("secret"), "{text}");
+        let mut store = SmtpProfileStore::load(&path).expect("reloaded");
+        let _ = std::fs::remove_dir_all(&dir);
+        let names: Vec<(&str, u16)> = store.profiles.iter().map(|item| (item.name.as_str(), item.smtp.port)).collect();
+        assert_eq!(names, [("工作", 587), ("relay", 25)]);
+
+        assert!(store.upsert(" ", &smtp).is_err());
+        assert!(store.upsert("both", &SmtpPayload { use_starttls: true, ..smtp.clone() }).is_err());
+        assert!(store.remove(" relay"));
+        assert!(!store.remove("relay"));
+        assert_eq!(store.profiles.len(), 1);
+    }
+}
@@ -0,0 +1,178 @@
+use flate2::read::GzDecoder;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use zip::ZipArchive;
+
+/// 默认从 GitHub Releases 下载 uv；可在运行时配置中改为内网镜像。
+pub const DEFAULT_UV_DOWNLOAD_BASE: &str = "https://github.com/astral-sh/uv/releases/latest/download";
+
+/// 下载进度回调的最小间隔（字节）。
+const PROGRESS_STEP_BYTES: u64 = 512 * 1024;
+
+/// uv 官方发布包使用的目标三元组。
+pub fn uv_target_triple(os: &str, arch: &str) -> Option<&'static str> {
+    match (os, arch) {
+        ("linux", "x86_64") => Some("x86_64-unknown-linux-gnu"),
+        ("linux", "aarch64") => Some("aarch64-unknown-linux-gnu"),
+        ("macos", "x86_64") => Some("x86_64-apple-darwin"),
+        ("macos", "aarch64") => Some("aarch64-apple-darwin"),
+        ("windows", "x86_64") => Some("x86_64-pc-windows-msvc"),
+        ("windows", "aarch64") => Some("aarch64-pc-windows-msvc"),
+        _ => None,
+    }
+}
+
+pub fn uv_archive_name(triple: &str) -> String {
+    if triple.contains("windows") {
+        format!("uv-{triple}.zip")
+    } else {
+        format!("uv-{triple}.tar.gz")
+    }
+}
+
+/// 解析发布包旁的 `.sha256` 文件（`<hex>  <文件名>`）。
+pub fn parse_sha256_file(text: &str) -> Option<String> {
+    let digest = text.split_whitespace().next()?.trim_start_matches('*');
+    (digest.len() == 64 && digest.chars().all(|ch| ch.is_ascii_hexdigit())).then(|| digest.to_ascii_lowercase())
+}
+
+/// 边下载边写入文件，并按 `PROGRESS_STEP_BYTES` 回调已下载 / 总字节数。
+pub fn copy_with_progress(
+    source: &mut dyn Read,
+    destination: &Path,
+    total_bytes: Option<u64>,
+    on_progress: &mut dyn FnMut(u64, Option<u64>),
+) -> Result<u64, String> {
+    let mut target = File::create(destination).map_err(|err| format!("创建下载文件失败: {err}"))?;
+    let mut buffer = [0_u8; 64 * 1024];
+    let mut downloaded: u64 = 0;
+    let mut reported: u64 = 0;
+    loop {
+        let size = source
+            .read(&mut buffer)
+            .map_err(|err| format!("下载中断: {err}"))?;
+        if size == 0 {
+            break;
+        }
+        target
+            .write_all(&buffer[..size])
+            .map_err(|err| format!("写入下载文件失败: {err}"))?;
+        downloaded += size as u64;
+        if downloaded - reported >= PROGRESS_STEP_BYTES {
+            reported = downloaded;
+            on_progress(downloaded, total_bytes);
+        }
+    }
+    on_progress(downloaded, total_bytes);
+    Ok(downloaded)
+}
+
+/// 从发布包中取出 uv 可执行文件，放到 `destination_dir` 下并返回其路径。
+pub fn extract_uv_binary(archive: &Path, destination_dir: &Path) -> Result<PathBuf, String> {
+    let binary_name = if archive.to_string_lossy().ends_with(".zip") {
+        "uv.exe"
+    } else {
+        "uv"
+    };
+    fs::create_dir_all(destination_dir).map_err(|err| format!("创建 uv 目录失败: {err}"))?;
+    let destination = destination_dir.join(binary_name);
+    let mut output = Vec::new();
+
+    let file = File::open(archive).map_err(|err| format!("打开 uv 安装包失败: {err}"))?;
+    if binary_name == "uv.exe" {
+        let mut zip = ZipArchive::new(file).map_err(|err| format!("读取 uv 安装包失败: {err}"))?;
+        let index = (0..zip.len())
+            .find(|index| {
+                zip.by_index(*index)
+                    .map(|entry| entry.name().rsplit('/').next() == Some(binary_name))
+                    .unwrap_or(false)
+            })
+            .ok_or_else(|| "uv 安装包中未找到 uv.exe".to_string())?;
+        zip.by_index(index)
+            .map_err(|err| format!("解压 uv 失败: {err}"))?
+            .read_to_end(&mut output)
+            .map_err(|err| format!("解压 uv 失败: {err}"))?;
+    } else {
+        let mut tar = tar::Archive::new(GzDecoder::new(file));
+        let entries = tar.entries().map_err(|err| format!("读取 uv 安装包失败: {err}"))?;
+        let mut found = false;
+        for entry in entries {
+            let mut entry = entry.map_err(|err| format!("解压 uv 失败: {err}"))?;
+            let is_binary = entry
+                .path()
+                .map(|path| path.file_name().is_some_and(|name| name == binary_name))
+                .unwrap_or(false);
+            if is_binary {
+                entry
+                    .read_to_end(&mut output)
+                    .map_err(|err| format!("解压 uv 失败: {err}"))?;
+                found = true;
+                break;
+            }
+        }
+        if !found {
+            return Err("uv 安装包中未找到 uv 可执行文件".to_string());
+        }
+    }
+
+    fs::write(&destination, output).map_err(|err| format!("写入 uv 可执行文件失败: {err}"))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&destination, fs::Permissions::from_mode(0o755))
+            .map_err(|err| format!("设置 uv 可执行权限失败: {err}"))?;
+    }
+    Ok(destination)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{extract_uv_binary, parse_sha256_file, uv_archive_name, uv_target_triple};
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+
+    #[test]
+    fn resolves_release_assets_and_checksums() {
+        let triple = uv_target_triple("linux", "x86_64").expect("supported target");
+        assert_eq!(uv_archive_name(triple), "uv-x86_64-unknown-linux-gnu.tar.gz");
+        assert_eq!(
+            uv_archive_name(uv_target_triple("windows", "x86_64").expect("supported target")),
+            "uv-x86_64-pc-windows-msvc.zip"
+        );
+        assert!(uv_target_triple("freebsd", "x86_64").is_none());
+
+        let digest = "A".repeat(64);
+        assert_eq!(
+            parse_sha256_file(&format!("{digest} *uv-x86_64-unknown-linux-gnu.tar.gz\n")),
+            Some("a".repeat(64))
+        );
+        assert!(parse_sha256_file("not-a-digest").is_none());
+    }
+
+    #[test]
+    fn extracts_uv_from_tarball() {
+        let dir = std::env::temp_dir().join(format!("bes-uv-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("temp dir");
+        let archive = dir.join("uv-x86_64-unknown-linux-gnu.tar.gz");
+        {
+            let file = std::fs::File::create(&archive).expect("create archive");
+            let mut builder = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+            let content = b"#!/bin/sh\necho uv\n";
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o755);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, "uv-x86_64-unknown-linux-gnu/uv", &content[..])
+                .expect("append uv");
+            builder.into_inner().expect("finish tar").finish().expect("finish gzip");
+        }
+
+        let binary = extract_uv_binary(&archive, &dir.join("bin")).expect("extract uv");
+        let content = std::fs::read_to_string(&binary).expect("read uv");
+        let _ = std::fs::remove_dir_all(&dir);
+        assert!(binary.ends_with("bin/uv"));
+        assert!(content.contains("echo uv"));
+    }
+}
@@ -0,0 +1,296 @@
+use bulk_email_core::worker_ipc::WorkerTransport;
+use bulk_email_core::worker_protocol::DEFAULT_REQUEST_TIMEOUT;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// 参与 worker 环境哈希的文件：依赖声明与 worker 入口。
+const WORKER_HASH_FILES: [&str; 4] = ["pyproject.toml", "uv.lock", "worker-requirements.txt", "worker.py"];
+
+/// 随应用打包的锁定依赖清单，供 `uv run --with` 一次性环境使用。
+pub const WORKER_REQUIREMENTS_FILE: &str = "worker-requirements.txt";
+
+/// 自定义 worker 命令参数中代表 worker 脚本路径的占位符。
+pub const WORKER_SCRIPT_PLACEHOLDER: &str = "{worker}";
+
+/// worker 启动方式设置。
+#[derive(Deserialize, Serialize, Clone, Default, PartialEq, Debug)]
+pub struct WorkerSettings {
+    /// 有 uv 时通过 `uv run --with <锁定依赖>` 启动，不依赖持久虚拟环境。
+    #[serde(default)]
+    pub ephemeral_uv: bool,
+    /// 完全自定义的 worker 启动命令；设置后跳过自动探测。
+    #[serde(default)]
+    pub command_override: Option<WorkerCommandOverride>,
+    /// 与 worker 交换协议消息的通道；`socket` 改走本地套接字（Windows 为命名管道），worker 的标准输出只作调试输出。
+    #[serde(default)]
+    pub transport: WorkerTransport,
+    /// 一问一答请求（如读取收件人文件）等待 worker 应答的秒数，超时后结束 worker；未设置时为 120 秒。
+    #[serde(default)]
+    pub request_timeout_sec: Option<u64>,
+}
+
+impl WorkerSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(command_override) = &self.command_override {
+            command_override.validate()?;
+        }
+        if self.request_timeout_sec == Some(0) {
+            return Err("worker 请求超时必须大于 0 秒".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn request_timeout(&self) -> Duration {
+        self.request_timeout_sec.map_or(DEFAULT_REQUEST_TIMEOUT, Duration::from_secs)
+    }
+}
+
+/// 自定义 worker 命令：解释器（或 conda / pyenv / 公司封装脚本）、参数与额外环境变量。
+/// 参数中的 `{worker}` 会替换为 worker 脚本路径；未出现时追加在末尾。
+#[derive(Deserialize, Serialize, Clone, Default, PartialEq, Debug)]
+pub struct WorkerCommandOverride {
+    pub program: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+}
+
+impl WorkerCommandOverride {
+    pub fn validate(&self) -> Result<(), String> {
+        let program = self.program.trim();
+        if program.is_empty() {
+            return Err("自定义 worker 命令的程序路径不能为空".to_string());
+        }
+        let looks_like_path = program.contains('/') || program.contains('\\');
+        if looks_like_path && !Path::new(program).is_file() {
+            return Err(format!("自定义 worker 程序不存在: {program}"));
+        }
+        let placeholders = self
+            .args
+            .iter()
+            .filter(|arg| arg.contains(WORKER_SCRIPT_PLACEHOLDER))
+            .count();
+        if placeholders > 1 {
+            return Err(format!("参数中只能出现一次 {WORKER_SCRIPT_PLACEHOLDER}"));
+        }
+        for name in self.env.keys() {
+            let name = name.trim();
+            if name.is_empty() || name.contains(['=', '\0']) {
+                return Err(format!("环境变量名无效: {name}"));
+            }
+        }
+        Ok(())
+    }
+
+    /// 展开后的参数列表：替换 `{worker}` 占位符，或在末尾追加 worker 脚本。
+    pub fn expanded_args(&self, worker_script: &Path) -> Vec<String> {
+        let script = worker_script.to_string_lossy();
+        let mut replaced = false;
+        let mut args: Vec<String> = self
+            .args
+            .iter()
+            .map(|arg| {
+                if arg.contains(WORKER_SCRIPT_PLACEHOLDER) {
+                    replaced = true;
+                    arg.replace(WORKER_SCRIPT_PLACEHOLDER, &script)
+                } else {
+                    arg.clone()
+                }
+            })
+            .collect();
+        if !replaced {
+            args.push(script.to_string());
+        }
+        args
+    }
+}
+
+/// worker 虚拟环境目录名：`<应用版本>-<worker 哈希前 12 位>`。
+/// 升级应用会得到新目录，旧版本仍在运行的 worker 不受影响。
+pub fn worker_env_key(app_version: &str, project_root: &Path) -> String {
+    let mut hasher = Sha256::new();
+    for name in WORKER_HASH_FILES {
+        if let Ok(bytes) = fs::read(project_root.join(name)) {
+            hasher.update(name.as_bytes());
+            hasher.update(&bytes);
+        }
+    }
+    let digest = format!("{:x}", hasher.finalize());
+    format!("{}-{}", app_version.trim(), &digest[..12])
+}
+
+/// 找出可以清理的环境：版本低于当前版本，或与当前版本相同但哈希已过期。
+/// 更高版本的环境可能属于并存安装的新版应用，予以保留。
+pub fn stale_worker_envs(envs_root: &Path, current_key: &str) -> Result<Vec<PathBuf>, String> {
+    if !envs_root.exists() {
+        return Ok(Vec::new());
+    }
+    let Some(current_version) = split_env_key(current_key).and_then(|(version, _)| parse_app_version(version))
+    else {
+        return Err(format!("无法解析当前 worker 环境版本: {current_key}"));
+    };
+    let entries = fs::read_dir(envs_root).map_err(|err| format!("读取 worker 环境目录失败: {err}"))?;
+    let mut stale = Vec::new();
+    for entry in entries.filter_map(Result::ok) {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !path.is_dir() || name == current_key {
+            continue;
+        }
+        let Some(version) = split_env_key(&name).and_then(|(version, _)| parse_app_version(version)) else {
+            continue;
+        };
+        if version <= current_version {
+            stale.push(path);
+        }
+    }
+    stale.sort();
+    Ok(stale)
+}
+
+/// 读取锁定依赖清单：每行一个 `name==version`，忽略空行与注释。
+pub fn read_locked_requirements(path: &Path) -> Result<Vec<String>, String> {
+    let text = fs::read_to_string(path).map_err(|err| format!("读取 worker 依赖清单失败: {err}"))?;
+    let mut requirements = Vec::new();
+    for (index, raw) in text.lines().enumerate() {
+        let line = raw.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+        if !line.contains("==") {
+            return Err(format!("worker 依赖清单第 {} 行未锁定版本: {line}", index + 1));
+        }
+        requirements.push(line.to_string());
+    }
+    Ok(requirements)
+}
+
+/// 一次性环境的 `uv run` 参数（到 `python` 为止）；离线时只使用本地缓存。
+pub fn ephemeral_uv_args(requirements: &[String], offline: bool) -> Vec<String> {
+    let mut args = vec!["run".to_string(), "--no-project".to_string()];
+    if offline {
+        args.push("--offline".to_string());
+    }
+    for requirement in requirements {
+        args.push("--with".to_string());
+        args.push(requirement.clone());
+    }
+    args.push("python".to_string());
+    args
+}
+
+fn split_env_key(key: &str) -> Option<(&str, &str)> {
+    key.rsplit_once('-')
+}
+
+fn parse_app_version(version: &str) -> Option<(u64, u64, u64)> {
+    let mut chunks = version.split('.').map(|chunk| chunk.parse::<u64>().ok());
+    let major = chunks.next()??;
+    let minor = chunks.next().unwrap_or(Some(0))?;
+    let patch = chunks.next().unwrap_or(Some(0))?;
+    Some((major, minor, patch))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        ephemeral_uv_args, read_locked_requirements, stale_worker_envs, worker_env_key, WorkerCommandOverride,
+    };
+    use std::collections::BTreeMap;
+    use std::path::Path;
+
+    #[test]
+    fn keeps_current_and_newer_envs_when_cleaning_up() {
+        let dir = std::env::temp_dir().join(format!("bes-worker-envs-{}", std::process::id()));
+        let project = dir.join("project");
+        let envs = dir.join("envs");
+        std::fs::create_dir_all(&project).expect("project dir");
+        std::fs::write(project.join("worker.py"), "print('v1')").expect("write worker");
+        let first = worker_env_key("0.3.0", &project);
+        std::fs::write(project.join("worker.py"), "print('v2')").expect("write worker");
+        let current = worker_env_key("0.3.0", &project);
+        assert_ne!(first, current);
+        assert!(current.starts_with("0.3.0-"));
+
+        for name in [first.as_str(), current.as_str(), "0.2.9-aaaaaaaaaaaa", "0.4.0-bbbbbbbbbbbb", "scratch"] {
+            std::fs::create_dir_all(envs.join(name)).expect("env dir");
+        }
+        let stale = stale_worker_envs(&envs, &current).expect("scan envs");
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let names: Vec<String> = stale
+            .iter()
+            .map(|path| path.file_name().unwrap_or_default().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(names.len(), 2);
+        assert!(names.contains(&first));
+        assert!(names.contains(&"0.2.9-aaaaaaaaaaaa".to_string()));
+    }
+
+    #[test]
+    fn builds_uv_run_args_from_locked_requirements() {
+        let dir = std::env::temp_dir().join(format!("bes-worker-reqs-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("temp dir");
+        let locked = dir.join("locked.txt");
+        std::fs::write(&locked, "# pinned\net-xmlfile==2.0.0\n\nopenpyxl==3.1.5  # xlsx\n").expect("write locked");
+        let loose = dir.join("loose.txt");
+        std::fs::write(&loose, "openpyxl>=3.1\n").expect("write loose");
+
+        let requirements = read_locked_requirements(&locked).expect("locked requirements");
+        let loose_result = read_locked_requirements(&loose);
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert!(loose_result.is_err());
+        assert_eq!(
+            ephemeral_uv_args(&requirements, true),
+            vec![
+                "run",
+                "--no-project",
+                "--offline",
+                "--with",
+                "et-xmlfile==2.0.0",
+                "--with",
+                "openpyxl==3.1.5",
+                "python"
+            ]
+        );
+    }
+
+    #[test]
+    fn expands_worker_placeholder_in_command_override() {
+        let conda = WorkerCommandOverride {
+            program: "conda".to_string(),
+            args: vec!["run".to_string(), "-n".to_string(), "mail".to_string(), "python".to_string(), "{worker}".to_string(), "--quiet".to_string()],
+            env: BTreeMap::from([("PYTHONIOENCODING".to_string(), "utf-8".to_string())]),
+        };
+        conda.validate().expect("valid override");
+        assert_eq!(
+            conda.expanded_args(Path::new("/opt/app/worker.py")),
+            vec!["run", "-n", "mail", "python", "/opt/app/worker.py", "--quiet"]
+        );
+
+        let plain = WorkerCommandOverride {
+            program: "python3".to_string(),
+            args: vec!["-X".to_string(), "utf8".to_string()],
+            env: BTreeMap::new(),
+        };
+        assert_eq!(plain.expanded_args(Path::new("worker.py")), vec!["-X", "utf8", "worker.py"]);
+
+        let invalid = WorkerCommandOverride {
+            program: "/nonexistent/python".to_string(),
+            ..WorkerCommandOverride::default()
+        };
+        assert!(invalid.validate().is_err());
+        let bad_env = WorkerCommandOverride {
+            program: "python3".to_string(),
+            env: BTreeMap::from([("A=B".to_string(), "x".to_string())]),
+            ..WorkerCommandOverride::default()
+        };
+        assert!(bad_env.validate().is_err());
+    }
+}
@@ -1,4 +1,4 @@
-import { useCallback, useEffect, useMemo, useRef, useState } from 'react';
+import { useCallback, useEffect, useEffectEvent, useMemo, useRef, useState } from 'react';
 import { flushSync } from 'react-dom';
 import {
   App,
@@ -14,11 +14,18 @@ import {
   cancelSend,
   clearRuntimePython,
   clearSentRecords,
+  followJob,
   getAppPaths,
+  getCapabilities,
   getRuntimeStatus,
+  listInterruptedJobs,
+  listJobs,
   loadRecipients,
   loadAppDraft,
+  onAlert,
   openPath,
+  preflightDeadDomains,
+  recoverJobs,
   saveAppDraft,
   setDataDir,
   setRuntimePython,
@@ -29,7 +36,18 @@ import { EmailContentWorkspace } from './features/email-content/EmailContentWork
 import { RecipientsWorkspace } from './features/recipients/RecipientsWorkspace';
 import { SettingsWorkspace } from './features/settings/SettingsWorkspace';
 import { SenderSettingsWorkspace } from './features/sender-settings/SenderSettingsWorkspace';
-import type { AppPaths, Recipient, RecipientStats, RuntimeStatus, SendPayload, WorkerEvent } from './types';
+import type {
+  AppPaths,
+  Capabilities,
+  DeadDomainRecipient,
+  Recipient,
+  RecipientStats,
+  RuntimeStatus,
+  SendPayload,
+  SendProgress,
+  SmtpCapabilities,
+  WorkerEvent,
+} from './types';
 import './App.css';
 
 const { Title, Text } = Typography;
@@ -111,6 +129,16 @@ const toErrMsg = (error: unknown, fallback = '操作失败'): string => {
   return fallback;
 };
 
+/** 服务器未声明、任务可能用到的扩展，SMTP 测试成功后提示。 */
+const describeMissingCapabilities = (capabilities: SmtpCapabilities): string[] => {
+  const missing: string[] = [];
+  if (!capabilities.dsn) missing.push('DSN（投递状态通知）');
+  if (!capabilities.smtputf8) missing.push('SMTPUTF8（非 ASCII 邮箱地址）');
+  if (!capabilities.pipelining) missing.push('PIPELINING（命令流水线）');
+  if (capabilities.size_limit === null) missing.push('SIZE（邮件大小上限）');
+  return missing;
+};
+
 const DEFAULT_SUBJECT = '推免自荐+学校名称+您的姓名';
 const DEFAULT_BODY_TEXT = `尊敬的{teacher_name}老师：
 
@@ -150,6 +178,8 @@ function AppContent() {
   const [smtpHost, setSmtpHost] = useState(DEFAULT_SMTP_HOST);
   const [smtpPort, setSmtpPort] = useState(DEFAULT_SMTP_PORT);
   const [smtpPassword, setSmtpPassword] = useState('');
+  // 连接测试读到的服务器 SIZE 上限，随草稿保存；更换服务器后作废。
+  const [smtpSizeLimit, setSmtpSizeLimit] = useState<number | null>(null);
 
   const [subject, setSubject] = useState(DEFAULT_SUBJECT);
   const [bodyText, setBodyText] = useState(DEFAULT_BODY_TEXT);
@@ -157,6 +187,7 @@ function AppContent() {
   const [recipientsPath, setRecipientsPath] = useState(DEFAULT_RECIPIENT_PATH);
   const [recipients, setRecipients] = useState<Recipient[]>([]);
   const [recipientsStats, setRecipientsStats] = useState<RecipientStats | null>(null);
+  const [deadDomainRecipients, setDeadDomainRecipients] = useState<DeadDomainRecipient[]>([]);
 
   const [attachmentsText, setAttachmentsText] = useState('');
   const [isSending, setIsSending] = useState(false);
@@ -167,12 +198,16 @@ function AppContent() {
   const [currentStatus, setCurrentStatus] = useState('等待开始发送');
   const [summary, setSummary] = useState<SendSummary>({ total: 0, success: 0, failed: 0, skipped: 0 });
   const [waitInfo, setWaitInfo] = useState<WaitInfo | null>(null);
+  const [throughput, setThroughput] = useState<SendProgress | null>(null);
   const [failures, setFailures] = useState<Array<{ email: string; name: string; error: string }>>([]);
   const [skipSent, setSkipSent] = useState(true);
   const [minDelaySec, setMinDelaySec] = useState(5);
   const [maxDelaySec, setMaxDelaySec] = useState(10);
   const [runtimeStatus, setRuntimeStatus] = useState<RuntimeStatus | null>(null);
+  const [capabilities, setCapabilities] = useState<Capabilities | null>(null);
   const smtpTestTickerRef = useRef<number | null>(null);
+  const cancelRequestedRef = useRef(false);
+  const sendingJobIdRef = useRef<string | null>(null);
   const [runtimePath, setRuntimePath] = useState('');
   const [runtimeBusy, setRuntimeBusy] = useState(false);
   const [dataPaths, setDataPaths] = useState<AppPaths | null>(null);
@@ -220,6 +255,11 @@ function AppContent() {
 
   const refreshRuntimeStatus = useCallback(async () => {
     try {
+      const available = await getCapabilities();
+      setCapabilities(available);
+      if (!available.python_worker.compiled) {
+        return;
+      }
       const status = await getRuntimeStatus();
       setRuntimeStatus(status);
       setRuntimePath(status.executable_path ?? '');
@@ -242,10 +282,59 @@ function AppContent() {
     void refreshRuntimeStatus();
   }, [refreshRuntimeStatus]);
 
+  useEffect(() => {
+    const unlisten = onAlert((event) => {
+      if (event.type === 'alert_email_failed') {
+        message.error(`告警邮件发送失败：${event.error}`);
+        return;
+      }
+      message.warning(event.message);
+      if ('Notification' in window && Notification.permission !== 'denied') {
+        void Notification.requestPermission().then((permission) => {
+          if (permission === 'granted') {
+            new Notification('Bulk Email Sender 告警', { body: event.message });
+          }
+        });
+      }
+    });
+    return () => {
+      void unlisten.then((dispose) => dispose());
+    };
+  }, [message]);
+
   useEffect(() => {
     void refreshAppPaths();
   }, [refreshAppPaths]);
 
+  useEffect(() => {
+    const offerRecovery = async () => {
+      try {
+        const interrupted = await listInterruptedJobs();
+        if (interrupted.length === 0) {
+          return;
+        }
+        const lines = interrupted.map(
+          (job) => `${job.job_id}（${job.subject || '无主题'}，已处理 ${job.processed}/${job.recipients}）`,
+        );
+        const confirmed = window.confirm(
+          `检测到 ${interrupted.length} 个中断的任务：\n${lines.join('\n')}\n\n是否继续发送？选择“取消”将放弃这些任务。`,
+        );
+        const results = await recoverJobs(confirmed ? interrupted.map((job) => job.job_id) : []);
+        for (const result of results) {
+          if (result.type === 'error') {
+            message.error(result.error);
+          }
+        }
+        if (confirmed) {
+          message.info(`已续发 ${results.filter((result) => result.type !== 'error').length} 个中断的任务`);
+        }
+      } catch (error) {
+        message.error(toErrMsg(error, '恢复中断的任务失败'));
+      }
+    };
+    void offerRecovery();
+  }, [message]);
+
   useEffect(() => {
     const hydrateDraft = async () => {
       try {
@@ -280,6 +369,9 @@ function AppContent() {
         if (typeof draft.smtpPassword === 'string') {
           setSmtpPassword(draft.smtpPassword);
         }
+        if (typeof draft.smtpSizeLimit === 'number') {
+          setSmtpSizeLimit(draft.smtpSizeLimit);
+        }
       } catch (error) {
         message.error(toErrMsg(error, '读取草稿配置失败'));
       } finally {
@@ -301,6 +393,7 @@ function AppContent() {
       smtpHost,
       smtpPort,
       smtpPassword,
+      smtpSizeLimit,
       subject,
       bodyText,
       recipientsPath,
@@ -320,11 +413,15 @@ function AppContent() {
     smtpHost,
     smtpPort,
     smtpPassword,
+    smtpSizeLimit,
     subject,
   ]);
 
+  // native-only 构建不含 Python worker，收件人导入与发送都走原生引擎。
+  const nativeOnly = capabilities?.python_worker.compiled === false;
+
   const ensureRuntimeReady = () => {
-    if (!runtimeStatus?.ready) {
+    if (!nativeOnly && !runtimeStatus?.ready) {
       message.error('请先完成 Python 运行时设置');
       return false;
     }
@@ -397,7 +494,7 @@ function AppContent() {
       multiple: false,
       directory: false,
       title: '选择收件人文件（json / xlsx）',
-      filters: [{ name: 'Recipients', extensions: ['json', 'xlsx', 'xls'] }],
+      filters: [{ name: 'Recipients', extensions: ['csv', 'json', 'xlsx', 'xls', 'ods'] }],
     });
     const paths = normalizeDialogSelection(selected);
     if (paths.length > 0) {
@@ -510,13 +607,21 @@ function AppContent() {
   const handleAutoDetectRuntime = async () => {
     setRuntimeBusy(true);
     try {
-      const status = await autoDetectRuntime();
+      const status = await autoDetectRuntime((progress) => {
+        if (progress.stage === 'download' && progress.total_bytes) {
+          const percent = Math.floor((progress.downloaded_bytes / progress.total_bytes) * 100);
+          message.open({ key: 'runtime-progress', type: 'loading', content: `${progress.message} ${percent}%` });
+        } else {
+          message.open({ key: 'runtime-progress', type: 'loading', content: progress.message });
+        }
+      });
       setRuntimeStatus(status);
       setRuntimePath(status.executable_path ?? '');
       message.success('Python 运行时配置成功');
     } catch (error) {
       message.error(toErrMsg(error, '自动检测 Python 失败'));
     } finally {
+      message.destroy('runtime-progress');
       setRuntimeBusy(false);
     }
   };
@@ -541,17 +646,33 @@ function AppContent() {
     }
     try {
       const result = await loadRecipients(recipientsPath);
-      setRecipients(result.recipientsPreview);
+      setRecipients(result.recipients ?? result.recipientsPreview);
       setRecipientsStats(result.stats);
       message.success(
         `导入成功：总数 ${result.stats.total_rows} 条，可发送 ${result.stats.sendable_rows} 条，无效邮箱 ${result.stats.invalid_email_rows} 条，缺姓名 ${result.stats.missing_name_rows} 条`,
       );
+      const report = await preflightDeadDomains(result.recipientsPreview);
+      setDeadDomainRecipients(report.flagged);
+      if (report.flagged.length > 0) {
+        message.warning(
+          `${report.flagged.length} 位收件人位于失效域名（${report.domains.join('、')}），建议排除后再发送`,
+        );
+      }
     } catch (error) {
       setRecipientsStats(null);
+      setDeadDomainRecipients([]);
       message.error(toErrMsg(error, '导入失败'));
     }
   };
 
+  const handleExcludeDeadDomains = () => {
+    const flaggedEmails = new Set(deadDomainRecipients.map((item) => item.email));
+    setRecipients((prev) => prev.filter((item) => !flaggedEmails.has(item.email)));
+    setRecipientsStats(null);
+    setDeadDomainRecipients([]);
+    message.success(`已排除 ${flaggedEmails.size} 位失效域名收件人`);
+  };
+
   const handleTestSmtp = async () => {
     if (isTestingSmtp) {
       return;
@@ -585,7 +706,7 @@ function AppContent() {
       smtpTestTickerRef.current = window.setInterval(() => {
         setSmtpTestElapsedSec((performance.now() - startedAt) / 1000);
       }, 120);
-      await testSmtp({
+      const result = await testSmtp({
         host: smtpHost,
         port: smtpPort,
         username: effectiveSmtpUsername,
@@ -595,11 +716,23 @@ function AppContent() {
         timeout_sec: SMTP_TEST_TIMEOUT_SEC,
       });
       const elapsedSec = (performance.now() - startedAt) / 1000;
-      const successMsg = `SMTP 连接测试成功（${elapsedSec.toFixed(1)}s）`;
+      const sizeLimit = result.capabilities?.size_limit;
+      setSmtpSizeLimit(sizeLimit ?? null);
+      const sizeNote = sizeLimit ? `，单封上限 ${(sizeLimit / 1024 / 1024).toFixed(1)} MB` : '';
+      const tlsProbe = result.tls_probe;
+      const tlsNote = tlsProbe ? `，TLS 探测 ${tlsProbe.protocol} ${tlsProbe.cipher}` : '';
+      const successMsg = `SMTP 连接与认证测试成功（${elapsedSec.toFixed(1)}s${sizeNote}${tlsNote}）`;
       message.success({
         key: SMTP_TEST_MESSAGE_KEY,
         content: successMsg,
       });
+      const missing = result.capabilities ? describeMissingCapabilities(result.capabilities) : [];
+      if (missing.length > 0) {
+        message.warning(`服务器未声明以下扩展，依赖它们的功能可能无法使用：${missing.join('、')}`);
+      }
+      for (const warning of result.warnings ?? []) {
+        message.warning({ content: warning, duration: 0 });
+      }
       setSmtpTestState('success');
       setSmtpTestElapsedSec(elapsedSec);
       setSmtpTestMessage(successMsg);
@@ -625,20 +758,59 @@ function AppContent() {
   };
 
   const handleEvent = (event: WorkerEvent) => {
+    if (event.type === 'job_queued' || event.type === 'job_started') {
+      sendingJobIdRef.current = event.job_id;
+    }
+    if (event.type === 'job_queued') {
+      setCurrentStatus(`已有任务在发送，本任务已排队（第 ${event.position} 位）`);
+      return;
+    }
+
+    if (event.type === 'worker_diagnostic') {
+      console.warn(`[worker ${event.job_id}]`, event.line);
+      return;
+    }
+
+    if (event.type === 'job_scheduled') {
+      setCurrentStatus(`已设置定时发送：${new Date(event.scheduled_at).toLocaleString()}`);
+      return;
+    }
+
+    if (event.type === 'scheduled_wait') {
+      setCurrentStatus(`定时发送倒计时：${event.remaining_sec}s 后开始`);
+      return;
+    }
+
     if (event.type === 'job_started') {
       setSummary({ total: event.total, success: 0, failed: 0, skipped: 0 });
       setWaitInfo(null);
+      setThroughput(null);
       setFailures([]);
       setCurrentStatus(`任务已启动，总计 ${event.total} 封`);
       return;
     }
 
+    if (event.type === 'job_paused') {
+      setWaitInfo(null);
+      setCurrentStatus(`已暂停，继续后从第 ${event.next_index} 位收件人开始`);
+      return;
+    }
+
+    if (event.type === 'job_resumed') {
+      setCurrentStatus('已继续发送');
+      return;
+    }
+
     if (event.type === 'recipient_started') {
       setWaitInfo(null);
       setCurrentStatus(`正在发送：${event.name} (${event.email})`);
       return;
     }
 
+    if (event.type === 'recipient_sent' || event.type === 'recipient_failed' || event.type === 'recipient_skipped') {
+      setThroughput(event.progress ?? null);
+    }
+
     if (event.type === 'recipient_sent') {
       setWaitInfo(null);
       setSummary((prev) => ({ ...prev, success: prev.success + 1 }));
@@ -654,6 +826,14 @@ function AppContent() {
       return;
     }
 
+    if (event.type === 'recipient_retry') {
+      setWaitInfo(null);
+      setCurrentStatus(
+        `发送失败，${event.delay_sec}s 后重试（第 ${event.attempt}/${event.max_attempts} 次）：${event.name} (${event.email})`,
+      );
+      return;
+    }
+
     if (event.type === 'recipient_skipped') {
       setWaitInfo(null);
       setSummary((prev) => ({ ...prev, skipped: prev.skipped + 1 }));
@@ -688,6 +868,19 @@ function AppContent() {
       return;
     }
 
+    if (event.type === 'job_failed') {
+      setWaitInfo(null);
+      setSummary((prev) => ({
+        total: event.total ?? prev.total,
+        success: event.success,
+        failed: event.failed,
+        skipped: event.skipped,
+      }));
+      setCurrentStatus(`任务异常结束：${event.error}`);
+      setIsSending(false);
+      return;
+    }
+
     if (event.type === 'error') {
       setWaitInfo(null);
       setCurrentStatus(`任务错误：${event.error}`);
@@ -696,7 +889,49 @@ function AppContent() {
     }
   };
 
+  // 界面刷新或重新打开时接上仍在运行的任务：先补发错过的事件，再继续接收。
+  const onFollowedEvent = useEffectEvent(handleEvent);
+  useEffect(() => {
+    let cancelled = false;
+    let dispose: (() => void) | null = null;
+    const resumeRunningJob = async () => {
+      try {
+        const job = (await listJobs()).running[0];
+        if (!job || cancelled) {
+          return;
+        }
+        sendingJobIdRef.current = job.job_id;
+        setIsSending(true);
+        setCurrentStatus('正在接上运行中的任务...');
+        const followed = await followJob(job.job_id, (event) => onFollowedEvent(event));
+        if (cancelled) {
+          followed.dispose();
+          return;
+        }
+        dispose = followed.dispose;
+        const { progress, truncated } = followed.replay;
+        if (truncated && progress) {
+          setSummary({
+            total: progress.total ?? job.recipients,
+            success: progress.sent,
+            failed: progress.failed,
+            skipped: progress.skipped,
+          });
+        }
+      } catch (error) {
+        setIsSending(false);
+        message.error(toErrMsg(error, '接上运行中的任务失败'));
+      }
+    };
+    void resumeRunningJob();
+    return () => {
+      cancelled = true;
+      dispose?.();
+    };
+  }, [message]);
+
   const buildSendPayload = (): SendPayload => ({
+    ...(nativeOnly ? { engine: 'native' as const } : {}),
     sender: {
       email: senderEmail,
       name: senderName,
@@ -709,6 +944,7 @@ function AppContent() {
       use_ssl: effectiveSmtpSecurity === 'ssl',
       use_starttls: effectiveSmtpSecurity === 'starttls',
       timeout_sec: 30,
+      size_limit: smtpSizeLimit,
     },
     template: {
       subject,
@@ -763,6 +999,8 @@ function AppContent() {
     }
 
     setIsSending(true);
+    cancelRequestedRef.current = false;
+    sendingJobIdRef.current = null;
     setWaitInfo(null);
     setCurrentStatus('正在启动发送任务...');
     setFailures([]);
@@ -775,11 +1013,19 @@ function AppContent() {
   };
 
   const handleCancelSend = async () => {
-    if (!isSending) {
+    const jobId = sendingJobIdRef.current;
+    if (!isSending || !jobId) {
+      return;
+    }
+    // 第一次点击等当前邮件发送完成后停止；再次点击可强制结束。
+    const force = cancelRequestedRef.current && window.confirm('正在等待当前邮件发送完成，是否强制结束？');
+    if (cancelRequestedRef.current && !force) {
       return;
     }
+    cancelRequestedRef.current = true;
+    setCurrentStatus(force ? '正在强制结束发送任务' : '正在停止：等待当前邮件发送完成');
     try {
-      await cancelSend();
+      await cancelSend(jobId, force);
     } catch {
       // Worker may have already exited
     }
@@ -787,8 +1033,8 @@ function AppContent() {
 
   const handleClearSentRecords = async () => {
     try {
-      await clearSentRecords();
-      message.success('已清除发送记录（jsonl + txt）');
+      const report = await clearSentRecords();
+      message.success(`已清除 ${report.removed_sent} 条发送记录（jsonl + txt）`);
     } catch (error) {
       message.error(toErrMsg(error, '清除发送记录失败'));
     }
@@ -796,6 +1042,7 @@ function AppContent() {
 
   const handleSmtpProviderChange = (providerKey: string) => {
     setSmtpProvider(providerKey);
+    setSmtpSizeLimit(null);
     if (providerKey === SMTP_PROVIDER_CUSTOM_KEY) {
       setSmtpHost('');
       setSmtpPort(0);
@@ -820,11 +1067,13 @@ function AppContent() {
     setSmtpHost(DEFAULT_SMTP_HOST);
     setSmtpPort(DEFAULT_SMTP_PORT);
     setSmtpPassword('');
+    setSmtpSizeLimit(null);
     setSubject(DEFAULT_SUBJECT);
     setBodyText(DEFAULT_BODY_TEXT);
     setRecipientsPath(DEFAULT_RECIPIENT_PATH);
     setRecipients([]);
     setRecipientsStats(null);
+    setDeadDomainRecipients([]);
     setAttachmentsText('');
     message.success('已重置本地草稿配置');
   };
@@ -895,8 +1144,14 @@ function AppContent() {
                     onSenderEmailChange={setSenderEmail}
                     onSenderNameChange={setSenderName}
                     onSmtpPasswordChange={setSmtpPassword}
-                    onSmtpHostChange={setSmtpHost}
-                    onSmtpPortChange={setSmtpPort}
+                    onSmtpHostChange={(host) => {
+                      setSmtpHost(host);
+                      setSmtpSizeLimit(null);
+                    }}
+                    onSmtpPortChange={(port) => {
+                      setSmtpPort(port);
+                      setSmtpSizeLimit(null);
+                    }}
                     onTestSmtp={() => void handleTestSmtp()}
                   />
                 ),
@@ -909,9 +1164,11 @@ function AppContent() {
                     recipientsPath={recipientsPath}
                     recipients={recipients}
                     recipientsStats={recipientsStats}
+                    deadDomainRecipients={deadDomainRecipients}
                     onRecipientsPathChange={setRecipientsPath}
                     onPickRecipientsFile={() => void handlePickRecipientsFile()}
                     onLoadRecipients={() => void handleLoadRecipients()}
+                    onExcludeDeadDomains={handleExcludeDeadDomains}
                   />
                 ),
               },
@@ -932,6 +1189,7 @@ function AppContent() {
                     doneCount={doneCount}
                     summary={summary}
                     waitInfo={waitInfo}
+                    throughput={throughput}
                     failures={failures}
                     onSubjectChange={setSubject}
                     onBodyTextChange={setBodyText}
@@ -34,6 +34,11 @@ interface WaitInfo {
   nextIndex: number;
 }
 
+interface ThroughputInfo {
+  per_minute?: number | null;
+  eta?: string | null;
+}
+
 interface FailureItem {
   email: string;
   name: string;
@@ -53,6 +58,7 @@ interface EmailContentWorkspaceProps {
   doneCount: number;
   summary: SendSummary;
   waitInfo: WaitInfo | null;
+  throughput: ThroughputInfo | null;
   failures: FailureItem[];
   onSubjectChange: (value: string) => void;
   onBodyTextChange: (value: string) => void;
@@ -86,6 +92,7 @@ function EmailContentWorkspaceInner({
   doneCount,
   summary,
   waitInfo,
+  throughput,
   failures,
   onSubjectChange,
   onBodyTextChange,
@@ -276,6 +283,10 @@ function EmailContentWorkspaceInner({
               <Tag color="red">失败 {summary.failed}</Tag>
               <Tag color="gold">跳过 {summary.skipped}</Tag>
               <Tag color="blue">总计 {summary.total}</Tag>
+              {isSending && throughput?.per_minute != null && <Tag color="cyan">{throughput.per_minute} 封/分钟</Tag>}
+              {isSending && throughput?.eta && (
+                <Tag color="geekblue">预计完成 {new Date(throughput.eta).toLocaleTimeString()}</Tag>
+              )}
             </Space>
           </div>
 
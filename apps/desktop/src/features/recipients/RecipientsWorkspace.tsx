@@ -1,8 +1,8 @@
 import { memo, useMemo } from 'react';
 import { Table } from 'antd';
-import { FileSpreadsheet, FolderOpen, ListChecks, Users } from 'lucide-react';
+import { FileSpreadsheet, FolderOpen, ListChecks, TriangleAlert, Users } from 'lucide-react';
 
-import type { Recipient, RecipientStats } from '@/types';
+import type { DeadDomainRecipient, Recipient, RecipientStats } from '@/types';
 import { Badge as UiBadge } from '@/components/ui/badge';
 import { Button as UiButton } from '@/components/ui/button';
 import {
@@ -18,9 +18,11 @@ interface RecipientsWorkspaceProps {
   recipientsPath: string;
   recipients: Recipient[];
   recipientsStats: RecipientStats | null;
+  deadDomainRecipients: DeadDomainRecipient[];
   onRecipientsPathChange: (value: string) => void;
   onPickRecipientsFile: () => void;
   onLoadRecipients: () => void;
+  onExcludeDeadDomains: () => void;
 }
 
 const recipientsColumns = [
@@ -38,10 +40,17 @@ function RecipientsWorkspaceInner({
   recipientsPath,
   recipients,
   recipientsStats,
+  deadDomainRecipients,
   onRecipientsPathChange,
   onPickRecipientsFile,
   onLoadRecipients,
+  onExcludeDeadDomains,
 }: RecipientsWorkspaceProps) {
+  const deadDomains = useMemo(
+    () => Array.from(new Set(deadDomainRecipients.map((item) => item.domain))),
+    [deadDomainRecipients],
+  );
+
   const stats = useMemo(() => {
     if (recipientsStats) {
       return {
@@ -126,6 +135,20 @@ function RecipientsWorkspaceInner({
               缺姓名数 {stats.missingName}
             </UiBadge>
           </div>
+
+          {deadDomainRecipients.length > 0 ? (
+            <div className="flex flex-col gap-3 rounded-md border border-amber-200 bg-amber-50 px-4 py-3 md:flex-row md:items-center">
+              <div className="flex flex-1 items-start gap-2 text-sm text-amber-800">
+                <TriangleAlert className="mt-0.5 size-4 shrink-0" />
+                <span>
+                  {deadDomainRecipients.length} 位收件人位于历史退信的失效域名：{deadDomains.join('、')}
+                </span>
+              </div>
+              <UiButton type="button" variant="outline" className="h-9" onClick={onExcludeDeadDomains}>
+                一键排除
+              </UiButton>
+            </div>
+          ) : null}
         </UiCardContent>
       </UiCard>
 
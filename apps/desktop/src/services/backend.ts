@@ -2,175 +2,1367 @@ import { invoke } from '@tauri-apps/api/core';
 import { listen } from '@tauri-apps/api/event';
 
 import type {
+  AlertEvent,
+  AlertSettings,
   AppDraft,
   AppPaths,
+  AttachmentCheckRequest,
+  AttachmentReport,
+  CampaignClone,
+  Capabilities,
+  CampaignReportPayload,
+  ClearSentRecordsFilter,
+  ClearSentRecordsReport,
+  CampaignTimeline,
+  CloneSelection,
+  ColumnFilter,
+  ColumnMapping,
+  ColumnMappingInfo,
+  ConsentImportSummary,
+  CsvFormat,
+  CsvOptions,
+  ConsentReport,
+  DatabaseQueryResult,
+  DatabaseSource,
+  DataDirLock,
+  DeadDomainReport,
+  DeadDomainSummary,
+  DisposableDomainsStatus,
+  DisposableReport,
+  EnrichmentReport,
+  EnrichmentSettings,
+  ImapSeedQuery,
+  ImapSeedResult,
+  ImapSettings,
+  InterruptedJob,
+  JobDetail,
+  JobHistoryEntry,
+  JobList,
+  JobQueueSettings,
+  JobEvents,
+  JobStatus,
+  LdapQuery,
+  LdapQueryResult,
+  LdapSettings,
   LoadRecipientsResult,
+  MaintenanceSettings,
+  MessageSizeReport,
+  MissingSystemLibrary,
+  NetworkPolicy,
+  PendingUpdatesStatus,
+  PolicyReport,
+  ProbeResult,
+  ProviderRules,
+  PurgeReport,
+  RcptProbeOptions,
+  RcptProbeReport,
   Recipient,
+  RecipientFilterReport,
+  RecipientStats,
+  RecipientValidationReport,
+  RecordNote,
+  RecurringCampaign,
+  RecurringCampaignSummary,
+  ResendFailedOptions,
+  RetentionPolicy,
+  RuntimeProgress,
   RuntimeStatus,
   SendPayload,
+  SendPlan,
+  SendStats,
+  SendStatsQuery,
+  SentRecordPage,
+  SentRecordQuery,
+  SessionState,
+  SharedDirSettings,
+  SmimeCertificateInfo,
+  SmtpAccount,
+  SmtpAccountSummary,
+  SmtpProfile,
   SmtpPayload,
+  SmtpTestFailure,
+  SmtpTestResult,
+  SuppressionImportSummary,
+  SuppressionListing,
+  TemplateOverrides,
+  ThrottleLimits,
+  ThrottleStatus,
+  TimelineBucket,
+  UploadSettings,
+  UserIdentity,
+  ValidationSettings,
+  WorkerCommandOverride,
   WorkerEvent,
+  WorkerSettings,
 } from '../types';
 
 const WORKER_EVENT_CHANNEL = 'worker-event';
+const JOB_LIFECYCLE_CHANNEL = 'job-lifecycle';
+const RUNTIME_PROGRESS_CHANNEL = 'runtime-progress';
+const PENDING_UPDATES_CHANNEL = 'pending-updates';
+const ALERT_CHANNEL = 'alert';
+const RCPT_PROBE_CHANNEL = 'rcpt-probe';
+
+/** 任务的事件频道；与桌面端一致，任务 ID 中字母、数字与 `-/:_` 以外的字符替换为 `_`。 */
+function jobEventChannel(jobId: string): string {
+  return `${WORKER_EVENT_CHANNEL}:${jobId.replace(/[^A-Za-z0-9/:_-]/gu, '_')}`;
+}
 
 function isTauriRuntime(): boolean {
   return typeof window !== 'undefined' && '__TAURI_INTERNALS__' in window;
 }
 
-function randomJobId(): string {
-  return `job-${Math.random().toString(36).slice(2, 10)}`;
+function randomJobId(): string {
+  return `job-${Math.random().toString(36).slice(2, 10)}`;
+}
+
+export async function loadRecipients(path: string, csv?: CsvOptions): Promise<LoadRecipientsResult> {
+  if (!isTauriRuntime()) {
+    const preview: Recipient[] = [
+      { email: 'teacher1@example.com', name: '张教授' },
+      { email: 'teacher2@example.com', name: '李教授' },
+      { email: 'teacher3@example.com', name: '王教授' },
+    ];
+    return {
+      stats: {
+        total_rows: preview.length,
+        valid_rows: preview.length,
+        sendable_rows: preview.length,
+        invalid_rows: 0,
+        invalid_email_rows: 0,
+        missing_name_rows: 0,
+        duplicate_rows: 0,
+        empty_rows: 0,
+      },
+      recipientsPreview: preview,
+    };
+  }
+
+  const event = (await invoke('load_recipients', { path, csv })) as WorkerEvent;
+  if (event.type === 'error') {
+    throw new Error(event.error);
+  }
+  if (event.type !== 'recipients_loaded') {
+    throw new Error(`Unexpected response type: ${event.type}`);
+  }
+
+  return {
+    stats: event.stats,
+    recipientsPreview: event.recipients_preview,
+    csvFormat: event.csv_format,
+    recipients: event.recipients,
+  };
+}
+
+export async function getColumnMapping(path: string, csv?: CsvOptions): Promise<ColumnMappingInfo> {
+  if (!isTauriRuntime()) {
+    return { headers: ['邮箱', '姓名'], mapping: null };
+  }
+  return (await invoke('get_column_mapping', { path, csv })) as ColumnMappingInfo;
+}
+
+/** 保存文件的列映射并按映射重新导入；`mapping` 为 null 时清除映射。 */
+export async function setColumnMapping(
+  path: string,
+  mapping: ColumnMapping | null,
+  csv?: CsvOptions,
+): Promise<LoadRecipientsResult> {
+  if (!isTauriRuntime()) {
+    throw new Error('设置列映射需要在桌面应用中运行');
+  }
+  const event = (await invoke('set_column_mapping', { payload: { path, mapping, csv } })) as {
+    stats: RecipientStats;
+    recipients_preview: Recipient[];
+    csv_format?: CsvFormat | null;
+  };
+  return {
+    stats: event.stats,
+    recipientsPreview: event.recipients_preview,
+    csvFormat: event.csv_format,
+  };
+}
+
+export async function loadRecipientsFromText(
+  text: string,
+): Promise<LoadRecipientsResult & { recipients: Recipient[]; suppressed: number }> {
+  if (!isTauriRuntime()) {
+    throw new Error('粘贴导入收件人需要在桌面应用中运行');
+  }
+  const event = (await invoke('load_recipients_from_text', { text })) as {
+    stats: RecipientStats;
+    recipients_preview: Recipient[];
+    recipients: Recipient[];
+    suppressed: number;
+  };
+  return {
+    stats: event.stats,
+    recipientsPreview: event.recipients_preview,
+    recipients: event.recipients,
+    suppressed: event.suppressed,
+  };
+}
+
+export async function loadRecipientsFromImap(imap: ImapSettings, query: ImapSeedQuery): Promise<ImapSeedResult> {
+  if (!isTauriRuntime()) {
+    throw new Error('邮箱搜索需要在桌面应用中运行');
+  }
+  const event = (await invoke('load_recipients_from_imap', { payload: { imap, ...query } })) as {
+    stats: RecipientStats;
+    recipients_preview: Recipient[];
+    recipients: Recipient[];
+    suppressed: number;
+    matched_messages: number;
+    scanned_messages: number;
+  };
+  return {
+    stats: event.stats,
+    recipientsPreview: event.recipients_preview,
+    recipients: event.recipients,
+    suppressed: event.suppressed,
+    matchedMessages: event.matched_messages,
+    scannedMessages: event.scanned_messages,
+  };
+}
+
+export async function filterRecipients(
+  path: string,
+  filters: ColumnFilter[],
+  matchAny = false,
+): Promise<RecipientFilterReport> {
+  if (!isTauriRuntime()) {
+    throw new Error('按列筛选收件人需要在桌面应用中运行');
+  }
+  return (await invoke('filter_recipients', { payload: { path, filters, match_any: matchAny } })) as RecipientFilterReport;
+}
+
+export async function ldapQueryRecipients(ldap: LdapSettings, query: LdapQuery): Promise<LdapQueryResult> {
+  if (!isTauriRuntime()) {
+    throw new Error('目录查询需要在桌面应用中运行');
+  }
+  const event = (await invoke('ldap_query_recipients', { payload: { ldap, ...query } })) as {
+    stats: RecipientStats;
+    recipients_preview: Recipient[];
+    recipients: Recipient[];
+    suppressed: number;
+    directory_entries: number;
+    truncated: boolean;
+  };
+  return {
+    stats: event.stats,
+    recipientsPreview: event.recipients_preview,
+    recipients: event.recipients,
+    suppressed: event.suppressed,
+    directoryEntries: event.directory_entries,
+    truncated: event.truncated,
+  };
+}
+
+export async function loadRecipientsFromDatabase(source: DatabaseSource): Promise<DatabaseQueryResult> {
+  if (!isTauriRuntime()) {
+    throw new Error('数据库查询需要在桌面应用中运行');
+  }
+  const event = (await invoke('load_recipients_from_database', { payload: source })) as {
+    stats: RecipientStats;
+    recipients_preview: Recipient[];
+    recipients: Recipient[];
+    suppressed: number;
+    database_rows: number;
+    truncated: boolean;
+  };
+  return {
+    stats: event.stats,
+    recipientsPreview: event.recipients_preview,
+    recipients: event.recipients,
+    suppressed: event.suppressed,
+    databaseRows: event.database_rows,
+    truncated: event.truncated,
+  };
+}
+
+export async function previewSendPlan(
+  recipients: Recipient[],
+  options: SendPayload['options'],
+): Promise<SendPlan> {
+  if (!isTauriRuntime()) {
+    return { seed: options.seed ?? 0, order: recipients, delays: recipients.map(() => options.min_delay_sec) };
+  }
+  return (await invoke('preview_send_plan', { payload: { recipients, options } })) as SendPlan;
+}
+
+export async function validateAttachments(payload: AttachmentCheckRequest): Promise<AttachmentReport> {
+  if (!isTauriRuntime()) {
+    return {
+      attachments: payload.paths.map((path) => ({
+        path,
+        exists: true,
+        size_bytes: 0,
+        mime_type: 'application/octet-stream',
+        problems: [],
+        ascii_name: null,
+        warnings: [],
+      })),
+      total_bytes: 0,
+      encoded_bytes: 0,
+      problems: [],
+      ok: true,
+    };
+  }
+  return (await invoke('validate_attachments', { payload })) as AttachmentReport;
+}
+
+export async function simulatePolicyCompliance(
+  payload: SendPayload,
+  rules?: ProviderRules | null,
+): Promise<PolicyReport> {
+  if (!isTauriRuntime()) {
+    const recipientsPerMessage = 1 + (payload.cc?.length ?? 0) + (payload.bcc?.length ?? 0);
+    return {
+      provider: rules ?? null,
+      messages: payload.recipients.length,
+      recipients_per_message: recipientsPerMessage,
+      total_recipients: payload.recipients.length * recipientsPerMessage,
+      sent_last_day: 0,
+      attachment_encoded_bytes: 0,
+      projected_days: null,
+      violations: [],
+      warnings: [],
+      ok: true,
+    };
+  }
+  return (await invoke('simulate_policy_compliance', { payload, rules: rules ?? null })) as PolicyReport;
+}
+
+export async function preflightMessageSizes(payload: SendPayload): Promise<MessageSizeReport> {
+  if (!isTauriRuntime()) {
+    return {
+      limit_bytes: 25 * 1024 * 1024,
+      attachment_bytes: 0,
+      largest_bytes: 0,
+      messages: payload.recipients.map((recipient, index) => ({
+        index,
+        email: recipient.email.toLowerCase(),
+        size_bytes: 0,
+        oversize: false,
+      })),
+      oversize: [],
+      problems: [],
+      ok: true,
+    };
+  }
+  return (await invoke('preflight_message_sizes', { payload })) as MessageSizeReport;
+}
+
+/** SMTP 测试失败；stage 区分连接问题与认证失败（账号或密码错误）。 */
+export class SmtpTestError extends Error {
+  readonly stage: SmtpTestFailure['stage'];
+
+  constructor(failure: SmtpTestFailure) {
+    super(failure.error);
+    this.name = 'SmtpTestError';
+    this.stage = failure.stage;
+  }
+}
+
+export async function testSmtp(payload: SmtpPayload): Promise<SmtpTestResult> {
+  if (!isTauriRuntime()) {
+    if (!payload.username || !payload.password || !payload.host) {
+      throw new Error('请先填写 SMTP 配置后再测试');
+    }
+    return {
+      offered_mechanisms: ['PLAIN', 'LOGIN'],
+      auth_mechanism: 'PLAIN',
+      capabilities: {
+        extensions: ['SIZE', 'PIPELINING', 'AUTH', 'SMTPUTF8'],
+        size_limit: 52428800,
+        starttls: false,
+        auth_mechanisms: ['PLAIN', 'LOGIN'],
+        pipelining: true,
+        smtputf8: true,
+        dsn: false,
+      },
+    };
+  }
+
+  let event: WorkerEvent;
+  try {
+    event = (await invoke('test_smtp', { payload })) as WorkerEvent;
+  } catch (error) {
+    const failure = error as Partial<SmtpTestFailure> | null;
+    if (typeof failure?.error === 'string') {
+      throw new SmtpTestError({ stage: failure.stage ?? 'connect', error: failure.error });
+    }
+    throw error;
+  }
+  if (event.type !== 'smtp_test_succeeded') {
+    throw new Error(`Unexpected response type: ${event.type}`);
+  }
+  return {
+    offered_mechanisms: event.offered_mechanisms ?? [],
+    auth_mechanism: event.auth_mechanism ?? '',
+    capabilities: event.capabilities,
+    warnings: event.warnings ?? [],
+    tls_probe: event.tls_probe ?? null,
+  };
+}
+
+export async function startSend(
+  payload: SendPayload,
+  onEvent: (event: WorkerEvent) => void,
+): Promise<() => Promise<void>> {
+  const effectivePayload: SendPayload = {
+    ...payload,
+    job_id: payload.job_id ?? randomJobId(),
+  };
+
+  if (!isTauriRuntime()) {
+    return createMockSendingFlow(effectivePayload, onEvent);
+  }
+  return runSendCommand('start_send', { payload: effectivePayload }, onEvent);
+}
+
+/** 把任务中最终失败的收件人作为新任务发送；`send` 中的收件人会被后端替换。 */
+export async function requeueFailedRecipients(
+  campaignId: string,
+  send: SendPayload,
+  onEvent: (event: WorkerEvent) => void,
+): Promise<() => Promise<void>> {
+  if (!isTauriRuntime()) {
+    return createMockSendingFlow({ ...send, job_id: randomJobId() }, onEvent);
+  }
+  return runSendCommand('requeue_failed_recipients', { payload: { campaign_id: campaignId, send } }, onEvent);
+}
+
+/** 按原任务保存的参数重发其失败收件人，可修改主题 / 正文或附件；新任务以 `resend_of` 关联原任务。 */
+export async function resendFailed(
+  campaignId: string,
+  templateOverrides: TemplateOverrides,
+  onEvent: (event: WorkerEvent) => void,
+  options: ResendFailedOptions = {},
+): Promise<() => Promise<void>> {
+  if (!isTauriRuntime()) {
+    throw new Error('重发失败收件人需要在桌面应用中运行');
+  }
+  const payload = { campaign_id: campaignId, template_overrides: templateOverrides, ...options };
+  return runSendCommand('resend_failed', { payload }, onEvent);
+}
+
+/**
+ * 快速发送单封事务性邮件（回复咨询、重发某位失败的收件人）：不排队、不等待发送间隔，
+ * 但计入发送记录与限速；`payload.recipients` 只能有一位收件人，超出限额时直接报错。
+ */
+export async function sendSingle(payload: SendPayload): Promise<Extract<WorkerEvent, { type: 'recipient_sent' }>> {
+  if (!isTauriRuntime()) {
+    throw new Error('快速发送需要在桌面应用中运行');
+  }
+  return (await invoke('send_single', { payload })) as Extract<WorkerEvent, { type: 'recipient_sent' }>;
+}
+
+export async function getCampaignTimeline(campaignId: string, bucket: TimelineBucket): Promise<CampaignTimeline> {
+  if (!isTauriRuntime()) {
+    return { campaign_id: campaignId, bucket, points: [] };
+  }
+  return (await invoke('get_campaign_timeline', { payload: { campaign_id: campaignId, bucket } })) as CampaignTimeline;
+}
+
+export async function listJobHistory(limit?: number): Promise<JobHistoryEntry[]> {
+  if (!isTauriRuntime()) {
+    return [];
+  }
+  return (await invoke('list_job_history', { limit })) as JobHistoryEntry[];
+}
+
+export async function getJobDetail(jobId: string): Promise<JobDetail> {
+  if (!isTauriRuntime()) {
+    throw new Error('查看任务详情需要在桌面应用中运行');
+  }
+  return (await invoke('get_job_detail', { jobId })) as JobDetail;
+}
+
+export async function cloneCampaign(id: string, recipients: CloneSelection): Promise<CampaignClone> {
+  if (!isTauriRuntime()) {
+    throw new Error('复制任务需要在桌面应用中运行');
+  }
+  return (await invoke('clone_campaign', { payload: { id, recipients } })) as CampaignClone;
+}
+
+function isTerminalEvent(event: WorkerEvent): boolean {
+  return (
+    event.type === 'job_finished' ||
+    event.type === 'job_cancelled' ||
+    event.type === 'job_failed' ||
+    event.type === 'error'
+  );
+}
+
+async function runSendCommand(
+  command: string,
+  args: Record<string, unknown>,
+  onEvent: (event: WorkerEvent) => void,
+): Promise<() => Promise<void>> {
+  let dispose: (() => void) | null = null;
+  let jobId = (args.payload as { job_id?: string } | undefined)?.job_id;
+  // 任务 ID 已知时先订阅任务频道，不会错过启动事件；否则受理后再订阅，并补发受理后已推送的事件。
+  if (jobId) {
+    dispose = await listen<WorkerEvent>(jobEventChannel(jobId), (event) => {
+      onEvent(event.payload);
+      if (isTerminalEvent(event.payload)) {
+        dispose?.();
+        dispose = null;
+      }
+    });
+  }
+
+  try {
+    const accepted = (await invoke(command, args)) as WorkerEvent;
+    const acceptedJobId = (accepted as { job_id?: string }).job_id;
+    if (accepted.type === 'job_queued' || accepted.type === 'job_scheduled') {
+      onEvent(accepted);
+    }
+    if (!jobId && acceptedJobId) {
+      jobId = acceptedJobId;
+      dispose = (await followJob(acceptedJobId, onEvent)).dispose;
+    }
+  } catch (error) {
+    dispose?.();
+    dispose = null;
+    throw error;
+  }
+
+  return async () => {
+    if (jobId) {
+      await invoke('cancel_send', { jobId });
+    }
+    dispose?.();
+    dispose = null;
+  };
+}
+
+export async function listJobs(): Promise<JobList> {
+  if (!isTauriRuntime()) {
+    return { running: [], queued: [], scheduled: [] };
+  }
+  return (await invoke('list_jobs')) as JobList;
+}
+
+/** 按给定顺序把等待中的任务排到队首。 */
+export async function reorderJobs(jobIds: string[]): Promise<JobList> {
+  if (!isTauriRuntime()) {
+    return { running: [], queued: [], scheduled: [] };
+  }
+  return (await invoke('reorder_jobs', { jobIds })) as JobList;
+}
+
+export async function cancelScheduledJob(jobId: string): Promise<JobList> {
+  if (!isTauriRuntime()) {
+    return { running: [], queued: [], scheduled: [] };
+  }
+  return (await invoke('cancel_scheduled_job', { jobId })) as JobList;
+}
+
+export async function listInterruptedJobs(): Promise<InterruptedJob[]> {
+  if (!isTauriRuntime()) {
+    return [];
+  }
+  return (await invoke('list_interrupted_jobs')) as InterruptedJob[];
+}
+
+/** 续发 jobIds 中的中断任务，其余中断任务放弃。 */
+export async function recoverJobs(jobIds: string[]): Promise<WorkerEvent[]> {
+  if (!isTauriRuntime()) {
+    return [];
+  }
+  return (await invoke('recover_jobs', { jobIds })) as WorkerEvent[];
+}
+
+export async function listRecurringCampaigns(): Promise<RecurringCampaignSummary[]> {
+  if (!isTauriRuntime()) {
+    return [];
+  }
+  return (await invoke('list_recurring_campaigns')) as RecurringCampaignSummary[];
+}
+
+export async function saveRecurringCampaign(campaign: RecurringCampaign): Promise<RecurringCampaignSummary> {
+  if (!isTauriRuntime()) {
+    return {
+      id: campaign.id || 'recurring-mock',
+      name: campaign.name,
+      cron: campaign.cron,
+      enabled: campaign.enabled ?? true,
+      subject: campaign.payload.template.subject,
+      recipients: campaign.payload.recipients.length,
+      last_run: null,
+      next_run: null,
+    };
+  }
+  return (await invoke('save_recurring_campaign', { campaign })) as RecurringCampaignSummary;
+}
+
+export async function deleteRecurringCampaign(id: string): Promise<RecurringCampaignSummary[]> {
+  if (!isTauriRuntime()) {
+    return [];
+  }
+  return (await invoke('delete_recurring_campaign', { id })) as RecurringCampaignSummary[];
+}
+
+/** 取消运行或排队中的任务：默认等当前邮件发送完成后停止；force 为 true 时直接结束 worker 进程。 */
+export async function cancelSend(jobId: string, force = false): Promise<void> {
+  if (!isTauriRuntime()) {
+    return;
+  }
+  await invoke('cancel_send', { jobId, force });
+}
+
+/** 紧急停止：清空任务队列并取消全部运行中的任务，返回被取消的任务 ID。 */
+export async function cancelAllJobs(force = false): Promise<string[]> {
+  if (!isTauriRuntime()) {
+    return [];
+  }
+  return (await invoke('cancel_all_jobs', { force })) as string[];
+}
+
+/** 暂停正在发送的任务：当前邮件发送完成后停在下一位收件人之前（仅 Python worker 引擎）。 */
+export async function pauseJob(jobId: string): Promise<void> {
+  if (!isTauriRuntime()) {
+    return;
+  }
+  await invoke('pause_job', { jobId });
+}
+
+export async function resumeJob(jobId: string): Promise<void> {
+  if (!isTauriRuntime()) {
+    return;
+  }
+  await invoke('resume_job', { jobId });
+}
+
+export async function getJobStatus(jobId: string): Promise<JobStatus> {
+  if (!isTauriRuntime()) {
+    throw new Error(`任务不存在: ${jobId}`);
+  }
+  return (await invoke('get_job_status', { jobId })) as JobStatus;
+}
+
+export async function getJobEvents(jobId: string, sinceSeq?: number): Promise<JobEvents> {
+  if (!isTauriRuntime()) {
+    return { events: [], progress: null, latest_seq: 0, truncated: false };
+  }
+  return (await invoke('get_job_events', { jobId, sinceSeq })) as JobEvents;
+}
+
+/**
+ * 接上已在运行的任务（如界面刷新后）：先补发缓存的事件，再转发实时事件，按 seq 去重。
+ * 返回补发结果（进度快照与是否有事件已丢失）与停止接收的函数；任务结束后自动停止。
+ */
+export async function followJob(
+  jobId: string,
+  onEvent: (event: WorkerEvent) => void,
+): Promise<{ replay: JobEvents; dispose: () => void }> {
+  let dispose: (() => void) | null = null;
+  let lastSeq = 0;
+  let pending: WorkerEvent[] | null = [];
+  const deliver = (event: WorkerEvent) => {
+    const seq = (event as { seq?: number }).seq;
+    if (seq !== undefined) {
+      if (seq <= lastSeq) {
+        return;
+      }
+      lastSeq = seq;
+    }
+    onEvent(event);
+    if (isTerminalEvent(event)) {
+      dispose?.();
+      dispose = null;
+    }
+  };
+  dispose = await listen<WorkerEvent>(jobEventChannel(jobId), (event) => {
+    // 补发完成前收到的实时事件先暂存，补发后再按顺序转发。
+    if (pending) {
+      pending.push(event.payload);
+    } else {
+      deliver(event.payload);
+    }
+  });
+
+  let replay: JobEvents;
+  try {
+    replay = await getJobEvents(jobId);
+  } catch (error) {
+    dispose?.();
+    dispose = null;
+    throw error;
+  }
+  const live = pending ?? [];
+  pending = null;
+  replay.events.forEach(deliver);
+  live.forEach(deliver);
+  return {
+    replay,
+    dispose: () => {
+      dispose?.();
+      dispose = null;
+    },
+  };
+}
+
+export async function clearSentRecords(filter: ClearSentRecordsFilter | null = null): Promise<ClearSentRecordsReport> {
+  if (!isTauriRuntime()) {
+    return { removed_sent: 0, removed_failed: 0 };
+  }
+  return (await invoke('clear_sent_records', { filter })) as ClearSentRecordsReport;
+}
+
+export async function querySentRecords(query: SentRecordQuery = {}): Promise<SentRecordPage> {
+  if (!isTauriRuntime()) {
+    return { records: [], total: 0, offset: query.offset ?? 0, limit: query.limit ?? 100 };
+  }
+  return (await invoke('query_sent_records', { query })) as SentRecordPage;
+}
+
+export async function getSendStats(query: SendStatsQuery = {}): Promise<SendStats> {
+  if (!isTauriRuntime()) {
+    return { sent: 0, failed: 0, success_rate: null, per_day: [], failures: [], sent_per_hour: null };
+  }
+  return (await invoke('get_send_stats', { query })) as SendStats;
+}
+
+export async function getSentRecordRetention(): Promise<RetentionPolicy> {
+  if (!isTauriRuntime()) {
+    return { keep_days: null, keep_rows: null };
+  }
+  return (await invoke('get_sent_record_retention')) as RetentionPolicy;
+}
+
+export async function setSentRecordRetention(payload: RetentionPolicy): Promise<RetentionPolicy> {
+  if (!isTauriRuntime()) {
+    return payload;
+  }
+  return (await invoke('set_sent_record_retention', { payload })) as RetentionPolicy;
+}
+
+export async function purgeSentRecords(policy: RetentionPolicy | null = null): Promise<PurgeReport> {
+  if (!isTauriRuntime()) {
+    return { removed_records: 0, kept_records: 0, removed_campaign_logs: 0 };
+  }
+  return (await invoke('purge_sent_records', { policy })) as PurgeReport;
+}
+
+export async function exportCampaignReportPdf(payload: CampaignReportPayload): Promise<string> {
+  if (!isTauriRuntime()) {
+    throw new Error('导出 PDF 报告需要在桌面应用中运行');
+  }
+  return (await invoke('export_campaign_report_pdf', { payload })) as string;
+}
+
+export async function getCapabilities(): Promise<Capabilities> {
+  if (!isTauriRuntime()) {
+    return {
+      version: 'mock',
+      native_engine: true,
+      python_worker: { compiled: true, ready: true, message: '当前为预览环境，使用模拟 Python 运行时' },
+      imap: true,
+      ldap: true,
+      databases: ['sqlite', 'postgres'],
+      smime: true,
+      local_mta: true,
+      max_connections: 16,
+      smtp_auth_mechanisms: ['PLAIN', 'LOGIN', 'CRAM-MD5', 'NTLM'],
+      tracking_server: false,
+      oauth_providers: [],
+      cli_agent: false,
+      strict_local: false,
+    };
+  }
+  return (await invoke('get_capabilities')) as Capabilities;
+}
+
+export async function getAppPaths(): Promise<AppPaths> {
+  if (!isTauriRuntime()) {
+    return {
+      data_dir: '.',
+      sent_store_file: 'sent_records.jsonl',
+      sent_store_text_file: 'sent_records.txt',
+      log_file: 'email_log.txt',
+      app_draft_file: 'app_draft.json',
+    };
+  }
+  return (await invoke('get_app_paths')) as AppPaths;
+}
+
+export async function setDataDir(path: string): Promise<AppPaths> {
+  if (!isTauriRuntime()) {
+    return getAppPaths();
+  }
+  return (await invoke('set_data_dir', { path })) as AppPaths;
+}
+
+export async function loadAppDraft(): Promise<Partial<AppDraft>> {
+  if (!isTauriRuntime()) {
+    const raw = window.localStorage.getItem('bulk-email-sender:draft:v1');
+    if (!raw) {
+      return {};
+    }
+    try {
+      return JSON.parse(raw) as Partial<AppDraft>;
+    } catch {
+      return {};
+    }
+  }
+  return (await invoke('load_app_draft')) as Partial<AppDraft>;
+}
+
+export const SESSION_SCHEMA_VERSION = 1;
+
+export async function loadSessionState(): Promise<SessionState> {
+  if (!isTauriRuntime()) {
+    const raw = window.localStorage.getItem('bulk-email-sender:session:v1');
+    if (!raw) {
+      return { version: SESSION_SCHEMA_VERSION };
+    }
+    try {
+      return JSON.parse(raw) as SessionState;
+    } catch {
+      return { version: SESSION_SCHEMA_VERSION };
+    }
+  }
+  return (await invoke('load_session_state')) as SessionState;
+}
+
+export async function saveSessionState(payload: SessionState): Promise<SessionState> {
+  if (!isTauriRuntime()) {
+    const saved = { ...payload, saved_at: new Date().toISOString() };
+    window.localStorage.setItem('bulk-email-sender:session:v1', JSON.stringify(saved));
+    return saved;
+  }
+  return (await invoke('save_session_state', { payload })) as SessionState;
+}
+
+export async function saveAppDraft(payload: AppDraft): Promise<void> {
+  if (!isTauriRuntime()) {
+    window.localStorage.setItem('bulk-email-sender:draft:v1', JSON.stringify(payload));
+    return;
+  }
+  await invoke('save_app_draft', { payload });
+}
+
+export async function getNetworkPolicy(): Promise<NetworkPolicy> {
+  if (!isTauriRuntime()) {
+    return { strict_local: false, allowed_smtp_host: null };
+  }
+  return (await invoke('get_network_policy')) as NetworkPolicy;
+}
+
+export async function setNetworkPolicy(payload: NetworkPolicy): Promise<NetworkPolicy> {
+  if (!isTauriRuntime()) {
+    return payload;
+  }
+  return (await invoke('set_network_policy', { payload })) as NetworkPolicy;
+}
+
+export async function getUploadSettings(): Promise<UploadSettings> {
+  if (!isTauriRuntime()) {
+    return {};
+  }
+  return (await invoke('get_upload_settings')) as UploadSettings;
+}
+
+export async function setUploadSettings(payload: UploadSettings): Promise<UploadSettings> {
+  if (!isTauriRuntime()) {
+    return payload;
+  }
+  return (await invoke('set_upload_settings', { payload })) as UploadSettings;
+}
+
+export async function getJobQueueSettings(): Promise<JobQueueSettings> {
+  if (!isTauriRuntime()) {
+    return { max_concurrent: 1 };
+  }
+  return (await invoke('get_job_queue_settings')) as JobQueueSettings;
+}
+
+export async function setJobQueueSettings(payload: JobQueueSettings): Promise<JobQueueSettings> {
+  if (!isTauriRuntime()) {
+    return payload;
+  }
+  return (await invoke('set_job_queue_settings', { payload })) as JobQueueSettings;
+}
+
+export async function getEnrichmentSettings(): Promise<EnrichmentSettings> {
+  if (!isTauriRuntime()) {
+    return {};
+  }
+  return (await invoke('get_enrichment_settings')) as EnrichmentSettings;
+}
+
+export async function setEnrichmentSettings(payload: EnrichmentSettings): Promise<EnrichmentSettings> {
+  if (!isTauriRuntime()) {
+    return payload;
+  }
+  return (await invoke('set_enrichment_settings', { payload })) as EnrichmentSettings;
+}
+
+export async function enrichRecipients(recipients: Recipient[]): Promise<EnrichmentReport> {
+  if (!isTauriRuntime()) {
+    throw new Error('联系人补充需要在桌面应用中运行');
+  }
+  return (await invoke('enrich_recipients', { recipients })) as EnrichmentReport;
+}
+
+export async function getWorkerSettings(): Promise<WorkerSettings> {
+  if (!isTauriRuntime()) {
+    return { ephemeral_uv: false, transport: 'stdio' };
+  }
+  return (await invoke('get_worker_settings')) as WorkerSettings;
+}
+
+export async function setWorkerSettings(payload: WorkerSettings): Promise<WorkerSettings> {
+  if (!isTauriRuntime()) {
+    return payload;
+  }
+  return (await invoke('set_worker_settings', { payload })) as WorkerSettings;
+}
+
+const MOCK_PENDING_UPDATES: PendingUpdatesStatus = {
+  updates: [],
+  maintenance: {},
+  job_running: false,
+  due: true,
+};
+
+export async function getPendingUpdates(): Promise<PendingUpdatesStatus> {
+  if (!isTauriRuntime()) {
+    return MOCK_PENDING_UPDATES;
+  }
+  return (await invoke('get_pending_updates')) as PendingUpdatesStatus;
+}
+
+export async function setMaintenanceSettings(payload: MaintenanceSettings): Promise<MaintenanceSettings> {
+  if (!isTauriRuntime()) {
+    return payload;
+  }
+  return (await invoke('set_maintenance_settings', { payload })) as MaintenanceSettings;
+}
+
+export async function applyPendingUpdates(): Promise<PendingUpdatesStatus> {
+  if (!isTauriRuntime()) {
+    return MOCK_PENDING_UPDATES;
+  }
+  return (await invoke('apply_pending_updates')) as PendingUpdatesStatus;
+}
+
+export async function discardPendingUpdates(): Promise<PendingUpdatesStatus> {
+  if (!isTauriRuntime()) {
+    return MOCK_PENDING_UPDATES;
+  }
+  return (await invoke('discard_pending_updates')) as PendingUpdatesStatus;
 }
 
-export async function loadRecipients(path: string): Promise<LoadRecipientsResult> {
+export async function onPendingUpdates(handler: (status: PendingUpdatesStatus) => void): Promise<() => void> {
   if (!isTauriRuntime()) {
-    const preview: Recipient[] = [
-      { email: 'teacher1@example.com', name: '张教授' },
-      { email: 'teacher2@example.com', name: '李教授' },
-      { email: 'teacher3@example.com', name: '王教授' },
-    ];
-    return {
-      stats: {
-        total_rows: preview.length,
-        valid_rows: preview.length,
-        sendable_rows: preview.length,
-        invalid_rows: 0,
-        invalid_email_rows: 0,
-        missing_name_rows: 0,
-        duplicate_rows: 0,
-        empty_rows: 0,
-      },
-      recipientsPreview: preview,
-    };
+    return () => {};
   }
+  return listen<PendingUpdatesStatus>(PENDING_UPDATES_CHANNEL, (event) => handler(event.payload));
+}
 
-  const event = (await invoke('load_recipients', { path })) as WorkerEvent;
-  if (event.type === 'error') {
-    throw new Error(event.error);
+export async function getAlertSettings(): Promise<AlertSettings> {
+  if (!isTauriRuntime()) {
+    return { min_attempts: 20 };
   }
-  if (event.type !== 'recipients_loaded') {
-    throw new Error(`Unexpected response type: ${event.type}`);
+  return (await invoke('get_alert_settings')) as AlertSettings;
+}
+
+export async function setAlertSettings(payload: AlertSettings): Promise<AlertSettings> {
+  if (!isTauriRuntime()) {
+    return payload;
   }
+  return (await invoke('set_alert_settings', { payload })) as AlertSettings;
+}
 
-  return {
-    stats: event.stats,
-    recipientsPreview: event.recipients_preview,
-  };
+/** 所有任务的生命周期事件（受理、排队、启动、结束与错误），用于同时显示多个任务的界面。 */
+export async function onJobLifecycle(handler: (event: WorkerEvent) => void): Promise<() => void> {
+  if (!isTauriRuntime()) {
+    return () => {};
+  }
+  return listen<WorkerEvent>(JOB_LIFECYCLE_CHANNEL, (event) => handler(event.payload));
 }
 
-export async function testSmtp(payload: SmtpPayload): Promise<void> {
+export async function onAlert(handler: (event: AlertEvent) => void): Promise<() => void> {
   if (!isTauriRuntime()) {
-    if (!payload.username || !payload.password || !payload.host) {
-      throw new Error('请先填写 SMTP 配置后再测试');
-    }
-    return;
+    return () => {};
   }
+  return listen<AlertEvent>(ALERT_CHANNEL, (event) => handler(event.payload));
+}
 
-  const event = (await invoke('test_smtp', { payload })) as WorkerEvent;
-  if (event.type === 'error') {
-    throw new Error(event.error);
+export async function validateSmimeCertificate(path: string, password: string): Promise<SmimeCertificateInfo> {
+  if (!isTauriRuntime()) {
+    throw new Error('预览环境不支持读取证书文件');
   }
-  if (event.type !== 'smtp_test_succeeded') {
-    throw new Error(`Unexpected response type: ${event.type}`);
+  return (await invoke('validate_smime_certificate', { path, password })) as SmimeCertificateInfo;
+}
+
+export async function importSmimeCertificate(path: string, password: string): Promise<SmimeCertificateInfo> {
+  if (!isTauriRuntime()) {
+    throw new Error('预览环境不支持导入证书');
   }
+  return (await invoke('import_smime_certificate', { path, password })) as SmimeCertificateInfo;
 }
 
-export async function startSend(
-  payload: SendPayload,
-  onEvent: (event: WorkerEvent) => void,
-): Promise<() => Promise<void>> {
-  const effectivePayload: SendPayload = {
-    ...payload,
-    job_id: payload.job_id ?? randomJobId(),
-  };
+export async function getSmimeCertificate(): Promise<SmimeCertificateInfo | null> {
+  if (!isTauriRuntime()) {
+    return null;
+  }
+  return (await invoke('get_smime_certificate')) as SmimeCertificateInfo | null;
+}
 
+export async function removeSmimeCertificate(): Promise<void> {
   if (!isTauriRuntime()) {
-    return createMockSendingFlow(effectivePayload, onEvent);
+    return;
   }
+  await invoke('remove_smime_certificate');
+}
 
-  let dispose: (() => void) | null = null;
-  dispose = await listen<WorkerEvent>(WORKER_EVENT_CHANNEL, (event) => {
-    onEvent(event.payload);
-    if (
-      event.payload.type === 'job_finished' ||
-      event.payload.type === 'job_cancelled' ||
-      event.payload.type === 'error'
-    ) {
-      dispose?.();
-      dispose = null;
-    }
-  });
+export async function importRecipientCertificate(path: string): Promise<SmimeCertificateInfo> {
+  if (!isTauriRuntime()) {
+    throw new Error('预览环境不支持导入证书');
+  }
+  return (await invoke('import_recipient_certificate', { path })) as SmimeCertificateInfo;
+}
 
-  try {
-    await invoke('start_send', { payload: effectivePayload });
-  } catch (error) {
-    dispose?.();
-    dispose = null;
-    throw error;
+export async function listRecipientCertificates(): Promise<SmimeCertificateInfo[]> {
+  if (!isTauriRuntime()) {
+    return [];
   }
+  return (await invoke('list_recipient_certificates')) as SmimeCertificateInfo[];
+}
 
-  return async () => {
-    await invoke('cancel_send');
-    dispose?.();
-    dispose = null;
-  };
+export async function getSharedDirSettings(): Promise<SharedDirSettings> {
+  if (!isTauriRuntime()) {
+    return { enabled: false };
+  }
+  return (await invoke('get_shared_dir_settings')) as SharedDirSettings;
+}
+
+export async function setSharedDirSettings(payload: SharedDirSettings): Promise<AppPaths> {
+  if (!isTauriRuntime()) {
+    return getAppPaths();
+  }
+  return (await invoke('set_shared_dir_settings', { payload })) as AppPaths;
+}
+
+export async function getCurrentUser(): Promise<UserIdentity> {
+  if (!isTauriRuntime()) {
+    return { user: 'preview', host: 'browser' };
+  }
+  return (await invoke('get_current_user')) as UserIdentity;
 }
 
-export async function cancelSend(): Promise<void> {
+export async function listDataDirLocks(): Promise<DataDirLock[]> {
+  if (!isTauriRuntime()) {
+    return [];
+  }
+  return (await invoke('list_data_dir_locks')) as DataDirLock[];
+}
+
+export async function breakDataDirLock(name: string): Promise<void> {
   if (!isTauriRuntime()) {
     return;
   }
-  await invoke('cancel_send');
+  await invoke('break_data_dir_lock', { name });
+}
+
+export async function testWorkerCommand(payload: WorkerCommandOverride): Promise<string> {
+  if (!isTauriRuntime()) {
+    return '当前为预览环境，自定义 worker 命令可用（模拟）';
+  }
+  return (await invoke('test_worker_command', { payload })) as string;
+}
+
+export async function warmWorkerCache(): Promise<string> {
+  if (!isTauriRuntime()) {
+    return '当前为预览环境，已跳过依赖缓存预热（模拟）';
+  }
+  return (await invoke('warm_worker_cache')) as string;
+}
+
+export async function importSuppressionList(path: string): Promise<SuppressionImportSummary> {
+  if (!isTauriRuntime()) {
+    return { imported: 0, duplicates: 0, invalid: [] };
+  }
+  return (await invoke('import_suppression_list', { path })) as SuppressionImportSummary;
+}
+
+export async function importSuppressionCsv(path: string): Promise<SuppressionImportSummary> {
+  if (!isTauriRuntime()) {
+    return { imported: 0, duplicates: 0, invalid: [] };
+  }
+  return (await invoke('import_suppression_csv', { path })) as SuppressionImportSummary;
+}
+
+export async function addToSuppression(entries: string[], reason?: string): Promise<SuppressionImportSummary> {
+  if (!isTauriRuntime()) {
+    return { imported: entries.length, duplicates: 0, invalid: [] };
+  }
+  return (await invoke('add_to_suppression', { payload: { entries, reason } })) as SuppressionImportSummary;
+}
+
+export async function listSuppression(query?: string, limit?: number): Promise<SuppressionListing> {
+  if (!isTauriRuntime()) {
+    return { total_addresses: 0, total_domains: 0, addresses: [], domains: [] };
+  }
+  return (await invoke('list_suppression', { payload: { query, limit } })) as SuppressionListing;
+}
+
+export async function suppressRecipients(
+  emails: string[],
+  reason?: string,
+  campaignId?: string,
+): Promise<SuppressionImportSummary> {
+  if (!isTauriRuntime()) {
+    return { imported: emails.length, duplicates: 0, invalid: [] };
+  }
+  return (await invoke('suppress_recipients', {
+    payload: { emails, reason, campaign_id: campaignId },
+  })) as SuppressionImportSummary;
 }
 
-export async function clearSentRecords(): Promise<void> {
+export async function annotateRecords(campaignId: string, emails: string[], note: string): Promise<number> {
+  if (!isTauriRuntime()) {
+    return emails.length;
+  }
+  return (await invoke('annotate_records', { payload: { campaign_id: campaignId, emails, note } })) as number;
+}
+
+export async function listRecordNotes(campaignId: string): Promise<RecordNote[]> {
+  if (!isTauriRuntime()) {
+    return [];
+  }
+  return (await invoke('list_record_notes', { campaignId })) as RecordNote[];
+}
+
+export async function exportSuppressionList(path: string): Promise<number> {
+  if (!isTauriRuntime()) {
+    return 0;
+  }
+  return (await invoke('export_suppression_list', { path })) as number;
+}
+
+export async function importConsentRecords(path: string): Promise<ConsentImportSummary> {
+  if (!isTauriRuntime()) {
+    return { imported: 0, updated: 0, invalid: [] };
+  }
+  return (await invoke('import_consent_records', { path })) as ConsentImportSummary;
+}
+
+export async function checkConsent(recipients: Recipient[]): Promise<ConsentReport> {
+  if (!isTauriRuntime()) {
+    return { consented: recipients.length, missing: [] };
+  }
+  return (await invoke('check_consent', { recipients })) as ConsentReport;
+}
+
+export async function validateRecipients(recipients: Recipient[]): Promise<RecipientValidationReport> {
+  if (!isTauriRuntime()) {
+    return {
+      results: recipients.map((recipient, index) => ({
+        row: index + 1,
+        ...recipient,
+        verdict: 'valid',
+        reasons: [],
+        role_account: false,
+      })),
+      valid: recipients.length,
+      suspicious: 0,
+      invalid: 0,
+      role_accounts: 0,
+      kept: recipients,
+    };
+  }
+  return (await invoke('validate_recipients', { recipients })) as RecipientValidationReport;
+}
+
+export async function getValidationSettings(): Promise<ValidationSettings> {
+  if (!isTauriRuntime()) {
+    return {
+      role_prefixes: [
+        'abuse',
+        'admin',
+        'administrator',
+        'billing',
+        'contact',
+        'help',
+        'hostmaster',
+        'info',
+        'mailer-daemon',
+        'marketing',
+        'no-reply',
+        'noreply',
+        'office',
+        'postmaster',
+        'root',
+        'sales',
+        'support',
+        'webmaster',
+      ],
+    };
+  }
+  return (await invoke('get_validation_settings')) as ValidationSettings;
+}
+
+export async function setValidationSettings(payload: ValidationSettings): Promise<ValidationSettings> {
+  if (!isTauriRuntime()) {
+    return payload;
+  }
+  return (await invoke('set_validation_settings', { payload })) as ValidationSettings;
+}
+
+/** 连接收件域名的 MX 检查邮箱是否存在；调用前须向用户展示风险并取得确认。 */
+export async function probeRecipients(
+  recipients: Recipient[],
+  options: RcptProbeOptions,
+  acknowledgeRisks: boolean,
+): Promise<RcptProbeReport> {
+  if (!isTauriRuntime()) {
+    throw new Error('RCPT 探测需要在桌面应用中运行');
+  }
+  return (await invoke('probe_recipients', {
+    payload: { recipients, options, acknowledge_risks: acknowledgeRisks },
+  })) as RcptProbeReport;
+}
+
+export async function onRcptProbeResult(handler: (result: ProbeResult) => void): Promise<() => void> {
+  if (!isTauriRuntime()) {
+    return () => {};
+  }
+  return listen<ProbeResult>(RCPT_PROBE_CHANNEL, (event) => handler(event.payload));
+}
+
+export async function preflightDeadDomains(recipients: Recipient[]): Promise<DeadDomainReport> {
+  if (!isTauriRuntime()) {
+    return { flagged: [], domains: [], kept: recipients };
+  }
+  return (await invoke('preflight_dead_domains', { recipients })) as DeadDomainReport;
+}
+
+export async function listDeadDomains(): Promise<DeadDomainSummary[]> {
+  if (!isTauriRuntime()) {
+    return [];
+  }
+  return (await invoke('list_dead_domains')) as DeadDomainSummary[];
+}
+
+export async function forgetDeadDomain(domain: string): Promise<void> {
   if (!isTauriRuntime()) {
     return;
   }
-  await invoke('clear_sent_records');
+  await invoke('forget_dead_domain', { domain });
 }
 
-export async function getAppPaths(): Promise<AppPaths> {
+export async function checkDisposableRecipients(recipients: Recipient[]): Promise<DisposableReport> {
+  if (!isTauriRuntime()) {
+    return { flagged: [], domains: [], kept: recipients };
+  }
+  return (await invoke('check_disposable_recipients', { recipients })) as DisposableReport;
+}
+
+export async function getDisposableDomains(): Promise<DisposableDomainsStatus> {
+  if (!isTauriRuntime()) {
+    throw new Error('查看一次性邮箱域名列表需要在桌面应用中运行');
+  }
+  return (await invoke('get_disposable_domains')) as DisposableDomainsStatus;
+}
+
+export async function updateDisposableDomains(url?: string): Promise<DisposableDomainsStatus> {
+  if (!isTauriRuntime()) {
+    throw new Error('更新一次性邮箱域名列表需要在桌面应用中运行');
+  }
+  return (await invoke('update_disposable_domains', { url: url ?? null })) as DisposableDomainsStatus;
+}
+
+export async function listSmtpAccounts(): Promise<SmtpAccountSummary[]> {
+  if (!isTauriRuntime()) {
+    return [];
+  }
+  return (await invoke('list_smtp_accounts')) as SmtpAccountSummary[];
+}
+
+export async function addSmtpAccount(payload: SmtpAccount): Promise<SmtpAccountSummary> {
   if (!isTauriRuntime()) {
     return {
-      data_dir: '.',
-      sent_store_file: 'sent_records.jsonl',
-      sent_store_text_file: 'sent_records.txt',
-      log_file: 'email_log.txt',
-      app_draft_file: 'app_draft.json',
+      id: payload.id ?? 'acct-mock',
+      label: payload.label || payload.sender.email,
+      sender_email: payload.sender.email,
+      sender_name: payload.sender.name,
+      host: payload.smtp.host,
+      port: payload.smtp.port,
+      has_password: Boolean(payload.smtp.password),
+      environment: payload.smtp.environment ?? null,
+      daily_cap: payload.daily_cap ?? null,
+      sent_today: 0,
     };
   }
-  return (await invoke('get_app_paths')) as AppPaths;
+  return (await invoke('add_smtp_account', { payload })) as SmtpAccountSummary;
 }
 
-export async function setDataDir(path: string): Promise<AppPaths> {
+export async function removeSmtpAccount(id: string): Promise<void> {
   if (!isTauriRuntime()) {
-    return getAppPaths();
+    return;
   }
-  return (await invoke('set_data_dir', { path })) as AppPaths;
+  await invoke('remove_smtp_account', { id });
 }
 
-export async function loadAppDraft(): Promise<Partial<AppDraft>> {
+export async function listSmtpProfiles(): Promise<SmtpProfile[]> {
   if (!isTauriRuntime()) {
-    const raw = window.localStorage.getItem('bulk-email-sender:draft:v1');
-    if (!raw) {
-      return {};
-    }
-    try {
-      return JSON.parse(raw) as Partial<AppDraft>;
-    } catch {
-      return {};
-    }
+    return [];
   }
-  return (await invoke('load_app_draft')) as Partial<AppDraft>;
+  return (await invoke('list_smtp_profiles')) as SmtpProfile[];
 }
 
-export async function saveAppDraft(payload: AppDraft): Promise<void> {
+/** 保存命名的 SMTP 配置；密码留空时保留该配置原有的密码。 */
+export async function saveSmtpProfile(name: string, smtp: SmtpPayload): Promise<SmtpProfile> {
+  if (!isTauriRuntime()) {
+    return { name: name.trim(), smtp };
+  }
+  return (await invoke('save_smtp_profile', { name, smtp })) as SmtpProfile;
+}
+
+export async function deleteSmtpProfile(name: string): Promise<void> {
   if (!isTauriRuntime()) {
-    window.localStorage.setItem('bulk-email-sender:draft:v1', JSON.stringify(payload));
     return;
   }
-  await invoke('save_app_draft', { payload });
+  await invoke('delete_smtp_profile', { name });
+}
+
+function mockThrottleStatus(limits: ThrottleLimits): ThrottleStatus {
+  return {
+    limits,
+    sent_last_minute: 0,
+    sent_last_hour: 0,
+    sent_last_day: 0,
+    remaining_today: limits.per_day ?? null,
+    next_send_in_sec: 0,
+    blocked_by: null,
+  };
+}
+
+export async function getThrottleStatus(): Promise<ThrottleStatus> {
+  if (!isTauriRuntime()) {
+    return mockThrottleStatus({});
+  }
+  return (await invoke('get_throttle_status')) as ThrottleStatus;
+}
+
+export async function setThrottleLimits(payload: ThrottleLimits): Promise<ThrottleStatus> {
+  if (!isTauriRuntime()) {
+    return mockThrottleStatus(payload);
+  }
+  return (await invoke('set_throttle_limits', { payload })) as ThrottleStatus;
+}
+
+/** 调整正在发送的任务的限速，从下一封邮件起生效。 */
+export async function updateJobThrottle(jobId: string, newLimits: ThrottleLimits): Promise<ThrottleLimits> {
+  if (!isTauriRuntime()) {
+    return newLimits;
+  }
+  return (await invoke('update_job_throttle', { jobId, newLimits })) as ThrottleLimits;
 }
 
 export async function openPath(path: string): Promise<void> {
@@ -219,6 +1411,20 @@ export async function clearRuntimePython(): Promise<RuntimeStatus> {
   return (await invoke('clear_runtime_python')) as RuntimeStatus;
 }
 
+export async function probeRuntimeLibraries(): Promise<MissingSystemLibrary[]> {
+  if (!isTauriRuntime()) {
+    return [];
+  }
+  return (await invoke('probe_runtime_libraries')) as MissingSystemLibrary[];
+}
+
+export async function cleanupWorkerEnvs(): Promise<string[]> {
+  if (!isTauriRuntime()) {
+    return [];
+  }
+  return (await invoke('cleanup_worker_envs')) as string[];
+}
+
 export async function installRuntimeFromArchive(archivePath: string): Promise<RuntimeStatus> {
   if (!isTauriRuntime()) {
     return {
@@ -255,7 +1461,9 @@ export async function autoInstallRuntime(manifestUrl: string): Promise<RuntimeSt
   })) as RuntimeStatus;
 }
 
-export async function autoDetectRuntime(): Promise<RuntimeStatus> {
+export async function autoDetectRuntime(
+  onProgress?: (progress: RuntimeProgress) => void,
+): Promise<RuntimeStatus> {
   if (!isTauriRuntime()) {
     return {
       ready: true,
@@ -265,7 +1473,21 @@ export async function autoDetectRuntime(): Promise<RuntimeStatus> {
       message: '当前为预览环境，已完成自动检测（模拟）',
     };
   }
-  return (await invoke('auto_detect_runtime')) as RuntimeStatus;
+  const dispose = onProgress
+    ? await listen<RuntimeProgress>(RUNTIME_PROGRESS_CHANNEL, (event) => onProgress(event.payload))
+    : null;
+  try {
+    return (await invoke('auto_detect_runtime')) as RuntimeStatus;
+  } finally {
+    dispose?.();
+  }
+}
+
+export async function setUvDownloadBase(url: string | null): Promise<string | null> {
+  if (!isTauriRuntime()) {
+    return url;
+  }
+  return (await invoke('set_uv_download_base', { url })) as string | null;
 }
 
 async function createMockSendingFlow(
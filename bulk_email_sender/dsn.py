@@ -0,0 +1,57 @@
+from __future__ import annotations
+
+from dataclasses import dataclass
+from typing import Any
+
+DSN_NOTIFY_VALUES = ("success", "failure", "delay", "never")
+DSN_RETURN_VALUES = {"full": "FULL", "headers": "HDRS"}
+# RFC 3461: ENVID is at most 100 characters.
+MAX_ENVID_LEN = 100
+
+
+@dataclass(frozen=True)
+class DsnOptions:
+    """Delivery status notification request, applied only when the server advertises DSN."""
+
+    notify: tuple[str, ...] = ("failure", "delay")
+    ret: str | None = None
+    envid: bool = True
+
+    def mail_options(self, message_id: str | None) -> list[str]:
+        options: list[str] = []
+        if self.ret:
+            options.append(f"RET={DSN_RETURN_VALUES[self.ret]}")
+        if self.envid and message_id:
+            options.append(f"ENVID={_xtext(envelope_id(message_id))}")
+        return options
+
+    def rcpt_options(self) -> list[str]:
+        return ["NOTIFY=" + ",".join(value.upper() for value in self.notify)]
+
+
+def envelope_id(message_id: str) -> str:
+    """ENVID derived from the Message-ID, so DSN reports can be matched to sent records."""
+    return message_id.strip().lstrip("<").rstrip(">")[:MAX_ENVID_LEN]
+
+
+def parse_dsn_options(value: Any) -> DsnOptions | None:
+    if not value:
+        return None
+    if not isinstance(value, dict):
+        raise ValueError("DSN 配置格式不正确")
+    notify = tuple(str(item).strip().lower() for item in value.get("notify") or ("failure", "delay"))
+    if not notify:
+        raise ValueError("DSN 至少需要选择一种通知条件")
+    for item in notify:
+        if item not in DSN_NOTIFY_VALUES:
+            raise ValueError(f"DSN 通知条件无效: {item}")
+    if "never" in notify and len(notify) > 1:
+        raise ValueError("DSN 的 never 不能与其他通知条件同时使用")
+    ret = str(value.get("ret") or "").strip().lower() or None
+    if ret is not None and ret not in DSN_RETURN_VALUES:
+        raise ValueError(f"DSN 退回内容无效: {ret}")
+    return DsnOptions(notify=notify, ret=ret, envid=bool(value.get("envid", True)))
+
+
+def _xtext(value: str) -> str:
+    return "".join(char if 33 <= ord(char) <= 126 and char not in "+=" else f"+{ord(char):02X}" for char in value)
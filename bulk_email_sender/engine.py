@@ -4,14 +4,16 @@ import random
 import re
 import threading
 import time
-from collections.abc import Iterator
-from datetime import datetime
+from collections.abc import Generator, Iterator
+from dataclasses import dataclass, field
+from datetime import datetime, timedelta, timezone
 from html import escape
 from math import ceil
 from pathlib import Path
 from typing import Any
 
-from bulk_email_sender.message_builder import build_email_message
+from bulk_email_sender.greylist import GreylistTracker, is_greylisting, recipient_domain, smtp_failure
+from bulk_email_sender.message_builder import build_email_message, stable_message_id
 from bulk_email_sender.models import JobConfig, Recipient
 from bulk_email_sender.sent_store import SentStore
 from bulk_email_sender.smtp_client import SMTPClient
@@ -27,6 +29,69 @@ SIGNATURE_TOKENS_PATTERN = re.compile(
 )
 
 
+class MessageTooLargeError(ValueError):
+    """The rendered message exceeds the server's advertised SIZE limit, so it is not sent."""
+
+
+class JobControl:
+    """Cooperative control of a running job, driven by control messages on the worker's stdin.
+
+    Pause takes effect before the next recipient, so the current message and its sent record
+    are always finished first. Throttle changes apply from the next inter-send wait.
+    """
+
+    def __init__(self) -> None:
+        self.cancel_event = threading.Event()
+        self._resumed = threading.Event()
+        self._resumed.set()
+        self._lock = threading.Lock()
+        self._min_delay_sec: int | None = None
+        self._max_delay_sec: int | None = None
+        self._min_interval_sec = 0
+
+    def cancel(self) -> None:
+        self.cancel_event.set()
+        # Wake a paused job so it can report the cancellation.
+        self._resumed.set()
+
+    def pause(self) -> None:
+        self._resumed.clear()
+
+    def resume(self) -> None:
+        self._resumed.set()
+
+    @property
+    def paused(self) -> bool:
+        return not self._resumed.is_set()
+
+    def wait_resumed(self) -> None:
+        self._resumed.wait()
+
+    def set_throttle(
+        self,
+        *,
+        per_minute: int | None = None,
+        per_hour: int | None = None,
+        per_day: int | None = None,
+        min_delay_sec: int | None = None,
+        max_delay_sec: int | None = None,
+    ) -> None:
+        """Override the job's delay bounds; rate limits become a minimum interval between sends."""
+        windows = ((60, per_minute), (3600, per_hour), (86400, per_day))
+        min_interval = max((ceil(window / limit) for window, limit in windows if limit), default=0)
+        with self._lock:
+            self._min_delay_sec = min_delay_sec
+            self._max_delay_sec = max_delay_sec
+            self._min_interval_sec = min_interval
+
+    def delay_bounds(self, job: JobConfig) -> tuple[int, int]:
+        with self._lock:
+            min_delay = job.options.min_delay_sec if self._min_delay_sec is None else self._min_delay_sec
+            max_delay = job.options.max_delay_sec if self._max_delay_sec is None else self._max_delay_sec
+            floor = self._min_interval_sec
+        return max(min_delay, floor), max(max_delay, floor)
+
+
 class SendEngine:
     def __init__(
         self,
@@ -41,37 +106,46 @@ class SendEngine:
         self.sleep_func = sleep_func
         self.randomizer = randomizer or random.Random()
 
-    def send(self, job: JobConfig, cancel_event: threading.Event | None = None) -> Iterator[dict[str, Any]]:
+    def send(
+        self,
+        job: JobConfig,
+        cancel_event: threading.Event | None = None,
+        control: JobControl | None = None,
+    ) -> Iterator[dict[str, Any]]:
+        if control is not None:
+            cancel_event = control.cancel_event
         self._validate_attachments(job.attachments)
+        if job.options.seed is not None:
+            self.randomizer = random.Random(job.options.seed)
         recipients = list(job.recipients)
         if job.options.randomize_order:
             self.randomizer.shuffle(recipients)
 
-        success = 0
-        failed = 0
-        skipped = 0
-        failures: list[dict[str, str]] = []
+        counters = _JobCounters()
+        tracker = GreylistTracker(job.options.greylist_delay_sec)
+        deferred: list[_DeferredRecipient] = []
 
-        yield {
+        started: dict[str, Any] = {
             "type": "job_started",
             "job_id": job.job_id,
             "total": len(recipients),
         }
+        if job.options.seed is not None:
+            started["seed"] = job.options.seed
+        yield started
 
         for index, recipient in enumerate(recipients, start=1):
+            if control is not None and control.paused:
+                yield {"type": "job_paused", "job_id": job.job_id, "next_index": index}
+                control.wait_resumed()
+                if not control.cancel_event.is_set():
+                    yield {"type": "job_resumed", "job_id": job.job_id, "next_index": index}
             if cancel_event and cancel_event.is_set():
-                yield {
-                    "type": "job_cancelled",
-                    "job_id": job.job_id,
-                    "success": success,
-                    "failed": failed,
-                    "skipped": skipped,
-                    "total": len(recipients),
-                }
+                yield _cancelled_event(job.job_id, counters, len(recipients))
                 return
 
             if job.options.skip_sent and self.sent_store.is_sent(recipient.email):
-                skipped += 1
+                counters.skipped += 1
                 yield {
                     "type": "recipient_skipped",
                     "job_id": job.job_id,
@@ -82,53 +156,42 @@ class SendEngine:
                 }
                 continue
 
-            teacher_name = recipient.name
-            yield {
-                "type": "recipient_started",
-                "job_id": job.job_id,
-                "index": index,
-                "email": recipient.email,
-                "name": teacher_name,
-            }
-
-            try:
-                message = self._build_message(job, recipient, teacher_name)
-                # Use a fresh connection per email: avoids idle-timeout reconnect
-                # penalties caused by SMTP servers silently dropping connections
-                # during the inter-message delay.
-                with self.smtp_client:
-                    self._send_with_retry(
-                        recipient_email=recipient.email,
-                        message=message,
-                        retry_count=job.options.retry_count,
-                    )
-                self.sent_store.append(
-                    email=recipient.email,
-                    teacher_name=teacher_name,
-                    job_id=job.job_id,
-                )
-                success += 1
+            domain = recipient_domain(recipient.email)
+            until = tracker.deferred_until(domain, time.monotonic())
+            if until is not None:
+                # The domain is still greylisting us: park without another rejection.
+                counters.deferred += 1
+                yield _deferred_event(job.job_id, index, recipient, domain, until, 0, "domain_deferred")
+                deferred.append(_DeferredRecipient(index=index, recipient=recipient, next_attempt=until))
+            else:
                 yield {
-                    "type": "recipient_sent",
+                    "type": "recipient_started",
                     "job_id": job.job_id,
                     "index": index,
                     "email": recipient.email,
-                    "name": teacher_name,
-                }
-            except Exception as exc:
-                failed += 1
-                failures.append({"email": recipient.email, "name": teacher_name, "error": str(exc)})
-                yield {
-                    "type": "recipient_failed",
-                    "job_id": job.job_id,
-                    "index": index,
-                    "email": recipient.email,
-                    "name": teacher_name,
-                    "error": str(exc),
+                    "name": recipient.name,
                 }
+                try:
+                    self._deliver(job, recipient)
+                except Exception as exc:
+                    if is_greylisting(exc) and job.options.greylist_max_attempts > 0:
+                        until = tracker.defer(domain, exc, time.monotonic())
+                        counters.deferred += 1
+                        yield _deferred_event(job.job_id, index, recipient, domain, until, 1, smtp_failure(exc)[1])
+                        deferred.append(
+                            _DeferredRecipient(index=index, recipient=recipient, next_attempt=until, attempts=1)
+                        )
+                    else:
+                        yield counters.record_failed(job.job_id, index, recipient, str(exc))
+                else:
+                    tracker.clear(domain)
+                    yield counters.record_sent(job.job_id, index, recipient)
 
             if index < len(recipients):
-                delay = self._pick_delay(job.options.min_delay_sec, job.options.max_delay_sec)
+                if control is not None:
+                    delay = self._pick_delay(*control.delay_bounds(job))
+                else:
+                    delay = self._pick_delay(job.options.min_delay_sec, job.options.max_delay_sec)
                 cancelled = False
                 remaining = float(delay)
                 while remaining > 0:
@@ -149,27 +212,118 @@ class SendEngine:
                         break
                     remaining -= chunk
                 if cancelled:
-                    yield {
-                        "type": "job_cancelled",
-                        "job_id": job.job_id,
-                        "success": success,
-                        "failed": failed,
-                        "skipped": skipped,
-                        "total": len(recipients),
-                    }
+                    yield _cancelled_event(job.job_id, counters, len(recipients))
                     return
 
+        completed = yield from self._drain_deferred(job, tracker, deferred, counters, cancel_event)
+        if not completed:
+            yield _cancelled_event(job.job_id, counters, len(recipients))
+            return
+
         yield {
             "type": "job_finished",
             "job_id": job.job_id,
-            "success": success,
-            "failed": failed,
-            "skipped": skipped,
+            "success": counters.success,
+            "failed": counters.failed,
+            "skipped": counters.skipped,
+            "deferred": counters.deferred,
             "total": len(recipients),
-            "failures": failures,
+            "failures": counters.failures,
         }
 
-    def _build_message(self, job: JobConfig, recipient: Recipient, teacher_name: str):
+    def _drain_deferred(
+        self,
+        job: JobConfig,
+        tracker: GreylistTracker,
+        queue: list[_DeferredRecipient],
+        counters: _JobCounters,
+        cancel_event: threading.Event | None,
+    ) -> Generator[dict[str, Any], None, bool]:
+        """Retry greylisted recipients once their deadline passes; returns False when cancelled."""
+        while queue:
+            item = min(queue, key=lambda entry: entry.next_attempt)
+            queue.remove(item)
+            domain = recipient_domain(item.recipient.email)
+
+            remaining = int(ceil(item.next_attempt - time.monotonic()))
+            while remaining > 0:
+                if cancel_event and cancel_event.is_set():
+                    return False
+                yield {
+                    "type": "deferred_wait",
+                    "job_id": job.job_id,
+                    "index": item.index,
+                    "email": item.recipient.email,
+                    "remaining_sec": remaining,
+                }
+                if self._sleep_with_cancel(1, cancel_event):
+                    return False
+                remaining -= 1
+            if cancel_event and cancel_event.is_set():
+                return False
+
+            yield {
+                "type": "recipient_started",
+                "job_id": job.job_id,
+                "index": item.index,
+                "email": item.recipient.email,
+                "name": item.recipient.name,
+                "attempt": item.attempts + 1,
+            }
+            try:
+                self._deliver(job, item.recipient)
+            except Exception as exc:
+                if not is_greylisting(exc):
+                    yield counters.record_failed(job.job_id, item.index, item.recipient, str(exc))
+                elif item.attempts < job.options.greylist_max_attempts:
+                    item.attempts += 1
+                    item.next_attempt = tracker.defer(domain, exc, time.monotonic())
+                    yield _deferred_event(
+                        job.job_id,
+                        item.index,
+                        item.recipient,
+                        domain,
+                        item.next_attempt,
+                        item.attempts,
+                        smtp_failure(exc)[1],
+                    )
+                    queue.append(item)
+                else:
+                    yield counters.record_failed(
+                        job.job_id, item.index, item.recipient, f"灰名单重试 {item.attempts} 次后仍被拒收"
+                    )
+            else:
+                tracker.clear(domain)
+                yield counters.record_sent(job.job_id, item.index, item.recipient)
+        return True
+
+    def _deliver(self, job: JobConfig, recipient: Recipient) -> None:
+        message_id = _message_id(job, recipient.email)
+        message = self._build_message(job, recipient, recipient.name, message_id)
+        _check_size_limit(message, job.smtp.size_limit)
+        # Use a fresh connection per email: avoids idle-timeout reconnect
+        # penalties caused by SMTP servers silently dropping connections
+        # during the inter-message delay.
+        with self.smtp_client:
+            self._send_with_retry(
+                recipient_email=recipient.email,
+                message=message,
+                retry_count=job.options.retry_count,
+            )
+        self.sent_store.append(
+            email=recipient.email,
+            teacher_name=recipient.name,
+            job_id=job.job_id,
+            envelope={
+                "from": job.sender.email,
+                "to": [recipient.email.strip().lower()],
+                "cc": list(job.cc),
+                "bcc": list(job.bcc),
+            },
+            message_id=message_id,
+        )
+
+    def _build_message(self, job: JobConfig, recipient: Recipient, teacher_name: str, message_id: str | None = None):
         send_date = _format_send_date(datetime.now())
         signature_name = _resolve_signature_name(job)
         normalized_body_text_template = _normalize_signature_tokens_in_template(job.template.body_text)
@@ -180,6 +334,8 @@ class SendEngine:
             "signature_name": signature_name,
             "send_date": send_date,
         }
+        for key, value in recipient.fields.items():
+            variables.setdefault(key, value)
 
         subject = render_template_text(job.template.subject, variables)
         body_text = render_template_text(normalized_body_text_template, variables)
@@ -199,6 +355,12 @@ class SendEngine:
             body_text=body_text,
             body_html=body_html,
             attachments=job.attachments,
+            inline_images=job.inline_images,
+            headers=job.headers,
+            cc=job.cc,
+            bcc=job.bcc,
+            message_id=message_id,
+            read_receipt_to=job.options.read_receipt_to,
         )
 
     def _send_with_retry(self, *, recipient_email: str, message, retry_count: int) -> None:
@@ -209,6 +371,9 @@ class SendEngine:
                 self.smtp_client.send(recipient_email, message)
                 return
             except Exception as exc:
+                if is_greylisting(exc):
+                    # Greylisting goes to the deferred queue; a quick retry would be rejected again.
+                    raise
                 last_error = exc
                 if attempt < retries - 1:
                     # Reset the persistent connection before next retry so we
@@ -240,6 +405,101 @@ class SendEngine:
                 raise FileNotFoundError(f"Attachment not found: {path}")
 
 
+@dataclass
+class _JobCounters:
+    success: int = 0
+    failed: int = 0
+    skipped: int = 0
+    deferred: int = 0
+    failures: list[dict[str, str]] = field(default_factory=list)
+
+    def record_sent(self, job_id: str, index: int, recipient: Recipient) -> dict[str, Any]:
+        self.success += 1
+        return {
+            "type": "recipient_sent",
+            "job_id": job_id,
+            "index": index,
+            "email": recipient.email,
+            "name": recipient.name,
+        }
+
+    def record_failed(self, job_id: str, index: int, recipient: Recipient, error: str) -> dict[str, Any]:
+        self.failed += 1
+        self.failures.append({"email": recipient.email, "name": recipient.name, "error": error})
+        return {
+            "type": "recipient_failed",
+            "job_id": job_id,
+            "index": index,
+            "email": recipient.email,
+            "name": recipient.name,
+            "error": error,
+        }
+
+
+@dataclass
+class _DeferredRecipient:
+    index: int
+    recipient: Recipient
+    next_attempt: float
+    attempts: int = 0
+
+
+def _cancelled_event(job_id: str, counters: _JobCounters, total: int) -> dict[str, Any]:
+    return {
+        "type": "job_cancelled",
+        "job_id": job_id,
+        "success": counters.success,
+        "failed": counters.failed,
+        "skipped": counters.skipped,
+        "total": total,
+    }
+
+
+def _deferred_event(
+    job_id: str,
+    index: int,
+    recipient: Recipient,
+    domain: str,
+    until: float,
+    attempt: int,
+    reason: str,
+) -> dict[str, Any]:
+    retry_after = max(int(until - time.monotonic()), 0)
+    retry_at = datetime.now(timezone.utc).replace(microsecond=0) + timedelta(seconds=retry_after)
+    return {
+        "type": "recipient_deferred",
+        "job_id": job_id,
+        "index": index,
+        "email": recipient.email,
+        "name": recipient.name,
+        "domain": domain,
+        "attempt": attempt,
+        "retry_after_sec": retry_after,
+        "retry_at": retry_at.isoformat().replace("+00:00", "Z"),
+        "reason": reason,
+    }
+
+
+def _check_size_limit(message, size_limit: int | None) -> None:
+    # Reject before connecting instead of getting a 552 halfway through the DATA transfer.
+    if not size_limit:
+        return
+    size = len(message.as_bytes())
+    if size > size_limit:
+        raise MessageTooLargeError(f"邮件大小 {_format_bytes(size)} 超过服务器 SIZE 上限 {_format_bytes(size_limit)}，未发送")
+
+
+def _format_bytes(size: int) -> str:
+    if size >= 1024 * 1024:
+        return f"{size / 1024 / 1024:.1f} MB"
+    return f"{size / 1024:.1f} KB"
+
+
+def _message_id(job: JobConfig, recipient_email: str) -> str:
+    domain = (job.options.message_id_domain or "").strip() or job.sender.email.rpartition("@")[2]
+    return stable_message_id(domain, job.job_id, recipient_email, job.bcc)
+
+
 def _format_send_date(timestamp: datetime) -> str:
     return f"{timestamp.year}年{timestamp.month}月{timestamp.day}日"
 
@@ -0,0 +1,96 @@
+from __future__ import annotations
+
+import re
+import smtplib
+
+DEFAULT_GREYLIST_DELAY_SEC = 15 * 60
+DEFAULT_GREYLIST_MAX_ATTEMPTS = 3
+# Cap on server-advertised delays so a malformed response can't park a job for days.
+MAX_ADVERTISED_DELAY_SEC = 6 * 60 * 60
+
+GREYLIST_MARKERS = (
+    "greylist",
+    "graylist",
+    "grey-list",
+    "gray-list",
+    "try again later",
+    "try later",
+    "retry later",
+    "please try again",
+    "temporarily deferred",
+    "temporarily rejected",
+)
+
+_DELAY_RE = re.compile(r"(?<![\w.])(\d+)\s*([a-z]*)")
+
+
+def smtp_failure(exc: BaseException) -> tuple[int | None, str]:
+    """Extract the SMTP reply code and text from an smtplib exception."""
+    if isinstance(exc, smtplib.SMTPRecipientsRefused):
+        for code, message in exc.recipients.values():
+            return code, _decode(message)
+        return None, str(exc)
+    if isinstance(exc, smtplib.SMTPResponseException):
+        return exc.smtp_code, _decode(exc.smtp_error)
+    return None, str(exc)
+
+
+def is_greylisting(exc: BaseException) -> bool:
+    """450/451 replies whose text carries a typical greylisting hint."""
+    code, message = smtp_failure(exc)
+    if code not in (450, 451):
+        return False
+    lowered = message.lower()
+    return any(marker in lowered for marker in GREYLIST_MARKERS)
+
+
+def advertised_delay(message: str) -> int | None:
+    """Parse a retry hint such as "try again in 300 seconds" or "5 minutes"."""
+    for match in _DELAY_RE.finditer(message.lower()):
+        number, unit = match.groups()
+        if unit == "s" or unit.startswith("sec"):
+            multiplier = 1
+        elif unit.startswith("min"):
+            multiplier = 60
+        elif unit == "h" or unit.startswith("hour"):
+            multiplier = 3600
+        else:
+            continue
+        return min(int(number) * multiplier, MAX_ADVERTISED_DELAY_SEC)
+    return None
+
+
+def recipient_domain(email: str) -> str:
+    _, _, domain = email.rpartition("@")
+    return domain.strip().lower()
+
+
+class GreylistTracker:
+    """Per-domain deferral deadlines, so later recipients on a greylisting
+    domain are parked without triggering another rejection."""
+
+    def __init__(self, default_delay_sec: int):
+        self.default_delay_sec = default_delay_sec
+        self._domains: dict[str, float] = {}
+
+    def defer(self, domain: str, exc: BaseException, now: float) -> float:
+        _, message = smtp_failure(exc)
+        delay = advertised_delay(message)
+        until = now + (self.default_delay_sec if delay is None else delay)
+        self._domains[domain] = max(self._domains.get(domain, until), until)
+        return self._domains[domain]
+
+    def deferred_until(self, domain: str, now: float) -> float | None:
+        until = self._domains.get(domain)
+        if until is not None and until > now:
+            return until
+        return None
+
+    def clear(self, domain: str) -> None:
+        self._domains.pop(domain, None)
+
+
+def _decode(message: bytes | str) -> str:
+    if isinstance(message, bytes):
+        return message.decode("utf-8", errors="replace")
+    return str(message)
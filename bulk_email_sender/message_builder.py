@@ -1,11 +1,35 @@
 from __future__ import annotations
 
+import hashlib
 import mimetypes
+import re
 from email.message import EmailMessage
 from email.utils import formataddr, make_msgid
 from pathlib import Path
 
-from bulk_email_sender.models import Sender
+from bulk_email_sender.models import InlineImage, Sender
+
+MESSAGE_ID_DOMAIN_LABEL_RE = re.compile(r"^[A-Za-z0-9-]+$")
+
+
+def stable_message_id(domain: str, job_id: str, to: str, bcc: list[str] | None = None) -> str:
+    """Deterministic Message-ID for a job and its envelope recipients.
+
+    Retries and re-renders reuse the same ID so bounces can be matched back to
+    the sent record; the native engine derives identical IDs.
+    """
+    job = re.sub(r"[^A-Za-z0-9._-]", "-", job_id)
+    normalized_bcc = ",".join(address.strip().lower() for address in bcc or [])
+    digest = hashlib.sha256(f"{job_id}\n{to.strip().lower()}\n{normalized_bcc}".encode("utf-8")).hexdigest()
+    return f"<{job}.{digest[:16]}@{domain.strip().lower()}>"
+
+
+def validate_message_id_domain(domain: str) -> str:
+    normalized = domain.strip()
+    labels = normalized.split(".")
+    if not normalized or len(normalized) > 253 or not all(MESSAGE_ID_DOMAIN_LABEL_RE.match(label) for label in labels):
+        raise ValueError(f"Message-ID 域名格式不正确: {normalized}")
+    return normalized
 
 
 def build_email_message(
@@ -16,24 +40,48 @@ def build_email_message(
     body_text: str,
     body_html: str | None,
     attachments: list[str],
+    inline_images: list[InlineImage] | None = None,
+    headers: dict[str, str] | None = None,
+    cc: list[str] | None = None,
+    bcc: list[str] | None = None,
+    message_id: str | None = None,
+    read_receipt_to: str | None = None,
 ) -> EmailMessage:
     message = EmailMessage()
     message["From"] = formataddr((sender.name or "", sender.email))
     message["To"] = recipient_email
+    if cc:
+        message["Cc"] = ", ".join(cc)
+    if bcc:
+        # smtplib.send_message 会把 Bcc 计入投递地址并在发送前移除该头。
+        message["Bcc"] = ", ".join(bcc)
     message["Subject"] = subject
-    message["Message-ID"] = make_msgid()
+    message["Message-ID"] = message_id or make_msgid()
+    if read_receipt_to:
+        message["Disposition-Notification-To"] = read_receipt_to
+    for name, value in (headers or {}).items():
+        message[name] = value
 
     message.set_content(body_text, subtype="plain", charset="utf-8")
     if body_html:
         message.add_alternative(body_html, subtype="html", charset="utf-8")
+        html_part = message.get_payload()[-1]
+        for image in inline_images or []:
+            path = Path(image.path)
+            maintype, subtype = _guess_mime_type(path)
+            with path.open("rb") as handle:
+                html_part.add_related(
+                    handle.read(),
+                    maintype=maintype,
+                    subtype=subtype,
+                    cid=f"<{image.cid}>",
+                    filename=path.name,
+                    disposition="inline",
+                )
 
     for attachment in attachments:
         path = Path(attachment)
-        mime_type, _ = mimetypes.guess_type(path.name)
-        if mime_type is None:
-            maintype, subtype = "application", "octet-stream"
-        else:
-            maintype, subtype = mime_type.split("/", 1)
+        maintype, subtype = _guess_mime_type(path)
 
         with path.open("rb") as handle:
             message.add_attachment(
@@ -44,3 +92,11 @@ def build_email_message(
             )
 
     return message
+
+
+def _guess_mime_type(path: Path) -> tuple[str, str]:
+    mime_type, _ = mimetypes.guess_type(path.name)
+    if mime_type is None:
+        return "application", "octet-stream"
+    maintype, subtype = mime_type.split("/", 1)
+    return maintype, subtype
@@ -1,13 +1,18 @@
 from __future__ import annotations
 
-from dataclasses import dataclass
+from dataclasses import dataclass, field
 from pathlib import Path
 
+from bulk_email_sender.dsn import DsnOptions
+from bulk_email_sender.greylist import DEFAULT_GREYLIST_DELAY_SEC, DEFAULT_GREYLIST_MAX_ATTEMPTS
+
 
 @dataclass(frozen=True)
 class Recipient:
     email: str
     name: str
+    # 联系人补充等来源提供的额外模板变量。
+    fields: dict[str, str] = field(default_factory=dict, compare=False)
 
 
 @dataclass(frozen=True)
@@ -25,6 +30,17 @@ class SMTPConfig:
     use_ssl: bool = True
     use_starttls: bool = False
     timeout_sec: int = 30
+    ehlo_hostname: str | None = None
+    return_path: str | None = None
+    auth_mechanism: str | None = None
+    # SIZE limit the server advertised in EHLO, recorded by the connection test; None or 0 means unlimited.
+    size_limit: int | None = None
+    # Extra trusted root certificates (PEM) for servers with self-signed or internal-CA certificates.
+    ca_cert_pem: str | None = None
+    # Skip certificate verification entirely; only for trusted internal networks.
+    accept_invalid_certs: bool = False
+    # Lowest TLS version to accept ("1.2" or "1.3"); None keeps the ssl module's default.
+    min_tls_version: str | None = None
 
 
 @dataclass(frozen=True)
@@ -34,6 +50,12 @@ class Template:
     body_html: str | None = None
 
 
+@dataclass(frozen=True)
+class InlineImage:
+    path: str
+    cid: str
+
+
 @dataclass(frozen=True)
 class SendOptions:
     min_delay_sec: int = 0
@@ -41,6 +63,13 @@ class SendOptions:
     randomize_order: bool = False
     retry_count: int = 1
     skip_sent: bool = True
+    greylist_delay_sec: int = DEFAULT_GREYLIST_DELAY_SEC
+    greylist_max_attempts: int = DEFAULT_GREYLIST_MAX_ATTEMPTS
+    message_id_domain: str | None = None
+    dsn: DsnOptions | None = None
+    read_receipt_to: str | None = None
+    # Fixed RNG seed so recipient order and delays can be replayed exactly.
+    seed: int | None = None
 
 
 @dataclass(frozen=True)
@@ -55,3 +84,9 @@ class JobConfig:
     log_file: Path
     sent_store_file: Path
     sent_store_text_file: Path | None = None
+    inline_images: list[InlineImage] = field(default_factory=list)
+    headers: dict[str, str] = field(default_factory=dict)
+    cc: list[str] = field(default_factory=list)
+    bcc: list[str] = field(default_factory=list)
+    operator: str | None = None
+    environment: str | None = None
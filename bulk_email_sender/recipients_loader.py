@@ -76,7 +76,13 @@ def _load_json_rows(path: Path) -> list[tuple[int, object, object]]:
 
 
 def _load_xlsx_rows(path: Path) -> list[tuple[int, object, object]]:
-    from openpyxl import load_workbook
+    # The desktop app reads XLSX natively in Rust; openpyxl is only needed for standalone use.
+    try:
+        from openpyxl import load_workbook
+    except ImportError as exc:
+        raise RecipientLoadError(
+            "Reading XLSX files requires openpyxl: install with `pip install bulk-email-sender[xlsx]`"
+        ) from exc
 
     workbook = load_workbook(filename=path, read_only=True, data_only=True)
     try:
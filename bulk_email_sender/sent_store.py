@@ -5,6 +5,9 @@ from datetime import datetime, timezone
 from io import TextIOWrapper
 from pathlib import Path
 from types import TracebackType
+from typing import Any
+
+ENVIRONMENT_NAMES = {"test": "测试", "production": "生产"}
 
 
 class SentStore:
@@ -15,8 +18,16 @@ class SentStore:
             store.append(...)
     """
 
-    def __init__(self, path: str | Path, text_path: str | Path | None = None):
+    def __init__(
+        self,
+        path: str | Path,
+        text_path: str | Path | None = None,
+        operator: str | None = None,
+        environment: str | None = None,
+    ):
         self.path = Path(path)
+        self.operator = operator.strip() if operator and operator.strip() else None
+        self.environment = environment
         self.path.parent.mkdir(parents=True, exist_ok=True)
         self.text_path = Path(text_path) if text_path else None
         if self.text_path is not None:
@@ -77,15 +88,30 @@ class SentStore:
     def is_sent(self, email: str) -> bool:
         return email.strip().lower() in self._emails
 
-    def append(self, email: str, teacher_name: str, job_id: str) -> None:
+    def append(
+        self,
+        email: str,
+        teacher_name: str,
+        job_id: str,
+        envelope: dict[str, Any] | None = None,
+        message_id: str | None = None,
+    ) -> None:
         normalized_email = email.strip().lower()
         sent_at = datetime.now(timezone.utc)
-        payload = {
+        payload: dict[str, Any] = {
             "email": normalized_email,
             "teacher_name": teacher_name,
             "job_id": job_id,
             "sent_at": sent_at.isoformat(),
         }
+        if envelope is not None:
+            payload["envelope"] = envelope
+        if self.operator:
+            payload["operator"] = self.operator
+        if self.environment:
+            payload["environment"] = self.environment
+        if message_id:
+            payload["message_id"] = message_id
         line = json.dumps(payload, ensure_ascii=False) + "\n"
 
         if self._handle is not None:
@@ -100,15 +126,33 @@ class SentStore:
             teacher_name=teacher_name,
             job_id=job_id,
             sent_at=sent_at,
+            envelope=envelope,
         )
         self._emails.add(normalized_email)
 
-    def _append_text_line(self, *, email: str, teacher_name: str, job_id: str, sent_at: datetime) -> None:
+    def _append_text_line(
+        self,
+        *,
+        email: str,
+        teacher_name: str,
+        job_id: str,
+        sent_at: datetime,
+        envelope: dict[str, Any] | None = None,
+    ) -> None:
         if self.text_path is None:
             return
 
         local_time = sent_at.astimezone().strftime("%Y-%m-%d %H:%M:%S")
-        line = f"[{local_time}] 发送成功 | 姓名: {teacher_name} | 邮箱: {email} | 任务: {job_id}\n"
+        line = f"[{local_time}] 发送成功 | 姓名: {teacher_name} | 邮箱: {email} | 任务: {job_id}"
+        if envelope and envelope.get("cc"):
+            line += f" | 抄送: {', '.join(envelope['cc'])}"
+        if envelope and envelope.get("bcc"):
+            line += f" | 密送: {', '.join(envelope['bcc'])}"
+        if self.operator:
+            line += f" | 操作人: {self.operator}"
+        if self.environment:
+            line += f" | 环境: {ENVIRONMENT_NAMES.get(self.environment, self.environment)}"
+        line += "\n"
 
         if self._text_handle is not None:
             if not self._text_header_written:
@@ -2,14 +2,24 @@ from __future__ import annotations
 
 import contextlib
 import smtplib
+import ssl
 import time
 from email.message import EmailMessage
 from types import TracebackType
 from typing import Callable
 
+from bulk_email_sender.dsn import DsnOptions
 from bulk_email_sender.models import SMTPConfig
 
 
+INSECURE_TLS_WARNING = "已关闭 TLS 证书校验：连接可能被中间人窃听或篡改，仅应在受信任的内部网络中使用"
+MIN_TLS_VERSIONS = {"1.2": ssl.TLSVersion.TLSv1_2, "1.3": ssl.TLSVersion.TLSv1_3}
+
+
+class SMTPAuthError(smtplib.SMTPException):
+    """The server accepted the connection but rejected the credentials (or the session after AUTH)."""
+
+
 class SMTPClient:
     """SMTP client with optional connection reuse.
 
@@ -28,8 +38,9 @@ class SMTPClient:
                 client.send(email, msg)
     """
 
-    def __init__(self, smtp_config: SMTPConfig):
+    def __init__(self, smtp_config: SMTPConfig, dsn: DsnOptions | None = None):
         self.smtp_config = smtp_config
+        self.dsn = dsn
         self._persistent_server: smtplib.SMTP | None = None
 
     # -- context manager for connection reuse ----------------------------------
@@ -73,12 +84,39 @@ class SMTPClient:
 
     # -- public API ------------------------------------------------------------
 
-    def test_connection(self) -> None:
-        self._with_server(lambda _: None)
+    def test_connection(self) -> dict[str, str] | None:
+        """Connect, authenticate and send a NOOP so a wrong password fails here rather than mid-job.
+
+        Returns the negotiated TLS protocol and cipher, or None on a plaintext connection.
+        Raises :class:`SMTPAuthError` when the login or the authenticated session is rejected;
+        connection and TLS failures propagate unchanged so callers can tell the two apart.
+        """
+        server = self._open()
+        try:
+            try:
+                self._login_if_needed(server)
+                code, reply = server.noop()
+                if code != 250:
+                    raise smtplib.SMTPResponseException(code, reply)
+            except smtplib.SMTPException as exc:
+                raise SMTPAuthError(str(exc)) from exc
+            return _negotiated_tls(server)
+        finally:
+            with contextlib.suppress(smtplib.SMTPException, OSError):
+                server.quit()
 
     def send(self, recipient_email: str, message: EmailMessage) -> None:
         def _send(server: smtplib.SMTP) -> None:
-            refused = server.send_message(message)
+            options: dict[str, object] = {}
+            if self.smtp_config.return_path:
+                options["from_addr"] = self.smtp_config.return_path
+            if self.dsn is not None:
+                # DSN parameters are only valid once the server has advertised the extension.
+                server.ehlo_or_helo_if_needed()
+                if server.has_extn("dsn"):
+                    options["mail_options"] = self.dsn.mail_options(message["Message-ID"])
+                    options["rcpt_options"] = self.dsn.rcpt_options()
+            refused = server.send_message(message, **options)
             if recipient_email in refused:
                 raise smtplib.SMTPRecipientsRefused(refused)
 
@@ -108,27 +146,60 @@ class SMTPClient:
     # -- internals -------------------------------------------------------------
 
     def _connect(self) -> smtplib.SMTP:
+        server = self._open()
+        self._login_if_needed(server)
+        return server
+
+    def _open(self) -> smtplib.SMTP:
+        """Connect (and upgrade to TLS when configured) without authenticating."""
         if self.smtp_config.use_ssl and self.smtp_config.use_starttls:
             raise ValueError("SMTP 配置冲突：use_ssl 与 use_starttls 不能同时开启")
 
+        extra: dict[str, str] = {}
+        if self.smtp_config.ehlo_hostname:
+            extra["local_hostname"] = self.smtp_config.ehlo_hostname
+
+        context = self._ssl_context()
+        tls_options = {"context": context} if context is not None else {}
         if self.smtp_config.use_ssl:
             server = smtplib.SMTP_SSL(
                 self.smtp_config.host,
                 self.smtp_config.port,
                 timeout=self.smtp_config.timeout_sec,
+                **extra,
+                **tls_options,
             )
         else:
             server = smtplib.SMTP(
                 self.smtp_config.host,
                 self.smtp_config.port,
                 timeout=self.smtp_config.timeout_sec,
+                **extra,
             )
             if self.smtp_config.use_starttls:
-                server.starttls()
-
-        self._login_if_needed(server)
+                server.starttls(**tls_options)
         return server
 
+    def _ssl_context(self) -> ssl.SSLContext | None:
+        """TLS context for a custom root CA, skipped verification or a minimum version; None keeps smtplib defaults."""
+        config = self.smtp_config
+        if not (config.accept_invalid_certs or config.ca_cert_pem or config.min_tls_version):
+            return None
+        context = ssl.create_default_context()
+        if config.min_tls_version:
+            if config.min_tls_version not in MIN_TLS_VERSIONS:
+                raise ValueError("最低 TLS 版本只能是 1.2 或 1.3")
+            context.minimum_version = MIN_TLS_VERSIONS[config.min_tls_version]
+        if config.accept_invalid_certs:
+            context.check_hostname = False
+            context.verify_mode = ssl.CERT_NONE
+        elif config.ca_cert_pem:
+            try:
+                context.load_verify_locations(cadata=config.ca_cert_pem)
+            except ssl.SSLError as exc:
+                raise ValueError(f"自定义根证书格式错误: {exc}") from exc
+        return context
+
     def _close_persistent(self) -> None:
         server = self._persistent_server
         self._persistent_server = None
@@ -147,4 +218,23 @@ class SMTPClient:
     def _login_if_needed(self, server: smtplib.SMTP) -> None:
         if not self.smtp_config.username or not self.smtp_config.password:
             return
-        server.login(self.smtp_config.username, self.smtp_config.password)
+        mechanism = self.smtp_config.auth_mechanism
+        if mechanism is None:
+            server.login(self.smtp_config.username, self.smtp_config.password)
+            return
+        # smtplib's auth_* callbacks read the credentials from the connection object.
+        server.ehlo_or_helo_if_needed()
+        server.user, server.password = self.smtp_config.username, self.smtp_config.password
+        server.auth(mechanism.upper().replace("_", "-"), getattr(server, f"auth_{mechanism}"))
+
+
+def _negotiated_tls(server: smtplib.SMTP) -> dict[str, str] | None:
+    """Protocol (e.g. "TLS 1.3") and cipher of the server socket, or None when it is not encrypted."""
+    sock = getattr(server, "sock", None)
+    if not isinstance(sock, ssl.SSLSocket):
+        return None
+    cipher = sock.cipher()
+    return {
+        "protocol": (sock.version() or "").replace("TLSv", "TLS "),
+        "cipher": cipher[0] if cipher else "",
+    }
@@ -1,60 +1,135 @@
 from __future__ import annotations
 
+import ipaddress
 import json
+import os
 import re
+import smtplib
+import socket
 import sys
 import threading
+import time
 import uuid
 from dataclasses import asdict
 from pathlib import Path
 from typing import TYPE_CHECKING, Any
 
+from bulk_email_sender.dsn import parse_dsn_options
+from bulk_email_sender.greylist import DEFAULT_GREYLIST_DELAY_SEC, DEFAULT_GREYLIST_MAX_ATTEMPTS
 from bulk_email_sender.recipients_loader import RecipientLoadError
 
 if TYPE_CHECKING:
+    from bulk_email_sender.engine import JobControl
     from bulk_email_sender.models import JobConfig, Recipient
 
+# Protocol 2: requests carry an ``id`` and every line written for a request echoes it as ``request_id``.
+# Protocol 3: the desktop app opens with a ``hello`` request and checks the reply before sending anything else.
+# Protocol 4: ``hello`` may negotiate length-prefixed frames for every message after the reply.
+PROTOCOL_VERSION = 4
+# Requests without an ``id`` (protocol 1) are still answered, and apps that don't negotiate framing keep JSON lines.
+MIN_PROTOCOL_VERSION = 1
+
+# A frame is an ``@<byte count>`` line followed by exactly that many bytes of UTF-8 JSON (and a newline for
+# readability), so large payloads don't depend on line buffering and stray output between frames can be skipped.
+FRAMING_LINES = "lines"
+FRAMING_LENGTH_PREFIXED = "length_prefixed"
+FRAME_HEADER_RE = re.compile(rb"^@(\d+)$")
+
+# Control messages for the running job. The desktop app keeps stdin open after ``start_send`` and writes
+# these to it, so pause/cancel are cooperative and the job can flush its sent records before exiting.
+CONTROL_TYPES = ("cancel", "pause", "resume", "throttle")
+
+# Set by the desktop app to switch from stdio to a local socket (a named pipe on Windows). The worker connects
+# twice: the first connection carries events, the second carries requests, and stdout is left for debug output.
+IPC_ADDRESS_ENV = "BULK_EMAIL_WORKER_IPC"
+WINDOWS_PIPE_PREFIX = "\\\\.\\pipe\\"
+PIPE_CONNECT_TIMEOUT_SEC = 10
+THROTTLE_FIELDS = ("per_minute", "per_hour", "per_day", "min_delay_sec", "max_delay_sec")
+# Reported in ``hello`` so the app can tell the user to update instead of sending requests this worker can't handle.
+FEATURES = ("hello", "load_recipients", "test_smtp", "start_send", *CONTROL_TYPES, "ping")
+
 EMAIL_RE = re.compile(r"^[^@\s]+@[^@\s]+\.[^@\s]+$")
+HOSTNAME_LABEL_RE = re.compile(r"^[A-Za-z0-9](?:[A-Za-z0-9-]{0,61}[A-Za-z0-9])?$")
 
 
 class JsonLineWriter:
+    """Writes messages to a binary stream as JSON lines, or as frames once ``hello`` has negotiated them."""
+
     def __init__(self, stream=None):
-        self.stream = stream or sys.stdout
+        self.stream = stream or sys.stdout.buffer
+        self.framing = FRAMING_LINES
         self._lock = threading.Lock()
 
     def write_line(self, payload: dict[str, Any]) -> None:
-        text = json.dumps(payload, ensure_ascii=False)
+        body = json.dumps(payload, ensure_ascii=False).encode("utf-8")
         with self._lock:
-            self.stream.write(text + "\n")
+            if self.framing == FRAMING_LENGTH_PREFIXED:
+                self.stream.write(b"@%d\n" % len(body))
+            self.stream.write(body + b"\n")
             self.stream.flush()
 
 
+class RequestWriter:
+    """Tags every line written on behalf of one request with its id."""
+
+    def __init__(self, writer, request_id: int | None):
+        self.writer = writer
+        self.request_id = request_id
+
+    def write_line(self, payload: dict[str, Any]) -> None:
+        if self.request_id is not None:
+            payload = {**payload, "request_id": self.request_id}
+        self.writer.write_line(payload)
+
+
 class Worker:
     def __init__(self, writer=None):
         self.writer = writer or JsonLineWriter()
         self._job_thread: threading.Thread | None = None
-        self._cancel_event: threading.Event | None = None
+        self._control: JobControl | None = None
 
     def handle_message(self, message: dict[str, Any]) -> None:
         message_type = str(message.get("type", "")).strip()
         payload = message.get("payload", {}) or {}
+        writer = RequestWriter(self.writer, message.get("id"))
         try:
-            if message_type == "load_recipients":
-                self._handle_load_recipients(payload)
+            protocol = int(message.get("protocol", 1))
+            if protocol > PROTOCOL_VERSION:
+                error = f"Unsupported protocol version {protocol} (worker speaks {PROTOCOL_VERSION})"
+                writer.write_line({"type": "error", "error": error})
+            elif message_type == "load_recipients":
+                self._handle_load_recipients(payload, writer)
             elif message_type == "test_smtp":
-                self._handle_test_smtp(payload)
+                self._handle_test_smtp(payload, writer)
             elif message_type == "start_send":
-                self._handle_start_send(payload)
-            elif message_type == "cancel":
-                self._handle_cancel()
+                self._handle_start_send(payload, writer)
+            elif message_type in CONTROL_TYPES:
+                self._handle_control(message_type, payload, writer)
+            elif message_type == "hello":
+                self._handle_hello(payload, writer)
+            elif message_type == "ping":
+                writer.write_line({"type": "pong", "protocol": PROTOCOL_VERSION})
             else:
-                self.writer.write_line({"type": "error", "error": f"Unknown message type: {message_type}"})
+                writer.write_line({"type": "error", "error": f"Unknown message type: {message_type}"})
         except RecipientLoadError as exc:
-            self.writer.write_line({"type": "error", "error": str(exc)})
+            writer.write_line({"type": "error", "error": str(exc)})
         except Exception as exc:
-            self.writer.write_line({"type": "error", "error": str(exc)})
-
-    def _handle_load_recipients(self, payload: dict[str, Any]) -> None:
+            writer.write_line({"type": "error", "error": str(exc)})
+
+    def _handle_hello(self, payload: dict[str, Any], writer) -> None:
+        hello = {
+            "type": "hello",
+            "protocol": PROTOCOL_VERSION,
+            "min_protocol": MIN_PROTOCOL_VERSION,
+            "features": list(FEATURES),
+        }
+        if FRAMING_LENGTH_PREFIXED in (payload.get("framing") or []):
+            hello["framing"] = FRAMING_LENGTH_PREFIXED
+        writer.write_line(hello)
+        # The reply is still a line; everything after it, in both directions, uses the chosen framing.
+        self.writer.framing = hello.get("framing", FRAMING_LINES)
+
+    def _handle_load_recipients(self, payload: dict[str, Any], writer) -> None:
         from bulk_email_sender.recipients_loader import load_recipients
 
         path = payload.get("path")
@@ -62,7 +137,7 @@ class Worker:
             raise RecipientLoadError("Missing recipient file path")
 
         result = load_recipients(path, raise_on_invalid=False)
-        self.writer.write_line(
+        writer.write_line(
             {
                 "type": "recipients_loaded",
                 "stats": asdict(result.stats),
@@ -70,9 +145,9 @@ class Worker:
             }
         )
 
-    def _handle_test_smtp(self, payload: dict[str, Any]) -> None:
+    def _handle_test_smtp(self, payload: dict[str, Any], writer) -> None:
         from bulk_email_sender.models import SMTPConfig
-        from bulk_email_sender.smtp_client import SMTPClient
+        from bulk_email_sender.smtp_client import INSECURE_TLS_WARNING, SMTPAuthError, SMTPClient
 
         smtp = SMTPConfig(
             host=str(payload.get("host", "")),
@@ -82,57 +157,103 @@ class Worker:
             use_ssl=bool(payload.get("use_ssl", True)),
             use_starttls=bool(payload.get("use_starttls", False)),
             timeout_sec=int(payload.get("timeout_sec", 30)),
+            ehlo_hostname=_parse_ehlo_hostname(payload.get("ehlo_hostname")),
+            return_path=_parse_return_path(payload.get("return_path")),
+            auth_mechanism=_parse_auth_mechanism(payload.get("auth_mechanism")),
+            ca_cert_pem=_parse_ca_cert_pem(payload.get("ca_cert_pem")),
+            accept_invalid_certs=bool(payload.get("accept_invalid_certs", False)),
+            min_tls_version=_parse_min_tls_version(payload.get("min_tls_version")),
         )
-        SMTPClient(smtp).test_connection()
-        self.writer.write_line({"type": "smtp_test_succeeded"})
+        # "stage" lets the UI tell a wrong password apart from a network or TLS problem.
+        try:
+            tls = SMTPClient(smtp).test_connection()
+        except SMTPAuthError as exc:
+            writer.write_line({"type": "error", "stage": "auth", "error": f"SMTP 认证失败: {exc}"})
+            return
+        except (OSError, smtplib.SMTPException) as exc:
+            writer.write_line({"type": "error", "stage": "connect", "error": f"SMTP 连接失败: {exc}"})
+            return
+        event: dict[str, Any] = {"type": "smtp_test_succeeded", "tls": tls}
+        if smtp.accept_invalid_certs and (smtp.use_ssl or smtp.use_starttls):
+            event["warnings"] = [INSECURE_TLS_WARNING]
+        writer.write_line(event)
 
-    def _handle_start_send(self, payload: dict[str, Any]) -> None:
+    def _handle_start_send(self, payload: dict[str, Any], writer) -> None:
         if self._job_thread and self._job_thread.is_alive():
-            self.writer.write_line({"type": "error", "error": "Another job is running"})
+            writer.write_line({"type": "error", "error": "Another job is running"})
             return
 
+        from bulk_email_sender.engine import JobControl
+
         job = _build_job_config(payload)
-        cancel_event = threading.Event()
+        control = JobControl()
         thread = threading.Thread(
             target=self._run_job,
-            kwargs={"job": job, "cancel_event": cancel_event},
+            kwargs={"job": job, "control": control, "writer": writer},
             daemon=True,
         )
-        self._cancel_event = cancel_event
+        self._control = control
         self._job_thread = thread
         thread.start()
-        self.writer.write_line({"type": "job_accepted", "job_id": job.job_id})
+        writer.write_line({"type": "job_accepted", "job_id": job.job_id})
 
-    def _handle_cancel(self) -> None:
-        if not self._job_thread or not self._job_thread.is_alive() or self._cancel_event is None:
-            self.writer.write_line({"type": "error", "error": "No active job"})
+    def _handle_control(self, message_type: str, payload: dict[str, Any], writer) -> None:
+        control = self._control
+        if not self._job_thread or not self._job_thread.is_alive() or control is None:
+            writer.write_line({"type": "error", "error": "No active job"})
             return
-        self._cancel_event.set()
-        self.writer.write_line({"type": "cancel_requested"})
-
-    def _run_job(self, job: JobConfig, cancel_event: threading.Event) -> None:
+        if message_type == "cancel":
+            control.cancel()
+            writer.write_line({"type": "cancel_requested"})
+        elif message_type == "pause":
+            control.pause()
+            writer.write_line({"type": "pause_requested"})
+        elif message_type == "resume":
+            control.resume()
+            writer.write_line({"type": "resume_requested"})
+        else:
+            limits = _parse_throttle(payload)
+            control.set_throttle(**limits)
+            writer.write_line({"type": "throttle_updated", "limits": limits})
+
+    def _run_job(
+        self,
+        job: JobConfig,
+        cancel_event: threading.Event | None = None,
+        writer=None,
+        control: JobControl | None = None,
+    ) -> None:
         from bulk_email_sender.engine import SendEngine
         from bulk_email_sender.sent_store import SentStore
         from bulk_email_sender.smtp_client import SMTPClient
 
-        smtp_client = SMTPClient(job.smtp)
-        with SentStore(job.sent_store_file, text_path=job.sent_store_text_file) as sent_store:
+        writer = writer or self.writer
+
+        smtp_client = SMTPClient(job.smtp, dsn=job.options.dsn)
+        with SentStore(
+            job.sent_store_file,
+            text_path=job.sent_store_text_file,
+            operator=job.operator,
+            environment=job.environment,
+        ) as sent_store:
             engine = SendEngine(smtp_client=smtp_client, sent_store=sent_store)
             try:
-                for event in engine.send(job, cancel_event=cancel_event):
-                    self.writer.write_line(event)
+                for event in engine.send(job, cancel_event=cancel_event, control=control):
+                    writer.write_line(event)
             except Exception as exc:
-                self.writer.write_line({"type": "error", "job_id": job.job_id, "error": str(exc)})
+                writer.write_line({"type": "error", "job_id": job.job_id, "error": str(exc)})
 
 
 def _build_job_config(payload: dict[str, Any]) -> JobConfig:
-    from bulk_email_sender.models import JobConfig, Sender, SendOptions, SMTPConfig, Template
+    from bulk_email_sender.models import InlineImage, JobConfig, Sender, SendOptions, SMTPConfig, Template
 
-    job_id = str(payload.get("job_id") or uuid.uuid4().hex)
     sender_payload = payload.get("sender", {})
     smtp_payload = payload.get("smtp", {})
     template_payload = payload.get("template", {})
     options_payload = payload.get("options", {})
+    seed_raw = options_payload.get("seed")
+    seed = None if seed_raw is None else _parse_int(seed_raw, field_name="随机种子", minimum=0)
+    job_id = str(payload.get("job_id") or (f"job-{seed:016x}" if seed is not None else uuid.uuid4().hex))
     paths_payload = payload.get("paths", {})
 
     sender_email = _validate_email(
@@ -173,6 +294,13 @@ def _build_job_config(payload: dict[str, Any]) -> JobConfig:
         use_ssl=use_ssl,
         use_starttls=use_starttls,
         timeout_sec=timeout_sec,
+        ehlo_hostname=_parse_ehlo_hostname(smtp_payload.get("ehlo_hostname")),
+        return_path=_parse_return_path(smtp_payload.get("return_path")),
+        auth_mechanism=_parse_auth_mechanism(smtp_payload.get("auth_mechanism")),
+        size_limit=_parse_size_limit(smtp_payload.get("size_limit")),
+        ca_cert_pem=_parse_ca_cert_pem(smtp_payload.get("ca_cert_pem")),
+        accept_invalid_certs=bool(smtp_payload.get("accept_invalid_certs", False)),
+        min_tls_version=_parse_min_tls_version(smtp_payload.get("min_tls_version")),
     )
     template = Template(
         subject=str(template_payload.get("subject", "")),
@@ -203,8 +331,35 @@ def _build_job_config(payload: dict[str, Any]) -> JobConfig:
             options_payload.get("skip_sent", True),
             field_name="skip_sent",
         ),
+        greylist_delay_sec=_parse_int(
+            options_payload.get("greylist_delay_sec", DEFAULT_GREYLIST_DELAY_SEC),
+            field_name="灰名单重试间隔",
+            minimum=0,
+        ),
+        greylist_max_attempts=_parse_int(
+            options_payload.get("greylist_max_attempts", DEFAULT_GREYLIST_MAX_ATTEMPTS),
+            field_name="灰名单重试次数",
+            minimum=0,
+        ),
+        message_id_domain=_parse_message_id_domain(options_payload.get("message_id_domain")),
+        dsn=parse_dsn_options(options_payload.get("dsn")),
+        read_receipt_to=_parse_read_receipt_to(options_payload.get("read_receipt_to")),
+        seed=seed,
     )
     attachments = [str(path) for path in payload.get("attachments", [])]
+    inline_images = [
+        InlineImage(path=str(item.get("path", "")), cid=str(item.get("cid", "")).strip())
+        for item in payload.get("inline_images") or []
+    ]
+    headers = {str(name).strip(): str(value).strip() for name, value in (payload.get("headers") or {}).items()}
+    cc = [
+        _validate_email(str(address), field_name=f"cc[{index}]")
+        for index, address in enumerate(payload.get("cc") or [], start=1)
+    ]
+    bcc = [
+        _validate_email(str(address), field_name=f"bcc[{index}]")
+        for index, address in enumerate(payload.get("bcc") or [], start=1)
+    ]
 
     recipients = _resolve_recipients(payload)
     if not recipients:
@@ -228,6 +383,12 @@ def _build_job_config(payload: dict[str, Any]) -> JobConfig:
         log_file=log_file,
         sent_store_file=sent_store_file,
         sent_store_text_file=sent_store_text_file,
+        inline_images=inline_images,
+        headers=headers,
+        cc=cc,
+        bcc=bcc,
+        operator=str(payload.get("operator") or "").strip() or None,
+        environment=_parse_environment(payload.get("environment")),
     )
 
 
@@ -244,7 +405,11 @@ def _resolve_recipients(payload: dict[str, Any]) -> list[Recipient]:
             name = str(item.get("name", "")).strip()
             if not name:
                 raise RecipientLoadError(f"Invalid recipients[{index}] data")
-            recipients.append(Recipient(email=email, name=name))
+            raw_fields = item.get("fields") or {}
+            if not isinstance(raw_fields, dict):
+                raise RecipientLoadError(f"Invalid recipients[{index}].fields payload")
+            fields = {str(key): str(value) for key, value in raw_fields.items()}
+            recipients.append(Recipient(email=email, name=name, fields=fields))
         return recipients
 
     recipients_file = payload.get("recipients_file")
@@ -254,6 +419,18 @@ def _resolve_recipients(payload: dict[str, Any]) -> list[Recipient]:
     raise RecipientLoadError("Missing recipients or recipients_file")
 
 
+def _parse_throttle(payload: dict[str, Any]) -> dict[str, int | None]:
+    limits: dict[str, int | None] = {}
+    for field in THROTTLE_FIELDS:
+        value = payload.get(field)
+        minimum = 0 if field.endswith("_delay_sec") else 1
+        limits[field] = None if value is None else _parse_int(value, field_name=field, minimum=minimum)
+    min_delay, max_delay = limits["min_delay_sec"], limits["max_delay_sec"]
+    if min_delay is not None and max_delay is not None and min_delay > max_delay:
+        raise ValueError("min_delay_sec 不能大于 max_delay_sec")
+    return limits
+
+
 def _validate_email(email: str, *, field_name: str) -> str:
     normalized = email.strip()
     if not normalized:
@@ -263,6 +440,97 @@ def _validate_email(email: str, *, field_name: str) -> str:
     return normalized
 
 
+def _parse_ehlo_hostname(value: Any) -> str | None:
+    normalized = str(value or "").strip()
+    if not normalized:
+        return None
+    literal = normalized[1:-1] if normalized.startswith("[") and normalized.endswith("]") else None
+    if literal is not None:
+        try:
+            ipaddress.ip_address(literal.removeprefix("IPv6:"))
+        except ValueError as exc:
+            raise ValueError(f"EHLO 主机名格式不正确: {normalized}") from exc
+        return normalized
+    domain = normalized.rstrip(".")
+    labels = domain.split(".")
+    if len(domain) > 253 or len(labels) < 2 or not all(HOSTNAME_LABEL_RE.match(label) for label in labels):
+        raise ValueError(f"EHLO 主机名格式不正确: {normalized}")
+    return domain.lower()
+
+
+def _parse_return_path(value: Any) -> str | None:
+    normalized = str(value or "").strip()
+    if not normalized:
+        return None
+    return _validate_email(normalized, field_name="退信地址（Return-Path）")
+
+
+def _parse_ca_cert_pem(value: Any) -> str | None:
+    normalized = str(value or "").strip()
+    if not normalized:
+        return None
+    if "-----BEGIN CERTIFICATE-----" not in normalized:
+        raise ValueError("自定义根证书必须是 PEM 格式（以 -----BEGIN CERTIFICATE----- 开头）")
+    return normalized
+
+
+def _parse_min_tls_version(value: Any) -> str | None:
+    normalized = str(value or "").strip()
+    if not normalized:
+        return None
+    if normalized not in ("1.2", "1.3"):
+        raise ValueError("最低 TLS 版本只能是 1.2 或 1.3")
+    return normalized
+
+
+def _parse_size_limit(value: Any) -> int | None:
+    if value is None or value == "":
+        return None
+    try:
+        size_limit = int(value)
+    except (TypeError, ValueError) as exc:
+        raise ValueError(f"SMTP SIZE 上限无效: {value}") from exc
+    if size_limit < 0:
+        raise ValueError(f"SMTP SIZE 上限无效: {value}")
+    return size_limit or None
+
+
+def _parse_auth_mechanism(value: Any) -> str | None:
+    normalized = str(value or "").strip().lower()
+    if not normalized:
+        return None
+    if normalized == "ntlm":
+        raise ValueError("NTLM 认证仅支持原生发送引擎（engine: native）")
+    if normalized not in ("plain", "login", "cram_md5"):
+        raise ValueError(f"SMTP 认证方式无效: {normalized}")
+    return normalized
+
+
+def _parse_environment(value: Any) -> str | None:
+    normalized = str(value or "").strip().lower()
+    if not normalized:
+        return None
+    if normalized not in ("test", "production"):
+        raise ValueError(f"任务环境标签无效: {normalized}")
+    return normalized
+
+
+def _parse_read_receipt_to(value: Any) -> str | None:
+    normalized = str(value or "").strip()
+    if not normalized:
+        return None
+    return _validate_email(normalized, field_name="阅读回执地址")
+
+
+def _parse_message_id_domain(value: Any) -> str | None:
+    from bulk_email_sender.message_builder import validate_message_id_domain
+
+    normalized = str(value or "").strip()
+    if not normalized:
+        return None
+    return validate_message_id_domain(normalized)
+
+
 def _parse_int(value: Any, *, field_name: str, minimum: int | None = None, maximum: int | None = None) -> int:
     try:
         parsed = int(value)
@@ -290,17 +558,65 @@ def _parse_bool(value: Any, *, field_name: str) -> bool:
     raise ValueError(f"{field_name} 必须是布尔值")
 
 
-def main() -> None:
-    worker = Worker()
-    for line in sys.stdin:
+def _connect_ipc(address: str):
+    """Return ``(requests, events)`` binary streams over the desktop app's local socket."""
+    if address.startswith(WINDOWS_PIPE_PREFIX):
+        events = _open_pipe(address, "wb")
+        return _open_pipe(address, "rb"), events
+    channels = []
+    for mode in ("wb", "rb"):
+        connection = socket.socket(socket.AF_UNIX, socket.SOCK_STREAM)
+        connection.connect(address)
+        channels.append(connection.makefile(mode))
+    events, requests = channels
+    return requests, events
+
+
+def _open_pipe(address: str, mode: str):
+    # Each connection needs a free pipe instance; the app creates the next one only after accepting.
+    deadline = time.monotonic() + PIPE_CONNECT_TIMEOUT_SEC
+    while True:
+        try:
+            return open(address, mode)
+        except OSError:
+            if time.monotonic() >= deadline:
+                raise
+            time.sleep(0.05)
+
+
+def _read_messages(stream, writer: JsonLineWriter):
+    """Yield requests read as JSON lines, or as frames once ``writer`` has switched framing.
+
+    Invalid JSON and unrecognised frame headers are reported as ``error`` events and skipped.
+    """
+    while True:
+        line = stream.readline()
+        if not line:
+            return
         raw = line.strip()
         if not raw:
             continue
+        if writer.framing == FRAMING_LENGTH_PREFIXED:
+            header = FRAME_HEADER_RE.match(raw)
+            if header is None:
+                preview = raw[:80].decode("utf-8", "replace")
+                writer.write_line({"type": "error", "error": f"Invalid frame header: {preview}"})
+                continue
+            raw = stream.read(int(header.group(1)))
         try:
             message = json.loads(raw)
-        except json.JSONDecodeError as exc:
-            worker.writer.write_line({"type": "error", "error": f"Invalid JSON: {exc}"})
+        except ValueError as exc:
+            writer.write_line({"type": "error", "error": f"Invalid JSON: {exc}"})
             continue
+        yield message
+
+
+def main() -> None:
+    address = os.environ.get(IPC_ADDRESS_ENV)
+    requests, events = _connect_ipc(address) if address else (sys.stdin.buffer, sys.stdout.buffer)
+    worker = Worker(writer=JsonLineWriter(events))
+    # Read lazily: ``hello`` switches the framing before the next request is read.
+    for message in _read_messages(requests, worker.writer):
         worker.handle_message(message)
     # Wait for any in-flight job thread so all events are flushed before exit.
     if worker._job_thread is not None and worker._job_thread.is_alive():
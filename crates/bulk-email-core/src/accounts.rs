@@ -0,0 +1,258 @@
+use crate::engine::{validate_email, SenderConfig};
+use crate::environment::Environment;
+use crate::smtp_client::SmtpPayload;
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// 发件账号注册表中的一项：发件身份、SMTP 配置与可选的每日发送上限。
+#[derive(Deserialize, Serialize, Clone)]
+pub struct SmtpAccount {
+    #[serde(default)]
+    pub id: String,
+    #[serde(default)]
+    pub label: String,
+    pub sender: SenderConfig,
+    pub smtp: SmtpPayload,
+    #[serde(default)]
+    pub daily_cap: Option<u32>,
+}
+
+impl SmtpAccount {
+    pub fn validate(&self) -> Result<(), String> {
+        validate_email(&self.sender.email, "发件邮箱")?;
+        if self.sender.name.trim().is_empty() {
+            return Err("发件人姓名不能为空".to_string());
+        }
+        if self.smtp.host.trim().is_empty() {
+            return Err("SMTP 主机不能为空".to_string());
+        }
+        if self.smtp.use_ssl && self.smtp.use_starttls {
+            return Err("SMTP 配置冲突：use_ssl 与 use_starttls 不能同时开启".to_string());
+        }
+        self.smtp.validate_envelope()?;
+        if self.daily_cap == Some(0) {
+            return Err("每日上限必须大于 0，不限制请留空".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// 返回给前端的账号摘要，不包含密码。
+#[derive(Serialize)]
+pub struct SmtpAccountSummary {
+    pub id: String,
+    pub label: String,
+    pub sender_email: String,
+    pub sender_name: String,
+    pub host: String,
+    pub port: u16,
+    pub has_password: bool,
+    pub environment: Option<Environment>,
+    pub daily_cap: Option<u32>,
+    pub sent_today: u32,
+}
+
+#[derive(Deserialize, Serialize, Default)]
+pub struct AccountRegistry {
+    pub accounts: Vec<SmtpAccount>,
+}
+
+impl AccountRegistry {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        if !path.exists() {
+            return Ok(AccountRegistry::default());
+        }
+        let text = fs::read_to_string(path).map_err(|err| format!("读取发件账号失败: {err}"))?;
+        serde_json::from_str(&text).map_err(|err| format!("发件账号文件格式错误: {err}"))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let text = serde_json::to_string_pretty(self).map_err(|err| err.to_string())?;
+        fs::write(path, text).map_err(|err| format!("写入发件账号失败: {err}"))
+    }
+
+    /// 新增或按 id 覆盖账号；未提供 id 时自动生成。
+    pub fn upsert(&mut self, mut account: SmtpAccount) -> Result<SmtpAccount, String> {
+        account.validate()?;
+        account.id = account.id.trim().to_string();
+        if account.id.is_empty() {
+            account.id = format!("acct-{:08x}", rand::random::<u32>());
+        }
+        if account.label.trim().is_empty() {
+            account.label = account.sender.email.trim().to_string();
+        }
+        match self.accounts.iter_mut().find(|item| item.id == account.id) {
+            Some(existing) => *existing = account.clone(),
+            None => self.accounts.push(account.clone()),
+        }
+        Ok(account)
+    }
+
+    pub fn remove(&mut self, id: &str) -> bool {
+        let before = self.accounts.len();
+        self.accounts.retain(|item| item.id != id);
+        before != self.accounts.len()
+    }
+
+    pub fn find(&self, id: &str) -> Option<&SmtpAccount> {
+        self.accounts.iter().find(|item| item.id == id)
+    }
+}
+
+/// 按本地日期记录各账号当日已发送数量，跨天自动清零。
+#[derive(Deserialize, Serialize, Default)]
+pub struct UsageLedger {
+    #[serde(default)]
+    pub date: String,
+    #[serde(default)]
+    pub sent: HashMap<String, u32>,
+}
+
+impl UsageLedger {
+    pub fn load(path: &Path) -> Self {
+        let ledger: UsageLedger = fs::read_to_string(path)
+            .ok()
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default();
+        ledger.for_today()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let text = serde_json::to_string_pretty(self).map_err(|err| err.to_string())?;
+        fs::write(path, text).map_err(|err| format!("写入账号用量失败: {err}"))
+    }
+
+    pub fn sent_today(&self, account_id: &str) -> u32 {
+        self.sent.get(account_id).copied().unwrap_or_default()
+    }
+
+    pub fn record(&mut self, account_id: &str) {
+        *self = std::mem::take(self).for_today();
+        *self.sent.entry(account_id.to_string()).or_default() += 1;
+    }
+
+    fn for_today(self) -> Self {
+        let today = Local::now().format("%Y-%m-%d").to_string();
+        if self.date == today {
+            return self;
+        }
+        UsageLedger {
+            date: today,
+            sent: HashMap::new(),
+        }
+    }
+}
+
+pub fn summarize(account: &SmtpAccount, ledger: &UsageLedger) -> SmtpAccountSummary {
+    SmtpAccountSummary {
+        id: account.id.clone(),
+        label: account.label.clone(),
+        sender_email: account.sender.email.clone(),
+        sender_name: account.sender.name.clone(),
+        host: account.smtp.host.clone(),
+        port: account.smtp.port,
+        has_password: !account.smtp.password.is_empty(),
+        environment: account.smtp.environment,
+        daily_cap: account.daily_cap,
+        sent_today: ledger.sent_today(&account.id),
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum RotationStrategy {
+    /// 每封邮件轮流使用下一个账号。
+    #[default]
+    RoundRobin,
+    /// 一直使用当前账号，直到达到每日上限再切换。
+    FillFirst,
+}
+
+#[derive(Deserialize, Clone, Default)]
+pub struct RotationConfig {
+    #[serde(default)]
+    pub account_ids: Vec<String>,
+    #[serde(default)]
+    pub strategy: RotationStrategy,
+}
+
+/// 任务内的账号选择器：按策略挑选下一个未达上限的账号。
+pub struct AccountRotation {
+    strategy: RotationStrategy,
+    caps: Vec<Option<u32>>,
+    used: Vec<u32>,
+    cursor: usize,
+}
+
+impl AccountRotation {
+    pub fn new(strategy: RotationStrategy, caps: Vec<Option<u32>>, used: Vec<u32>) -> Self {
+        AccountRotation {
+            strategy,
+            caps,
+            used,
+            cursor: 0,
+        }
+    }
+
+    /// 返回下一封邮件应使用的账号下标；所有账号都达到上限时返回 `None`。
+    pub fn next_slot(&mut self) -> Option<usize> {
+        let count = self.caps.len();
+        for offset in 0..count {
+            let candidate = (self.cursor + offset) % count;
+            if self.has_capacity(candidate) {
+                self.cursor = match self.strategy {
+                    RotationStrategy::RoundRobin => (candidate + 1) % count,
+                    RotationStrategy::FillFirst => candidate,
+                };
+                return Some(candidate);
+            }
+        }
+        None
+    }
+
+    pub fn record_sent(&mut self, slot: usize) {
+        if let Some(used) = self.used.get_mut(slot) {
+            *used += 1;
+        }
+    }
+
+    fn has_capacity(&self, slot: usize) -> bool {
+        match self.caps[slot] {
+            Some(cap) => self.used[slot] < cap,
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AccountRotation, RotationStrategy};
+
+    #[test]
+    fn round_robin_skips_capped_accounts() {
+        let mut rotation = AccountRotation::new(RotationStrategy::RoundRobin, vec![Some(1), None, Some(5)], vec![1, 0, 5]);
+        assert_eq!(rotation.next_slot(), Some(1));
+        assert_eq!(rotation.next_slot(), Some(1));
+    }
+
+    #[test]
+    fn round_robin_cycles_through_accounts() {
+        let mut rotation = AccountRotation::new(RotationStrategy::RoundRobin, vec![None, None, None], vec![0, 0, 0]);
+        let picks: Vec<Option<usize>> = (0..4).map(|_| rotation.next_slot()).collect();
+        assert_eq!(picks, vec![Some(0), Some(1), Some(2), Some(0)]);
+    }
+
+    #[test]
+    fn fill_first_moves_on_after_cap() {
+        let mut rotation = AccountRotation::new(RotationStrategy::FillFirst, vec![Some(2), Some(1)], vec![0, 0]);
+        let mut picks = Vec::new();
+        while let Some(slot) = rotation.next_slot() {
+            rotation.record_sent(slot);
+            picks.push(slot);
+        }
+        assert_eq!(picks, vec![0, 0, 1]);
+    }
+}
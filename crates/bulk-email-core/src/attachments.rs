@@ -0,0 +1,225 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// 未指定时的附件合计上限（常见邮箱服务商约 25 MB）。
+pub const DEFAULT_MAX_TOTAL_BYTES: u64 = 25 * 1024 * 1024;
+
+#[derive(Deserialize, Default)]
+pub struct AttachmentCheckRequest {
+    #[serde(default)]
+    pub paths: Vec<String>,
+    /// 单个附件上限；为空时不单独限制。
+    #[serde(default)]
+    pub max_file_bytes: Option<u64>,
+    /// 附件合计上限；为空时使用 `DEFAULT_MAX_TOTAL_BYTES`。
+    #[serde(default)]
+    pub max_total_bytes: Option<u64>,
+    /// 服务器 EHLO 声明的 SIZE 上限，按 base64 编码后的大小比较。
+    #[serde(default)]
+    pub server_max_bytes: Option<u64>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct AttachmentInfo {
+    pub path: String,
+    pub exists: bool,
+    pub size_bytes: u64,
+    pub mime_type: String,
+    pub problems: Vec<String>,
+    /// 文件名含非 ASCII 字符时，启用 ASCII 文件名后实际使用的名称。
+    pub ascii_name: Option<String>,
+    /// 不影响发送的提示，如部分客户端可能把文件名显示为乱码。
+    pub warnings: Vec<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct AttachmentReport {
+    pub attachments: Vec<AttachmentInfo>,
+    pub total_bytes: u64,
+    /// base64 编码后的估算大小，即实际占用邮件体积的部分。
+    pub encoded_bytes: u64,
+    pub problems: Vec<String>,
+    pub ok: bool,
+}
+
+pub fn validate_attachments(request: &AttachmentCheckRequest) -> AttachmentReport {
+    let mut attachments = Vec::new();
+    let mut total_bytes: u64 = 0;
+    for raw in &request.paths {
+        let path = Path::new(raw.trim());
+        let mime_type = mime_guess::from_path(path).first_or_octet_stream().essence_str().to_string();
+        let mut problems = Vec::new();
+        let metadata = fs::metadata(path).ok().filter(|metadata| metadata.is_file());
+        let size_bytes = metadata.as_ref().map(|metadata| metadata.len()).unwrap_or(0);
+        if metadata.is_none() {
+            problems.push("文件不存在或不是普通文件".to_string());
+        } else if size_bytes == 0 {
+            problems.push("文件为空".to_string());
+        }
+        if let Some(limit) = request.max_file_bytes {
+            if size_bytes > limit {
+                problems.push(format!("超过单个附件上限 {}", format_bytes(limit)));
+            }
+        }
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let mut warnings = Vec::new();
+        let ascii_name = (!file_name.is_ascii()).then(|| ascii_file_name(&file_name));
+        if ascii_name.is_some() {
+            warnings.push(
+                "文件名包含中文等非 ASCII 字符，旧版 Outlook 与部分网页邮箱可能显示为乱码，可启用 ASCII 文件名".to_string(),
+            );
+        }
+        total_bytes = total_bytes.saturating_add(size_bytes);
+        attachments.push(AttachmentInfo {
+            path: raw.trim().to_string(),
+            exists: metadata.is_some(),
+            size_bytes,
+            mime_type,
+            problems,
+            ascii_name,
+            warnings,
+        });
+    }
+
+    let encoded_bytes = base64_encoded_len(total_bytes);
+    let mut problems = Vec::new();
+    let max_total = request.max_total_bytes.unwrap_or(DEFAULT_MAX_TOTAL_BYTES);
+    if total_bytes > max_total {
+        problems.push(format!(
+            "附件合计 {} 超过上限 {}",
+            format_bytes(total_bytes),
+            format_bytes(max_total)
+        ));
+    }
+    if let Some(server_max) = request.server_max_bytes.filter(|limit| *limit > 0) {
+        if encoded_bytes > server_max {
+            problems.push(format!(
+                "编码后约 {}，超过服务器允许的邮件大小 {}",
+                format_bytes(encoded_bytes),
+                format_bytes(server_max)
+            ));
+        }
+    }
+    let ok = problems.is_empty() && attachments.iter().all(|item| item.problems.is_empty());
+    AttachmentReport {
+        attachments,
+        total_bytes,
+        encoded_bytes,
+        problems,
+        ok,
+    }
+}
+
+/// 把文件名转写为 ASCII：全角字符与中文标点换成对应的半角字符，带变音符号的拉丁字母去掉变音，
+/// 其余无法转写的字符（如汉字）替换为 `_`；保留扩展名，转写后为空时使用 `attachment`。
+pub fn ascii_file_name(name: &str) -> String {
+    let (stem, extension) = match name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() && extension.is_ascii() => (stem, Some(extension)),
+        _ => (name, None),
+    };
+    let mut ascii = String::new();
+    for ch in stem.chars() {
+        let mapped = transliterate_char(ch);
+        match mapped {
+            Some(text) => ascii.push_str(text.as_str()),
+            None if !ascii.ends_with('_') => ascii.push('_'),
+            None => {}
+        }
+    }
+    let ascii = ascii.trim_matches(|ch: char| ch == '_' || ch == ' ' || ch == '.');
+    let stem = if ascii.chars().any(|ch| ch.is_ascii_alphanumeric()) {
+        ascii
+    } else {
+        "attachment"
+    };
+    match extension {
+        Some(extension) => format!("{stem}.{extension}"),
+        None => stem.to_string(),
+    }
+}
+
+fn transliterate_char(ch: char) -> Option<String> {
+    const LATIN: &str = "ÀÁÂÃÄÅàáâãäåÇçÈÉÊËèéêëÌÍÎÏìíîïÑñÒÓÔÕÖØòóôõöøÙÚÛÜùúûüÝýÿ";
+    const PLAIN: &str = "AAAAAAaaaaaaCcEEEEeeeeIIIIiiiiNnOOOOOOooooooUUUUuuuuYyy";
+    if ch.is_ascii() {
+        return (!ch.is_ascii_control() && !"\\/:*?\"<>|".contains(ch)).then(|| ch.to_string());
+    }
+    if let Some(index) = LATIN.chars().position(|latin| latin == ch) {
+        return PLAIN.chars().nth(index).map(String::from);
+    }
+    match ch {
+        // 全角 ASCII（！到～）与全角空格。
+        '\u{FF01}'..='\u{FF5E}' => char::from_u32(ch as u32 - 0xFEE0).and_then(transliterate_char),
+        '\u{3000}' => Some(" ".to_string()),
+        '、' => Some(",".to_string()),
+        '。' | '·' | '・' => Some(".".to_string()),
+        '【' | '《' | '「' | '『' | '〈' => Some("(".to_string()),
+        '】' | '》' | '」' | '』' | '〉' => Some(")".to_string()),
+        '—' | '–' => Some("-".to_string()),
+        '…' => Some("...".to_string()),
+        '‘' | '’' | '“' | '”' => Some("'".to_string()),
+        'ß' => Some("ss".to_string()),
+        'æ' => Some("ae".to_string()),
+        'Æ' => Some("AE".to_string()),
+        _ => None,
+    }
+}
+
+/// base64 编码（每 76 字符换行）后的字节数。
+pub fn base64_encoded_len(bytes: u64) -> u64 {
+    let encoded = bytes.div_ceil(3) * 4;
+    encoded + encoded.div_ceil(76) * 2
+}
+
+pub fn format_bytes(bytes: u64) -> String {
+    if bytes >= 1024 * 1024 {
+        format!("{:.1} MB", bytes as f64 / 1024.0 / 1024.0)
+    } else {
+        format!("{:.1} KB", bytes as f64 / 1024.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ascii_file_name, validate_attachments, AttachmentCheckRequest};
+
+    #[test]
+    fn flags_missing_and_oversized_attachments() {
+        let dir = std::env::temp_dir().join(format!("bes-attachments-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("temp dir");
+        let resume = dir.join("resume.pdf");
+        std::fs::write(&resume, vec![b'a'; 3000]).expect("write pdf");
+        let request = AttachmentCheckRequest {
+            paths: vec![
+                resume.to_string_lossy().to_string(),
+                dir.join("missing.docx").to_string_lossy().to_string(),
+            ],
+            max_file_bytes: Some(2048),
+            max_total_bytes: None,
+            server_max_bytes: Some(3000),
+        };
+        let report = validate_attachments(&request);
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert!(!report.ok);
+        assert_eq!(report.total_bytes, 3000);
+        assert_eq!(report.encoded_bytes, 4000 + 53 * 2);
+        assert_eq!(report.attachments[0].mime_type, "application/pdf");
+        assert_eq!(report.attachments[0].problems.len(), 1);
+        assert!(!report.attachments[1].exists);
+        assert_eq!(report.problems.len(), 1);
+    }
+
+    #[test]
+    fn transliterates_non_ascii_file_names() {
+        assert_eq!(ascii_file_name("resume.pdf"), "resume.pdf");
+        assert_eq!(ascii_file_name("【2024】招生简章（终版）.pdf"), "(2024)_(_).pdf");
+        assert_eq!(ascii_file_name("招生简章.docx"), "attachment.docx");
+        assert_eq!(ascii_file_name("Résumé－Zoë.PDF"), "Resume-Zoe.PDF");
+        assert_eq!(ascii_file_name("ＡＢＣ　简历.txt"), "ABC.txt");
+    }
+}
@@ -0,0 +1,71 @@
+use crate::engine::{validate_email, RecipientEntry};
+use serde::{Deserialize, Serialize};
+
+/// 单批密送人数的硬上限；各服务商的单封 RCPT 上限都远低于此值。
+pub const MAX_BATCH_SIZE: usize = 1000;
+
+/// 批量密送模式：每 `size` 个收件人合并为一封邮件，全部放入密送，To 显示固定地址。
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct BatchBccOptions {
+    pub size: usize,
+    /// 显示在 To 中的地址，通常是发件人自己或公告列表地址。
+    pub to_email: String,
+    /// 模板中 `{teacher_name}` 的取值；为空时使用 `to_email`。
+    #[serde(default)]
+    pub to_name: String,
+}
+
+impl BatchBccOptions {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.size == 0 || self.size > MAX_BATCH_SIZE {
+            return Err(format!("每批密送人数必须在 1 到 {MAX_BATCH_SIZE} 之间"));
+        }
+        validate_email(&self.to_email, "批量密送的收件地址")
+    }
+
+    /// 批量邮件的显示收件人，同时用于渲染模板变量。
+    pub fn visible_recipient(&self) -> RecipientEntry {
+        let name = self.to_name.trim();
+        RecipientEntry {
+            email: self.to_email.trim().to_string(),
+            name: if name.is_empty() { self.to_email.trim() } else { name }.to_string(),
+            ..RecipientEntry::default()
+        }
+    }
+}
+
+/// 批次编号从 1 开始，格式为 `<job_id>-b0001`。
+pub fn batch_id(job_id: &str, number: usize) -> String {
+    format!("{job_id}-b{number:04}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{batch_id, BatchBccOptions, MAX_BATCH_SIZE};
+
+    #[test]
+    fn validates_batch_options() {
+        let options = BatchBccOptions {
+            size: 50,
+            to_email: " news@example.edu ".to_string(),
+            to_name: String::new(),
+        };
+        assert!(options.validate().is_ok());
+        assert_eq!(options.visible_recipient().email, "news@example.edu");
+        assert_eq!(options.visible_recipient().name, "news@example.edu");
+        assert!(BatchBccOptions { size: 0, ..options.clone() }.validate().is_err());
+        assert!(BatchBccOptions {
+            size: MAX_BATCH_SIZE + 1,
+            ..options.clone()
+        }
+        .validate()
+        .is_err());
+        assert!(BatchBccOptions {
+            to_email: "not-an-address".to_string(),
+            ..options
+        }
+        .validate()
+        .is_err());
+        assert_eq!(batch_id("job-1", 7), "job-1-b0007");
+    }
+}
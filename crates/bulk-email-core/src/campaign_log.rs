@@ -0,0 +1,659 @@
+use crate::engine::RecipientEntry;
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+/// 需要长期保存的任务事件；等待倒计时等高频事件不落盘。
+const LOGGED_EVENTS: [&str; 8] = [
+    "job_started",
+    "recipient_sent",
+    "recipient_failed",
+    "recipient_retry",
+    "recipient_deferred",
+    "recipient_skipped",
+    "job_finished",
+    "job_cancelled",
+];
+
+/// 任务参数中不随任务保存的字段：每次发送重新生成或只对当次有效。
+const TRANSIENT_FIELDS: [&str; 5] = ["job_id", "operator", "attachment_links", "linked_recipients", "resume_campaign"];
+
+/// 按任务（campaign）保存的事件日志：`<dir>/<job_id>.jsonl`，每行一个带 `at` 时间戳的事件。
+/// 两种发送引擎的事件都经由 `SendEventHooks` 写入，失败明细因此不会随界面关闭而丢失。
+pub struct CampaignLog {
+    dir: PathBuf,
+}
+
+impl CampaignLog {
+    pub fn new(dir: &Path) -> Self {
+        CampaignLog { dir: dir.to_path_buf() }
+    }
+
+    pub fn path(&self, job_id: &str) -> PathBuf {
+        let slug: String = job_id
+            .chars()
+            .map(|ch| if ch.is_ascii_alphanumeric() || matches!(ch, '-' | '_' | '.') { ch } else { '_' })
+            .collect();
+        self.dir.join(format!("{slug}.jsonl"))
+    }
+
+    /// 记录一条事件；不需要保存的事件类型直接忽略。
+    pub fn record(&self, event: &Value) -> Result<(), String> {
+        let Some(kind) = event["type"].as_str().filter(|kind| LOGGED_EVENTS.contains(kind)) else {
+            return Ok(());
+        };
+        let Some(job_id) = event["job_id"].as_str().filter(|id| !id.is_empty()) else {
+            return Ok(());
+        };
+        fs::create_dir_all(&self.dir).map_err(|err| format!("创建任务日志目录失败: {err}"))?;
+        let mut entry = event.clone();
+        entry["type"] = json!(kind);
+        entry["at"] = json!(Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true));
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.path(job_id))
+            .map_err(|err| format!("写入任务日志失败: {err}"))?;
+        writeln!(file, "{entry}").map_err(|err| format!("写入任务日志失败: {err}"))
+    }
+
+    /// 保存任务的发送参数（`<job_id>.campaign.json`），供之后复制任务；密码等字段不落盘。
+    pub fn save_definition(&self, job_id: &str, payload: &Value) -> Result<(), String> {
+        fs::create_dir_all(&self.dir).map_err(|err| format!("创建任务日志目录失败: {err}"))?;
+        let text = serde_json::to_string_pretty(&redact_definition(payload)).map_err(|err| err.to_string())?;
+        fs::write(self.path(job_id).with_extension("campaign.json"), text)
+            .map_err(|err| format!("保存任务参数失败: {err}"))
+    }
+
+    /// 读取任务的发送参数；早于参数保存功能的任务返回 `None`。
+    pub fn read_definition(&self, job_id: &str) -> Result<Option<Value>, String> {
+        let path = self.path(job_id).with_extension("campaign.json");
+        if !path.exists() {
+            return Ok(None);
+        }
+        let text = fs::read_to_string(&path).map_err(|err| format!("读取任务参数失败: {err}"))?;
+        serde_json::from_str(&text)
+            .map(Some)
+            .map_err(|err| format!("任务参数格式错误: {err}"))
+    }
+
+    /// 读取任务的全部事件；日志不存在时返回 `None`。
+    pub fn read(&self, job_id: &str) -> Result<Option<Vec<Value>>, String> {
+        let path = self.path(job_id);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let file = File::open(&path).map_err(|err| format!("读取任务日志失败: {err}"))?;
+        let mut events = Vec::new();
+        for line in BufReader::new(file).lines() {
+            let line = line.map_err(|err| format!("读取任务日志失败: {err}"))?;
+            // 崩溃时可能留下半行，跳过即可。
+            if let Ok(event) = serde_json::from_str::<Value>(&line) {
+                events.push(event);
+            }
+        }
+        Ok(Some(events))
+    }
+
+    /// 全部任务的历史记录，按开始时间从新到旧排列。
+    pub fn history(&self) -> Result<Vec<JobHistoryEntry>, String> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+        let entries = fs::read_dir(&self.dir).map_err(|err| format!("读取任务日志目录失败: {err}"))?;
+        let mut history = Vec::new();
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Some(stem) = path.file_name().and_then(|name| name.to_str()?.strip_suffix(".jsonl")) else {
+                continue;
+            };
+            if let Some(detail) = self.detail(stem)? {
+                history.push(detail.entry);
+            }
+        }
+        history.sort_by(|a, b| b.started_at.cmp(&a.started_at).then_with(|| a.job_id.cmp(&b.job_id)));
+        Ok(history)
+    }
+
+    /// 单个任务的结果汇总与发送参数快照；任务日志不存在时返回 `None`。
+    pub fn detail(&self, job_id: &str) -> Result<Option<JobDetail>, String> {
+        let Some(events) = self.read(job_id)? else {
+            return Ok(None);
+        };
+        // 文件名中的特殊字符已被替换，以事件中的任务 ID 为准。
+        let job_id = events
+            .iter()
+            .find_map(|event| event["job_id"].as_str())
+            .unwrap_or(job_id)
+            .to_string();
+        let summary = CampaignSummary::from_events(&job_id, &events);
+        let definition = self.read_definition(&job_id)?;
+        let snapshot = definition.as_ref();
+        let format_time = |at: Option<DateTime<Utc>>| at.map(|at| at.to_rfc3339_opts(SecondsFormat::Secs, true));
+        let entry = JobHistoryEntry {
+            job_id,
+            status: summary.status,
+            started_at: format_time(summary.started_at),
+            ended_at: format_time(summary.ended_at),
+            total: summary.total,
+            sent: summary.sent,
+            failed: summary.failed,
+            skipped: summary.skipped,
+            subject: snapshot
+                .and_then(|definition| definition.pointer("/template/subject")?.as_str())
+                .map(str::to_string),
+            engine: snapshot
+                .and_then(|definition| definition["engine"].as_str())
+                .map(str::to_string),
+            resend_of: summary.resend_of,
+        };
+        Ok(Some(JobDetail {
+            entry,
+            failures: summary.failures,
+            failed_domains: summary.failed_domains,
+            definition,
+        }))
+    }
+}
+
+/// 任务历史中的一项，由任务日志计算。
+#[derive(Serialize, Clone, Debug)]
+pub struct JobHistoryEntry {
+    pub job_id: String,
+    /// `finished` / `cancelled` / `incomplete`。
+    pub status: String,
+    pub started_at: Option<String>,
+    pub ended_at: Option<String>,
+    pub total: Option<u64>,
+    pub sent: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    /// 以下字段来自发送参数快照，早于参数保存功能的任务为空。
+    pub subject: Option<String>,
+    pub engine: Option<String>,
+    pub resend_of: Option<String>,
+}
+
+/// 任务详情：结果汇总、失败原因与当时使用的发送参数（不含密码）。
+#[derive(Serialize, Clone, Debug)]
+pub struct JobDetail {
+    #[serde(flatten)]
+    pub entry: JobHistoryEntry,
+    pub failures: Vec<FailureGroup>,
+    pub failed_domains: Vec<(String, usize)>,
+    pub definition: Option<Value>,
+}
+
+/// 一类失败原因的汇总。
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct FailureGroup {
+    /// SMTP 状态码（如 `550`），无法识别时为 `other`。
+    pub class: String,
+    pub count: usize,
+    pub sample: String,
+}
+
+/// 由事件日志计算的任务结果。
+#[derive(Clone, Debug, Default)]
+pub struct CampaignSummary {
+    pub job_id: String,
+    /// `finished` / `cancelled` / `incomplete`（没有结束事件，可能仍在运行或已崩溃）。
+    pub status: String,
+    pub started_at: Option<DateTime<Utc>>,
+    pub ended_at: Option<DateTime<Utc>>,
+    pub total: Option<u64>,
+    pub sent: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    pub deferred: usize,
+    pub retries: usize,
+    pub failures: Vec<FailureGroup>,
+    /// 失败数最多的收件域名：(域名, 失败数)。
+    pub failed_domains: Vec<(String, usize)>,
+    /// 成功发送的时间点，用于绘制时间线。
+    pub sent_times: Vec<DateTime<Utc>>,
+    /// 重发失败收件人的任务所对应的原任务。
+    pub resend_of: Option<String>,
+}
+
+impl CampaignSummary {
+    pub fn from_events(job_id: &str, events: &[Value]) -> Self {
+        let mut summary = CampaignSummary {
+            job_id: job_id.to_string(),
+            status: "incomplete".to_string(),
+            ..CampaignSummary::default()
+        };
+        let mut groups: BTreeMap<String, FailureGroup> = BTreeMap::new();
+        let mut domains: BTreeMap<String, usize> = BTreeMap::new();
+        for event in events {
+            let at = event["at"]
+                .as_str()
+                .or_else(|| event["sent_at"].as_str())
+                .and_then(|value| DateTime::parse_from_rfc3339(value).ok())
+                .map(|value| value.with_timezone(&Utc));
+            if let Some(at) = at {
+                summary.started_at = Some(summary.started_at.map_or(at, |start| start.min(at)));
+                summary.ended_at = Some(summary.ended_at.map_or(at, |end| end.max(at)));
+            }
+            match event["type"].as_str() {
+                Some("job_started") => {
+                    summary.total = event["total"].as_u64();
+                    summary.resend_of = event["resend_of"].as_str().map(str::to_string);
+                }
+                Some("recipient_sent") => {
+                    summary.sent += 1;
+                    summary.sent_times.extend(at);
+                }
+                Some("recipient_failed") => {
+                    summary.failed += 1;
+                    let error = event["error"].as_str().unwrap_or_default();
+                    let class = smtp_error_class(error);
+                    groups
+                        .entry(class.clone())
+                        .or_insert_with(|| FailureGroup {
+                            class,
+                            count: 0,
+                            sample: error.to_string(),
+                        })
+                        .count += 1;
+                    let email = event["email"].as_str().unwrap_or_default();
+                    if let Some((_, domain)) = email.rsplit_once('@') {
+                        *domains.entry(domain.to_lowercase()).or_default() += 1;
+                    }
+                }
+                Some("recipient_skipped") => summary.skipped += 1,
+                Some("recipient_deferred") => summary.deferred += 1,
+                Some("recipient_retry") => summary.retries += 1,
+                Some("job_finished") => summary.status = "finished".to_string(),
+                Some("job_cancelled") => summary.status = "cancelled".to_string(),
+                _ => {}
+            }
+        }
+        summary.failures = groups.into_values().collect();
+        summary.failures.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.class.cmp(&b.class)));
+        summary.failed_domains = domains.into_iter().collect();
+        summary
+            .failed_domains
+            .sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        summary
+    }
+}
+
+/// 时间线的分桶粒度。
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TimelineBucket {
+    Minute,
+    Hour,
+}
+
+impl TimelineBucket {
+    fn seconds(self) -> i64 {
+        match self {
+            TimelineBucket::Minute => 60,
+            TimelineBucket::Hour => 3600,
+        }
+    }
+}
+
+/// 时间线最多包含的桶数，避免按分钟查看长时间任务时返回过多数据。
+pub const MAX_TIMELINE_BUCKETS: usize = 10_000;
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct TimelinePoint {
+    /// 桶的起始时间（UTC，RFC 3339）。
+    pub start: String,
+    pub sent: usize,
+    pub failed: usize,
+    pub retried: usize,
+}
+
+#[derive(Serialize, Debug)]
+pub struct CampaignTimeline {
+    pub campaign_id: String,
+    pub bucket: TimelineBucket,
+    /// 从第一个到最后一个有事件的桶，中间没有事件的桶计数为 0。
+    pub points: Vec<TimelinePoint>,
+}
+
+impl CampaignTimeline {
+    pub fn from_events(campaign_id: &str, events: &[Value], bucket: TimelineBucket) -> Result<Self, String> {
+        let width = bucket.seconds();
+        // 每个桶依次为 发送 / 失败 / 重试 次数。
+        let mut counts: BTreeMap<i64, [usize; 3]> = BTreeMap::new();
+        for event in events {
+            let Some(at) = event["at"]
+                .as_str()
+                .or_else(|| event["sent_at"].as_str())
+                .and_then(|value| DateTime::parse_from_rfc3339(value).ok())
+            else {
+                continue;
+            };
+            let kind = match event["type"].as_str() {
+                Some("recipient_sent") => 0,
+                Some("recipient_failed") => 1,
+                Some("recipient_retry") => 2,
+                _ => continue,
+            };
+            counts.entry(at.timestamp().div_euclid(width)).or_default()[kind] += 1;
+        }
+        let points = match (counts.keys().next(), counts.keys().next_back()) {
+            (Some(&first), Some(&last)) => {
+                if (last - first) as usize >= MAX_TIMELINE_BUCKETS {
+                    return Err("任务时间跨度过长，请改为按小时查看时间线".to_string());
+                }
+                (first..=last)
+                    .map(|slot| {
+                        let [sent, failed, retried] = counts.get(&slot).copied().unwrap_or_default();
+                        TimelinePoint {
+                            start: DateTime::from_timestamp(slot * width, 0)
+                                .unwrap_or_default()
+                                .to_rfc3339_opts(SecondsFormat::Secs, true),
+                            sent,
+                            failed,
+                            retried,
+                        }
+                    })
+                    .collect()
+            }
+            _ => Vec::new(),
+        };
+        Ok(CampaignTimeline {
+            campaign_id: campaign_id.to_string(),
+            bucket,
+            points,
+        })
+    }
+}
+
+/// 任务结束时仍处于失败状态的收件人：之后重试成功的不计入，同一地址只保留一次，按首次失败的顺序排列。
+pub fn unresolved_failures(events: &[Value]) -> Vec<RecipientEntry> {
+    let sent: HashSet<String> = events
+        .iter()
+        .filter(|event| event["type"] == "recipient_sent")
+        .filter_map(|event| event["email"].as_str())
+        .map(|email| email.trim().to_lowercase())
+        .collect();
+    let mut seen = HashSet::new();
+    events
+        .iter()
+        .filter(|event| event["type"] == "recipient_failed")
+        .filter_map(|event| {
+            let email = event["email"].as_str()?.trim();
+            let key = email.to_lowercase();
+            (!sent.contains(&key) && seen.insert(key)).then(|| RecipientEntry {
+                email: email.to_string(),
+                name: event["name"].as_str().unwrap_or_default().to_string(),
+                ..RecipientEntry::default()
+            })
+        })
+        .collect()
+}
+
+/// 重发失败收件人时对原任务模板的修改；为空的字段沿用原任务。
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq)]
+pub struct TemplateOverrides {
+    #[serde(default)]
+    pub subject: Option<String>,
+    #[serde(default)]
+    pub body_text: Option<String>,
+    /// 空字符串表示去掉 HTML 正文。
+    #[serde(default)]
+    pub body_html: Option<String>,
+    /// 替换原任务的附件；空列表即不带附件（如附件过大导致 552 拒收）。
+    #[serde(default)]
+    pub attachments: Option<Vec<String>>,
+}
+
+/// 由原任务保存的参数生成重发任务：收件人换成 `recipients`，应用模板修改，并以 `resend_of` 记录来源任务。
+/// 生产环境确认不随原任务沿用，需要重新确认。
+pub fn resend_definition(
+    source_id: &str,
+    definition: Value,
+    recipients: Vec<RecipientEntry>,
+    overrides: &TemplateOverrides,
+) -> Result<Value, String> {
+    let Value::Object(mut send) = definition else {
+        return Err(format!("任务 {source_id} 的发送参数格式错误"));
+    };
+    for field in ["recipients_file", "recipient_source", "confirm_production"] {
+        send.remove(field);
+    }
+    send.insert("recipients".to_string(), json!(recipients));
+    send.insert("resend_of".to_string(), json!(source_id));
+    let template = send
+        .entry("template")
+        .or_insert_with(|| json!({}))
+        .as_object_mut()
+        .ok_or_else(|| format!("任务 {source_id} 的模板格式错误"))?;
+    for (field, value) in [("subject", &overrides.subject), ("body_text", &overrides.body_text)] {
+        if let Some(value) = value {
+            template.insert(field.to_string(), json!(value));
+        }
+    }
+    if let Some(html) = &overrides.body_html {
+        let html = Some(html).filter(|html| !html.trim().is_empty());
+        template.insert("body_html".to_string(), json!(html));
+    }
+    if let Some(attachments) = &overrides.attachments {
+        send.insert("attachments".to_string(), json!(attachments));
+    }
+    Ok(Value::Object(send))
+}
+
+/// 去掉一次性字段以及任意层级的 `password`。
+fn redact_definition(payload: &Value) -> Value {
+    fn strip_passwords(value: &mut Value) {
+        match value {
+            Value::Object(object) => {
+                object.remove("password");
+                object.values_mut().for_each(strip_passwords);
+            }
+            Value::Array(items) => items.iter_mut().for_each(strip_passwords),
+            _ => {}
+        }
+    }
+    let mut definition = payload.clone();
+    if let Some(object) = definition.as_object_mut() {
+        for field in TRANSIENT_FIELDS {
+            object.remove(field);
+        }
+    }
+    strip_passwords(&mut definition);
+    definition
+}
+
+/// 复制任务时保留的收件人范围。
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CloneSelection {
+    /// 最终仍失败的收件人。
+    Failed,
+    All,
+}
+
+/// 按范围从原任务中选出收件人。有保存的任务参数时沿用其中的姓名与补充字段；
+/// 否则（如旧任务、从文件导入的任务）按事件日志中出现过的收件人还原。
+pub fn select_recipients(
+    events: &[Value],
+    definition: Option<&Value>,
+    selection: CloneSelection,
+) -> Vec<RecipientEntry> {
+    let original: Vec<RecipientEntry> = definition
+        .and_then(|definition| definition.get("recipients"))
+        .and_then(|recipients| serde_json::from_value(recipients.clone()).ok())
+        .unwrap_or_default();
+    let selected = match selection {
+        CloneSelection::Failed => unresolved_failures(events),
+        CloneSelection::All if !original.is_empty() => return original,
+        CloneSelection::All => {
+            let mut seen = HashSet::new();
+            events
+                .iter()
+                .filter(|event| event["type"].as_str().is_some_and(|kind| kind.starts_with("recipient_")))
+                .filter_map(|event| {
+                    let email = event["email"].as_str()?.trim();
+                    seen.insert(email.to_lowercase()).then(|| RecipientEntry {
+                        email: email.to_string(),
+                        name: event["name"].as_str().unwrap_or_default().to_string(),
+                        ..RecipientEntry::default()
+                    })
+                })
+                .collect()
+        }
+    };
+    let by_email: HashMap<String, &RecipientEntry> = original
+        .iter()
+        .map(|recipient| (recipient.email.trim().to_lowercase(), recipient))
+        .collect();
+    selected
+        .into_iter()
+        .map(|recipient| match by_email.get(&recipient.email.to_lowercase()) {
+            Some(entry) => (*entry).clone(),
+            None => recipient,
+        })
+        .collect()
+}
+
+/// 从错误信息中提取 SMTP 状态码（4xx / 5xx）作为分类。
+pub fn smtp_error_class(error: &str) -> String {
+    error
+        .split(|ch: char| !ch.is_ascii_digit())
+        .find(|token| token.len() == 3 && (token.starts_with('4') || token.starts_with('5')))
+        .map_or_else(|| "other".to_string(), str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        resend_definition, select_recipients, smtp_error_class, unresolved_failures, CampaignLog, CampaignSummary,
+        CampaignTimeline, CloneSelection, TemplateOverrides, TimelineBucket,
+    };
+    use serde_json::json;
+
+    #[test]
+    fn records_and_summarizes_campaign_events() {
+        let dir = std::env::temp_dir().join(format!("bes-campaign-log-{}", std::process::id()));
+        let log = CampaignLog::new(&dir);
+        assert!(log.read("job/1").expect("missing log").is_none());
+        for event in [
+            json!({ "type": "job_started", "job_id": "job/1", "total": 4 }),
+            json!({ "type": "inter_send_wait", "job_id": "job/1", "remaining_sec": 3 }),
+            json!({ "type": "recipient_sent", "job_id": "job/1", "email": "a@example.edu" }),
+            json!({ "type": "recipient_failed", "job_id": "job/1", "email": "b@bad.example", "error": "(550, b'5.1.1 User unknown')" }),
+            json!({ "type": "recipient_failed", "job_id": "job/1", "email": "c@bad.example", "error": "550 mailbox unavailable" }),
+            json!({ "type": "recipient_failed", "job_id": "job/1", "email": "d@example.edu", "error": "connection reset" }),
+            json!({ "type": "recipient_failed", "job_id": "job/1", "email": "a@example.edu", "error": "421 try later" }),
+            json!({ "type": "job_finished", "job_id": "job/1" }),
+        ] {
+            log.record(&event).expect("record");
+        }
+        let events = log.read("job/1").expect("read").expect("log exists");
+        assert_eq!(events.len(), 7);
+        assert!(log.path("job/1").ends_with("job_1.jsonl"));
+
+        let summary = CampaignSummary::from_events("job/1", &events);
+        assert_eq!(summary.status, "finished");
+        assert_eq!((summary.total, summary.sent, summary.failed), (Some(4), 1, 4));
+        assert_eq!(summary.failures[0].class, "550");
+        assert_eq!(summary.failures[0].count, 2);
+        assert_eq!(summary.failed_domains[0], ("bad.example".to_string(), 2));
+        assert!(summary.started_at.is_some());
+        let failures: Vec<_> = unresolved_failures(&events).into_iter().map(|entry| entry.email).collect();
+        assert_eq!(failures, ["b@bad.example", "c@bad.example", "d@example.edu"]);
+        assert_eq!(smtp_error_class("421 4.7.0 try again later"), "421");
+        assert_eq!(smtp_error_class("timed out after 30s"), "other");
+
+        let payload = json!({
+            "job_id": "job/1",
+            "smtp": { "host": "smtp.example.edu", "password": "secret" },
+            "rotation": { "accounts": [{ "smtp": { "password": "other" } }] },
+            "recipients": [{ "email": "B@bad.example", "name": "乙", "fields": { "company": "Bad" } }],
+        });
+        log.save_definition("job/1", &payload).expect("save definition");
+        let definition = log.read_definition("job/1").expect("read").expect("definition exists");
+        assert!(!definition.to_string().contains("secret") && !definition.to_string().contains("other"));
+        assert!(definition.get("job_id").is_none());
+        let failed = select_recipients(&events, Some(&definition), CloneSelection::Failed);
+        assert_eq!((failed[0].name.as_str(), failed[0].fields["company"].as_str()), ("乙", "Bad"));
+        let all = select_recipients(&events, None, CloneSelection::All);
+        assert_eq!(all.len(), 4);
+
+        let overrides = TemplateOverrides {
+            subject: Some("重发：资料".to_string()),
+            attachments: Some(Vec::new()),
+            ..TemplateOverrides::default()
+        };
+        let mut definition = definition;
+        definition["template"] = json!({ "subject": "资料", "body_text": "您好" });
+        definition["attachments"] = json!(["/tmp/big.pdf"]);
+        definition["confirm_production"] = json!(true);
+        let resend = resend_definition("job/1", definition, failed, &overrides).expect("resend");
+        assert_eq!(resend["template"]["subject"], "重发：资料");
+        assert_eq!(resend["template"]["body_text"], "您好");
+        assert_eq!(resend["attachments"], json!([]));
+        assert_eq!(resend["recipients"].as_array().map(Vec::len), Some(3));
+        assert!(resend.get("confirm_production").is_none());
+        let started = [json!({ "type": "job_started", "job_id": "job-2", "resend_of": resend["resend_of"] })];
+        assert_eq!(CampaignSummary::from_events("job-2", &started).resend_of.as_deref(), Some("job/1"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn lists_job_history_with_snapshots() {
+        let dir = std::env::temp_dir().join(format!("bes-job-history-{}", std::process::id()));
+        let log = CampaignLog::new(&dir);
+        assert!(log.history().expect("empty").is_empty());
+        for event in [
+            json!({ "type": "job_started", "job_id": "job/a", "total": 2, "at": "2024-03-01T09:00:00Z" }),
+            json!({ "type": "recipient_sent", "job_id": "job/a", "email": "a@example.edu" }),
+            json!({ "type": "job_started", "job_id": "job-b", "total": 1 }),
+        ] {
+            log.record(&event).expect("record");
+        }
+        let payload = json!({ "engine": "native", "template": { "subject": "资料" }, "smtp": { "password": "secret" } });
+        log.save_definition("job/a", &payload).expect("save definition");
+
+        let history = log.history().expect("history");
+        let ids: Vec<&str> = history.iter().map(|entry| entry.job_id.as_str()).collect();
+        assert_eq!(ids, ["job-b", "job/a"]);
+        assert_eq!((history[1].sent, history[1].status.as_str()), (1, "incomplete"));
+        assert_eq!(history[1].subject.as_deref(), Some("资料"));
+        assert!(history[0].subject.is_none());
+
+        let detail = log.detail("job/a").expect("detail").expect("exists");
+        assert_eq!(detail.entry.engine.as_deref(), Some("native"));
+        assert!(!detail.definition.expect("snapshot").to_string().contains("secret"));
+        assert!(log.detail("job-c").expect("missing").is_none());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn buckets_timeline_and_fills_gaps() {
+        let events = [
+            json!({ "type": "recipient_sent", "at": "2024-05-01T08:00:05Z" }),
+            json!({ "type": "recipient_retry", "at": "2024-05-01T08:00:40Z" }),
+            json!({ "type": "recipient_failed", "at": "2024-05-01T08:00:59Z" }),
+            json!({ "type": "recipient_sent", "sent_at": "2024-05-01T16:03:00+08:00" }),
+            json!({ "type": "job_finished", "at": "2024-05-01T09:00:00Z" }),
+        ];
+        let timeline = CampaignTimeline::from_events("job-1", &events, TimelineBucket::Minute).expect("minutes");
+        assert_eq!(timeline.points.len(), 4);
+        assert_eq!((timeline.points[0].sent, timeline.points[0].failed, timeline.points[0].retried), (1, 1, 1));
+        assert_eq!(timeline.points[1].sent, 0);
+        assert_eq!(timeline.points[3].start, "2024-05-01T08:03:00Z");
+        let hourly = CampaignTimeline::from_events("job-1", &events, TimelineBucket::Hour).expect("hours");
+        assert_eq!(hourly.points.len(), 1);
+        assert_eq!(hourly.points[0].sent, 2);
+
+        let long = [
+            json!({ "type": "recipient_sent", "at": "2024-05-01T08:00:00Z" }),
+            json!({ "type": "recipient_sent", "at": "2024-06-01T08:00:00Z" }),
+        ];
+        assert!(CampaignTimeline::from_events("job-2", &long, TimelineBucket::Minute).is_err());
+    }
+}
@@ -0,0 +1,186 @@
+use crate::engine::validate_email;
+use crate::imap_seed::{decode_folder_name, quote, search_uids, ImapSession, ImapSettings, Mailbox};
+use serde::{Deserialize, Serialize};
+
+/// 服务商没有标记 `\Junk` 时按名称识别的垃圾邮件文件夹（解码后忽略大小写比较最后一级）。
+const JUNK_FOLDER_NAMES: [&str; 5] = ["spam", "junk", "junk e-mail", "junk email", "垃圾邮件"];
+
+/// 金丝雀收件人：发件人自己的测试邮箱（Gmail / Outlook / 163 等），每发送若干封真实邮件插入一轮，
+/// 通过 IMAP 检查它们落在收件箱还是垃圾邮件箱，发送途中就能发现邮件开始进入垃圾箱。
+#[derive(Deserialize, Clone)]
+pub struct CanaryOptions {
+    pub mailboxes: Vec<CanaryMailbox>,
+    /// 每发送多少位真实收件人插入一轮金丝雀。
+    #[serde(default = "default_every")]
+    pub every: usize,
+    /// 金丝雀发出后至少等待多久再检查投递位置。
+    #[serde(default = "default_check_delay_sec")]
+    pub check_delay_sec: u64,
+}
+
+fn default_every() -> usize {
+    100
+}
+
+fn default_check_delay_sec() -> u64 {
+    60
+}
+
+#[derive(Deserialize, Clone)]
+pub struct CanaryMailbox {
+    pub email: String,
+    /// 模板中的收件人姓名；为空时沿用上一位真实收件人的姓名。
+    #[serde(default)]
+    pub name: String,
+    /// 检查投递位置用的 IMAP 登录；为空时只记录是否投递成功。
+    #[serde(default)]
+    pub imap: Option<ImapSettings>,
+}
+
+impl CanaryOptions {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.mailboxes.is_empty() {
+            return Err("金丝雀至少需要一个邮箱".to_string());
+        }
+        if self.every == 0 {
+            return Err("金丝雀间隔 必须 >= 1".to_string());
+        }
+        for (index, mailbox) in self.mailboxes.iter().enumerate() {
+            validate_email(&mailbox.email, &format!("金丝雀邮箱[{}]", index + 1))?;
+            if mailbox.imap.as_ref().is_some_and(|imap| imap.host.trim().is_empty()) {
+                return Err(format!("金丝雀邮箱 {} 的 IMAP 主机不能为空", mailbox.email.trim()));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 邮件在金丝雀邮箱中的位置。
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Placement {
+    Inbox,
+    Spam,
+    /// 收件箱与垃圾邮件箱中都没有找到（尚未送达或被服务商拦截）。
+    Missing,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct PlacementResult {
+    pub message_id: String,
+    pub placement: Placement,
+    pub folder: Option<String>,
+}
+
+/// 登录金丝雀邮箱，依次在收件箱与垃圾邮件文件夹中按 Message-ID 查找，结果与 `message_ids` 一一对应。
+pub fn check_placement(settings: &ImapSettings, message_ids: &[String]) -> Result<Vec<PlacementResult>, String> {
+    let mut session = ImapSession::open(settings)?;
+    let result = check_in_session(&mut session, message_ids);
+    let _ = session.command("LOGOUT");
+    result
+}
+
+fn check_in_session(session: &mut ImapSession, message_ids: &[String]) -> Result<Vec<PlacementResult>, String> {
+    let mut folders = vec![("INBOX".to_string(), Placement::Inbox)];
+    folders.extend(
+        session
+            .list_mailboxes()?
+            .into_iter()
+            .filter(is_junk_folder)
+            .map(|mailbox| (mailbox.name, Placement::Spam)),
+    );
+    let mut results: Vec<PlacementResult> = message_ids
+        .iter()
+        .map(|message_id| PlacementResult {
+            message_id: message_id.clone(),
+            placement: Placement::Missing,
+            folder: None,
+        })
+        .collect();
+    for (folder, placement) in &folders {
+        if results.iter().all(|result| result.placement != Placement::Missing) {
+            break;
+        }
+        if session.command(&format!("EXAMINE {}", quote(folder))).is_err() {
+            continue;
+        }
+        for result in results.iter_mut().filter(|result| result.placement == Placement::Missing) {
+            let search = format!("UID SEARCH HEADER Message-ID {}", quote(&result.message_id));
+            if !search_uids(&session.command(&search)?).is_empty() {
+                result.placement = *placement;
+                result.folder = Some(decode_folder_name(folder));
+            }
+        }
+    }
+    Ok(results)
+}
+
+fn is_junk_folder(mailbox: &Mailbox) -> bool {
+    if mailbox.flags.iter().any(|flag| flag == "\\junk") {
+        return true;
+    }
+    let name = decode_folder_name(&mailbox.name).to_lowercase();
+    let last = name.rsplit(['/', '.']).next().unwrap_or_default();
+    JUNK_FOLDER_NAMES.contains(&last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_placement, Placement};
+    use crate::imap_seed::ImapSettings;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+
+    #[test]
+    fn finds_canaries_in_inbox_and_junk_folders() {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind");
+        let port = listener.local_addr().expect("addr").port();
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().expect("accept");
+            let mut writer = stream.try_clone().expect("clone");
+            let mut reader = BufReader::new(stream);
+            writer.write_all(b"* OK IMAP4rev1 ready\r\n").expect("greeting");
+            let mut folder = String::new();
+            let mut line = String::new();
+            while reader.read_line(&mut line).expect("read") > 0 {
+                let (tag, command) = line.trim_end().split_once(' ').expect("tagged");
+                if let Some(name) = command.strip_prefix("EXAMINE ") {
+                    folder = name.to_string();
+                }
+                let reply = match (folder.as_str(), command) {
+                    (_, "LIST \"\" \"*\"") => concat!(
+                        "* LIST (\\HasNoChildren) \"/\" INBOX\r\n",
+                        "* LIST (\\HasNoChildren) \"/\" \"&V4NXPpCuTvY-\"\r\n",
+                        "* LIST (\\HasNoChildren \\Sent) \"/\" Sent\r\n",
+                    ),
+                    ("\"INBOX\"", "UID SEARCH HEADER Message-ID \"<a@example.edu>\"") => "* SEARCH 3\r\n",
+                    ("\"&V4NXPpCuTvY-\"", "UID SEARCH HEADER Message-ID \"<b@example.edu>\"") => "* SEARCH 8\r\n",
+                    (_, command) if command.starts_with("UID SEARCH") => "* SEARCH\r\n",
+                    _ => "",
+                };
+                writer.write_all(format!("{reply}{tag} OK done\r\n").as_bytes()).expect("reply");
+                if command == "LOGOUT" {
+                    break;
+                }
+                line.clear();
+            }
+        });
+        let settings = ImapSettings {
+            host: "127.0.0.1".to_string(),
+            port,
+            username: "canary@163.com".to_string(),
+            password: "secret".to_string(),
+            use_ssl: false,
+            timeout_sec: 5,
+        };
+        let ids = ["<a@example.edu>", "<b@example.edu>", "<c@example.edu>"].map(str::to_string);
+        let results = check_placement(&settings, &ids).expect("placement");
+        server.join().expect("server");
+
+        assert_eq!(results[0].placement, Placement::Inbox);
+        assert_eq!(results[1].placement, Placement::Spam);
+        assert_eq!(results[1].folder.as_deref(), Some("垃圾邮件"));
+        assert_eq!(results[2].placement, Placement::Missing);
+        assert_eq!(results[2].folder, None);
+    }
+}
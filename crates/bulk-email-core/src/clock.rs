@@ -0,0 +1,88 @@
+use chrono::{DateTime, Utc};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// 发送引擎读取时间与等待所用的时钟；测试中注入 `FrozenClock`，调度与限速不再依赖真实时间。
+pub trait Clock: Send + Sync {
+    fn now(&self) -> Instant;
+    fn utc_now(&self) -> DateTime<Utc>;
+    fn sleep(&self, duration: Duration);
+
+    fn unix_now(&self) -> u64 {
+        self.utc_now().timestamp().max(0) as u64
+    }
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn utc_now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        std::thread::sleep(duration);
+    }
+}
+
+/// 冻结的时钟：只有 `sleep` / `advance` 会推动时间，且立即返回。
+pub struct FrozenClock {
+    start: Instant,
+    start_utc: DateTime<Utc>,
+    elapsed: Mutex<Duration>,
+}
+
+impl FrozenClock {
+    pub fn new(start_utc: DateTime<Utc>) -> Self {
+        FrozenClock {
+            start: Instant::now(),
+            start_utc,
+            elapsed: Mutex::new(Duration::ZERO),
+        }
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        if let Ok(mut elapsed) = self.elapsed.lock() {
+            *elapsed += duration;
+        }
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed.lock().map(|elapsed| *elapsed).unwrap_or_default()
+    }
+}
+
+impl Clock for FrozenClock {
+    fn now(&self) -> Instant {
+        self.start + self.elapsed()
+    }
+
+    fn utc_now(&self) -> DateTime<Utc> {
+        self.start_utc + chrono::Duration::from_std(self.elapsed()).unwrap_or_default()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Clock, FrozenClock};
+    use chrono::{TimeZone, Utc};
+    use std::time::Duration;
+
+    #[test]
+    fn frozen_clock_only_moves_when_sleeping() {
+        let clock = FrozenClock::new(Utc.timestamp_opt(1_700_000_000, 0).single().expect("timestamp"));
+        let before = clock.now();
+        assert_eq!(clock.now(), before);
+        clock.sleep(Duration::from_secs(90));
+        assert_eq!(clock.now() - before, Duration::from_secs(90));
+        assert_eq!(clock.unix_now(), 1_700_000_090);
+    }
+}
@@ -0,0 +1,97 @@
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// 单个任务同时保持的 SMTP 连接数上限；再多通常只会触发服务商的并发限制。
+pub const MAX_CONNECTIONS: usize = 16;
+
+/// 并发发送：同时保持多条 SMTP 连接，各连接独立遵守发送间隔与自身的限速。
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub struct ConcurrencyOptions {
+    pub connections: usize,
+    /// 每条连接每分钟最多发送的邮件数；为空时只受任务间隔与全局限速约束。
+    #[serde(default)]
+    pub per_connection_per_minute: Option<u32>,
+}
+
+impl ConcurrencyOptions {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.connections == 0 || self.connections > MAX_CONNECTIONS {
+            return Err(format!("并发连接数必须在 1 到 {MAX_CONNECTIONS} 之间"));
+        }
+        if self.per_connection_per_minute == Some(0) {
+            return Err("每条连接的每分钟上限必须大于 0，不限制请留空".to_string());
+        }
+        Ok(())
+    }
+
+    /// 同一连接两封邮件之间的最短间隔。
+    pub fn connection_interval(&self) -> Duration {
+        match self.per_connection_per_minute {
+            Some(limit) if limit > 0 => Duration::from_millis(60_000u64.div_ceil(limit.into())),
+            _ => Duration::ZERO,
+        }
+    }
+}
+
+/// 按派发顺序归并各连接的结果：先完成的结果暂存，直到前面的结果都到齐后再依次放出，
+/// 使进度事件的顺序与单连接发送时一致。
+pub struct ReorderBuffer<T> {
+    next: usize,
+    pending: BTreeMap<usize, T>,
+}
+
+impl<T> Default for ReorderBuffer<T> {
+    fn default() -> Self {
+        ReorderBuffer {
+            next: 0,
+            pending: BTreeMap::new(),
+        }
+    }
+}
+
+impl<T> ReorderBuffer<T> {
+    /// 放入序号为 `seq` 的结果（序号从 0 开始连续分配），返回现在可以按顺序处理的结果。
+    pub fn push(&mut self, seq: usize, item: T) -> Vec<T> {
+        self.pending.insert(seq, item);
+        let mut ready = Vec::new();
+        while let Some(item) = self.pending.remove(&self.next) {
+            ready.push(item);
+            self.next += 1;
+        }
+        ready
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ConcurrencyOptions, ReorderBuffer, MAX_CONNECTIONS};
+    use std::time::Duration;
+
+    #[test]
+    fn validates_connections_and_derives_interval() {
+        let options = ConcurrencyOptions {
+            connections: 4,
+            per_connection_per_minute: Some(7),
+        };
+        assert!(options.validate().is_ok());
+        assert_eq!(options.connection_interval(), Duration::from_millis(8_572));
+        let unlimited = ConcurrencyOptions {
+            per_connection_per_minute: None,
+            ..options.clone()
+        };
+        assert_eq!(unlimited.connection_interval(), Duration::ZERO);
+        for connections in [0, MAX_CONNECTIONS + 1] {
+            assert!(ConcurrencyOptions { connections, ..options.clone() }.validate().is_err());
+        }
+    }
+
+    #[test]
+    fn releases_results_in_dispatch_order() {
+        let mut buffer = ReorderBuffer::default();
+        assert!(buffer.push(1, "b").is_empty());
+        assert!(buffer.push(2, "c").is_empty());
+        assert_eq!(buffer.push(0, "a"), ["a", "b", "c"]);
+        assert_eq!(buffer.push(3, "d"), ["d"]);
+    }
+}
@@ -0,0 +1,198 @@
+use crate::engine::{validate_email, RecipientEntry};
+use chrono::{DateTime, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// 一条收件人同意（opt-in）记录：来源、同意时间与可供审计的凭证编号。
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct ConsentRecord {
+    pub email: String,
+    #[serde(default)]
+    pub source: String,
+    #[serde(default)]
+    pub opted_in_at: String,
+    #[serde(default)]
+    pub proof_ref: String,
+}
+
+impl ConsentRecord {
+    pub fn validate(&self) -> Result<(), String> {
+        validate_email(&self.email, "同意记录邮箱")?;
+        let opted_in_at = self.opted_in_at.trim();
+        if opted_in_at.is_empty() {
+            return Err(format!("{} 缺少同意时间", self.email.trim()));
+        }
+        let parsed = NaiveDate::parse_from_str(opted_in_at, "%Y-%m-%d").is_ok()
+            || DateTime::parse_from_rfc3339(opted_in_at).is_ok();
+        if !parsed {
+            return Err(format!(
+                "{} 的同意时间格式无效，请使用 YYYY-MM-DD 或 RFC3339: {opted_in_at}",
+                self.email.trim()
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// 活动级同意策略：`require` 时只向有同意记录的收件人发送。
+#[derive(Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum ConsentPolicy {
+    #[default]
+    Off,
+    Require,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ConsentImportError {
+    pub row: usize,
+    pub error: String,
+}
+
+#[derive(Serialize, Default, Debug)]
+pub struct ConsentImportSummary {
+    pub imported: usize,
+    pub updated: usize,
+    pub invalid: Vec<ConsentImportError>,
+}
+
+#[derive(Serialize)]
+pub struct ConsentReport {
+    pub consented: usize,
+    pub missing: Vec<RecipientEntry>,
+}
+
+#[derive(Deserialize, Serialize, Default)]
+pub struct ConsentStore {
+    #[serde(default)]
+    pub records: BTreeMap<String, ConsentRecord>,
+}
+
+impl ConsentStore {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        if !path.exists() {
+            return Ok(ConsentStore::default());
+        }
+        let text = fs::read_to_string(path).map_err(|err| format!("读取同意记录失败: {err}"))?;
+        serde_json::from_str(&text).map_err(|err| format!("同意记录格式错误: {err}"))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let text = serde_json::to_string_pretty(self).map_err(|err| err.to_string())?;
+        fs::write(path, text).map_err(|err| format!("写入同意记录失败: {err}"))
+    }
+
+    /// 写入一条记录；返回 true 表示新增，false 表示覆盖了已有记录。
+    pub fn upsert(&mut self, mut record: ConsentRecord) -> Result<bool, String> {
+        record.validate()?;
+        record.email = record.email.trim().to_string();
+        record.opted_in_at = record.opted_in_at.trim().to_string();
+        let key = record.email.to_lowercase();
+        Ok(self.records.insert(key, record).is_none())
+    }
+
+    pub fn has_consent(&self, email: &str) -> bool {
+        self.records.contains_key(&email.trim().to_lowercase())
+    }
+
+    pub fn import(&mut self, rows: Vec<Result<ConsentRecord, String>>) -> ConsentImportSummary {
+        let mut summary = ConsentImportSummary::default();
+        for (position, row) in rows.into_iter().enumerate() {
+            match row.and_then(|record| self.upsert(record)) {
+                Ok(true) => summary.imported += 1,
+                Ok(false) => summary.updated += 1,
+                Err(error) => summary.invalid.push(ConsentImportError {
+                    row: position + 1,
+                    error,
+                }),
+            }
+        }
+        summary
+    }
+
+    pub fn check(&self, recipients: Vec<RecipientEntry>) -> ConsentReport {
+        let (consented, missing): (Vec<_>, Vec<_>) = recipients
+            .into_iter()
+            .partition(|recipient| self.has_consent(&recipient.email));
+        ConsentReport {
+            consented: consented.len(),
+            missing,
+        }
+    }
+}
+
+/// 读取同意记录文件：支持 JSON 数组，或带表头（email, source, opted_in_at, proof_ref）的 CSV。
+pub fn read_consent_file(path: &Path) -> Result<Vec<Result<ConsentRecord, String>>, String> {
+    let extension = path
+        .extension()
+        .and_then(|value| value.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+    match extension.as_str() {
+        "json" => {
+            let text = fs::read_to_string(path).map_err(|err| format!("读取同意记录文件失败: {err}"))?;
+            let records: Vec<ConsentRecord> =
+                serde_json::from_str(&text).map_err(|err| format!("同意记录文件格式错误: {err}"))?;
+            Ok(records.into_iter().map(Ok).collect())
+        }
+        "csv" => {
+            let mut reader = csv::ReaderBuilder::new()
+                .trim(csv::Trim::All)
+                .from_path(path)
+                .map_err(|err| format!("读取同意记录文件失败: {err}"))?;
+            Ok(reader
+                .deserialize::<ConsentRecord>()
+                .map(|row| row.map_err(|err| format!("CSV 行解析失败: {err}")))
+                .collect())
+        }
+        _ => Err("同意记录仅支持 .json 或 .csv 文件".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_consent_file, ConsentStore};
+    use crate::engine::RecipientEntry;
+
+    #[test]
+    fn imports_csv_and_blocks_recipients_without_consent() {
+        let dir = std::env::temp_dir().join(format!("bes-consent-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("temp dir");
+        let path = dir.join("consent.csv");
+        std::fs::write(
+            &path,
+            "email,source,opted_in_at,proof_ref\n\
+             A@Example.com,signup-form,2024-03-01,form-123\n\
+             b@example.com,event,not-a-date,\n\
+             a@example.com,import,2024-04-01T08:00:00+08:00,crm-9\n",
+        )
+        .expect("write csv");
+
+        let mut store = ConsentStore::default();
+        let summary = store.import(read_consent_file(&path).expect("parse csv"));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert_eq!(summary.imported, 1);
+        assert_eq!(summary.updated, 1);
+        assert_eq!(summary.invalid.len(), 1);
+        assert_eq!(summary.invalid[0].row, 2);
+
+        let report = store.check(vec![
+            RecipientEntry {
+                email: "a@example.com".to_string(),
+                name: "A".to_string(),
+                ..RecipientEntry::default()
+            },
+            RecipientEntry {
+                email: "b@example.com".to_string(),
+                name: "B".to_string(),
+                ..RecipientEntry::default()
+            },
+        ]);
+        assert_eq!(report.consented, 1);
+        assert_eq!(report.missing.len(), 1);
+        assert_eq!(report.missing[0].email, "b@example.com");
+    }
+}
@@ -0,0 +1,237 @@
+use crate::engine::RecipientEntry;
+use crate::greylist::recipient_domain;
+use chrono::{Local, SecondsFormat};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// 同一域名累计多少次硬退信后视为失效域名。
+pub const HARD_BOUNCE_THRESHOLD: u32 = 3;
+
+const DOMAIN_NOT_FOUND_MARKERS: [&str; 7] = [
+    "domain not found",
+    "no such domain",
+    "domain does not exist",
+    "host or domain name not found",
+    "unrouteable address",
+    "unroutable address",
+    "5.1.2",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DomainFailure {
+    NotFound,
+    HardBounce,
+}
+
+/// 根据发送失败的错误文本判断是否与收件域名本身有关。
+pub fn classify_failure(error: &str) -> Option<DomainFailure> {
+    let lowered = error.to_lowercase();
+    if DOMAIN_NOT_FOUND_MARKERS.iter().any(|marker| lowered.contains(marker)) {
+        return Some(DomainFailure::NotFound);
+    }
+    if lowered.contains("permanent error") || has_permanent_code(&lowered) {
+        return Some(DomainFailure::HardBounce);
+    }
+    None
+}
+
+/// 检查文本中是否出现独立的 5xx 响应码。
+fn has_permanent_code(text: &str) -> bool {
+    let bytes = text.as_bytes();
+    bytes.windows(3).enumerate().any(|(start, window)| {
+        let before = start.checked_sub(1).map(|index| bytes[index]);
+        let after = bytes.get(start + 3).copied();
+        window[0] == b'5'
+            && window[1].is_ascii_digit()
+            && window[2].is_ascii_digit()
+            && !before.is_some_and(|byte| byte.is_ascii_digit() || byte == b'.')
+            && !after.is_some_and(|byte| byte.is_ascii_digit() || byte == b'.')
+    })
+}
+
+#[derive(Deserialize, Serialize, Clone, Default)]
+pub struct DomainRecord {
+    #[serde(default)]
+    pub hard_bounces: u32,
+    #[serde(default)]
+    pub not_found: u32,
+    #[serde(default)]
+    pub last_error: String,
+    #[serde(default)]
+    pub last_seen: String,
+}
+
+impl DomainRecord {
+    pub fn reason(&self) -> Option<&'static str> {
+        if self.not_found > 0 {
+            Some("domain_not_found")
+        } else if self.hard_bounces >= HARD_BOUNCE_THRESHOLD {
+            Some("repeated_hard_bounce")
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct DeadDomainSummary {
+    pub domain: String,
+    pub reason: &'static str,
+    pub hard_bounces: u32,
+    pub not_found: u32,
+    pub last_error: String,
+    pub last_seen: String,
+}
+
+#[derive(Serialize)]
+pub struct FlaggedRecipient {
+    pub email: String,
+    pub name: String,
+    pub domain: String,
+    pub reason: &'static str,
+}
+
+/// 发送前检查结果：被标记的收件人，以及排除它们之后可直接使用的列表。
+#[derive(Serialize)]
+pub struct DeadDomainReport {
+    pub flagged: Vec<FlaggedRecipient>,
+    pub domains: Vec<String>,
+    pub kept: Vec<RecipientEntry>,
+}
+
+/// 按域名累计硬退信与“域名不存在”结果；成功投递会清除该域名的记录。
+#[derive(Deserialize, Serialize, Default)]
+pub struct DeadDomainStore {
+    #[serde(default)]
+    pub domains: BTreeMap<String, DomainRecord>,
+}
+
+impl DeadDomainStore {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        if !path.exists() {
+            return Ok(DeadDomainStore::default());
+        }
+        let text = fs::read_to_string(path).map_err(|err| format!("读取失效域名记录失败: {err}"))?;
+        serde_json::from_str(&text).map_err(|err| format!("失效域名记录格式错误: {err}"))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let text = serde_json::to_string_pretty(self).map_err(|err| err.to_string())?;
+        fs::write(path, text).map_err(|err| format!("写入失效域名记录失败: {err}"))
+    }
+
+    /// 记录一次失败；返回该失败是否被计入（与域名无关的错误会被忽略）。
+    pub fn record_failure(&mut self, email: &str, error: &str) -> bool {
+        let domain = recipient_domain(email);
+        let Some(kind) = classify_failure(error).filter(|_| !domain.is_empty()) else {
+            return false;
+        };
+        let record = self.domains.entry(domain).or_default();
+        match kind {
+            DomainFailure::NotFound => record.not_found += 1,
+            DomainFailure::HardBounce => record.hard_bounces += 1,
+        }
+        record.last_error = error.to_string();
+        record.last_seen = Local::now().to_rfc3339_opts(SecondsFormat::Secs, false);
+        true
+    }
+
+    pub fn record_success(&mut self, email: &str) -> bool {
+        self.domains.remove(&recipient_domain(email)).is_some()
+    }
+
+    pub fn forget(&mut self, domain: &str) -> bool {
+        self.domains.remove(&domain.trim().to_lowercase()).is_some()
+    }
+
+    pub fn dead_domains(&self) -> Vec<DeadDomainSummary> {
+        self.domains
+            .iter()
+            .filter_map(|(domain, record)| {
+                Some(DeadDomainSummary {
+                    domain: domain.clone(),
+                    reason: record.reason()?,
+                    hard_bounces: record.hard_bounces,
+                    not_found: record.not_found,
+                    last_error: record.last_error.clone(),
+                    last_seen: record.last_seen.clone(),
+                })
+            })
+            .collect()
+    }
+
+    pub fn preflight(&self, recipients: Vec<RecipientEntry>) -> DeadDomainReport {
+        let mut flagged = Vec::new();
+        let mut domains: Vec<String> = Vec::new();
+        let mut kept = Vec::new();
+        for recipient in recipients {
+            let domain = recipient_domain(&recipient.email);
+            match self.domains.get(&domain).and_then(DomainRecord::reason) {
+                Some(reason) => {
+                    if !domains.contains(&domain) {
+                        domains.push(domain.clone());
+                    }
+                    flagged.push(FlaggedRecipient {
+                        email: recipient.email,
+                        name: recipient.name,
+                        domain,
+                        reason,
+                    });
+                }
+                None => kept.push(recipient),
+            }
+        }
+        DeadDomainReport { flagged, domains, kept }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{classify_failure, DeadDomainStore, DomainFailure, HARD_BOUNCE_THRESHOLD};
+    use crate::engine::RecipientEntry;
+
+    #[test]
+    fn classifies_domain_level_failures() {
+        assert_eq!(
+            classify_failure("{'a@gone.example': (550, b'5.1.2 Domain not found')}"),
+            Some(DomainFailure::NotFound)
+        );
+        assert_eq!(
+            classify_failure("permanent error (550): 5.1.1 user unknown"),
+            Some(DomainFailure::HardBounce)
+        );
+        assert_eq!(classify_failure("451 4.7.1 greylisted, try again later"), None);
+        assert_eq!(classify_failure("connection timed out after 5000 ms"), None);
+    }
+
+    #[test]
+    fn flags_recipients_after_repeated_hard_bounces() {
+        let mut store = DeadDomainStore::default();
+        for _ in 0..HARD_BOUNCE_THRESHOLD {
+            assert!(store.record_failure("x@bounce.example", "550 mailbox unavailable"));
+        }
+        store.record_failure("y@ok.example", "550 mailbox unavailable");
+
+        let report = store.preflight(vec![
+            RecipientEntry {
+                email: "a@bounce.example".to_string(),
+                name: "A".to_string(),
+                ..RecipientEntry::default()
+            },
+            RecipientEntry {
+                email: "b@ok.example".to_string(),
+                name: "B".to_string(),
+                ..RecipientEntry::default()
+            },
+        ]);
+        assert_eq!(report.domains, vec!["bounce.example".to_string()]);
+        assert_eq!(report.flagged.len(), 1);
+        assert_eq!(report.kept.len(), 1);
+        assert_eq!(report.kept[0].email, "b@ok.example");
+
+        assert!(store.record_success("z@bounce.example"));
+        assert!(store.dead_domains().is_empty());
+    }
+}
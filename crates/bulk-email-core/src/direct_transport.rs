@@ -0,0 +1,175 @@
+use crate::dsn::{message_envelope_id, DsnOptions};
+use crate::smtp_auth::{authenticate, offered_mechanisms, AuthMechanism, AuthProbe};
+use crate::smtp_client::{
+    tls_parameters, MailTransport, ProbeFailure, ProbeStage, SendFailure, ServerCapabilities, SmtpPayload, SmtpProbe,
+};
+use lettre::address::Envelope;
+use lettre::transport::smtp::client::{SmtpConnection, TlsParameters};
+use lettre::transport::smtp::commands::{Data, Ehlo, Mail, Noop, Rcpt};
+use lettre::transport::smtp::extension::{ClientId, Extension, MailBodyParameter, MailParameter};
+use lettre::transport::smtp::response::Response;
+use lettre::transport::smtp::Error;
+use lettre::{Address, Message};
+use std::time::Duration;
+
+/// 自行维护一条 SMTP 连接的投递通道：lettre 的连接池不能附加 MAIL / RCPT 参数（DSN），
+/// 也不支持 CRAM-MD5、NTLM 认证。服务器未声明 DSN 时照常投递，不附加任何参数。
+pub struct DirectTransport {
+    host: String,
+    port: u16,
+    tls: Option<TlsParameters>,
+    implicit_tls: bool,
+    hello: ClientId,
+    username: String,
+    password: String,
+    auth_mechanism: Option<AuthMechanism>,
+    timeout: Duration,
+    options: Option<DsnOptions>,
+    connection: Option<(SmtpConnection, bool)>,
+}
+
+impl DirectTransport {
+    pub fn new(payload: &SmtpPayload, options: Option<DsnOptions>) -> Result<Self, String> {
+        Ok(DirectTransport {
+            host: payload.host.clone(),
+            port: payload.port,
+            tls: tls_parameters(payload)?,
+            implicit_tls: payload.use_ssl,
+            hello: payload.client_id()?.unwrap_or_default(),
+            username: payload.username.clone(),
+            password: payload.password.clone(),
+            auth_mechanism: payload.auth_mechanism,
+            timeout: Duration::from_secs(payload.timeout_sec.into()),
+            options,
+            connection: None,
+        })
+    }
+
+    /// 建立连接（含 STARTTLS），返回连接与 TLS 之后的 EHLO 响应。
+    fn open(&self) -> Result<(SmtpConnection, Response), Error> {
+        let wrapper = self.tls.as_ref().filter(|_| self.implicit_tls);
+        let mut connection =
+            SmtpConnection::connect((self.host.as_str(), self.port), Some(self.timeout), &self.hello, wrapper, None)?;
+        if let Some(tls) = self.tls.as_ref().filter(|_| !self.implicit_tls) {
+            connection.starttls(tls, &self.hello)?;
+        }
+        // lettre 只解析它认识的扩展，这里重新 EHLO 读取 DSN 与 AUTH 关键字。
+        let ehlo = connection.command(Ehlo::new(self.hello.clone()))?;
+        Ok((connection, ehlo))
+    }
+
+    fn authenticate(&self, connection: &mut SmtpConnection, ehlo: &Response) -> Result<AuthProbe, SendFailure> {
+        let offered_mechanisms = offered_mechanisms(ehlo);
+        let auth_mechanism = authenticate(
+            connection,
+            &offered_mechanisms,
+            self.auth_mechanism,
+            &self.username,
+            &self.password,
+        )?;
+        Ok(AuthProbe { offered_mechanisms, auth_mechanism })
+    }
+
+    /// 建立连接并认证，返回连接及服务器是否声明了 DSN。
+    fn connect(&self) -> Result<(SmtpConnection, bool), SendFailure> {
+        let (mut connection, ehlo) = self.open()?;
+        let advertised = ehlo
+            .message()
+            .any(|line| line.split_whitespace().next().is_some_and(|keyword| keyword.eq_ignore_ascii_case("DSN")));
+        if let Err(err) = self.authenticate(&mut connection, &ehlo) {
+            connection.abort();
+            return Err(err);
+        }
+        Ok((connection, advertised))
+    }
+
+    /// 连接并认证，再发送 NOOP 确认认证后的会话可用，随后断开；报告认证方式与服务器声明的扩展。
+    /// 失败时区分连接（含 TLS）阶段与认证阶段。
+    pub fn probe(&self) -> Result<SmtpProbe, ProbeFailure> {
+        let (mut connection, ehlo) = self.open().map_err(|err| ProbeFailure {
+            stage: ProbeStage::Connect,
+            message: err.to_string(),
+        })?;
+        let result = self.authenticate(&mut connection, &ehlo).and_then(|probe| {
+            connection.command(Noop)?;
+            Ok(probe)
+        });
+        match result {
+            Ok(auth) => {
+                let _ = connection.quit();
+                let mut capabilities = ServerCapabilities::from_ehlo(&ehlo);
+                capabilities.starttls |= self.tls.is_some() && !self.implicit_tls;
+                Ok(SmtpProbe {
+                    auth,
+                    capabilities,
+                    tls_probe: None,
+                })
+            }
+            Err(failure) => {
+                connection.abort();
+                Err(ProbeFailure {
+                    stage: ProbeStage::Auth,
+                    message: failure.message,
+                })
+            }
+        }
+    }
+
+    fn deliver(&mut self, envelope: &Envelope, body: &[u8]) -> Result<(), SendFailure> {
+        let reusable = self
+            .connection
+            .as_mut()
+            .is_some_and(|(connection, _)| connection.test_connected());
+        let mut current = match self.connection.take() {
+            Some(current) if reusable => current,
+            _ => self.connect()?,
+        };
+        let result = self.transfer(&mut current, envelope, body);
+        match result {
+            Ok(()) => self.connection = Some(current),
+            Err(_) => current.0.abort(),
+        }
+        result.map_err(SendFailure::from)
+    }
+
+    fn transfer(&self, current: &mut (SmtpConnection, bool), envelope: &Envelope, body: &[u8]) -> Result<(), Error> {
+        let (connection, advertised) = current;
+        let mut mail_parameters = Vec::new();
+        let non_ascii_addresses = envelope
+            .from()
+            .into_iter()
+            .chain(envelope.to())
+            .any(|address: &Address| !str::is_ascii(address.as_ref()));
+        if non_ascii_addresses && connection.server_info().supports_feature(Extension::SmtpUtfEight) {
+            mail_parameters.push(MailParameter::SmtpUtfEight);
+        }
+        if !body.is_ascii() && connection.server_info().supports_feature(Extension::EightBitMime) {
+            mail_parameters.push(MailParameter::Body(MailBodyParameter::EightBitMime));
+        }
+        let rcpt_parameters = match self.options.as_ref().filter(|_| *advertised) {
+            Some(options) => {
+                let envid = message_envelope_id(body);
+                mail_parameters.extend(options.mail_parameters(envid.as_deref()));
+                options.rcpt_parameters()
+            }
+            None => Vec::new(),
+        };
+        connection.command(Mail::new(envelope.from().cloned(), mail_parameters))?;
+        for address in envelope.to() {
+            connection.command(Rcpt::new(address.clone(), rcpt_parameters.clone()))?;
+        }
+        connection.command(Data)?;
+        connection.message(body)?;
+        Ok(())
+    }
+}
+
+impl MailTransport for DirectTransport {
+    fn send_message(&mut self, message: &Message) -> Result<(), SendFailure> {
+        self.send_raw(message.envelope(), &message.formatted())
+    }
+
+    fn send_raw(&mut self, envelope: &Envelope, body: &[u8]) -> Result<(), SendFailure> {
+        self.deliver(envelope, body)
+    }
+}
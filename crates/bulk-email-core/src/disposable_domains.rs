@@ -0,0 +1,169 @@
+use crate::engine::RecipientEntry;
+use crate::greylist::recipient_domain;
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+
+/// 随程序发布的一次性邮箱域名列表。
+pub const BUNDLED_DISPOSABLE_DOMAINS: &str = include_str!("../data/disposable_domains.txt");
+/// 默认的更新来源：社区维护的 disposable-email-domains 黑名单。
+pub const DEFAULT_DISPOSABLE_DOMAINS_URL: &str =
+    "https://raw.githubusercontent.com/disposable-email-domains/disposable-email-domains/main/disposable_email_blocklist.conf";
+
+#[derive(Serialize)]
+pub struct DisposableRecipient {
+    /// 在提交列表中的序号（从 1 开始）。
+    pub row: usize,
+    pub email: String,
+    pub name: String,
+    pub domain: String,
+}
+
+/// 检查结果：使用一次性邮箱的收件人，以及排除它们之后可直接使用的列表。
+#[derive(Serialize)]
+pub struct DisposableReport {
+    pub flagged: Vec<DisposableRecipient>,
+    pub domains: Vec<String>,
+    pub kept: Vec<RecipientEntry>,
+}
+
+/// 一次性邮箱（mailinator、10minutemail 等）域名集合；列表中域名的子域名同样命中。
+pub struct DisposableDomains {
+    domains: BTreeSet<String>,
+}
+
+impl DisposableDomains {
+    pub fn bundled() -> Self {
+        DisposableDomains::parse(BUNDLED_DISPOSABLE_DOMAINS)
+    }
+
+    /// 读取更新后的列表；文件不存在时使用内置列表。
+    pub fn load(path: &Path) -> Result<Self, String> {
+        if !path.exists() {
+            return Ok(DisposableDomains::bundled());
+        }
+        let text = fs::read_to_string(path).map_err(|err| format!("读取一次性邮箱域名列表失败: {err}"))?;
+        Ok(DisposableDomains::parse(&text))
+    }
+
+    /// 每行一个域名，忽略空行与 `#` / `//` 注释。
+    pub fn parse(text: &str) -> Self {
+        let domains = text
+            .lines()
+            .map(|line| line.trim().trim_start_matches("*.").trim_start_matches('.').to_lowercase())
+            .filter(|line| !line.is_empty() && !line.starts_with('#') && !line.starts_with("//"))
+            .collect();
+        DisposableDomains { domains }
+    }
+
+    /// 校验下载到的列表：必须是纯文本域名列表，避免把错误页面当作列表保存。
+    pub fn parse_download(text: &str) -> Result<Self, String> {
+        let list = DisposableDomains::parse(text);
+        if list.is_empty() {
+            return Err("下载的一次性邮箱域名列表为空".to_string());
+        }
+        if let Some(line) = list.domains.iter().find(|domain| !is_domain_line(domain)) {
+            return Err(format!("一次性邮箱域名列表格式错误: {line}"));
+        }
+        Ok(list)
+    }
+
+    pub fn len(&self) -> usize {
+        self.domains.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.domains.is_empty()
+    }
+
+    /// 返回命中的列表域名（收件域名本身或其上级域名）。
+    pub fn matching(&self, domain: &str) -> Option<&str> {
+        let domain = domain.trim().trim_end_matches('.').to_lowercase();
+        let mut candidate = domain.as_str();
+        loop {
+            if let Some(found) = self.domains.get(candidate) {
+                return Some(found);
+            }
+            candidate = candidate.split_once('.')?.1;
+        }
+    }
+
+    pub fn contains(&self, email: &str) -> bool {
+        self.matching(&recipient_domain(email)).is_some()
+    }
+
+    pub fn check(&self, recipients: Vec<RecipientEntry>) -> DisposableReport {
+        let mut flagged = Vec::new();
+        let mut domains: Vec<String> = Vec::new();
+        let mut kept = Vec::new();
+        for (index, recipient) in recipients.into_iter().enumerate() {
+            let domain = recipient_domain(&recipient.email);
+            if self.matching(&domain).is_none() {
+                kept.push(recipient);
+                continue;
+            }
+            if !domains.contains(&domain) {
+                domains.push(domain.clone());
+            }
+            flagged.push(DisposableRecipient {
+                row: index + 1,
+                email: recipient.email,
+                name: recipient.name,
+                domain,
+            });
+        }
+        DisposableReport { flagged, domains, kept }
+    }
+
+    /// 按行写出，供更新后保存。
+    pub fn to_text(&self) -> String {
+        let mut text = String::with_capacity(self.domains.len() * 16);
+        for domain in &self.domains {
+            text.push_str(domain);
+            text.push('\n');
+        }
+        text
+    }
+}
+
+fn is_domain_line(line: &str) -> bool {
+    line.contains('.')
+        && line
+            .split('.')
+            .all(|label| !label.is_empty() && label.chars().all(|ch| ch.is_alphanumeric() || ch == '-'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DisposableDomains;
+    use crate::engine::RecipientEntry;
+
+    #[test]
+    fn flags_disposable_domains_and_subdomains() {
+        let list = DisposableDomains::bundled();
+        assert!(list.len() > 50);
+        assert!(list.contains("someone@Mailinator.com"));
+        assert!(list.contains("x@inbox.yopmail.com"));
+        assert!(!list.contains("prof@pku.edu.cn"));
+
+        let recipients = ["a@pku.edu.cn", "b@mailinator.com", "c@guerrillamail.com", "d@mailinator.com"]
+            .map(|email| RecipientEntry {
+                email: email.to_string(),
+                ..RecipientEntry::default()
+            });
+        let report = list.check(recipients.to_vec());
+        assert_eq!(report.flagged.len(), 3);
+        assert_eq!(report.flagged[1].row, 3);
+        assert_eq!(report.domains, vec!["mailinator.com", "guerrillamail.com"]);
+        assert_eq!(report.kept.len(), 1);
+    }
+
+    #[test]
+    fn validates_downloaded_lists() {
+        let list = DisposableDomains::parse_download("# header\n*.Throwaway.example\nmailinator.com\n\n").expect("list");
+        assert_eq!(list.to_text(), "mailinator.com\nthrowaway.example\n");
+        assert!(DisposableDomains::parse_download("<html><body>404</body></html>").is_err());
+        assert!(DisposableDomains::parse_download("# only comments\n").is_err());
+    }
+}
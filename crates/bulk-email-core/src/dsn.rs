@@ -0,0 +1,154 @@
+use lettre::transport::smtp::extension::{MailParameter, RcptParameter};
+use serde::{Deserialize, Serialize};
+
+/// RFC 3461：ENVID 最长 100 个字符。
+const MAX_ENVID_LEN: usize = 100;
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DsnNotify {
+    Success,
+    Failure,
+    Delay,
+    Never,
+}
+
+impl DsnNotify {
+    fn keyword(self) -> &'static str {
+        match self {
+            DsnNotify::Success => "SUCCESS",
+            DsnNotify::Failure => "FAILURE",
+            DsnNotify::Delay => "DELAY",
+            DsnNotify::Never => "NEVER",
+        }
+    }
+}
+
+/// 退信报告中附带原邮件的范围：完整邮件或仅邮件头。
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DsnReturn {
+    Full,
+    Headers,
+}
+
+/// 投递状态通知（DSN）请求；仅在服务器 EHLO 声明 DSN 时附加到 MAIL FROM / RCPT TO。
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct DsnOptions {
+    #[serde(default = "default_notify")]
+    pub notify: Vec<DsnNotify>,
+    /// 为空时由服务器决定退回的内容。
+    #[serde(default)]
+    pub ret: Option<DsnReturn>,
+    /// 以 Message-ID 作为 ENVID，退信报告可据此关联发送记录。
+    #[serde(default = "default_envid")]
+    pub envid: bool,
+}
+
+fn default_notify() -> Vec<DsnNotify> {
+    vec![DsnNotify::Failure, DsnNotify::Delay]
+}
+
+fn default_envid() -> bool {
+    true
+}
+
+impl DsnOptions {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.notify.is_empty() {
+            return Err("DSN 至少需要选择一种通知条件".to_string());
+        }
+        if self.notify.contains(&DsnNotify::Never) && self.notify.len() > 1 {
+            return Err("DSN 的 never 不能与其他通知条件同时使用".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn mail_parameters(&self, envid: Option<&str>) -> Vec<MailParameter> {
+        let mut parameters = Vec::new();
+        if let Some(ret) = self.ret {
+            let value = match ret {
+                DsnReturn::Full => "FULL",
+                DsnReturn::Headers => "HDRS",
+            };
+            parameters.push(MailParameter::Other {
+                keyword: "RET".to_string(),
+                value: Some(value.to_string()),
+            });
+        }
+        if let Some(envid) = envid.filter(|_| self.envid) {
+            parameters.push(MailParameter::Other {
+                keyword: "ENVID".to_string(),
+                value: Some(envid.to_string()),
+            });
+        }
+        parameters
+    }
+
+    pub fn rcpt_parameters(&self) -> Vec<RcptParameter> {
+        let notify: Vec<&str> = self.notify.iter().map(|notify| notify.keyword()).collect();
+        vec![RcptParameter::Other {
+            keyword: "NOTIFY".to_string(),
+            value: Some(notify.join(",")),
+        }]
+    }
+}
+
+/// 从 Message-ID 推导 ENVID：去掉尖括号并截断到 RFC 3461 的长度上限。
+pub fn envelope_id(message_id: &str) -> String {
+    message_id
+        .trim()
+        .trim_start_matches('<')
+        .trim_end_matches('>')
+        .chars()
+        .take(MAX_ENVID_LEN)
+        .collect()
+}
+
+/// 从已序列化邮件头部的 Message-ID 推导 ENVID；没有 Message-ID 时返回 None。
+pub fn message_envelope_id(body: &[u8]) -> Option<String> {
+    raw_message_id(body).map(|message_id| envelope_id(&message_id))
+}
+
+/// 在已序列化邮件的头部中查找 Message-ID。
+fn raw_message_id(body: &[u8]) -> Option<String> {
+    let text = String::from_utf8_lossy(body);
+    text.split("\r\n")
+        .take_while(|line| !line.is_empty())
+        .find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            name.trim().eq_ignore_ascii_case("message-id").then(|| value.trim().to_string())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{envelope_id, raw_message_id, DsnNotify, DsnOptions, DsnReturn};
+
+    #[test]
+    fn builds_dsn_parameters() {
+        let options: DsnOptions = serde_json::from_value(serde_json::json!({ "ret": "headers" })).expect("options");
+        assert!(options.validate().is_ok());
+        let mail: Vec<String> = options
+            .mail_parameters(Some("job-1.abc@example.edu"))
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(mail, ["RET=HDRS", "ENVID=job-1.abc@example.edu"]);
+        let rcpt: Vec<String> = options.rcpt_parameters().iter().map(ToString::to_string).collect();
+        assert_eq!(rcpt, ["NOTIFY=FAILURE,DELAY"]);
+
+        let never = DsnOptions {
+            notify: vec![DsnNotify::Never, DsnNotify::Failure],
+            ret: Some(DsnReturn::Full),
+            envid: false,
+        };
+        assert!(never.validate().is_err());
+        assert_eq!(never.mail_parameters(Some("id@example.edu")).len(), 1);
+
+        let raw = b"From: a@example.edu\r\nMessage-ID: <job-1.abc@example.edu>\r\n\r\nMessage-ID: body";
+        assert_eq!(raw_message_id(raw).as_deref(), Some("<job-1.abc@example.edu>"));
+        assert_eq!(envelope_id("<job-1.abc@example.edu>"), "job-1.abc@example.edu");
+        assert_eq!(envelope_id(&format!("<{}@example.edu>", "x".repeat(120))).len(), 100);
+    }
+}
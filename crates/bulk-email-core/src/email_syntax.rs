@@ -0,0 +1,465 @@
+use crate::engine::RecipientEntry;
+use serde::{Deserialize, Serialize};
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+/// RFC 5321 4.5.3.1：本地部分最长 64 个八位组，域名 255，整个路径（不含尖括号）254。
+const MAX_LOCAL_LEN: usize = 64;
+const MAX_DOMAIN_LEN: usize = 255;
+const MAX_ADDRESS_LEN: usize = 254;
+const MAX_LABEL_LEN: usize = 63;
+/// RFC 5322 atext 中除字母数字外允许的字符。
+const ATEXT_SYMBOLS: &str = "!#$%&'*+-/=?^_`{|}~";
+/// 角色邮箱（多人共用或无人阅读的职能地址），许多邮件服务商的使用政策禁止向它们群发。
+pub const DEFAULT_ROLE_PREFIXES: [&str; 18] = [
+    "abuse",
+    "admin",
+    "administrator",
+    "billing",
+    "contact",
+    "help",
+    "hostmaster",
+    "info",
+    "mailer-daemon",
+    "marketing",
+    "no-reply",
+    "noreply",
+    "office",
+    "postmaster",
+    "root",
+    "sales",
+    "support",
+    "webmaster",
+];
+/// 常见邮箱服务商域名，用于发现 `gmial.com`、`163.con` 一类的拼写错误。
+const COMMON_DOMAINS: [&str; 28] = [
+    "126.com",
+    "139.com",
+    "163.com",
+    "189.cn",
+    "aliyun.com",
+    "aol.com",
+    "foxmail.com",
+    "gmail.com",
+    "gmx.com",
+    "googlemail.com",
+    "hotmail.com",
+    "icloud.com",
+    "live.com",
+    "mail.com",
+    "me.com",
+    "msn.com",
+    "outlook.com",
+    "proton.me",
+    "protonmail.com",
+    "qq.com",
+    "sina.cn",
+    "sina.com",
+    "sohu.com",
+    "tom.com",
+    "vip.qq.com",
+    "yahoo.com",
+    "yandex.com",
+    "yeah.net",
+];
+/// 常见顶级域拼写错误；`.cm`、`.co` 是真实存在的国家域名，不在此列。
+const TLD_TYPOS: [(&str, &str); 9] = [
+    ("con", "com"),
+    ("cmo", "com"),
+    ("ocm", "com"),
+    ("comm", "com"),
+    ("cpm", "com"),
+    ("vom", "com"),
+    ("xom", "com"),
+    ("ner", "net"),
+    ("ogr", "org"),
+];
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct ValidationSettings {
+    /// 视为角色邮箱的用户名（`@` 前部分，忽略大小写与 `+` 标签）。
+    pub role_prefixes: Vec<String>,
+}
+
+impl Default for ValidationSettings {
+    fn default() -> Self {
+        ValidationSettings {
+            role_prefixes: DEFAULT_ROLE_PREFIXES.map(str::to_string).to_vec(),
+        }
+    }
+}
+
+impl ValidationSettings {
+    /// 是否为角色邮箱；`support+cn@` 与 `Support@` 同样命中。
+    pub fn is_role_account(&self, email: &str) -> bool {
+        let Some((local, _)) = email.trim().rsplit_once('@') else {
+            return false;
+        };
+        let local = local.split('+').next().unwrap_or_default().to_lowercase();
+        self.role_prefixes
+            .iter()
+            .any(|prefix| !prefix.trim().is_empty() && prefix.trim().to_lowercase() == local)
+    }
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Verdict {
+    Valid,
+    /// 语法上合法，但实际投递中很少见，多半是录入错误。
+    Suspicious,
+    Invalid,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct RecipientVerdict {
+    /// 在提交列表中的序号（从 1 开始）。
+    pub row: usize,
+    pub email: String,
+    pub name: String,
+    pub verdict: Verdict,
+    pub reasons: Vec<String>,
+    pub role_account: bool,
+    /// 域名疑似拼写错误时建议的完整地址。
+    pub suggestion: Option<String>,
+}
+
+/// 逐行检查结果；`kept` 为排除无效地址后的收件人（可疑地址保留，由用户决定）。
+#[derive(Serialize)]
+pub struct RecipientValidationReport {
+    pub results: Vec<RecipientVerdict>,
+    pub valid: usize,
+    pub suspicious: usize,
+    pub invalid: usize,
+    pub role_accounts: usize,
+    pub kept: Vec<RecipientEntry>,
+}
+
+/// 语法有效的角色邮箱（`info@`、`noreply@` 等）标记为可疑，由用户决定是否排除。
+pub fn validate_recipients(recipients: Vec<RecipientEntry>, settings: &ValidationSettings) -> RecipientValidationReport {
+    let mut report = RecipientValidationReport {
+        results: Vec::with_capacity(recipients.len()),
+        valid: 0,
+        suspicious: 0,
+        invalid: 0,
+        role_accounts: 0,
+        kept: Vec::new(),
+    };
+    for (index, recipient) in recipients.into_iter().enumerate() {
+        let (mut verdict, mut reasons) = check_address(&recipient.email);
+        let role_account = verdict != Verdict::Invalid && settings.is_role_account(&recipient.email);
+        if role_account {
+            report.role_accounts += 1;
+            reasons.push("角色邮箱（info@、noreply@ 等），许多服务商政策禁止群发".to_string());
+            verdict = Verdict::Suspicious;
+        }
+        let suggestion = (verdict != Verdict::Invalid)
+            .then(|| suggest_address(&recipient.email))
+            .flatten();
+        if let Some(suggested) = &suggestion {
+            reasons.push(format!("域名可能拼写错误，是否为 {suggested}？"));
+            verdict = Verdict::Suspicious;
+        }
+        match verdict {
+            Verdict::Valid => report.valid += 1,
+            Verdict::Suspicious => report.suspicious += 1,
+            Verdict::Invalid => report.invalid += 1,
+        }
+        if verdict != Verdict::Invalid {
+            report.kept.push(recipient.clone());
+        }
+        report.results.push(RecipientVerdict {
+            row: index + 1,
+            email: recipient.email,
+            name: recipient.name,
+            verdict,
+            reasons,
+            role_account,
+            suggestion,
+        });
+    }
+    report
+}
+
+/// 按 RFC 5321 / 5322 的 addr-spec 检查单个地址（不接受注释与折叠空白）。
+pub fn check_address(raw: &str) -> (Verdict, Vec<String>) {
+    let mut invalid = Vec::new();
+    let mut suspicious = Vec::new();
+    let address = raw.trim();
+    if address.is_empty() {
+        return (Verdict::Invalid, vec!["邮箱为空".to_string()]);
+    }
+    if address.len() != raw.len() {
+        suspicious.push("首尾有空白字符".to_string());
+    }
+    if address.chars().any(char::is_control) {
+        invalid.push("包含控制字符".to_string());
+    }
+    if address.len() > MAX_ADDRESS_LEN {
+        invalid.push(format!("地址长度超过 {MAX_ADDRESS_LEN} 个字符"));
+    }
+    let Some((local, domain)) = split_address(address) else {
+        return (Verdict::Invalid, vec!["缺少 @ 或 @ 位置不正确".to_string()]);
+    };
+    check_local_part(local, &mut invalid, &mut suspicious);
+    check_domain(domain, &mut invalid, &mut suspicious);
+    if !address.is_ascii() && invalid.is_empty() {
+        suspicious.push("包含非 ASCII 字符，需要服务器支持 SMTPUTF8".to_string());
+    }
+    if !invalid.is_empty() {
+        (Verdict::Invalid, invalid)
+    } else if !suspicious.is_empty() {
+        (Verdict::Suspicious, suspicious)
+    } else {
+        (Verdict::Valid, Vec::new())
+    }
+}
+
+/// 域名与常见服务商只差一两个字符（或顶级域明显拼错）时，返回改正后的地址。
+pub fn suggest_address(email: &str) -> Option<String> {
+    let address = email.trim();
+    let (local, domain) = address.rsplit_once('@')?;
+    let domain = domain.to_lowercase();
+    if COMMON_DOMAINS.contains(&domain.as_str()) {
+        return None;
+    }
+    let name_len = domain.split('.').next().unwrap_or_default().chars().count();
+    let max_distance = if name_len <= 5 { 1 } else { 2 };
+    let mut best: Option<(&str, usize)> = None;
+    let mut tied = false;
+    for candidate in COMMON_DOMAINS {
+        let distance = edit_distance(&domain, candidate);
+        if distance > max_distance {
+            continue;
+        }
+        match best {
+            Some((_, best_distance)) if distance > best_distance => {}
+            Some((_, best_distance)) if distance == best_distance => tied = true,
+            _ => {
+                best = Some((candidate, distance));
+                tied = false;
+            }
+        }
+    }
+    let corrected = match best {
+        Some((candidate, _)) if !tied => candidate.to_string(),
+        _ => {
+            let (name, tld) = domain.rsplit_once('.')?;
+            let (_, fixed) = TLD_TYPOS.iter().find(|(typo, _)| *typo == tld)?;
+            format!("{name}.{fixed}")
+        }
+    };
+    Some(format!("{local}@{corrected}"))
+}
+
+/// 编辑距离（含相邻字符换位），`gmial` 与 `gmail` 相差 1。
+fn edit_distance(left: &str, right: &str) -> usize {
+    let left: Vec<char> = left.chars().collect();
+    let right: Vec<char> = right.chars().collect();
+    let mut rows = vec![vec![0usize; right.len() + 1]; left.len() + 1];
+    for (index, row) in rows.iter_mut().enumerate() {
+        row[0] = index;
+    }
+    for (index, cell) in rows[0].iter_mut().enumerate() {
+        *cell = index;
+    }
+    for i in 1..=left.len() {
+        for j in 1..=right.len() {
+            let cost = usize::from(left[i - 1] != right[j - 1]);
+            let mut distance = (rows[i - 1][j] + 1).min(rows[i][j - 1] + 1).min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && left[i - 1] == right[j - 2] && left[i - 2] == right[j - 1] {
+                distance = distance.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = distance;
+        }
+    }
+    rows[left.len()][right.len()]
+}
+
+/// 以最后一个不在引号内的 `@` 拆分；带引号的本地部分可以包含 `@`。
+fn split_address(address: &str) -> Option<(&str, &str)> {
+    let mut in_quotes = false;
+    let mut escaped = false;
+    let mut split = None;
+    for (index, ch) in address.char_indices() {
+        match ch {
+            _ if escaped => escaped = false,
+            '\\' if in_quotes => escaped = true,
+            '"' => in_quotes = !in_quotes,
+            '@' if !in_quotes => {
+                if split.is_some() {
+                    return None;
+                }
+                split = Some(index);
+            }
+            _ => {}
+        }
+    }
+    let index = split?;
+    Some((&address[..index], &address[index + 1..]))
+}
+
+fn check_local_part(local: &str, invalid: &mut Vec<String>, suspicious: &mut Vec<String>) {
+    if local.is_empty() {
+        invalid.push("@ 前的用户名为空".to_string());
+        return;
+    }
+    if local.len() > MAX_LOCAL_LEN {
+        invalid.push(format!("用户名超过 {MAX_LOCAL_LEN} 个字符"));
+    }
+    if local.starts_with('"') {
+        if local.len() < 2 || !local.ends_with('"') {
+            invalid.push("带引号的用户名未闭合".to_string());
+        } else {
+            suspicious.push("用户名带引号，很少有服务器接受".to_string());
+        }
+        return;
+    }
+    if local.starts_with('.') || local.ends_with('.') || local.contains("..") {
+        invalid.push("用户名不能以点开头或结尾，也不能包含连续的点".to_string());
+    }
+    let illegal: String = local
+        .chars()
+        .filter(|ch| ch.is_ascii() && !(ch.is_ascii_alphanumeric() || *ch == '.' || ATEXT_SYMBOLS.contains(*ch)))
+        .collect();
+    if !illegal.is_empty() {
+        invalid.push(format!("用户名包含非法字符: {illegal}"));
+    }
+}
+
+fn check_domain(domain: &str, invalid: &mut Vec<String>, suspicious: &mut Vec<String>) {
+    if domain.is_empty() {
+        invalid.push("@ 后的域名为空".to_string());
+        return;
+    }
+    if domain.len() > MAX_DOMAIN_LEN {
+        invalid.push(format!("域名超过 {MAX_DOMAIN_LEN} 个字符"));
+    }
+    if let Some(literal) = domain.strip_prefix('[') {
+        let Some(literal) = literal.strip_suffix(']') else {
+            invalid.push("域名的 IP 地址字面量未闭合".to_string());
+            return;
+        };
+        let parsed = match literal.strip_prefix("IPv6:") {
+            Some(ipv6) => ipv6.parse::<Ipv6Addr>().is_ok(),
+            None => literal.parse::<Ipv4Addr>().is_ok(),
+        };
+        if parsed {
+            suspicious.push("域名是 IP 地址字面量".to_string());
+        } else {
+            invalid.push(format!("IP 地址字面量无效: [{literal}]"));
+        }
+        return;
+    }
+    let labels: Vec<&str> = domain.split('.').collect();
+    for label in &labels {
+        if label.is_empty() {
+            invalid.push("域名包含空的标签（多余或连续的点）".to_string());
+            return;
+        }
+        if label.len() > MAX_LABEL_LEN {
+            invalid.push(format!("域名标签超过 {MAX_LABEL_LEN} 个字符: {label}"));
+        }
+        if label.starts_with('-') || label.ends_with('-') {
+            invalid.push(format!("域名标签不能以连字符开头或结尾: {label}"));
+        }
+        if label.chars().any(|ch| ch.is_ascii() && !(ch.is_ascii_alphanumeric() || ch == '-')) {
+            invalid.push(format!("域名包含非法字符: {label}"));
+        }
+    }
+    let tld = labels.last().copied().unwrap_or_default();
+    if labels.len() < 2 {
+        suspicious.push("域名没有顶级域（如 .com、.edu.cn）".to_string());
+    } else if tld.chars().all(|ch| ch.is_ascii_digit()) {
+        invalid.push("顶级域不能全为数字".to_string());
+    } else if tld.chars().count() < 2 {
+        suspicious.push(format!("顶级域过短: .{tld}"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_address, suggest_address, validate_recipients, ValidationSettings, Verdict};
+    use crate::engine::RecipientEntry;
+
+    #[test]
+    fn classifies_addresses() {
+        for valid in ["zhang@example.edu.cn", "first.last+tag@sub.example.com", "o'brien@example.ie"] {
+            assert_eq!(check_address(valid).0, Verdict::Valid, "{valid}");
+        }
+        for suspicious in [
+            " zhang@example.edu ",
+            "\"john doe\"@example.com",
+            "root@localhost",
+            "user@[192.0.2.1]",
+            "张三@例子.中国",
+        ] {
+            assert_eq!(check_address(suspicious).0, Verdict::Suspicious, "{suspicious}");
+        }
+        for invalid in [
+            "",
+            "plainaddress",
+            "a@b@example.com",
+            ".lead@example.com",
+            "two..dots@example.com",
+            "bad,comma@example.com",
+            "user@-example.com",
+            "user@example..com",
+            "user@example.123",
+            "user@[999.1.1.1]",
+            &format!("{}@example.com", "x".repeat(65)),
+        ] {
+            assert_eq!(check_address(invalid).0, Verdict::Invalid, "{invalid}");
+        }
+
+        let recipients = vec![
+            RecipientEntry {
+                email: "ok@example.com".to_string(),
+                name: "甲".to_string(),
+                ..RecipientEntry::default()
+            },
+            RecipientEntry {
+                email: "broken@".to_string(),
+                name: "乙".to_string(),
+                ..RecipientEntry::default()
+            },
+            RecipientEntry {
+                email: "NoReply+news@example.com".to_string(),
+                name: "丙".to_string(),
+                ..RecipientEntry::default()
+            },
+        ];
+        let report = validate_recipients(recipients.clone(), &ValidationSettings::default());
+        assert_eq!((report.valid, report.suspicious, report.invalid), (1, 1, 1));
+        assert_eq!(report.results[1].row, 2);
+        assert!(report.results[2].role_account);
+        assert_eq!((report.role_accounts, report.kept.len()), (1, 2));
+
+        let settings = ValidationSettings {
+            role_prefixes: vec!["ok".to_string()],
+        };
+        let report = validate_recipients(recipients, &settings);
+        assert!(report.results[0].role_account);
+        assert!(!report.results[2].role_account);
+    }
+
+    #[test]
+    fn suggests_common_domain_typos() {
+        assert_eq!(suggest_address("li@gmial.com").as_deref(), Some("li@gmail.com"));
+        assert_eq!(suggest_address("li@163.con").as_deref(), Some("li@163.com"));
+        assert_eq!(suggest_address("li@hotmall.com").as_deref(), Some("li@hotmail.com"));
+        assert_eq!(suggest_address("li@pku.edu.con").as_deref(), Some("li@pku.edu.com"));
+        for unchanged in ["li@gmail.com", "li@mail.com", "li@pku.edu.cn", "li@136.com", "li@example.cm"] {
+            assert_eq!(suggest_address(unchanged), None, "{unchanged}");
+        }
+        let report = validate_recipients(
+            vec![RecipientEntry {
+                email: "wang@qq.con".to_string(),
+                ..RecipientEntry::default()
+            }],
+            &ValidationSettings::default(),
+        );
+        assert_eq!(report.results[0].verdict, Verdict::Suspicious);
+        assert_eq!(report.results[0].suggestion.as_deref(), Some("wang@qq.com"));
+    }
+}
@@ -0,0 +1,2792 @@
+use crate::accounts::{AccountRotation, RotationConfig, RotationStrategy};
+use crate::attachments::format_bytes;
+use crate::batch_bcc::{batch_id, BatchBccOptions};
+use crate::canary::{self, CanaryOptions, Placement};
+use crate::clock::{Clock, SystemClock};
+use crate::concurrency::{ConcurrencyOptions, ReorderBuffer};
+use crate::dsn::DsnOptions;
+use crate::environment::Environment;
+use crate::greylist::{
+    advertised_delay, is_greylisting, recipient_domain, GreylistTracker, DEFAULT_GREYLIST_DELAY_SEC,
+    DEFAULT_GREYLIST_MAX_ATTEMPTS,
+};
+use crate::local_mta::LocalMtaOptions;
+use crate::message_builder::{
+    build_email_message, html_to_plain_text, stable_message_id, validate_custom_headers, validate_inline_images,
+    validate_message_id_domain, InlineImage, MessageContent,
+};
+use crate::message_size::OversizeOptions;
+use crate::readback::{self, ReadbackOptions};
+use crate::recipients_loader::{ColumnMapping, RecipientExclusion, RecipientSource, RecipientStream};
+use crate::render_pipeline::{self, RenderOptions, RenderSpool, SpooledMessage};
+use crate::retry::RetryPolicy;
+use crate::send_plan::{PlannedRecipient, SendPlan, StreamPlan};
+use crate::sent_store::{SentBatch, SentEnvelope, SentStore};
+use crate::smime::{SmimeContext, SmimeOptions};
+use crate::smtp_client::{MailTransport, SendFailure, SmtpPayload};
+use crate::template::{
+    format_send_date, normalize_signature_tokens_in_template, render_template_text, template_variables,
+    BUILTIN_VARIABLES,
+};
+use crate::throttle::Throttle;
+use crate::upload::AttachmentLink;
+use chrono::{DateTime, Local, SecondsFormat, Utc};
+use lettre::address::Envelope;
+use lettre::{Address, Message};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, sync_channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+#[derive(Deserialize, Serialize, Clone, Default)]
+pub struct SenderConfig {
+    pub email: String,
+    pub name: String,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct TemplateConfig {
+    pub subject: String,
+    /// 纯文本正文；留空且提供了 HTML 正文时，发送时由 HTML 自动生成。
+    #[serde(default)]
+    pub body_text: String,
+    #[serde(default)]
+    pub body_html: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Default)]
+pub struct RecipientEntry {
+    pub email: String,
+    pub name: String,
+    /// 表格外的补充字段（如联系人补充得到的单位名称），可在模板中作为变量使用。
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub fields: BTreeMap<String, String>,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct SendOptions {
+    pub min_delay_sec: u64,
+    pub max_delay_sec: u64,
+    pub randomize_order: bool,
+    pub retry_count: u32,
+    pub skip_sent: bool,
+    pub greylist_delay_sec: u64,
+    pub greylist_max_attempts: u32,
+    /// 显式的重试策略；为空时按 `retry_count` 兼容旧行为。
+    pub retry: Option<RetryPolicy>,
+    /// 附件文件名转写为 ASCII（仅原生引擎）。
+    pub ascii_attachment_names: bool,
+    /// Message-ID 的域名；为空时取发件邮箱的域名。
+    pub message_id_domain: Option<String>,
+    /// 请求投递状态通知（DSN）；服务器未声明 DSN 时忽略。
+    pub dsn: Option<DsnOptions>,
+    /// 阅读回执（Disposition-Notification-To）的接收地址。
+    pub read_receipt_to: Option<String>,
+    /// 固定随机种子：收件人顺序与发送间隔可完全复现，未指定时随机生成并写入 `job_started`。
+    pub seed: Option<u64>,
+}
+
+impl SendOptions {
+    pub fn retry_policy(&self) -> RetryPolicy {
+        self.retry
+            .clone()
+            .unwrap_or_else(|| RetryPolicy::from_retry_count(self.retry_count))
+    }
+}
+
+impl Default for SendOptions {
+    fn default() -> Self {
+        SendOptions {
+            min_delay_sec: 0,
+            max_delay_sec: 0,
+            randomize_order: false,
+            retry_count: 1,
+            skip_sent: true,
+            greylist_delay_sec: DEFAULT_GREYLIST_DELAY_SEC,
+            greylist_max_attempts: DEFAULT_GREYLIST_MAX_ATTEMPTS,
+            retry: None,
+            ascii_attachment_names: false,
+            message_id_domain: None,
+            dsn: None,
+            read_receipt_to: None,
+            seed: None,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Default)]
+pub struct JobPaths {
+    #[serde(default)]
+    pub sent_store_file: Option<String>,
+    #[serde(default)]
+    pub sent_store_text_file: Option<String>,
+}
+
+/// `start_send` 载荷的强类型形式，字段与 Python worker 的 `_build_job_config` 一致。
+#[derive(Deserialize, Clone)]
+pub struct SendJob {
+    #[serde(default)]
+    pub job_id: Option<String>,
+    #[serde(default)]
+    pub sender: SenderConfig,
+    #[serde(default)]
+    pub smtp: SmtpPayload,
+    pub template: TemplateConfig,
+    #[serde(default)]
+    pub recipients: Vec<RecipientEntry>,
+    /// 每封邮件的抄送 / 密送地址（已由 `normalize_copy_addresses` 规范化）。
+    #[serde(default)]
+    pub cc: Vec<String>,
+    #[serde(default)]
+    pub bcc: Vec<String>,
+    #[serde(default)]
+    pub attachments: Vec<String>,
+    /// HTML 正文中通过 `cid:` 引用的内嵌图片。
+    #[serde(default)]
+    pub inline_images: Vec<InlineImage>,
+    /// 自定义邮件头（Reply-To、List-Unsubscribe、X- 追踪头等）。
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default)]
+    pub options: SendOptions,
+    #[serde(default)]
+    pub paths: JobPaths,
+    /// 多账号轮换配置；为空时使用 `sender` + `smtp` 单账号发送。
+    #[serde(default)]
+    pub rotation: Option<RotationConfig>,
+    /// 发起任务的操作人（用户名@主机名），写入发送记录。
+    #[serde(default)]
+    pub operator: Option<String>,
+    /// 任务的环境标签，由 `start_send` 校验后写入发送记录。
+    #[serde(default)]
+    pub environment: Option<Environment>,
+    /// S/MIME 签名 / 加密开关；证书由调用方通过 `SendEngine::with_smime` 提供。
+    #[serde(default)]
+    pub smime: SmimeOptions,
+    /// 邮件体积超过上限时的处理方式。
+    #[serde(default)]
+    pub oversize: OversizeOptions,
+    /// 附件的下载链接，由 `start_send` 上传附件后填入。
+    #[serde(default)]
+    pub attachment_links: Vec<AttachmentLink>,
+    /// 改为发送下载链接的收件人（小写邮箱）。
+    #[serde(default)]
+    pub linked_recipients: HashSet<String>,
+    /// 批量密送模式；为空时每个收件人单独发送一封。
+    #[serde(default)]
+    pub batch_bcc: Option<BatchBccOptions>,
+    /// 并行预渲染；批量密送模式下不生效。
+    #[serde(default)]
+    pub render: RenderOptions,
+    /// 交给本机 MTA（sendmail / 投递目录）投递；此时不直接连接 SMTP 服务器。
+    #[serde(default)]
+    pub local_mta: Option<LocalMtaOptions>,
+    /// 发送结束后通过 IMAP 在已发送文件夹中回读验证。
+    #[serde(default)]
+    pub readback: Option<ReadbackOptions>,
+    /// 从文件分批读取收件人（超大名单）；与 `recipients` 二选一。
+    #[serde(default)]
+    pub recipient_source: Option<RecipientSource>,
+    /// 每隔若干封插入发给自己测试邮箱的金丝雀邮件，并检查其投递位置。
+    #[serde(default)]
+    pub canary: Option<CanaryOptions>,
+    /// 同时使用多条 SMTP 连接发送；连接由调用方通过 `SendEngine::with_connections` 提供。
+    #[serde(default)]
+    pub concurrency: Option<ConcurrencyOptions>,
+}
+
+/// 未指定任务 ID 时的默认值：固定种子的任务 ID 可复现，否则随机生成。
+pub fn default_job_id(seed: Option<u64>) -> String {
+    match seed {
+        Some(seed) => format!("job-{seed:016x}"),
+        None => format!("job-{:08x}", rand::random::<u32>()),
+    }
+}
+
+impl SendJob {
+    pub fn from_payload(payload: Value) -> Result<Self, String> {
+        let mut job: SendJob =
+            serde_json::from_value(payload).map_err(|err| format!("发送参数格式错误: {err}"))?;
+        job.validate()?;
+        if job.job_id.as_deref().map(str::trim).unwrap_or_default().is_empty() {
+            job.job_id = Some(default_job_id(job.options.seed));
+        }
+        Ok(job)
+    }
+
+    pub fn job_id(&self) -> &str {
+        self.job_id.as_deref().unwrap_or_default()
+    }
+
+    fn attachment_delivery(&self, recipient: &RecipientEntry) -> AttachmentDelivery<'_> {
+        if self.linked_recipients.contains(&recipient.email.trim().to_lowercase()) {
+            AttachmentDelivery::Linked(&self.attachment_links)
+        } else {
+            AttachmentDelivery::Attached
+        }
+    }
+
+    /// 批量密送时各批内容相同：只要有收件人需要改为下载链接，所有批次都改用链接。
+    fn batch_attachment_delivery(&self) -> AttachmentDelivery<'_> {
+        if self.linked_recipients.is_empty() {
+            AttachmentDelivery::Attached
+        } else {
+            AttachmentDelivery::Linked(&self.attachment_links)
+        }
+    }
+
+    /// 邮件的 Message-ID：域名优先取 `message_id_domain`，否则取发件邮箱的域名。
+    pub fn message_id(&self, sender: &SenderConfig, to: &str, bcc: &[String]) -> String {
+        let domain = self
+            .options
+            .message_id_domain
+            .as_deref()
+            .map(str::trim)
+            .filter(|domain| !domain.is_empty())
+            .map(str::to_string)
+            .unwrap_or_else(|| recipient_domain(&sender.email));
+        stable_message_id(&domain, self.job_id(), to, bcc)
+    }
+
+    fn read_receipt_address(&self) -> Option<&str> {
+        self.options
+            .read_receipt_to
+            .as_deref()
+            .map(str::trim)
+            .filter(|address| !address.is_empty())
+    }
+
+    /// 预渲染邮件的落盘目录，位于发送记录旁的 `spool/<job_id>`。
+    pub fn spool_dir(&self) -> PathBuf {
+        let store = self.sent_store_path();
+        store
+            .parent()
+            .unwrap_or_else(|| Path::new("."))
+            .join("spool")
+            .join(self.job_id())
+    }
+
+    pub fn sent_store_path(&self) -> PathBuf {
+        PathBuf::from(
+            self.paths
+                .sent_store_file
+                .clone()
+                .unwrap_or_else(|| "sent_records.jsonl".to_string()),
+        )
+    }
+
+    pub fn sent_store_text_path(&self) -> PathBuf {
+        match &self.paths.sent_store_text_file {
+            Some(path) if !path.trim().is_empty() => PathBuf::from(path),
+            _ => self.sent_store_path().with_extension("txt"),
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        match &self.rotation {
+            Some(rotation) if rotation.account_ids.is_empty() => {
+                return Err("账号轮换至少需要选择一个发件账号".to_string());
+            }
+            Some(_) => {}
+            None => {
+                validate_email(&self.sender.email, "发件邮箱")?;
+                if self.local_mta.is_none() {
+                    if self.smtp.host.trim().is_empty() {
+                        return Err("SMTP 主机不能为空".to_string());
+                    }
+                    if self.smtp.port == 0 {
+                        return Err("SMTP 端口 必须 >= 1".to_string());
+                    }
+                    if self.smtp.use_ssl && self.smtp.use_starttls {
+                        return Err("SMTP 配置冲突：use_ssl 与 use_starttls 不能同时开启".to_string());
+                    }
+                    if self.smtp.timeout_sec == 0 {
+                        return Err("SMTP 超时时间 必须 >= 1".to_string());
+                    }
+                }
+                self.smtp.validate_envelope()?;
+                if self.sender.name.trim().is_empty() {
+                    return Err("发件人姓名不能为空".to_string());
+                }
+            }
+        }
+        let has_html = self
+            .template
+            .body_html
+            .as_deref()
+            .is_some_and(|html| !html.trim().is_empty());
+        if self.template.body_text.trim().is_empty() && !has_html {
+            return Err("邮件正文不能为空".to_string());
+        }
+        if self.options.retry_count == 0 {
+            return Err("重试次数 必须 >= 1".to_string());
+        }
+        if let Some(retry) = &self.options.retry {
+            retry.validate()?;
+        }
+        if let Some(batch) = &self.batch_bcc {
+            batch.validate()?;
+        }
+        self.render.validate()?;
+        if let Some(domain) = self.options.message_id_domain.as_deref().filter(|domain| !domain.trim().is_empty()) {
+            validate_message_id_domain(domain)?;
+        }
+        if let Some(dsn) = &self.options.dsn {
+            dsn.validate()?;
+        }
+        if let Some(local_mta) = &self.local_mta {
+            local_mta.validate()?;
+        }
+        if let Some(canary) = &self.canary {
+            canary.validate()?;
+            if self.batch_bcc.is_some() {
+                return Err("金丝雀不能与批量密送同时使用".to_string());
+            }
+        }
+        if let Some(address) = self.read_receipt_address() {
+            validate_email(address, "阅读回执地址")?;
+        }
+        if let Some(concurrency) = &self.concurrency {
+            concurrency.validate()?;
+            let exclusive = self.rotation.is_some()
+                || self.batch_bcc.is_some()
+                || self.render.parallel
+                || self.canary.is_some()
+                || self.local_mta.is_some();
+            if exclusive {
+                return Err("并发发送不能与账号轮换、批量密送、预渲染、金丝雀或本机 MTA 同时使用".to_string());
+            }
+        }
+        if self.render.parallel && self.rotation.is_some() {
+            return Err("预渲染需要固定发件账号，不能与账号轮换同时使用".to_string());
+        }
+        match &self.recipient_source {
+            Some(_) if !self.recipients.is_empty() => {
+                return Err("recipients 与 recipient_source 不能同时提供".to_string());
+            }
+            Some(_) if self.batch_bcc.is_some() || self.render.parallel => {
+                return Err("流式收件人不能与批量密送或预渲染同时使用".to_string());
+            }
+            Some(source) => {
+                source.validate()?;
+                if let Some(mapping) = &source.column_mapping {
+                    self.validate_mapped_variables(mapping)?;
+                }
+            }
+            None if self.recipients.is_empty() => return Err("收件人列表不能为空".to_string()),
+            None => {}
+        }
+        for (index, recipient) in self.recipients.iter().enumerate() {
+            validate_email(&recipient.email, &format!("recipients[{}].email", index + 1))?;
+            if recipient.name.trim().is_empty() {
+                return Err(format!("Invalid recipients[{}] data", index + 1));
+            }
+        }
+        for (field, addresses) in [("cc", &self.cc), ("bcc", &self.bcc)] {
+            for (index, address) in addresses.iter().enumerate() {
+                validate_email(address, &format!("{field}[{}]", index + 1))?;
+            }
+        }
+        for attachment in &self.attachments {
+            if !Path::new(attachment).is_file() {
+                return Err(format!("Attachment not found: {attachment}"));
+            }
+        }
+        if !self.inline_images.is_empty() && !has_html {
+            return Err("内嵌图片需要提供 HTML 正文（body_html）".to_string());
+        }
+        validate_inline_images(&self.inline_images)?;
+        validate_custom_headers(&self.headers)?;
+        Ok(())
+    }
+
+    /// 流式收件人无法预先逐个渲染，发送前检查模板变量都有对应的内置变量或映射列。
+    fn validate_mapped_variables(&self, mapping: &ColumnMapping) -> Result<(), String> {
+        let templates = [
+            self.template.subject.as_str(),
+            self.template.body_text.as_str(),
+            self.template.body_html.as_deref().unwrap_or_default(),
+        ];
+        for variable in templates.into_iter().flat_map(template_variables) {
+            if !BUILTIN_VARIABLES.contains(&variable.as_str()) && !mapping.field_names().any(|name| name == variable) {
+                return Err(format!("模板变量 {{{variable}}} 没有对应的列，请在列映射中添加"));
+            }
+        }
+        Ok(())
+    }
+}
+
+pub fn validate_email(email: &str, field_name: &str) -> Result<(), String> {
+    let normalized = email.trim();
+    if normalized.is_empty() {
+        return Err(format!("{field_name} 不能为空"));
+    }
+    let valid = match normalized.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && !normalized.chars().any(char::is_whitespace)
+                && !domain.contains('@')
+                && domain
+                    .split_once('.')
+                    .is_some_and(|(head, tail)| !head.is_empty() && !tail.is_empty())
+        }
+        None => false,
+    };
+    if !valid {
+        return Err(format!("{field_name} 格式不正确"));
+    }
+    Ok(())
+}
+
+/// 规范化抄送 / 密送列表：去除首尾空白、校验格式、忽略大小写去重。
+pub fn normalize_copy_addresses(addresses: &[String], field_name: &str) -> Result<Vec<String>, String> {
+    let mut seen = HashSet::new();
+    let mut normalized = Vec::new();
+    for (index, raw) in addresses.iter().enumerate() {
+        let address = raw.trim();
+        if address.is_empty() {
+            continue;
+        }
+        validate_email(address, &format!("{field_name}[{}]", index + 1))?;
+        if seen.insert(address.to_lowercase()) {
+            normalized.push(address.to_string());
+        }
+    }
+    Ok(normalized)
+}
+
+struct DeferredRecipient {
+    index: usize,
+    recipient: RecipientEntry,
+    next_attempt: Instant,
+    attempts: u32,
+}
+
+#[derive(Default)]
+struct JobCounters {
+    success: usize,
+    failed: usize,
+    skipped: usize,
+    deferred: usize,
+    failures: Vec<Value>,
+}
+
+enum Delivery {
+    Sent,
+    Deferred(SendFailure),
+    Failed(String),
+}
+
+/// 一个可用于发送的发件账号：账号 id、发件身份及其 SMTP 连接。
+pub struct SenderSlot<T: MailTransport> {
+    pub account_id: String,
+    pub sender: SenderConfig,
+    /// 该账号的信封发件人（`SmtpPayload::return_path`）。
+    pub return_path: Option<Address>,
+    /// 该账号服务器声明的 SIZE 上限（`SmtpPayload::server_size_limit`）；超过的邮件不发送，直接记为失败。
+    pub size_limit: Option<u64>,
+    pub transport: T,
+}
+
+/// Rust 原生发送引擎：事件格式与 Python `SendEngine` 保持一致，额外支持灰名单延后重试与多账号轮换。
+pub struct SendEngine<T: MailTransport> {
+    slots: Vec<SenderSlot<T>>,
+    rotation: AccountRotation,
+    sent_store: SentStore,
+    throttle: Option<Arc<Mutex<Throttle>>>,
+    /// 本任务自己的限速，发送过程中可由操作人调整（`update_job_throttle`）。
+    job_throttle: Option<Arc<Mutex<Throttle>>>,
+    smime: Option<Arc<SmimeContext>>,
+    cancel: Arc<AtomicBool>,
+    clock: Arc<dyn Clock>,
+    /// 本次任务已发送的邮件，仅在开启回读验证时记录。
+    sent_messages: Vec<SentMessage>,
+    /// 流式收件人的排除规则（退订名单等），在读取时应用。
+    recipient_exclusion: Option<RecipientExclusion>,
+    canary: Option<CanaryRun>,
+    /// 并发发送时各连接使用的投递通道，均以第一个发件账号的身份发送。
+    connections: Vec<T>,
+}
+
+/// 一次任务中金丝雀的发送轮次与待检查的邮件。
+struct CanaryRun {
+    /// 去掉收件人与抄送 / 密送的任务副本：金丝雀邮件不抄送他人，且每轮使用独立的任务 ID 生成 Message-ID。
+    job: SendJob,
+    attempted: usize,
+    round: usize,
+    pending: Vec<PendingCanary>,
+}
+
+struct PendingCanary {
+    round: usize,
+    mailbox: usize,
+    message_id: String,
+    sent_at: Instant,
+}
+
+/// 一位收件人对应的已发送邮件（批量密送时多位收件人共用同一 Message-ID）。
+struct SentMessage {
+    index: usize,
+    email: String,
+    name: String,
+    message_id: String,
+}
+
+impl<T: MailTransport + Send> SendEngine<T> {
+    pub fn new(slots: Vec<SenderSlot<T>>, sent_store: SentStore, cancel: Arc<AtomicBool>) -> Self {
+        let rotation = AccountRotation::new(RotationStrategy::RoundRobin, vec![None; slots.len()], vec![0; slots.len()]);
+        SendEngine {
+            slots,
+            rotation,
+            sent_store,
+            throttle: None,
+            job_throttle: None,
+            smime: None,
+            cancel,
+            clock: Arc::new(SystemClock),
+            sent_messages: Vec::new(),
+            recipient_exclusion: None,
+            canary: None,
+            connections: Vec::new(),
+        }
+    }
+
+    pub fn with_rotation(mut self, rotation: AccountRotation) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    pub fn with_throttle(mut self, throttle: Arc<Mutex<Throttle>>) -> Self {
+        self.throttle = Some(throttle);
+        self
+    }
+
+    /// 任务级限速：与全局限速同时生效，其间隔上下限覆盖任务配置的发送间隔；共享的限速器被修改后从下一封邮件起生效。
+    pub fn with_job_throttle(mut self, throttle: Arc<Mutex<Throttle>>) -> Self {
+        self.job_throttle = Some(throttle);
+        self
+    }
+
+    pub fn with_smime(mut self, smime: SmimeContext) -> Self {
+        self.smime = Some(Arc::new(smime));
+        self
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn with_recipient_exclusion(mut self, exclusion: RecipientExclusion) -> Self {
+        self.recipient_exclusion = Some(exclusion);
+        self
+    }
+
+    /// 并发发送使用的连接；任务未开启 `concurrency` 或未提供连接时按单连接顺序发送。
+    pub fn with_connections(mut self, connections: Vec<T>) -> Self {
+        self.connections = connections;
+        self
+    }
+
+    pub fn run(&mut self, job: &SendJob, emit: &mut dyn FnMut(Value)) {
+        self.sent_messages.clear();
+        self.canary = job.canary.as_ref().map(|_| CanaryRun {
+            job: SendJob {
+                recipients: Vec::new(),
+                cc: Vec::new(),
+                bcc: Vec::new(),
+                ..job.clone()
+            },
+            attempted: 0,
+            round: 0,
+            pending: Vec::new(),
+        });
+        self.dispatch(job, emit);
+        if let Some(options) = &job.canary {
+            if !self.is_cancelled() {
+                self.finish_canaries(job, options, emit);
+            }
+        }
+        if let Some(options) = &job.readback {
+            if !self.is_cancelled() {
+                self.read_back(job, options, emit);
+            }
+        }
+    }
+
+    /// 快速发送一封事务性邮件（回复咨询、单独重发失败的收件人）：不排队、不等待发送间隔，也不跳过已发送的收件人，
+    /// 但照常写入发送记录；触及全局限速或账号每日上限时直接返回错误，不等待。
+    pub fn send_single(&mut self, job: &SendJob, emit: &mut dyn FnMut(Value)) -> Result<(), String> {
+        let [recipient] = job.recipients.as_slice() else {
+            return Err("快速发送只能有一位收件人".to_string());
+        };
+        if job.recipient_source.is_some() || job.batch_bcc.is_some() || job.canary.is_some() || job.readback.is_some() {
+            return Err("快速发送不支持流式收件人、批量密送、金丝雀或回读验证".to_string());
+        }
+        let wait = self
+            .throttle
+            .as_ref()
+            .and_then(|throttle| throttle.lock().ok()?.wait_secs(self.clock.unix_now()));
+        if let Some((remaining, window)) = wait {
+            return Err(format!("已达到发送限速（{window}），请 {remaining} 秒后再试"));
+        }
+        let slot = self
+            .rotation
+            .next_slot()
+            .ok_or_else(|| "发件账号均已达到今日发送上限".to_string())?;
+        match self.deliver(job, slot, 1, recipient, emit) {
+            Ok(()) => {
+                self.record_sent(job, slot, 1, recipient, None, emit);
+                Ok(())
+            }
+            Err(failure) => {
+                let mut counters = JobCounters::default();
+                record_failed(job.job_id(), 1, recipient, failure.message.clone(), &mut counters, emit);
+                Err(failure.message)
+            }
+        }
+    }
+
+    fn dispatch(&mut self, job: &SendJob, emit: &mut dyn FnMut(Value)) {
+        if let Some(source) = &job.recipient_source {
+            self.run_stream(job, source, emit);
+            return;
+        }
+        let plan = SendPlan::build(&job.options, &job.recipients);
+        let total = plan.order.len();
+        if let Some(batch) = &job.batch_bcc {
+            self.run_batches(job, batch, &plan, emit);
+            return;
+        }
+        if !job.render.parallel || self.slots.len() != 1 {
+            self.run_planned(job, plan.seed, total, &mut plan.entries().map(Ok), emit);
+            return;
+        }
+
+        let pending: Vec<(usize, RecipientEntry)> = plan
+            .order
+            .iter()
+            .enumerate()
+            .filter(|(_, recipient)| !(job.options.skip_sent && self.sent_store.is_sent(&recipient.email)))
+            .map(|(position, recipient)| (position + 1, recipient.clone()))
+            .collect();
+        let sender = self.slots[0].sender.clone();
+        let return_path = self.slots[0].return_path.clone();
+        let smime = self.smime.clone();
+        let dir = job.spool_dir();
+        std::thread::scope(|scope| {
+            let render = move |recipient: &RecipientEntry| {
+                let attachments = job.attachment_delivery(recipient);
+                build_message(job, &sender, return_path.as_ref(), recipient, &job.bcc, smime.as_deref(), attachments)
+            };
+            match render_pipeline::start(scope, &job.render, &dir, pending, Arc::clone(&self.cancel), render) {
+                Ok(mut spool) => {
+                    self.run_recipients(job, plan.seed, total, &mut plan.entries().map(Ok), Some(&mut spool), emit)
+                }
+                Err(err) => {
+                    emit(json!({ "type": "error", "job_id": job.job_id(), "error": err }));
+                    self.run_recipients(job, plan.seed, total, &mut plan.entries().map(Ok), None, emit);
+                }
+            }
+        });
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    /// 流式收件人：先完整扫描一遍文件得到总数与统计（只计数，不保留收件人），再重新打开文件分批发送。
+    fn run_stream(&mut self, job: &SendJob, source: &RecipientSource, emit: &mut dyn FnMut(Value)) {
+        let open = || {
+            RecipientStream::open(source).map(|stream| stream.with_exclusion(self.recipient_exclusion.clone()))
+        };
+        let scanned = open().and_then(|mut stream| {
+            let mut total = 0;
+            for chunk in stream.by_ref() {
+                total += chunk?.len();
+            }
+            Ok((total, stream))
+        });
+        let (total, scan) = match scanned {
+            Ok(scanned) => scanned,
+            Err(err) => {
+                emit(json!({ "type": "error", "job_id": job.job_id(), "error": err }));
+                return;
+            }
+        };
+        emit(json!({
+            "type": "recipient_stream_opened",
+            "job_id": job.job_id(),
+            "path": source.path,
+            "chunk_size": source.chunk_size,
+            "csv_format": scan.format(),
+            "stats": scan.stats(),
+            "excluded": scan.excluded(),
+        }));
+        let stream = match open() {
+            Ok(stream) => stream,
+            Err(err) => {
+                emit(json!({ "type": "error", "job_id": job.job_id(), "error": err }));
+                return;
+            }
+        };
+        let seed = job.options.seed.unwrap_or_else(rand::random);
+        let mut plan = StreamPlan::new(&job.options, seed, stream);
+        self.run_planned(job, seed, total, &mut plan, emit);
+    }
+
+    /// 开启并发且提供了连接时多连接发送，否则逐个发送。
+    fn run_planned(
+        &mut self,
+        job: &SendJob,
+        seed: u64,
+        total: usize,
+        entries: &mut dyn Iterator<Item = Result<PlannedRecipient, String>>,
+        emit: &mut dyn FnMut(Value),
+    ) {
+        match &job.concurrency {
+            Some(options) if !self.connections.is_empty() => {
+                self.run_concurrent(job, options, seed, total, entries, emit)
+            }
+            _ => self.run_recipients(job, seed, total, entries, None, emit),
+        }
+    }
+
+    /// 插入一轮金丝雀：先检查之前几轮中已到检查时间的邮件，再向每个金丝雀邮箱各发一封。
+    /// 金丝雀沿用上一位真实收件人的补充字段与发件账号，使邮件内容与真实邮件一致；不写入发送记录，不重试。
+    fn send_canaries(
+        &mut self,
+        job: &SendJob,
+        slot: usize,
+        after_index: usize,
+        template: &RecipientEntry,
+        emit: &mut dyn FnMut(Value),
+    ) {
+        let (Some(options), Some(mut run)) = (&job.canary, self.canary.take()) else {
+            return;
+        };
+        self.check_canaries(job, options, &mut run, false, emit);
+        run.round += 1;
+        run.job.job_id = Some(format!("{}-canary-{}", job.job_id(), run.round));
+        for (mailbox_index, mailbox) in options.mailboxes.iter().enumerate() {
+            let recipient = RecipientEntry {
+                email: mailbox.email.trim().to_string(),
+                name: match mailbox.name.trim() {
+                    "" => template.name.clone(),
+                    name => name.to_string(),
+                },
+                fields: template.fields.clone(),
+            };
+            let sender = &self.slots[slot].sender;
+            let message_id = run.job.message_id(sender, &recipient.email, &[]);
+            let attachments = run.job.attachment_delivery(&recipient);
+            let return_path = self.slots[slot].return_path.as_ref();
+            let sent = build_message(&run.job, sender, return_path, &recipient, &[], self.smime.as_deref(), attachments)
+                .map_err(|message| SendFailure { code: None, message })
+                .and_then(|message| self.slots[slot].transport.send_message(&message));
+            let mut event = json!({
+                "type": "canary_sent",
+                "job_id": job.job_id(),
+                "round": run.round,
+                "after_index": after_index,
+                "email": recipient.email,
+                "message_id": message_id,
+                "account_id": self.slots[slot].account_id,
+            });
+            match sent {
+                Ok(()) if mailbox.imap.is_some() => run.pending.push(PendingCanary {
+                    round: run.round,
+                    mailbox: mailbox_index,
+                    message_id,
+                    sent_at: self.clock.now(),
+                }),
+                Ok(()) => {}
+                Err(failure) => {
+                    event["type"] = json!("canary_failed");
+                    event["error"] = json!(failure.message);
+                }
+            }
+            emit(event);
+        }
+        self.canary = Some(run);
+    }
+
+    /// 检查已到时间的金丝雀投递位置；未找到的邮件留到下一轮再查，`final_check` 时直接报告为未找到。
+    fn check_canaries(
+        &self,
+        job: &SendJob,
+        options: &CanaryOptions,
+        run: &mut CanaryRun,
+        final_check: bool,
+        emit: &mut dyn FnMut(Value),
+    ) {
+        let now = self.clock.now();
+        let delay = Duration::from_secs(options.check_delay_sec);
+        for (mailbox_index, mailbox) in options.mailboxes.iter().enumerate() {
+            let Some(imap) = &mailbox.imap else {
+                continue;
+            };
+            let (due, rest): (Vec<PendingCanary>, Vec<PendingCanary>) =
+                std::mem::take(&mut run.pending).into_iter().partition(|pending| {
+                    pending.mailbox == mailbox_index
+                        && (final_check || now.duration_since(pending.sent_at) >= delay)
+                });
+            run.pending = rest;
+            if due.is_empty() {
+                continue;
+            }
+            let message_ids: Vec<String> = due.iter().map(|pending| pending.message_id.clone()).collect();
+            let results = match canary::check_placement(imap, &message_ids) {
+                Ok(results) => results,
+                Err(err) => {
+                    emit(json!({
+                        "type": "canary_check_failed",
+                        "job_id": job.job_id(),
+                        "email": mailbox.email.trim(),
+                        "error": err,
+                    }));
+                    continue;
+                }
+            };
+            for (pending, result) in due.into_iter().zip(results) {
+                if result.placement == Placement::Missing && !final_check {
+                    run.pending.push(pending);
+                    continue;
+                }
+                emit(json!({
+                    "type": "canary_placement",
+                    "job_id": job.job_id(),
+                    "round": pending.round,
+                    "email": mailbox.email.trim(),
+                    "message_id": result.message_id,
+                    "placement": result.placement,
+                    "folder": result.folder,
+                }));
+            }
+        }
+    }
+
+    /// 发送结束后等最后一轮金丝雀到达检查时间，报告全部尚未检查的金丝雀。
+    fn finish_canaries(&mut self, job: &SendJob, options: &CanaryOptions, emit: &mut dyn FnMut(Value)) {
+        let Some(mut run) = self.canary.take() else {
+            return;
+        };
+        let Some(latest) = run.pending.iter().map(|pending| pending.sent_at).max() else {
+            return;
+        };
+        let elapsed = self.clock.now().duration_since(latest);
+        if self.sleep_with_cancel(Duration::from_secs(options.check_delay_sec).saturating_sub(elapsed)) {
+            return;
+        }
+        self.check_canaries(job, options, &mut run, true, emit);
+    }
+
+    /// 等待服务商保存后，按 Message-ID 在已发送文件夹中查找每封邮件，逐个收件人发出验证结果。
+    fn read_back(&mut self, job: &SendJob, options: &ReadbackOptions, emit: &mut dyn FnMut(Value)) {
+        let sent = std::mem::take(&mut self.sent_messages);
+        if sent.is_empty() {
+            return;
+        }
+        let mut message_ids: Vec<String> = sent.iter().map(|message| message.message_id.clone()).collect();
+        message_ids.sort();
+        message_ids.dedup();
+        emit(json!({
+            "type": "readback_started",
+            "job_id": job.job_id(),
+            "messages": message_ids.len(),
+            "delay_sec": options.delay_sec,
+        }));
+        if self.sleep_with_cancel(Duration::from_secs(options.delay_sec)) {
+            return;
+        }
+        let results = match readback::verify_messages(options, &message_ids) {
+            Ok(results) => results,
+            Err(err) => {
+                emit(json!({ "type": "readback_failed", "job_id": job.job_id(), "error": err }));
+                return;
+            }
+        };
+        let verified = results.iter().filter(|result| result.found).count();
+        for message in &sent {
+            let Some(result) = results.iter().find(|result| result.message_id == message.message_id) else {
+                continue;
+            };
+            emit(json!({
+                "type": "recipient_readback",
+                "job_id": job.job_id(),
+                "index": message.index,
+                "email": message.email,
+                "name": message.name,
+                "message_id": result.message_id,
+                "found": result.found,
+                "folder": result.folder,
+                "provider_id": result.provider_id,
+                "provider_id_kind": result.provider_id_kind,
+            }));
+        }
+        emit(json!({
+            "type": "readback_finished",
+            "job_id": job.job_id(),
+            "verified": verified,
+            "missing": results.len() - verified,
+        }));
+    }
+
+    fn run_recipients(
+        &mut self,
+        job: &SendJob,
+        seed: u64,
+        total: usize,
+        entries: &mut dyn Iterator<Item = Result<PlannedRecipient, String>>,
+        mut spool: Option<&mut RenderSpool>,
+        emit: &mut dyn FnMut(Value),
+    ) {
+        let job_id = job.job_id().to_string();
+        let mut counters = JobCounters::default();
+        let mut tracker = GreylistTracker::new(Duration::from_secs(job.options.greylist_delay_sec));
+        let mut deferred: Vec<DeferredRecipient> = Vec::new();
+
+        emit(json!({ "type": "job_started", "job_id": job_id, "total": total, "seed": seed }));
+
+        for entry in entries {
+            let PlannedRecipient { index, recipient, delay_after } = match entry {
+                Ok(entry) => entry,
+                Err(err) => {
+                    // 读取中途出错（文件被修改等）：不再继续读取，已读到的延后收件人照常处理。
+                    emit(json!({ "type": "error", "job_id": job_id, "error": err }));
+                    break;
+                }
+            };
+            if self.is_cancelled() {
+                emit(cancelled_event(&job_id, &counters, total));
+                return;
+            }
+
+            if job.options.skip_sent && self.sent_store.is_sent(&recipient.email) {
+                counters.skipped += 1;
+                emit(already_sent_skipped_event(&job_id, index, &recipient));
+                continue;
+            }
+
+            let domain = recipient_domain(&recipient.email);
+            if let Some(until) = tracker.deferred_until(&domain, self.clock.now()) {
+                counters.deferred += 1;
+                let window = self.retry_window(until);
+                emit(deferred_event(&job_id, index, &recipient, &domain, window, 0, "domain_deferred"));
+                deferred.push(DeferredRecipient {
+                    index,
+                    recipient,
+                    next_attempt: until,
+                    attempts: 0,
+                });
+            } else if let Some(slot) = self.rotation.next_slot() {
+                if !self.wait_throttle(&job_id, index, emit) {
+                    emit(cancelled_event(&job_id, &counters, total));
+                    return;
+                }
+                emit(json!({
+                    "type": "recipient_started",
+                    "job_id": job_id,
+                    "index": index,
+                    "email": recipient.email,
+                    "name": recipient.name,
+                    "account_id": self.slots[slot].account_id,
+                }));
+                let canary_template = match (&mut self.canary, &job.canary) {
+                    (Some(run), Some(options)) => {
+                        run.attempted += 1;
+                        (run.attempted % options.every == 0).then(|| recipient.clone())
+                    }
+                    _ => None,
+                };
+                let result = match spool.as_deref_mut().and_then(|spool| spool.take(index)) {
+                    Some(rendered) => self.deliver_spooled(job, slot, index, &recipient, rendered, emit),
+                    None => self.deliver(job, slot, index, &recipient, emit),
+                };
+                match result {
+                    Ok(()) => {
+                        tracker.clear(&domain);
+                        counters.success += 1;
+                        self.record_sent(job, slot, index, &recipient, None, emit);
+                    }
+                    Err(failure) if is_greylisting(&failure) && job.options.greylist_max_attempts > 0 => {
+                        let until = tracker.defer(&domain, &failure, self.clock.now());
+                        counters.deferred += 1;
+                        let window = self.retry_window(until);
+                        emit(deferred_event(&job_id, index, &recipient, &domain, window, 1, &failure.message));
+                        deferred.push(DeferredRecipient {
+                            index,
+                            recipient,
+                            next_attempt: until,
+                            attempts: 1,
+                        });
+                    }
+                    Err(failure) => record_failed(&job_id, index, &recipient, failure.message, &mut counters, emit),
+                }
+                if let Some(template) = canary_template {
+                    self.send_canaries(job, slot, index, &template, emit);
+                }
+            } else {
+                counters.skipped += 1;
+                emit(daily_cap_skipped_event(&job_id, index, &recipient));
+                continue;
+            }
+
+            if index < total && !self.wait_between(&job_id, index, delay_after, emit) {
+                emit(cancelled_event(&job_id, &counters, total));
+                return;
+            }
+        }
+
+        if !self.drain_deferred(job, &mut tracker, deferred, &mut counters, emit) {
+            emit(cancelled_event(&job_id, &counters, total));
+            return;
+        }
+
+        emit(finished_event(&job_id, &counters, total));
+    }
+
+    /// 多连接发送：主线程按计划顺序派发收件人，各连接线程独立构建并投递邮件，
+    /// 结果按派发顺序归并后再发出事件、写入发送记录。灰名单延后的收件人在全部派发完成后由第一个账号逐个重试。
+    fn run_concurrent(
+        &mut self,
+        job: &SendJob,
+        options: &ConcurrencyOptions,
+        seed: u64,
+        total: usize,
+        entries: &mut dyn Iterator<Item = Result<PlannedRecipient, String>>,
+        emit: &mut dyn FnMut(Value),
+    ) {
+        let job_id = job.job_id().to_string();
+        let mut run = ConcurrentRun {
+            order: ReorderBuffer::default(),
+            tracker: GreylistTracker::new(Duration::from_secs(job.options.greylist_delay_sec)),
+            deferred: Vec::new(),
+            counters: JobCounters::default(),
+        };
+        let mut connections = std::mem::take(&mut self.connections);
+
+        emit(json!({
+            "type": "job_started",
+            "job_id": job_id,
+            "total": total,
+            "seed": seed,
+            "connections": connections.len(),
+        }));
+
+        let context = ConnectionContext {
+            job,
+            sender: self.slots[0].sender.clone(),
+            return_path: self.slots[0].return_path.clone(),
+            size_limit: self.slots[0].size_limit,
+            smime: self.smime.clone(),
+            clock: Arc::clone(&self.clock),
+            cancel: Arc::clone(&self.cancel),
+            interval: options.connection_interval(),
+        };
+        let (work, queue) = sync_channel::<ConnectionWork>(connections.len());
+        let queue = Mutex::new(queue);
+        let (results, finished) = channel::<ConnectionResult>();
+        let completed = std::thread::scope(|scope| {
+            for (number, transport) in connections.iter_mut().enumerate() {
+                let (context, queue, results) = (&context, &queue, results.clone());
+                scope.spawn(move || run_connection(number + 1, transport, context, queue, results));
+            }
+            drop(results);
+
+            let mut completed = true;
+            for (seq, entry) in entries.enumerate() {
+                let PlannedRecipient { index, recipient, delay_after } = match entry {
+                    Ok(entry) => entry,
+                    Err(err) => {
+                        emit(json!({ "type": "error", "job_id": job_id, "error": err }));
+                        break;
+                    }
+                };
+                while let Ok(result) = finished.try_recv() {
+                    self.settle(job, &mut run, result.seq, Completion::Delivered(result), emit);
+                }
+                if self.is_cancelled() {
+                    completed = false;
+                    break;
+                }
+                if job.options.skip_sent && self.sent_store.is_sent(&recipient.email) {
+                    let event = already_sent_skipped_event(&job_id, index, &recipient);
+                    self.settle(job, &mut run, seq, Completion::Skipped(event), emit);
+                    continue;
+                }
+                let domain = recipient_domain(&recipient.email);
+                if let Some(until) = run.tracker.deferred_until(&domain, self.clock.now()) {
+                    let window = self.retry_window(until);
+                    let event = deferred_event(&job_id, index, &recipient, &domain, window, 0, "domain_deferred");
+                    let item = DeferredRecipient {
+                        index,
+                        recipient,
+                        next_attempt: until,
+                        attempts: 0,
+                    };
+                    self.settle(job, &mut run, seq, Completion::DomainDeferred(item, event), emit);
+                    continue;
+                }
+                if !self.wait_throttle(&job_id, index, emit) {
+                    completed = false;
+                    break;
+                }
+                let item = ConnectionWork {
+                    seq,
+                    index,
+                    recipient,
+                    delay_after: self.paced_delay(delay_after),
+                };
+                if work.send(item).is_err() {
+                    completed = false;
+                    break;
+                }
+            }
+            // 关闭派发通道后等待各连接发完手中的邮件，取消时未开始的邮件不再发送。
+            drop(work);
+            for result in finished {
+                self.settle(job, &mut run, result.seq, Completion::Delivered(result), emit);
+            }
+            completed && !self.is_cancelled()
+        });
+        self.connections = connections;
+
+        let ConcurrentRun {
+            mut tracker,
+            deferred,
+            mut counters,
+            ..
+        } = run;
+        if !completed || !self.drain_deferred(job, &mut tracker, deferred, &mut counters, emit) {
+            emit(cancelled_event(&job_id, &counters, total));
+            return;
+        }
+        emit(finished_event(&job_id, &counters, total));
+    }
+
+    /// 放入序号为 `seq` 的结果，并按派发顺序处理所有已就绪的结果。
+    fn settle(
+        &mut self,
+        job: &SendJob,
+        run: &mut ConcurrentRun,
+        seq: usize,
+        outcome: Completion,
+        emit: &mut dyn FnMut(Value),
+    ) {
+        for outcome in run.order.push(seq, outcome) {
+            self.settle_one(job, run, outcome, emit);
+        }
+    }
+
+    /// 处理一位收件人的结果：发出进度事件并更新计数、灰名单与发送记录。
+    fn settle_one(&mut self, job: &SendJob, run: &mut ConcurrentRun, outcome: Completion, emit: &mut dyn FnMut(Value)) {
+        let job_id = job.job_id();
+        let ConcurrentRun {
+            tracker,
+            deferred,
+            counters,
+            ..
+        } = run;
+        let result = match outcome {
+            Completion::Skipped(event) => {
+                counters.skipped += 1;
+                emit(event);
+                return;
+            }
+            Completion::DomainDeferred(item, event) => {
+                counters.deferred += 1;
+                emit(event);
+                deferred.push(item);
+                return;
+            }
+            Completion::Delivered(result) => result,
+        };
+        // 取消后才取到的收件人没有发送，也不发出事件。
+        let Some(sent) = result.sent else {
+            return;
+        };
+        emit(json!({
+            "type": "recipient_started",
+            "job_id": job_id,
+            "index": result.index,
+            "email": result.recipient.email,
+            "name": result.recipient.name,
+            "account_id": self.slots[0].account_id,
+            "connection": result.connection,
+        }));
+        for event in result.events {
+            emit(event);
+        }
+        let domain = recipient_domain(&result.recipient.email);
+        match sent {
+            Ok(()) => {
+                tracker.clear(&domain);
+                counters.success += 1;
+                self.record_sent(job, 0, result.index, &result.recipient, None, emit);
+            }
+            Err(failure) if is_greylisting(&failure) && job.options.greylist_max_attempts > 0 => {
+                let until = tracker.defer(&domain, &failure, self.clock.now());
+                counters.deferred += 1;
+                let window = self.retry_window(until);
+                emit(deferred_event(job_id, result.index, &result.recipient, &domain, window, 1, &failure.message));
+                deferred.push(DeferredRecipient {
+                    index: result.index,
+                    recipient: result.recipient,
+                    next_attempt: until,
+                    attempts: 1,
+                });
+            }
+            Err(failure) => record_failed(job_id, result.index, &result.recipient, failure.message, counters, emit),
+        }
+    }
+
+    /// 批量密送：每批一封邮件，To 为固定地址，本批收件人全部放入密送；进度事件仍按收件人发出。
+    fn run_batches(
+        &mut self,
+        job: &SendJob,
+        options: &BatchBccOptions,
+        plan: &SendPlan,
+        emit: &mut dyn FnMut(Value),
+    ) {
+        let job_id = job.job_id().to_string();
+        let total = plan.order.len();
+        let mut counters = JobCounters::default();
+        let visible = options.visible_recipient();
+
+        emit(json!({
+            "type": "job_started",
+            "job_id": job_id,
+            "total": total,
+            "batch_size": options.size,
+            "seed": plan.seed,
+        }));
+
+        let mut pending = Vec::new();
+        for (position, recipient) in plan.order.iter().cloned().enumerate() {
+            let index = position + 1;
+            if job.options.skip_sent && self.sent_store.is_sent(&recipient.email) {
+                counters.skipped += 1;
+                emit(already_sent_skipped_event(&job_id, index, &recipient));
+            } else {
+                pending.push((index, recipient));
+            }
+        }
+        let batches: Vec<&[(usize, RecipientEntry)]> = pending.chunks(options.size).collect();
+
+        for (position, batch) in batches.iter().enumerate() {
+            let number = position + 1;
+            let first_index = batch[0].0;
+            let last_index = batch[batch.len() - 1].0;
+            if self.is_cancelled() || !self.wait_throttle(&job_id, first_index, emit) {
+                emit(cancelled_event(&job_id, &counters, total));
+                return;
+            }
+            let Some(slot) = self.rotation.next_slot() else {
+                for (index, recipient) in batch.iter() {
+                    counters.skipped += 1;
+                    emit(daily_cap_skipped_event(&job_id, *index, recipient));
+                }
+                continue;
+            };
+            let id = batch_id(&job_id, number);
+            emit(json!({
+                "type": "batch_started",
+                "job_id": job_id,
+                "batch_id": id,
+                "batch": number,
+                "batches": batches.len(),
+                "size": batch.len(),
+                "first_index": first_index,
+                "last_index": last_index,
+                "to": visible.email,
+                "account_id": self.slots[slot].account_id,
+            }));
+            match self.deliver_batch(job, slot, &id, &visible, batch, emit) {
+                Ok(()) => {
+                    let message_id =
+                        job.message_id(&self.slots[slot].sender, &visible.email, &batch_bcc_addresses(job, batch));
+                    let sent = SentBatch {
+                        id: &id,
+                        number,
+                        size: batch.len(),
+                        to: &visible.email,
+                        message_id: &message_id,
+                    };
+                    for (index, recipient) in batch.iter() {
+                        counters.success += 1;
+                        self.record_sent(job, slot, *index, recipient, Some(sent), emit);
+                    }
+                }
+                // 等待重试期间被取消：本批既未成功也不计为失败。
+                Err(_) if self.is_cancelled() => {
+                    emit(cancelled_event(&job_id, &counters, total));
+                    return;
+                }
+                Err(error) => {
+                    for (index, recipient) in batch.iter() {
+                        record_failed(&job_id, *index, recipient, error.clone(), &mut counters, emit);
+                    }
+                }
+            }
+
+            if number < batches.len() && !self.wait_between(&job_id, last_index, plan.delay_after(number), emit) {
+                emit(cancelled_event(&job_id, &counters, total));
+                return;
+            }
+        }
+
+        emit(json!({
+            "type": "job_finished",
+            "job_id": job_id,
+            "success": counters.success,
+            "failed": counters.failed,
+            "skipped": counters.skipped,
+            "deferred": counters.deferred,
+            "batches": batches.len(),
+            "total": total,
+            "failures": counters.failures,
+        }));
+    }
+
+    /// 发送一批邮件；灰名单拒收时整批等待后重试，不进入逐个收件人的延后队列。
+    fn deliver_batch(
+        &mut self,
+        job: &SendJob,
+        slot: usize,
+        id: &str,
+        visible: &RecipientEntry,
+        batch: &[(usize, RecipientEntry)],
+        emit: &mut dyn FnMut(Value),
+    ) -> Result<(), String> {
+        let bcc = batch_bcc_addresses(job, batch);
+        let sender = &self.slots[slot].sender;
+        let return_path = self.slots[slot].return_path.as_ref();
+        let attachments = job.batch_attachment_delivery();
+        let message = build_message(job, sender, return_path, visible, &bcc, self.smime.as_deref(), attachments)?;
+        let first_index = batch[0].0;
+        let mut attempts = 0;
+        loop {
+            let failure = match self.transmit(job, slot, first_index, visible, Outgoing::Built(&message), emit) {
+                Ok(()) => return Ok(()),
+                Err(failure) => failure,
+            };
+            if !is_greylisting(&failure) || attempts >= job.options.greylist_max_attempts {
+                return Err(failure.message);
+            }
+            attempts += 1;
+            let wait = advertised_delay(&failure.message).unwrap_or(Duration::from_secs(job.options.greylist_delay_sec));
+            emit(json!({
+                "type": "batch_deferred",
+                "job_id": job.job_id(),
+                "batch_id": id,
+                "attempt": attempts,
+                "retry_after_sec": wait.as_secs(),
+                "reason": failure.message,
+            }));
+            let item = DeferredRecipient {
+                index: first_index,
+                recipient: visible.clone(),
+                next_attempt: self.clock.now() + wait,
+                attempts,
+            };
+            if !self.wait_deferred(job.job_id(), &item, wait, emit) {
+                return Err(failure.message);
+            }
+        }
+    }
+
+    /// 依次处理被灰名单延后的收件人；返回 false 表示过程中被取消。
+    fn drain_deferred(
+        &mut self,
+        job: &SendJob,
+        tracker: &mut GreylistTracker,
+        mut queue: Vec<DeferredRecipient>,
+        counters: &mut JobCounters,
+        emit: &mut dyn FnMut(Value),
+    ) -> bool {
+        let job_id = job.job_id().to_string();
+        while !queue.is_empty() {
+            let earliest = queue
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, item)| item.next_attempt)
+                .map(|(position, _)| position)
+                .unwrap_or_default();
+            let mut item = queue.remove(earliest);
+            let domain = recipient_domain(&item.recipient.email);
+
+            let wait = item.next_attempt.saturating_duration_since(self.clock.now());
+            if !self.wait_deferred(&job_id, &item, wait, emit) {
+                return false;
+            }
+            if !self.wait_throttle(&job_id, item.index, emit) {
+                return false;
+            }
+
+            let Some(slot) = self.rotation.next_slot() else {
+                counters.skipped += 1;
+                emit(daily_cap_skipped_event(&job_id, item.index, &item.recipient));
+                continue;
+            };
+            emit(json!({
+                "type": "recipient_started",
+                "job_id": job_id,
+                "index": item.index,
+                "email": item.recipient.email,
+                "name": item.recipient.name,
+                "account_id": self.slots[slot].account_id,
+                "attempt": item.attempts + 1,
+            }));
+            match self.deliver_deferred(job, slot, item.index, &item.recipient, emit) {
+                Delivery::Sent => {
+                    tracker.clear(&domain);
+                    counters.success += 1;
+                    self.record_sent(job, slot, item.index, &item.recipient, None, emit);
+                }
+                Delivery::Deferred(failure) if item.attempts < job.options.greylist_max_attempts => {
+                    item.attempts += 1;
+                    item.next_attempt = tracker.defer(&domain, &failure, self.clock.now());
+                    emit(deferred_event(
+                        &job_id,
+                        item.index,
+                        &item.recipient,
+                        &domain,
+                        self.retry_window(item.next_attempt),
+                        item.attempts,
+                        &failure.message,
+                    ));
+                    queue.push(item);
+                }
+                Delivery::Deferred(_) => record_failed(
+                    &job_id,
+                    item.index,
+                    &item.recipient,
+                    format!("灰名单重试 {} 次后仍被拒收", item.attempts),
+                    counters,
+                    emit,
+                ),
+                Delivery::Failed(message) => {
+                    record_failed(&job_id, item.index, &item.recipient, message, counters, emit)
+                }
+            }
+        }
+        true
+    }
+
+    fn deliver_deferred(
+        &mut self,
+        job: &SendJob,
+        slot: usize,
+        index: usize,
+        recipient: &RecipientEntry,
+        emit: &mut dyn FnMut(Value),
+    ) -> Delivery {
+        match self.deliver(job, slot, index, recipient, emit) {
+            Ok(()) => Delivery::Sent,
+            Err(failure) if is_greylisting(&failure) => Delivery::Deferred(failure),
+            Err(failure) => Delivery::Failed(failure.message),
+        }
+    }
+
+    /// 投递一封邮件，按重试策略对失败进行指数退避重试，每次重试都会发出 `recipient_retry` 事件。
+    fn deliver(
+        &mut self,
+        job: &SendJob,
+        slot: usize,
+        index: usize,
+        recipient: &RecipientEntry,
+        emit: &mut dyn FnMut(Value),
+    ) -> Result<(), SendFailure> {
+        let sender = &self.slots[slot].sender;
+        let return_path = self.slots[slot].return_path.as_ref();
+        let attachments = job.attachment_delivery(recipient);
+        let message = build_message(job, sender, return_path, recipient, &job.bcc, self.smime.as_deref(), attachments)
+            .map_err(|message| SendFailure { code: None, message })?;
+        self.transmit(job, slot, index, recipient, Outgoing::Built(&message), emit)
+    }
+
+    /// 投递预渲染落盘的邮件；无论成败都删除落盘文件。
+    fn deliver_spooled(
+        &mut self,
+        job: &SendJob,
+        slot: usize,
+        index: usize,
+        recipient: &RecipientEntry,
+        rendered: Result<SpooledMessage, String>,
+        emit: &mut dyn FnMut(Value),
+    ) -> Result<(), SendFailure> {
+        let spooled = rendered.map_err(|message| SendFailure { code: None, message })?;
+        let body = std::fs::read(&spooled.path);
+        let _ = std::fs::remove_file(&spooled.path);
+        let body = body.map_err(|err| SendFailure {
+            code: None,
+            message: format!("读取预渲染邮件失败: {err}"),
+        })?;
+        self.transmit(job, slot, index, recipient, Outgoing::Raw(&spooled.envelope, &body), emit)
+    }
+
+    fn transmit(
+        &mut self,
+        job: &SendJob,
+        slot: usize,
+        index: usize,
+        recipient: &RecipientEntry,
+        message: Outgoing<'_>,
+        emit: &mut dyn FnMut(Value),
+    ) -> Result<(), SendFailure> {
+        let waiter = Waiter {
+            clock: &*self.clock,
+            cancel: &self.cancel,
+        };
+        check_size_limit(message, self.slots[slot].size_limit)?;
+        send_with_retry(&mut self.slots[slot].transport, waiter, job, index, recipient, message, emit)
+    }
+
+    fn record_sent(
+        &mut self,
+        job: &SendJob,
+        slot: usize,
+        index: usize,
+        recipient: &RecipientEntry,
+        batch: Option<SentBatch<'_>>,
+        emit: &mut dyn FnMut(Value),
+    ) {
+        let message_id = match batch {
+            Some(batch) => batch.message_id.to_string(),
+            None => job.message_id(&self.slots[slot].sender, &recipient.email, &job.bcc),
+        };
+        let attachments_linked = if batch.is_some() {
+            job.batch_attachment_delivery().is_linked()
+        } else {
+            job.attachment_delivery(recipient).is_linked()
+        };
+        let queue_id = self.slots[slot].transport.last_queue_id();
+        let envelope = SentEnvelope {
+            from: &self.slots[slot].sender.email,
+            cc: &job.cc,
+            bcc: &job.bcc,
+            attachments_linked,
+            batch,
+            message_id: Some(&message_id),
+            queue_id: queue_id.as_deref(),
+        };
+        if let Err(err) = self
+            .sent_store
+            .append(&recipient.email, &recipient.name, job.job_id(), &envelope)
+        {
+            emit(json!({ "type": "error", "job_id": job.job_id(), "error": err }));
+        }
+        self.rotation.record_sent(slot);
+        if let Some(Ok(mut throttle)) = self.job_throttle.as_ref().map(|throttle| throttle.lock()) {
+            throttle.record(self.clock.unix_now());
+        }
+        if job.readback.is_some() {
+            self.sent_messages.push(SentMessage {
+                index,
+                email: recipient.email.clone(),
+                name: recipient.name.clone(),
+                message_id,
+            });
+        }
+        let mut event = json!({
+            "type": "recipient_sent",
+            "job_id": job.job_id(),
+            "index": index,
+            "email": recipient.email,
+            "name": recipient.name,
+            "account_id": self.slots[slot].account_id,
+        });
+        if attachments_linked {
+            event["attachments_linked"] = json!(true);
+        }
+        if let Some(batch) = batch {
+            event["batch_id"] = json!(batch.id);
+        }
+        if let Some(queue_id) = &queue_id {
+            event["queue_id"] = json!(queue_id);
+        }
+        if let Some(smime) = &self.smime {
+            let addresses: Vec<&str> = std::iter::once(recipient.email.as_str())
+                .chain(job.cc.iter().map(String::as_str))
+                .chain(job.bcc.iter().map(String::as_str))
+                .collect();
+            event["signed"] = json!(smime.signer.is_some());
+            event["encrypted"] = json!(smime.encryption_certs(&addresses).is_some());
+        }
+        emit(event);
+    }
+
+    /// 等待两封邮件之间的间隔；每秒重新按任务级限速计算间隔，等待中调整限速也会立即生效。
+    fn wait_between(&self, job_id: &str, index: usize, delay: u64, emit: &mut dyn FnMut(Value)) -> bool {
+        let mut waited = 0;
+        loop {
+            let delay = self.paced_delay(delay);
+            if waited >= delay {
+                break;
+            }
+            if self.is_cancelled() {
+                return false;
+            }
+            emit(json!({
+                "type": "inter_send_wait",
+                "job_id": job_id,
+                "index": index,
+                "next_index": index + 1,
+                "delay_sec": delay,
+                "remaining_sec": delay - waited,
+            }));
+            if self.sleep_with_cancel(Duration::from_secs(1)) {
+                return false;
+            }
+            waited += 1;
+        }
+        !self.is_cancelled()
+    }
+
+    /// 把计划的发送间隔限制在任务级限速的间隔上下限之内。
+    fn paced_delay(&self, delay: u64) -> u64 {
+        let Some(Ok(throttle)) = self.job_throttle.as_ref().map(|throttle| throttle.lock()) else {
+            return delay;
+        };
+        let limits = throttle.limits();
+        let max = limits.max_delay_sec.unwrap_or(u64::MAX);
+        delay.min(max).max(limits.min_delay_sec.unwrap_or(0))
+    }
+
+    /// 按全局限速与任务级限速等待到允许发送为止；返回 false 表示等待期间被取消。
+    fn wait_throttle(&self, job_id: &str, index: usize, emit: &mut dyn FnMut(Value)) -> bool {
+        if self.throttle.is_none() && self.job_throttle.is_none() {
+            return true;
+        }
+        loop {
+            if self.is_cancelled() {
+                return false;
+            }
+            let now = self.clock.unix_now();
+            let wait = [&self.throttle, &self.job_throttle]
+                .into_iter()
+                .flatten()
+                .filter_map(|throttle| throttle.lock().ok()?.wait_secs(now))
+                .max_by_key(|(wait, _)| *wait);
+            let Some((remaining, window)) = wait else {
+                return true;
+            };
+            emit(json!({
+                "type": "throttle_wait",
+                "job_id": job_id,
+                "index": index,
+                "window": window,
+                "remaining_sec": remaining,
+            }));
+            if self.sleep_with_cancel(Duration::from_secs(1)) {
+                return false;
+            }
+        }
+    }
+
+    fn wait_deferred(
+        &self,
+        job_id: &str,
+        item: &DeferredRecipient,
+        wait: Duration,
+        emit: &mut dyn FnMut(Value),
+    ) -> bool {
+        let mut remaining = wait.as_secs();
+        while remaining > 0 {
+            if self.is_cancelled() {
+                return false;
+            }
+            emit(json!({
+                "type": "deferred_wait",
+                "job_id": job_id,
+                "index": item.index,
+                "email": item.recipient.email,
+                "remaining_sec": remaining,
+            }));
+            if self.sleep_with_cancel(Duration::from_secs(1)) {
+                return false;
+            }
+            remaining -= 1;
+        }
+        !self.is_cancelled()
+    }
+
+    fn sleep_with_cancel(&self, duration: Duration) -> bool {
+        Waiter {
+            clock: &*self.clock,
+            cancel: &self.cancel,
+        }
+        .sleep(duration)
+    }
+
+    /// 距 `until` 还需等待的时长，以及对应的重试时间点。
+    fn retry_window(&self, until: Instant) -> (Duration, DateTime<Utc>) {
+        let retry_after = until.saturating_duration_since(self.clock.now());
+        (retry_after, self.clock.utc_now() + chrono::Duration::from_std(retry_after).unwrap_or_default())
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::SeqCst)
+    }
+}
+
+/// 发送线程等待时使用的时钟与取消标志。
+#[derive(Clone, Copy)]
+struct Waiter<'a> {
+    clock: &'a dyn Clock,
+    cancel: &'a AtomicBool,
+}
+
+impl Waiter<'_> {
+    /// 分段等待，期间被取消时提前返回；返回 true 表示已取消。
+    fn sleep(&self, duration: Duration) -> bool {
+        let deadline = self.clock.now() + duration;
+        while self.clock.now() < deadline {
+            if self.is_cancelled() {
+                return true;
+            }
+            self.clock.sleep(Duration::from_millis(100).min(deadline - self.clock.now()));
+        }
+        self.is_cancelled()
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::SeqCst)
+    }
+}
+
+/// 按重试策略发送已构建好的邮件；灰名单拒收不重试，直接返回给调用方。
+fn send_with_retry<T: MailTransport>(
+    transport: &mut T,
+    waiter: Waiter<'_>,
+    job: &SendJob,
+    index: usize,
+    recipient: &RecipientEntry,
+    message: Outgoing<'_>,
+    emit: &mut dyn FnMut(Value),
+) -> Result<(), SendFailure> {
+    let policy = job.options.retry_policy();
+    let mut attempt = 1;
+    loop {
+        let sent = match message {
+            Outgoing::Built(message) => transport.send_message(message),
+            Outgoing::Raw(envelope, body) => transport.send_raw(envelope, body),
+        };
+        let failure = match sent {
+            Ok(()) => return Ok(()),
+            // 灰名单拒收立即交给延后队列，短间隔重试只会再次被拒。
+            Err(failure) if is_greylisting(&failure) => return Err(failure),
+            Err(failure) => failure,
+        };
+        if !policy.should_retry(&failure, attempt) {
+            return Err(failure);
+        }
+        let delay = policy.backoff_delay(attempt);
+        emit(json!({
+            "type": "recipient_retry",
+            "job_id": job.job_id(),
+            "index": index,
+            "email": recipient.email,
+            "name": recipient.name,
+            "attempt": attempt,
+            "max_attempts": policy.max_attempts,
+            "delay_sec": delay.as_secs(),
+            "code": failure.code,
+            "error": failure.message,
+        }));
+        if waiter.sleep(delay) {
+            return Err(failure);
+        }
+        attempt += 1;
+    }
+}
+
+/// 服务器声明了 SIZE 上限时，在发送前拒绝超过上限的邮件（正文 + 附件），免得传输途中才收到 552。
+fn check_size_limit(message: Outgoing<'_>, limit: Option<u64>) -> Result<(), SendFailure> {
+    let Some(limit) = limit else {
+        return Ok(());
+    };
+    let size = match message {
+        Outgoing::Built(message) => message.formatted().len() as u64,
+        Outgoing::Raw(_, body) => body.len() as u64,
+    };
+    if size <= limit {
+        return Ok(());
+    }
+    Err(SendFailure {
+        code: None,
+        message: format!("邮件大小 {} 超过服务器 SIZE 上限 {}，未发送", format_bytes(size), format_bytes(limit)),
+    })
+}
+
+/// 各连接线程共享的只读发送上下文。
+struct ConnectionContext<'a> {
+    job: &'a SendJob,
+    sender: SenderConfig,
+    return_path: Option<Address>,
+    size_limit: Option<u64>,
+    smime: Option<Arc<SmimeContext>>,
+    clock: Arc<dyn Clock>,
+    cancel: Arc<AtomicBool>,
+    /// 同一连接两封邮件之间的最短间隔（每连接限速）。
+    interval: Duration,
+}
+
+/// 派发给连接线程的一位收件人；`seq` 为派发序号，用于按顺序归并结果。
+struct ConnectionWork {
+    seq: usize,
+    index: usize,
+    recipient: RecipientEntry,
+    delay_after: u64,
+}
+
+/// 连接线程的发送结果；`sent` 为空表示取消后未发送。重试事件随结果带回，由主线程按顺序发出。
+struct ConnectionResult {
+    seq: usize,
+    index: usize,
+    recipient: RecipientEntry,
+    connection: usize,
+    sent: Option<Result<(), SendFailure>>,
+    events: Vec<Value>,
+}
+
+/// 多连接发送过程中由主线程维护的状态。
+struct ConcurrentRun {
+    order: ReorderBuffer<Completion>,
+    tracker: GreylistTracker,
+    deferred: Vec<DeferredRecipient>,
+    counters: JobCounters,
+}
+
+/// 按派发顺序归并的一项：主线程直接决定的跳过 / 延后，或连接线程的发送结果。
+enum Completion {
+    Skipped(Value),
+    DomainDeferred(DeferredRecipient, Value),
+    Delivered(ConnectionResult),
+}
+
+/// 一条连接的发送循环：依次取出收件人并投递；每封之后等待任务间隔与每连接限速中较长的一个，
+/// 等待放在取下一位收件人之后，最后一封发完即可退出。
+fn run_connection<T: MailTransport>(
+    connection: usize,
+    transport: &mut T,
+    context: &ConnectionContext<'_>,
+    queue: &Mutex<Receiver<ConnectionWork>>,
+    results: Sender<ConnectionResult>,
+) {
+    let waiter = Waiter {
+        clock: &*context.clock,
+        cancel: &context.cancel,
+    };
+    let mut ready_at: Option<Instant> = None;
+    loop {
+        let work = match queue.lock() {
+            Ok(queue) => queue.recv(),
+            Err(_) => return,
+        };
+        let Ok(work) = work else {
+            return;
+        };
+        let wait = ready_at.map_or(Duration::ZERO, |at| at.saturating_duration_since(context.clock.now()));
+        let mut events = Vec::new();
+        let sent = (!waiter.sleep(wait)).then(|| {
+            let job = context.job;
+            let attachments = job.attachment_delivery(&work.recipient);
+            let smime = context.smime.as_deref();
+            build_message(job, &context.sender, context.return_path.as_ref(), &work.recipient, &job.bcc, smime, attachments)
+                .map_err(|message| SendFailure { code: None, message })
+                .and_then(|message| {
+                    check_size_limit(Outgoing::Built(&message), context.size_limit)?;
+                    let mut emit = |event| events.push(event);
+                    send_with_retry(transport, waiter, job, work.index, &work.recipient, Outgoing::Built(&message), &mut emit)
+                })
+        });
+        let pause = Duration::from_secs(work.delay_after).max(context.interval);
+        ready_at = Some(context.clock.now() + pause);
+        let result = ConnectionResult {
+            seq: work.seq,
+            index: work.index,
+            recipient: work.recipient,
+            connection,
+            sent,
+            events,
+        };
+        if results.send(result).is_err() {
+            return;
+        }
+    }
+}
+
+/// 待投递的邮件：内存中构建好的 `Message`，或预渲染落盘后读回的原始字节。
+#[derive(Clone, Copy)]
+enum Outgoing<'a> {
+    Built(&'a Message),
+    Raw(&'a Envelope, &'a [u8]),
+}
+
+/// 附件的投递方式：随信附带、改为正文中的下载链接，或（预估体积时）暂不附带。
+#[derive(Clone, Copy)]
+enum AttachmentDelivery<'a> {
+    Attached,
+    Linked(&'a [AttachmentLink]),
+    Omitted,
+}
+
+impl AttachmentDelivery<'_> {
+    fn is_linked(&self) -> bool {
+        matches!(self, AttachmentDelivery::Linked(_))
+    }
+}
+
+/// 渲染单个收件人的邮件但不附带附件，返回其字节数；附件部分由调用方另行估算。
+pub fn message_size_without_attachments(job: &SendJob, recipient: &RecipientEntry) -> Result<u64, String> {
+    // 账号轮换时任务本身没有发件人，用占位地址估算即可。
+    let placeholder = SenderConfig {
+        email: "sender@example.invalid".to_string(),
+        name: String::new(),
+    };
+    let sender = if job.sender.email.trim().is_empty() {
+        &placeholder
+    } else {
+        &job.sender
+    };
+    let message = build_message(job, sender, None, recipient, &job.bcc, None, AttachmentDelivery::Omitted)?;
+    Ok(message.formatted().len() as u64)
+}
+
+fn build_message(
+    job: &SendJob,
+    sender: &SenderConfig,
+    return_path: Option<&Address>,
+    recipient: &RecipientEntry,
+    bcc: &[String],
+    smime: Option<&SmimeContext>,
+    attachments: AttachmentDelivery<'_>,
+) -> Result<Message, String> {
+    let send_date = format_send_date(&Local::now());
+    let signature_name = if sender.name.trim().is_empty() {
+        sender.email.clone()
+    } else {
+        sender.name.trim().to_string()
+    };
+    let mut variables: HashMap<&str, String> = HashMap::from([
+        ("teacher_name", recipient.name.clone()),
+        ("teacher_email", recipient.email.clone()),
+        ("sender_name", signature_name.clone()),
+        ("signature_name", signature_name),
+        ("send_date", send_date),
+    ]);
+    for (name, value) in &recipient.fields {
+        variables.entry(name.as_str()).or_insert_with(|| value.clone());
+    }
+    let subject = render_template_text(&job.template.subject, &variables)?;
+    let body_html = match &job.template.body_html {
+        Some(html) if !html.trim().is_empty() => Some(render_template_text(html, &variables)?),
+        _ => None,
+    };
+    let mut body_text = match &body_html {
+        Some(html) if job.template.body_text.trim().is_empty() => html_to_plain_text(html),
+        _ => render_template_text(
+            &normalize_signature_tokens_in_template(&job.template.body_text),
+            &variables,
+        )?,
+    };
+    let mut body_html = body_html;
+    if let AttachmentDelivery::Linked(links) = attachments {
+        append_attachment_links(&mut body_text, body_html.as_mut(), links);
+    }
+    let attachment_paths: &[String] = match attachments {
+        AttachmentDelivery::Attached => &job.attachments,
+        AttachmentDelivery::Linked(_) | AttachmentDelivery::Omitted => &[],
+    };
+    build_email_message(&MessageContent {
+        sender_email: sender.email.trim(),
+        sender_name: sender.name.trim(),
+        recipient_email: recipient.email.trim(),
+        subject: &subject,
+        body_text: &body_text,
+        body_html: body_html.as_deref(),
+        attachments: attachment_paths,
+        inline_images: &job.inline_images,
+        headers: &job.headers,
+        cc: &job.cc,
+        bcc,
+        smime,
+        return_path,
+        ascii_attachment_names: job.options.ascii_attachment_names,
+        message_id: Some(&job.message_id(sender, &recipient.email, bcc)),
+        read_receipt_to: job.read_receipt_address(),
+    })
+}
+
+/// 在正文末尾列出附件下载链接（纯文本与 HTML 两个版本）。
+fn append_attachment_links(body_text: &mut String, body_html: Option<&mut String>, links: &[AttachmentLink]) {
+    body_text.push_str("\n\n附件较大，已改为下载链接：\n");
+    for link in links {
+        body_text.push_str(&format!("- {}（{}）：{}\n", link.name, format_bytes(link.size_bytes), link.url));
+    }
+    if let Some(html) = body_html {
+        let escape = |text: &str| {
+            text.replace('&', "&amp;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;")
+                .replace('"', "&quot;")
+        };
+        let items: String = links
+            .iter()
+            .map(|link| {
+                format!(
+                    "<li><a href=\"{}\">{}</a>（{}）</li>",
+                    escape(&link.url),
+                    escape(&link.name),
+                    format_bytes(link.size_bytes)
+                )
+            })
+            .collect();
+        let block = format!("<p>附件较大，已改为下载链接：</p><ul>{items}</ul>");
+        match html.to_ascii_lowercase().rfind("</body>") {
+            Some(position) => html.insert_str(position, &block),
+            None => html.push_str(&block),
+        }
+    }
+}
+
+fn record_failed(
+    job_id: &str,
+    index: usize,
+    recipient: &RecipientEntry,
+    error: String,
+    counters: &mut JobCounters,
+    emit: &mut dyn FnMut(Value),
+) {
+    counters.failed += 1;
+    counters.failures.push(json!({
+        "email": recipient.email,
+        "name": recipient.name,
+        "error": error,
+    }));
+    emit(json!({
+        "type": "recipient_failed",
+        "job_id": job_id,
+        "index": index,
+        "email": recipient.email,
+        "name": recipient.name,
+        "error": error,
+    }));
+}
+
+fn deferred_event(
+    job_id: &str,
+    index: usize,
+    recipient: &RecipientEntry,
+    domain: &str,
+    (retry_after, retry_at): (Duration, DateTime<Utc>),
+    attempt: u32,
+    reason: &str,
+) -> Value {
+    json!({
+        "type": "recipient_deferred",
+        "job_id": job_id,
+        "index": index,
+        "email": recipient.email,
+        "name": recipient.name,
+        "domain": domain,
+        "attempt": attempt,
+        "retry_after_sec": retry_after.as_secs(),
+        "retry_at": retry_at.to_rfc3339_opts(SecondsFormat::Secs, true),
+        "reason": reason,
+    })
+}
+
+/// 一批邮件的密送列表：本批收件人在前，任务级密送地址在后。
+fn batch_bcc_addresses(job: &SendJob, batch: &[(usize, RecipientEntry)]) -> Vec<String> {
+    batch
+        .iter()
+        .map(|(_, recipient)| recipient.email.trim().to_string())
+        .chain(job.bcc.iter().cloned())
+        .collect()
+}
+
+fn already_sent_skipped_event(job_id: &str, index: usize, recipient: &RecipientEntry) -> Value {
+    json!({
+        "type": "recipient_skipped",
+        "job_id": job_id,
+        "index": index,
+        "email": recipient.email,
+        "name": recipient.name,
+        "reason": "already_sent",
+    })
+}
+
+fn daily_cap_skipped_event(job_id: &str, index: usize, recipient: &RecipientEntry) -> Value {
+    json!({
+        "type": "recipient_skipped",
+        "job_id": job_id,
+        "index": index,
+        "email": recipient.email,
+        "name": recipient.name,
+        "reason": "daily_cap_reached",
+    })
+}
+
+fn finished_event(job_id: &str, counters: &JobCounters, total: usize) -> Value {
+    json!({
+        "type": "job_finished",
+        "job_id": job_id,
+        "success": counters.success,
+        "failed": counters.failed,
+        "skipped": counters.skipped,
+        "deferred": counters.deferred,
+        "total": total,
+        "failures": counters.failures,
+    })
+}
+
+fn cancelled_event(job_id: &str, counters: &JobCounters, total: usize) -> Value {
+    json!({
+        "type": "job_cancelled",
+        "job_id": job_id,
+        "success": counters.success,
+        "failed": counters.failed,
+        "skipped": counters.skipped,
+        "total": total,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        message_size_without_attachments, normalize_copy_addresses, validate_email, SendEngine, SendJob, SenderSlot,
+    };
+    use crate::clock::FrozenClock;
+    use crate::sent_store::{SentEnvelope, SentStore};
+    use crate::smtp_client::{MailTransport, SendFailure};
+    use crate::throttle::{unix_now, Throttle, ThrottleLimits};
+    use lettre::address::Envelope;
+    use lettre::Message;
+    use serde_json::{json, Value};
+    use std::collections::VecDeque;
+    use std::sync::atomic::AtomicBool;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    struct ScriptedTransport {
+        responses: VecDeque<Result<(), SendFailure>>,
+    }
+
+    impl MailTransport for ScriptedTransport {
+        fn send_message(&mut self, _message: &Message) -> Result<(), SendFailure> {
+            self.responses.pop_front().unwrap_or(Ok(()))
+        }
+
+        fn send_raw(&mut self, _envelope: &Envelope, _body: &[u8]) -> Result<(), SendFailure> {
+            self.responses.pop_front().unwrap_or(Ok(()))
+        }
+    }
+
+    fn job_payload(store: &std::path::Path) -> Value {
+        json!({
+            "job_id": "job-test",
+            "sender": { "email": "me@example.com", "name": "小王" },
+            "smtp": {
+                "host": "smtp.example.com", "port": 465, "username": "me@example.com",
+                "password": "secret", "use_ssl": true, "use_starttls": false, "timeout_sec": 30
+            },
+            "template": { "subject": "你好 {teacher_name}", "body_text": "{teacher_name}您好" },
+            "recipients": [
+                { "email": "a@grey.example", "name": "A" },
+                { "email": "b@grey.example", "name": "B" }
+            ],
+            "options": { "skip_sent": false, "greylist_delay_sec": 0 },
+            "paths": { "sent_store_file": store.to_string_lossy() }
+        })
+    }
+
+    #[test]
+    fn validates_email_syntax() {
+        assert!(validate_email("user@example.com", "邮箱").is_ok());
+        assert!(validate_email("user@example", "邮箱").is_err());
+        assert!(validate_email("us er@example.com", "邮箱").is_err());
+    }
+
+    #[test]
+    fn normalizes_copy_addresses() {
+        let addresses = [" Boss@example.edu ", "", "boss@EXAMPLE.edu", "hr@example.edu"].map(str::to_string);
+        assert_eq!(
+            normalize_copy_addresses(&addresses, "抄送"),
+            Ok(vec!["Boss@example.edu".to_string(), "hr@example.edu".to_string()])
+        );
+        let error = normalize_copy_addresses(&["hr@example.edu".to_string(), "hr".to_string()], "抄送").expect_err("bad");
+        assert!(error.contains("抄送[2]"), "{error}");
+    }
+
+    #[test]
+    fn skips_recipients_already_in_the_sent_store() {
+        let dir = std::env::temp_dir().join(format!("bes-engine-skip-{}", std::process::id()));
+        let mut payload = job_payload(&dir.join("sent_records.jsonl"));
+        payload["options"]["skip_sent"] = json!(true);
+        let job = SendJob::from_payload(payload).expect("valid job");
+        let mut store = SentStore::open(&job.sent_store_path(), None).expect("store");
+        let envelope = SentEnvelope {
+            from: "me@example.com",
+            cc: &[],
+            bcc: &[],
+            attachments_linked: false,
+            batch: None,
+            message_id: None,
+            queue_id: None,
+        };
+        store.append("A@Grey.example", "A", "job-earlier", &envelope).expect("earlier record");
+        let slot = SenderSlot {
+            account_id: "default".to_string(),
+            sender: job.sender.clone(),
+            return_path: None,
+            size_limit: None,
+            transport: ScriptedTransport { responses: VecDeque::new() },
+        };
+        let mut engine = SendEngine::new(vec![slot], store, Arc::new(AtomicBool::new(false)));
+        let mut events: Vec<Value> = Vec::new();
+        engine.run(&job, &mut |event| events.push(event));
+        let reopened = SentStore::open(&job.sent_store_path(), None).expect("reopen");
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let skipped: Vec<&Value> = events.iter().filter(|event| event["type"] == "recipient_skipped").collect();
+        assert_eq!(skipped.len(), 1);
+        assert_eq!((&skipped[0]["email"], &skipped[0]["reason"]), (&json!("a@grey.example"), &json!("already_sent")));
+        let finished = events.last().expect("finished");
+        assert_eq!((finished["success"].as_u64(), finished["skipped"].as_u64()), (Some(1), Some(1)));
+        assert!(reopened.is_sent("a@grey.example") && reopened.is_sent("b@grey.example"));
+    }
+
+    #[test]
+    fn stops_before_the_first_recipient_when_cancelled() {
+        let dir = std::env::temp_dir().join(format!("bes-engine-cancel-{}", std::process::id()));
+        let job = SendJob::from_payload(job_payload(&dir.join("sent_records.jsonl"))).expect("valid job");
+        let store = SentStore::open(&job.sent_store_path(), None).expect("store");
+        let slot = SenderSlot {
+            account_id: "default".to_string(),
+            sender: job.sender.clone(),
+            return_path: None,
+            size_limit: None,
+            transport: ScriptedTransport { responses: VecDeque::new() },
+        };
+        let mut engine = SendEngine::new(vec![slot], store, Arc::new(AtomicBool::new(true)));
+        let mut events: Vec<Value> = Vec::new();
+        engine.run(&job, &mut |event| events.push(event));
+        let reopened = SentStore::open(&job.sent_store_path(), None).expect("reopen");
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let types: Vec<&str> = events.iter().filter_map(|event| event["type"].as_str()).collect();
+        assert_eq!(types, ["job_started", "job_cancelled"]);
+        assert_eq!((events[1]["success"].as_u64(), events[1]["total"].as_u64()), (Some(0), Some(2)));
+        assert!(!reopened.is_sent("a@grey.example"));
+    }
+
+    #[test]
+    fn streams_recipients_from_file_in_chunks() {
+        let dir = std::env::temp_dir().join(format!("bes-engine-stream-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("temp dir");
+        let list = dir.join("recipients.csv");
+        let rows: String = (1..=7).map(|index| format!("r{index}@example.edu,R{index}\n")).collect();
+        std::fs::write(&list, rows + "r2@example.edu,Dup\n").expect("write csv");
+        let mut payload = job_payload(&dir.join("sent_records.jsonl"));
+        payload["recipients"] = json!([]);
+        payload["recipient_source"] = json!({ "path": list.to_string_lossy(), "chunk_size": 3 });
+        payload["options"] = json!({ "skip_sent": false, "randomize_order": true, "seed": 3 });
+        let job = SendJob::from_payload(payload.clone()).expect("valid job");
+        let store = SentStore::open(&job.sent_store_path(), None).expect("store");
+        let slot = SenderSlot {
+            account_id: "default".to_string(),
+            sender: job.sender.clone(),
+            return_path: None,
+            size_limit: None,
+            transport: ScriptedTransport { responses: VecDeque::new() },
+        };
+        let mut engine = SendEngine::new(vec![slot], store, Arc::new(AtomicBool::new(false)))
+            .with_recipient_exclusion(Arc::new(|email: &str| email == "r5@example.edu"));
+        let mut events: Vec<Value> = Vec::new();
+        engine.run(&job, &mut |event| events.push(event));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert_eq!(events[0]["type"], "recipient_stream_opened");
+        assert_eq!(events[0]["csv_format"]["encoding"], "UTF-8");
+        assert_eq!((events[0]["stats"]["duplicate_rows"].as_u64(), events[0]["excluded"].as_u64()), (Some(1), Some(1)));
+        assert_eq!(events[1]["total"], 6);
+        let mut sent: Vec<&str> = events
+            .iter()
+            .filter(|event| event["type"] == "recipient_sent")
+            .map(|event| event["email"].as_str().unwrap_or_default())
+            .collect();
+        // 随机顺序只在每批（3 行）内部打乱。
+        assert!(sent[..3].iter().all(|email| ["r1", "r2", "r3"].contains(&email.trim_end_matches("@example.edu"))));
+        sent.sort();
+        let expected: Vec<String> = [1, 2, 3, 4, 6, 7].iter().map(|index| format!("r{index}@example.edu")).collect();
+        assert_eq!(sent, expected);
+        assert_eq!(events.last().expect("finished")["success"], 6);
+
+        payload["recipients"] = json!([{ "email": "a@example.edu", "name": "A" }]);
+        assert!(SendJob::from_payload(payload).is_err());
+    }
+
+    #[test]
+    fn interleaves_canaries_every_n_recipients() {
+        let dir = std::env::temp_dir().join(format!("bes-engine-canary-{}", std::process::id()));
+        let mut payload = job_payload(&dir.join("sent_records.jsonl"));
+        payload["recipients"] = json!((1..=5)
+            .map(|index| json!({ "email": format!("r{index}@example.edu"), "name": format!("R{index}") }))
+            .collect::<Vec<_>>());
+        payload["cc"] = json!(["boss@example.edu"]);
+        payload["canary"] = json!({ "mailboxes": [{ "email": "me+canary@gmail.com" }], "every": 2 });
+        let job = SendJob::from_payload(payload).expect("valid job");
+        let store = SentStore::open(&job.sent_store_path(), None).expect("store");
+        let slot = SenderSlot {
+            account_id: "default".to_string(),
+            sender: job.sender.clone(),
+            return_path: None,
+            size_limit: None,
+            transport: ScriptedTransport { responses: VecDeque::new() },
+        };
+        let mut engine = SendEngine::new(vec![slot], store, Arc::new(AtomicBool::new(false)));
+        let mut events: Vec<Value> = Vec::new();
+        engine.run(&job, &mut |event| events.push(event));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let canaries: Vec<&Value> = events.iter().filter(|event| event["type"] == "canary_sent").collect();
+        assert_eq!(canaries.len(), 2);
+        assert_eq!((canaries[0]["after_index"].as_u64(), canaries[1]["round"].as_u64()), (Some(2), Some(2)));
+        assert_eq!(canaries[0]["email"], "me+canary@gmail.com");
+        assert_ne!(canaries[0]["message_id"], canaries[1]["message_id"]);
+        let finished = events.last().expect("finished");
+        assert_eq!((finished["success"].as_u64(), finished["total"].as_u64()), (Some(5), Some(5)));
+    }
+
+    #[test]
+    fn defers_greylisted_recipients_instead_of_failing() {
+        let dir = std::env::temp_dir().join(format!("bes-engine-{}", std::process::id()));
+        let store_path = dir.join("sent_records.jsonl");
+        let job = SendJob::from_payload(job_payload(&store_path)).expect("valid job");
+        let transport = ScriptedTransport {
+            responses: VecDeque::from([Err(SendFailure {
+                code: Some(451),
+                message: "transient error (451): 4.7.1 Greylisted, please try again later".to_string(),
+            })]),
+        };
+        let store = SentStore::open(&job.sent_store_path(), None).expect("store");
+        let slot = SenderSlot {
+            account_id: "default".to_string(),
+            sender: job.sender.clone(),
+            return_path: None,
+            size_limit: None,
+            transport,
+        };
+        let mut engine = SendEngine::new(vec![slot], store, Arc::new(AtomicBool::new(false)));
+        let mut events: Vec<Value> = Vec::new();
+        engine.run(&job, &mut |event| events.push(event));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let types: Vec<&str> = events.iter().filter_map(|event| event["type"].as_str()).collect();
+        assert_eq!(
+            types,
+            vec![
+                "job_started",
+                "recipient_started",
+                "recipient_deferred",
+                "recipient_started",
+                "recipient_sent",
+                "recipient_started",
+                "recipient_sent",
+                "job_finished",
+            ]
+        );
+        assert_eq!(events[2]["email"], "a@grey.example");
+        assert_eq!(events[5]["email"], "a@grey.example");
+        let finished = events.last().expect("finished event");
+        assert_eq!(finished["success"], 2);
+        assert_eq!(finished["failed"], 0);
+        assert_eq!(finished["deferred"], 1);
+    }
+
+    #[test]
+    fn seeded_job_replays_identically_on_frozen_clock() {
+        let dir = std::env::temp_dir().join(format!("bes-engine-seeded-{}", std::process::id()));
+        let mut payload = job_payload(&dir.join("sent_records.jsonl"));
+        payload.as_object_mut().expect("payload").remove("job_id");
+        payload["recipients"] = json!((1..=6)
+            .map(|index| json!({ "email": format!("t{index}@grey.example"), "name": format!("T{index}") }))
+            .collect::<Vec<_>>());
+        payload["options"] = json!({
+            "skip_sent": false, "greylist_delay_sec": 300, "min_delay_sec": 10, "max_delay_sec": 60,
+            "randomize_order": true, "seed": 7
+        });
+        let run = || {
+            let job = SendJob::from_payload(payload.clone()).expect("valid job");
+            let transport = ScriptedTransport {
+                responses: VecDeque::from([Err(SendFailure {
+                    code: Some(451),
+                    message: "transient error (451): 4.7.1 Greylisted, please try again later".to_string(),
+                })]),
+            };
+            let store = SentStore::open(&job.sent_store_path(), None).expect("store");
+            let slot = SenderSlot {
+                account_id: "default".to_string(),
+                sender: job.sender.clone(),
+                return_path: None,
+                size_limit: None,
+                transport,
+            };
+            let clock = Arc::new(FrozenClock::new(chrono::DateTime::UNIX_EPOCH));
+            let mut engine = SendEngine::new(vec![slot], store, Arc::new(AtomicBool::new(false)))
+                .with_clock(clock.clone());
+            let mut events: Vec<Value> = Vec::new();
+            engine.run(&job, &mut |event| events.push(event));
+            let _ = std::fs::remove_dir_all(&dir);
+            (events, clock.elapsed())
+        };
+
+        let (first, elapsed) = run();
+        let (second, _) = run();
+        assert_eq!(first, second);
+        assert_eq!(first[0]["seed"], 7);
+        assert_eq!(first[0]["job_id"], "job-0000000000000007");
+        assert_eq!(first.last().expect("finished")["success"], 6);
+        let waited: u64 = first
+            .iter()
+            .filter(|event| event["type"] == "inter_send_wait" && event["remaining_sec"] == 1)
+            .map(|event| event["delay_sec"].as_u64().unwrap_or_default())
+            .sum();
+        assert!(elapsed >= Duration::from_secs(waited) && waited >= 50);
+    }
+
+    #[test]
+    fn sends_recipients_in_bcc_batches() {
+        let dir = std::env::temp_dir().join(format!("bes-engine-batch-{}", std::process::id()));
+        let store_path = dir.join("sent_records.jsonl");
+        let mut payload = job_payload(&store_path);
+        payload["recipients"] = json!((1..=5)
+            .map(|index| json!({ "email": format!("t{index}@example.edu"), "name": format!("T{index}") }))
+            .collect::<Vec<_>>());
+        payload["batch_bcc"] = json!({ "size": 2, "to_email": "news@example.com" });
+        let job = SendJob::from_payload(payload).expect("valid job");
+        let transport = ScriptedTransport {
+            responses: VecDeque::from([
+                Ok(()),
+                Err(SendFailure {
+                    code: Some(550),
+                    message: "permanent error (550): 5.7.1 Too many recipients".to_string(),
+                }),
+            ]),
+        };
+        let store = SentStore::open(&job.sent_store_path(), None).expect("store");
+        let slot = SenderSlot {
+            account_id: "default".to_string(),
+            sender: job.sender.clone(),
+            return_path: None,
+            size_limit: None,
+            transport,
+        };
+        let mut engine = SendEngine::new(vec![slot], store, Arc::new(AtomicBool::new(false)));
+        let mut events: Vec<Value> = Vec::new();
+        engine.run(&job, &mut |event| events.push(event));
+        let records = std::fs::read_to_string(&store_path).unwrap_or_default();
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let batches: Vec<&Value> = events.iter().filter(|event| event["type"] == "batch_started").collect();
+        assert_eq!(batches.len(), 3);
+        assert_eq!(batches[1]["batch_id"], "job-test-b0002");
+        assert_eq!(batches[1]["first_index"], 3);
+        let finished = events.last().expect("finished event");
+        assert_eq!(finished["success"], 3);
+        assert_eq!(finished["failed"], 2);
+        assert_eq!(finished["batches"], 3);
+
+        let records: Vec<Value> = records
+            .lines()
+            .map(|line| serde_json::from_str(line).expect("record json"))
+            .collect();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[1]["email"], "t2@example.edu");
+        assert_eq!(records[1]["batch"]["id"], "job-test-b0001");
+        assert_eq!(records[2]["batch"]["number"], 3);
+        assert_eq!(records[2]["batch"]["size"], 1);
+    }
+
+    #[test]
+    fn sends_prerendered_messages_from_spool() {
+        let dir = std::env::temp_dir().join(format!("bes-engine-render-{}", std::process::id()));
+        let store_path = dir.join("sent_records.jsonl");
+        let mut payload = job_payload(&store_path);
+        payload["recipients"] = json!((1..=7)
+            .map(|index| json!({ "email": format!("t{index}@example.edu"), "name": format!("T{index}") }))
+            .collect::<Vec<_>>());
+        payload["render"] = json!({ "parallel": true, "threads": 2, "ahead": 3 });
+        let job = SendJob::from_payload(payload).expect("valid job");
+        let spool_dir = job.spool_dir();
+        let transport = ScriptedTransport {
+            responses: VecDeque::from([
+                Ok(()),
+                Err(SendFailure {
+                    code: Some(550),
+                    message: "permanent error (550): 5.1.1 User unknown".to_string(),
+                }),
+            ]),
+        };
+        let store = SentStore::open(&job.sent_store_path(), None).expect("store");
+        let slot = SenderSlot {
+            account_id: "default".to_string(),
+            sender: job.sender.clone(),
+            return_path: None,
+            size_limit: None,
+            transport,
+        };
+        let mut engine = SendEngine::new(vec![slot], store, Arc::new(AtomicBool::new(false)));
+        let mut events: Vec<Value> = Vec::new();
+        engine.run(&job, &mut |event| events.push(event));
+        let spool_left = spool_dir.exists();
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert!(events.iter().all(|event| event["type"] != "error"));
+        let sent: Vec<&str> = events
+            .iter()
+            .filter(|event| event["type"] == "recipient_sent")
+            .filter_map(|event| event["email"].as_str())
+            .collect();
+        assert_eq!(sent.len(), 6);
+        assert!(!sent.contains(&"t2@example.edu"));
+        let finished = events.last().expect("finished event");
+        assert_eq!(finished["failed"], 1);
+        assert!(!spool_left);
+    }
+
+    #[test]
+    fn sends_single_message_without_queue_but_within_quota() {
+        let dir = std::env::temp_dir().join(format!("bes-engine-single-{}", std::process::id()));
+        let mut payload = job_payload(&dir.join("sent_records.jsonl"));
+        payload["recipients"] = json!([{ "email": "a@example.edu", "name": "A" }]);
+        payload["options"] = json!({ "skip_sent": true, "min_delay_sec": 30, "max_delay_sec": 60 });
+        let job = SendJob::from_payload(payload).expect("valid job");
+        let limits = ThrottleLimits {
+            per_minute: Some(2),
+            ..ThrottleLimits::default()
+        };
+        let throttle = Arc::new(Mutex::new(Throttle::new(limits, Vec::new())));
+        let store = SentStore::open(&job.sent_store_path(), None).expect("store");
+        let slot = SenderSlot {
+            account_id: "default".to_string(),
+            sender: job.sender.clone(),
+            return_path: None,
+            size_limit: None,
+            transport: ScriptedTransport { responses: VecDeque::new() },
+        };
+        let mut engine =
+            SendEngine::new(vec![slot], store, Arc::new(AtomicBool::new(false))).with_throttle(Arc::clone(&throttle));
+        let mut events: Vec<Value> = Vec::new();
+        // 已发送过的收件人也照常发送，调用方负责把 `recipient_sent` 计入限速。
+        for _ in 0..2 {
+            engine.send_single(&job, &mut |event| events.push(event)).expect("sent");
+            throttle.lock().expect("throttle").record(unix_now());
+        }
+        assert!(engine.send_single(&job, &mut |_| {}).is_err_and(|err| err.contains("per_minute")));
+        let store = SentStore::open(&job.sent_store_path(), None).expect("reopen store");
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let types: Vec<&str> = events.iter().filter_map(|event| event["type"].as_str()).collect();
+        assert_eq!(types, ["recipient_sent", "recipient_sent"]);
+        assert!(store.is_sent("a@example.edu"));
+
+        let job = SendJob::from_payload(job_payload(&dir.join("sent_records.jsonl"))).expect("valid job");
+        assert!(engine.send_single(&job, &mut |_| {}).is_err_and(|err| err.contains("一位收件人")));
+    }
+
+    #[test]
+    fn sends_over_parallel_connections_in_plan_order() {
+        let dir = std::env::temp_dir().join(format!("bes-engine-concurrent-{}", std::process::id()));
+        let mut payload = job_payload(&dir.join("sent_records.jsonl"));
+        payload["recipients"] = json!((1..=9)
+            .map(|index| json!({ "email": format!("t{index}@example.edu"), "name": format!("T{index}") }))
+            .collect::<Vec<_>>());
+        payload["concurrency"] = json!({ "connections": 3 });
+        let job = SendJob::from_payload(payload.clone()).expect("valid job");
+        let store = SentStore::open(&job.sent_store_path(), None).expect("store");
+        let slot = SenderSlot {
+            account_id: "default".to_string(),
+            sender: job.sender.clone(),
+            return_path: None,
+            size_limit: None,
+            transport: ScriptedTransport { responses: VecDeque::new() },
+        };
+        let rejecting = ScriptedTransport {
+            responses: VecDeque::from([Err(SendFailure {
+                code: Some(550),
+                message: "permanent error (550): 5.1.1 User unknown".to_string(),
+            })]),
+        };
+        let idle = || ScriptedTransport { responses: VecDeque::new() };
+        let connections = vec![rejecting, idle(), idle()];
+        let mut engine =
+            SendEngine::new(vec![slot], store, Arc::new(AtomicBool::new(false))).with_connections(connections);
+        let mut events: Vec<Value> = Vec::new();
+        engine.run(&job, &mut |event| events.push(event));
+        let records = std::fs::read_to_string(job.sent_store_path()).unwrap_or_default();
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert_eq!(events[0]["connections"], 3);
+        let started: Vec<u64> = events
+            .iter()
+            .filter(|event| event["type"] == "recipient_started")
+            .filter_map(|event| event["index"].as_u64())
+            .collect();
+        assert_eq!(started, (1..=9).collect::<Vec<u64>>());
+        assert!(events
+            .iter()
+            .filter(|event| event["type"] == "recipient_started")
+            .all(|event| (1..=3).contains(&event["connection"].as_u64().unwrap_or_default())));
+        let finished = events.last().expect("finished event");
+        assert_eq!((finished["success"].as_u64(), finished["failed"].as_u64()), (Some(8), Some(1)));
+        assert_eq!(records.lines().count(), 8);
+
+        payload["rotation"] = json!({ "account_ids": ["a"] });
+        assert!(SendJob::from_payload(payload).is_err_and(|err| err.contains("并发")));
+    }
+
+    #[test]
+    fn applies_job_throttle_changes_while_sending() {
+        let dir = std::env::temp_dir().join(format!("bes-engine-job-throttle-{}", std::process::id()));
+        let mut payload = job_payload(&dir.join("sent_records.jsonl"));
+        payload["recipients"] = json!((1..=3)
+            .map(|index| json!({ "email": format!("t{index}@example.edu"), "name": format!("T{index}") }))
+            .collect::<Vec<_>>());
+        let job = SendJob::from_payload(payload).expect("valid job");
+        let store = SentStore::open(&job.sent_store_path(), None).expect("store");
+        let slot = SenderSlot {
+            account_id: "default".to_string(),
+            sender: job.sender.clone(),
+            return_path: None,
+            size_limit: None,
+            transport: ScriptedTransport { responses: VecDeque::new() },
+        };
+        let limits = ThrottleLimits {
+            min_delay_sec: Some(5),
+            ..ThrottleLimits::default()
+        };
+        let throttle = Arc::new(Mutex::new(Throttle::new(limits, Vec::new())));
+        let clock = Arc::new(FrozenClock::new(chrono::DateTime::from_timestamp(1_700_000_000, 0).expect("time")));
+        let mut engine = SendEngine::new(vec![slot], store, Arc::new(AtomicBool::new(false)))
+            .with_clock(clock.clone())
+            .with_job_throttle(Arc::clone(&throttle));
+        let mut events: Vec<Value> = Vec::new();
+        engine.run(&job, &mut |event| {
+            // 第二封发出后操作人放宽间隔。
+            if event["type"] == "recipient_sent" && event["index"] == 2 {
+                let faster = ThrottleLimits {
+                    min_delay_sec: Some(1),
+                    ..ThrottleLimits::default()
+                };
+                throttle.lock().expect("throttle").set_limits(faster);
+            }
+            events.push(event);
+        });
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let delays: Vec<(u64, u64)> = events
+            .iter()
+            .filter(|event| event["type"] == "inter_send_wait" && event["remaining_sec"] == 1)
+            .map(|event| (event["index"].as_u64().unwrap_or_default(), event["delay_sec"].as_u64().unwrap_or_default()))
+            .collect();
+        assert_eq!(delays, [(1, 5), (2, 1)]);
+        assert_eq!(clock.elapsed(), Duration::from_secs(6));
+        assert_eq!(throttle.lock().expect("throttle").history().len(), 3);
+    }
+
+    #[test]
+    fn retries_transient_failures_with_backoff() {
+        let dir = std::env::temp_dir().join(format!("bes-engine-retry-{}", std::process::id()));
+        let store_path = dir.join("sent_records.jsonl");
+        let mut payload = job_payload(&store_path);
+        payload["options"]["retry"] = json!({ "max_attempts": 3, "backoff_base_sec": 0, "backoff_max_sec": 0 });
+        let job = SendJob::from_payload(payload).expect("valid job");
+        let transport = ScriptedTransport {
+            responses: VecDeque::from([
+                Err(SendFailure {
+                    code: Some(421),
+                    message: "transient error (421): 4.3.2 Service not available".to_string(),
+                }),
+                Ok(()),
+                Err(SendFailure {
+                    code: Some(550),
+                    message: "permanent error (550): 5.1.1 User unknown".to_string(),
+                }),
+            ]),
+        };
+        let store = SentStore::open(&job.sent_store_path(), None).expect("store");
+        let slot = SenderSlot {
+            account_id: "default".to_string(),
+            sender: job.sender.clone(),
+            return_path: None,
+            size_limit: None,
+            transport,
+        };
+        let mut engine = SendEngine::new(vec![slot], store, Arc::new(AtomicBool::new(false)));
+        let mut events: Vec<Value> = Vec::new();
+        engine.run(&job, &mut |event| events.push(event));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let types: Vec<&str> = events.iter().filter_map(|event| event["type"].as_str()).collect();
+        assert_eq!(
+            types,
+            vec![
+                "job_started",
+                "recipient_started",
+                "recipient_retry",
+                "recipient_sent",
+                "recipient_started",
+                "recipient_failed",
+                "job_finished",
+            ]
+        );
+        assert_eq!(events[2]["attempt"], 1);
+        assert_eq!(events[2]["code"], 421);
+    }
+
+    #[test]
+    fn rejects_messages_over_the_server_size_limit_before_sending() {
+        let dir = std::env::temp_dir().join(format!("bes-engine-size-{}", std::process::id()));
+        let mut payload = job_payload(&dir.join("sent_records.jsonl"));
+        payload["recipients"][1]["name"] = json!("B".repeat(4000));
+        let job = SendJob::from_payload(payload).expect("valid job");
+        let small = message_size_without_attachments(&job, &job.recipients[0]).expect("size");
+        let transport = ScriptedTransport {
+            responses: VecDeque::from([
+                Ok(()),
+                Err(SendFailure {
+                    code: Some(552),
+                    message: "permanent error (552): 5.3.4 Message size exceeds fixed limit".to_string(),
+                }),
+            ]),
+        };
+        let store = SentStore::open(&job.sent_store_path(), None).expect("store");
+        let slot = SenderSlot {
+            account_id: "default".to_string(),
+            sender: job.sender.clone(),
+            return_path: None,
+            size_limit: Some(small + 1000),
+            transport,
+        };
+        let mut engine = SendEngine::new(vec![slot], store, Arc::new(AtomicBool::new(false)));
+        let mut events: Vec<Value> = Vec::new();
+        engine.run(&job, &mut |event| events.push(event));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let outcomes: Vec<&Value> = events
+            .iter()
+            .filter(|event| event["type"] == "recipient_sent" || event["type"] == "recipient_failed")
+            .collect();
+        assert_eq!(outcomes.len(), 2);
+        assert_eq!((&outcomes[0]["type"], &outcomes[0]["index"]), (&json!("recipient_sent"), &json!(1)));
+        assert_eq!(outcomes[1]["type"], "recipient_failed");
+        let error = outcomes[1]["error"].as_str().unwrap_or_default();
+        assert!(error.contains("超过服务器 SIZE 上限"), "{error}");
+    }
+}
@@ -0,0 +1,439 @@
+use crate::clock::Clock;
+use crate::engine::RecipientEntry;
+use crate::greylist::recipient_domain;
+use crate::net_policy::{http_client, NetworkPolicy};
+use crate::template::BUILTIN_VARIABLES;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+pub type FieldMap = BTreeMap<String, String>;
+
+/// 补充数据的来源。HTTP 接口收到 POST 的 JSON，命令从标准输入读取同样的 JSON：
+/// `{"key": "domain", "values": [...]}`，返回 `{"results": {"<value>": {"company": "..."}}}`。
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum EnrichmentSource {
+    Http {
+        url: String,
+        /// 以 `Authorization: Bearer` 发送的令牌。
+        #[serde(default)]
+        token: Option<String>,
+    },
+    Command {
+        program: String,
+        #[serde(default)]
+        args: Vec<String>,
+    },
+}
+
+impl EnrichmentSource {
+    /// 来源标识；来源变化后旧缓存不再使用。
+    fn fingerprint(&self) -> String {
+        match self {
+            EnrichmentSource::Http { url, .. } => format!("http {}", url.trim()),
+            EnrichmentSource::Command { program, args } => format!("command {} {}", program.trim(), args.join(" ")),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum EnrichmentKey {
+    /// 按收件域名查询（如域名 → 单位名称）。
+    #[default]
+    Domain,
+    Email,
+}
+
+impl EnrichmentKey {
+    fn value_for(self, email: &str) -> String {
+        match self {
+            EnrichmentKey::Domain => recipient_domain(email),
+            EnrichmentKey::Email => email.trim().to_lowercase(),
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            EnrichmentKey::Domain => "domain",
+            EnrichmentKey::Email => "email",
+        }
+    }
+}
+
+/// 联系人补充设置，保存在本机应用设置中。
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct EnrichmentSettings {
+    pub source: Option<EnrichmentSource>,
+    pub key: EnrichmentKey,
+    /// 每次查询携带的键数量。
+    pub batch_size: usize,
+    /// 每分钟最多查询次数；0 表示不限。
+    pub requests_per_minute: u32,
+    /// 缓存有效天数；0 表示每次都重新查询。
+    pub cache_days: u32,
+    pub timeout_sec: u64,
+}
+
+impl Default for EnrichmentSettings {
+    fn default() -> Self {
+        EnrichmentSettings {
+            source: None,
+            key: EnrichmentKey::Domain,
+            batch_size: 50,
+            requests_per_minute: 60,
+            cache_days: 30,
+            timeout_sec: 30,
+        }
+    }
+}
+
+impl EnrichmentSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.batch_size == 0 || self.batch_size > 1000 {
+            return Err("每批查询数量需在 1 到 1000 之间".to_string());
+        }
+        if self.timeout_sec == 0 {
+            return Err("查询超时时间必须大于 0".to_string());
+        }
+        match &self.source {
+            Some(EnrichmentSource::Http { url, .. }) => {
+                let url = reqwest::Url::parse(url.trim()).map_err(|err| format!("补充数据接口地址无效: {err}"))?;
+                if url.scheme() != "https" && url.scheme() != "http" {
+                    return Err("补充数据接口仅支持 http/https".to_string());
+                }
+            }
+            Some(EnrichmentSource::Command { program, .. }) if program.trim().is_empty() => {
+                return Err("未填写补充数据命令".to_string());
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn min_interval(&self) -> Duration {
+        if self.requests_per_minute == 0 {
+            Duration::ZERO
+        } else {
+            Duration::from_millis(60_000 / u64::from(self.requests_per_minute))
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Default)]
+pub struct CachedFields {
+    #[serde(default)]
+    pub fields: FieldMap,
+    #[serde(default)]
+    pub fetched_at: u64,
+}
+
+/// 查询结果缓存，键为 `domain:example.edu` / `email:a@example.edu`；未查到的键也会缓存为空结果。
+#[derive(Deserialize, Serialize, Default)]
+pub struct EnrichmentCache {
+    #[serde(default)]
+    pub source: String,
+    #[serde(default)]
+    pub entries: BTreeMap<String, CachedFields>,
+}
+
+impl EnrichmentCache {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        if !path.exists() {
+            return Ok(EnrichmentCache::default());
+        }
+        let text = fs::read_to_string(path).map_err(|err| format!("读取补充数据缓存失败: {err}"))?;
+        serde_json::from_str(&text).map_err(|err| format!("补充数据缓存格式错误: {err}"))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let text = serde_json::to_string_pretty(self).map_err(|err| err.to_string())?;
+        fs::write(path, text).map_err(|err| format!("写入补充数据缓存失败: {err}"))
+    }
+
+    /// 合并另一份缓存（如本次查询结果）；来源不同时以新来源为准。
+    pub fn merge(&mut self, other: EnrichmentCache) {
+        if self.source != other.source {
+            self.source = other.source;
+            self.entries.clear();
+        }
+        self.entries.extend(other.entries);
+    }
+}
+
+#[derive(Serialize)]
+pub struct EnrichmentReport {
+    pub keys: usize,
+    pub cached: usize,
+    pub looked_up: usize,
+    pub batches: usize,
+    /// 至少补充了一个字段的收件人数。
+    pub enriched: usize,
+    /// 可在模板中使用的补充字段。
+    pub fields: BTreeSet<String>,
+    /// 因不是合法变量名或与内置变量重名而忽略的字段。
+    pub ignored_fields: BTreeSet<String>,
+    pub recipients: Vec<RecipientEntry>,
+}
+
+/// 为收件人补充字段：先查缓存，其余按批调用 `lookup`，批次之间按每分钟次数限速。
+/// 已有的同名字段不会被覆盖。
+pub fn enrich_recipients(
+    mut recipients: Vec<RecipientEntry>,
+    settings: &EnrichmentSettings,
+    cache: &mut EnrichmentCache,
+    clock: &dyn Clock,
+    mut lookup: impl FnMut(&[String]) -> Result<BTreeMap<String, FieldMap>, String>,
+) -> Result<EnrichmentReport, String> {
+    let source = settings
+        .source
+        .as_ref()
+        .ok_or_else(|| "未配置补充数据来源".to_string())?;
+    settings.validate()?;
+    let fingerprint = source.fingerprint();
+    if cache.source != fingerprint {
+        cache.source = fingerprint;
+        cache.entries.clear();
+    }
+
+    let prefix = settings.key.as_str();
+    let keys: BTreeSet<String> = recipients
+        .iter()
+        .map(|recipient| settings.key.value_for(&recipient.email))
+        .filter(|key| !key.is_empty())
+        .collect();
+    let max_age = u64::from(settings.cache_days) * 86_400;
+    let now = clock.unix_now();
+    let pending: Vec<String> = keys
+        .iter()
+        .filter(|key| {
+            cache
+                .entries
+                .get(&format!("{prefix}:{key}"))
+                .is_none_or(|cached| now.saturating_sub(cached.fetched_at) >= max_age)
+        })
+        .cloned()
+        .collect();
+
+    let mut batches = 0;
+    let mut last_call: Option<Instant> = None;
+    for chunk in pending.chunks(settings.batch_size) {
+        if let Some(last_call) = last_call {
+            let elapsed = clock.now().saturating_duration_since(last_call);
+            if let Some(wait) = settings.min_interval().checked_sub(elapsed).filter(|wait| !wait.is_zero()) {
+                clock.sleep(wait);
+            }
+        }
+        last_call = Some(clock.now());
+        let mut results = lookup(chunk)?;
+        batches += 1;
+        let fetched_at = clock.unix_now();
+        for key in chunk {
+            let fields = results.remove(key).unwrap_or_default();
+            cache.entries.insert(format!("{prefix}:{key}"), CachedFields { fields, fetched_at });
+        }
+    }
+
+    let mut fields = BTreeSet::new();
+    let mut ignored_fields = BTreeSet::new();
+    let mut enriched = 0;
+    for recipient in &mut recipients {
+        let key = settings.key.value_for(&recipient.email);
+        let Some(cached) = cache.entries.get(&format!("{prefix}:{key}")) else {
+            continue;
+        };
+        let mut added = false;
+        for (name, value) in &cached.fields {
+            if !is_field_name(name) {
+                ignored_fields.insert(name.clone());
+                continue;
+            }
+            fields.insert(name.clone());
+            if !recipient.fields.contains_key(name) {
+                recipient.fields.insert(name.clone(), value.clone());
+                added = true;
+            }
+        }
+        enriched += usize::from(added);
+    }
+
+    Ok(EnrichmentReport {
+        keys: keys.len(),
+        cached: keys.len() - pending.len(),
+        looked_up: pending.len(),
+        batches,
+        enriched,
+        fields,
+        ignored_fields,
+        recipients,
+    })
+}
+
+/// 按设置的来源查询一批键。
+pub fn lookup_batch(
+    policy: &NetworkPolicy,
+    settings: &EnrichmentSettings,
+    values: &[String],
+) -> Result<BTreeMap<String, FieldMap>, String> {
+    let request = json!({ "key": settings.key.as_str(), "values": values });
+    let timeout = Duration::from_secs(settings.timeout_sec);
+    let response = match &settings.source {
+        Some(EnrichmentSource::Http { url, token }) => {
+            let mut builder = http_client(policy, url, "联系人补充")?
+                .post(url.trim())
+                .timeout(timeout)
+                .json(&request);
+            if let Some(token) = token.as_deref().map(str::trim).filter(|token| !token.is_empty()) {
+                builder = builder.bearer_auth(token);
+            }
+            let response = builder.send().map_err(|err| format!("补充数据接口请求失败: {err}"))?;
+            if !response.status().is_success() {
+                return Err(format!("补充数据接口返回 HTTP {}", response.status()));
+            }
+            response
+                .json::<Value>()
+                .map_err(|err| format!("补充数据接口返回的不是 JSON: {err}"))?
+        }
+        Some(EnrichmentSource::Command { program, args }) => run_command(program, args, &request, timeout)?,
+        None => return Err("未配置补充数据来源".to_string()),
+    };
+    parse_results(&response)
+}
+
+fn run_command(program: &str, args: &[String], request: &Value, timeout: Duration) -> Result<Value, String> {
+    let mut child = Command::new(program.trim())
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| format!("启动补充数据命令失败: {err}"))?;
+    let input = request.to_string();
+    let mut stdin = child.stdin.take();
+    let writer = std::thread::spawn(move || stdin.as_mut().map(|stdin| stdin.write_all(input.as_bytes())));
+    let mut stdout = child.stdout.take();
+    let reader = std::thread::spawn(move || {
+        let mut output = Vec::new();
+        stdout.as_mut().map(|stdout| stdout.read_to_end(&mut output));
+        output
+    });
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        match child.try_wait().map_err(|err| format!("等待补充数据命令失败: {err}"))? {
+            Some(status) => break status,
+            None if Instant::now() >= deadline => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!("补充数据命令超过 {} 秒未结束", timeout.as_secs()));
+            }
+            None => std::thread::sleep(Duration::from_millis(20)),
+        }
+    };
+    let _ = writer.join();
+    let output = reader.join().unwrap_or_default();
+    if !status.success() {
+        let mut stderr = String::new();
+        if let Some(mut pipe) = child.stderr.take() {
+            let _ = pipe.read_to_string(&mut stderr);
+        }
+        return Err(format!("补充数据命令失败（{status}）: {}", stderr.trim()));
+    }
+    serde_json::from_slice(&output).map_err(|err| format!("补充数据命令输出的不是 JSON: {err}"))
+}
+
+/// 读取 `results`；字段值为字符串、数字或布尔值，其他值忽略。
+fn parse_results(response: &Value) -> Result<BTreeMap<String, FieldMap>, String> {
+    let results = response
+        .get("results")
+        .and_then(Value::as_object)
+        .ok_or_else(|| "补充数据返回缺少 results 对象".to_string())?;
+    Ok(results
+        .iter()
+        .map(|(key, fields)| {
+            let fields = fields
+                .as_object()
+                .into_iter()
+                .flatten()
+                .filter_map(|(name, value)| {
+                    let value = match value {
+                        Value::String(text) => text.trim().to_string(),
+                        Value::Number(number) => number.to_string(),
+                        Value::Bool(flag) => flag.to_string(),
+                        _ => return None,
+                    };
+                    Some((name.trim().to_string(), value))
+                })
+                .collect();
+            (key.trim().to_lowercase(), fields)
+        })
+        .collect())
+}
+
+/// 模板变量名：字母或下划线开头，只含字母、数字、下划线，且不与内置变量重名。
+pub fn is_field_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+        && chars.all(|ch| ch.is_ascii_alphanumeric() || ch == '_')
+        && !BUILTIN_VARIABLES.contains(&name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{enrich_recipients, parse_results, EnrichmentCache, EnrichmentSettings, EnrichmentSource};
+    use crate::clock::FrozenClock;
+    use crate::engine::RecipientEntry;
+    use chrono::{TimeZone, Utc};
+    use std::time::Duration;
+
+    #[test]
+    fn enriches_in_rate_limited_batches_and_reuses_cache() {
+        let clock = FrozenClock::new(Utc.timestamp_opt(1_700_000_000, 0).single().expect("timestamp"));
+        let settings = EnrichmentSettings {
+            source: Some(EnrichmentSource::Command {
+                program: "lookup".to_string(),
+                args: Vec::new(),
+            }),
+            batch_size: 2,
+            requests_per_minute: 6,
+            ..EnrichmentSettings::default()
+        };
+        let recipients: Vec<RecipientEntry> = ["a@one.edu", "b@one.edu", "c@two.edu", "d@three.edu"]
+            .iter()
+            .map(|email| RecipientEntry {
+                email: email.to_string(),
+                name: "R".to_string(),
+                ..RecipientEntry::default()
+            })
+            .collect();
+        let mut cache = EnrichmentCache::default();
+        let mut calls = Vec::new();
+        let report = enrich_recipients(recipients.clone(), &settings, &mut cache, &clock, |keys| {
+            calls.push(keys.to_vec());
+            parse_results(&serde_json::json!({ "results": {
+                "one.edu": { "company": "One University", "rank": 3, "send_date": "x" },
+                "two.edu": { "company": "Two College", "bad name": "y" }
+            }}))
+        })
+        .expect("enrich");
+
+        assert_eq!(calls, vec![vec!["one.edu", "three.edu"], vec!["two.edu"]]);
+        assert_eq!(clock.elapsed(), Duration::from_secs(10));
+        assert_eq!(report.batches, 2);
+        assert_eq!(report.enriched, 3);
+        assert_eq!(report.recipients[0].fields["company"], "One University");
+        assert_eq!(report.recipients[0].fields["rank"], "3");
+        assert!(report.recipients[3].fields.is_empty());
+        assert!(report.ignored_fields.contains("send_date") && report.ignored_fields.contains("bad name"));
+
+        let again = enrich_recipients(recipients, &settings, &mut cache, &clock, |_| Err("不应再次查询".to_string()))
+            .expect("cached");
+        assert_eq!((again.cached, again.looked_up), (3, 0));
+    }
+}
@@ -0,0 +1,78 @@
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+
+/// 常见的本地 / 托管测试收件服务（Mailpit、MailHog、Mailtrap、Ethereal 等）。
+const TEST_SINK_HOSTS: [&str; 5] = [
+    "mailpit",
+    "mailhog",
+    "sandbox.smtp.mailtrap.io",
+    "smtp.mailtrap.io",
+    "smtp.ethereal.email",
+];
+/// RFC 2606 / 6761 保留的测试域名后缀。
+const TEST_SINK_SUFFIXES: [&str; 4] = [".test", ".localhost", ".invalid", ".example"];
+
+/// SMTP 配置与发送任务的环境标签：测试任务只能使用测试配置，生产任务发送前需要确认。
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Environment {
+    Test,
+    Production,
+}
+
+impl Environment {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Environment::Test => "test",
+            Environment::Production => "production",
+        }
+    }
+
+    /// 可读版发送记录中的名称。
+    pub fn display_name(self) -> &'static str {
+        match self {
+            Environment::Test => "测试",
+            Environment::Production => "生产",
+        }
+    }
+}
+
+/// 主机是否为测试收件服务：回环地址、保留测试域名或已知的邮件沙箱。
+pub fn is_test_sink(host: &str) -> bool {
+    let host = host.trim().trim_end_matches('.').to_lowercase();
+    let bare = host.trim_start_matches('[').trim_end_matches(']');
+    if let Ok(address) = bare.parse::<IpAddr>() {
+        return address.is_loopback();
+    }
+    host == "localhost"
+        || TEST_SINK_HOSTS.contains(&host.as_str())
+        || TEST_SINK_SUFFIXES.iter().any(|suffix| host.ends_with(suffix))
+}
+
+/// 测试任务可用的 SMTP 配置：标记为测试，或未标记但指向测试收件服务。
+/// 标记为生产的配置即使指向本机也不允许。
+pub fn usable_for_test(label: Option<Environment>, host: &str) -> bool {
+    match label {
+        Some(environment) => environment == Environment::Test,
+        None => is_test_sink(host),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_test_sink, usable_for_test, Environment};
+
+    #[test]
+    fn recognizes_test_sinks() {
+        for host in ["localhost", "127.0.0.1", "[::1]", "mailpit", "smtp.relay.test", "Sandbox.SMTP.Mailtrap.io"] {
+            assert!(is_test_sink(host), "{host} should be a test sink");
+        }
+        for host in ["smtp.example.edu", "10.0.0.5", "smtp.gmail.com", "testing.example.com"] {
+            assert!(!is_test_sink(host), "{host} should not be a test sink");
+        }
+        assert!(usable_for_test(Some(Environment::Test), "smtp.example.edu"));
+        assert!(usable_for_test(None, "localhost"));
+        assert!(!usable_for_test(Some(Environment::Production), "localhost"));
+        assert!(!usable_for_test(None, "smtp.example.edu"));
+    }
+}
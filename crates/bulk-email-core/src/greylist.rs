@@ -0,0 +1,153 @@
+use crate::smtp_client::SendFailure;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_GREYLIST_DELAY_SEC: u64 = 15 * 60;
+pub const DEFAULT_GREYLIST_MAX_ATTEMPTS: u32 = 3;
+/// 服务器声明的等待时间上限，避免异常响应把任务挂起数天。
+const MAX_ADVERTISED_DELAY: Duration = Duration::from_secs(6 * 60 * 60);
+
+const GREYLIST_MARKERS: [&str; 10] = [
+    "greylist",
+    "graylist",
+    "grey-list",
+    "gray-list",
+    "try again later",
+    "try later",
+    "retry later",
+    "please try again",
+    "temporarily deferred",
+    "temporarily rejected",
+];
+
+/// 判断一次失败是否为灰名单（greylisting）临时拒收：450/451 且响应文本包含典型提示。
+pub fn is_greylisting(failure: &SendFailure) -> bool {
+    if !matches!(failure.code, Some(450) | Some(451)) {
+        return false;
+    }
+    let message = failure.message.to_lowercase();
+    GREYLIST_MARKERS.iter().any(|marker| message.contains(marker))
+}
+
+/// 从响应文本中解析服务器建议的重试间隔，例如 "try again in 300 seconds" / "5 minutes"。
+pub fn advertised_delay(message: &str) -> Option<Duration> {
+    let lowered = message.to_lowercase();
+    let chars: Vec<char> = lowered.chars().collect();
+    let mut index = 0;
+    while index < chars.len() {
+        if !chars[index].is_ascii_digit() || (index > 0 && (chars[index - 1].is_ascii_alphanumeric() || chars[index - 1] == '.')) {
+            index += 1;
+            continue;
+        }
+        let start = index;
+        while index < chars.len() && chars[index].is_ascii_digit() {
+            index += 1;
+        }
+        let number: String = chars[start..index].iter().collect();
+        let mut cursor = index;
+        while cursor < chars.len() && chars[cursor] == ' ' {
+            cursor += 1;
+        }
+        let unit_start = cursor;
+        while cursor < chars.len() && chars[cursor].is_ascii_alphabetic() {
+            cursor += 1;
+        }
+        let unit: String = chars[unit_start..cursor].iter().collect();
+        let multiplier = if unit == "s" || unit.starts_with("sec") {
+            1
+        } else if unit.starts_with("min") {
+            60
+        } else if unit == "h" || unit.starts_with("hour") {
+            3600
+        } else {
+            continue;
+        };
+        if let Ok(value) = number.parse::<u64>() {
+            let delay = Duration::from_secs(value.saturating_mul(multiplier));
+            return Some(delay.min(MAX_ADVERTISED_DELAY));
+        }
+    }
+    None
+}
+
+pub fn recipient_domain(email: &str) -> String {
+    email
+        .rsplit_once('@')
+        .map(|(_, domain)| domain.trim().to_lowercase())
+        .unwrap_or_default()
+}
+
+/// 按收件域名记录灰名单等待截止时间：同一域名在等待期内的后续收件人直接延后，不再触发拒收。
+pub struct GreylistTracker {
+    default_delay: Duration,
+    domains: HashMap<String, Instant>,
+}
+
+impl GreylistTracker {
+    pub fn new(default_delay: Duration) -> Self {
+        GreylistTracker {
+            default_delay,
+            domains: HashMap::new(),
+        }
+    }
+
+    /// 记录一次灰名单拒收，返回该域名的下一次可尝试时间。
+    pub fn defer(&mut self, domain: &str, failure: &SendFailure, now: Instant) -> Instant {
+        let delay = advertised_delay(&failure.message).unwrap_or(self.default_delay);
+        let until = now + delay;
+        let entry = self.domains.entry(domain.to_string()).or_insert(until);
+        if *entry < until {
+            *entry = until;
+        }
+        *entry
+    }
+
+    pub fn deferred_until(&self, domain: &str, now: Instant) -> Option<Instant> {
+        self.domains.get(domain).copied().filter(|until| *until > now)
+    }
+
+    pub fn clear(&mut self, domain: &str) {
+        self.domains.remove(domain);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{advertised_delay, is_greylisting, GreylistTracker};
+    use crate::smtp_client::SendFailure;
+    use std::time::{Duration, Instant};
+
+    fn failure(code: u16, message: &str) -> SendFailure {
+        SendFailure {
+            code: Some(code),
+            message: message.to_string(),
+        }
+    }
+
+    #[test]
+    fn detects_greylisting_responses() {
+        assert!(is_greylisting(&failure(450, "4.2.0 Recipient address rejected: Greylisted")));
+        assert!(is_greylisting(&failure(451, "4.7.1 Please try again later")));
+        assert!(!is_greylisting(&failure(451, "4.3.0 Mail server temporarily out of disk")));
+        assert!(!is_greylisting(&failure(550, "5.1.1 greylisted user unknown")));
+    }
+
+    #[test]
+    fn parses_advertised_delay() {
+        assert_eq!(advertised_delay("Greylisted, try again in 300 seconds"), Some(Duration::from_secs(300)));
+        assert_eq!(advertised_delay("451 4.7.1 retry after 5 minutes"), Some(Duration::from_secs(300)));
+        assert_eq!(advertised_delay("451 4.7.1 please try again later"), None);
+    }
+
+    #[test]
+    fn tracks_deferral_per_domain() {
+        let mut tracker = GreylistTracker::new(Duration::from_secs(900));
+        let now = Instant::now();
+        let until = tracker.defer("example.com", &failure(451, "greylisted"), now);
+        assert_eq!(until, now + Duration::from_secs(900));
+        assert_eq!(tracker.deferred_until("example.com", now), Some(until));
+        assert_eq!(tracker.deferred_until("other.com", now), None);
+        tracker.clear("example.com");
+        assert_eq!(tracker.deferred_until("example.com", now), None);
+    }
+}
@@ -0,0 +1,572 @@
+use crate::recipients_loader::{load_recipient_rows, RecipientLoadResult};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine as _;
+use chrono::{Duration as ChronoDuration, Utc};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// 单次最多读取的邮件数（取最新的部分），避免对大邮箱做全量抓取。
+pub const MAX_IMAP_MESSAGES: usize = 2000;
+/// 每条 `UID FETCH` 命令包含的邮件数。
+const FETCH_CHUNK: usize = 200;
+/// 单个字面量（邮件头）的大小上限。
+const MAX_LITERAL_BYTES: usize = 64 * 1024;
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct ImapSettings {
+    pub host: String,
+    pub port: u16,
+    pub username: String,
+    pub password: String,
+    /// 993 端口的隐式 TLS；关闭时使用明文连接（仅用于本机测试服务器）。
+    pub use_ssl: bool,
+    pub timeout_sec: u64,
+}
+
+impl Default for ImapSettings {
+    fn default() -> Self {
+        ImapSettings {
+            host: String::new(),
+            port: 993,
+            username: String::new(),
+            password: String::new(),
+            use_ssl: true,
+            timeout_sec: 30,
+        }
+    }
+}
+
+/// 在邮箱中搜索邮件并提取发件人的条件。
+#[derive(Deserialize, Clone)]
+pub struct ImapSeedQuery {
+    #[serde(default = "default_folder")]
+    pub folder: String,
+    /// IMAP SEARCH 条件（如 `TO "support@example.com"`），为空时匹配全部邮件。
+    #[serde(default)]
+    pub query: String,
+    /// 只搜索最近若干天的邮件，追加为 `SINCE` 条件。
+    #[serde(default)]
+    pub since_days: Option<u32>,
+}
+
+fn default_folder() -> String {
+    "INBOX".to_string()
+}
+
+/// 搜索邮箱并提取去重后的发件人，结果与从文件导入收件人时的统计口径一致。
+pub fn load_recipients_from_imap(settings: &ImapSettings, query: &ImapSeedQuery) -> Result<ImapSeedResult, String> {
+    let criteria = search_criteria(query)?;
+    let mut session = ImapSession::open(settings)?;
+    let result = session.collect_senders(query, &criteria);
+    let _ = session.command("LOGOUT");
+    result
+}
+
+pub struct ImapSeedResult {
+    pub recipients: RecipientLoadResult,
+    /// 匹配搜索条件的邮件数，以及实际读取的邮件数（超过上限时只取最新的部分）。
+    pub matched: usize,
+    pub scanned: usize,
+}
+
+fn search_criteria(query: &ImapSeedQuery) -> Result<String, String> {
+    let raw = query.query.trim();
+    if raw.chars().any(|ch| ch.is_control() || !ch.is_ascii()) {
+        return Err("搜索条件仅支持 ASCII 的 IMAP SEARCH 语法，例如 TO \"support@example.com\"".to_string());
+    }
+    let mut parts = Vec::new();
+    if !raw.is_empty() {
+        parts.push(raw.to_string());
+    }
+    if let Some(days) = query.since_days {
+        let since = Utc::now() - ChronoDuration::days(i64::from(days));
+        parts.push(format!("SINCE {}", since.format("%-d-%b-%Y")));
+    }
+    if parts.is_empty() {
+        parts.push("ALL".to_string());
+    }
+    Ok(parts.join(" "))
+}
+
+trait Stream: Read + Write + Send {}
+impl<T: Read + Write + Send> Stream for T {}
+
+/// 一条未标记响应：文本行（字面量以 `{n}` 保留在行内）与其中的字面量内容。
+pub struct Untagged {
+    pub line: String,
+    pub literals: Vec<Vec<u8>>,
+}
+
+/// `LIST` 返回的一个文件夹。
+pub struct Mailbox {
+    /// 线上形式（修改版 UTF-7）的文件夹名，可直接用于 `EXAMINE`。
+    pub name: String,
+    /// 小写的文件夹属性，如 `\sent`、`\junk`（RFC 6154）。
+    pub flags: Vec<String>,
+}
+
+/// 最小的 IMAP 客户端会话，供联系人导入与发送回读验证共用。
+pub struct ImapSession {
+    reader: BufReader<Box<dyn Stream>>,
+    tag: u32,
+}
+
+impl ImapSession {
+    fn connect(settings: &ImapSettings) -> Result<Self, String> {
+        let host = settings.host.trim();
+        if host.is_empty() {
+            return Err("IMAP 主机不能为空".to_string());
+        }
+        let timeout = Duration::from_secs(settings.timeout_sec.max(1));
+        let address = (host, settings.port)
+            .to_socket_addrs()
+            .map_err(|err| format!("解析 IMAP 主机失败: {err}"))?
+            .next()
+            .ok_or_else(|| format!("无法解析 IMAP 主机: {host}"))?;
+        let tcp = TcpStream::connect_timeout(&address, timeout).map_err(|err| format!("连接 IMAP 服务器失败: {err}"))?;
+        tcp.set_read_timeout(Some(timeout)).map_err(|err| err.to_string())?;
+        tcp.set_write_timeout(Some(timeout)).map_err(|err| err.to_string())?;
+        let stream: Box<dyn Stream> = if settings.use_ssl {
+            Box::new(tls_stream(host, tcp)?)
+        } else {
+            Box::new(tcp)
+        };
+        let mut session = ImapSession {
+            reader: BufReader::new(stream),
+            tag: 0,
+        };
+        let greeting = session.read_response()?;
+        if !greeting.line.starts_with("* OK") && !greeting.line.starts_with("* PREAUTH") {
+            return Err(format!("IMAP 服务器拒绝连接: {}", greeting.line));
+        }
+        Ok(session)
+    }
+
+    /// 连接并以 LOGIN 登录。
+    pub fn open(settings: &ImapSettings) -> Result<Self, String> {
+        let mut session = ImapSession::connect(settings)?;
+        session.command(&format!(
+            "LOGIN {} {}",
+            quote(&settings.username),
+            quote(&settings.password)
+        ))?;
+        Ok(session)
+    }
+
+    fn collect_senders(&mut self, query: &ImapSeedQuery, criteria: &str) -> Result<ImapSeedResult, String> {
+        self.command(&format!("EXAMINE {}", quote(&encode_folder_name(query.folder.trim()))))?;
+        let mut uids = search_uids(&self.command(&format!("UID SEARCH {criteria}"))?);
+        uids.sort_unstable();
+        let matched = uids.len();
+        let recent = &uids[matched.saturating_sub(MAX_IMAP_MESSAGES)..];
+
+        let mut rows = Vec::new();
+        for chunk in recent.chunks(FETCH_CHUNK) {
+            let set: Vec<String> = chunk.iter().map(u64::to_string).collect();
+            let responses = self.command(&format!("UID FETCH {} (BODY.PEEK[HEADER.FIELDS (FROM)])", set.join(",")))?;
+            for response in responses.iter().filter(|response| response.line.contains(" FETCH ")) {
+                for header in &response.literals {
+                    if let Some(sender) = parse_from_header(header) {
+                        rows.push(sender);
+                    }
+                }
+            }
+        }
+        Ok(ImapSeedResult {
+            recipients: load_recipient_rows(unique_senders(rows)),
+            matched,
+            scanned: recent.len(),
+        })
+    }
+
+    /// 列出全部文件夹。
+    pub fn list_mailboxes(&mut self) -> Result<Vec<Mailbox>, String> {
+        let responses = self.command("LIST \"\" \"*\"")?;
+        Ok(responses
+            .iter()
+            .filter_map(|response| {
+                let rest = response.line.strip_prefix("* LIST (")?;
+                let (flags, rest) = rest.split_once(')')?;
+                let name = match response.literals.first() {
+                    Some(literal) => String::from_utf8_lossy(literal).into_owned(),
+                    None => mailbox_name(rest.trim()),
+                };
+                Some(Mailbox {
+                    name,
+                    flags: flags.split_whitespace().map(str::to_ascii_lowercase).collect(),
+                })
+            })
+            .collect())
+    }
+
+    /// 发送一条带标记的命令，返回其间的未标记响应；标记响应不是 OK 时返回错误。
+    pub fn command(&mut self, command: &str) -> Result<Vec<Untagged>, String> {
+        self.tag += 1;
+        let tag = format!("a{}", self.tag);
+        let stream = self.reader.get_mut();
+        write!(stream, "{tag} {command}\r\n")
+            .and_then(|_| stream.flush())
+            .map_err(|err| format!("写入 IMAP 命令失败: {err}"))?;
+        let verb = command.split_whitespace().next().unwrap_or_default();
+        let mut untagged = Vec::new();
+        loop {
+            let response = self.read_response()?;
+            if let Some(status) = response.line.strip_prefix(&format!("{tag} ")) {
+                if status.starts_with("OK") {
+                    return Ok(untagged);
+                }
+                return Err(format!("IMAP {verb} 失败: {status}"));
+            }
+            untagged.push(response);
+        }
+    }
+
+    /// 读取一条完整响应：行尾的 `{n}` 表示随后有 n 字节字面量，读完后该响应继续到下一行。
+    fn read_response(&mut self) -> Result<Untagged, String> {
+        let mut line = String::new();
+        let mut literals = Vec::new();
+        loop {
+            let mut raw = Vec::new();
+            let read = self
+                .reader
+                .read_until(b'\n', &mut raw)
+                .map_err(|err| format!("读取 IMAP 响应失败: {err}"))?;
+            if read == 0 {
+                return Err("IMAP 服务器断开了连接".to_string());
+            }
+            let text = String::from_utf8_lossy(&raw);
+            let text = text.trim_end_matches(['\r', '\n']);
+            line.push_str(text);
+            let Some(size) = literal_size(text) else {
+                return Ok(Untagged { line, literals });
+            };
+            if size > MAX_LITERAL_BYTES {
+                return Err(format!("IMAP 响应过大（{size} 字节）"));
+            }
+            let mut literal = vec![0; size];
+            self.reader
+                .read_exact(&mut literal)
+                .map_err(|err| format!("读取 IMAP 响应失败: {err}"))?;
+            literals.push(literal);
+        }
+    }
+}
+
+fn tls_stream(host: &str, tcp: TcpStream) -> Result<rustls::StreamOwned<rustls::ClientConnection, TcpStream>, String> {
+    let roots = rustls::RootCertStore {
+        roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+    };
+    let config = rustls::ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .map_err(|err| format!("初始化 TLS 失败: {err}"))?
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    let name = rustls::pki_types::ServerName::try_from(host.to_string())
+        .map_err(|err| format!("IMAP 主机名无效: {err}"))?;
+    let connection =
+        rustls::ClientConnection::new(Arc::new(config), name).map_err(|err| format!("初始化 TLS 失败: {err}"))?;
+    Ok(rustls::StreamOwned::new(connection, tcp))
+}
+
+fn literal_size(line: &str) -> Option<usize> {
+    let inner = line.strip_suffix('}')?;
+    let start = inner.rfind('{')?;
+    inner[start + 1..].trim_end_matches('+').parse().ok()
+}
+
+/// `LIST` 响应中分隔符之后的文件夹名（带引号或原子形式）。
+fn mailbox_name(rest: &str) -> String {
+    let after_delimiter = if let Some(quoted) = rest.strip_prefix('"') {
+        // 分隔符本身可能是转义的 `\"`。
+        let end = if quoted.starts_with('\\') { 3 } else { 2 };
+        quoted.get(end..).unwrap_or_default()
+    } else {
+        rest.strip_prefix("NIL").unwrap_or(rest)
+    }
+    .trim();
+    match after_delimiter.strip_prefix('"').and_then(|name| name.strip_suffix('"')) {
+        Some(name) => name.replace("\\\"", "\"").replace("\\\\", "\\"),
+        None => after_delimiter.to_string(),
+    }
+}
+
+/// `* SEARCH` 响应中的 UID / 序号。
+pub fn search_uids(responses: &[Untagged]) -> Vec<u64> {
+    responses
+        .iter()
+        .filter_map(|response| response.line.strip_prefix("* SEARCH"))
+        .flat_map(|rest| rest.split_whitespace().filter_map(|value| value.parse().ok()).collect::<Vec<u64>>())
+        .collect()
+}
+
+pub fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// IMAP 文件夹名使用修改版 UTF-7（RFC 3501 §5.1.3），如「已发送」写作 `&XfJT0ZAB-`。
+pub fn encode_folder_name(name: &str) -> String {
+    let mut encoded = String::new();
+    let mut pending: Vec<u16> = Vec::new();
+    let flush = |pending: &mut Vec<u16>, encoded: &mut String| {
+        if pending.is_empty() {
+            return;
+        }
+        let bytes: Vec<u8> = pending.iter().flat_map(|unit| unit.to_be_bytes()).collect();
+        let text = BASE64.encode(bytes).trim_end_matches('=').replace('/', ",");
+        encoded.push('&');
+        encoded.push_str(&text);
+        encoded.push('-');
+        pending.clear();
+    };
+    for ch in name.chars() {
+        if (' '..='~').contains(&ch) {
+            flush(&mut pending, &mut encoded);
+            if ch == '&' {
+                encoded.push_str("&-");
+            } else {
+                encoded.push(ch);
+            }
+        } else {
+            pending.extend(ch.encode_utf16(&mut [0; 2]).iter());
+        }
+    }
+    flush(&mut pending, &mut encoded);
+    encoded
+}
+
+/// `encode_folder_name` 的逆过程；无法解码的片段原样保留。
+pub fn decode_folder_name(name: &str) -> String {
+    let mut decoded = String::new();
+    let mut rest = name;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('-').map(|end| start + end) else {
+            rest = &rest[start..];
+            break;
+        };
+        let shifted = &rest[start + 1..end];
+        if shifted.is_empty() {
+            decoded.push('&');
+        } else {
+            let text = base64::engine::general_purpose::STANDARD_NO_PAD
+                .decode(shifted.replace(',', "/"))
+                .ok()
+                .filter(|bytes| bytes.len() % 2 == 0)
+                .and_then(|bytes| {
+                    let units: Vec<u16> = bytes.chunks(2).map(|pair| u16::from_be_bytes([pair[0], pair[1]])).collect();
+                    String::from_utf16(&units).ok()
+                });
+            match text {
+                Some(text) => decoded.push_str(&text),
+                None => decoded.push_str(&rest[start..=end]),
+            }
+        }
+        rest = &rest[end + 1..];
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+/// 从 `From:` 邮件头中取出（邮箱, 显示名）；显示名中的 RFC 2047 编码词会被解码。
+fn parse_from_header(header: &[u8]) -> Option<(String, String)> {
+    let text = String::from_utf8_lossy(header).replace("\r\n ", " ").replace("\r\n\t", " ");
+    let value = text
+        .lines()
+        .find_map(|line| line.get(..5).filter(|name| name.eq_ignore_ascii_case("from:")).map(|_| &line[5..]))?
+        .trim();
+    let (name, email) = match (value.rfind('<'), value.rfind('>')) {
+        (Some(open), Some(close)) if open < close => (&value[..open], &value[open + 1..close]),
+        _ => ("", value),
+    };
+    let email = email.trim().to_string();
+    let name = decode_encoded_words(name.trim()).trim().trim_matches('"').trim().to_string();
+    if email.is_empty() {
+        return None;
+    }
+    Some((email, name))
+}
+
+/// 解码 `=?charset?B|Q?text?=` 编码词；相邻编码词之间的空白按 RFC 2047 忽略。
+fn decode_encoded_words(value: &str) -> String {
+    let mut output = String::new();
+    let mut rest = value;
+    let mut previous_encoded = false;
+    while let Some(start) = rest.find("=?") {
+        let Some(decoded) = rest[start + 2..]
+            .find("?=")
+            .and_then(|end| decode_word(&rest[start + 2..start + 2 + end]).map(|text| (text, start + 4 + end)))
+        else {
+            break;
+        };
+        let between = &rest[..start];
+        if !(previous_encoded && between.trim().is_empty()) {
+            output.push_str(between);
+        }
+        output.push_str(&decoded.0);
+        rest = &rest[decoded.1..];
+        previous_encoded = true;
+    }
+    output.push_str(rest);
+    output
+}
+
+fn decode_word(word: &str) -> Option<String> {
+    let mut parts = word.splitn(3, '?');
+    let charset = parts.next()?.split('*').next()?;
+    let encoding = parts.next()?;
+    let text = parts.next()?;
+    let bytes = if encoding.eq_ignore_ascii_case("b") {
+        BASE64.decode(text).ok()?
+    } else if encoding.eq_ignore_ascii_case("q") {
+        decode_q(text)
+    } else {
+        return None;
+    };
+    let encoding = encoding_rs::Encoding::for_label(charset.as_bytes()).unwrap_or(encoding_rs::UTF_8);
+    Some(encoding.decode(&bytes).0.into_owned())
+}
+
+fn decode_q(text: &str) -> Vec<u8> {
+    let bytes = text.as_bytes();
+    let mut output = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        match bytes[index] {
+            b'_' => output.push(b' '),
+            b'=' if index + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[index + 1..index + 3]).ok();
+                match hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                    Some(byte) => {
+                        output.push(byte);
+                        index += 2;
+                    }
+                    None => output.push(b'='),
+                }
+            }
+            byte => output.push(byte),
+        }
+        index += 1;
+    }
+    output
+}
+
+/// 按邮箱（忽略大小写）去重，保留第一个非空显示名；没有显示名时用邮箱的本地部分。
+fn unique_senders(rows: Vec<(String, String)>) -> Vec<(String, String)> {
+    let mut positions = HashMap::new();
+    let mut unique: Vec<(String, String)> = Vec::new();
+    for (email, name) in rows {
+        match positions.get(&email.to_lowercase()) {
+            Some(&position) => {
+                let existing: &mut (String, String) = &mut unique[position];
+                if existing.1.is_empty() {
+                    existing.1 = name;
+                }
+            }
+            None => {
+                positions.insert(email.to_lowercase(), unique.len());
+                unique.push((email, name));
+            }
+        }
+    }
+    for (email, name) in &mut unique {
+        if name.is_empty() {
+            *name = email.split('@').next().unwrap_or_default().to_string();
+        }
+    }
+    unique
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_folder_name, encode_folder_name, load_recipients_from_imap, ImapSeedQuery, ImapSettings};
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+
+    /// 按命令动词回放固定响应的本地 IMAP 服务器。
+    fn serve_once(listener: TcpListener) -> Vec<String> {
+        let (stream, _) = listener.accept().expect("accept");
+        let mut writer = stream.try_clone().expect("clone");
+        let mut reader = BufReader::new(stream);
+        writer.write_all(b"* OK IMAP4rev1 ready\r\n").expect("greeting");
+        let headers = [
+            "From: =?UTF-8?B?5byg5pWZ5o6I?= <Zhang@Example.edu>\r\n\r\n",
+            "From: zhang@example.edu\r\n\r\n",
+            "From: \"Li, Wei\"\r\n <li@example.org>\r\n\r\n",
+            "From: plain@example.net\r\n\r\n",
+        ];
+        let mut commands = Vec::new();
+        let mut line = String::new();
+        while reader.read_line(&mut line).expect("read") > 0 {
+            let (tag, command) = line.trim_end().split_once(' ').expect("tagged");
+            commands.push(command.to_string());
+            let verb = command.split_whitespace().take(2).collect::<Vec<_>>().join(" ");
+            let mut reply = String::new();
+            match verb.as_str() {
+                "UID SEARCH" => reply.push_str("* SEARCH 7 3 9 12\r\n"),
+                "UID FETCH" => {
+                    for (position, header) in headers.iter().enumerate() {
+                        reply.push_str(&format!(
+                            "* {} FETCH (UID {} BODY[HEADER.FIELDS (FROM)] {{{}}}\r\n{header})\r\n",
+                            position + 1,
+                            position + 1,
+                            header.len()
+                        ));
+                    }
+                }
+                _ => {}
+            }
+            reply.push_str(&format!("{tag} OK done\r\n"));
+            writer.write_all(reply.as_bytes()).expect("reply");
+            if command == "LOGOUT" {
+                break;
+            }
+            line.clear();
+        }
+        commands
+    }
+
+    #[test]
+    fn extracts_unique_senders_from_search_results() {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind");
+        let port = listener.local_addr().expect("addr").port();
+        let server = std::thread::spawn(move || serve_once(listener));
+        let settings = ImapSettings {
+            host: "127.0.0.1".to_string(),
+            port,
+            username: "support@example.com".to_string(),
+            password: "p\"w".to_string(),
+            use_ssl: false,
+            timeout_sec: 5,
+        };
+        let query = ImapSeedQuery {
+            folder: "已发送".to_string(),
+            query: "TO \"support@example.com\"".to_string(),
+            since_days: None,
+        };
+        let result = load_recipients_from_imap(&settings, &query).expect("imap seed");
+        let commands = server.join().expect("server");
+
+        assert_eq!(commands[0], "LOGIN \"support@example.com\" \"p\\\"w\"");
+        assert_eq!(commands[1], "EXAMINE \"&XfJT0ZAB-\"");
+        assert_eq!(commands[2], "UID SEARCH TO \"support@example.com\"");
+        assert_eq!(commands[3], "UID FETCH 3,7,9,12 (BODY.PEEK[HEADER.FIELDS (FROM)])");
+        assert_eq!((result.matched, result.scanned), (4, 4));
+        let senders: Vec<(&str, &str)> = result
+            .recipients
+            .recipients
+            .iter()
+            .map(|recipient| (recipient.email.as_str(), recipient.name.as_str()))
+            .collect();
+        assert_eq!(
+            senders,
+            [("Zhang@Example.edu", "张教授"), ("li@example.org", "Li, Wei"), ("plain@example.net", "plain")]
+        );
+        assert_eq!(encode_folder_name("A&B"), "A&-B");
+        assert_eq!(decode_folder_name("&XfJT0ZAB-/A&-B"), "已发送/A&B");
+    }
+}
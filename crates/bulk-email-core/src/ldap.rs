@@ -0,0 +1,723 @@
+use crate::engine::RecipientEntry;
+use crate::recipients_loader::{load_recipient_entries, RecipientLoadResult};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// 单次查询最多读取的条目数。
+pub const MAX_LDAP_ENTRIES: usize = 20_000;
+/// 分页查询（Active Directory 默认每次最多返回 1000 条）的页大小。
+const PAGE_SIZE: i64 = 500;
+/// 单条 LDAP 消息的大小上限。
+const MAX_MESSAGE_BYTES: usize = 16 * 1024 * 1024;
+const PAGED_RESULTS_OID: &str = "1.2.840.113556.1.4.319";
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct LdapSettings {
+    pub host: String,
+    pub port: u16,
+    /// 636 端口的 LDAPS；关闭时使用明文连接（仅用于本机或内网测试服务器）。
+    pub use_ssl: bool,
+    /// 绑定账号（如 `CN=svc-mail,OU=Service,DC=corp,DC=example` 或 `corp\svc-mail`）；为空时匿名查询。
+    pub bind_dn: String,
+    pub password: String,
+    pub timeout_sec: u64,
+}
+
+impl Default for LdapSettings {
+    fn default() -> Self {
+        LdapSettings {
+            host: String::new(),
+            port: 636,
+            use_ssl: true,
+            bind_dn: String::new(),
+            password: String::new(),
+            timeout_sec: 30,
+        }
+    }
+}
+
+/// 查询条件与属性映射。
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct LdapQuery {
+    pub base_dn: String,
+    /// RFC 4515 过滤器，如 `(&(objectClass=user)(department=Sales))`。
+    pub filter: String,
+    pub email_attribute: String,
+    pub name_attribute: String,
+    /// 模板变量名 → LDAP 属性（如 `department` → `department`），作为收件人的补充字段。
+    pub fields: BTreeMap<String, String>,
+}
+
+impl Default for LdapQuery {
+    fn default() -> Self {
+        LdapQuery {
+            base_dn: String::new(),
+            filter: "(&(objectClass=person)(mail=*))".to_string(),
+            email_attribute: "mail".to_string(),
+            name_attribute: "displayName".to_string(),
+            fields: BTreeMap::new(),
+        }
+    }
+}
+
+pub struct LdapQueryResult {
+    pub recipients: RecipientLoadResult,
+    /// 读取的目录条目数；达到上限时为 `MAX_LDAP_ENTRIES`。
+    pub entries: usize,
+    pub truncated: bool,
+}
+
+/// 绑定后按过滤器分页查询目录，把条目按属性映射转换为收件人（与文件导入相同的校验与去重）。
+pub fn query_recipients(settings: &LdapSettings, query: &LdapQuery) -> Result<LdapQueryResult, String> {
+    if query.base_dn.trim().is_empty() {
+        return Err("请填写查询的 Base DN".to_string());
+    }
+    let filter = encode_filter(query.filter.trim())?;
+    let mut attributes = vec![query.email_attribute.trim(), query.name_attribute.trim()];
+    attributes.extend(query.fields.values().map(|attribute| attribute.trim()));
+    if attributes.iter().any(|attribute| attribute.is_empty()) {
+        return Err("属性映射不能为空".to_string());
+    }
+
+    let mut connection = LdapConnection::connect(settings)?;
+    connection.bind(settings.bind_dn.trim(), &settings.password)?;
+    let mut entries = Vec::new();
+    let mut cookie = Vec::new();
+    let mut truncated = false;
+    loop {
+        let page = connection.search(query.base_dn.trim(), &filter, &attributes, &cookie)?;
+        entries.extend(page.entries);
+        truncated |= page.size_limit_exceeded;
+        if entries.len() >= MAX_LDAP_ENTRIES {
+            entries.truncate(MAX_LDAP_ENTRIES);
+            truncated = true;
+            break;
+        }
+        match page.cookie {
+            Some(next) if !next.is_empty() => cookie = next,
+            _ => break,
+        }
+    }
+    connection.unbind();
+
+    let first = |entry: &BTreeMap<String, Vec<String>>, attribute: &str| {
+        entry
+            .get(&attribute.trim().to_lowercase())
+            .and_then(|values| values.first())
+            .cloned()
+            .unwrap_or_default()
+    };
+    let count = entries.len();
+    let recipients = entries
+        .iter()
+        .map(|entry| RecipientEntry {
+            email: first(entry, &query.email_attribute),
+            name: first(entry, &query.name_attribute),
+            fields: query
+                .fields
+                .iter()
+                .map(|(field, attribute)| (field.trim().to_string(), first(entry, attribute)))
+                .filter(|(field, value)| !field.is_empty() && !value.is_empty())
+                .collect(),
+        })
+        .collect();
+    Ok(LdapQueryResult {
+        recipients: load_recipient_entries(recipients),
+        entries: count,
+        truncated,
+    })
+}
+
+trait Stream: Read + Write + Send {}
+impl<T: Read + Write + Send> Stream for T {}
+
+struct SearchPage {
+    /// 每个条目的属性（属性名小写）→ 值。
+    entries: Vec<BTreeMap<String, Vec<String>>>,
+    cookie: Option<Vec<u8>>,
+    size_limit_exceeded: bool,
+}
+
+/// 一条响应的协议操作（标签与内容）及附带的控件。
+struct Response {
+    operation: u8,
+    body: Vec<u8>,
+    controls: Option<Vec<u8>>,
+}
+
+struct LdapConnection {
+    stream: Box<dyn Stream>,
+    message_id: i64,
+}
+
+impl LdapConnection {
+    fn connect(settings: &LdapSettings) -> Result<Self, String> {
+        let host = settings.host.trim();
+        if host.is_empty() {
+            return Err("LDAP 主机不能为空".to_string());
+        }
+        let timeout = Duration::from_secs(settings.timeout_sec.max(1));
+        let address = (host, settings.port)
+            .to_socket_addrs()
+            .map_err(|err| format!("解析 LDAP 主机失败: {err}"))?
+            .next()
+            .ok_or_else(|| format!("无法解析 LDAP 主机: {host}"))?;
+        let tcp = TcpStream::connect_timeout(&address, timeout).map_err(|err| format!("连接 LDAP 服务器失败: {err}"))?;
+        tcp.set_read_timeout(Some(timeout)).map_err(|err| err.to_string())?;
+        tcp.set_write_timeout(Some(timeout)).map_err(|err| err.to_string())?;
+        let stream: Box<dyn Stream> = if settings.use_ssl {
+            Box::new(tls_stream(host, tcp)?)
+        } else {
+            Box::new(tcp)
+        };
+        Ok(LdapConnection { stream, message_id: 0 })
+    }
+
+    fn send(&mut self, operation: Vec<u8>, controls: Option<Vec<u8>>) -> Result<i64, String> {
+        self.message_id += 1;
+        let mut message = integer(self.message_id);
+        message.extend(operation);
+        message.extend(controls.unwrap_or_default());
+        self.stream
+            .write_all(&tlv(0x30, &message))
+            .and_then(|_| self.stream.flush())
+            .map_err(|err| format!("发送 LDAP 请求失败: {err}"))?;
+        Ok(self.message_id)
+    }
+
+    /// 读取一条属于 `id` 的响应。
+    fn receive(&mut self, id: i64) -> Result<Response, String> {
+        loop {
+            let message = read_message(&mut self.stream)?;
+            let (tag, content, _) = read_tlv(&message)?;
+            if tag != 0x30 {
+                return Err("LDAP 响应格式错误".to_string());
+            }
+            let (_, raw_id, rest) = read_tlv(content)?;
+            let (operation, body, rest) = read_tlv(rest)?;
+            let controls = match read_tlv(rest) {
+                Ok((0xa0, controls, _)) => Some(controls.to_vec()),
+                _ => None,
+            };
+            // 消息 ID 为 0 的是服务器主动通知（如断开连接）。
+            if parse_integer(raw_id) == 0 {
+                return Err(format!("LDAP 服务器中断了连接: {}", result_message(body)));
+            }
+            if parse_integer(raw_id) == id {
+                return Ok(Response {
+                    operation,
+                    body: body.to_vec(),
+                    controls,
+                });
+            }
+        }
+    }
+
+    fn bind(&mut self, dn: &str, password: &str) -> Result<(), String> {
+        let mut request = integer(3);
+        request.extend(octets(dn.as_bytes()));
+        request.extend(tlv(0x80, password.as_bytes()));
+        let id = self.send(tlv(0x60, &request), None)?;
+        let Response { operation, body, .. } = self.receive(id)?;
+        if operation != 0x61 {
+            return Err("LDAP 绑定响应格式错误".to_string());
+        }
+        match result_code(&body)? {
+            0 => Ok(()),
+            49 => Err(format!("LDAP 登录失败，请检查绑定账号与密码: {}", result_message(&body))),
+            code => Err(format!("LDAP 绑定失败（{code}）: {}", result_message(&body))),
+        }
+    }
+
+    fn search(&mut self, base: &str, filter: &[u8], attributes: &[&str], cookie: &[u8]) -> Result<SearchPage, String> {
+        let mut request = octets(base.as_bytes());
+        request.extend(tlv(0x0a, &[2])); // scope: wholeSubtree
+        request.extend(tlv(0x0a, &[0])); // derefAliases: never
+        request.extend(integer(0));
+        request.extend(integer(0));
+        request.extend(tlv(0x01, &[0]));
+        request.extend_from_slice(filter);
+        let names: Vec<u8> = attributes.iter().flat_map(|name| octets(name.as_bytes())).collect();
+        request.extend(tlv(0x30, &names));
+
+        let mut paging = integer(PAGE_SIZE);
+        paging.extend(octets(cookie));
+        let mut control = octets(PAGED_RESULTS_OID.as_bytes());
+        control.extend(octets(&tlv(0x30, &paging)));
+        let controls = tlv(0xa0, &tlv(0x30, &control));
+
+        let id = self.send(tlv(0x63, &request), Some(controls))?;
+        let mut page = SearchPage {
+            entries: Vec::new(),
+            cookie: None,
+            size_limit_exceeded: false,
+        };
+        loop {
+            let Response {
+                operation,
+                body,
+                controls,
+            } = self.receive(id)?;
+            match operation {
+                0x64 => page.entries.push(parse_entry(&body)?),
+                // 搜索引用（指向其他服务器）不跟随。
+                0x73 => {}
+                0x65 => {
+                    match result_code(&body)? {
+                        0 => {}
+                        4 => page.size_limit_exceeded = true,
+                        32 => return Err(format!("Base DN 不存在: {}", result_message(&body))),
+                        code => return Err(format!("LDAP 查询失败（{code}）: {}", result_message(&body))),
+                    }
+                    page.cookie = controls.as_deref().and_then(paged_cookie);
+                    return Ok(page);
+                }
+                other => return Err(format!("未预期的 LDAP 响应类型: 0x{other:02x}")),
+            }
+        }
+    }
+
+    fn unbind(&mut self) {
+        let _ = self.send(tlv(0x42, &[]), None);
+    }
+}
+
+fn tls_stream(host: &str, tcp: TcpStream) -> Result<rustls::StreamOwned<rustls::ClientConnection, TcpStream>, String> {
+    let roots = rustls::RootCertStore {
+        roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+    };
+    let config = rustls::ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .map_err(|err| format!("初始化 TLS 失败: {err}"))?
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    let name = rustls::pki_types::ServerName::try_from(host.to_string())
+        .map_err(|err| format!("LDAP 主机名无效: {err}"))?;
+    let connection =
+        rustls::ClientConnection::new(Arc::new(config), name).map_err(|err| format!("初始化 TLS 失败: {err}"))?;
+    Ok(rustls::StreamOwned::new(connection, tcp))
+}
+
+fn tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut out = vec![tag];
+    let length = content.len();
+    if length < 0x80 {
+        out.push(length as u8);
+    } else {
+        let bytes: Vec<u8> = length.to_be_bytes().into_iter().skip_while(|byte| *byte == 0).collect();
+        out.push(0x80 | bytes.len() as u8);
+        out.extend(bytes);
+    }
+    out.extend_from_slice(content);
+    out
+}
+
+fn octets(value: &[u8]) -> Vec<u8> {
+    tlv(0x04, value)
+}
+
+fn integer(value: i64) -> Vec<u8> {
+    let bytes = value.to_be_bytes();
+    let mut start = 0;
+    while start < 7
+        && ((bytes[start] == 0 && bytes[start + 1] & 0x80 == 0) || (bytes[start] == 0xff && bytes[start + 1] & 0x80 != 0))
+    {
+        start += 1;
+    }
+    tlv(0x02, &bytes[start..])
+}
+
+fn parse_integer(content: &[u8]) -> i64 {
+    let negative = content.first().is_some_and(|byte| byte & 0x80 != 0);
+    content
+        .iter()
+        .take(8)
+        .fold(if negative { -1 } else { 0 }, |value, byte| (value << 8) | i64::from(*byte))
+}
+
+/// 解析一个 TLV：(标签, 内容, 剩余部分)。仅支持确定长度编码。
+fn read_tlv(data: &[u8]) -> Result<(u8, &[u8], &[u8]), String> {
+    let malformed = || "LDAP 响应格式错误".to_string();
+    let (&tag, rest) = data.split_first().ok_or_else(malformed)?;
+    let (&first, rest) = rest.split_first().ok_or_else(malformed)?;
+    let (length, rest) = if first < 0x80 {
+        (first as usize, rest)
+    } else {
+        let count = (first & 0x7f) as usize;
+        if count == 0 || count > 4 || rest.len() < count {
+            return Err(malformed());
+        }
+        let length = rest[..count].iter().fold(0usize, |value, byte| (value << 8) | *byte as usize);
+        (length, &rest[count..])
+    };
+    if rest.len() < length {
+        return Err(malformed());
+    }
+    Ok((tag, &rest[..length], &rest[length..]))
+}
+
+/// 从连接读取一条完整的 LDAP 消息。
+fn read_message(stream: &mut dyn Stream) -> Result<Vec<u8>, String> {
+    let read_error = |err: std::io::Error| format!("读取 LDAP 响应失败: {err}");
+    let mut head = [0u8; 2];
+    stream.read_exact(&mut head).map_err(read_error)?;
+    let mut message = head.to_vec();
+    let length = if head[1] < 0x80 {
+        head[1] as usize
+    } else {
+        let count = (head[1] & 0x7f) as usize;
+        if count == 0 || count > 4 {
+            return Err("LDAP 响应格式错误".to_string());
+        }
+        let mut bytes = vec![0u8; count];
+        stream.read_exact(&mut bytes).map_err(read_error)?;
+        message.extend_from_slice(&bytes);
+        bytes.iter().fold(0usize, |value, byte| (value << 8) | *byte as usize)
+    };
+    if length > MAX_MESSAGE_BYTES {
+        return Err("LDAP 响应过大".to_string());
+    }
+    let start = message.len();
+    message.resize(start + length, 0);
+    stream.read_exact(&mut message[start..]).map_err(read_error)?;
+    Ok(message)
+}
+
+fn result_code(body: &[u8]) -> Result<i64, String> {
+    let (_, code, _) = read_tlv(body)?;
+    Ok(parse_integer(code))
+}
+
+fn result_message(body: &[u8]) -> String {
+    let message = read_tlv(body)
+        .and_then(|(_, _, rest)| read_tlv(rest))
+        .and_then(|(_, _, rest)| read_tlv(rest))
+        .map(|(_, message, _)| String::from_utf8_lossy(message).trim().to_string())
+        .unwrap_or_default();
+    if message.is_empty() {
+        "（无详细信息）".to_string()
+    } else {
+        message
+    }
+}
+
+fn parse_entry(body: &[u8]) -> Result<BTreeMap<String, Vec<String>>, String> {
+    let (_, _, rest) = read_tlv(body)?;
+    let (_, mut attributes, _) = read_tlv(rest)?;
+    let mut entry = BTreeMap::new();
+    while !attributes.is_empty() {
+        let (_, attribute, rest) = read_tlv(attributes)?;
+        attributes = rest;
+        let (_, name, rest) = read_tlv(attribute)?;
+        let (_, mut values, _) = read_tlv(rest)?;
+        let mut collected = Vec::new();
+        while !values.is_empty() {
+            let (_, value, rest) = read_tlv(values)?;
+            values = rest;
+            collected.push(String::from_utf8_lossy(value).trim().to_string());
+        }
+        entry.insert(String::from_utf8_lossy(name).to_lowercase(), collected);
+    }
+    Ok(entry)
+}
+
+/// 从 SearchResultDone 的控件中取出分页 cookie。
+fn paged_cookie(mut controls: &[u8]) -> Option<Vec<u8>> {
+    while !controls.is_empty() {
+        let (_, control, rest) = read_tlv(controls).ok()?;
+        controls = rest;
+        let (_, oid, mut fields) = read_tlv(control).ok()?;
+        if oid != PAGED_RESULTS_OID.as_bytes() {
+            continue;
+        }
+        while !fields.is_empty() {
+            let (tag, value, rest) = read_tlv(fields).ok()?;
+            fields = rest;
+            if tag == 0x04 {
+                let (_, paging, _) = read_tlv(value).ok()?;
+                let (_, _, rest) = read_tlv(paging).ok()?;
+                let (_, cookie, _) = read_tlv(rest).ok()?;
+                return Some(cookie.to_vec());
+            }
+        }
+    }
+    None
+}
+
+/// 把 RFC 4515 过滤器字符串编码为 BER。
+fn encode_filter(filter: &str) -> Result<Vec<u8>, String> {
+    let filter = if filter.starts_with('(') {
+        filter.to_string()
+    } else {
+        format!("({filter})")
+    };
+    let mut parser = FilterParser {
+        bytes: filter.as_bytes(),
+        position: 0,
+    };
+    let encoded = parser.filter()?;
+    if parser.position != parser.bytes.len() {
+        return Err(format!("LDAP 过滤器格式错误：第 {} 个字符后有多余内容", parser.position));
+    }
+    Ok(encoded)
+}
+
+struct FilterParser<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl FilterParser<'_> {
+    fn error(&self, detail: &str) -> String {
+        format!("LDAP 过滤器格式错误（位置 {}）：{detail}", self.position)
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), String> {
+        if self.bytes.get(self.position) != Some(&byte) {
+            return Err(self.error(&format!("缺少「{}」", byte as char)));
+        }
+        self.position += 1;
+        Ok(())
+    }
+
+    fn filter(&mut self) -> Result<Vec<u8>, String> {
+        self.expect(b'(')?;
+        let encoded = match self.bytes.get(self.position) {
+            Some(b'&') | Some(b'|') => {
+                let tag = if self.bytes[self.position] == b'&' { 0xa0 } else { 0xa1 };
+                self.position += 1;
+                let mut children = Vec::new();
+                while self.bytes.get(self.position) == Some(&b'(') {
+                    children.extend(self.filter()?);
+                }
+                if children.is_empty() {
+                    return Err(self.error("& / | 后至少需要一个条件"));
+                }
+                tlv(tag, &children)
+            }
+            Some(b'!') => {
+                self.position += 1;
+                tlv(0xa2, &self.filter()?)
+            }
+            Some(_) => self.item()?,
+            None => return Err(self.error("过滤器不完整")),
+        };
+        self.expect(b')')?;
+        Ok(encoded)
+    }
+
+    fn item(&mut self) -> Result<Vec<u8>, String> {
+        let start = self.position;
+        while self.bytes.get(self.position).is_some_and(|byte| *byte != b')' && *byte != b'(') {
+            self.position += 1;
+        }
+        let text = std::str::from_utf8(&self.bytes[start..self.position]).map_err(|_| self.error("编码无效"))?;
+        let equals = text.find('=').ok_or_else(|| self.error("条件缺少「=」"))?;
+        let (left, value) = (&text[..equals], &text[equals + 1..]);
+        let operator = |suffix: char| left.ends_with(suffix).then(|| left[..left.len() - 1].trim());
+        if let Some(attribute) = operator(':') {
+            return self.extensible(attribute, value);
+        }
+        let (tag, attribute) = match (operator('~'), operator('>'), operator('<')) {
+            (Some(attribute), _, _) => (0xa8, attribute),
+            (_, Some(attribute), _) => (0xa5, attribute),
+            (_, _, Some(attribute)) => (0xa6, attribute),
+            _ => (0xa3, left.trim()),
+        };
+        if attribute.is_empty() {
+            return Err(self.error("缺少属性名"));
+        }
+        if tag != 0xa3 || !value.contains('*') {
+            let mut content = octets(attribute.as_bytes());
+            content.extend(octets(&unescape(value).map_err(|detail| self.error(&detail))?));
+            return Ok(tlv(tag, &content));
+        }
+        if value == "*" {
+            return Ok(tlv(0x87, attribute.as_bytes()));
+        }
+        let parts: Vec<&str> = value.split('*').collect();
+        let mut substrings = Vec::new();
+        for (index, part) in parts.iter().enumerate() {
+            if part.is_empty() {
+                continue;
+            }
+            let tag = match index {
+                0 => 0x80,
+                last if last == parts.len() - 1 => 0x82,
+                _ => 0x81,
+            };
+            substrings.extend(tlv(tag, &unescape(part).map_err(|detail| self.error(&detail))?));
+        }
+        let mut content = octets(attribute.as_bytes());
+        content.extend(tlv(0x30, &substrings));
+        Ok(tlv(0xa4, &content))
+    }
+
+    /// `属性[:dn][:匹配规则]:=值`，如 AD 的 `userAccountControl:1.2.840.113556.1.4.803:=2`。
+    fn extensible(&mut self, left: &str, value: &str) -> Result<Vec<u8>, String> {
+        let mut parts = left.split(':');
+        let attribute = parts.next().unwrap_or_default().trim();
+        let mut dn_attributes = false;
+        let mut rule = None;
+        for part in parts {
+            if part.eq_ignore_ascii_case("dn") {
+                dn_attributes = true;
+            } else if !part.is_empty() {
+                rule = Some(part);
+            }
+        }
+        if attribute.is_empty() && rule.is_none() {
+            return Err(self.error("扩展匹配需要属性名或匹配规则"));
+        }
+        let mut content = Vec::new();
+        if let Some(rule) = rule {
+            content.extend(tlv(0x81, rule.as_bytes()));
+        }
+        if !attribute.is_empty() {
+            content.extend(tlv(0x82, attribute.as_bytes()));
+        }
+        content.extend(tlv(0x83, &unescape(value).map_err(|detail| self.error(&detail))?));
+        if dn_attributes {
+            content.extend(tlv(0x84, &[0xff]));
+        }
+        Ok(tlv(0xa9, &content))
+    }
+}
+
+/// 处理 `\2a` 形式的转义。
+fn unescape(value: &str) -> Result<Vec<u8>, String> {
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        if bytes[index] == b'\\' {
+            let hex = bytes
+                .get(index + 1..index + 3)
+                .and_then(|hex| std::str::from_utf8(hex).ok())
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                .ok_or_else(|| "转义字符应为 \\ 加两位十六进制数".to_string())?;
+            out.push(hex);
+            index += 3;
+        } else {
+            out.push(bytes[index]);
+            index += 1;
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        encode_filter, integer, octets, query_recipients, read_message, read_tlv, tlv, LdapQuery, LdapSettings,
+    };
+    use std::net::TcpListener;
+
+    #[test]
+    fn encodes_filters() {
+        assert_eq!(
+            encode_filter("(cn=Babs*)").expect("substring"),
+            tlv(0xa4, &[octets(b"cn"), tlv(0x30, &tlv(0x80, b"Babs"))].concat())
+        );
+        assert_eq!(encode_filter("mail=*").expect("present"), tlv(0x87, b"mail"));
+        let enabled = tlv(
+            0xa2,
+            &tlv(
+                0xa9,
+                &[tlv(0x81, b"1.2.840.113556.1.4.803"), tlv(0x82, b"userAccountControl"), tlv(0x83, b"2")].concat(),
+            ),
+        );
+        assert_eq!(
+            encode_filter("(&(mail=*)(!(userAccountControl:1.2.840.113556.1.4.803:=2)))").expect("and"),
+            tlv(0xa0, &[tlv(0x87, b"mail"), enabled].concat())
+        );
+        assert_eq!(
+            encode_filter("(o=a\\2ab)").expect("escaped"),
+            tlv(0xa3, &[octets(b"o"), octets(b"a*b")].concat())
+        );
+        assert!(encode_filter("(&(mail=*)").is_err());
+        assert!(encode_filter("(mail)").is_err());
+    }
+
+    /// 一次绑定、两页结果的本地 LDAP 服务器。
+    fn serve(listener: TcpListener) -> usize {
+        let (mut stream, _) = listener.accept().expect("accept");
+        let entry = |dn: &str, mail: &str, name: &str, department: &str| {
+            let attribute = |name: &str, value: &str| {
+                tlv(0x30, &[octets(name.as_bytes()), tlv(0x31, &octets(value.as_bytes()))].concat())
+            };
+            let attributes = [
+                attribute("mail", mail),
+                attribute("displayName", name),
+                attribute("department", department),
+            ]
+            .concat();
+            tlv(0x64, &[octets(dn.as_bytes()), tlv(0x30, &attributes)].concat())
+        };
+        let done = |cookie: &[u8]| {
+            let paging = tlv(0x30, &[integer(500), octets(cookie)].concat());
+            let control = tlv(0x30, &[octets(super::PAGED_RESULTS_OID.as_bytes()), octets(&paging)].concat());
+            (tlv(0x65, &[tlv(0x0a, &[0]), octets(b""), octets(b"")].concat()), tlv(0xa0, &control))
+        };
+        let mut searches = 0;
+        while let Ok(message) = read_message(&mut stream) {
+            let (_, content, _) = read_tlv(&message).expect("message");
+            let (_, id, rest) = read_tlv(content).expect("id");
+            let (operation, _, _) = read_tlv(rest).expect("operation");
+            let reply = |body: Vec<u8>, controls: Vec<u8>| tlv(0x30, &[tlv(0x02, id), body, controls].concat());
+            let mut out = Vec::new();
+            match operation {
+                0x60 => out.extend(reply(tlv(0x61, &[tlv(0x0a, &[0]), octets(b""), octets(b"")].concat()), Vec::new())),
+                0x63 => {
+                    searches += 1;
+                    if searches == 1 {
+                        out.extend(reply(entry("cn=a", "a@corp.example", "Alice", "Sales"), Vec::new()));
+                        out.extend(reply(entry("cn=b", "not-mail", "Bob", "Ops"), Vec::new()));
+                        let (body, controls) = done(b"next");
+                        out.extend(reply(body, controls));
+                    } else {
+                        out.extend(reply(entry("cn=c", "c@corp.example", "Carol", "Sales"), Vec::new()));
+                        let (body, controls) = done(b"");
+                        out.extend(reply(body, controls));
+                    }
+                }
+                _ => break,
+            }
+            std::io::Write::write_all(&mut stream, &out).expect("reply");
+        }
+        searches
+    }
+
+    #[test]
+    fn queries_directory_with_paging() {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind");
+        let port = listener.local_addr().expect("addr").port();
+        let server = std::thread::spawn(move || serve(listener));
+        let settings = LdapSettings {
+            host: "127.0.0.1".to_string(),
+            port,
+            use_ssl: false,
+            bind_dn: "cn=svc,dc=corp,dc=example".to_string(),
+            password: "secret".to_string(),
+            timeout_sec: 5,
+        };
+        let query = LdapQuery {
+            base_dn: "dc=corp,dc=example".to_string(),
+            fields: [("dept".to_string(), "department".to_string())].into(),
+            ..LdapQuery::default()
+        };
+        let result = query_recipients(&settings, &query).expect("query");
+        assert_eq!(server.join().expect("server"), 2);
+        assert_eq!(result.entries, 3);
+        assert_eq!(result.recipients.stats.invalid_email_rows, 1);
+        let recipients = &result.recipients.recipients;
+        assert_eq!(recipients.len(), 2);
+        assert_eq!((recipients[1].name.as_str(), recipients[1].fields["dept"].as_str()), ("Carol", "Sales"));
+    }
+}
@@ -0,0 +1,48 @@
+//! 群发邮件的核心逻辑：发送引擎、发送记录与各类名单存储、模板渲染、收件人解析。
+//! 不依赖 Tauri，桌面应用与其他前端（命令行、后台代理）共用同一实现。
+
+pub mod accounts;
+pub mod attachments;
+pub mod batch_bcc;
+pub mod campaign_log;
+pub mod canary;
+pub mod clock;
+pub mod concurrency;
+pub mod consent;
+pub mod database;
+pub mod dead_domains;
+pub mod direct_transport;
+pub mod disposable_domains;
+pub mod dsn;
+pub mod email_syntax;
+pub mod engine;
+pub mod enrichment;
+pub mod environment;
+pub mod greylist;
+pub mod imap_seed;
+pub mod ldap;
+pub mod local_mta;
+pub mod message_builder;
+pub mod message_size;
+pub mod net_policy;
+pub mod provider_policy;
+pub mod rcpt_probe;
+pub mod readback;
+pub mod recipient_filter;
+pub mod recipients_loader;
+pub mod record_notes;
+pub mod render_pipeline;
+pub mod retry;
+pub mod send_plan;
+pub mod sent_history;
+pub mod sent_store;
+pub mod smime;
+pub mod smtp_auth;
+pub mod smtp_client;
+pub mod suppression;
+pub mod template;
+pub mod throttle;
+pub mod tls_session;
+pub mod upload;
+pub mod worker_ipc;
+pub mod worker_protocol;
@@ -0,0 +1,281 @@
+use crate::smtp_client::{MailTransport, SendFailure};
+use lettre::address::Envelope;
+use lettre::Message;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// sysexits.h：EX_TEMPFAIL，MTA 暂时无法接收，稍后重试。
+const EX_TEMPFAIL: i32 = 75;
+/// 未指定时从 MTA 输出中识别队列号：Postfix / Sendmail 的 `queued as`、Exim 的 `id=`、
+/// Sendmail `-v` 的 `250 2.0.0 <id> Message accepted`。
+const DEFAULT_QUEUE_ID_PATTERN: &str =
+    r"(?i)(?:queued as|queue id:?|\bid=)\s*([A-Za-z0-9][A-Za-z0-9._-]*)|\b250 2\.0\.0 ([A-Za-z0-9]+) message accepted";
+
+/// 交给本机 MTA 投递，而不是直接连接 SMTP 服务器（单位要求所有邮件经由本机中继时使用）。
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum LocalMtaOptions {
+    /// sendmail 兼容程序（Sendmail、Postfix、Exim 均提供）；Postfix 经由它写入 maildrop 再由 pickup 入队。
+    Sendmail {
+        #[serde(default = "default_sendmail_path")]
+        path: String,
+        /// 附加参数，放在收件人之前，例如 `-C /etc/mail/relay.cf`。
+        #[serde(default)]
+        args: Vec<String>,
+        #[serde(default = "default_timeout_sec")]
+        timeout_sec: u32,
+        /// 从程序输出中提取队列号的正则（取第一个捕获组）；为空时使用内置规则。
+        #[serde(default)]
+        queue_id_pattern: Option<String>,
+    },
+    /// 写入 MTA 定时扫描的投递目录（IIS SMTP / Exchange 的 Pickup 目录），
+    /// 信封通过文件开头的 `X-Sender` / `X-Receiver` 行传递；文件名即队列号。
+    Pickup { directory: String },
+}
+
+fn default_sendmail_path() -> String {
+    "/usr/sbin/sendmail".to_string()
+}
+
+fn default_timeout_sec() -> u32 {
+    60
+}
+
+impl LocalMtaOptions {
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            LocalMtaOptions::Sendmail { path, timeout_sec, queue_id_pattern, .. } => {
+                if path.trim().is_empty() {
+                    return Err("sendmail 程序路径不能为空".to_string());
+                }
+                if *timeout_sec == 0 {
+                    return Err("sendmail 超时时间 必须 >= 1".to_string());
+                }
+                compile_pattern(queue_id_pattern.as_deref())?;
+            }
+            LocalMtaOptions::Pickup { directory } => {
+                if !PathBuf::from(directory.trim()).is_dir() {
+                    return Err(format!("投递目录不存在: {}", directory.trim()));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn compile_pattern(pattern: Option<&str>) -> Result<Regex, String> {
+    let pattern = pattern.map(str::trim).filter(|pattern| !pattern.is_empty());
+    Regex::new(pattern.unwrap_or(DEFAULT_QUEUE_ID_PATTERN)).map_err(|err| format!("队列号正则表达式无效: {err}"))
+}
+
+/// 本机 MTA 投递通道；每封邮件成功提交后记录 MTA 返回的队列号。
+pub struct LocalMtaTransport {
+    options: LocalMtaOptions,
+    queue_id_pattern: Regex,
+    last_queue_id: Option<String>,
+}
+
+impl LocalMtaTransport {
+    pub fn new(options: &LocalMtaOptions) -> Result<Self, String> {
+        options.validate()?;
+        let pattern = match options {
+            LocalMtaOptions::Sendmail { queue_id_pattern, .. } => queue_id_pattern.as_deref(),
+            LocalMtaOptions::Pickup { .. } => None,
+        };
+        Ok(LocalMtaTransport {
+            options: options.clone(),
+            queue_id_pattern: compile_pattern(pattern)?,
+            last_queue_id: None,
+        })
+    }
+
+    fn submit(&self, envelope: &Envelope, body: &[u8]) -> Result<Option<String>, SendFailure> {
+        match &self.options {
+            LocalMtaOptions::Sendmail { path, args, timeout_sec, .. } => {
+                let output = run_sendmail(path, args, envelope, body, Duration::from_secs((*timeout_sec).into()))?;
+                Ok(extract_queue_id(&self.queue_id_pattern, &output))
+            }
+            LocalMtaOptions::Pickup { directory } => write_pickup(directory, envelope, body).map(Some),
+        }
+    }
+}
+
+impl MailTransport for LocalMtaTransport {
+    fn send_message(&mut self, message: &Message) -> Result<(), SendFailure> {
+        self.send_raw(message.envelope(), &message.formatted())
+    }
+
+    fn send_raw(&mut self, envelope: &Envelope, body: &[u8]) -> Result<(), SendFailure> {
+        self.last_queue_id = None;
+        self.last_queue_id = self.submit(envelope, body)?;
+        Ok(())
+    }
+
+    fn last_queue_id(&self) -> Option<String> {
+        self.last_queue_id.clone()
+    }
+}
+
+fn local_failure(message: String) -> SendFailure {
+    SendFailure { code: None, message }
+}
+
+/// `sendmail -i -f <发件人> [附加参数] -- <收件人...>`，邮件从标准输入写入（换行转为本地的 LF）。
+/// 返回标准输出与标准错误的合并文本。
+fn run_sendmail(
+    path: &str,
+    args: &[String],
+    envelope: &Envelope,
+    body: &[u8],
+    timeout: Duration,
+) -> Result<String, SendFailure> {
+    let mut command = Command::new(path.trim());
+    command.arg("-i");
+    if let Some(from) = envelope.from() {
+        command.arg("-f").arg(from);
+    }
+    command.args(args).arg("--").args(envelope.to());
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| local_failure(format!("启动 sendmail 失败: {err}")))?;
+
+    let input: Vec<u8> = String::from_utf8_lossy(body).replace("\r\n", "\n").into_bytes();
+    let mut stdin = child.stdin.take();
+    let writer = std::thread::spawn(move || stdin.as_mut().map(|stdin| stdin.write_all(&input)));
+    let readers = [child.stdout.take().map(read_pipe), child.stderr.take().map(read_pipe)];
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        match child.try_wait().map_err(|err| local_failure(format!("等待 sendmail 失败: {err}")))? {
+            Some(status) => break status,
+            None if Instant::now() >= deadline => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(local_failure(format!("sendmail 超过 {} 秒未结束", timeout.as_secs())));
+            }
+            None => std::thread::sleep(Duration::from_millis(20)),
+        }
+    };
+    let written = writer.join().ok().flatten();
+    let output = readers
+        .into_iter()
+        .flatten()
+        .map(|reader| reader.join().unwrap_or_default())
+        .collect::<Vec<_>>()
+        .join("\n");
+    if !status.success() {
+        // 永久性的 sysexits 错误（用户不存在、数据错误等）按 5xx 处理，避免反复重试。
+        let code = status.code().filter(|code| *code != EX_TEMPFAIL).map(|_| 554);
+        return Err(SendFailure {
+            code,
+            message: format!("sendmail 投递失败（{status}）: {}", output.trim()),
+        });
+    }
+    if let Some(Err(err)) = written {
+        return Err(local_failure(format!("写入 sendmail 失败: {err}")));
+    }
+    Ok(output)
+}
+
+fn read_pipe(mut pipe: impl Read + Send + 'static) -> std::thread::JoinHandle<String> {
+    std::thread::spawn(move || {
+        let mut output = String::new();
+        let _ = pipe.read_to_string(&mut output);
+        output
+    })
+}
+
+fn extract_queue_id(pattern: &Regex, output: &str) -> Option<String> {
+    pattern.captures_iter(output).find_map(|captures| {
+        captures
+            .iter()
+            .skip(1)
+            .flatten()
+            .next()
+            .map(|id| id.as_str().to_string())
+    })
+}
+
+/// 先写入临时文件再改名，避免 MTA 扫描到写了一半的邮件。
+fn write_pickup(directory: &str, envelope: &Envelope, body: &[u8]) -> Result<String, SendFailure> {
+    let directory = PathBuf::from(directory.trim());
+    let queue_id = format!("bes-{}-{:08x}", chrono::Utc::now().format("%Y%m%d%H%M%S%3f"), rand::random::<u32>());
+    let mut content = Vec::with_capacity(body.len() + 256);
+    if let Some(from) = envelope.from() {
+        content.extend_from_slice(format!("X-Sender: {from}\r\n").as_bytes());
+    }
+    for address in envelope.to() {
+        content.extend_from_slice(format!("X-Receiver: {address}\r\n").as_bytes());
+    }
+    content.extend_from_slice(body);
+    let temp = directory.join(format!(".{queue_id}.tmp"));
+    let target = directory.join(format!("{queue_id}.eml"));
+    fs::write(&temp, &content)
+        .and_then(|_| fs::rename(&temp, &target))
+        .map_err(|err| {
+            let _ = fs::remove_file(&temp);
+            local_failure(format!("写入投递目录失败: {err}"))
+        })?;
+    Ok(queue_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compile_pattern, extract_queue_id, LocalMtaOptions, LocalMtaTransport};
+    use crate::smtp_client::MailTransport;
+    use lettre::address::Envelope;
+
+    #[test]
+    fn extracts_queue_ids_from_mta_output() {
+        let pattern = compile_pattern(None).expect("default pattern");
+        assert_eq!(
+            extract_queue_id(&pattern, "postfix/smtp: 250 2.0.0 Ok: queued as 4XyZ12AbCd").as_deref(),
+            Some("4XyZ12AbCd")
+        );
+        assert_eq!(
+            extract_queue_id(&pattern, "LOG: MAIN\n  <= sender@example.edu U=app P=local S=812 id=1tAbCd-000Xy-9Q")
+                .as_deref(),
+            Some("1tAbCd-000Xy-9Q")
+        );
+        assert_eq!(
+            extract_queue_id(&pattern, "250 2.0.0 4BJ8Xa1k012345 Message accepted for delivery").as_deref(),
+            Some("4BJ8Xa1k012345")
+        );
+        assert_eq!(extract_queue_id(&pattern, ""), None);
+        let custom = compile_pattern(Some(r"ticket=(\d+)")).expect("custom pattern");
+        assert_eq!(extract_queue_id(&custom, "ok ticket=42").as_deref(), Some("42"));
+        assert!(compile_pattern(Some("(")).is_err());
+    }
+
+    #[test]
+    fn writes_messages_to_pickup_directory() {
+        let dir = std::env::temp_dir().join(format!("bes-pickup-{:08x}", rand::random::<u32>()));
+        std::fs::create_dir_all(&dir).expect("pickup dir");
+        let options = LocalMtaOptions::Pickup { directory: dir.to_string_lossy().into_owned() };
+        let mut transport = LocalMtaTransport::new(&options).expect("transport");
+        let envelope = Envelope::new(
+            Some("sender@example.edu".parse().expect("from")),
+            vec!["a@example.com".parse().expect("to"), "b@example.com".parse().expect("bcc")],
+        )
+        .expect("envelope");
+        transport
+            .send_raw(&envelope, b"Subject: hi\r\n\r\nbody\r\n")
+            .expect("pickup");
+        let queue_id = transport.last_queue_id().expect("queue id");
+
+        let files: Vec<_> = std::fs::read_dir(&dir).expect("read dir").flatten().map(|entry| entry.path()).collect();
+        assert_eq!(files, [dir.join(format!("{queue_id}.eml"))]);
+        let content = std::fs::read_to_string(&files[0]).expect("message");
+        assert!(content.starts_with(
+            "X-Sender: sender@example.edu\r\nX-Receiver: a@example.com\r\nX-Receiver: b@example.com\r\nSubject: hi"
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,737 @@
+use crate::attachments::ascii_file_name;
+use crate::smime::{encrypt_enveloped, sign_detached, SmimeContext};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use lettre::address::Envelope;
+use lettre::message::header::{ContentType, HeaderName, HeaderValue};
+use lettre::message::{Attachment, Mailbox, Mailboxes, MultiPart, SinglePart};
+use lettre::{Address, Message};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+/// 所有内嵌图片合计的大小上限。
+pub const MAX_INLINE_IMAGES_BYTES: u64 = 10 * 1024 * 1024;
+
+/// 由发送流程生成、不允许通过自定义邮件头覆盖的字段。
+const PROTECTED_HEADERS: [&str; 17] = [
+    "from",
+    "to",
+    "cc",
+    "bcc",
+    "sender",
+    "subject",
+    "date",
+    "message-id",
+    "return-path",
+    "received",
+    "mime-version",
+    "content-type",
+    "content-transfer-encoding",
+    "content-disposition",
+    "content-id",
+    "dkim-signature",
+    "disposition-notification-to",
+];
+
+/// RFC 5322 单行上限（不含 CRLF）。
+const MAX_HEADER_LINE_LEN: usize = 998;
+
+/// HTML 正文中以 `cid:<cid>` 引用的本地图片。
+#[derive(Deserialize, Clone, Debug)]
+pub struct InlineImage {
+    pub path: String,
+    pub cid: String,
+}
+
+pub struct MessageContent<'a> {
+    pub sender_email: &'a str,
+    pub sender_name: &'a str,
+    pub recipient_email: &'a str,
+    pub subject: &'a str,
+    pub body_text: &'a str,
+    pub body_html: Option<&'a str>,
+    pub attachments: &'a [String],
+    pub inline_images: &'a [InlineImage],
+    pub headers: &'a BTreeMap<String, String>,
+    pub cc: &'a [String],
+    pub bcc: &'a [String],
+    pub smime: Option<&'a SmimeContext>,
+    /// 信封发件人（退信地址）；为空时由 From 推导。
+    pub return_path: Option<&'a Address>,
+    /// 把附件文件名转写为 ASCII，避免不支持 RFC 2231 的客户端显示乱码。
+    pub ascii_attachment_names: bool,
+    /// 指定的 Message-ID（含尖括号）；为空时由 lettre 按本机主机名生成。
+    pub message_id: Option<&'a str>,
+    /// 阅读回执的接收地址，写入 Disposition-Notification-To。
+    pub read_receipt_to: Option<&'a str>,
+}
+
+/// 生成稳定的 Message-ID：同一任务、同一组投递地址总是得到同一个 ID，
+/// 重试与重新渲染都不会改变，便于之后把退信关联回发送记录。
+pub fn stable_message_id(domain: &str, job_id: &str, to: &str, bcc: &[String]) -> String {
+    let job: String = job_id
+        .chars()
+        .map(|ch| if ch.is_ascii_alphanumeric() || matches!(ch, '.' | '-' | '_') { ch } else { '-' })
+        .collect();
+    let bcc: Vec<String> = bcc.iter().map(|address| address.trim().to_lowercase()).collect();
+    let mut hasher = Sha256::new();
+    hasher.update(job_id.as_bytes());
+    hasher.update(b"\n");
+    hasher.update(to.trim().to_lowercase().as_bytes());
+    hasher.update(b"\n");
+    hasher.update(bcc.join(",").as_bytes());
+    let digest = format!("{:x}", hasher.finalize());
+    format!("<{job}.{}@{}>", &digest[..16], domain.trim().to_lowercase())
+}
+
+/// 校验 Message-ID 的域名部分：以点分隔的字母、数字与连字符。
+pub fn validate_message_id_domain(domain: &str) -> Result<(), String> {
+    let domain = domain.trim();
+    let valid = !domain.is_empty()
+        && domain.len() <= 253
+        && domain.split('.').all(|label| {
+            !label.is_empty() && label.chars().all(|ch| ch.is_ascii_alphanumeric() || ch == '-')
+        });
+    if !valid {
+        return Err(format!("Message-ID 域名格式不正确: {domain}"));
+    }
+    Ok(())
+}
+
+/// 邮件正文的顶层 MIME 实体，S/MIME 需要对其原始字节签名或加密。
+enum MimeBody {
+    Single(SinglePart),
+    Multi(MultiPart),
+}
+
+impl MimeBody {
+    fn formatted(&self) -> Vec<u8> {
+        match self {
+            MimeBody::Single(part) => part.formatted(),
+            MimeBody::Multi(part) => part.formatted(),
+        }
+    }
+}
+
+pub fn build_email_message(content: &MessageContent<'_>) -> Result<Message, String> {
+    let from = Mailbox::new(
+        Some(content.sender_name.to_string()).filter(|name| !name.is_empty()),
+        content
+            .sender_email
+            .parse()
+            .map_err(|err| format!("发件邮箱格式不正确: {err}"))?,
+    );
+    let to: Mailbox = content
+        .recipient_email
+        .parse()
+        .map_err(|err| format!("收件邮箱格式不正确: {err}"))?;
+
+    let mut builder = Message::builder()
+        .from(from)
+        .to(to)
+        .subject(content.subject)
+        .message_id(content.message_id.map(str::to_string));
+    for address in content.cc {
+        builder = builder.cc(address.parse().map_err(|err| format!("抄送邮箱格式不正确: {err}"))?);
+    }
+    for address in content.bcc {
+        builder = builder.bcc(address.parse().map_err(|err| format!("密送邮箱格式不正确: {err}"))?);
+    }
+    if let Some(return_path) = content.return_path {
+        let recipients = std::iter::once(content.recipient_email)
+            .chain(content.cc.iter().map(String::as_str))
+            .chain(content.bcc.iter().map(String::as_str))
+            .map(|address| address.trim().parse::<Address>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| format!("收件邮箱格式不正确: {err}"))?;
+        let envelope =
+            Envelope::new(Some(return_path.clone()), recipients).map_err(|err| format!("构建邮件信封失败: {err}"))?;
+        builder = builder.envelope(envelope);
+    }
+    if let Some(address) = content.read_receipt_to {
+        address
+            .parse::<Mailbox>()
+            .map_err(|err| format!("阅读回执地址格式不正确: {err}"))?;
+        builder = builder.raw_header(HeaderValue::new(
+            HeaderName::new_from_ascii_str("Disposition-Notification-To"),
+            address.to_string(),
+        ));
+    }
+    for (name, value) in content.headers {
+        let name = HeaderName::new_from_ascii(name.trim().to_string())
+            .map_err(|_| format!("自定义邮件头名称无效: {name}"))?;
+        builder = builder.raw_header(HeaderValue::new(name, value.trim().to_string()));
+    }
+
+    let html = content.body_html.filter(|html| !html.trim().is_empty());
+    let alternative = match html {
+        Some(html) => Some(build_alternative(content.body_text, html, content.inline_images)?),
+        None => None,
+    };
+    let mut body = match (alternative, content.attachments.is_empty()) {
+        (None, true) => MimeBody::Single(SinglePart::plain(content.body_text.to_string())),
+        (Some(alternative), true) => MimeBody::Multi(alternative),
+        (alternative, false) => {
+            let mut mixed = match alternative {
+                Some(alternative) => MultiPart::mixed().multipart(alternative),
+                None => MultiPart::mixed().singlepart(SinglePart::plain(content.body_text.to_string())),
+            };
+            let mut used_names = HashSet::new();
+            for attachment in content.attachments {
+                let path = Path::new(attachment);
+                let mut file_name = path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_else(|| "attachment".to_string());
+                if content.ascii_attachment_names {
+                    file_name = unique_file_name(&ascii_file_name(&file_name), &mut used_names);
+                }
+                mixed = mixed.singlepart(build_attachment(path, file_name)?);
+            }
+            MimeBody::Multi(mixed)
+        }
+    };
+    if let Some(smime) = content.smime {
+        let recipients: Vec<&str> = std::iter::once(content.recipient_email)
+            .chain(content.cc.iter().map(String::as_str))
+            .chain(content.bcc.iter().map(String::as_str))
+            .collect();
+        body = protect_with_smime(body, smime, &recipients)?;
+    }
+    let message = match body {
+        MimeBody::Single(part) => builder.singlepart(part),
+        MimeBody::Multi(part) => builder.multipart(part),
+    };
+    message.map_err(|err| format!("构建邮件失败: {err}"))
+}
+
+/// 先签名（`multipart/signed`），再按需整体加密（`application/pkcs7-mime`）。
+fn protect_with_smime(body: MimeBody, smime: &SmimeContext, recipients: &[&str]) -> Result<MimeBody, String> {
+    let mut body = body;
+    if let Some(identity) = &smime.signer {
+        let signature = sign_detached(identity, &body.formatted())?;
+        let signed = MultiPart::signed("application/pkcs7-signature".to_string(), "sha-256".to_string());
+        let signed = match body {
+            MimeBody::Single(part) => signed.singlepart(part),
+            MimeBody::Multi(part) => signed.multipart(part),
+        };
+        let content_type = ContentType::parse("application/pkcs7-signature").map_err(|err| err.to_string())?;
+        body = MimeBody::Multi(signed.singlepart(Attachment::new("smime.p7s".to_string()).body(signature, content_type)));
+    }
+    if let Some(certificates) = smime.encryption_certs(recipients) {
+        let enveloped = encrypt_enveloped(&body.formatted(), &certificates)?;
+        let content_type = ContentType::parse("application/pkcs7-mime; smime-type=enveloped-data; name=smime.p7m")
+            .map_err(|err| err.to_string())?;
+        body = MimeBody::Single(Attachment::new("smime.p7m".to_string()).body(enveloped, content_type));
+    }
+    Ok(body)
+}
+
+/// 有内嵌图片时，HTML 与图片组成 `multipart/related`，再与纯文本组成 `multipart/alternative`。
+fn build_alternative(body_text: &str, html: &str, inline_images: &[InlineImage]) -> Result<MultiPart, String> {
+    if inline_images.is_empty() {
+        return Ok(MultiPart::alternative_plain_html(body_text.to_string(), html.to_string()));
+    }
+    let mut related = MultiPart::related().singlepart(SinglePart::html(html.to_string()));
+    for image in inline_images {
+        let path = Path::new(&image.path);
+        let bytes = fs::read(path).map_err(|err| format!("读取内嵌图片失败 {}: {err}", path.display()))?;
+        related = related.singlepart(Attachment::new_inline(image.cid.trim().to_string()).body(bytes, guess_content_type(path)));
+    }
+    Ok(MultiPart::alternative()
+        .singlepart(SinglePart::plain(body_text.to_string()))
+        .multipart(related))
+}
+
+/// 校验内嵌图片：CID 非空且不重复、文件存在且为图片、合计大小不超过上限。返回合计字节数。
+pub fn validate_inline_images(inline_images: &[InlineImage]) -> Result<u64, String> {
+    let mut seen = HashSet::new();
+    let mut total: u64 = 0;
+    for image in inline_images {
+        let cid = image.cid.trim();
+        if cid.is_empty() || cid.contains(|ch: char| ch.is_whitespace() || ch == '<' || ch == '>') {
+            return Err(format!("内嵌图片 CID 无效: {}", image.cid));
+        }
+        if !seen.insert(cid.to_lowercase()) {
+            return Err(format!("内嵌图片 CID 重复: {cid}"));
+        }
+        let path = Path::new(&image.path);
+        let metadata = fs::metadata(path)
+            .ok()
+            .filter(|metadata| metadata.is_file())
+            .ok_or_else(|| format!("Inline image not found: {}", image.path))?;
+        if mime_guess::from_path(path).first_or_octet_stream().type_() != mime_guess::mime::IMAGE {
+            return Err(format!("内嵌图片必须是图片文件: {}", image.path));
+        }
+        total = total.saturating_add(metadata.len());
+    }
+    if total > MAX_INLINE_IMAGES_BYTES {
+        return Err(format!(
+            "内嵌图片合计 {:.1} MB，超过上限 {} MB",
+            total as f64 / 1024.0 / 1024.0,
+            MAX_INLINE_IMAGES_BYTES / 1024 / 1024
+        ));
+    }
+    Ok(total)
+}
+
+/// 校验自定义邮件头：名称符合 RFC 5322、不能覆盖发送流程生成的字段、值不能包含换行；
+/// Reply-To / List-Unsubscribe 额外校验格式。
+pub fn validate_custom_headers(headers: &BTreeMap<String, String>) -> Result<(), String> {
+    let mut seen = HashSet::new();
+    for (name, value) in headers {
+        let name = name.trim();
+        let valid_name = !name.is_empty() && name.bytes().all(|byte| (33..=126).contains(&byte) && byte != b':');
+        if !valid_name {
+            return Err(format!("自定义邮件头名称无效: {name}"));
+        }
+        let lowered = name.to_ascii_lowercase();
+        if PROTECTED_HEADERS.contains(&lowered.as_str()) {
+            return Err(format!("不允许通过自定义邮件头覆盖 {name}"));
+        }
+        if !seen.insert(lowered.clone()) {
+            return Err(format!("自定义邮件头重复: {name}"));
+        }
+        if value.contains(['\r', '\n']) {
+            return Err(format!("自定义邮件头 {name} 的值不能包含换行"));
+        }
+        let value = value.trim();
+        if value.is_empty() {
+            return Err(format!("自定义邮件头 {name} 的值不能为空"));
+        }
+        if name.len() + 2 + value.len() > MAX_HEADER_LINE_LEN {
+            return Err(format!("自定义邮件头 {name} 过长（上限 {MAX_HEADER_LINE_LEN} 字符）"));
+        }
+        match lowered.as_str() {
+            "reply-to" => {
+                value
+                    .parse::<Mailboxes>()
+                    .map_err(|err| format!("Reply-To 格式不正确: {err}"))?;
+            }
+            "list-unsubscribe" => {
+                for item in value.split(',') {
+                    let target = item
+                        .trim()
+                        .strip_prefix('<')
+                        .and_then(|rest| rest.strip_suffix('>'))
+                        .ok_or_else(|| format!("List-Unsubscribe 每一项都需要用尖括号包裹: {}", item.trim()))?;
+                    if !["mailto:", "https://", "http://"].iter().any(|scheme| target.starts_with(scheme)) {
+                        return Err(format!("List-Unsubscribe 仅支持 mailto: 或 http(s) 地址: {target}"));
+                    }
+                }
+            }
+            "list-unsubscribe-post" if value != "List-Unsubscribe=One-Click" => {
+                return Err("List-Unsubscribe-Post 的值必须是 List-Unsubscribe=One-Click".to_string());
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn guess_content_type(path: &Path) -> ContentType {
+    let mime = mime_guess::from_path(path).first_or_octet_stream();
+    ContentType::parse(mime.essence_str())
+        .unwrap_or_else(|_| ContentType::parse("application/octet-stream").expect("valid mime"))
+}
+
+/// Content-Disposition 中的文件名由 lettre 按 RFC 2231 编码；非 ASCII 文件名另外以 RFC 2047
+/// 编码字写入 Content-Type 的 `name` 参数，供 Outlook 等只认该参数的客户端使用。
+fn build_attachment(path: &Path, file_name: String) -> Result<SinglePart, String> {
+    let bytes = fs::read(path).map_err(|err| format!("读取附件失败 {}: {err}", path.display()))?;
+    let mut content_type = guess_content_type(path);
+    if !file_name.is_ascii() {
+        let mime = mime_guess::from_path(path).first_or_octet_stream();
+        let with_name = format!("{}; name=\"{}\"", mime.essence_str(), encode_words(&file_name));
+        content_type = ContentType::parse(&with_name).unwrap_or(content_type);
+    }
+    Ok(Attachment::new(file_name).body(bytes, content_type))
+}
+
+/// RFC 2047 `B` 编码；按字符边界切分，保证每个编码字不超过 75 个字符。
+fn encode_words(text: &str) -> String {
+    let mut words = Vec::new();
+    let mut chunk = String::new();
+    for ch in text.chars() {
+        if chunk.len() + ch.len_utf8() > 45 {
+            words.push(format!("=?UTF-8?B?{}?=", BASE64.encode(&chunk)));
+            chunk.clear();
+        }
+        chunk.push(ch);
+    }
+    if !chunk.is_empty() {
+        words.push(format!("=?UTF-8?B?{}?=", BASE64.encode(&chunk)));
+    }
+    words.join(" ")
+}
+
+/// 转写后可能重名（如两个纯中文文件名都变成 `attachment.pdf`），重名时追加序号。
+fn unique_file_name(name: &str, used: &mut HashSet<String>) -> String {
+    let (stem, extension) = match name.rsplit_once('.') {
+        Some((stem, extension)) => (stem, format!(".{extension}")),
+        None => (name, String::new()),
+    };
+    let mut candidate = name.to_string();
+    let mut counter = 2;
+    while !used.insert(candidate.to_lowercase()) {
+        candidate = format!("{stem}-{counter}{extension}");
+        counter += 1;
+    }
+    candidate
+}
+
+/// 由 HTML 正文生成纯文本备选内容：去掉脚本与样式，保留段落换行、列表与链接地址。
+pub fn html_to_plain_text(html: &str) -> String {
+    let mut output = String::new();
+    let mut rest = html;
+    let mut pending_href: Option<String> = None;
+    let mut link_text_start = 0;
+    while let Some(start) = rest.find('<') {
+        output.push_str(&decode_entities(&rest[..start]));
+        let after = &rest[start + 1..];
+        let Some(end) = after.find('>') else {
+            rest = "";
+            break;
+        };
+        let tag = &after[..end];
+        rest = &after[end + 1..];
+        let name = tag_name(tag);
+        match name.as_str() {
+            "script" | "style" | "head" | "title" => {
+                let closing = format!("</{name}");
+                rest = match rest.to_ascii_lowercase().find(&closing) {
+                    Some(position) => rest[position..].split_once('>').map(|(_, tail)| tail).unwrap_or(""),
+                    None => "",
+                };
+            }
+            "br" => output.push('\n'),
+            "p" | "div" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "table" | "tr" | "ul" | "ol" | "blockquote" => {
+                push_break(&mut output)
+            }
+            "/p" | "/div" | "/h1" | "/h2" | "/h3" | "/h4" | "/h5" | "/h6" | "/table" | "/ul" | "/ol"
+            | "/blockquote" => push_break(&mut output),
+            "/tr" | "/li" => output.push('\n'),
+            "li" => output.push_str("- "),
+            "td" | "th" => output.push(' '),
+            "img" => {
+                if let Some(alt) = attribute(tag, "alt").filter(|alt| !alt.is_empty()) {
+                    output.push_str(&alt);
+                }
+            }
+            "a" => {
+                pending_href = attribute(tag, "href");
+                link_text_start = output.len();
+            }
+            "/a" => {
+                if let Some(href) = pending_href.take() {
+                    let text = output[link_text_start..].trim().to_string();
+                    let target = href.strip_prefix("mailto:").unwrap_or(&href);
+                    if !href.starts_with('#') && !target.is_empty() && text != target {
+                        output.push_str(&format!(" ({target})"));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    output.push_str(&decode_entities(rest));
+    tidy_whitespace(&output)
+}
+
+fn push_break(output: &mut String) {
+    if !output.is_empty() {
+        output.push_str("\n\n");
+    }
+}
+
+fn tag_name(tag: &str) -> String {
+    let tag = tag.trim_start();
+    let (prefix, rest) = match tag.strip_prefix('/') {
+        Some(rest) => ("/", rest),
+        None => ("", tag),
+    };
+    let name: String = rest.chars().take_while(|ch| ch.is_ascii_alphanumeric()).collect();
+    format!("{prefix}{}", name.to_ascii_lowercase())
+}
+
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    let lowered = tag.to_ascii_lowercase();
+    let mut search_from = 0;
+    while let Some(found) = lowered[search_from..].find(name) {
+        let start = search_from + found;
+        search_from = start + name.len();
+        let preceded_by_space = lowered[..start].ends_with(|ch: char| ch.is_whitespace());
+        let value = lowered[search_from..].trim_start();
+        if !preceded_by_space || !value.starts_with('=') {
+            continue;
+        }
+        let offset = tag.len() - value.len() + 1;
+        let raw = tag[offset..].trim_start();
+        let value = match raw.chars().next() {
+            Some(quote @ ('"' | '\'')) => raw[1..].split(quote).next().unwrap_or_default(),
+            _ => raw.split(|ch: char| ch.is_whitespace() || ch == '>').next().unwrap_or_default(),
+        };
+        return Some(decode_entities(value).trim().to_string());
+    }
+    None
+}
+
+fn decode_entities(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        output.push_str(&rest[..start]);
+        let candidate = &rest[start + 1..];
+        let decoded = candidate.find(';').filter(|end| *end <= 10).and_then(|end| {
+            let entity = &candidate[..end];
+            let ch = match entity {
+                "nbsp" => Some(' '),
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                _ => entity
+                    .strip_prefix("#x")
+                    .or_else(|| entity.strip_prefix("#X"))
+                    .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                    .or_else(|| entity.strip_prefix('#').and_then(|dec| dec.parse().ok()))
+                    .and_then(char::from_u32),
+            }?;
+            Some((ch, end))
+        });
+        match decoded {
+            Some((ch, end)) => {
+                output.push(ch);
+                rest = &candidate[end + 1..];
+            }
+            None => {
+                output.push('&');
+                rest = candidate;
+            }
+        }
+    }
+    output.push_str(rest);
+    output
+}
+
+/// 合并行内多余空白，最多保留一个空行。
+fn tidy_whitespace(text: &str) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    for line in text.lines() {
+        let collapsed = line.split_whitespace().collect::<Vec<_>>().join(" ");
+        if collapsed.is_empty() && lines.last().is_none_or(|last| last.is_empty()) {
+            continue;
+        }
+        lines.push(collapsed);
+    }
+    while lines.last().is_some_and(|last| last.is_empty()) {
+        lines.pop();
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        build_email_message, html_to_plain_text, stable_message_id, validate_custom_headers, validate_inline_images,
+        validate_message_id_domain, InlineImage, MessageContent,
+    };
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn converts_html_to_plain_text_fallback() {
+        let html = "<html><head><style>p { color: red; }</style></head><body>\
+            <p>张老师&nbsp;您好，</p><p>附上<a href=\"https://example.com/cv\">简历</a>，\
+            请查阅。<br>谢谢！</p><ul><li>第一项</li><li>第二项 &amp; 补充</li></ul>\
+            <img src=\"cid:logo\" alt=\"Logo\"></body></html>";
+        assert_eq!(
+            html_to_plain_text(html),
+            "张老师 您好，\n\n附上简历 (https://example.com/cv)，请查阅。\n谢谢！\n\n- 第一项\n- 第二项 & 补充\n\nLogo"
+        );
+    }
+
+    #[test]
+    fn embeds_inline_images_as_related_parts() {
+        let dir = std::env::temp_dir().join(format!("bes-inline-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("temp dir");
+        let logo = dir.join("logo.png");
+        std::fs::write(&logo, b"\x89PNG\r\n\x1a\n").expect("write image");
+        let images = vec![InlineImage {
+            path: logo.to_string_lossy().to_string(),
+            cid: "logo".to_string(),
+        }];
+        assert_eq!(validate_inline_images(&images).expect("valid images"), 8);
+
+        let message = build_email_message(&MessageContent {
+            sender_email: "sender@example.com",
+            sender_name: "Sender",
+            recipient_email: "teacher@example.com",
+            subject: "Hello",
+            body_text: "Hello",
+            body_html: Some("<p>Hello</p><img src=\"cid:logo\">"),
+            attachments: &[],
+            inline_images: &images,
+            headers: &BTreeMap::new(),
+            cc: &[],
+            bcc: &[],
+            smime: None,
+            return_path: None,
+            ascii_attachment_names: false,
+            message_id: None,
+            read_receipt_to: None,
+        })
+        .expect("build message");
+        let formatted = String::from_utf8(message.formatted()).expect("utf8 message");
+        assert!(formatted.contains("multipart/alternative"));
+        assert!(formatted.contains("multipart/related"));
+        assert!(formatted.contains("Content-ID: <logo>"));
+
+        let duplicate = vec![images[0].clone(), images[0].clone()];
+        assert!(validate_inline_images(&duplicate).is_err());
+        let missing = vec![InlineImage {
+            path: dir.join("missing.png").to_string_lossy().to_string(),
+            cid: "missing".to_string(),
+        }];
+        let _ = std::fs::remove_dir_all(&dir);
+        assert!(validate_inline_images(&missing).is_err());
+    }
+
+    #[test]
+    fn encodes_non_ascii_attachment_names_for_legacy_clients() {
+        let dir = std::env::temp_dir().join(format!("bes-attachment-name-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("temp dir");
+        let attachments: Vec<String> = ["招生简章.pdf", "报名表.pdf"]
+            .iter()
+            .map(|name| {
+                let path = dir.join(name);
+                std::fs::write(&path, b"%PDF-1.4").expect("write attachment");
+                path.to_string_lossy().to_string()
+            })
+            .collect();
+        let build = |ascii_attachment_names| {
+            let message = build_email_message(&MessageContent {
+                sender_email: "sender@example.com",
+                sender_name: "Sender",
+                recipient_email: "teacher@example.com",
+                subject: "Hello",
+                body_text: "Hello",
+                body_html: None,
+                attachments: &attachments,
+                inline_images: &[],
+                headers: &BTreeMap::new(),
+                cc: &[],
+                bcc: &[],
+                smime: None,
+                return_path: None,
+                ascii_attachment_names,
+                message_id: None,
+                read_receipt_to: None,
+            })
+            .expect("build message");
+            String::from_utf8(message.formatted()).expect("utf8 message")
+        };
+        let encoded = build(false);
+        let ascii = build(true);
+        let _ = std::fs::remove_dir_all(&dir);
+        assert!(encoded.contains("filename*"));
+        assert!(encoded.contains("name=\"=?UTF-8?B?5oub55Sf566A56ugLnBkZg==?=\""));
+        assert!(ascii.contains("filename=\"attachment.pdf\""));
+        assert!(ascii.contains("filename=\"attachment-2.pdf\""));
+        assert!(!ascii.contains("=?UTF-8?B?"));
+    }
+
+    #[test]
+    fn validates_and_applies_custom_headers() {
+        let headers = BTreeMap::from([
+            ("Reply-To".to_string(), "Office <office@example.com>".to_string()),
+            (
+                "List-Unsubscribe".to_string(),
+                "<mailto:unsubscribe@example.com>, <https://example.com/u?id=1>".to_string(),
+            ),
+            ("X-Campaign-Id".to_string(), "spring-2024".to_string()),
+        ]);
+        validate_custom_headers(&headers).expect("valid headers");
+
+        let message = build_email_message(&MessageContent {
+            sender_email: "sender@example.com",
+            sender_name: "Sender",
+            recipient_email: "teacher@example.com",
+            subject: "Hello",
+            body_text: "Hello",
+            body_html: None,
+            attachments: &[],
+            inline_images: &[],
+            headers: &headers,
+            cc: &["office@example.com".to_string()],
+            bcc: &["audit@example.com".to_string()],
+            smime: None,
+            return_path: Some(&"bounces@example.com".parse().expect("return path")),
+            ascii_attachment_names: false,
+            message_id: None,
+            read_receipt_to: None,
+        })
+        .expect("build message");
+        let formatted = String::from_utf8(message.formatted()).expect("utf8 message");
+        assert!(formatted.contains("Reply-To: Office <office@example.com>"));
+        assert!(formatted.contains("X-Campaign-Id: spring-2024"));
+        assert!(formatted.contains("Cc: office@example.com"));
+        assert!(!formatted.contains("audit@example.com"));
+        assert_eq!(message.envelope().to().len(), 3);
+        assert_eq!(message.envelope().from().map(ToString::to_string).as_deref(), Some("bounces@example.com"));
+        assert!(!formatted.contains("bounces@example.com"));
+
+        let rejected = [
+            ("From", "attacker@example.com"),
+            ("X-Note", "line\r\nBcc: victim@example.com"),
+            ("Bad Name", "value"),
+            ("Reply-To", "not an address"),
+            ("List-Unsubscribe", "https://example.com/u"),
+        ];
+        for (name, value) in rejected {
+            let headers = BTreeMap::from([(name.to_string(), value.to_string())]);
+            assert!(validate_custom_headers(&headers).is_err(), "{name} should be rejected");
+        }
+    }
+
+    #[test]
+    fn generates_stable_message_ids() {
+        let bcc = vec!["Office@Example.edu".to_string()];
+        let id = stable_message_id("Example.EDU", "job 42", "Teacher@example.edu", &bcc);
+        assert!(id.starts_with("<job-42."));
+        assert!(id.ends_with("@example.edu>"));
+        let normalized = stable_message_id("example.edu", "job 42", "teacher@example.edu ", &["office@example.edu".to_string()]);
+        assert_eq!(id, normalized);
+        assert_ne!(id, stable_message_id("example.edu", "job 43", "teacher@example.edu", &bcc));
+        assert_ne!(id, stable_message_id("example.edu", "job 42", "other@example.edu", &bcc));
+        assert!(validate_message_id_domain("mail.example.edu").is_ok());
+        assert!(validate_message_id_domain("bad domain").is_err());
+        assert!(validate_message_id_domain("example..edu").is_err());
+
+        let message = build_email_message(&MessageContent {
+            sender_email: "sender@example.com",
+            sender_name: "",
+            recipient_email: "teacher@example.edu",
+            subject: "Hello",
+            body_text: "Hello",
+            body_html: None,
+            attachments: &[],
+            inline_images: &[],
+            headers: &BTreeMap::new(),
+            cc: &[],
+            bcc: &[],
+            smime: None,
+            return_path: None,
+            ascii_attachment_names: false,
+            message_id: Some(&id),
+            read_receipt_to: Some("office@example.edu"),
+        })
+        .expect("build message");
+        let formatted = String::from_utf8(message.formatted()).expect("utf8 message");
+        assert!(formatted.contains(&format!("Message-ID: {id}")));
+        assert!(formatted.contains("Disposition-Notification-To: office@example.edu"));
+    }
+}
@@ -0,0 +1,129 @@
+use crate::attachments::{base64_encoded_len, format_bytes};
+use crate::engine::{message_size_without_attachments, SendJob};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// 每个附件 MIME 头部（Content-Type / Content-Disposition / 分隔线）的估算开销。
+const ATTACHMENT_PART_OVERHEAD: u64 = 256;
+
+/// 邮件体积超过上限时的处理方式。
+#[derive(Deserialize, Serialize, Clone, Copy, Default, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum OversizeAction {
+    /// 仅在预检中标记，照常发送。
+    #[default]
+    Flag,
+    /// 上传附件，超限的收件人改为收到正文中的下载链接。
+    Link,
+}
+
+#[derive(Deserialize, Serialize, Clone, Default, Debug)]
+pub struct OversizeOptions {
+    #[serde(default)]
+    pub action: OversizeAction,
+    /// 单封邮件的体积上限；为空时按发件服务商规则。
+    #[serde(default)]
+    pub limit_bytes: Option<u64>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct MessageSizeEntry {
+    pub index: usize,
+    pub email: String,
+    pub size_bytes: u64,
+    pub oversize: bool,
+}
+
+#[derive(Serialize, Debug)]
+pub struct MessageSizeReport {
+    pub limit_bytes: u64,
+    /// 全部附件编码后（含 MIME 头部）的估算大小。
+    pub attachment_bytes: u64,
+    pub largest_bytes: u64,
+    pub messages: Vec<MessageSizeEntry>,
+    /// 超限的收件人邮箱（小写）。
+    pub oversize: Vec<String>,
+    pub problems: Vec<String>,
+    pub ok: bool,
+}
+
+/// 估算每位收件人渲染后的邮件大小：正文按实际模板渲染，附件按 base64 编码后的大小累加。
+pub fn measure_message_sizes(job: &SendJob, limit_bytes: u64) -> Result<MessageSizeReport, String> {
+    let attachment_bytes: u64 = job.attachments.iter().map(|path| attachment_part_bytes(Path::new(path))).sum();
+    let mut messages = Vec::with_capacity(job.recipients.len());
+    for (index, recipient) in job.recipients.iter().enumerate() {
+        let size_bytes = message_size_without_attachments(job, recipient)? + attachment_bytes;
+        messages.push(MessageSizeEntry {
+            index,
+            email: recipient.email.trim().to_lowercase(),
+            size_bytes,
+            oversize: size_bytes > limit_bytes,
+        });
+    }
+    let oversize: Vec<String> = messages
+        .iter()
+        .filter(|entry| entry.oversize)
+        .map(|entry| entry.email.clone())
+        .collect();
+    let largest_bytes = messages.iter().map(|entry| entry.size_bytes).max().unwrap_or_default();
+    let mut problems = Vec::new();
+    if !oversize.is_empty() {
+        problems.push(format!(
+            "{} 封邮件超过大小上限 {}（最大 {}）",
+            oversize.len(),
+            format_bytes(limit_bytes),
+            format_bytes(largest_bytes)
+        ));
+    }
+    Ok(MessageSizeReport {
+        limit_bytes,
+        attachment_bytes,
+        largest_bytes,
+        messages,
+        ok: oversize.is_empty(),
+        oversize,
+        problems,
+    })
+}
+
+fn attachment_part_bytes(path: &Path) -> u64 {
+    let size = fs::metadata(path).map(|metadata| metadata.len()).unwrap_or_default();
+    let name_len = path.file_name().map(|name| name.len() as u64).unwrap_or_default();
+    // RFC 2231 编码的文件名最多膨胀约三倍，且会同时出现在 Content-Type 与 Content-Disposition 中。
+    base64_encoded_len(size) + ATTACHMENT_PART_OVERHEAD + name_len * 6
+}
+
+#[cfg(test)]
+mod tests {
+    use super::measure_message_sizes;
+    use crate::engine::SendJob;
+    use serde_json::json;
+
+    #[test]
+    fn flags_recipients_whose_rendered_message_exceeds_the_limit() {
+        let dir = std::env::temp_dir().join(format!("bes-message-size-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("temp dir");
+        let brochure = dir.join("brochure.pdf");
+        std::fs::write(&brochure, vec![b'a'; 30_000]).expect("write attachment");
+        let job = SendJob::from_payload(json!({
+            "sender": { "email": "sender@example.com", "name": "Sender" },
+            "smtp": { "host": "smtp.example.com", "port": 465, "username": "", "password": "", "use_ssl": true, "use_starttls": false, "timeout_sec": 10 },
+            "template": { "subject": "Hello", "body_text": "Dear {{ teacher_name }}" },
+            "recipients": [
+                { "email": "Short@example.com", "name": "A" },
+                { "email": "long@example.com", "name": "B".repeat(4_000) },
+            ],
+            "attachments": [brochure.to_string_lossy()],
+        }))
+        .expect("valid job");
+
+        let report = measure_message_sizes(&job, 45_000).expect("measure sizes");
+        let _ = std::fs::remove_dir_all(&dir);
+        assert!(report.attachment_bytes > 40_000);
+        assert!(report.messages[0].size_bytes > report.attachment_bytes);
+        assert!(!report.messages[0].oversize);
+        assert_eq!(report.oversize, vec!["long@example.com"]);
+        assert!(!report.ok);
+    }
+}
@@ -0,0 +1,96 @@
+use serde::{Deserialize, Serialize};
+
+/// 数据驻留设置：开启 strict-local 后禁止一切外发 HTTP，只允许连接配置的 SMTP 服务器。
+#[derive(Deserialize, Serialize, Clone, Default, PartialEq, Debug)]
+pub struct NetworkPolicy {
+    #[serde(default)]
+    pub strict_local: bool,
+    /// strict-local 下允许连接的 SMTP 主机；为空时允许任务中配置的主机。
+    #[serde(default)]
+    pub allowed_smtp_host: Option<String>,
+    /// 外发 HTTP 使用的代理（如 `http://proxy.corp:8080`）；为空时沿用系统 HTTP(S)_PROXY 环境变量。
+    #[serde(default)]
+    pub proxy: Option<String>,
+}
+
+impl NetworkPolicy {
+    /// 检查一次 HTTP 请求是否被允许；strict-local 下仅放行本机回环地址。
+    pub fn check_http(&self, url: &str, purpose: &str) -> Result<(), String> {
+        if self.strict_local && !is_loopback_url(url) {
+            return Err(format!("已开启仅本地模式，禁止访问外部网络（{purpose}）：{}", url.trim()));
+        }
+        Ok(())
+    }
+
+    pub fn check_smtp_host(&self, host: &str) -> Result<(), String> {
+        if !self.strict_local {
+            return Ok(());
+        }
+        match self.allowed_smtp_host.as_deref().map(str::trim) {
+            Some(allowed) if !allowed.is_empty() && !allowed.eq_ignore_ascii_case(host.trim()) => Err(format!(
+                "已开启仅本地模式，只允许连接 SMTP 服务器 {allowed}，当前为 {}",
+                host.trim()
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// 是否允许下载安装运行时组件（uv / Python 等）。
+    pub fn allows_downloads(&self) -> bool {
+        !self.strict_local
+    }
+}
+
+/// 所有外发 HTTP 请求的唯一入口：先按网络策略校验目标地址，再创建客户端。
+pub fn http_client(policy: &NetworkPolicy, url: &str, purpose: &str) -> Result<reqwest::blocking::Client, String> {
+    policy.check_http(url, purpose)?;
+    let mut builder = reqwest::blocking::Client::builder();
+    if let Some(proxy) = policy.proxy.as_deref().map(str::trim).filter(|proxy| !proxy.is_empty()) {
+        builder = builder.proxy(reqwest::Proxy::all(proxy).map_err(|err| format!("代理地址无效: {err}"))?);
+    }
+    builder
+        .build()
+        .map_err(|err| format!("创建 HTTP 客户端失败: {err}"))
+}
+
+fn is_loopback_url(url: &str) -> bool {
+    let trimmed = url.trim();
+    let Some(rest) = trimmed
+        .strip_prefix("http://")
+        .or_else(|| trimmed.strip_prefix("https://"))
+    else {
+        return false;
+    };
+    let authority = rest.split('/').next().unwrap_or_default();
+    let authority = authority.rsplit('@').next().unwrap_or(authority);
+    let host = match authority.strip_prefix('[') {
+        Some(ipv6) => ipv6.split(']').next().unwrap_or_default(),
+        None => authority.split(':').next().unwrap_or(authority),
+    };
+    let host = host.to_ascii_lowercase();
+    host == "localhost" || host == "::1" || host.starts_with("127.")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NetworkPolicy;
+
+    #[test]
+    fn strict_local_blocks_external_http_and_other_smtp_hosts() {
+        let policy = NetworkPolicy {
+            strict_local: true,
+            allowed_smtp_host: Some("smtp.corp.example".to_string()),
+            proxy: None,
+        };
+        assert!(policy.check_http("https://example.com/manifest.json", "manifest").is_err());
+        assert!(policy.check_http("http://127.0.0.1:8000/manifest.json", "manifest").is_ok());
+        assert!(policy.check_http("https://[::1]/manifest.json", "manifest").is_ok());
+        assert!(policy.check_smtp_host("SMTP.corp.example").is_ok());
+        assert!(policy.check_smtp_host("smtp.163.com").is_err());
+        assert!(!policy.allows_downloads());
+
+        let open = NetworkPolicy::default();
+        assert!(open.check_http("https://example.com", "manifest").is_ok());
+        assert!(open.check_smtp_host("smtp.163.com").is_ok());
+    }
+}
@@ -0,0 +1,269 @@
+use crate::attachments::{base64_encoded_len, format_bytes};
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+/// 邮箱服务商的发送规则；数值取各家公开说明中较保守的一档，未知项为空表示不限制。
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct ProviderRules {
+    pub id: String,
+    pub label: String,
+    /// 匹配 SMTP 主机名的后缀，如 `smtp.gmail.com`。
+    #[serde(default)]
+    pub smtp_hosts: Vec<String>,
+    /// 匹配发件人邮箱域名。
+    #[serde(default)]
+    pub sender_domains: Vec<String>,
+    /// 24 小时内的投递地址总数（收件、抄送、密送都计入）。
+    #[serde(default)]
+    pub max_recipients_per_day: Option<u64>,
+    /// 单封邮件的 RCPT TO 数量上限。
+    #[serde(default)]
+    pub max_recipients_per_message: Option<u64>,
+    /// 附件 base64 编码后的合计上限。
+    #[serde(default)]
+    pub max_attachment_bytes: Option<u64>,
+}
+
+/// 需要评估的任务信息，字段与 `start_send` 载荷一致，多余字段忽略。
+#[derive(Deserialize, Default)]
+pub struct PolicyCampaign {
+    #[serde(default)]
+    pub sender: PolicySender,
+    #[serde(default)]
+    pub smtp: PolicySmtp,
+    #[serde(default)]
+    pub recipients: Vec<PolicyRecipient>,
+    #[serde(default)]
+    pub cc: Vec<String>,
+    #[serde(default)]
+    pub bcc: Vec<String>,
+    #[serde(default)]
+    pub attachments: Vec<String>,
+}
+
+#[derive(Deserialize, Default)]
+pub struct PolicySender {
+    #[serde(default)]
+    pub email: String,
+}
+
+#[derive(Deserialize, Default)]
+pub struct PolicySmtp {
+    #[serde(default)]
+    pub host: String,
+}
+
+#[derive(Deserialize)]
+pub struct PolicyRecipient {
+    #[serde(default)]
+    pub email: String,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct PolicyViolation {
+    /// `recipients_per_day` / `recipients_per_message` / `attachment_size`。
+    pub rule: &'static str,
+    pub message: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct PolicyReport {
+    pub provider: Option<ProviderRules>,
+    pub messages: u64,
+    pub recipients_per_message: u64,
+    pub total_recipients: u64,
+    pub sent_last_day: u64,
+    pub attachment_encoded_bytes: u64,
+    /// 按每日上限需要的天数（含今天已用额度）；无每日上限时为空。
+    pub projected_days: Option<u64>,
+    pub violations: Vec<PolicyViolation>,
+    pub warnings: Vec<String>,
+    pub ok: bool,
+}
+
+/// 内置的常见服务商规则。
+pub fn builtin_rules() -> Vec<ProviderRules> {
+    let rules = |id: &str, label: &str, hosts: &[&str], domains: &[&str], day: u64, message: u64, mb: u64| ProviderRules {
+        id: id.to_string(),
+        label: label.to_string(),
+        smtp_hosts: hosts.iter().map(|host| host.to_string()).collect(),
+        sender_domains: domains.iter().map(|domain| domain.to_string()).collect(),
+        max_recipients_per_day: Some(day),
+        max_recipients_per_message: Some(message),
+        max_attachment_bytes: Some(mb * 1024 * 1024),
+    };
+    vec![
+        rules("gmail", "Gmail", &["smtp.gmail.com"], &["gmail.com", "googlemail.com"], 500, 100, 25),
+        rules(
+            "outlook",
+            "Outlook.com",
+            &["smtp-mail.outlook.com"],
+            &["outlook.com", "hotmail.com", "live.com"],
+            300,
+            100,
+            20,
+        ),
+        rules("microsoft365", "Microsoft 365", &["smtp.office365.com"], &[], 10_000, 500, 35),
+        rules("qq", "QQ 邮箱", &["smtp.qq.com", "smtp.exmail.qq.com"], &["qq.com", "foxmail.com"], 100, 50, 50),
+        rules("163", "163 邮箱", &["smtp.163.com"], &["163.com"], 200, 40, 50),
+        rules("126", "126 邮箱", &["smtp.126.com"], &["126.com"], 200, 40, 50),
+    ]
+}
+
+/// 先按 SMTP 主机、再按发件人域名识别服务商。
+pub fn detect_provider<'a>(rules: &'a [ProviderRules], smtp_host: &str, sender_email: &str) -> Option<&'a ProviderRules> {
+    let host = smtp_host.trim().trim_end_matches('.').to_lowercase();
+    let domain = sender_email
+        .trim()
+        .rsplit_once('@')
+        .map(|(_, domain)| domain.to_lowercase())
+        .unwrap_or_default();
+    let host_match = (!host.is_empty()).then(|| {
+        rules.iter().find(|rule| {
+            rule.smtp_hosts
+                .iter()
+                .any(|candidate| host == *candidate || host.ends_with(&format!(".{candidate}")))
+        })
+    });
+    host_match
+        .flatten()
+        .or_else(|| rules.iter().find(|rule| !domain.is_empty() && rule.sender_domains.contains(&domain)))
+}
+
+/// 按服务商规则模拟整个任务，找出会被服务商拒绝或限流的部分。
+pub fn simulate(rules: Option<&ProviderRules>, campaign: &PolicyCampaign, sent_last_day: u64) -> PolicyReport {
+    let messages = campaign
+        .recipients
+        .iter()
+        .filter(|recipient| !recipient.email.trim().is_empty())
+        .count() as u64;
+    let recipients_per_message = 1 + campaign.cc.len() as u64 + campaign.bcc.len() as u64;
+    let total_recipients = messages * recipients_per_message;
+    let attachment_bytes: u64 = campaign
+        .attachments
+        .iter()
+        .filter_map(|path| fs::metadata(path.trim()).ok())
+        .map(|metadata| metadata.len())
+        .sum();
+    let attachment_encoded_bytes = base64_encoded_len(attachment_bytes);
+
+    let mut violations = Vec::new();
+    let mut warnings = Vec::new();
+    let mut projected_days = None;
+    match rules {
+        None => warnings.push("未识别发件服务商，无法按服务商规则检查".to_string()),
+        Some(rules) => {
+            if let Some(limit) = rules.max_recipients_per_message {
+                if recipients_per_message > limit {
+                    violations.push(PolicyViolation {
+                        rule: "recipients_per_message",
+                        message: format!(
+                            "每封邮件有 {recipients_per_message} 个投递地址（含抄送 / 密送），超过 {} 单封 {limit} 个的上限",
+                            rules.label
+                        ),
+                    });
+                }
+            }
+            if let Some(limit) = rules.max_recipients_per_day {
+                let remaining = limit.saturating_sub(sent_last_day);
+                if total_recipients > remaining {
+                    violations.push(PolicyViolation {
+                        rule: "recipients_per_day",
+                        message: format!(
+                            "本次共 {total_recipients} 个投递地址，{} 每日上限 {limit} 个，过去 24 小时已发送 {sent_last_day} 封，剩余 {remaining} 个",
+                            rules.label
+                        ),
+                    });
+                }
+                if total_recipients > 0 && limit > 0 {
+                    projected_days = Some((sent_last_day.min(limit) + total_recipients).div_ceil(limit));
+                }
+            }
+            if let Some(limit) = rules.max_attachment_bytes {
+                if attachment_encoded_bytes > limit {
+                    violations.push(PolicyViolation {
+                        rule: "attachment_size",
+                        message: format!(
+                            "附件编码后 {} 超过 {} 的上限 {}",
+                            format_bytes(attachment_encoded_bytes),
+                            rules.label,
+                            format_bytes(limit)
+                        ),
+                    });
+                }
+            }
+        }
+    }
+    let missing = campaign
+        .attachments
+        .iter()
+        .filter(|path| fs::metadata(path.trim()).is_err())
+        .count();
+    if missing > 0 {
+        warnings.push(format!("{missing} 个附件不存在，未计入大小"));
+    }
+
+    PolicyReport {
+        provider: rules.cloned(),
+        messages,
+        recipients_per_message,
+        total_recipients,
+        sent_last_day,
+        attachment_encoded_bytes,
+        projected_days,
+        ok: violations.is_empty(),
+        violations,
+        warnings,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{builtin_rules, detect_provider, simulate, PolicyCampaign, PolicyRecipient};
+
+    #[test]
+    fn flags_campaigns_that_exceed_provider_limits() {
+        let rules = builtin_rules();
+        assert_eq!(
+            detect_provider(&rules, "SMTP.Gmail.com", "someone@example.edu").map(|rule| rule.id.as_str()),
+            Some("gmail")
+        );
+        assert_eq!(
+            detect_provider(&rules, "mail.example.edu", "someone@foxmail.com").map(|rule| rule.id.as_str()),
+            Some("qq")
+        );
+        assert!(detect_provider(&rules, "mail.example.edu", "someone@example.edu").is_none());
+
+        let gmail = detect_provider(&rules, "smtp.gmail.com", "").expect("gmail rules");
+        let campaign = PolicyCampaign {
+            recipients: (0..300)
+                .map(|index| PolicyRecipient {
+                    email: format!("teacher{index}@example.edu"),
+                })
+                .collect(),
+            cc: vec!["office@example.edu".to_string()],
+            ..PolicyCampaign::default()
+        };
+        let report = simulate(Some(gmail), &campaign, 50);
+        assert_eq!(report.total_recipients, 600);
+        assert_eq!(report.projected_days, Some(2));
+        assert!(!report.ok);
+        assert_eq!(report.violations.len(), 1);
+        assert_eq!(report.violations[0].rule, "recipients_per_day");
+
+        let small = PolicyCampaign {
+            recipients: campaign.recipients[..10]
+                .iter()
+                .map(|recipient| PolicyRecipient {
+                    email: recipient.email.clone(),
+                })
+                .collect(),
+            bcc: (0..120).map(|index| format!("bcc{index}@example.edu")).collect(),
+            ..PolicyCampaign::default()
+        };
+        let report = simulate(Some(gmail), &small, 0);
+        assert_eq!(report.violations.len(), 2);
+        assert_eq!(report.violations[0].rule, "recipients_per_message");
+        assert!(simulate(None, &small, 0).ok);
+    }
+}
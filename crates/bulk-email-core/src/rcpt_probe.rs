@@ -0,0 +1,465 @@
+use crate::engine::{validate_email, RecipientEntry};
+use crate::greylist::recipient_domain;
+use crate::smtp_client::parse_ehlo_hostname;
+use lettre::address::Address;
+use lettre::transport::smtp::client::{SmtpConnection, TlsParameters};
+use lettre::transport::smtp::commands::{Mail, Rcpt};
+use lettre::transport::smtp::extension::ClientId;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// 单次探测的收件人上限，防止把探测当作批量验证服务使用。
+pub const MAX_PROBE_RECIPIENTS: usize = 200;
+/// 同一域名两次连接之间的最小间隔。
+const MIN_DOMAIN_INTERVAL_SEC: u64 = 5;
+const MAX_RCPT_PER_SESSION: usize = 10;
+pub const DEFAULT_DOH_URL: &str = "https://cloudflare-dns.com/dns-query";
+const DNS_TYPE_MX: u64 = 15;
+const DNS_NXDOMAIN: u64 = 3;
+
+/// 探测结果的局限，随每份报告一并返回，界面应原样展示。
+pub const PROBE_WARNINGS: [&str; 3] = [
+    "许多服务器（Gmail、Outlook、企业邮件网关等）对任意地址都返回接受，“存在”不代表邮箱一定存在；被判定为全部接受的域名无法验证。",
+    "频繁探测可能被对方视为地址收集，导致当前网络的 IP 被列入黑名单；家庭宽带和多数云主机会封锁 25 端口。",
+    "探测不会发送邮件（不执行 DATA），但对方服务器仍会记录连接与 MAIL FROM 地址。",
+];
+
+/// RCPT 探测参数。探测直接连接收件域名的 MX（默认 25 端口），不经过配置的发件 SMTP 服务器。
+#[derive(Deserialize, Clone, Debug)]
+pub struct RcptProbeOptions {
+    /// `MAIL FROM` 使用的地址，应为真实可收信的发件地址，否则多数服务器会直接拒绝。
+    pub mail_from: String,
+    #[serde(default)]
+    pub helo_name: Option<String>,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    #[serde(default = "default_timeout_sec")]
+    pub timeout_sec: u64,
+    /// 同一域名两次连接之间至少等待的秒数。
+    #[serde(default = "default_domain_interval_sec")]
+    pub domain_interval_sec: u64,
+    /// 每次连接最多检查的地址数，超出部分在间隔后重新连接。
+    #[serde(default = "default_max_per_session")]
+    pub max_per_session: usize,
+    /// DNS over HTTPS 查询地址（JSON 格式），用于查找 MX 记录。
+    #[serde(default)]
+    pub doh_url: Option<String>,
+}
+
+fn default_port() -> u16 {
+    25
+}
+
+fn default_timeout_sec() -> u64 {
+    20
+}
+
+fn default_domain_interval_sec() -> u64 {
+    30
+}
+
+fn default_max_per_session() -> usize {
+    5
+}
+
+impl RcptProbeOptions {
+    pub fn validate(&self) -> Result<(), String> {
+        validate_email(&self.mail_from, "探测发件地址")?;
+        if let Some(name) = self.helo_name.as_deref().map(str::trim).filter(|name| !name.is_empty()) {
+            parse_ehlo_hostname(name)?;
+        }
+        if self.domain_interval_sec < MIN_DOMAIN_INTERVAL_SEC {
+            return Err(format!("同一域名的探测间隔不能少于 {MIN_DOMAIN_INTERVAL_SEC} 秒"));
+        }
+        if !(1..=MAX_RCPT_PER_SESSION).contains(&self.max_per_session) {
+            return Err(format!("每次连接检查的地址数应在 1 到 {MAX_RCPT_PER_SESSION} 之间"));
+        }
+        if !(5..=120).contains(&self.timeout_sec) {
+            return Err("探测超时应在 5 到 120 秒之间".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn doh_url(&self) -> String {
+        self.doh_url
+            .as_deref()
+            .map(str::trim)
+            .filter(|url| !url.is_empty())
+            .unwrap_or(DEFAULT_DOH_URL)
+            .to_string()
+    }
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProbeStatus {
+    /// 服务器接受了 RCPT TO（且域名不是全部接受）。
+    Exists,
+    /// 服务器永久拒绝，或域名不存在 / 不接收邮件。
+    Rejected,
+    /// 域名对随机地址也返回接受，无法判断。
+    AcceptAll,
+    /// 临时错误、连接失败或服务器不验证地址。
+    Unknown,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ProbeResult {
+    pub email: String,
+    pub name: String,
+    pub status: ProbeStatus,
+    pub mx: Option<String>,
+    pub code: Option<u16>,
+    pub detail: String,
+}
+
+#[derive(Serialize, Default)]
+pub struct RcptProbeReport {
+    pub results: Vec<ProbeResult>,
+    pub exists: usize,
+    pub rejected: usize,
+    pub accept_all: usize,
+    pub unknown: usize,
+    pub warnings: Vec<String>,
+}
+
+impl RcptProbeReport {
+    fn push(&mut self, result: ProbeResult) {
+        match result.status {
+            ProbeStatus::Exists => self.exists += 1,
+            ProbeStatus::Rejected => self.rejected += 1,
+            ProbeStatus::AcceptAll => self.accept_all += 1,
+            ProbeStatus::Unknown => self.unknown += 1,
+        }
+        self.results.push(result);
+    }
+}
+
+/// 逐个域名探测收件人：每个域名先用随机地址检测是否全部接受，再按间隔分批发送 RCPT TO。
+/// 每得到一条结果就回调一次，便于界面显示进度。
+pub fn probe_recipients(
+    client: &reqwest::blocking::Client,
+    options: &RcptProbeOptions,
+    recipients: Vec<RecipientEntry>,
+    mut on_result: impl FnMut(&ProbeResult),
+) -> Result<RcptProbeReport, String> {
+    options.validate()?;
+    if recipients.is_empty() {
+        return Err("请至少选择一个收件人".to_string());
+    }
+    if recipients.len() > MAX_PROBE_RECIPIENTS {
+        return Err(format!("单次最多探测 {MAX_PROBE_RECIPIENTS} 个地址，当前为 {}", recipients.len()));
+    }
+    let mail_from: Address = options
+        .mail_from
+        .trim()
+        .parse()
+        .map_err(|err| format!("探测发件地址无效: {err}"))?;
+    let hello = match options.helo_name.as_deref().map(str::trim).filter(|name| !name.is_empty()) {
+        Some(name) => parse_ehlo_hostname(name)?,
+        None => ClientId::default(),
+    };
+    let mut report = RcptProbeReport {
+        warnings: PROBE_WARNINGS.iter().map(|warning| warning.to_string()).collect(),
+        ..RcptProbeReport::default()
+    };
+    let mut record = |result: ProbeResult| {
+        on_result(&result);
+        report.push(result);
+    };
+    let mut pacer = DomainPacer::new(Duration::from_secs(options.domain_interval_sec));
+    let doh_url = options.doh_url();
+    let (recipients, malformed): (Vec<_>, Vec<_>) = recipients
+        .into_iter()
+        .partition(|recipient| validate_email(&recipient.email, "收件人邮箱").is_ok());
+    for recipient in malformed {
+        record(outcome(recipient, ProbeStatus::Rejected, None, None, "地址格式无效"));
+    }
+    for (domain, group) in group_by_domain(recipients) {
+        let hosts = match lookup_mx(client, &doh_url, &domain) {
+            Ok(Some(hosts)) => hosts,
+            Ok(None) => {
+                for recipient in group {
+                    record(outcome(recipient, ProbeStatus::Rejected, None, None, "域名不存在或声明不接收邮件"));
+                }
+                continue;
+            }
+            Err(err) => {
+                for recipient in group {
+                    record(outcome(recipient, ProbeStatus::Unknown, None, None, &err));
+                }
+                continue;
+            }
+        };
+        let mut accept_all = None;
+        for chunk in group.chunks(options.max_per_session) {
+            let connects = accept_all != Some(true);
+            if connects {
+                pacer.wait(&domain);
+            }
+            let session = ProbeSession {
+                hosts: &hosts,
+                port: options.port,
+                timeout: Duration::from_secs(options.timeout_sec),
+                hello: &hello,
+                mail_from: &mail_from,
+            };
+            for result in session.run(&domain, chunk, &mut accept_all) {
+                record(result);
+            }
+            if connects {
+                pacer.mark(&domain, Instant::now());
+            }
+        }
+    }
+    Ok(report)
+}
+
+struct ProbeSession<'a> {
+    hosts: &'a [String],
+    port: u16,
+    timeout: Duration,
+    hello: &'a ClientId,
+    mail_from: &'a Address,
+}
+
+impl ProbeSession<'_> {
+    /// 一次 SMTP 会话：MAIL FROM 后逐个 RCPT TO，不发送 DATA。
+    /// `accept_all` 在域名的第一次会话中用随机地址检测，之后的会话沿用结论。
+    fn run(&self, domain: &str, chunk: &[RecipientEntry], accept_all: &mut Option<bool>) -> Vec<ProbeResult> {
+        let unknown = |detail: &str, mx: Option<&str>| {
+            chunk
+                .iter()
+                .map(|recipient| outcome(recipient.clone(), ProbeStatus::Unknown, mx, None, detail))
+                .collect()
+        };
+        if *accept_all == Some(true) {
+            return chunk
+                .iter()
+                .map(|recipient| {
+                    outcome(recipient.clone(), ProbeStatus::AcceptAll, None, None, "该域名对任意地址都返回接受")
+                })
+                .collect();
+        }
+        let (mut connection, mx) = match self.connect() {
+            Ok(connected) => connected,
+            Err(err) => return unknown(&err, None),
+        };
+        if let Err(err) = connection.command(Mail::new(Some(self.mail_from.clone()), Vec::new())) {
+            connection.abort();
+            return unknown(&format!("MAIL FROM 被拒绝: {err}"), Some(&mx));
+        }
+        if accept_all.is_none() {
+            let canary = format!("bes-probe-{:08x}@{domain}", rand::random::<u32>());
+            let accepted = canary
+                .parse::<Address>()
+                .ok()
+                .map(|address| connection.command(Rcpt::new(address, Vec::new())).is_ok());
+            *accept_all = accepted;
+            if accepted == Some(true) {
+                let _ = connection.quit();
+                return self.run(domain, chunk, accept_all);
+            }
+        }
+        let mut results = Vec::with_capacity(chunk.len());
+        for recipient in chunk {
+            let Ok(address) = recipient.email.trim().parse::<Address>() else {
+                results.push(outcome(recipient.clone(), ProbeStatus::Rejected, Some(&mx), None, "地址格式无效"));
+                continue;
+            };
+            let result = match connection.command(Rcpt::new(address, Vec::new())) {
+                Ok(response) => {
+                    let code = u16::from(response.code());
+                    let detail = response.first_line().unwrap_or_default().to_string();
+                    // 252：服务器不验证地址，但会尝试投递。
+                    let status = if code == 252 { ProbeStatus::Unknown } else { ProbeStatus::Exists };
+                    outcome(recipient.clone(), status, Some(&mx), Some(code), &detail)
+                }
+                Err(err) => {
+                    let code = err.status().map(u16::from);
+                    let status = if err.is_permanent() { ProbeStatus::Rejected } else { ProbeStatus::Unknown };
+                    let broken = code.is_none();
+                    results.push(outcome(recipient.clone(), status, Some(&mx), code, &err.to_string()));
+                    if broken {
+                        // 连接已断开，剩余地址无法继续检查。
+                        let remaining = &chunk[results.len()..];
+                        results.extend(remaining.iter().map(|recipient| {
+                            outcome(recipient.clone(), ProbeStatus::Unknown, Some(&mx), None, "连接已断开")
+                        }));
+                        connection.abort();
+                        return results;
+                    }
+                    continue;
+                }
+            };
+            results.push(result);
+        }
+        let _ = connection.quit();
+        results
+    }
+
+    /// 按优先级依次尝试 MX 主机；服务器支持时升级到 STARTTLS。
+    fn connect(&self) -> Result<(SmtpConnection, String), String> {
+        let mut last_error = "没有可用的 MX 主机".to_string();
+        for host in self.hosts {
+            let connected = SmtpConnection::connect((host.as_str(), self.port), Some(self.timeout), self.hello, None, None)
+                .and_then(|mut connection| {
+                    if connection.can_starttls() {
+                        let tls = TlsParameters::new(host.clone())?;
+                        connection.starttls(&tls, self.hello)?;
+                    }
+                    Ok(connection)
+                });
+            match connected {
+                Ok(connection) => return Ok((connection, host.clone())),
+                Err(err) => last_error = format!("连接 {host}:{} 失败: {err}", self.port),
+            }
+        }
+        Err(last_error)
+    }
+}
+
+fn outcome(recipient: RecipientEntry, status: ProbeStatus, mx: Option<&str>, code: Option<u16>, detail: &str) -> ProbeResult {
+    ProbeResult {
+        email: recipient.email,
+        name: recipient.name,
+        status,
+        mx: mx.map(str::to_string),
+        code,
+        detail: detail.to_string(),
+    }
+}
+
+/// 按域名分组，保持域名首次出现的顺序。
+fn group_by_domain(recipients: Vec<RecipientEntry>) -> Vec<(String, Vec<RecipientEntry>)> {
+    let mut groups: Vec<(String, Vec<RecipientEntry>)> = Vec::new();
+    for recipient in recipients {
+        let domain = recipient_domain(&recipient.email);
+        match groups.iter_mut().find(|(existing, _)| *existing == domain) {
+            Some((_, group)) => group.push(recipient),
+            None => groups.push((domain, vec![recipient])),
+        }
+    }
+    groups
+}
+
+/// 同一域名两次连接之间的最小间隔。
+struct DomainPacer {
+    interval: Duration,
+    last: HashMap<String, Instant>,
+}
+
+impl DomainPacer {
+    fn new(interval: Duration) -> Self {
+        DomainPacer {
+            interval,
+            last: HashMap::new(),
+        }
+    }
+
+    fn wait_time(&self, domain: &str, now: Instant) -> Duration {
+        self.last
+            .get(domain)
+            .map_or(Duration::ZERO, |last| (*last + self.interval).saturating_duration_since(now))
+    }
+
+    fn wait(&self, domain: &str) {
+        let delay = self.wait_time(domain, Instant::now());
+        if !delay.is_zero() {
+            std::thread::sleep(delay);
+        }
+    }
+
+    fn mark(&mut self, domain: &str, now: Instant) {
+        self.last.insert(domain.to_string(), now);
+    }
+}
+
+/// 通过 DNS over HTTPS 查询 MX，按优先级返回主机名。
+/// 域名不存在或声明 null MX（RFC 7505）时返回 `None`；没有 MX 记录时按 RFC 5321 以域名本身作为主机。
+pub fn lookup_mx(client: &reqwest::blocking::Client, doh_url: &str, domain: &str) -> Result<Option<Vec<String>>, String> {
+    let answer: Value = client
+        .get(doh_url)
+        .query(&[("name", domain), ("type", "MX")])
+        .header("accept", "application/dns-json")
+        .send()
+        .and_then(|response| response.error_for_status())
+        .and_then(|response| response.json())
+        .map_err(|err| format!("查询 {domain} 的 MX 记录失败: {err}"))?;
+    parse_mx_answer(domain, &answer)
+}
+
+fn parse_mx_answer(domain: &str, answer: &Value) -> Result<Option<Vec<String>>, String> {
+    match answer["Status"].as_u64() {
+        Some(0) => {}
+        Some(DNS_NXDOMAIN) => return Ok(None),
+        status => return Err(format!("查询 {domain} 的 MX 记录失败（DNS 状态 {status:?}）")),
+    }
+    let mut records: Vec<(u64, String)> = answer["Answer"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|record| record["type"].as_u64() == Some(DNS_TYPE_MX))
+        .filter_map(|record| {
+            let (preference, host) = record["data"].as_str()?.trim().split_once(' ')?;
+            Some((preference.parse().ok()?, host.trim().trim_end_matches('.').to_lowercase()))
+        })
+        .collect();
+    if records.is_empty() {
+        return Ok(Some(vec![domain.to_string()]));
+    }
+    if records.iter().all(|(_, host)| host.is_empty()) {
+        return Ok(None);
+    }
+    records.sort();
+    Ok(Some(records.into_iter().map(|(_, host)| host).filter(|host| !host.is_empty()).collect()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{group_by_domain, parse_mx_answer, DomainPacer, RcptProbeOptions};
+    use crate::engine::RecipientEntry;
+    use serde_json::json;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn parses_mx_answers_and_paces_domains() {
+        let answer = json!({ "Status": 0, "Answer": [
+            { "name": "example.edu.", "type": 15, "data": "20 backup.example.edu." },
+            { "name": "example.edu.", "type": 15, "data": "10 MX1.example.edu." },
+            { "name": "example.edu.", "type": 5, "data": "alias.example.edu." },
+        ] });
+        assert_eq!(
+            parse_mx_answer("example.edu", &answer).expect("mx"),
+            Some(vec!["mx1.example.edu".to_string(), "backup.example.edu".to_string()])
+        );
+        let no_mx = json!({ "Status": 0 });
+        assert_eq!(parse_mx_answer("a.example", &no_mx).expect("implicit"), Some(vec!["a.example".to_string()]));
+        let null_mx = json!({ "Status": 0, "Answer": [{ "type": 15, "data": "0 ." }] });
+        assert_eq!(parse_mx_answer("b.example", &null_mx).expect("null mx"), None);
+        assert_eq!(parse_mx_answer("c.example", &json!({ "Status": 3 })).expect("nxdomain"), None);
+        assert!(parse_mx_answer("d.example", &json!({ "Status": 2 })).is_err());
+
+        let entry = |email: &str| RecipientEntry {
+            email: email.to_string(),
+            name: String::new(),
+            ..RecipientEntry::default()
+        };
+        let groups = group_by_domain(vec![entry("a@x.edu"), entry("b@y.edu"), entry("c@X.edu")]);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].1.len(), 2);
+
+        let mut pacer = DomainPacer::new(Duration::from_secs(30));
+        let now = Instant::now();
+        assert_eq!(pacer.wait_time("x.edu", now), Duration::ZERO);
+        pacer.mark("x.edu", now);
+        assert_eq!(pacer.wait_time("x.edu", now + Duration::from_secs(10)), Duration::from_secs(20));
+        assert_eq!(pacer.wait_time("y.edu", now), Duration::ZERO);
+
+        let options: RcptProbeOptions =
+            serde_json::from_value(json!({ "mail_from": "office@example.edu", "domain_interval_sec": 1 })).expect("options");
+        assert!(options.validate().is_err());
+    }
+}
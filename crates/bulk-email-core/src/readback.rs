@@ -0,0 +1,229 @@
+use crate::imap_seed::{
+    decode_folder_name, encode_folder_name, quote, search_uids, ImapSession, ImapSettings, Mailbox, Untagged,
+};
+use serde::{Deserialize, Serialize};
+
+/// 发送后的 IMAP 回读验证：在发件账号的「已发送」（或 Gmail 的「所有邮件」）中按 Message-ID 查找邮件，
+/// 确认服务商已保存并记录其分配的 ID。仅适用于会自动保存已发送邮件的服务商。
+#[derive(Deserialize, Clone)]
+pub struct ReadbackOptions {
+    pub imap: ImapSettings,
+    /// 要搜索的文件夹；为空时按 RFC 6154 的 `\All` / `\Sent` 标记自动识别。
+    #[serde(default)]
+    pub folders: Vec<String>,
+    /// 发送结束后等待多久再回读，给服务商留出保存时间。
+    #[serde(default = "default_delay_sec")]
+    pub delay_sec: u64,
+}
+
+fn default_delay_sec() -> u64 {
+    30
+}
+
+/// 服务商分配的邮件 ID 类型。
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderIdKind {
+    /// Gmail 的 `X-GM-MSGID`。
+    GmailMsgid,
+    /// RFC 8474 的 `EMAILID`。
+    EmailId,
+    /// `UIDVALIDITY/UID`，仅在该文件夹内唯一。
+    Uid,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct ReadbackResult {
+    pub message_id: String,
+    pub found: bool,
+    pub folder: Option<String>,
+    pub provider_id: Option<String>,
+    pub provider_id_kind: Option<ProviderIdKind>,
+}
+
+/// 登录 IMAP 并逐个查找 Message-ID，返回的结果与 `message_ids` 一一对应。
+pub fn verify_messages(options: &ReadbackOptions, message_ids: &[String]) -> Result<Vec<ReadbackResult>, String> {
+    let mut session = ImapSession::open(&options.imap)?;
+    let result = verify_in_session(&mut session, options, message_ids);
+    let _ = session.command("LOGOUT");
+    result
+}
+
+fn verify_in_session(
+    session: &mut ImapSession,
+    options: &ReadbackOptions,
+    message_ids: &[String],
+) -> Result<Vec<ReadbackResult>, String> {
+    let capabilities = capabilities(&session.command("CAPABILITY")?);
+    let id_kind = if capabilities.iter().any(|name| name == "X-GM-EXT-1") {
+        ProviderIdKind::GmailMsgid
+    } else if capabilities.iter().any(|name| name == "OBJECTID") {
+        ProviderIdKind::EmailId
+    } else {
+        ProviderIdKind::Uid
+    };
+    let folders: Vec<String> = if options.folders.iter().any(|folder| !folder.trim().is_empty()) {
+        options
+            .folders
+            .iter()
+            .map(|folder| folder.trim())
+            .filter(|folder| !folder.is_empty())
+            .map(encode_folder_name)
+            .collect()
+    } else {
+        detect_folders(&session.list_mailboxes()?)
+    };
+
+    let mut results: Vec<ReadbackResult> = message_ids
+        .iter()
+        .map(|message_id| ReadbackResult {
+            message_id: message_id.clone(),
+            found: false,
+            folder: None,
+            provider_id: None,
+            provider_id_kind: None,
+        })
+        .collect();
+    for folder in &folders {
+        if results.iter().all(|result| result.found) {
+            break;
+        }
+        // 文件夹不存在时跳过，继续查找下一个。
+        let Ok(examined) = session.command(&format!("EXAMINE {}", quote(folder))) else {
+            continue;
+        };
+        let uid_validity = examined.iter().find_map(|response| bracket_value(&response.line, "UIDVALIDITY"));
+        for result in results.iter_mut().filter(|result| !result.found) {
+            let search = format!("UID SEARCH HEADER Message-ID {}", quote(&result.message_id));
+            let Some(uid) = search_uids(&session.command(&search)?).into_iter().max() else {
+                continue;
+            };
+            let provider_id = match id_kind {
+                ProviderIdKind::GmailMsgid => fetch_attribute(session, uid, "X-GM-MSGID")?,
+                ProviderIdKind::EmailId => fetch_attribute(session, uid, "EMAILID")?
+                    .map(|value| value.trim_matches(['(', ')']).to_string()),
+                ProviderIdKind::Uid => Some(format!("{}/{uid}", uid_validity.as_deref().unwrap_or("0"))),
+            };
+            result.found = true;
+            result.folder = Some(decode_folder_name(folder));
+            result.provider_id_kind = provider_id.as_ref().map(|_| id_kind);
+            result.provider_id = provider_id;
+        }
+    }
+    Ok(results)
+}
+
+fn capabilities(responses: &[Untagged]) -> Vec<String> {
+    responses
+        .iter()
+        .filter_map(|response| response.line.strip_prefix("* CAPABILITY"))
+        .flat_map(|rest| rest.split_whitespace().map(str::to_ascii_uppercase).collect::<Vec<_>>())
+        .collect()
+}
+
+/// 从 `LIST` 结果中选出 `\All`（优先）或 `\Sent` 文件夹；都没有时退回常见的 `Sent`。
+fn detect_folders(mailboxes: &[Mailbox]) -> Vec<String> {
+    let mut all = Vec::new();
+    let mut sent = Vec::new();
+    for mailbox in mailboxes {
+        if mailbox.flags.iter().any(|flag| flag == "\\all") {
+            all.push(mailbox.name.clone());
+        } else if mailbox.flags.iter().any(|flag| flag == "\\sent") {
+            sent.push(mailbox.name.clone());
+        }
+    }
+    if !all.is_empty() {
+        all
+    } else if !sent.is_empty() {
+        sent
+    } else {
+        vec!["Sent".to_string()]
+    }
+}
+
+/// `[KEY value]` 形式的响应码。
+fn bracket_value(line: &str, key: &str) -> Option<String> {
+    let start = line.find(&format!("[{key} "))? + key.len() + 2;
+    let end = line[start..].find(']')? + start;
+    Some(line[start..end].trim().to_string())
+}
+
+fn fetch_attribute(session: &mut ImapSession, uid: u64, attribute: &str) -> Result<Option<String>, String> {
+    let responses = session.command(&format!("UID FETCH {uid} ({attribute})"))?;
+    Ok(responses
+        .iter()
+        .filter(|response| response.line.contains(" FETCH "))
+        .find_map(|response| {
+            let mut tokens = response.line.split(|ch: char| ch.is_whitespace() || ch == '(' || ch == ')');
+            tokens.find(|token| token.eq_ignore_ascii_case(attribute))?;
+            tokens.find(|token| !token.is_empty()).map(str::to_string)
+        }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{verify_messages, ProviderIdKind, ReadbackOptions};
+    use crate::imap_seed::ImapSettings;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+
+    #[test]
+    fn finds_sent_messages_in_detected_folder() {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind");
+        let port = listener.local_addr().expect("addr").port();
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().expect("accept");
+            let mut writer = stream.try_clone().expect("clone");
+            let mut reader = BufReader::new(stream);
+            writer.write_all(b"* OK IMAP4rev1 ready\r\n").expect("greeting");
+            let mut commands = Vec::new();
+            let mut line = String::new();
+            while reader.read_line(&mut line).expect("read") > 0 {
+                let (tag, command) = line.trim_end().split_once(' ').expect("tagged");
+                commands.push(command.to_string());
+                let reply = match command {
+                    "CAPABILITY" => "* CAPABILITY IMAP4rev1 OBJECTID\r\n".to_string(),
+                    "LIST \"\" \"*\"" => concat!(
+                        "* LIST (\\HasNoChildren) \"/\" INBOX\r\n",
+                        "* LIST (\\HasNoChildren \\Sent) \"/\" \"&XfJT0ZAB-\"\r\n",
+                    )
+                    .to_string(),
+                    "EXAMINE \"&XfJT0ZAB-\"" => "* OK [UIDVALIDITY 42] UIDs valid\r\n".to_string(),
+                    "UID SEARCH HEADER Message-ID \"<a@example.edu>\"" => "* SEARCH 5 9\r\n".to_string(),
+                    "UID SEARCH HEADER Message-ID \"<b@example.edu>\"" => "* SEARCH\r\n".to_string(),
+                    "UID FETCH 9 (EMAILID)" => "* 3 FETCH (EMAILID (M6d99ac3275bb4e) UID 9)\r\n".to_string(),
+                    _ => String::new(),
+                };
+                writer.write_all(format!("{reply}{tag} OK done\r\n").as_bytes()).expect("reply");
+                if command == "LOGOUT" {
+                    break;
+                }
+                line.clear();
+            }
+            commands
+        });
+        let options = ReadbackOptions {
+            imap: ImapSettings {
+                host: "127.0.0.1".to_string(),
+                port,
+                username: "sender@example.edu".to_string(),
+                password: "secret".to_string(),
+                use_ssl: false,
+                timeout_sec: 5,
+            },
+            folders: Vec::new(),
+            delay_sec: 0,
+        };
+        let ids = ["<a@example.edu>".to_string(), "<b@example.edu>".to_string()];
+        let results = verify_messages(&options, &ids).expect("readback");
+        let commands = server.join().expect("server");
+
+        assert_eq!(commands[3], "EXAMINE \"&XfJT0ZAB-\"");
+        assert!(results[0].found);
+        assert_eq!(results[0].folder.as_deref(), Some("已发送"));
+        assert_eq!(results[0].provider_id.as_deref(), Some("M6d99ac3275bb4e"));
+        assert_eq!(results[0].provider_id_kind, Some(ProviderIdKind::EmailId));
+        assert!(!results[1].found);
+        assert_eq!(results[1].provider_id, None);
+    }
+}
@@ -0,0 +1,185 @@
+use crate::engine::RecipientEntry;
+use crate::recipients_loader::{RecipientStats, RecipientTable};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+/// 对某一列的判断条件。
+#[derive(Deserialize, Clone, Debug)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Condition {
+    Equals {
+        value: String,
+        #[serde(default)]
+        case_sensitive: bool,
+    },
+    Contains {
+        value: String,
+        #[serde(default)]
+        case_sensitive: bool,
+    },
+    Regex {
+        pattern: String,
+    },
+    /// 数值区间（含端点）；单元格不是数字时不匹配。
+    Range {
+        #[serde(default)]
+        min: Option<f64>,
+        #[serde(default)]
+        max: Option<f64>,
+    },
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct ColumnFilter {
+    pub column: String,
+    #[serde(flatten)]
+    pub condition: Condition,
+    /// 取反，例如「region 不等于 EU」。
+    #[serde(default)]
+    pub negate: bool,
+}
+
+#[derive(Serialize)]
+pub struct RecipientFilterReport {
+    pub columns: Vec<String>,
+    pub total_rows: usize,
+    /// 满足条件的行数（校验与去重之前）。
+    pub matched_rows: usize,
+    pub stats: RecipientStats,
+    pub recipients: Vec<RecipientEntry>,
+}
+
+/// 编译后的条件：列号已解析，正则只编译一次。
+struct Compiled<'a> {
+    column: usize,
+    filter: &'a ColumnFilter,
+    regex: Option<Regex>,
+}
+
+impl Compiled<'_> {
+    fn matches(&self, row: &[String]) -> bool {
+        let cell = row.get(self.column).map(|value| value.trim()).unwrap_or_default();
+        let matched = match &self.filter.condition {
+            Condition::Equals { value, case_sensitive } => {
+                if *case_sensitive {
+                    cell == value.trim()
+                } else {
+                    cell.to_lowercase() == value.trim().to_lowercase()
+                }
+            }
+            Condition::Contains { value, case_sensitive } => {
+                if *case_sensitive {
+                    cell.contains(value.as_str())
+                } else {
+                    cell.to_lowercase().contains(&value.to_lowercase())
+                }
+            }
+            Condition::Regex { .. } => self.regex.as_ref().is_some_and(|regex| regex.is_match(cell)),
+            Condition::Range { min, max } => parse_number(cell).is_some_and(|number| {
+                min.is_none_or(|min| number >= min) && max.is_none_or(|max| number <= max)
+            }),
+        };
+        matched != self.filter.negate
+    }
+}
+
+/// 按条件筛选表格行（`match_any` 为 false 时需满足全部条件），再转换为收件人。
+pub fn filter_table(
+    table: &RecipientTable,
+    filters: &[ColumnFilter],
+    match_any: bool,
+) -> Result<RecipientFilterReport, String> {
+    let compiled = filters
+        .iter()
+        .map(|filter| {
+            let column = table.column(&filter.column).ok_or_else(|| {
+                format!("找不到列「{}」，可用的列：{}", filter.column.trim(), table.headers.join("、"))
+            })?;
+            let regex = match &filter.condition {
+                Condition::Regex { pattern } => Some(
+                    RegexBuilder::new(pattern)
+                        .size_limit(1 << 20)
+                        .build()
+                        .map_err(|err| format!("列「{}」的正则表达式无效: {err}", filter.column.trim()))?,
+                ),
+                _ => None,
+            };
+            Ok(Compiled { column, filter, regex })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let matched: Vec<&Vec<String>> = table
+        .rows
+        .iter()
+        .filter(|row| {
+            compiled.is_empty()
+                || if match_any {
+                    compiled.iter().any(|filter| filter.matches(row))
+                } else {
+                    compiled.iter().all(|filter| filter.matches(row))
+                }
+        })
+        .collect();
+    let matched_rows = matched.len();
+    let loaded = table.to_recipients(matched);
+    Ok(RecipientFilterReport {
+        columns: table.headers.clone(),
+        total_rows: table.rows.len(),
+        matched_rows,
+        stats: loaded.stats,
+        recipients: loaded.recipients,
+    })
+}
+
+/// 数字列允许千分位逗号与空格（如 `1,200`）。
+fn parse_number(cell: &str) -> Option<f64> {
+    let cleaned: String = cell.chars().filter(|ch| *ch != ',' && !ch.is_whitespace()).collect();
+    cleaned.parse::<f64>().ok().filter(|number| number.is_finite())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{filter_table, ColumnFilter};
+    use crate::recipients_loader::RecipientTable;
+
+    fn table() -> RecipientTable {
+        let row = |values: &[&str]| values.iter().map(|value| value.to_string()).collect::<Vec<_>>();
+        RecipientTable {
+            headers: row(&["Email", "Name", "Region", "Score"]),
+            rows: vec![
+                row(&["a@example.com", "A", "EU", "1,200"]),
+                row(&["b@example.com", "B", "eu", "80"]),
+                row(&["c@example.com", "C", "US", "950"]),
+                row(&["bad-email", "D", "EU", "500"]),
+            ],
+        }
+    }
+
+    #[test]
+    fn filters_rows_by_column_predicates() {
+        let filters: Vec<ColumnFilter> = serde_json::from_value(serde_json::json!([
+            { "column": "region", "op": "equals", "value": "EU" },
+            { "column": "Score", "op": "range", "min": 100 }
+        ]))
+        .expect("filters");
+        let report = filter_table(&table(), &filters, false).expect("filter");
+        assert_eq!(report.matched_rows, 2);
+        assert_eq!(report.stats.invalid_email_rows, 1);
+        assert_eq!(report.recipients.len(), 1);
+        assert_eq!(report.recipients[0].email, "a@example.com");
+
+        let filters: Vec<ColumnFilter> = serde_json::from_value(serde_json::json!([
+            { "column": "Email", "op": "regex", "pattern": "^[ab]@" },
+            { "column": "Region", "op": "contains", "value": "U", "case_sensitive": true, "negate": true }
+        ]))
+        .expect("filters");
+        let report = filter_table(&table(), &filters, true).expect("filter");
+        assert_eq!(report.matched_rows, 2);
+
+        let missing: Vec<ColumnFilter> =
+            serde_json::from_value(serde_json::json!([{ "column": "city", "op": "equals", "value": "x" }]))
+                .expect("filters");
+        let error = filter_table(&table(), &missing, false).err().expect("unknown column");
+        assert!(error.contains("Region"));
+    }
+}
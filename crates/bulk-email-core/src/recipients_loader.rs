@@ -0,0 +1,1038 @@
+use crate::engine::RecipientEntry;
+use crate::enrichment::is_field_name;
+use calamine::{open_workbook_auto, Data, Reader};
+use encoding_rs::{Encoding, GB18030, UTF_8};
+use encoding_rs_io::{DecodeReaderBytes, DecodeReaderBytesBuilder};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{self, File};
+use std::hash::{Hash, Hasher};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// 与 Python `recipients_loader` 相同的表头别名。
+const EMAIL_HEADERS: [&str; 4] = ["email", "e-mail", "邮箱", "邮箱地址"];
+const NAME_HEADERS: [&str; 4] = ["name", "姓名", "导师姓名", "老师姓名"];
+/// `recipients_loaded` 事件中预览的收件人数量。
+const PREVIEW_LIMIT: usize = 20;
+/// 可识别与手动指定的 CSV 分隔符。
+const CSV_DELIMITERS: [u8; 4] = [b',', b';', b'\t', b'|'];
+/// 流式读取时用于识别编码与分隔符的文件开头字节数。
+const CSV_SNIFF_BYTES: u64 = 64 * 1024;
+
+/// 与 Python worker 返回的统计字段一致，前端无需区分来源。
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+pub struct RecipientStats {
+    pub total_rows: usize,
+    pub valid_rows: usize,
+    pub sendable_rows: usize,
+    pub invalid_rows: usize,
+    pub invalid_email_rows: usize,
+    pub missing_name_rows: usize,
+    pub duplicate_rows: usize,
+    pub empty_rows: usize,
+}
+
+pub struct RecipientLoadResult {
+    pub recipients: Vec<RecipientEntry>,
+    pub stats: RecipientStats,
+    /// CSV 文件实际使用的编码与分隔符；其他来源为空。
+    pub csv_format: Option<CsvFormat>,
+}
+
+impl RecipientLoadResult {
+    /// 与 worker 的 `recipients_loaded` 响应同形。
+    pub fn to_event(&self) -> Value {
+        let preview: Vec<&RecipientEntry> = self.recipients.iter().take(PREVIEW_LIMIT).collect();
+        json!({
+            "type": "recipients_loaded",
+            "stats": self.stats,
+            "recipients_preview": preview,
+            "csv_format": self.csv_format,
+        })
+    }
+}
+
+/// 手动指定 CSV 的字符编码与分隔符；为空时自动识别。识别结果随导入结果返回，识别有误（如乱码）时可指定后重新导入。
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq)]
+pub struct CsvOptions {
+    /// 编码名称，如 `utf-8`、`gbk`、`gb18030`、`big5`、`utf-16le`。
+    #[serde(default)]
+    pub encoding: Option<String>,
+    /// 逗号、分号、制表符或竖线。
+    #[serde(default)]
+    pub delimiter: Option<char>,
+}
+
+impl CsvOptions {
+    pub fn validate(&self) -> Result<(), String> {
+        self.forced_encoding()?;
+        self.forced_delimiter()?;
+        Ok(())
+    }
+
+    fn forced_encoding(&self) -> Result<Option<&'static Encoding>, String> {
+        let Some(label) = self.encoding.as_deref().map(str::trim).filter(|label| !label.is_empty()) else {
+            return Ok(None);
+        };
+        Encoding::for_label(label.as_bytes())
+            .map(Some)
+            .ok_or_else(|| format!("不支持的字符编码: {label}"))
+    }
+
+    fn forced_delimiter(&self) -> Result<Option<u8>, String> {
+        match self.delimiter {
+            None => Ok(None),
+            Some(delimiter) if delimiter.is_ascii() && CSV_DELIMITERS.contains(&(delimiter as u8)) => {
+                Ok(Some(delimiter as u8))
+            }
+            Some(delimiter) => Err(format!(
+                "不支持的分隔符「{}」：只能是逗号、分号、制表符或竖线",
+                delimiter.escape_default()
+            )),
+        }
+    }
+
+    /// 文件带 BOM 时以 BOM 为准；否则使用指定的编码，未指定时按内容识别。
+    fn encoding(&self, sample: &[u8]) -> Result<&'static Encoding, String> {
+        if let Some((encoding, _)) = Encoding::for_bom(sample) {
+            return Ok(encoding);
+        }
+        match self.forced_encoding()? {
+            Some(encoding) => Ok(encoding),
+            None => sniff_encoding(sample),
+        }
+    }
+
+    fn delimiter(&self, text: &str) -> Result<u8, String> {
+        Ok(self.forced_delimiter()?.unwrap_or_else(|| sniff_delimiter(text)))
+    }
+}
+
+/// CSV 文件实际使用的编码与分隔符。
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct CsvFormat {
+    pub encoding: String,
+    pub delimiter: char,
+}
+
+impl CsvFormat {
+    fn new(encoding: &'static Encoding, delimiter: u8) -> Self {
+        CsvFormat {
+            encoding: encoding.name().to_string(),
+            delimiter: char::from(delimiter),
+        }
+    }
+}
+
+/// 流式发送每批读取的默认行数。
+const DEFAULT_CHUNK_SIZE: usize = 1000;
+const MAX_CHUNK_SIZE: usize = 100_000;
+
+/// 表格中一行的邮箱与姓名原文。
+#[derive(Default)]
+struct RawRow {
+    email: String,
+    name: String,
+    fields: BTreeMap<String, String>,
+}
+
+fn extension(path: &Path) -> String {
+    path.extension()
+        .and_then(|value| value.to_str())
+        .unwrap_or_default()
+        .to_lowercase()
+}
+
+/// Rust 可直接解析的格式；其他格式仍交给 Python worker。
+pub fn is_native_format(path: &Path) -> bool {
+    matches!(extension(path).as_str(), "csv" | "xlsx" | "xlsm" | "ods")
+}
+
+pub fn load_recipients(path: &Path) -> Result<RecipientLoadResult, String> {
+    load_recipients_with(path, None, &CsvOptions::default())
+}
+
+/// 按列映射与 CSV 选项导入：有映射时表头必须包含映射中的全部列，映射到变量名的列作为补充字段供模板使用。
+pub fn load_recipients_with(
+    path: &Path,
+    mapping: Option<&ColumnMapping>,
+    csv: &CsvOptions,
+) -> Result<RecipientLoadResult, String> {
+    let (cells, csv_format) = read_cells(path, csv)?;
+    Ok(RecipientLoadResult {
+        csv_format,
+        ..normalize_rows(detect_columns(cells, mapping)?)
+    })
+}
+
+/// 表格的首行（表头），供前端配置列映射。
+pub fn read_headers(path: &Path, csv: &CsvOptions) -> Result<Vec<String>, String> {
+    let first = read_cells(path, csv)?.0.into_iter().next().unwrap_or_default();
+    Ok(first.iter().map(|header| header.trim().to_string()).collect())
+}
+
+/// 表头到收件人字段的映射，如 `"邮箱" -> "email"`、`"单位" -> "company"`：`email` 与 `name` 必须各映射一列，
+/// 其他目标作为模板变量（`{company}`）写入收件人的补充字段。表头比较忽略大小写与首尾空白。
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq)]
+#[serde(transparent)]
+pub struct ColumnMapping(pub BTreeMap<String, String>);
+
+impl ColumnMapping {
+    pub fn validate(&self) -> Result<(), String> {
+        let mut targets = HashSet::new();
+        for (header, target) in &self.0 {
+            let target = target.trim();
+            if header.trim().is_empty() {
+                return Err("列映射中的表头不能为空".to_string());
+            }
+            if !matches!(target, "email" | "name") && !is_field_name(target) {
+                return Err(format!(
+                    "列「{}」的映射目标「{target}」无效：只能是 email、name 或由字母、数字、下划线组成的变量名，且不能与内置变量重名",
+                    header.trim()
+                ));
+            }
+            if !targets.insert(target) {
+                return Err(format!("多个列映射到了「{target}」"));
+            }
+        }
+        for required in ["email", "name"] {
+            if !targets.contains(required) {
+                return Err(format!("列映射缺少必填字段「{required}」"));
+            }
+        }
+        Ok(())
+    }
+
+    /// 映射出的补充字段名（模板变量）。
+    pub fn field_names(&self) -> impl Iterator<Item = &str> {
+        self.0
+            .values()
+            .map(|target| target.trim())
+            .filter(|target| !matches!(*target, "email" | "name"))
+    }
+
+    fn resolve<S: AsRef<str>>(&self, headers: &[S]) -> Result<RecipientColumns, String> {
+        self.validate()?;
+        let mut columns = RecipientColumns::default();
+        for (header, target) in &self.0 {
+            let wanted = header.trim().to_lowercase();
+            let column = headers
+                .iter()
+                .position(|cell| cell.as_ref().trim().to_lowercase() == wanted)
+                .ok_or_else(|| format!("表头中没有列「{}」", header.trim()))?;
+            match target.trim() {
+                "email" => columns.email = column,
+                "name" => columns.name = column,
+                field => columns.fields.push((column, field.to_string())),
+            }
+        }
+        Ok(columns)
+    }
+}
+
+/// 邮箱、姓名与补充字段所在的列号。
+#[derive(Default)]
+struct RecipientColumns {
+    email: usize,
+    name: usize,
+    fields: Vec<(usize, String)>,
+}
+
+impl RecipientColumns {
+    fn row<'a>(&self, cell: impl Fn(usize) -> Option<&'a str>) -> RawRow {
+        let text = |column: usize| cell(column).unwrap_or_default().to_string();
+        RawRow {
+            email: text(self.email),
+            name: text(self.name),
+            fields: self
+                .fields
+                .iter()
+                .map(|(column, name)| (name.clone(), text(*column).trim().to_string()))
+                .collect(),
+        }
+    }
+}
+
+/// 带表头的收件人表格，供按列名筛选等需要其他列的功能使用。
+pub struct RecipientTable {
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+impl RecipientTable {
+    /// 按列名查找（忽略大小写与首尾空白）。
+    pub fn column(&self, name: &str) -> Option<usize> {
+        let name = name.trim().to_lowercase();
+        self.headers.iter().position(|header| header.trim().to_lowercase() == name)
+    }
+
+    fn recipient_columns(&self) -> Option<(usize, usize)> {
+        let find = |candidates: &[&str]| candidates.iter().find_map(|candidate| self.column(candidate));
+        Some((find(&EMAIL_HEADERS)?, find(&NAME_HEADERS)?))
+    }
+
+    /// 把选中的行按与文件导入相同的规则转换为收件人。
+    pub fn to_recipients<'a>(&self, rows: impl IntoIterator<Item = &'a Vec<String>>) -> RecipientLoadResult {
+        let (email_column, name_column) = self.recipient_columns().unwrap_or((0, 1));
+        normalize_rows(
+            rows.into_iter()
+                .map(|row| RawRow {
+                    email: row.get(email_column).cloned().unwrap_or_default(),
+                    name: row.get(name_column).cloned().unwrap_or_default(),
+                    ..RawRow::default()
+                })
+                .collect(),
+        )
+    }
+}
+
+/// 读取带表头的表格；表头中必须有邮箱与姓名列。
+pub fn load_table(path: &Path) -> Result<RecipientTable, String> {
+    let mut cells = read_cells(path, &CsvOptions::default())?.0.into_iter();
+    let headers = cells.next().unwrap_or_default();
+    let table = RecipientTable {
+        headers: headers.iter().map(|header| header.trim().to_string()).collect(),
+        rows: cells.collect(),
+    };
+    if table.recipient_columns().is_none() {
+        return Err("按列筛选需要表头：请在第一行包含「邮箱 / 姓名」列".to_string());
+    }
+    Ok(table)
+}
+
+fn read_cells(path: &Path, csv: &CsvOptions) -> Result<(Vec<Vec<String>>, Option<CsvFormat>), String> {
+    if !path.is_file() {
+        return Err(format!("收件人文件不存在: {}", path.display()));
+    }
+    match extension(path).as_str() {
+        "csv" => read_csv_cells(path, csv).map(|(cells, format)| (cells, Some(format))),
+        "xlsx" | "xlsm" | "ods" => Ok((read_workbook_cells(path)?, None)),
+        other => Err(format!("不支持的收件人文件格式: .{other}")),
+    }
+}
+
+/// 其他来源（如邮箱搜索）得到的（邮箱, 姓名）行，按与文件导入相同的规则校验、去重和统计。
+pub fn load_recipient_rows(rows: Vec<(String, String)>) -> RecipientLoadResult {
+    load_recipient_entries(
+        rows.into_iter()
+            .map(|(email, name)| RecipientEntry {
+                email,
+                name,
+                ..RecipientEntry::default()
+            })
+            .collect(),
+    )
+}
+
+/// 同 `load_recipient_rows`，并保留每行的补充字段（如目录服务中的部门、职位）。
+pub fn load_recipient_entries(entries: Vec<RecipientEntry>) -> RecipientLoadResult {
+    normalize_rows(
+        entries
+            .into_iter()
+            .map(|entry| RawRow {
+                email: entry.email,
+                name: entry.name,
+                fields: entry.fields,
+            })
+            .collect(),
+    )
+}
+
+/// 从 URL 下载的收件人列表：内容以 `[` 或 `{` 开头时按 JSON 解析，否则按 CSV（与本地文件相同的编码与分隔符识别）。
+/// JSON 可以是数组或带 `recipients` 数组的对象，元素为邮箱字符串或 `{ "email", "name", ... }` 对象，
+/// 没有姓名时以邮箱 @ 前的部分作为姓名，对象中其他可作为模板变量的键写入补充字段。
+pub fn load_recipients_from_bytes(bytes: &[u8], csv: &CsvOptions) -> Result<RecipientLoadResult, String> {
+    let head = bytes.strip_prefix(b"\xef\xbb\xbf").unwrap_or(bytes).trim_ascii_start();
+    if !head.starts_with(b"[") && !head.starts_with(b"{") {
+        let (cells, format) = csv_cells(bytes, csv)?;
+        return Ok(RecipientLoadResult {
+            csv_format: Some(format),
+            ..normalize_rows(detect_columns(cells, None)?)
+        });
+    }
+    let value: Value = serde_json::from_slice(head).map_err(|err| format!("收件人 JSON 格式错误: {err}"))?;
+    let items = match &value {
+        Value::Array(items) => items,
+        Value::Object(object) => object
+            .get("recipients")
+            .and_then(Value::as_array)
+            .ok_or_else(|| "收件人 JSON 必须是数组或包含 recipients 数组".to_string())?,
+        _ => return Err("收件人 JSON 必须是数组或包含 recipients 数组".to_string()),
+    };
+    let rows = items
+        .iter()
+        .enumerate()
+        .map(|(index, item)| match item {
+            Value::String(email) => Ok(RawRow {
+                email: email.clone(),
+                name: email.trim().split('@').next().unwrap_or_default().to_string(),
+                ..RawRow::default()
+            }),
+            Value::Object(object) => {
+                let mut row = RawRow::default();
+                for (key, value) in object {
+                    let text = match value {
+                        Value::String(text) => text.clone(),
+                        Value::Null => continue,
+                        other => other.to_string(),
+                    };
+                    let header = key.trim().to_lowercase();
+                    if EMAIL_HEADERS.contains(&header.as_str()) {
+                        row.email = text;
+                    } else if NAME_HEADERS.contains(&header.as_str()) {
+                        row.name = text;
+                    } else if is_field_name(key) {
+                        row.fields.insert(key.clone(), text);
+                    }
+                }
+                if row.name.trim().is_empty() {
+                    row.name = row.email.trim().split('@').next().unwrap_or_default().to_string();
+                }
+                Ok(row)
+            }
+            _ => Err(format!("收件人 JSON 第 {} 项必须是邮箱字符串或对象", index + 1)),
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    Ok(normalize_rows(rows))
+}
+
+/// 粘贴的文本：含制表符时按表格处理（从 Excel 复制，可带「邮箱 / 姓名」表头）；
+/// 否则每行一个地址，支持 `姓名 <邮箱>`，没有姓名时以邮箱 @ 前的部分作为姓名。空行忽略。
+pub fn load_recipients_from_text(text: &str) -> Result<RecipientLoadResult, String> {
+    let lines: Vec<&str> = text
+        .trim_start_matches('\u{feff}')
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .collect();
+    if lines.iter().any(|line| line.contains('\t')) {
+        let cells = lines
+            .iter()
+            .map(|line| line.split('\t').map(str::to_string).collect())
+            .collect();
+        return Ok(normalize_rows(detect_columns(cells, None)?));
+    }
+    let rows = lines
+        .iter()
+        .map(|line| {
+            let line = line.trim();
+            let (name, email) = match line.strip_suffix('>').and_then(|rest| rest.rsplit_once('<')) {
+                Some((name, email)) => (name.trim().trim_matches('"').trim(), email.trim()),
+                None => ("", line),
+            };
+            let name = if name.is_empty() {
+                email.split('@').next().unwrap_or_default()
+            } else {
+                name
+            };
+            RawRow {
+                email: email.to_string(),
+                name: name.to_string(),
+                ..RawRow::default()
+            }
+        })
+        .collect();
+    Ok(normalize_rows(rows))
+}
+
+fn read_csv_cells(path: &Path, options: &CsvOptions) -> Result<(Vec<Vec<String>>, CsvFormat), String> {
+    let bytes = fs::read(path).map_err(|err| format!("读取收件人文件失败: {err}"))?;
+    csv_cells(&bytes, options)
+}
+
+fn csv_cells(bytes: &[u8], options: &CsvOptions) -> Result<(Vec<Vec<String>>, CsvFormat), String> {
+    let encoding = options.encoding(bytes)?;
+    let (text, _, _) = encoding.decode(bytes);
+    let delimiter = options.delimiter(&text)?;
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .delimiter(delimiter)
+        .from_reader(text.as_bytes());
+    let mut rows = Vec::new();
+    for (index, record) in reader.records().enumerate() {
+        let record = record.map_err(|err| format!("CSV 第 {} 行解析失败: {err}", index + 1))?;
+        rows.push(record.iter().map(str::to_string).collect());
+    }
+    Ok((rows, CsvFormat::new(encoding, delimiter)))
+}
+
+/// Excel 与 LibreOffice (ODS) 表格：与 Python worker 一致，只读取第一个工作表；
+/// 表格不从 A1 开始时补齐前面的空行 / 空列，列号保持一致。
+fn read_workbook_cells(path: &Path) -> Result<Vec<Vec<String>>, String> {
+    let mut workbook = open_workbook_auto(path).map_err(|err| format!("读取表格文件失败: {err}"))?;
+    let Some(sheet) = workbook.sheet_names().first().cloned() else {
+        return Ok(Vec::new());
+    };
+    let range = workbook
+        .worksheet_range(&sheet)
+        .map_err(|err| format!("读取工作表「{sheet}」失败: {err}"))?;
+    let (first_row, first_column) = range.start().unwrap_or_default();
+    let padding = vec![String::new(); first_column as usize];
+    let mut rows = vec![Vec::new(); first_row as usize];
+    rows.extend(
+        range
+            .rows()
+            .map(|row| padding.iter().cloned().chain(row.iter().map(cell_text)).collect()),
+    );
+    Ok(rows)
+}
+
+/// 单元格转文本：整数值的数字不带小数点（如电话、工号列），其余按显示值。
+fn cell_text(cell: &Data) -> String {
+    match cell {
+        Data::Empty => String::new(),
+        Data::String(value) => value.clone(),
+        Data::Float(value) if value.fract() == 0.0 && value.abs() < 1e15 => format!("{}", *value as i64),
+        Data::Bool(value) => if *value { "True" } else { "False" }.to_string(),
+        other => other.to_string(),
+    }
+}
+
+/// 没有 BOM 时先按 UTF-8 校验，再尝试 GB18030（兼容 GBK / GB2312，中文版 Excel 导出的 CSV 多为此编码）。
+fn sniff_encoding(sample: &[u8]) -> Result<&'static Encoding, String> {
+    if std::str::from_utf8(sample).is_ok() {
+        return Ok(UTF_8);
+    }
+    if !GB18030.decode_without_bom_handling(sample).1 {
+        return Ok(GB18030);
+    }
+    Err("无法识别 CSV 文件的字符编码，请手动指定编码（如 big5、windows-1252）".to_string())
+}
+
+/// 按首个非空行中出现最多的分隔符判断：逗号、分号（欧洲区域设置的 Excel）、制表符或竖线。
+fn sniff_delimiter(text: &str) -> u8 {
+    let first_line = text
+        .trim_start_matches('\u{feff}')
+        .lines()
+        .find(|line| !line.trim().is_empty())
+        .unwrap_or_default();
+    CSV_DELIMITERS
+        .into_iter()
+        .max_by_key(|delimiter| (first_line.bytes().filter(|byte| byte == delimiter).count(), *delimiter == b','))
+        .unwrap_or(b',')
+}
+
+/// 按首行确定收件人列：有列映射时首行必须是表头；否则优先按表头别名识别，
+/// 再退回到第一列是邮箱、第二列是姓名。返回列号与首行是否为表头。
+fn recipient_columns<S: AsRef<str>>(
+    first: &[S],
+    mapping: Option<&ColumnMapping>,
+) -> Result<(RecipientColumns, bool), String> {
+    if let Some(mapping) = mapping {
+        return Ok((mapping.resolve(first)?, true));
+    }
+    let find = |candidates: &[&str]| {
+        first
+            .iter()
+            .position(|cell| candidates.contains(&cell.as_ref().trim().to_lowercase().as_str()))
+    };
+    let columns = |email, name| RecipientColumns { email, name, fields: Vec::new() };
+    match (find(&EMAIL_HEADERS), find(&NAME_HEADERS)) {
+        (Some(email), Some(name)) => Ok((columns(email, name), true)),
+        _ if first.first().is_some_and(|cell| looks_like_email(cell.as_ref().trim())) => Ok((columns(0, 1), false)),
+        _ => Err("无法识别收件人列：请使用「邮箱 / 姓名」表头，把邮箱放在第一列、姓名放在第二列，或设置列映射".to_string()),
+    }
+}
+
+/// 确定收件人列后把表格转为原始行。
+fn detect_columns(cells: Vec<Vec<String>>, mapping: Option<&ColumnMapping>) -> Result<Vec<RawRow>, String> {
+    let Some(first) = cells.first() else {
+        return Ok(Vec::new());
+    };
+    let (columns, has_header) = recipient_columns(first, mapping)?;
+    Ok(cells
+        .iter()
+        .skip(usize::from(has_header))
+        .map(|row| columns.row(|column| row.get(column).map(String::as_str)))
+        .collect())
+}
+
+/// 逐行校验与去重，统计口径与 Python worker 相同：空行跳过，无效行计数，重复邮箱（忽略大小写）只保留第一次。
+/// 去重只保存小写邮箱的 64 位哈希，百万行名单也只占用几十 MB 以内的内存。
+#[derive(Default)]
+struct Normalizer {
+    stats: RecipientStats,
+    seen: HashSet<u64>,
+}
+
+impl Normalizer {
+    fn push(&mut self, row: RawRow) -> Option<RecipientEntry> {
+        let stats = &mut self.stats;
+        stats.total_rows += 1;
+        let email = row.email.trim();
+        let name = row.name.trim();
+        if email.is_empty() && name.is_empty() {
+            stats.empty_rows += 1;
+            return None;
+        }
+        if !looks_like_email(email) {
+            stats.invalid_email_rows += 1;
+            return None;
+        }
+        if name.is_empty() {
+            stats.missing_name_rows += 1;
+            return None;
+        }
+        stats.sendable_rows += 1;
+        let mut hasher = DefaultHasher::new();
+        email.to_lowercase().hash(&mut hasher);
+        if !self.seen.insert(hasher.finish()) {
+            stats.duplicate_rows += 1;
+            return None;
+        }
+        stats.valid_rows += 1;
+        Some(RecipientEntry {
+            email: email.to_string(),
+            name: name.to_string(),
+            fields: row.fields,
+        })
+    }
+
+    fn stats(&self) -> RecipientStats {
+        RecipientStats {
+            invalid_rows: self.stats.invalid_email_rows + self.stats.missing_name_rows,
+            ..self.stats.clone()
+        }
+    }
+}
+
+fn normalize_rows(rows: Vec<RawRow>) -> RecipientLoadResult {
+    let mut normalizer = Normalizer::default();
+    let recipients = rows.into_iter().filter_map(|row| normalizer.push(row)).collect();
+    RecipientLoadResult {
+        recipients,
+        stats: normalizer.stats(),
+        csv_format: None,
+    }
+}
+
+/// 由调用方判断是否排除某个收件人（退订名单、未授权等），返回 `true` 表示不发送。
+pub type RecipientExclusion = Arc<dyn Fn(&str) -> bool + Send + Sync>;
+
+/// 按文件流式读取的收件人来源：发送时分批读取，不把整个名单放进内存或任务载荷。
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct RecipientSource {
+    pub path: String,
+    /// 每批读取的收件人行数。
+    #[serde(default = "default_chunk_size")]
+    pub chunk_size: usize,
+    /// 文件的列映射；为空时按表头别名识别。
+    #[serde(default)]
+    pub column_mapping: Option<ColumnMapping>,
+    #[serde(default)]
+    pub csv: CsvOptions,
+}
+
+fn default_chunk_size() -> usize {
+    DEFAULT_CHUNK_SIZE
+}
+
+impl RecipientSource {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(1..=MAX_CHUNK_SIZE).contains(&self.chunk_size) {
+            return Err(format!("每批读取行数 必须在 1 到 {MAX_CHUNK_SIZE} 之间"));
+        }
+        let path = PathBuf::from(self.path.trim());
+        if !path.is_file() {
+            return Err(format!("收件人文件不存在: {}", path.display()));
+        }
+        // xlsx 是压缩包，必须整体解压后才能读取，无法流式处理。
+        if extension(&path) != "csv" {
+            return Err("流式读取收件人仅支持 CSV 文件，请把表格另存为 CSV".to_string());
+        }
+        self.csv.validate()?;
+        if let Some(mapping) = &self.column_mapping {
+            mapping.validate()?;
+        }
+        Ok(())
+    }
+}
+
+/// 分批读取 CSV 收件人文件；校验、去重与统计规则与一次性导入相同，每次迭代返回一批收件人。
+pub struct RecipientStream {
+    /// 非 UTF-8 文件边读边转码为 UTF-8。
+    reader: csv::Reader<DecodeReaderBytes<File, Vec<u8>>>,
+    format: CsvFormat,
+    /// 没有表头时，首行本身就是收件人。
+    first_row: Option<csv::StringRecord>,
+    columns: RecipientColumns,
+    chunk_size: usize,
+    normalizer: Normalizer,
+    exclusion: Option<RecipientExclusion>,
+    excluded: usize,
+    finished: bool,
+}
+
+impl RecipientStream {
+    pub fn open(source: &RecipientSource) -> Result<Self, String> {
+        source.validate()?;
+        let path = PathBuf::from(source.path.trim());
+        let open_error = |err: std::io::Error| format!("读取收件人文件失败: {err}");
+        let mut file = File::open(&path).map_err(open_error)?;
+        let mut sample = Vec::new();
+        file.by_ref()
+            .take(CSV_SNIFF_BYTES)
+            .read_to_end(&mut sample)
+            .map_err(open_error)?;
+        // 只用完整的行识别编码，避免截断在多字节字符中间。
+        if sample.len() as u64 == CSV_SNIFF_BYTES {
+            if let Some(end) = sample.iter().rposition(|byte| *byte == b'\n') {
+                sample.truncate(end + 1);
+            }
+        }
+        let encoding = source.csv.encoding(&sample)?;
+        let delimiter = source.csv.delimiter(&encoding.decode(&sample).0)?;
+        file.seek(SeekFrom::Start(0)).map_err(open_error)?;
+        let reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .delimiter(delimiter)
+            .from_reader(DecodeReaderBytesBuilder::new().encoding(Some(encoding)).build(file));
+
+        let mut stream = RecipientStream {
+            reader,
+            format: CsvFormat::new(encoding, delimiter),
+            first_row: None,
+            columns: RecipientColumns::default(),
+            chunk_size: source.chunk_size,
+            normalizer: Normalizer::default(),
+            exclusion: None,
+            excluded: 0,
+            finished: false,
+        };
+        let mut first = csv::StringRecord::new();
+        if stream.reader.read_record(&mut first).map_err(|err| csv_error(&first, err))? {
+            let cells: Vec<&str> = first.iter().collect();
+            let (columns, has_header) = recipient_columns(&cells, source.column_mapping.as_ref())?;
+            stream.columns = columns;
+            if !has_header {
+                stream.first_row = Some(first);
+            }
+        } else {
+            stream.finished = true;
+        }
+        Ok(stream)
+    }
+
+    pub fn with_exclusion(mut self, exclusion: Option<RecipientExclusion>) -> Self {
+        self.exclusion = exclusion;
+        self
+    }
+
+    pub fn format(&self) -> &CsvFormat {
+        &self.format
+    }
+
+    /// 到目前为止读取的行数统计；读完后与一次性导入的统计一致。
+    pub fn stats(&self) -> RecipientStats {
+        self.normalizer.stats()
+    }
+
+    /// 被排除规则跳过的有效收件人数。
+    pub fn excluded(&self) -> usize {
+        self.excluded
+    }
+
+    fn push(&mut self, record: &csv::StringRecord, chunk: &mut Vec<RecipientEntry>) {
+        let row = self.columns.row(|column| record.get(column));
+        let Some(recipient) = self.normalizer.push(row) else {
+            return;
+        };
+        if self.exclusion.as_ref().is_some_and(|exclude| exclude(&recipient.email)) {
+            self.excluded += 1;
+        } else {
+            chunk.push(recipient);
+        }
+    }
+}
+
+impl Iterator for RecipientStream {
+    type Item = Result<Vec<RecipientEntry>, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut chunk = Vec::with_capacity(self.chunk_size);
+        if let Some(first) = self.first_row.take() {
+            self.push(&first, &mut chunk);
+        }
+        let mut record = csv::StringRecord::new();
+        while !self.finished && chunk.len() < self.chunk_size {
+            match self.reader.read_record(&mut record) {
+                Ok(true) => self.push(&record, &mut chunk),
+                Ok(false) => self.finished = true,
+                Err(err) => {
+                    self.finished = true;
+                    return Some(Err(csv_error(&record, err)));
+                }
+            }
+        }
+        (!chunk.is_empty()).then_some(Ok(chunk))
+    }
+}
+
+fn csv_error(record: &csv::StringRecord, err: csv::Error) -> String {
+    let line = err
+        .position()
+        .or(record.position())
+        .map(|position| position.line())
+        .unwrap_or_default();
+    format!("CSV 第 {line} 行解析失败: {err}")
+}
+
+/// 等价于 Python 的 `^[^@\s]+@[^@\s]+\.[^@\s]+$`。
+fn looks_like_email(value: &str) -> bool {
+    if value.chars().any(char::is_whitespace) {
+        return false;
+    }
+    match value.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && !domain.contains('@')
+                && domain.char_indices().any(|(index, ch)| ch == '.' && index > 0 && index + 1 < domain.len())
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        is_native_format, load_recipients, load_recipients_from_bytes, load_recipients_from_text, load_recipients_with,
+        looks_like_email, read_headers, ColumnMapping, CsvOptions, RecipientSource, RecipientStats, RecipientStream,
+    };
+    use std::path::Path;
+    use std::sync::Arc;
+
+    #[test]
+    fn loads_csv_with_headers_and_matches_worker_stats() {
+        let dir = std::env::temp_dir().join(format!("bes-recipients-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("temp dir");
+        let path = dir.join("teachers.csv");
+        std::fs::write(
+            &path,
+            "\u{feff}姓名;邮箱\n\
+             张教授;zhang@example.edu\n\
+             ;\n\
+             李教授;not-an-email\n\
+             ;wang@example.edu\n\
+             张教授;ZHANG@example.edu\n\
+             \"赵, 教授\";zhao@example.edu\n",
+        )
+        .expect("write csv");
+        let result = load_recipients(&path).expect("load csv");
+        assert_eq!(
+            result.stats,
+            RecipientStats {
+                total_rows: 6,
+                valid_rows: 2,
+                sendable_rows: 3,
+                invalid_rows: 2,
+                invalid_email_rows: 1,
+                missing_name_rows: 1,
+                duplicate_rows: 1,
+                empty_rows: 1,
+            }
+        );
+        assert_eq!(result.recipients[1].name, "赵, 教授");
+        let event = result.to_event();
+        assert_eq!(event["type"], "recipients_loaded");
+        assert_eq!(event["recipients_preview"][0]["email"], "zhang@example.edu");
+
+        std::fs::write(&path, "a@example.edu,Alice\nb@example.edu,Bob\n").expect("write headerless csv");
+        assert_eq!(load_recipients(&path).expect("headerless").recipients.len(), 2);
+        std::fs::write(&path, "foo,bar\n1,2\n").expect("write unknown csv");
+        assert!(load_recipients(&path).is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert!(looks_like_email("a@b.c"));
+        assert!(!looks_like_email("a@b."));
+        assert!(!looks_like_email("a b@c.d"));
+    }
+
+    #[test]
+    fn streams_csv_in_chunks_with_same_stats_as_full_load() {
+        let dir = std::env::temp_dir().join(format!("bes-recipient-stream-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("temp dir");
+        let path = dir.join("large.csv");
+        let mut csv = String::from("\u{feff}email;name\n\n");
+        for index in 1..=25 {
+            csv.push_str(&format!("user{index}@example.edu;User {index}\n"));
+        }
+        csv.push_str("USER3@example.edu;Again\nbroken;Nobody\n;\n");
+        std::fs::write(&path, csv).expect("write csv");
+
+        let source = RecipientSource {
+            path: path.to_string_lossy().into_owned(),
+            chunk_size: 10,
+            column_mapping: None,
+            csv: CsvOptions::default(),
+        };
+        let mut stream = RecipientStream::open(&source)
+            .expect("open stream")
+            .with_exclusion(Some(Arc::new(|email: &str| email.starts_with("user7@"))));
+        let chunks: Vec<Vec<_>> = stream.by_ref().map(|chunk| chunk.expect("chunk")).collect();
+        assert_eq!(chunks.iter().map(Vec::len).collect::<Vec<_>>(), [10, 10, 4]);
+        assert_eq!(chunks[0][0].email, "user1@example.edu");
+        assert!(chunks.iter().flatten().all(|recipient| recipient.email != "user7@example.edu"));
+        assert_eq!(stream.excluded(), 1);
+        assert_eq!(stream.stats(), load_recipients(&path).expect("full load").stats);
+        assert_eq!((stream.stats().duplicate_rows, stream.stats().invalid_rows), (1, 1));
+
+        let xlsx = RecipientSource {
+            path: dir.join("list.xlsx").to_string_lossy().into_owned(),
+            ..source.clone()
+        };
+        std::fs::write(&xlsx.path, b"").expect("write xlsx");
+        assert!(RecipientStream::open(&xlsx).is_err());
+        assert!(RecipientStream::open(&RecipientSource { chunk_size: 0, ..source }).is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn applies_column_mapping_to_files_and_streams() {
+        let dir = std::env::temp_dir().join(format!("bes-column-mapping-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("temp dir");
+        let path = dir.join("contacts.csv");
+        std::fs::write(&path, "Contact Mail,Full Name,Org\na@example.edu,Alice,MIT\nb@example.edu,Bob,\n")
+            .expect("write csv");
+        assert!(load_recipients(&path).is_err());
+        assert_eq!(read_headers(&path, &CsvOptions::default()).expect("headers"), ["Contact Mail", "Full Name", "Org"]);
+
+        let mapping: ColumnMapping = serde_json::from_value(serde_json::json!({
+            "contact mail": "email", "Full Name": "name", "Org": "company"
+        }))
+        .expect("mapping");
+        let loaded = load_recipients_with(&path, Some(&mapping), &CsvOptions::default()).expect("mapped load");
+        assert_eq!(loaded.stats.valid_rows, 2);
+        assert_eq!(loaded.recipients[0].fields["company"], "MIT");
+        assert_eq!(loaded.recipients[1].fields["company"], "");
+        assert_eq!(mapping.field_names().collect::<Vec<_>>(), ["company"]);
+
+        let source = RecipientSource {
+            path: path.to_string_lossy().into_owned(),
+            chunk_size: 10,
+            column_mapping: Some(mapping.clone()),
+            csv: CsvOptions::default(),
+        };
+        let streamed: Vec<_> = RecipientStream::open(&source).expect("stream").flatten().flatten().collect();
+        assert_eq!(streamed.len(), 2);
+        assert_eq!((streamed[0].name.as_str(), streamed[0].fields["company"].as_str()), ("Alice", "MIT"));
+
+        let mut invalid = mapping.clone();
+        invalid.0.insert("Org".to_string(), "teacher_name".to_string());
+        assert!(invalid.validate().is_err());
+        invalid.0.remove("Org");
+        invalid.0.remove("Full Name");
+        assert!(invalid.validate().unwrap_err().contains("name"));
+        let mut missing = mapping;
+        missing.0.insert("Phone".to_string(), "phone".to_string());
+        let missing = load_recipients_with(&path, Some(&missing), &CsvOptions::default());
+        assert!(missing.is_err_and(|err| err.contains("Phone")));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn detects_gbk_encoding_and_delimiter() {
+        let dir = std::env::temp_dir().join(format!("bes-csv-encoding-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("temp dir");
+        let path = dir.join("gbk.csv");
+        let text = "姓名;邮箱;单位\r\n张教授;zhang@example.edu;清华大学\r\n李教授;li@example.edu;北京大学\r\n";
+        std::fs::write(&path, encoding_rs::GBK.encode(text).0).expect("write csv");
+
+        let loaded = load_recipients(&path).expect("gbk csv");
+        assert_eq!(loaded.recipients[0].name, "张教授");
+        let format = loaded.csv_format.clone().expect("csv format");
+        assert_eq!((format.encoding.as_str(), format.delimiter), ("gb18030", ';'));
+        assert_eq!(loaded.to_event()["csv_format"]["delimiter"], ";");
+
+        let source = RecipientSource {
+            path: path.to_string_lossy().into_owned(),
+            chunk_size: 10,
+            column_mapping: None,
+            csv: CsvOptions::default(),
+        };
+        let stream = RecipientStream::open(&source).expect("stream");
+        assert_eq!(stream.format(), &format);
+        let streamed: Vec<_> = stream.flatten().flatten().collect();
+        assert_eq!(streamed[1].name, "李教授");
+
+        // 手动指定的编码或分隔符不对时表头变成乱码或落在一列，无法识别收件人列。
+        let comma = CsvOptions { delimiter: Some(','), ..CsvOptions::default() };
+        assert!(load_recipients_with(&path, None, &comma).is_err());
+        let utf8 = CsvOptions { encoding: Some("utf-8".to_string()), ..CsvOptions::default() };
+        assert!(load_recipients_with(&path, None, &utf8).is_err());
+        let unknown = CsvOptions { encoding: Some("klingon".to_string()), ..CsvOptions::default() };
+        assert!(load_recipients_with(&path, None, &unknown).is_err_and(|err| err.contains("klingon")));
+        assert!(CsvOptions { delimiter: Some('#'), ..CsvOptions::default() }.validate().is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn loads_pasted_addresses_and_tsv() {
+        let pasted = "zhang@example.edu\n\n\"李 教授\" <li@example.edu>\nnot-an-email\nZHANG@example.edu\n";
+        let result = load_recipients_from_text(pasted).expect("lines");
+        assert_eq!(result.stats.total_rows, 4);
+        assert_eq!((result.stats.invalid_email_rows, result.stats.duplicate_rows), (1, 1));
+        assert_eq!(result.recipients[0].name, "zhang");
+        assert_eq!(result.recipients[1].name, "李 教授");
+        assert_eq!(result.recipients[1].email, "li@example.edu");
+
+        let tsv = "姓名\t部门\t邮箱\r\n王教授\t物理\twang@example.edu\r\n\t化学\tzhao@example.edu\r\n";
+        let result = load_recipients_from_text(tsv).expect("tsv");
+        assert_eq!(result.recipients.len(), 1);
+        assert_eq!(result.recipients[0].email, "wang@example.edu");
+        assert_eq!(result.stats.missing_name_rows, 1);
+        assert!(load_recipients_from_text("foo\tbar\n").is_err());
+    }
+
+    #[test]
+    fn loads_bundled_xlsx_sample() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../examples/recipients/recipients_sample.xlsx");
+        assert!(is_native_format(&path));
+        let result = load_recipients(&path).expect("load xlsx");
+        assert_eq!(result.stats.total_rows, 3);
+        assert_eq!(result.stats.valid_rows, 3);
+        assert_eq!(result.recipients[0].email, "teacher_a@example.com");
+        assert_eq!(result.recipients[2].name, "王老师");
+    }
+
+    #[test]
+    fn loads_ods_spreadsheet() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/recipients/teachers.ods");
+        assert!(is_native_format(&path));
+        assert_eq!(read_headers(&path, &CsvOptions::default()).expect("headers"), ["", "姓名", "邮箱", "工号"]);
+        let mapping: ColumnMapping = serde_json::from_value(serde_json::json!({
+            "邮箱": "email", "姓名": "name", "工号": "staff_id"
+        }))
+        .expect("mapping");
+        let result = load_recipients_with(&path, Some(&mapping), &CsvOptions::default()).expect("load ods");
+        assert_eq!((result.stats.total_rows, result.stats.invalid_email_rows), (3, 1));
+        assert_eq!(result.recipients[0].email, "zhang@example.edu");
+        assert_eq!(result.recipients[1].fields["staff_id"], "1002");
+        assert!(result.csv_format.is_none());
+    }
+
+    #[test]
+    fn loads_downloaded_json_and_csv_lists() {
+        let json = r#"{ "recipients": [
+            "a@example.edu",
+            { "邮箱": "b@example.edu", "姓名": "乙", "company": "北大", "seats": 2 },
+            { "email": "not-an-email" }
+        ] }"#;
+        let result = load_recipients_from_bytes(json.as_bytes(), &CsvOptions::default()).expect("json");
+        assert_eq!((result.stats.total_rows, result.stats.valid_rows), (3, 2));
+        assert_eq!((result.recipients[0].name.as_str(), result.recipients[1].name.as_str()), ("a", "乙"));
+        assert_eq!(result.recipients[1].fields["company"], "北大");
+        assert_eq!(result.recipients[1].fields["seats"], "2");
+        assert!(result.csv_format.is_none());
+        assert!(load_recipients_from_bytes(b"[1]", &CsvOptions::default()).is_err());
+
+        let csv = "email;name\nc@example.edu;丙\n";
+        let result = load_recipients_from_bytes(csv.as_bytes(), &CsvOptions::default()).expect("csv");
+        assert_eq!(result.recipients[0].name, "丙");
+        assert_eq!(result.csv_format.map(|format| format.delimiter), Some(';'));
+    }
+}
@@ -0,0 +1,108 @@
+use crate::engine::validate_email;
+use chrono::{Local, SecondsFormat};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+const MAX_NOTE_CHARS: usize = 2000;
+
+/// 附在某次任务中某个收件人记录上的备注。
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct RecordNote {
+    pub email: String,
+    pub note: String,
+    #[serde(default)]
+    pub operator: String,
+    pub updated_at: String,
+}
+
+/// 发送记录备注：按任务 ID、再按小写邮箱索引。发送记录本身只追加不修改，备注单独保存。
+#[derive(Deserialize, Serialize, Default)]
+pub struct RecordNotes {
+    #[serde(default)]
+    pub campaigns: BTreeMap<String, BTreeMap<String, RecordNote>>,
+}
+
+impl RecordNotes {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        if !path.exists() {
+            return Ok(RecordNotes::default());
+        }
+        let text = fs::read_to_string(path).map_err(|err| format!("读取记录备注失败: {err}"))?;
+        serde_json::from_str(&text).map_err(|err| format!("记录备注格式错误: {err}"))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let text = serde_json::to_string_pretty(self).map_err(|err| err.to_string())?;
+        fs::write(path, text).map_err(|err| format!("写入记录备注失败: {err}"))
+    }
+
+    /// 为一批收件人写入同一条备注；备注为空时删除这些备注。
+    /// 任一地址无效时整体拒绝，不做部分修改。返回受影响的记录数。
+    pub fn annotate(&mut self, job_id: &str, emails: &[String], note: &str, operator: &str) -> Result<usize, String> {
+        let job_id = job_id.trim();
+        if job_id.is_empty() {
+            return Err("任务 ID 不能为空".to_string());
+        }
+        let note = note.trim();
+        if note.chars().count() > MAX_NOTE_CHARS {
+            return Err(format!("备注不能超过 {MAX_NOTE_CHARS} 个字符"));
+        }
+        for email in emails {
+            validate_email(email, "记录邮箱")?;
+        }
+        let updated_at = Local::now().to_rfc3339_opts(SecondsFormat::Secs, false);
+        let campaign = self.campaigns.entry(job_id.to_string()).or_default();
+        let mut changed = 0;
+        for email in emails {
+            let key = email.trim().to_lowercase();
+            if note.is_empty() {
+                changed += usize::from(campaign.remove(&key).is_some());
+                continue;
+            }
+            campaign.insert(
+                key,
+                RecordNote {
+                    email: email.trim().to_string(),
+                    note: note.to_string(),
+                    operator: operator.to_string(),
+                    updated_at: updated_at.clone(),
+                },
+            );
+            changed += 1;
+        }
+        if campaign.is_empty() {
+            self.campaigns.remove(job_id);
+        }
+        Ok(changed)
+    }
+
+    pub fn notes_for(&self, job_id: &str) -> Vec<RecordNote> {
+        self.campaigns
+            .get(job_id.trim())
+            .map(|notes| notes.values().cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RecordNotes;
+
+    #[test]
+    fn annotates_in_bulk_and_rejects_invalid_batches() {
+        let mut notes = RecordNotes::default();
+        let emails = vec!["A@example.com".to_string(), "b@example.com".to_string()];
+        assert_eq!(notes.annotate("job-1", &emails, "已电话确认", "张老师").expect("annotate"), 2);
+        assert_eq!(notes.notes_for("job-1")[0].note, "已电话确认");
+
+        let mixed = vec!["c@example.com".to_string(), "broken".to_string()];
+        assert!(notes.annotate("job-1", &mixed, "x", "").is_err());
+        assert_eq!(notes.notes_for("job-1").len(), 2);
+
+        assert_eq!(notes.annotate("job-1", &["a@example.com".to_string()], " ", "").expect("clear"), 1);
+        assert_eq!(notes.annotate("job-1", &["b@example.com".to_string()], "", "").expect("clear"), 1);
+        assert!(notes.campaigns.is_empty());
+    }
+}
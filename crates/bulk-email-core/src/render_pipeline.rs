@@ -0,0 +1,234 @@
+use crate::engine::RecipientEntry;
+use lettre::address::Envelope;
+use lettre::Message;
+use rayon::prelude::*;
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{sync_channel, Receiver};
+use std::sync::Arc;
+use std::thread::Scope;
+
+const DEFAULT_RENDER_AHEAD: usize = 200;
+
+/// 预渲染设置：并行渲染邮件并落盘到任务目录，发送线程只负责投递。
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct RenderOptions {
+    pub parallel: bool,
+    /// 渲染线程数；0 表示按 CPU 核数。
+    pub threads: usize,
+    /// 最多领先发送进度的邮件数，决定落盘文件与内存占用的上限。
+    pub ahead: usize,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions {
+            parallel: false,
+            threads: 0,
+            ahead: DEFAULT_RENDER_AHEAD,
+        }
+    }
+}
+
+impl RenderOptions {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.parallel && self.ahead == 0 {
+            return Err("预渲染数量必须 >= 1".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// 落盘的已渲染邮件：原始字节保存在 `path`，信封留在内存中。
+pub struct SpooledMessage {
+    pub path: PathBuf,
+    pub envelope: Envelope,
+}
+
+struct RenderedMessage {
+    index: usize,
+    result: Result<SpooledMessage, String>,
+}
+
+/// 发送线程一侧的预渲染队列，按收件人序号依次取出。
+pub struct RenderSpool {
+    receiver: Receiver<RenderedMessage>,
+    peeked: Option<RenderedMessage>,
+}
+
+impl RenderSpool {
+    /// 取出序号为 `index` 的邮件；序号更小的（已跳过或延后的收件人）直接丢弃。
+    /// 没有预渲染该收件人时返回 `None`，由调用方现场渲染。
+    pub fn take(&mut self, index: usize) -> Option<Result<SpooledMessage, String>> {
+        loop {
+            let next = match self.peeked.take() {
+                Some(next) => next,
+                None => self.receiver.recv().ok()?,
+            };
+            if next.index == index {
+                return Some(next.result);
+            }
+            if next.index > index {
+                self.peeked = Some(next);
+                return None;
+            }
+            if let Ok(spooled) = next.result {
+                let _ = fs::remove_file(spooled.path);
+            }
+        }
+    }
+}
+
+/// 启动渲染线程：按 `ahead` 分块用 rayon 并行渲染，写入 `dir/<序号>.eml` 后经有界通道交给发送线程。
+/// 发送线程落后时通道写满，渲染线程随之阻塞，因此磁盘与内存占用都有上限。
+pub fn start<'scope, F>(
+    scope: &'scope Scope<'scope, '_>,
+    options: &RenderOptions,
+    dir: &Path,
+    items: Vec<(usize, RecipientEntry)>,
+    cancel: Arc<AtomicBool>,
+    render: F,
+) -> Result<RenderSpool, String>
+where
+    F: Fn(&RecipientEntry) -> Result<Message, String> + Send + Sync + 'scope,
+{
+    fs::create_dir_all(dir).map_err(|err| format!("创建预渲染目录失败: {err}"))?;
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(options.threads)
+        .build()
+        .map_err(|err| format!("创建渲染线程池失败: {err}"))?;
+    let ahead = options.ahead.max(1);
+    let (sender, receiver) = sync_channel(ahead);
+    let dir = dir.to_path_buf();
+    scope.spawn(move || {
+        for chunk in items.chunks(ahead) {
+            if cancel.load(Ordering::SeqCst) {
+                return;
+            }
+            let rendered: Vec<RenderedMessage> = pool.install(|| {
+                chunk
+                    .par_iter()
+                    .map(|(index, recipient)| RenderedMessage {
+                        index: *index,
+                        result: spool_message(&dir, *index, render(recipient)),
+                    })
+                    .collect()
+            });
+            for message in rendered {
+                if sender.send(message).is_err() {
+                    // 发送线程已结束（完成或取消），停止渲染。
+                    return;
+                }
+            }
+        }
+    });
+    Ok(RenderSpool {
+        receiver,
+        peeked: None,
+    })
+}
+
+fn spool_message(dir: &Path, index: usize, message: Result<Message, String>) -> Result<SpooledMessage, String> {
+    let message = message?;
+    let path = dir.join(format!("{index:06}.eml"));
+    fs::write(&path, message.formatted()).map_err(|err| format!("写入预渲染邮件失败: {err}"))?;
+    Ok(SpooledMessage {
+        path,
+        envelope: message.envelope().clone(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{start, RenderOptions};
+    use crate::engine::RecipientEntry;
+    use lettre::Message;
+    use std::collections::BTreeMap;
+    use std::sync::atomic::AtomicBool;
+    use std::sync::Arc;
+
+    fn recipients(emails: &[&str]) -> Vec<(usize, RecipientEntry)> {
+        emails
+            .iter()
+            .enumerate()
+            .map(|(index, email)| {
+                let recipient = RecipientEntry {
+                    email: email.to_string(),
+                    name: format!("R{index}"),
+                    fields: BTreeMap::new(),
+                };
+                (index, recipient)
+            })
+            .collect()
+    }
+
+    fn render(recipient: &RecipientEntry) -> Result<Message, String> {
+        Message::builder()
+            .from("me@example.com".parse().map_err(|err| format!("{err}"))?)
+            .to(recipient.email.parse().map_err(|err| format!("收件人格式错误: {err}"))?)
+            .subject(format!("你好 {}", recipient.name))
+            .body(String::from("您好"))
+            .map_err(|err| err.to_string())
+    }
+
+    #[test]
+    fn requires_a_positive_render_ahead_when_parallel() {
+        assert!(RenderOptions::default().validate().is_ok());
+        let options = RenderOptions {
+            parallel: true,
+            ahead: 0,
+            ..RenderOptions::default()
+        };
+        assert!(options.validate().is_err());
+    }
+
+    #[test]
+    fn spools_messages_in_order_and_discards_skipped_ones() {
+        let dir = std::env::temp_dir().join(format!("bes-render-spool-{}", std::process::id()));
+        let options = RenderOptions {
+            parallel: true,
+            threads: 2,
+            ahead: 2,
+        };
+        let items = recipients(&["a@pku.edu.cn", "b@pku.edu.cn", "bad", "d@pku.edu.cn", "e@pku.edu.cn"]);
+        std::thread::scope(|scope| {
+            let cancel = Arc::new(AtomicBool::new(false));
+            let mut spool = start(scope, &options, &dir, items, cancel, render).expect("start");
+            let first = spool.take(0).expect("rendered").expect("spooled");
+            assert!(first.path.ends_with("000000.eml"));
+            let raw = std::fs::read_to_string(&first.path).expect("eml");
+            assert!(raw.contains("To: a@pku.edu.cn"), "{raw}");
+            assert_eq!(first.envelope.to().len(), 1);
+
+            // 跳过 1 号收件人：它的文件被删除；2 号渲染失败，错误原样交给发送线程。
+            let error = spool.take(2).expect("rendered").err().expect("invalid address");
+            assert!(error.contains("收件人格式错误"), "{error}");
+            assert!(!dir.join("000001.eml").exists());
+            assert!(spool.take(4).expect("rendered").is_ok());
+            assert!(!dir.join("000003.eml").exists());
+            // 渲染线程结束后没有更多预渲染结果，由调用方现场渲染。
+            assert!(spool.take(5).is_none());
+        });
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn renders_nothing_once_cancelled() {
+        let dir = std::env::temp_dir().join(format!("bes-render-cancel-{}", std::process::id()));
+        let options = RenderOptions {
+            parallel: true,
+            ..RenderOptions::default()
+        };
+        std::thread::scope(|scope| {
+            let cancel = Arc::new(AtomicBool::new(true));
+            let items = recipients(&["a@pku.edu.cn"]);
+            let mut spool = start(scope, &options, &dir, items, cancel, render).expect("start");
+            assert!(spool.take(0).is_none());
+        });
+        assert_eq!(std::fs::read_dir(&dir).map(Iterator::count).unwrap_or_default(), 0);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
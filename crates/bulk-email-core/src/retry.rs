@@ -0,0 +1,103 @@
+use crate::smtp_client::SendFailure;
+use serde::Deserialize;
+use std::time::Duration;
+
+/// 单封邮件的重试策略：最大尝试次数、指数退避参数，以及是否只对临时错误重试。
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub backoff_base_sec: u64,
+    pub backoff_max_sec: u64,
+    pub transient_only: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            backoff_base_sec: 5,
+            backoff_max_sec: 300,
+            transient_only: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// 兼容旧的 `retry_count` 选项：固定 1 秒间隔，任何错误都重试。
+    pub fn from_retry_count(retry_count: u32) -> Self {
+        RetryPolicy {
+            max_attempts: retry_count.max(1),
+            backoff_base_sec: 1,
+            backoff_max_sec: 1,
+            transient_only: false,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_attempts == 0 {
+            return Err("最大尝试次数 必须 >= 1".to_string());
+        }
+        if self.backoff_max_sec < self.backoff_base_sec {
+            return Err("退避上限不能小于退避基数".to_string());
+        }
+        Ok(())
+    }
+
+    /// 第 `attempt` 次（从 1 开始）失败后，是否还应再试一次。
+    pub fn should_retry(&self, failure: &SendFailure, attempt: u32) -> bool {
+        attempt < self.max_attempts && (!self.transient_only || is_transient(failure))
+    }
+
+    /// 第 `attempt` 次失败后的等待时间：base * 2^(attempt-1)，不超过上限。
+    pub fn backoff_delay(&self, attempt: u32) -> Duration {
+        let factor = 1u64.checked_shl(attempt.saturating_sub(1)).unwrap_or(u64::MAX);
+        Duration::from_secs(self.backoff_base_sec.saturating_mul(factor).min(self.backoff_max_sec))
+    }
+}
+
+/// 4xx 响应与没有响应码的连接层错误视为临时错误；5xx 为永久拒收。
+pub fn is_transient(failure: &SendFailure) -> bool {
+    match failure.code {
+        Some(code) => (400..500).contains(&code),
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RetryPolicy;
+    use crate::smtp_client::SendFailure;
+    use std::time::Duration;
+
+    fn failure(code: Option<u16>) -> SendFailure {
+        SendFailure {
+            code,
+            message: "failure".to_string(),
+        }
+    }
+
+    #[test]
+    fn retries_only_transient_failures() {
+        let policy = RetryPolicy::default();
+        assert!(policy.should_retry(&failure(Some(421)), 1));
+        assert!(policy.should_retry(&failure(None), 2));
+        assert!(!policy.should_retry(&failure(Some(421)), 3));
+        assert!(!policy.should_retry(&failure(Some(550)), 1));
+        assert!(RetryPolicy::from_retry_count(2).should_retry(&failure(Some(550)), 1));
+    }
+
+    #[test]
+    fn backs_off_exponentially_up_to_cap() {
+        let policy = RetryPolicy {
+            backoff_base_sec: 5,
+            backoff_max_sec: 30,
+            ..RetryPolicy::default()
+        };
+        assert_eq!(policy.backoff_delay(1), Duration::from_secs(5));
+        assert_eq!(policy.backoff_delay(2), Duration::from_secs(10));
+        assert_eq!(policy.backoff_delay(3), Duration::from_secs(20));
+        assert_eq!(policy.backoff_delay(4), Duration::from_secs(30));
+        assert_eq!(policy.backoff_delay(80), Duration::from_secs(30));
+    }
+}
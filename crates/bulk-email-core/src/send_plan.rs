@@ -0,0 +1,158 @@
+use crate::engine::{RecipientEntry, SendOptions};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use serde::Serialize;
+
+/// 一次发送任务中所有随机决定的部分：收件人顺序与每封之间的间隔。
+/// 计划在发送前一次算好，与投递结果无关；同一版本下种子相同则计划完全相同。
+#[derive(Serialize, Clone)]
+pub struct SendPlan {
+    pub seed: u64,
+    pub order: Vec<RecipientEntry>,
+    /// `delays[i]` 为第 `i + 1` 个位置发送后的等待秒数。
+    pub delays: Vec<u64>,
+}
+
+impl SendPlan {
+    /// 使用 `options.seed`；未指定时随机生成种子，并记录在计划（及 `job_started` 事件）中以便复现。
+    pub fn build(options: &SendOptions, recipients: &[RecipientEntry]) -> Self {
+        let seed = options.seed.unwrap_or_else(rand::random);
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut order = recipients.to_vec();
+        if options.randomize_order {
+            order.shuffle(&mut rng);
+        }
+        let (low, high) = delay_bounds(options);
+        let delays = order.iter().map(|_| rng.gen_range(low..=high)).collect();
+        SendPlan { seed, order, delays }
+    }
+
+    /// 按发送顺序逐个给出收件人及其后的等待时间。
+    pub fn entries(&self) -> impl Iterator<Item = PlannedRecipient> + '_ {
+        self.order.iter().enumerate().map(|(position, recipient)| PlannedRecipient {
+            index: position + 1,
+            recipient: recipient.clone(),
+            delay_after: self.delays.get(position).copied().unwrap_or_default(),
+        })
+    }
+
+    /// 第 `index`（从 1 开始）个位置之后的等待秒数。
+    pub fn delay_after(&self, index: usize) -> u64 {
+        index
+            .checked_sub(1)
+            .and_then(|position| self.delays.get(position))
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+fn delay_bounds(options: &SendOptions) -> (u64, u64) {
+    if options.max_delay_sec < options.min_delay_sec {
+        (options.max_delay_sec, options.min_delay_sec)
+    } else {
+        (options.min_delay_sec, options.max_delay_sec)
+    }
+}
+
+/// 计划中的一个发送位置。
+pub struct PlannedRecipient {
+    /// 从 1 开始的位置。
+    pub index: usize,
+    pub recipient: RecipientEntry,
+    /// 发送后的等待秒数。
+    pub delay_after: u64,
+}
+
+/// 流式收件人的发送计划：收件人分批到达，无法整体打乱，随机顺序只在每批内部生效；
+/// 种子与批大小相同则计划相同。
+pub struct StreamPlan<I> {
+    chunks: I,
+    rng: StdRng,
+    randomize_order: bool,
+    delay_bounds: (u64, u64),
+    current: std::vec::IntoIter<RecipientEntry>,
+    index: usize,
+}
+
+impl<I> StreamPlan<I>
+where
+    I: Iterator<Item = Result<Vec<RecipientEntry>, String>>,
+{
+    pub fn new(options: &SendOptions, seed: u64, chunks: I) -> Self {
+        StreamPlan {
+            chunks,
+            rng: StdRng::seed_from_u64(seed),
+            randomize_order: options.randomize_order,
+            delay_bounds: delay_bounds(options),
+            current: Vec::new().into_iter(),
+            index: 0,
+        }
+    }
+}
+
+impl<I> Iterator for StreamPlan<I>
+where
+    I: Iterator<Item = Result<Vec<RecipientEntry>, String>>,
+{
+    type Item = Result<PlannedRecipient, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let recipient = loop {
+            if let Some(recipient) = self.current.next() {
+                break recipient;
+            }
+            let mut chunk = match self.chunks.next()? {
+                Ok(chunk) => chunk,
+                Err(err) => return Some(Err(err)),
+            };
+            if self.randomize_order {
+                chunk.shuffle(&mut self.rng);
+            }
+            self.current = chunk.into_iter();
+        };
+        self.index += 1;
+        let (low, high) = self.delay_bounds;
+        Some(Ok(PlannedRecipient {
+            index: self.index,
+            recipient,
+            delay_after: self.rng.gen_range(low..=high),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SendPlan;
+    use crate::engine::{RecipientEntry, SendOptions};
+
+    #[test]
+    fn same_seed_reproduces_order_and_delays() {
+        let recipients: Vec<RecipientEntry> = (1..=20)
+            .map(|index| RecipientEntry {
+                email: format!("r{index}@example.com"),
+                name: format!("R{index}"),
+                ..RecipientEntry::default()
+            })
+            .collect();
+        let options = SendOptions {
+            min_delay_sec: 5,
+            max_delay_sec: 30,
+            randomize_order: true,
+            seed: Some(42),
+            ..SendOptions::default()
+        };
+        let emails = |plan: &SendPlan| plan.order.iter().map(|r| r.email.clone()).collect::<Vec<_>>();
+
+        let first = SendPlan::build(&options, &recipients);
+        let second = SendPlan::build(&options, &recipients);
+        assert_eq!(emails(&first), emails(&second));
+        assert_eq!(first.delays, second.delays);
+        assert!(first.delays.iter().all(|delay| (5..=30).contains(delay)));
+        assert_eq!(first.delay_after(1), first.delays[0]);
+        assert_eq!(first.delay_after(0), 0);
+
+        let other = SendPlan::build(&SendOptions { seed: Some(43), ..options }, &recipients);
+        assert_ne!(emails(&first), emails(&other));
+    }
+}
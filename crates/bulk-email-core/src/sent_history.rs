@@ -0,0 +1,760 @@
+use crate::campaign_log::{smtp_error_class, FailureGroup};
+use chrono::{DateTime, Duration, Local, NaiveDate, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// 单页最多返回的记录数。
+pub const MAX_PAGE_SIZE: usize = 1000;
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordStatus {
+    Sent,
+    Failed,
+}
+
+/// 发送历史查询条件；`since` / `until` 接受 RFC 3339 时间或本地日期 `YYYY-MM-DD`（`until` 含当天）。
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct SentRecordQuery {
+    pub status: Option<RecordStatus>,
+    pub since: Option<String>,
+    pub until: Option<String>,
+    /// 邮箱或姓名中包含的文本，忽略大小写。
+    pub recipient: Option<String>,
+    pub campaign_id: Option<String>,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+impl Default for SentRecordQuery {
+    fn default() -> Self {
+        SentRecordQuery {
+            status: None,
+            since: None,
+            until: None,
+            recipient: None,
+            campaign_id: None,
+            offset: 0,
+            limit: 100,
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct SentRecord {
+    pub status: RecordStatus,
+    pub email: String,
+    pub name: String,
+    pub campaign_id: String,
+    /// UTC，RFC 3339。
+    pub at: String,
+    pub error: Option<String>,
+    pub operator: Option<String>,
+    pub environment: Option<String>,
+    pub message_id: Option<String>,
+}
+
+/// 按时间倒序的一页记录；`total` 为符合条件的记录总数。
+#[derive(Serialize, Debug)]
+pub struct SentRecordPage {
+    pub records: Vec<SentRecord>,
+    pub total: usize,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+/// 统计范围；均为空时统计全部历史。
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct SendStatsQuery {
+    pub since: Option<String>,
+    pub until: Option<String>,
+    pub campaign_id: Option<String>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct DailySendCount {
+    /// 本地日期 `YYYY-MM-DD`。
+    pub date: String,
+    pub sent: usize,
+    pub failed: usize,
+}
+
+/// 发送历史的汇总，供界面直接绘制统计面板。
+#[derive(Serialize, Debug)]
+pub struct SendStats {
+    pub sent: usize,
+    pub failed: usize,
+    /// 成功数 / (成功数 + 失败数)；没有记录时为空。
+    pub success_rate: Option<f64>,
+    pub per_day: Vec<DailySendCount>,
+    /// 按 SMTP 状态码分组的失败，数量多的在前。
+    pub failures: Vec<FailureGroup>,
+    /// 平均每小时成功发送数：各任务首末两次发送之间的时长合计；少于两次发送的任务不计入。
+    pub sent_per_hour: Option<f64>,
+}
+
+/// 发送记录保留策略：只保留最近 `keep_days` 天、最多 `keep_rows` 条成功记录；均为空时不清理。
+/// 按天数清理时，最后一次写入早于期限的任务日志（连同保存的任务参数）一并删除。
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct RetentionPolicy {
+    pub keep_days: Option<u32>,
+    pub keep_rows: Option<usize>,
+}
+
+impl RetentionPolicy {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.keep_days == Some(0) {
+            return Err("保留天数 必须 >= 1".to_string());
+        }
+        if self.keep_rows == Some(0) {
+            return Err("保留条数 必须 >= 1".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.keep_days.is_some() || self.keep_rows.is_some()
+    }
+}
+
+#[derive(Serialize, Debug, Default, PartialEq)]
+pub struct PurgeReport {
+    pub removed_records: usize,
+    pub kept_records: usize,
+    pub removed_campaign_logs: usize,
+}
+
+/// 选择性清除发送历史的条件；`before` 接受 RFC 3339 时间或本地日期 `YYYY-MM-DD`（不含当天）。
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct ClearFilter {
+    pub before: Option<String>,
+    pub campaign_id: Option<String>,
+    pub status: Option<RecordStatus>,
+}
+
+#[derive(Serialize, Debug, Default, PartialEq)]
+pub struct ClearReport {
+    pub removed_sent: usize,
+    pub removed_failed: usize,
+}
+
+/// 发送历史的两个来源：发送记录（成功）与各任务事件日志中的失败事件。
+pub struct SentHistory {
+    sent_store: PathBuf,
+    campaigns_dir: PathBuf,
+}
+
+impl SentHistory {
+    pub fn new(sent_store: &Path, campaigns_dir: &Path) -> Self {
+        SentHistory {
+            sent_store: sent_store.to_path_buf(),
+            campaigns_dir: campaigns_dir.to_path_buf(),
+        }
+    }
+
+    /// 逐行读取，只在内存中保留当前页所需的最新记录。
+    pub fn query(&self, query: &SentRecordQuery) -> Result<SentRecordPage, String> {
+        let filter = RecordFilter::new(query.since.as_deref(), query.until.as_deref(), query.recipient.as_deref())?;
+        let limit = query.limit.clamp(1, MAX_PAGE_SIZE);
+        let keep = query.offset.saturating_add(limit);
+        let mut total = 0;
+        let mut newest: Vec<(DateTime<Utc>, SentRecord)> = Vec::new();
+        self.for_each(query.status, query.campaign_id.as_deref(), |at, record| {
+            if !filter.matches(at, &record) {
+                return;
+            }
+            total += 1;
+            newest.push((at, record));
+            if newest.len() >= keep.saturating_mul(2).max(1024) {
+                newest.sort_by_key(|(at, _)| Reverse(*at));
+                newest.truncate(keep);
+            }
+        })?;
+        newest.sort_by_key(|(at, _)| Reverse(*at));
+        let records = newest
+            .into_iter()
+            .skip(query.offset)
+            .take(limit)
+            .map(|(_, record)| record)
+            .collect();
+        Ok(SentRecordPage {
+            records,
+            total,
+            offset: query.offset,
+            limit,
+        })
+    }
+
+    pub fn stats(&self, query: &SendStatsQuery) -> Result<SendStats, String> {
+        let filter = RecordFilter::new(query.since.as_deref(), query.until.as_deref(), None)?;
+        let mut days: BTreeMap<String, DailySendCount> = BTreeMap::new();
+        let mut groups: BTreeMap<String, FailureGroup> = BTreeMap::new();
+        // 每个任务的 (成功数, 首次发送, 最后一次发送)。
+        let mut campaigns: BTreeMap<String, (usize, DateTime<Utc>, DateTime<Utc>)> = BTreeMap::new();
+        let (mut sent, mut failed) = (0, 0);
+        self.for_each(None, query.campaign_id.as_deref(), |at, record| {
+            if !filter.matches(at, &record) {
+                return;
+            }
+            let date = at.with_timezone(&Local).format("%Y-%m-%d").to_string();
+            let day = days.entry(date.clone()).or_insert_with(|| DailySendCount {
+                date,
+                sent: 0,
+                failed: 0,
+            });
+            match record.status {
+                RecordStatus::Sent => {
+                    sent += 1;
+                    day.sent += 1;
+                    let span = campaigns.entry(record.campaign_id).or_insert((0, at, at));
+                    span.0 += 1;
+                    span.1 = span.1.min(at);
+                    span.2 = span.2.max(at);
+                }
+                RecordStatus::Failed => {
+                    failed += 1;
+                    day.failed += 1;
+                    let error = record.error.unwrap_or_default();
+                    let class = smtp_error_class(&error);
+                    groups
+                        .entry(class.clone())
+                        .or_insert_with(|| FailureGroup {
+                            class,
+                            count: 0,
+                            sample: error,
+                        })
+                        .count += 1;
+                }
+            }
+        })?;
+        let mut failures: Vec<FailureGroup> = groups.into_values().collect();
+        failures.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.class.cmp(&b.class)));
+        let (timed_sent, seconds) = campaigns
+            .values()
+            .filter(|(count, first, last)| *count > 1 && last > first)
+            .fold((0, 0i64), |(total, seconds), (count, first, last)| {
+                (total + count, seconds + (*last - *first).num_seconds())
+            });
+        Ok(SendStats {
+            sent,
+            failed,
+            success_rate: (sent + failed > 0).then(|| sent as f64 / (sent + failed) as f64),
+            per_day: days.into_values().collect(),
+            failures,
+            sent_per_hour: (seconds > 0).then(|| timed_sent as f64 * 3600.0 / seconds as f64),
+        })
+    }
+
+    /// 按保留策略清理发送记录（同步清理可读版 `text_path`）与过期的任务日志，返回清理结果。
+    /// 记录按写入顺序追加，超出条数时删除最早的记录；无法解析的行原样保留。
+    pub fn purge(
+        &self,
+        text_path: Option<&Path>,
+        policy: &RetentionPolicy,
+        now: DateTime<Utc>,
+    ) -> Result<PurgeReport, String> {
+        policy.validate()?;
+        let mut report = PurgeReport::default();
+        if !policy.is_enabled() {
+            return Ok(report);
+        }
+        let cutoff = policy.keep_days.map(|days| now - Duration::days(i64::from(days)));
+        let mut lines: Vec<(Option<DateTime<Utc>>, String)> = Vec::new();
+        for_each_line(&self.sent_store, |line| {
+            if !line.is_empty() {
+                lines.push((sent_record(line).map(|(at, _)| at), line.to_string()));
+            }
+        })?;
+        let is_expired = |at: &DateTime<Utc>| cutoff.is_some_and(|cutoff| *at < cutoff);
+        let records = lines.iter().filter(|(at, _)| at.is_some()).count();
+        let unexpired = lines.iter().filter(|(at, _)| at.as_ref().is_some_and(|at| !is_expired(at))).count();
+        let mut excess = policy.keep_rows.map_or(0, |rows| unexpired.saturating_sub(rows));
+        lines.retain(|(at, _)| {
+            let Some(at) = at else {
+                return true;
+            };
+            if is_expired(at) {
+                return false;
+            }
+            if excess > 0 {
+                excess -= 1;
+                return false;
+            }
+            true
+        });
+        report.kept_records = lines.iter().filter(|(at, _)| at.is_some()).count();
+        report.removed_records = records - report.kept_records;
+        if report.removed_records > 0 {
+            let text: String = lines.iter().map(|(_, line)| format!("{line}\n")).collect();
+            replace_file(&self.sent_store, &text)?;
+            if let Some(text_path) = text_path.filter(|path| path.exists()) {
+                let oldest = lines.iter().filter_map(|(at, _)| *at).min();
+                purge_text_records(text_path, oldest)?;
+            }
+        }
+        if let Some(cutoff) = cutoff {
+            report.removed_campaign_logs = self.purge_campaign_logs(cutoff.into())?;
+        }
+        Ok(report)
+    }
+
+    /// 删除符合条件的成功记录（同步删除可读版中对应的行）与失败事件，其余历史保持不变。
+    pub fn clear(&self, text_path: Option<&Path>, filter: &ClearFilter) -> Result<ClearReport, String> {
+        let before = filter.before.as_deref().map(|value| parse_bound(value, false)).transpose()?;
+        let campaign_id = filter.campaign_id.as_deref().map(str::trim).filter(|id| !id.is_empty());
+        let matches = |at: DateTime<Utc>, record: &SentRecord| {
+            before.is_none_or(|before| at < before) && campaign_id.is_none_or(|id| id == record.campaign_id)
+        };
+        let mut report = ClearReport::default();
+        if filter.status != Some(RecordStatus::Failed) {
+            let mut removed = HashSet::new();
+            report.removed_sent = rewrite_lines(&self.sent_store, |line| match sent_record(line) {
+                Some((at, record)) if matches(at, &record) => {
+                    let local_time = at.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S").to_string();
+                    removed.insert((local_time, record.email.to_lowercase(), record.campaign_id));
+                    false
+                }
+                _ => true,
+            })?;
+            if let Some(text_path) = text_path.filter(|path| path.exists() && !removed.is_empty()) {
+                rewrite_lines(text_path, |line| text_record_key(line).is_none_or(|key| !removed.contains(&key)))?;
+            }
+        }
+        if filter.status != Some(RecordStatus::Sent) {
+            for log in self.campaign_logs()? {
+                report.removed_failed += rewrite_lines(&log, |line| {
+                    failed_record(line).is_none_or(|(at, record)| !matches(at, &record))
+                })?;
+            }
+        }
+        Ok(report)
+    }
+
+    fn purge_campaign_logs(&self, cutoff: SystemTime) -> Result<usize, String> {
+        let mut removed = 0;
+        for log in self.campaign_logs()? {
+            let modified = fs::metadata(&log).and_then(|meta| meta.modified());
+            if modified.is_ok_and(|modified| modified < cutoff) {
+                fs::remove_file(&log).map_err(|err| format!("删除任务日志失败: {err}"))?;
+                let _ = fs::remove_file(log.with_extension("campaign.json"));
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+
+    /// 依次读取两个来源中带有效时间的记录；指定任务时只读取该任务的记录与日志。
+    pub fn for_each(
+        &self,
+        status: Option<RecordStatus>,
+        campaign_id: Option<&str>,
+        mut visit: impl FnMut(DateTime<Utc>, SentRecord),
+    ) -> Result<(), String> {
+        let campaign_id = campaign_id.map(str::trim).filter(|id| !id.is_empty());
+        if status != Some(RecordStatus::Failed) {
+            for_each_line(&self.sent_store, |line| {
+                let Some((at, record)) = sent_record(line) else {
+                    return;
+                };
+                if campaign_id.is_none_or(|id| id == record.campaign_id) {
+                    visit(at, record);
+                }
+            })?;
+        }
+        if status != Some(RecordStatus::Sent) {
+            for log in self.campaign_logs()? {
+                for_each_line(&log, |line| {
+                    let Some((at, record)) = failed_record(line) else {
+                        return;
+                    };
+                    if campaign_id.is_none_or(|id| id == record.campaign_id) {
+                        visit(at, record);
+                    }
+                })?;
+            }
+        }
+        Ok(())
+    }
+
+    fn campaign_logs(&self) -> Result<Vec<PathBuf>, String> {
+        if !self.campaigns_dir.exists() {
+            return Ok(Vec::new());
+        }
+        let entries = fs::read_dir(&self.campaigns_dir).map_err(|err| format!("读取任务日志目录失败: {err}"))?;
+        let mut logs: Vec<PathBuf> = entries
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "jsonl"))
+            .collect();
+        logs.sort();
+        Ok(logs)
+    }
+}
+
+struct RecordFilter {
+    since: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+    recipient: Option<String>,
+}
+
+impl RecordFilter {
+    fn new(since: Option<&str>, until: Option<&str>, recipient: Option<&str>) -> Result<Self, String> {
+        Ok(RecordFilter {
+            since: since.map(|value| parse_bound(value, false)).transpose()?,
+            until: until.map(|value| parse_bound(value, true)).transpose()?,
+            recipient: recipient.map(|text| text.trim().to_lowercase()).filter(|text| !text.is_empty()),
+        })
+    }
+
+    fn matches(&self, at: DateTime<Utc>, record: &SentRecord) -> bool {
+        self.since.is_none_or(|since| at >= since)
+            && self.until.is_none_or(|until| at < until)
+            && self.recipient.as_ref().is_none_or(|text| {
+                record.email.to_lowercase().contains(text) || record.name.to_lowercase().contains(text)
+            })
+    }
+}
+
+/// 解析时间范围边界；日期按本地时区计算，作为上界时取次日零点（不含）。
+pub fn parse_bound(value: &str, end_of_day: bool) -> Result<DateTime<Utc>, String> {
+    let value = value.trim();
+    if let Ok(at) = DateTime::parse_from_rfc3339(value) {
+        return Ok(at.with_timezone(&Utc));
+    }
+    let date = NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_| format!("无法识别的日期: {value}"))?;
+    let date = if end_of_day { date.succ_opt().unwrap_or(date) } else { date };
+    Local
+        .from_local_datetime(&date.and_hms_opt(0, 0, 0).unwrap_or_default())
+        .earliest()
+        .map(|at| at.with_timezone(&Utc))
+        .ok_or_else(|| format!("无法识别的日期: {value}"))
+}
+
+fn parse_time(value: &Value) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value.as_str()?)
+        .ok()
+        .map(|at| at.with_timezone(&Utc))
+}
+
+fn text(value: &Value) -> Option<String> {
+    value.as_str().map(str::to_string)
+}
+
+fn sent_record(line: &str) -> Option<(DateTime<Utc>, SentRecord)> {
+    let payload: Value = serde_json::from_str(line).ok()?;
+    let at = parse_time(&payload["sent_at"])?;
+    Some((
+        at,
+        SentRecord {
+            status: RecordStatus::Sent,
+            email: text(&payload["email"])?,
+            name: text(&payload["teacher_name"]).unwrap_or_default(),
+            campaign_id: text(&payload["job_id"]).unwrap_or_default(),
+            at: at.to_rfc3339(),
+            error: None,
+            operator: text(&payload["operator"]),
+            environment: text(&payload["environment"]),
+            message_id: text(&payload["message_id"]),
+        },
+    ))
+}
+
+fn failed_record(line: &str) -> Option<(DateTime<Utc>, SentRecord)> {
+    let event: Value = serde_json::from_str(line).ok()?;
+    if event["type"] != "recipient_failed" {
+        return None;
+    }
+    let at = parse_time(&event["at"])?;
+    Some((
+        at,
+        SentRecord {
+            status: RecordStatus::Failed,
+            email: text(&event["email"])?,
+            name: text(&event["name"]).unwrap_or_default(),
+            campaign_id: text(&event["job_id"]).unwrap_or_default(),
+            at: at.to_rfc3339(),
+            error: text(&event["error"]),
+            operator: None,
+            environment: None,
+            message_id: None,
+        },
+    ))
+}
+
+/// 可读版记录按行首的本地时间 `[YYYY-MM-DD HH:MM:SS]` 清理：删除早于最早保留记录的行。
+fn purge_text_records(path: &Path, oldest: Option<DateTime<Utc>>) -> Result<(), String> {
+    let oldest = oldest.map(|at| at.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S").to_string());
+    let text = fs::read_to_string(path).map_err(|err| format!("读取发送记录失败: {err}"))?;
+    let kept: String = text
+        .lines()
+        .filter(|line| {
+            let Some(time) = line.strip_prefix('[').and_then(|rest| rest.split(']').next()) else {
+                return true;
+            };
+            oldest.as_deref().is_some_and(|oldest| time >= oldest)
+        })
+        .map(|line| format!("{line}\n"))
+        .collect();
+    replace_file(path, &kept)
+}
+
+/// 只保留 `keep` 返回 true 的行，返回删除的行数；没有删除时不改写文件。
+fn rewrite_lines(path: &Path, mut keep: impl FnMut(&str) -> bool) -> Result<usize, String> {
+    let mut kept = String::new();
+    let mut removed = 0;
+    for_each_line(path, |line| {
+        if line.is_empty() {
+            return;
+        }
+        if keep(line) {
+            kept.push_str(line);
+            kept.push('\n');
+        } else {
+            removed += 1;
+        }
+    })?;
+    if removed > 0 {
+        replace_file(path, &kept)?;
+    }
+    Ok(removed)
+}
+
+/// 可读版记录行的 (本地时间, 邮箱, 任务ID)，用于与 JSONL 记录对应。
+fn text_record_key(line: &str) -> Option<(String, String, String)> {
+    let (time, rest) = line.strip_prefix('[')?.split_once(']')?;
+    let field = |label: &str| {
+        rest.split(" | ")
+            .find_map(|part| part.trim().strip_prefix(label))
+            .map(|value| value.trim().to_string())
+    };
+    Some((time.to_string(), field("邮箱: ")?.to_lowercase(), field("任务: ")?))
+}
+
+/// 先写临时文件再替换，避免清理中途失败时丢失记录。
+fn replace_file(path: &Path, text: &str) -> Result<(), String> {
+    let staging = path.with_extension("purge.tmp");
+    fs::write(&staging, text)
+        .and_then(|_| fs::rename(&staging, path))
+        .map_err(|err| format!("写入发送记录失败: {err}"))
+}
+
+fn for_each_line(path: &Path, mut visit: impl FnMut(&str)) -> Result<(), String> {
+    if !path.exists() {
+        return Ok(());
+    }
+    let file = File::open(path).map_err(|err| format!("读取发送历史失败: {err}"))?;
+    for line in BufReader::new(file).lines() {
+        let line = line.map_err(|err| format!("读取发送历史失败: {err}"))?;
+        visit(line.trim());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        ClearFilter, ClearReport, PurgeReport, RecordStatus, RetentionPolicy, SendStatsQuery, SentHistory,
+        SentRecordQuery,
+    };
+    use chrono::{DateTime, Local, Utc};
+    use std::fs;
+
+    #[test]
+    fn queries_sent_and_failed_records_with_filters() {
+        let dir = std::env::temp_dir().join(format!("bes-sent-history-{}", std::process::id()));
+        let campaigns = dir.join("campaigns");
+        fs::create_dir_all(&campaigns).expect("dir");
+        let sent_store = dir.join("sent_records.jsonl");
+        fs::write(
+            &sent_store,
+            [
+                r#"{"email":"a@pku.edu.cn","teacher_name":"甲","job_id":"job-1","sent_at":"2024-03-01T08:00:00+00:00"}"#,
+                r#"{"email":"b@pku.edu.cn","teacher_name":"乙","job_id":"job-1","sent_at":"2024-03-02T08:00:00+00:00"}"#,
+                "{broken",
+                r#"{"email":"c@tsinghua.edu.cn","teacher_name":"丙","job_id":"job-2","sent_at":"2024-03-03T08:00:00+00:00"}"#,
+            ]
+            .join("\n"),
+        )
+        .expect("sent store");
+        fs::write(
+            campaigns.join("job-1.jsonl"),
+            [
+                r#"{"type":"recipient_sent","job_id":"job-1","email":"a@pku.edu.cn","at":"2024-03-01T08:00:00Z"}"#,
+                r#"{"type":"recipient_failed","job_id":"job-1","email":"d@pku.edu.cn","name":"丁","error":"550 user unknown","at":"2024-03-02T09:00:00Z"}"#,
+            ]
+            .join("\n"),
+        )
+        .expect("campaign log");
+        let history = SentHistory::new(&sent_store, &campaigns);
+
+        let page = history.query(&SentRecordQuery::default()).expect("all");
+        assert_eq!(page.total, 4);
+        let emails: Vec<&str> = page.records.iter().map(|record| record.email.as_str()).collect();
+        assert_eq!(emails, ["c@tsinghua.edu.cn", "d@pku.edu.cn", "b@pku.edu.cn", "a@pku.edu.cn"]);
+
+        let query = SentRecordQuery {
+            status: Some(RecordStatus::Failed),
+            ..SentRecordQuery::default()
+        };
+        let page = history.query(&query).expect("failed");
+        assert_eq!(page.total, 1);
+        assert_eq!(page.records[0].error.as_deref(), Some("550 user unknown"));
+
+        let query = SentRecordQuery {
+            recipient: Some("PKU".to_string()),
+            campaign_id: Some("job-1".to_string()),
+            since: Some("2024-03-01T12:00:00Z".to_string()),
+            offset: 1,
+            limit: 1,
+            ..SentRecordQuery::default()
+        };
+        let page = history.query(&query).expect("filtered");
+        assert_eq!(page.total, 2);
+        assert_eq!(page.records.len(), 1);
+        assert_eq!(page.records[0].email, "b@pku.edu.cn");
+
+        let query = SentRecordQuery {
+            until: Some("not a date".to_string()),
+            ..SentRecordQuery::default()
+        };
+        assert!(history.query(&query).is_err());
+
+        let stats = history.stats(&SendStatsQuery::default()).expect("stats");
+        assert_eq!((stats.sent, stats.failed, stats.per_day.len()), (3, 1, 3));
+        assert_eq!(stats.success_rate, Some(0.75));
+        assert_eq!(stats.failures[0].class, "550");
+        // 只有 job-1 有两次发送，相隔 24 小时。
+        assert_eq!(stats.sent_per_hour, Some(2.0 / 24.0));
+        let query = SendStatsQuery {
+            campaign_id: Some("job-2".to_string()),
+            ..SendStatsQuery::default()
+        };
+        let stats = history.stats(&query).expect("campaign stats");
+        assert_eq!((stats.sent, stats.failed, stats.sent_per_hour), (1, 0, None));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn purges_records_by_age_and_count() {
+        let dir = std::env::temp_dir().join(format!("bes-sent-purge-{}", std::process::id()));
+        let campaigns = dir.join("campaigns");
+        fs::create_dir_all(&campaigns).expect("dir");
+        let sent_store = dir.join("sent_records.jsonl");
+        let records: Vec<String> = (1..=5)
+            .map(|day| {
+                format!(r#"{{"email":"{day}@pku.edu.cn","job_id":"job-1","sent_at":"2024-03-0{day}T08:00:00+00:00"}}"#)
+            })
+            .chain(["{broken".to_string()])
+            .collect();
+        fs::write(&sent_store, records.join("\n")).expect("sent store");
+        fs::write(campaigns.join("job-1.jsonl"), "{}\n").expect("campaign log");
+        fs::File::options()
+            .write(true)
+            .open(campaigns.join("job-1.jsonl"))
+            .and_then(|file| file.set_modified(std::time::SystemTime::UNIX_EPOCH))
+            .expect("mtime");
+        fs::write(campaigns.join("job-1.campaign.json"), "{}").expect("definition");
+        let history = SentHistory::new(&sent_store, &campaigns);
+        let now: DateTime<Utc> = "2024-03-06T00:00:00Z".parse().expect("now");
+
+        let disabled = history.purge(None, &RetentionPolicy::default(), now).expect("disabled");
+        assert_eq!(disabled, PurgeReport::default());
+        let policy = RetentionPolicy {
+            keep_days: None,
+            keep_rows: Some(4),
+        };
+        let report = history.purge(None, &policy, now).expect("rows");
+        assert_eq!((report.removed_records, report.kept_records), (1, 4));
+        // 2024-03-03 08:00 之前的记录超过 3 天。
+        let policy = RetentionPolicy {
+            keep_days: Some(3),
+            keep_rows: Some(1),
+        };
+        let report = history.purge(None, &policy, now).expect("days");
+        assert_eq!((report.removed_records, report.kept_records), (3, 1));
+        let emails: Vec<String> = history
+            .query(&SentRecordQuery::default())
+            .expect("query")
+            .records
+            .into_iter()
+            .map(|record| record.email)
+            .collect();
+        assert_eq!(emails, ["5@pku.edu.cn"]);
+        assert!(fs::read_to_string(&sent_store).expect("read").contains("{broken"));
+        assert!(!campaigns.join("job-1.jsonl").exists());
+        assert!(!campaigns.join("job-1.campaign.json").exists());
+        assert_eq!(report.removed_campaign_logs, 1);
+
+        let invalid = RetentionPolicy {
+            keep_days: Some(0),
+            keep_rows: None,
+        };
+        assert!(history.purge(None, &invalid, now).is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn clears_records_matching_filter() {
+        let dir = std::env::temp_dir().join(format!("bes-sent-clear-{}", std::process::id()));
+        let campaigns = dir.join("campaigns");
+        fs::create_dir_all(&campaigns).expect("dir");
+        let sent_store = dir.join("sent_records.jsonl");
+        let text_path = dir.join("sent_records.txt");
+        let sent = [
+            ("a@pku.edu.cn", "job-1", "2024-03-01T08:00:00Z"),
+            ("b@pku.edu.cn", "job-2", "2024-03-05T08:00:00Z"),
+        ];
+        let mut json_lines = Vec::new();
+        let mut text_lines = vec!["# header".to_string()];
+        for (email, job, at) in sent {
+            json_lines.push(format!(r#"{{"email":"{email}","job_id":"{job}","sent_at":"{at}"}}"#));
+            let local = at.parse::<DateTime<Utc>>().expect("at").with_timezone(&Local);
+            let local = local.format("%Y-%m-%d %H:%M:%S");
+            text_lines.push(format!("[{local}] 发送成功 | 姓名: 甲 | 邮箱: {email} | 任务: {job}"));
+        }
+        fs::write(&sent_store, json_lines.join("\n")).expect("sent store");
+        fs::write(&text_path, text_lines.join("\n")).expect("text");
+        fs::write(
+            campaigns.join("job-1.jsonl"),
+            [
+                r#"{"type":"job_started","job_id":"job-1","at":"2024-03-01T07:59:00Z"}"#,
+                r#"{"type":"recipient_failed","job_id":"job-1","email":"c@pku.edu.cn","error":"550","at":"2024-03-01T08:01:00Z"}"#,
+            ]
+            .join("\n"),
+        )
+        .expect("campaign log");
+        let history = SentHistory::new(&sent_store, &campaigns);
+
+        let filter = ClearFilter {
+            before: Some("2024-03-03T00:00:00Z".to_string()),
+            status: Some(RecordStatus::Sent),
+            ..ClearFilter::default()
+        };
+        let report = history.clear(Some(&text_path), &filter).expect("clear sent");
+        assert_eq!(report, ClearReport { removed_sent: 1, removed_failed: 0 });
+        let text = fs::read_to_string(&text_path).expect("text");
+        assert!(text.starts_with("# header") && !text.contains("a@pku.edu.cn") && text.contains("b@pku.edu.cn"));
+
+        let filter = ClearFilter {
+            campaign_id: Some("job-1".to_string()),
+            ..ClearFilter::default()
+        };
+        let report = history.clear(Some(&text_path), &filter).expect("clear campaign");
+        assert_eq!(report, ClearReport { removed_sent: 0, removed_failed: 1 });
+        let page = history.query(&SentRecordQuery::default()).expect("query");
+        assert_eq!(page.total, 1);
+        assert_eq!(page.records[0].email, "b@pku.edu.cn");
+        assert!(fs::read_to_string(campaigns.join("job-1.jsonl")).expect("log").contains("job_started"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
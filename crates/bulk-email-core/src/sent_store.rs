@@ -0,0 +1,267 @@
+use crate::environment::Environment;
+use chrono::{Local, SecondsFormat, Utc};
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+const TEXT_HEADER: &str = "# Bulk-Email-Sender 发送记录（可读版）\n# 格式: 时间 | 姓名 | 邮箱 | 任务ID\n";
+
+/// 一封邮件的实际投递信封：发件人与全部收件地址（含抄送、密送）。
+pub struct SentEnvelope<'a> {
+    pub from: &'a str,
+    pub cc: &'a [String],
+    pub bcc: &'a [String],
+    /// 附件因体积超限改为下载链接发送。
+    pub attachments_linked: bool,
+    /// 批量密送模式下收件人所在的批次。
+    pub batch: Option<SentBatch<'a>>,
+    /// 邮件的 Message-ID，用于关联退信。
+    pub message_id: Option<&'a str>,
+    /// 本机 MTA 返回的队列号，便于在 MTA 日志中追踪。
+    pub queue_id: Option<&'a str>,
+}
+
+/// 批量密送的批次信息：同一批次的收件人共用一封邮件。
+#[derive(Clone, Copy)]
+pub struct SentBatch<'a> {
+    pub id: &'a str,
+    pub number: usize,
+    pub size: usize,
+    /// 该批邮件 To 中显示的地址。
+    pub to: &'a str,
+    /// 同一批次共用的 Message-ID。
+    pub message_id: &'a str,
+}
+
+/// 与 Python `SentStore` 共用同一份 JSONL 记录格式，两种引擎可互相识别已发送邮箱。
+pub struct SentStore {
+    path: PathBuf,
+    text_path: Option<PathBuf>,
+    emails: HashSet<String>,
+    operator: Option<String>,
+    environment: Option<Environment>,
+}
+
+impl SentStore {
+    pub fn open(path: &Path, text_path: Option<&Path>) -> Result<Self, String> {
+        for target in std::iter::once(path).chain(text_path) {
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent).map_err(|err| format!("创建发送记录目录失败: {err}"))?;
+            }
+        }
+        let emails = load_emails(path)?;
+        Ok(SentStore {
+            path: path.to_path_buf(),
+            text_path: text_path.map(Path::to_path_buf),
+            emails,
+            operator: None,
+            environment: None,
+        })
+    }
+
+    /// 共享数据目录中区分记录来源：每条记录附带操作人。
+    pub fn with_operator(mut self, operator: Option<String>) -> Self {
+        self.operator = operator.map(|value| value.trim().to_string()).filter(|value| !value.is_empty());
+        self
+    }
+
+    /// 每条记录附带任务的环境标签，便于区分测试发送与生产发送。
+    pub fn with_environment(mut self, environment: Option<Environment>) -> Self {
+        self.environment = environment;
+        self
+    }
+
+    pub fn is_sent(&self, email: &str) -> bool {
+        self.emails.contains(&email.trim().to_lowercase())
+    }
+
+    pub fn append(
+        &mut self,
+        email: &str,
+        teacher_name: &str,
+        job_id: &str,
+        envelope: &SentEnvelope<'_>,
+    ) -> Result<(), String> {
+        let normalized_email = email.trim().to_lowercase();
+        let sent_at = Utc::now();
+        let mut payload = json!({
+            "email": normalized_email,
+            "teacher_name": teacher_name,
+            "job_id": job_id,
+            "sent_at": sent_at.to_rfc3339_opts(SecondsFormat::Micros, false),
+            "envelope": {
+                "from": envelope.from.trim(),
+                "to": [normalized_email],
+                "cc": envelope.cc,
+                "bcc": envelope.bcc,
+            },
+        });
+        if let Some(operator) = &self.operator {
+            payload["operator"] = json!(operator);
+        }
+        if let Some(environment) = self.environment {
+            payload["environment"] = json!(environment.as_str());
+        }
+        if let Some(message_id) = envelope.message_id {
+            payload["message_id"] = json!(message_id);
+        }
+        if let Some(queue_id) = envelope.queue_id {
+            payload["queue_id"] = json!(queue_id);
+        }
+        if envelope.attachments_linked {
+            payload["attachments_linked"] = json!(true);
+        }
+        if let Some(batch) = &envelope.batch {
+            payload["batch"] = json!({
+                "id": batch.id,
+                "number": batch.number,
+                "size": batch.size,
+                "to": batch.to,
+            });
+        }
+        append_line(&self.path, &payload.to_string())?;
+
+        if let Some(text_path) = &self.text_path {
+            let needs_header = fs::metadata(text_path).map(|meta| meta.len() == 0).unwrap_or(true);
+            let local_time = sent_at.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S");
+            let mut line = String::new();
+            if needs_header {
+                line.push_str(TEXT_HEADER);
+            }
+            line.push_str(&format!(
+                "[{local_time}] 发送成功 | 姓名: {teacher_name} | 邮箱: {normalized_email} | 任务: {job_id}"
+            ));
+            if !envelope.cc.is_empty() {
+                line.push_str(&format!(" | 抄送: {}", envelope.cc.join(", ")));
+            }
+            if !envelope.bcc.is_empty() {
+                line.push_str(&format!(" | 密送: {}", envelope.bcc.join(", ")));
+            }
+            if envelope.attachments_linked {
+                line.push_str(" | 附件: 下载链接");
+            }
+            if let Some(batch) = &envelope.batch {
+                line.push_str(&format!(" | 批次: {}（{} 人）", batch.id, batch.size));
+            }
+            if let Some(queue_id) = envelope.queue_id {
+                line.push_str(&format!(" | 队列号: {queue_id}"));
+            }
+            if let Some(operator) = &self.operator {
+                line.push_str(&format!(" | 操作人: {operator}"));
+            }
+            if let Some(environment) = self.environment {
+                line.push_str(&format!(" | 环境: {}", environment.display_name()));
+            }
+            append_line(text_path, &line)?;
+        }
+
+        self.emails.insert(normalized_email);
+        Ok(())
+    }
+}
+
+fn load_emails(path: &Path) -> Result<HashSet<String>, String> {
+    let mut emails = HashSet::new();
+    if !path.exists() {
+        return Ok(emails);
+    }
+    let file = File::open(path).map_err(|err| format!("读取发送记录失败: {err}"))?;
+    for line in BufReader::new(file).lines() {
+        let line = line.map_err(|err| format!("读取发送记录失败: {err}"))?;
+        let Ok(payload) = serde_json::from_str::<Value>(line.trim()) else {
+            continue;
+        };
+        let email = payload
+            .get("email")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase();
+        if !email.is_empty() {
+            emails.insert(email);
+        }
+    }
+    Ok(emails)
+}
+
+fn append_line(path: &Path, line: &str) -> Result<(), String> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|err| format!("写入发送记录失败: {err}"))?;
+    writeln!(file, "{line}").map_err(|err| format!("写入发送记录失败: {err}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SentBatch, SentEnvelope, SentStore, TEXT_HEADER};
+    use crate::environment::Environment;
+    use serde_json::Value;
+    use std::fs;
+
+    fn envelope<'a>(cc: &'a [String], batch: Option<SentBatch<'a>>) -> SentEnvelope<'a> {
+        SentEnvelope {
+            from: " me@example.com ",
+            cc,
+            bcc: &[],
+            attachments_linked: false,
+            batch,
+            message_id: Some("<m1@example.com>"),
+            queue_id: None,
+        }
+    }
+
+    #[test]
+    fn appends_json_and_text_records_and_reloads_sent_emails() {
+        let dir = std::env::temp_dir().join(format!("bes-sent-store-{}", std::process::id()));
+        let path = dir.join("records/sent_records.jsonl");
+        let text_path = dir.join("records/sent_records.txt");
+        let mut store = SentStore::open(&path, Some(&text_path))
+            .expect("open")
+            .with_operator(Some(" 小王 ".to_string()))
+            .with_environment(Some(Environment::Test));
+        assert!(!store.is_sent("a@pku.edu.cn"));
+        let cc = ["boss@pku.edu.cn".to_string()];
+        store.append(" A@PKU.edu.cn ", "张老师", "job-1", &envelope(&cc, None)).expect("append");
+        let batch = SentBatch {
+            id: "batch-1",
+            number: 1,
+            size: 2,
+            to: "me@example.com",
+            message_id: "<b1@example.com>",
+        };
+        store.append("b@pku.edu.cn", "李老师", "job-1", &envelope(&[], Some(batch))).expect("append");
+        assert!(store.is_sent("a@pku.edu.cn") && store.is_sent(" B@pku.edu.cn"));
+
+        let records: Vec<Value> = fs::read_to_string(&path)
+            .expect("json records")
+            .lines()
+            .map(|line| serde_json::from_str(line).expect("json line"))
+            .collect();
+        assert_eq!(records[0]["email"], "a@pku.edu.cn");
+        assert_eq!(records[0]["envelope"]["from"], "me@example.com");
+        assert_eq!(records[0]["envelope"]["cc"][0], "boss@pku.edu.cn");
+        assert_eq!((&records[0]["operator"], &records[0]["environment"]), (&"小王".into(), &"test".into()));
+        assert_eq!(records[0]["message_id"], "<m1@example.com>");
+        assert!(records[0].get("batch").is_none() && records[0].get("queue_id").is_none());
+        assert_eq!((&records[1]["batch"]["id"], &records[1]["batch"]["size"]), (&"batch-1".into(), &2.into()));
+
+        let text = fs::read_to_string(&text_path).expect("text records");
+        assert_eq!(text.matches(TEXT_HEADER).count(), 1);
+        let lines: Vec<&str> = text.lines().skip(TEXT_HEADER.lines().count()).collect();
+        assert!(lines[0].contains("邮箱: a@pku.edu.cn | 任务: job-1 | 抄送: boss@pku.edu.cn"), "{}", lines[0]);
+        assert!(lines[0].contains("操作人: 小王") && lines[0].contains("环境: "), "{}", lines[0]);
+        assert!(lines[1].contains("批次: batch-1（2 人）"), "{}", lines[1]);
+
+        // 重新打开时从 JSONL 载入已发送邮箱，无法解析的行跳过。
+        let mut file = fs::OpenOptions::new().append(true).open(&path).expect("reopen for append");
+        std::io::Write::write_all(&mut file, b"not json\n{\"email\":\" C@pku.edu.cn \"}\n").expect("append raw");
+        let reopened = SentStore::open(&path, None).expect("reopen").with_operator(Some("  ".to_string()));
+        let _ = fs::remove_dir_all(&dir);
+        assert!(["a@pku.edu.cn", "b@pku.edu.cn", "c@pku.edu.cn"].iter().all(|email| reopened.is_sent(email)));
+        assert!(reopened.operator.is_none());
+    }
+}
@@ -0,0 +1,504 @@
+use cbc::cipher::block_padding::Pkcs7;
+use cbc::cipher::{BlockDecryptMut, KeyIvInit};
+use chrono::{DateTime, Utc};
+use cms::builder::{
+    create_signing_time_attribute, ContentEncryptionAlgorithm, EnvelopedDataBuilder, KeyEncryptionInfo,
+    KeyTransRecipientInfoBuilder, SignedDataBuilder, SignerInfoBuilder,
+};
+use cms::cert::{CertificateChoices, IssuerAndSerialNumber};
+use cms::content_info::ContentInfo;
+use cms::encrypted_data::EncryptedData;
+use cms::enveloped_data::RecipientIdentifier;
+use cms::signed_data::{EncapsulatedContentInfo, SignerIdentifier};
+use const_oid::db::rfc3280::EMAIL_ADDRESS;
+use const_oid::db::rfc5911::{ID_DATA, ID_ENCRYPTED_DATA, ID_ENVELOPED_DATA};
+use const_oid::db::rfc5912::ID_SHA_256;
+use der::asn1::{ContextSpecific, OctetString};
+use der::pem::LineEnding;
+use der::{Any, Decode, DecodePem, Encode, EncodePem};
+use pkcs12::cert_type::CertBag;
+use pkcs12::kdf::{derive_key_utf8, Pkcs12KeyType};
+use pkcs12::pbe_params::{EncryptedPrivateKeyInfo, Pkcs12PbeParams};
+use pkcs12::pfx::Pfx;
+use pkcs12::safe_bag::SafeContents;
+use pkcs12::{
+    PKCS_12_CERT_BAG_OID, PKCS_12_KEY_BAG_OID, PKCS_12_PBE_WITH_SHAAND3_KEY_TRIPLE_DES_CBC, PKCS_12_PKCS8_KEY_BAG_OID,
+};
+use pkcs5::pbes2::PBES2_OID;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rsa::pkcs1v15::{Signature, SigningKey};
+use rsa::pkcs8::{DecodePrivateKey, DecodePublicKey};
+use rsa::{RsaPrivateKey, RsaPublicKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+use x509_cert::ext::pkix::name::GeneralName;
+use x509_cert::ext::pkix::SubjectAltName;
+use x509_cert::spki::AlgorithmIdentifierOwned;
+use x509_cert::Certificate;
+
+/// 已导入的签名证书（保存在本机应用设置中，不随共享数据目录同步）。
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct SmimeSettings {
+    #[serde(default)]
+    pub pkcs12_path: Option<String>,
+    #[serde(default)]
+    pub password: String,
+}
+
+/// `start_send` 载荷中的 S/MIME 开关。
+#[derive(Deserialize, Serialize, Clone, Copy, Default, Debug, PartialEq)]
+pub struct SmimeOptions {
+    #[serde(default)]
+    pub sign: bool,
+    /// 为已导入证书的收件人加密；缺少任一收件人证书时该封邮件不加密。
+    #[serde(default)]
+    pub encrypt: bool,
+}
+
+impl SmimeOptions {
+    pub fn enabled(&self) -> bool {
+        self.sign || self.encrypt
+    }
+}
+
+/// 证书摘要，供前端展示与导入前校验。
+#[derive(Serialize, Clone, Debug)]
+pub struct SmimeCertificateInfo {
+    pub subject: String,
+    pub issuer: String,
+    pub serial: String,
+    pub email_addresses: Vec<String>,
+    pub not_before: String,
+    pub not_after: String,
+    pub fingerprint_sha256: String,
+    pub problems: Vec<String>,
+}
+
+/// 从 PKCS#12 中取出的签名身份：证书、证书链与私钥。
+pub struct SmimeIdentity {
+    pub certificate: Certificate,
+    pub chain: Vec<Certificate>,
+    key: RsaPrivateKey,
+}
+
+/// 发送任务使用的 S/MIME 上下文。
+pub struct SmimeContext {
+    pub signer: Option<SmimeIdentity>,
+    pub recipient_certs: HashMap<String, Certificate>,
+    pub encrypt: bool,
+}
+
+impl SmimeContext {
+    /// 所有投递地址（收件、抄送、密送）都有证书时返回加密用的证书列表，并附带发件人自己的证书。
+    pub fn encryption_certs(&self, addresses: &[&str]) -> Option<Vec<&Certificate>> {
+        if !self.encrypt {
+            return None;
+        }
+        let mut certs = addresses
+            .iter()
+            .map(|address| self.recipient_certs.get(&address.trim().to_lowercase()))
+            .collect::<Option<Vec<&Certificate>>>()?;
+        if let Some(signer) = &self.signer {
+            certs.push(&signer.certificate);
+        }
+        Some(certs)
+    }
+}
+
+/// 收件人公钥证书（邮箱 → PEM），保存在数据目录中，团队共享。
+#[derive(Serialize, Deserialize, Default)]
+pub struct RecipientCertStore {
+    #[serde(default)]
+    certificates: BTreeMap<String, String>,
+}
+
+impl RecipientCertStore {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let text = fs::read_to_string(path).map_err(|err| format!("读取收件人证书失败: {err}"))?;
+        serde_json::from_str(&text).map_err(|err| format!("收件人证书文件格式错误: {err}"))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let text = serde_json::to_string_pretty(self).map_err(|err| err.to_string())?;
+        fs::write(path, text).map_err(|err| format!("写入收件人证书失败: {err}"))
+    }
+
+    /// 按证书中的邮箱登记；同一邮箱的新证书覆盖旧证书。
+    pub fn import(&mut self, certificate: &Certificate) -> Result<SmimeCertificateInfo, String> {
+        let info = certificate_info(certificate);
+        if info.email_addresses.is_empty() {
+            return Err("证书中没有邮箱地址，无法用于加密".to_string());
+        }
+        let pem = certificate
+            .to_pem(LineEnding::LF)
+            .map_err(|err| format!("证书编码失败: {err}"))?;
+        for email in &info.email_addresses {
+            self.certificates.insert(email.clone(), pem.clone());
+        }
+        Ok(info)
+    }
+
+    pub fn certificates(&self) -> HashMap<String, Certificate> {
+        self.certificates
+            .iter()
+            .filter_map(|(email, pem)| Some((email.clone(), Certificate::from_pem(pem).ok()?)))
+            .collect()
+    }
+
+    pub fn list(&self) -> Vec<SmimeCertificateInfo> {
+        let mut seen = Vec::new();
+        let mut infos = Vec::new();
+        for pem in self.certificates.values() {
+            if seen.contains(pem) {
+                continue;
+            }
+            seen.push(pem.clone());
+            if let Ok(certificate) = Certificate::from_pem(pem) {
+                infos.push(certificate_info(&certificate));
+            }
+        }
+        infos
+    }
+}
+
+/// 解析 PKCS#12（.p12 / .pfx），支持 PBES2（AES）与传统 3DES 加密。
+pub fn load_pkcs12(bytes: &[u8], password: &str) -> Result<SmimeIdentity, String> {
+    let pfx = Pfx::from_der(bytes).map_err(|err| format!("PKCS#12 文件格式错误: {err}"))?;
+    if pfx.auth_safe.content_type != ID_DATA {
+        return Err("不支持使用公钥保护的 PKCS#12 文件".to_string());
+    }
+    let auth_safe: Vec<ContentInfo> = Vec::from_der(octet_string_bytes(&pfx.auth_safe.content)?.as_slice())
+        .map_err(|err| format!("PKCS#12 内容格式错误: {err}"))?;
+
+    let mut certificates = Vec::new();
+    let mut keys = Vec::new();
+    for content in auth_safe {
+        let safe_contents = if content.content_type == ID_DATA {
+            octet_string_bytes(&content.content)?
+        } else if content.content_type == ID_ENCRYPTED_DATA {
+            let encrypted = EncryptedData::from_der(&to_der(&content.content)?)
+                .map_err(|err| format!("PKCS#12 加密内容格式错误: {err}"))?;
+            let ciphertext = encrypted
+                .enc_content_info
+                .encrypted_content
+                .ok_or_else(|| "PKCS#12 加密内容为空".to_string())?;
+            decrypt_pbe(&encrypted.enc_content_info.content_enc_alg, password, ciphertext.as_bytes())?
+        } else {
+            continue;
+        };
+        let bags = SafeContents::from_der(&safe_contents).map_err(|err| format!("PKCS#12 内容格式错误: {err}"))?;
+        for bag in bags {
+            if bag.bag_id == PKCS_12_CERT_BAG_OID {
+                let cert_bag: ContextSpecific<CertBag> =
+                    ContextSpecific::from_der(&bag.bag_value).map_err(|err| format!("证书格式错误: {err}"))?;
+                certificates.push(
+                    Certificate::from_der(cert_bag.value.cert_value.as_bytes())
+                        .map_err(|err| format!("证书格式错误: {err}"))?,
+                );
+            } else if bag.bag_id == PKCS_12_PKCS8_KEY_BAG_OID {
+                let shrouded: ContextSpecific<EncryptedPrivateKeyInfo> =
+                    ContextSpecific::from_der(&bag.bag_value).map_err(|err| format!("私钥格式错误: {err}"))?;
+                let plaintext = decrypt_pbe(
+                    &shrouded.value.encryption_algorithm,
+                    password,
+                    shrouded.value.encrypted_data.as_bytes(),
+                )?;
+                keys.push(parse_private_key(&plaintext)?);
+            } else if bag.bag_id == PKCS_12_KEY_BAG_OID {
+                let plain: ContextSpecific<Any> =
+                    ContextSpecific::from_der(&bag.bag_value).map_err(|err| format!("私钥格式错误: {err}"))?;
+                keys.push(parse_private_key(&to_der(&plain.value)?)?);
+            }
+        }
+    }
+
+    let key = keys.into_iter().next().ok_or_else(|| "PKCS#12 文件中没有私钥".to_string())?;
+    let public_key = key.to_public_key();
+    let position = certificates
+        .iter()
+        .position(|certificate| rsa_public_key(certificate).is_ok_and(|candidate| candidate == public_key))
+        .ok_or_else(|| "PKCS#12 文件中没有与私钥匹配的证书".to_string())?;
+    let certificate = certificates.remove(position);
+    Ok(SmimeIdentity {
+        certificate,
+        chain: certificates,
+        key,
+    })
+}
+
+/// 读取 PEM 或 DER 编码的 X.509 证书。
+pub fn parse_certificate(bytes: &[u8]) -> Result<Certificate, String> {
+    if bytes.starts_with(b"-----BEGIN") {
+        Certificate::from_pem(bytes).map_err(|err| format!("证书格式错误: {err}"))
+    } else {
+        Certificate::from_der(bytes).map_err(|err| format!("证书格式错误: {err}"))
+    }
+}
+
+pub fn certificate_info(certificate: &Certificate) -> SmimeCertificateInfo {
+    let tbs = &certificate.tbs_certificate;
+    let not_before = to_datetime(tbs.validity.not_before.to_unix_duration().as_secs());
+    let not_after = to_datetime(tbs.validity.not_after.to_unix_duration().as_secs());
+    let now = Utc::now();
+    let mut problems = Vec::new();
+    if now < not_before {
+        problems.push("证书尚未生效".to_string());
+    }
+    if now > not_after {
+        problems.push("证书已过期".to_string());
+    }
+    let email_addresses = certificate_emails(certificate);
+    if email_addresses.is_empty() {
+        problems.push("证书中没有邮箱地址".to_string());
+    }
+    let fingerprint = certificate
+        .to_der()
+        .map(|der| format!("{:x}", Sha256::digest(der)))
+        .unwrap_or_default();
+    SmimeCertificateInfo {
+        subject: tbs.subject.to_string(),
+        issuer: tbs.issuer.to_string(),
+        serial: tbs.serial_number.to_string(),
+        email_addresses,
+        not_before: not_before.to_rfc3339(),
+        not_after: not_after.to_rfc3339(),
+        fingerprint_sha256: fingerprint,
+        problems,
+    }
+}
+
+/// 证书中的邮箱：主题中的 emailAddress 与 SubjectAltName 中的 rfc822Name。
+pub fn certificate_emails(certificate: &Certificate) -> Vec<String> {
+    let mut emails: Vec<String> = certificate
+        .tbs_certificate
+        .subject
+        .0
+        .iter()
+        .flat_map(|rdn| rdn.0.iter())
+        .filter(|attribute| attribute.oid == EMAIL_ADDRESS)
+        .map(|attribute| String::from_utf8_lossy(attribute.value.value()).trim().to_lowercase())
+        .collect();
+    if let Ok(Some((_, alt_names))) = certificate.tbs_certificate.get::<SubjectAltName>() {
+        for name in alt_names.0 {
+            if let GeneralName::Rfc822Name(email) = name {
+                emails.push(email.to_string().trim().to_lowercase());
+            }
+        }
+    }
+    emails.retain(|email| !email.is_empty());
+    emails.sort();
+    emails.dedup();
+    emails
+}
+
+/// 生成 `multipart/signed` 所需的分离式 CMS 签名（SHA-256 / RSA PKCS#1 v1.5）。
+pub fn sign_detached(identity: &SmimeIdentity, content: &[u8]) -> Result<Vec<u8>, String> {
+    let digest = Sha256::digest(content);
+    let encapsulated = EncapsulatedContentInfo {
+        econtent_type: ID_DATA,
+        econtent: None,
+    };
+    let digest_algorithm = AlgorithmIdentifierOwned {
+        oid: ID_SHA_256,
+        parameters: None,
+    };
+    let signer = SigningKey::<Sha256>::new(identity.key.clone());
+    let mut signer_info = SignerInfoBuilder::new(
+        &signer,
+        SignerIdentifier::IssuerAndSerialNumber(issuer_and_serial(&identity.certificate)),
+        digest_algorithm.clone(),
+        &encapsulated,
+        Some(digest.as_slice()),
+    )
+    .map_err(|err| format!("S/MIME 签名失败: {err}"))?;
+    signer_info
+        .add_signed_attribute(create_signing_time_attribute().map_err(|err| format!("S/MIME 签名失败: {err}"))?)
+        .map_err(|err| format!("S/MIME 签名失败: {err}"))?;
+
+    let mut builder = SignedDataBuilder::new(&encapsulated);
+    builder
+        .add_digest_algorithm(digest_algorithm)
+        .map_err(|err| format!("S/MIME 签名失败: {err}"))?;
+    for certificate in std::iter::once(&identity.certificate).chain(&identity.chain) {
+        builder
+            .add_certificate(CertificateChoices::Certificate(certificate.clone()))
+            .map_err(|err| format!("S/MIME 签名失败: {err}"))?;
+    }
+    builder
+        .add_signer_info::<_, Signature>(signer_info)
+        .map_err(|err| format!("S/MIME 签名失败: {err}"))?;
+    let signed = builder.build().map_err(|err| format!("S/MIME 签名失败: {err}"))?;
+    signed.to_der().map_err(|err| format!("S/MIME 签名编码失败: {err}"))
+}
+
+/// 用收件人公钥加密 MIME 实体，生成 `application/pkcs7-mime` 的 EnvelopedData（AES-256-CBC）。
+pub fn encrypt_enveloped(content: &[u8], recipients: &[&Certificate]) -> Result<Vec<u8>, String> {
+    let keys = recipients
+        .iter()
+        .map(|certificate| Ok((issuer_and_serial(certificate), rsa_public_key(certificate)?)))
+        .collect::<Result<Vec<_>, String>>()?;
+    let mut rngs: Vec<StdRng> = keys.iter().map(|_| StdRng::from_entropy()).collect();
+    let mut builder = EnvelopedDataBuilder::new(None, content, ContentEncryptionAlgorithm::Aes256Cbc, None)
+        .map_err(|err| format!("S/MIME 加密失败: {err}"))?;
+    for ((rid, key), rng) in keys.into_iter().zip(rngs.iter_mut()) {
+        let recipient = KeyTransRecipientInfoBuilder::new(
+            RecipientIdentifier::IssuerAndSerialNumber(rid),
+            KeyEncryptionInfo::Rsa(key),
+            rng,
+        )
+        .map_err(|err| format!("S/MIME 加密失败: {err}"))?;
+        builder
+            .add_recipient_info(recipient)
+            .map_err(|err| format!("S/MIME 加密失败: {err}"))?;
+    }
+    let enveloped = builder
+        .build_with_rng(&mut StdRng::from_entropy())
+        .map_err(|err| format!("S/MIME 加密失败: {err}"))?;
+    let content_info = ContentInfo {
+        content_type: ID_ENVELOPED_DATA,
+        content: Any::encode_from(&enveloped).map_err(|err| format!("S/MIME 加密编码失败: {err}"))?,
+    };
+    content_info.to_der().map_err(|err| format!("S/MIME 加密编码失败: {err}"))
+}
+
+fn decrypt_pbe(algorithm: &AlgorithmIdentifierOwned, password: &str, ciphertext: &[u8]) -> Result<Vec<u8>, String> {
+    let parameters = algorithm
+        .parameters
+        .as_ref()
+        .ok_or_else(|| "PKCS#12 加密参数缺失".to_string())?;
+    let parameters = to_der(parameters)?;
+    if algorithm.oid == PBES2_OID {
+        let params =
+            pkcs5::pbes2::Parameters::from_der(&parameters).map_err(|err| format!("PKCS#12 加密参数错误: {err}"))?;
+        return params
+            .decrypt(password, ciphertext)
+            .map_err(|_| "证书密码错误或文件已损坏".to_string());
+    }
+    if algorithm.oid == PKCS_12_PBE_WITH_SHAAND3_KEY_TRIPLE_DES_CBC {
+        let params = Pkcs12PbeParams::from_der(&parameters).map_err(|err| format!("PKCS#12 加密参数错误: {err}"))?;
+        let salt = params.salt.as_bytes();
+        let derive = |kind, length| {
+            derive_key_utf8::<sha1::Sha1>(password, salt, kind, params.iterations, length)
+                .map_err(|err| format!("证书密码无效: {err}"))
+        };
+        let key = derive(Pkcs12KeyType::EncryptionKey, 24)?;
+        let iv = derive(Pkcs12KeyType::Iv, 8)?;
+        return cbc::Decryptor::<des::TdesEde3>::new_from_slices(&key, &iv)
+            .map_err(|err| format!("PKCS#12 解密失败: {err}"))?
+            .decrypt_padded_vec_mut::<Pkcs7>(ciphertext)
+            .map_err(|_| "证书密码错误或文件已损坏".to_string());
+    }
+    Err(format!(
+        "不支持的 PKCS#12 加密算法（{}），请使用 AES 或 3DES 重新导出证书",
+        algorithm.oid
+    ))
+}
+
+fn parse_private_key(der: &[u8]) -> Result<RsaPrivateKey, String> {
+    RsaPrivateKey::from_pkcs8_der(der).map_err(|_| "仅支持 RSA 私钥的 S/MIME 证书".to_string())
+}
+
+fn rsa_public_key(certificate: &Certificate) -> Result<RsaPublicKey, String> {
+    let spki = to_der(&certificate.tbs_certificate.subject_public_key_info)?;
+    RsaPublicKey::from_public_key_der(&spki).map_err(|_| "仅支持 RSA 公钥的 S/MIME 证书".to_string())
+}
+
+fn issuer_and_serial(certificate: &Certificate) -> IssuerAndSerialNumber {
+    IssuerAndSerialNumber {
+        issuer: certificate.tbs_certificate.issuer.clone(),
+        serial_number: certificate.tbs_certificate.serial_number.clone(),
+    }
+}
+
+fn octet_string_bytes(content: &Any) -> Result<Vec<u8>, String> {
+    let octets = OctetString::from_der(&to_der(content)?).map_err(|err| format!("PKCS#12 内容格式错误: {err}"))?;
+    Ok(octets.into_bytes())
+}
+
+fn to_der(value: &impl Encode) -> Result<Vec<u8>, String> {
+    value.to_der().map_err(|err| format!("DER 编码失败: {err}"))
+}
+
+fn to_datetime(secs: u64) -> DateTime<Utc> {
+    DateTime::from_timestamp(secs as i64, 0).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{certificate_emails, load_pkcs12, parse_certificate, RecipientCertStore, SmimeContext};
+    use crate::message_builder::{build_email_message, MessageContent};
+    use std::collections::{BTreeMap, HashMap};
+
+    const SENDER_AES: &[u8] = include_bytes!("../tests/fixtures/smime/sender_aes.p12");
+    const SENDER_3DES: &[u8] = include_bytes!("../tests/fixtures/smime/sender_3des.p12");
+    const RECIPIENT_PEM: &[u8] = include_bytes!("../tests/fixtures/smime/recipient.pem");
+
+    #[test]
+    fn loads_modern_and_legacy_pkcs12() {
+        for bytes in [SENDER_AES, SENDER_3DES] {
+            let identity = load_pkcs12(bytes, "secret").expect("load pkcs12");
+            assert_eq!(certificate_emails(&identity.certificate), vec!["sender@example.com"]);
+            assert!(identity.chain.is_empty());
+            assert_eq!(load_pkcs12(bytes, "wrong").err().as_deref(), Some("证书密码错误或文件已损坏"));
+        }
+    }
+
+    #[test]
+    fn encrypts_only_when_every_recipient_has_a_certificate() {
+        let recipient = parse_certificate(RECIPIENT_PEM).expect("recipient cert");
+        let mut store = RecipientCertStore::default();
+        let info = store.import(&recipient).expect("import recipient");
+        assert_eq!(info.email_addresses, vec!["teacher@example.com"]);
+
+        let context = SmimeContext {
+            signer: Some(load_pkcs12(SENDER_AES, "secret").expect("load pkcs12")),
+            recipient_certs: store.certificates(),
+            encrypt: true,
+        };
+        assert_eq!(context.encryption_certs(&["Teacher@Example.com"]).map(|certs| certs.len()), Some(2));
+        assert!(context.encryption_certs(&["teacher@example.com", "cc@example.com"]).is_none());
+
+        let build = |cc: &[String]| {
+            let message = build_email_message(&MessageContent {
+                sender_email: "sender@example.com",
+                sender_name: "Sender",
+                recipient_email: "teacher@example.com",
+                subject: "Hello",
+                body_text: "Confidential body",
+                body_html: None,
+                attachments: &[],
+                inline_images: &[],
+                headers: &BTreeMap::new(),
+                cc,
+                bcc: &[],
+                smime: Some(&context),
+                return_path: None,
+                ascii_attachment_names: false,
+                message_id: None,
+                read_receipt_to: None,
+            })
+            .expect("build message");
+            String::from_utf8(message.formatted()).expect("utf8 message")
+        };
+        let encrypted = build(&[]);
+        assert!(encrypted.contains("application/pkcs7-mime; smime-type=enveloped-data"));
+        assert!(!encrypted.contains("Confidential body"));
+        let signed_only = build(&["cc@example.com".to_string()]);
+        assert!(signed_only.contains("multipart/signed"));
+        assert!(signed_only.contains("application/pkcs7-signature"));
+        assert!(signed_only.contains("Confidential body"));
+        let disabled = SmimeContext {
+            signer: None,
+            recipient_certs: HashMap::new(),
+            encrypt: false,
+        };
+        assert!(disabled.encryption_certs(&[]).is_none());
+    }
+}
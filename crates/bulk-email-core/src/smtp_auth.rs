@@ -0,0 +1,349 @@
+use crate::smtp_client::SendFailure;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine as _;
+use hmac::{Hmac, Mac};
+use lettre::transport::smtp::authentication::{Credentials, Mechanism};
+use lettre::transport::smtp::client::SmtpConnection;
+use lettre::transport::smtp::response::Response;
+use md4::{Digest, Md4};
+use md5::Md5;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 自动选择认证方式时的优先顺序（与 lettre 默认的 PLAIN、LOGIN 一致，其后再尝试 CRAM-MD5、NTLM）。
+const AUTO_ORDER: [AuthMechanism; 4] =
+    [AuthMechanism::Plain, AuthMechanism::Login, AuthMechanism::CramMd5, AuthMechanism::Ntlm];
+
+/// NTLM 协商标志：UNICODE | OEM | REQUEST_TARGET | NTLM | ALWAYS_SIGN | EXTENDED_SESSIONSECURITY | TARGET_INFO | 128 | 56。
+const NTLM_FLAGS: u32 = 0xa088_8207;
+const NTLM_SIGNATURE: &[u8; 8] = b"NTLMSSP\0";
+/// Windows FILETIME 纪元（1601-01-01）与 Unix 纪元之间的 100 纳秒间隔数。
+const FILETIME_UNIX_OFFSET: u64 = 116_444_736_000_000_000;
+
+/// SMTP AUTH 认证方式；未指定时按服务器声明自动选择。
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthMechanism {
+    Plain,
+    Login,
+    CramMd5,
+    /// Exchange 本地部署常用；用户名可写作 `DOMAIN\user`。
+    Ntlm,
+}
+
+impl AuthMechanism {
+    /// EHLO `AUTH` 行中的名称。
+    pub fn name(self) -> &'static str {
+        match self {
+            AuthMechanism::Plain => "PLAIN",
+            AuthMechanism::Login => "LOGIN",
+            AuthMechanism::CramMd5 => "CRAM-MD5",
+            AuthMechanism::Ntlm => "NTLM",
+        }
+    }
+
+    /// lettre 连接池能直接使用的认证方式；CRAM-MD5 与 NTLM 需要自行完成质询。
+    pub fn lettre_mechanism(self) -> Option<Mechanism> {
+        match self {
+            AuthMechanism::Plain => Some(Mechanism::Plain),
+            AuthMechanism::Login => Some(Mechanism::Login),
+            AuthMechanism::CramMd5 | AuthMechanism::Ntlm => None,
+        }
+    }
+}
+
+/// 一次认证的结果：服务器声明的认证方式与实际使用的方式。
+#[derive(Serialize, Clone, Debug)]
+pub struct AuthProbe {
+    pub offered_mechanisms: Vec<String>,
+    pub auth_mechanism: AuthMechanism,
+}
+
+/// 解析 EHLO 响应中的 `AUTH`（以及旧式 `AUTH=`）行，返回大写的认证方式名称。
+pub fn offered_mechanisms(ehlo: &Response) -> Vec<String> {
+    let mut offered: Vec<String> = Vec::new();
+    for line in ehlo.message() {
+        let mut words = line.split_whitespace();
+        let Some(keyword) = words.next() else { continue };
+        let rest: Vec<&str> = match keyword.split_once('=') {
+            Some((name, first)) if name.eq_ignore_ascii_case("AUTH") => {
+                std::iter::once(first).chain(words).collect()
+            }
+            None if keyword.eq_ignore_ascii_case("AUTH") => words.collect(),
+            _ => continue,
+        };
+        for name in rest.into_iter().filter(|name| !name.is_empty()) {
+            let name = name.to_ascii_uppercase();
+            if !offered.contains(&name) {
+                offered.push(name);
+            }
+        }
+    }
+    offered
+}
+
+/// 按指定（或自动选择的）方式完成 SMTP AUTH，返回实际使用的认证方式。
+pub fn authenticate(
+    connection: &mut SmtpConnection,
+    offered: &[String],
+    requested: Option<AuthMechanism>,
+    username: &str,
+    password: &str,
+) -> Result<AuthMechanism, SendFailure> {
+    let is_offered = |mechanism: &AuthMechanism| offered.iter().any(|name| name == mechanism.name());
+    let mechanism = match requested {
+        Some(mechanism) if is_offered(&mechanism) => mechanism,
+        Some(mechanism) => {
+            return Err(failure(format!(
+                "服务器未提供 {} 认证方式，可用：{}",
+                mechanism.name(),
+                describe(offered)
+            )))
+        }
+        None => AUTO_ORDER.into_iter().find(is_offered).ok_or_else(|| {
+            failure(format!("服务器未提供受支持的认证方式（可用：{}）", describe(offered)))
+        })?,
+    };
+    match mechanism {
+        AuthMechanism::Plain | AuthMechanism::Login => {
+            let credentials = Credentials::new(username.to_string(), password.to_string());
+            let lettre_mechanism = mechanism.lettre_mechanism().into_iter().collect::<Vec<_>>();
+            connection.auth(&lettre_mechanism, &credentials)?;
+        }
+        AuthMechanism::CramMd5 => {
+            let challenge = decode_challenge(&connection.command(Line("AUTH CRAM-MD5".to_string()))?)?;
+            connection.command(Line(STANDARD.encode(cram_md5_response(username, password, &challenge))))?;
+        }
+        AuthMechanism::Ntlm => {
+            let negotiate = STANDARD.encode(ntlm_negotiate());
+            let challenge = decode_challenge(&connection.command(Line(format!("AUTH NTLM {negotiate}")))?)?;
+            let challenge = NtlmChallenge::parse(&challenge).map_err(failure)?;
+            let (domain, user) = split_ntlm_user(username);
+            let client_challenge: [u8; 8] = rand::random();
+            let authenticate = ntlm_authenticate(&challenge, domain, user, password, client_challenge, filetime_now());
+            connection.command(Line(STANDARD.encode(authenticate)))?;
+        }
+    }
+    Ok(mechanism)
+}
+
+/// 原样发送的一行 SMTP 命令（AUTH 质询应答）。
+struct Line(String);
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}\r\n", self.0)
+    }
+}
+
+fn failure(message: String) -> SendFailure {
+    SendFailure { code: None, message }
+}
+
+fn describe(offered: &[String]) -> String {
+    if offered.is_empty() {
+        "无".to_string()
+    } else {
+        offered.join(", ")
+    }
+}
+
+/// 334 响应携带的 Base64 质询。
+fn decode_challenge(response: &Response) -> Result<Vec<u8>, SendFailure> {
+    if !response.has_code(334) {
+        return Err(failure(format!("服务器未返回认证质询（{}）", response.code())));
+    }
+    let encoded = response.first_line().unwrap_or_default().trim();
+    STANDARD
+        .decode(encoded)
+        .map_err(|err| failure(format!("认证质询不是有效的 Base64: {err}")))
+}
+
+fn hmac_md5(key: &[u8], parts: &[&[u8]]) -> [u8; 16] {
+    let mut mac = <Hmac<Md5> as Mac>::new_from_slice(key).expect("HMAC accepts any key length");
+    for part in parts {
+        mac.update(part);
+    }
+    mac.finalize().into_bytes().into()
+}
+
+/// RFC 2195：`用户名 空格 HMAC-MD5(密码, 质询) 的十六进制`。
+fn cram_md5_response(username: &str, password: &str, challenge: &[u8]) -> String {
+    let digest = hmac_md5(password.as_bytes(), &[challenge]);
+    let hex: String = digest.iter().map(|byte| format!("{byte:02x}")).collect();
+    format!("{username} {hex}")
+}
+
+/// `DOMAIN\user` 拆分为域与用户名；`user@domain`（UPN）整体作为用户名。
+fn split_ntlm_user(username: &str) -> (&str, &str) {
+    username.split_once('\\').unwrap_or(("", username))
+}
+
+fn utf16le(text: &str) -> Vec<u8> {
+    text.encode_utf16().flat_map(u16::to_le_bytes).collect()
+}
+
+fn filetime_now() -> u64 {
+    let since_epoch = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    FILETIME_UNIX_OFFSET + (since_epoch.as_nanos() / 100) as u64
+}
+
+/// NTLM Type 1（NEGOTIATE）消息，不携带域与工作站。
+fn ntlm_negotiate() -> Vec<u8> {
+    let mut message = NTLM_SIGNATURE.to_vec();
+    message.extend_from_slice(&1u32.to_le_bytes());
+    message.extend_from_slice(&NTLM_FLAGS.to_le_bytes());
+    message.extend_from_slice(&[0; 16]);
+    message
+}
+
+/// NTLM Type 2（CHALLENGE）中用到的字段。
+struct NtlmChallenge {
+    flags: u32,
+    server_challenge: [u8; 8],
+    target_info: Vec<u8>,
+}
+
+impl NtlmChallenge {
+    fn parse(message: &[u8]) -> Result<Self, String> {
+        let invalid = || "服务器返回的 NTLM 质询格式不正确".to_string();
+        if message.len() < 32 || &message[..8] != NTLM_SIGNATURE || message[8..12] != 2u32.to_le_bytes() {
+            return Err(invalid());
+        }
+        let flags = u32::from_le_bytes(message[20..24].try_into().map_err(|_| invalid())?);
+        let server_challenge = message[24..32].try_into().map_err(|_| invalid())?;
+        let target_info = if message.len() >= 48 {
+            let length = usize::from(u16::from_le_bytes([message[40], message[41]]));
+            let offset = u32::from_le_bytes(message[44..48].try_into().map_err(|_| invalid())?) as usize;
+            message.get(offset..offset + length).ok_or_else(invalid)?.to_vec()
+        } else {
+            Vec::new()
+        };
+        Ok(NtlmChallenge { flags, server_challenge, target_info })
+    }
+
+    /// 目标信息中的 MsvAvTimestamp（AV 编号 7）；存在时须以服务器时间计算应答。
+    fn timestamp(&self) -> Option<u64> {
+        let mut rest = self.target_info.as_slice();
+        while rest.len() >= 4 {
+            let id = u16::from_le_bytes([rest[0], rest[1]]);
+            let length = usize::from(u16::from_le_bytes([rest[2], rest[3]]));
+            let value = rest.get(4..4 + length)?;
+            match id {
+                0 => return None,
+                7 => return value.try_into().ok().map(u64::from_le_bytes),
+                _ => rest = &rest[4 + length..],
+            }
+        }
+        None
+    }
+}
+
+/// NTLMv2 响应密钥：HMAC-MD5(MD4(UTF-16LE(密码)), UTF-16LE(大写用户名 + 域))。
+fn ntowf_v2(domain: &str, user: &str, password: &str) -> [u8; 16] {
+    let nt_hash: [u8; 16] = Md4::digest(utf16le(password)).into();
+    hmac_md5(&nt_hash, &[&utf16le(&(user.to_uppercase() + domain))])
+}
+
+/// NTLMv2 的 LM 与 NT 质询应答。
+fn ntlm_v2_responses(
+    challenge: &NtlmChallenge,
+    key: &[u8; 16],
+    client_challenge: [u8; 8],
+    timestamp: u64,
+) -> (Vec<u8>, Vec<u8>) {
+    let server_timestamp = challenge.timestamp();
+    let mut blob = vec![1, 1, 0, 0, 0, 0, 0, 0];
+    blob.extend_from_slice(&server_timestamp.unwrap_or(timestamp).to_le_bytes());
+    blob.extend_from_slice(&client_challenge);
+    blob.extend_from_slice(&[0; 4]);
+    blob.extend_from_slice(&challenge.target_info);
+    blob.extend_from_slice(&[0; 4]);
+    let mut nt_response = hmac_md5(key, &[&challenge.server_challenge, &blob]).to_vec();
+    nt_response.extend_from_slice(&blob);
+    // 服务器提供时间戳时 LMv2 应答须置零（MS-NLMP 3.1.5.1.2）。
+    let lm_response = if server_timestamp.is_some() {
+        vec![0; 24]
+    } else {
+        let mut lm = hmac_md5(key, &[&challenge.server_challenge, &client_challenge]).to_vec();
+        lm.extend_from_slice(&client_challenge);
+        lm
+    };
+    (lm_response, nt_response)
+}
+
+/// NTLM Type 3（AUTHENTICATE）消息。
+fn ntlm_authenticate(
+    challenge: &NtlmChallenge,
+    domain: &str,
+    user: &str,
+    password: &str,
+    client_challenge: [u8; 8],
+    timestamp: u64,
+) -> Vec<u8> {
+    let key = ntowf_v2(domain, user, password);
+    let (lm_response, nt_response) = ntlm_v2_responses(challenge, &key, client_challenge, timestamp);
+    let fields = [lm_response, nt_response, utf16le(domain), utf16le(user), Vec::new(), Vec::new()];
+    const HEADER_LEN: usize = 64;
+
+    let mut message = NTLM_SIGNATURE.to_vec();
+    message.extend_from_slice(&3u32.to_le_bytes());
+    let mut offset = HEADER_LEN;
+    for field in &fields {
+        let length = field.len() as u16;
+        message.extend_from_slice(&length.to_le_bytes());
+        message.extend_from_slice(&length.to_le_bytes());
+        message.extend_from_slice(&(offset as u32).to_le_bytes());
+        offset += field.len();
+    }
+    message.extend_from_slice(&(NTLM_FLAGS & challenge.flags).to_le_bytes());
+    for field in &fields {
+        message.extend_from_slice(field);
+    }
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{cram_md5_response, ntlm_v2_responses, ntowf_v2, split_ntlm_user, NtlmChallenge};
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+    }
+
+    #[test]
+    fn computes_cram_md5_response() {
+        // RFC 2195 第 2 节示例。
+        let response =
+            cram_md5_response("tim", "tanstaaftanstaaf", b"<1896.697170952@postoffice.reston.mci.net>");
+        assert_eq!(response, "tim b913a602c7eda7a495b4e6e7334d3890");
+    }
+
+    #[test]
+    fn computes_ntlm_v2_responses() {
+        // MS-NLMP 4.2.4 示例：User / Domain / Password，服务器质询 0123456789abcdef。
+        assert_eq!(split_ntlm_user("Domain\\User"), ("Domain", "User"));
+        assert_eq!(split_ntlm_user("user@corp.example"), ("", "user@corp.example"));
+        let key = ntowf_v2("Domain", "User", "Password");
+        assert_eq!(hex(&key), "0c868a403bfd7a93a3001ef22ef02e3f");
+
+        let mut target_info = Vec::new();
+        for (id, value) in [(2u16, "Domain"), (1u16, "Server")] {
+            let value = super::utf16le(value);
+            target_info.extend_from_slice(&id.to_le_bytes());
+            target_info.extend_from_slice(&(value.len() as u16).to_le_bytes());
+            target_info.extend_from_slice(&value);
+        }
+        target_info.extend_from_slice(&[0; 4]);
+        let challenge = NtlmChallenge {
+            flags: 0xe28a_8233,
+            server_challenge: [0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef],
+            target_info,
+        };
+        assert_eq!(challenge.timestamp(), None);
+        let (lm, nt) = ntlm_v2_responses(&challenge, &key, [0xaa; 8], 0);
+        assert_eq!(hex(&lm), "86c35097ac9cec102554764a57cccc19aaaaaaaaaaaaaaaa");
+        assert_eq!(hex(&nt[..16]), "68cd0ab851e51c96aabc927bebef6a1c");
+    }
+}
@@ -0,0 +1,502 @@
+use crate::direct_transport::DirectTransport;
+use crate::dsn::DsnOptions;
+use crate::environment::Environment;
+use crate::local_mta::LocalMtaTransport;
+use crate::smtp_auth::{offered_mechanisms, AuthMechanism, AuthProbe};
+use crate::tls_session::{probe_tls_session, TlsSession};
+use lettre::address::Envelope;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::transport::smtp::client::{Certificate, Tls, TlsParameters, TlsVersion};
+use lettre::transport::smtp::extension::ClientId;
+use lettre::transport::smtp::response::Response;
+use lettre::{Address, Message, SmtpTransport, Transport};
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+use std::time::Duration;
+
+#[derive(Deserialize, Serialize, Clone, Default)]
+pub struct SmtpPayload {
+    pub host: String,
+    pub port: u16,
+    pub username: String,
+    pub password: String,
+    pub use_ssl: bool,
+    pub use_starttls: bool,
+    pub timeout_sec: u32,
+    /// EHLO 中声明的客户端主机名；为空时使用本机主机名。部分中继会拒绝默认值。
+    #[serde(default)]
+    pub ehlo_hostname: Option<String>,
+    /// 信封发件人（MAIL FROM，即退信地址）；为空时与发件邮箱相同。
+    #[serde(default)]
+    pub return_path: Option<String>,
+    /// 环境标签；测试任务只能使用标记为测试（或指向测试收件服务）的配置。
+    #[serde(default)]
+    pub environment: Option<Environment>,
+    /// 指定 SMTP AUTH 认证方式；为空时按服务器声明自动选择。
+    #[serde(default)]
+    pub auth_mechanism: Option<AuthMechanism>,
+    /// 服务器 EHLO 声明的 SIZE 上限（字节），由连接测试读取后随配置保存；超过上限的邮件在发送前拒绝。
+    #[serde(default)]
+    pub size_limit: Option<u64>,
+    /// 额外信任的根证书（PEM，可包含多张），用于使用自签名证书或内部 CA 的邮件服务器；系统根证书仍然有效。
+    #[serde(default)]
+    pub ca_cert_pem: Option<String>,
+    /// 不校验服务器证书（包括过期、自签名与主机名不符）。连接可能被中间人窃听或篡改，仅用于受信任的内部网络。
+    #[serde(default)]
+    pub accept_invalid_certs: bool,
+    /// 允许的最低 TLS 版本；为空时为 TLS 1.2（不支持更早的版本）。
+    #[serde(default)]
+    pub min_tls_version: Option<MinTlsVersion>,
+}
+
+/// 可设置的最低 TLS 版本，服务器只支持更低版本时连接失败。
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MinTlsVersion {
+    #[serde(rename = "1.2")]
+    Tls12,
+    #[serde(rename = "1.3")]
+    Tls13,
+}
+
+impl MinTlsVersion {
+    fn lettre_version(self) -> TlsVersion {
+        match self {
+            MinTlsVersion::Tls12 => TlsVersion::Tlsv12,
+            MinTlsVersion::Tls13 => TlsVersion::Tlsv13,
+        }
+    }
+}
+
+impl SmtpPayload {
+    /// 校验可选的 EHLO 主机名与退信地址。
+    pub fn validate_envelope(&self) -> Result<(), String> {
+        self.client_id()?;
+        self.return_path_address()?;
+        Ok(())
+    }
+
+    /// 发送前需要遵守的 SIZE 上限；`SIZE 0`（声明了扩展但没有固定上限）视为不限制。
+    pub fn server_size_limit(&self) -> Option<u64> {
+        self.size_limit.filter(|limit| *limit > 0)
+    }
+
+    /// 关闭了证书校验时给用户的警告；明文连接或正常校验时返回 `None`。
+    pub fn tls_warning(&self) -> Option<&'static str> {
+        (self.accept_invalid_certs && (self.use_ssl || self.use_starttls))
+            .then_some("已关闭 TLS 证书校验：连接可能被中间人窃听或篡改，仅应在受信任的内部网络中使用")
+    }
+
+    pub fn client_id(&self) -> Result<Option<ClientId>, String> {
+        match self.ehlo_hostname.as_deref().map(str::trim) {
+            Some(name) if !name.is_empty() => parse_ehlo_hostname(name).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    pub fn return_path_address(&self) -> Result<Option<Address>, String> {
+        match self.return_path.as_deref().map(str::trim) {
+            Some(address) if !address.is_empty() => address
+                .parse()
+                .map(Some)
+                .map_err(|err| format!("退信地址（Return-Path）格式不正确: {err}")),
+            _ => Ok(None),
+        }
+    }
+}
+
+/// 解析 EHLO 主机名：RFC 5321 完整域名，或 `[192.0.2.1]` / `[IPv6:2001:db8::1]` 地址字面量。
+pub fn parse_ehlo_hostname(name: &str) -> Result<ClientId, String> {
+    let invalid = || format!("EHLO 主机名格式不正确: {name}");
+    if let Some(literal) = name.strip_prefix('[').and_then(|rest| rest.strip_suffix(']')) {
+        let literal = literal.strip_prefix("IPv6:").unwrap_or(literal);
+        return match literal.parse::<IpAddr>().map_err(|_| invalid())? {
+            IpAddr::V4(addr) => Ok(ClientId::Ipv4(addr)),
+            IpAddr::V6(addr) => Ok(ClientId::Ipv6(addr)),
+        };
+    }
+    let name = name.trim_end_matches('.');
+    let labels: Vec<&str> = name.split('.').collect();
+    let valid_label = |label: &&str| {
+        !label.is_empty()
+            && label.len() <= 63
+            && !label.starts_with('-')
+            && !label.ends_with('-')
+            && label.chars().all(|ch| ch.is_ascii_alphanumeric() || ch == '-')
+    };
+    if name.len() > 253 || labels.len() < 2 || !labels.iter().all(valid_label) {
+        return Err(invalid());
+    }
+    Ok(ClientId::Domain(name.to_ascii_lowercase()))
+}
+
+/// 一次发送失败的结构化描述：SMTP 状态码（若来自服务器响应）与原始错误文本。
+#[derive(Debug, Clone)]
+pub struct SendFailure {
+    pub code: Option<u16>,
+    pub message: String,
+}
+
+impl From<lettre::transport::smtp::Error> for SendFailure {
+    fn from(err: lettre::transport::smtp::Error) -> Self {
+        let code = err
+            .status()
+            .and_then(|status| status.to_string().parse::<u16>().ok());
+        SendFailure {
+            code,
+            message: err.to_string(),
+        }
+    }
+}
+
+/// 发送引擎依赖的最小投递接口，便于在测试中替换真实 SMTP 连接。
+pub trait MailTransport {
+    fn send_message(&mut self, message: &Message) -> Result<(), SendFailure>;
+    /// 投递已序列化的邮件（预渲染落盘的邮件）。
+    fn send_raw(&mut self, envelope: &Envelope, body: &[u8]) -> Result<(), SendFailure>;
+    /// 上一封邮件的队列号（本机 MTA 返回时才有），写入发送记录。
+    fn last_queue_id(&self) -> Option<String> {
+        None
+    }
+}
+
+impl MailTransport for SmtpTransport {
+    fn send_message(&mut self, message: &Message) -> Result<(), SendFailure> {
+        self.send(message).map(|_| ()).map_err(SendFailure::from)
+    }
+
+    fn send_raw(&mut self, envelope: &Envelope, body: &[u8]) -> Result<(), SendFailure> {
+        Transport::send_raw(self, envelope, body)
+            .map(|_| ())
+            .map_err(SendFailure::from)
+    }
+}
+
+/// 原生引擎的投递通道：默认使用 lettre 连接池；请求 DSN 或使用 CRAM-MD5 / NTLM 认证时改用自行维护的连接；
+/// 配置本机 MTA 时交给 sendmail 或投递目录。
+pub enum NativeTransport {
+    Pooled(SmtpTransport),
+    Direct(Box<DirectTransport>),
+    Local(LocalMtaTransport),
+}
+
+impl MailTransport for NativeTransport {
+    fn send_message(&mut self, message: &Message) -> Result<(), SendFailure> {
+        match self {
+            NativeTransport::Pooled(transport) => transport.send_message(message),
+            NativeTransport::Direct(transport) => transport.send_message(message),
+            NativeTransport::Local(transport) => transport.send_message(message),
+        }
+    }
+
+    fn send_raw(&mut self, envelope: &Envelope, body: &[u8]) -> Result<(), SendFailure> {
+        match self {
+            NativeTransport::Pooled(transport) => MailTransport::send_raw(transport, envelope, body),
+            NativeTransport::Direct(transport) => transport.send_raw(envelope, body),
+            NativeTransport::Local(transport) => transport.send_raw(envelope, body),
+        }
+    }
+
+    fn last_queue_id(&self) -> Option<String> {
+        match self {
+            NativeTransport::Local(transport) => transport.last_queue_id(),
+            NativeTransport::Pooled(_) | NativeTransport::Direct(_) => None,
+        }
+    }
+}
+
+pub fn build_mail_transport(payload: &SmtpPayload, dsn: Option<&DsnOptions>) -> Result<NativeTransport, String> {
+    let pooled_auth = payload.auth_mechanism.is_none_or(|mechanism| mechanism.lettre_mechanism().is_some());
+    if dsn.is_none() && pooled_auth {
+        return build_transport(payload).map(NativeTransport::Pooled);
+    }
+    check_tls_mode(payload)?;
+    DirectTransport::new(payload, dsn.cloned()).map(|transport| NativeTransport::Direct(Box::new(transport)))
+}
+
+/// `probe_auth` 失败的阶段：连接（含 TLS 握手与配置检查）或认证。
+/// 界面据此区分网络问题与账号密码错误；认证失败不应重试，以免触发服务器锁定账号。
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProbeStage {
+    Connect,
+    Auth,
+}
+
+#[derive(Debug, Clone)]
+pub struct ProbeFailure {
+    pub stage: ProbeStage,
+    pub message: String,
+}
+
+/// 服务器在 EHLO 中声明的扩展（STARTTLS 模式下为升级后的 EHLO）。
+/// `test_smtp` 成功时返回，界面据此在围绕某项功能配置任务前提示服务器不支持。
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+pub struct ServerCapabilities {
+    /// EHLO 中的全部扩展关键字（大写），如 `PIPELINING`、`8BITMIME`。
+    pub extensions: Vec<String>,
+    /// `SIZE` 声明的单封邮件上限（字节）；`Some(0)` 表示声明了 SIZE 但没有固定上限（RFC 1870）。
+    pub size_limit: Option<u64>,
+    /// 服务器提供 STARTTLS；当前连接已经通过 STARTTLS 升级时也为 `true`。
+    pub starttls: bool,
+    pub auth_mechanisms: Vec<String>,
+    pub pipelining: bool,
+    pub smtputf8: bool,
+    pub dsn: bool,
+}
+
+impl ServerCapabilities {
+    pub fn from_ehlo(ehlo: &Response) -> Self {
+        let mut capabilities = ServerCapabilities {
+            auth_mechanisms: offered_mechanisms(ehlo),
+            ..ServerCapabilities::default()
+        };
+        // 第一行是服务器的域名与问候语，其后每行一个扩展。
+        for line in ehlo.message().skip(1) {
+            let mut words = line.split_whitespace();
+            let Some(keyword) = words.next().and_then(|word| word.split('=').next()) else {
+                continue;
+            };
+            let keyword = keyword.to_ascii_uppercase();
+            match keyword.as_str() {
+                "SIZE" => capabilities.size_limit = Some(words.next().and_then(|size| size.parse().ok()).unwrap_or(0)),
+                "STARTTLS" => capabilities.starttls = true,
+                "PIPELINING" => capabilities.pipelining = true,
+                "SMTPUTF8" => capabilities.smtputf8 = true,
+                "DSN" => capabilities.dsn = true,
+                _ => {}
+            }
+            if !keyword.is_empty() && !capabilities.extensions.contains(&keyword) {
+                capabilities.extensions.push(keyword);
+            }
+        }
+        capabilities
+    }
+}
+
+/// `probe_auth` 的结果：认证方式、服务器声明的扩展与另行探测的 TLS 参数。
+#[derive(Serialize, Clone, Debug)]
+pub struct SmtpProbe {
+    pub auth: AuthProbe,
+    pub capabilities: ServerCapabilities,
+    /// 另行握手探测到的 TLS 参数，不是认证所用连接的参数（lettre 不暴露后者）；明文连接或探测失败时为 `None`。
+    pub tls_probe: Option<TlsSession>,
+}
+
+/// 连接、认证并确认会话可用（NOOP），报告认证方式、服务器声明的扩展，并另行握手探测 TLS 协议和加密套件。
+/// 探测失败不影响测试结果。
+pub fn probe_auth(payload: &SmtpPayload) -> Result<SmtpProbe, ProbeFailure> {
+    let connect_failure = |message| ProbeFailure {
+        stage: ProbeStage::Connect,
+        message,
+    };
+    check_tls_mode(payload).map_err(connect_failure)?;
+    let mut probe = DirectTransport::new(payload, None).map_err(connect_failure)?.probe()?;
+    probe.tls_probe = probe_tls_session(payload).ok().flatten();
+    Ok(probe)
+}
+
+fn check_tls_mode(payload: &SmtpPayload) -> Result<(), String> {
+    if payload.use_ssl && payload.use_starttls {
+        return Err("SMTP 配置冲突：use_ssl 与 use_starttls 不能同时开启".to_string());
+    }
+    Ok(())
+}
+
+/// SSL 或 STARTTLS 模式下的 TLS 参数（含自定义根证书、证书校验开关与最低版本）；明文连接返回 `None`。
+pub fn tls_parameters(payload: &SmtpPayload) -> Result<Option<TlsParameters>, String> {
+    if !payload.use_ssl && !payload.use_starttls {
+        return Ok(None);
+    }
+    let mut builder = TlsParameters::builder(payload.host.clone());
+    if let Some(pem) = payload.ca_cert_pem.as_deref().map(str::trim).filter(|pem| !pem.is_empty()) {
+        builder = builder.add_root_certificate(parse_ca_certificate(pem)?);
+    }
+    if let Some(version) = payload.min_tls_version {
+        builder = builder.set_min_tls_version(version.lettre_version());
+    }
+    builder
+        .dangerous_accept_invalid_certs(payload.accept_invalid_certs)
+        .build()
+        .map(Some)
+        .map_err(|e| format!("TLS 配置失败: {e}"))
+}
+
+fn parse_ca_certificate(pem: &str) -> Result<Certificate, String> {
+    if !pem.contains("-----BEGIN CERTIFICATE-----") {
+        return Err("自定义根证书必须是 PEM 格式（以 -----BEGIN CERTIFICATE----- 开头）".to_string());
+    }
+    Certificate::from_pem(pem.as_bytes()).map_err(|e| format!("自定义根证书格式错误: {e}"))
+}
+
+pub fn build_transport(payload: &SmtpPayload) -> Result<SmtpTransport, String> {
+    check_tls_mode(payload)?;
+    let creds = Credentials::new(payload.username.clone(), payload.password.clone());
+
+    let tls = match tls_parameters(payload)? {
+        Some(tls_params) if payload.use_ssl => Tls::Wrapper(tls_params),
+        Some(tls_params) => Tls::Required(tls_params),
+        None => Tls::None,
+    };
+
+    let mut builder = SmtpTransport::builder_dangerous(&payload.host)
+        .port(payload.port)
+        .tls(tls)
+        .credentials(creds)
+        .timeout(Some(Duration::from_secs(payload.timeout_sec.into())));
+    if let Some(mechanism) = payload.auth_mechanism {
+        let mechanism = mechanism
+            .lettre_mechanism()
+            .ok_or_else(|| format!("{} 认证不支持 lettre 连接池，需要使用直连发送通道", mechanism.name()))?;
+        builder = builder.authentication(vec![mechanism]);
+    }
+    if let Some(client_id) = payload.client_id()? {
+        builder = builder.hello_name(client_id);
+    }
+    Ok(builder.build())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_ehlo_hostname, probe_auth, tls_parameters, MinTlsVersion, ProbeStage, SmtpPayload};
+    use crate::smtp_auth::AuthMechanism;
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine as _;
+    use lettre::transport::smtp::extension::ClientId;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+
+    /// 只支持 AUTH PLAIN 的明文 SMTP 服务器（用户 `user`，密码 `secret`），接受 `sessions` 次连接；返回端口。
+    fn fake_smtp_server(sessions: usize) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind");
+        let port = listener.local_addr().expect("addr").port();
+        let accepted = format!("AUTH PLAIN {}", STANDARD.encode("\0user\0secret"));
+        std::thread::spawn(move || {
+            for stream in listener.incoming().take(sessions) {
+                let stream = stream.expect("accept");
+                let mut writer = stream.try_clone().expect("clone");
+                let mut reader = BufReader::new(stream);
+                writer.write_all(b"220 smtp.example.edu ESMTP\r\n").expect("greeting");
+                let mut authenticated = false;
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap_or(0) > 0 {
+                    let command = line.trim_end();
+                    let reply = match command {
+                        _ if command.starts_with("EHLO ") => concat!(
+                            "250-smtp.example.edu greets you\r\n",
+                            "250-SIZE 10485760\r\n",
+                            "250-PIPELINING\r\n",
+                            "250-8BITMIME\r\n",
+                            "250 AUTH PLAIN\r\n",
+                        ),
+                        _ if command == accepted => {
+                            authenticated = true;
+                            "235 2.7.0 Authentication successful\r\n"
+                        }
+                        _ if command.starts_with("AUTH ") => "535 5.7.8 Authentication credentials invalid\r\n",
+                        "NOOP" if authenticated => "250 2.0.0 OK\r\n",
+                        "QUIT" => "221 2.0.0 Bye\r\n",
+                        _ => "530 5.7.0 Authentication required\r\n",
+                    };
+                    writer.write_all(reply.as_bytes()).expect("reply");
+                    if command == "QUIT" {
+                        break;
+                    }
+                    line.clear();
+                }
+            }
+        });
+        port
+    }
+
+    #[test]
+    fn probe_reports_auth_failures_separately_from_connection_failures() {
+        let mut payload = SmtpPayload {
+            host: "127.0.0.1".to_string(),
+            port: fake_smtp_server(2),
+            username: "user".to_string(),
+            password: "secret".to_string(),
+            timeout_sec: 5,
+            ..SmtpPayload::default()
+        };
+        let probe = probe_auth(&payload).expect("authenticated");
+        assert_eq!(probe.auth.auth_mechanism, AuthMechanism::Plain);
+        let capabilities = probe.capabilities;
+        assert_eq!(capabilities.extensions, ["SIZE", "PIPELINING", "8BITMIME", "AUTH"]);
+        assert_eq!(capabilities.auth_mechanisms, ["PLAIN"]);
+        assert_eq!(capabilities.size_limit, Some(10_485_760));
+        assert!(capabilities.pipelining && !capabilities.starttls && !capabilities.smtputf8 && !capabilities.dsn);
+
+        payload.password = "wrong".to_string();
+        let failure = probe_auth(&payload).expect_err("bad password");
+        assert_eq!(failure.stage, ProbeStage::Auth);
+        assert!(failure.message.contains("credentials invalid"), "{}", failure.message);
+
+        payload.port = TcpListener::bind("127.0.0.1:0").and_then(|closed| closed.local_addr()).expect("addr").port();
+        let failure = probe_auth(&payload).expect_err("nothing listening");
+        assert_eq!(failure.stage, ProbeStage::Connect);
+    }
+
+    #[test]
+    fn validates_ehlo_hostname_and_return_path() {
+        assert_eq!(
+            parse_ehlo_hostname("Mail.Example.EDU.").expect("domain"),
+            ClientId::Domain("mail.example.edu".to_string())
+        );
+        assert!(matches!(parse_ehlo_hostname("[192.0.2.10]"), Ok(ClientId::Ipv4(_))));
+        assert!(matches!(parse_ehlo_hostname("[IPv6:2001:db8::1]"), Ok(ClientId::Ipv6(_))));
+        for invalid in ["localhost", "bad_host.example.com", "-edge.example.com", "a..b.com", "[not-an-ip]"] {
+            assert!(parse_ehlo_hostname(invalid).is_err(), "{invalid} should be rejected");
+        }
+
+        let mut payload = SmtpPayload {
+            ehlo_hostname: Some("  ".to_string()),
+            return_path: Some("bounces@example.com".to_string()),
+            ..SmtpPayload::default()
+        };
+        assert!(payload.validate_envelope().is_ok());
+        assert!(payload.client_id().expect("blank hostname").is_none());
+        payload.return_path = Some("not an address".to_string());
+        assert!(payload.validate_envelope().is_err());
+    }
+
+    #[test]
+    fn applies_custom_root_certificate_and_insecure_tls_option() {
+        let mut payload = SmtpPayload {
+            host: "mail.internal.example".to_string(),
+            use_starttls: true,
+            ca_cert_pem: Some(include_str!("../tests/fixtures/smime/recipient.pem").to_string()),
+            ..SmtpPayload::default()
+        };
+        assert!(tls_parameters(&payload).expect("custom root certificate").is_some());
+        assert!(payload.tls_warning().is_none());
+
+        payload.ca_cert_pem = Some("not a certificate".to_string());
+        let error = tls_parameters(&payload).err().unwrap_or_default();
+        assert!(error.contains("PEM"), "{error}");
+
+        payload.ca_cert_pem = Some("  ".to_string());
+        payload.accept_invalid_certs = true;
+        assert!(tls_parameters(&payload).expect("insecure TLS").is_some());
+        assert!(payload.tls_warning().is_some());
+        payload.use_starttls = false;
+        assert!(payload.tls_warning().is_none());
+    }
+
+    #[test]
+    fn applies_minimum_tls_version() {
+        let payload: SmtpPayload = serde_json::from_value(serde_json::json!({
+            "host": "smtp.example.com",
+            "port": 465,
+            "username": "me@example.com",
+            "password": "secret",
+            "use_ssl": true,
+            "use_starttls": false,
+            "timeout_sec": 30,
+            "min_tls_version": "1.3",
+        }))
+        .expect("payload");
+        assert_eq!(payload.min_tls_version, Some(MinTlsVersion::Tls13));
+        assert!(tls_parameters(&payload).expect("TLS 1.3 only").is_some());
+        assert!(serde_json::from_value::<MinTlsVersion>(serde_json::json!("1.1")).is_err());
+    }
+}
@@ -0,0 +1,468 @@
+use crate::engine::validate_email;
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, SecondsFormat, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+const EMAIL_COLUMNS: [&str; 5] = ["email", "address", "email_address", "recipient", "email address"];
+const REASON_COLUMNS: [&str; 6] = ["reason", "reason_code", "type", "suppression_reason", "status", "code"];
+const TIME_COLUMNS: [&str; 7] = [
+    "suppressed_at",
+    "created_at",
+    "created",
+    "timestamp",
+    "date",
+    "last_event_time",
+    "time",
+];
+
+/// 一条抑制记录：被抑制的地址、归一化后的原因代码、来源与加入时间。
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct SuppressionEntry {
+    pub email: String,
+    pub reason: String,
+    #[serde(default)]
+    pub source: String,
+    pub suppressed_at: String,
+}
+
+/// 整个域名的抑制记录：该域名及其子域名下的地址都不会被发送。
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct SuppressedDomain {
+    pub domain: String,
+    pub reason: String,
+    #[serde(default)]
+    pub source: String,
+    pub suppressed_at: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SuppressionImportError {
+    pub line: usize,
+    pub value: String,
+    pub error: String,
+}
+
+#[derive(Serialize, Default, Debug)]
+pub struct SuppressionImportSummary {
+    pub imported: usize,
+    pub duplicates: usize,
+    pub invalid: Vec<SuppressionImportError>,
+}
+
+/// 抑制列表的一页查询结果；`total_*` 为过滤前的总数。
+#[derive(Serialize, Debug)]
+pub struct SuppressionListing {
+    pub total_addresses: usize,
+    pub total_domains: usize,
+    pub addresses: Vec<SuppressionEntry>,
+    pub domains: Vec<SuppressedDomain>,
+}
+
+#[derive(Deserialize, Serialize, Default)]
+pub struct SuppressionList {
+    #[serde(default)]
+    pub entries: BTreeMap<String, SuppressionEntry>,
+    #[serde(default)]
+    pub domains: BTreeMap<String, SuppressedDomain>,
+}
+
+impl SuppressionList {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        if !path.exists() {
+            return Ok(SuppressionList::default());
+        }
+        let text = fs::read_to_string(path).map_err(|err| format!("读取抑制列表失败: {err}"))?;
+        serde_json::from_str(&text).map_err(|err| format!("抑制列表格式错误: {err}"))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let text = serde_json::to_string_pretty(self).map_err(|err| err.to_string())?;
+        fs::write(path, text).map_err(|err| format!("写入抑制列表失败: {err}"))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty() && self.domains.is_empty()
+    }
+
+    /// 地址本身或其域名（含上级域名）在列表中即视为被抑制。
+    pub fn is_suppressed(&self, email: &str) -> bool {
+        let email = email.trim().to_lowercase();
+        if self.entries.contains_key(&email) {
+            return true;
+        }
+        let Some((_, mut domain)) = email.rsplit_once('@') else {
+            return false;
+        };
+        loop {
+            if self.domains.contains_key(domain) {
+                return true;
+            }
+            match domain.split_once('.') {
+                Some((_, parent)) => domain = parent,
+                None => return false,
+            }
+        }
+    }
+
+    /// 加入一条记录；已存在的地址保留最早的记录，返回 false。
+    pub fn insert(&mut self, entry: SuppressionEntry) -> bool {
+        let key = entry.email.to_lowercase();
+        if self.entries.contains_key(&key) {
+            return false;
+        }
+        self.entries.insert(key, entry);
+        true
+    }
+
+    /// 加入一个域名；已存在时保留最早的记录，返回 false。
+    pub fn insert_domain(&mut self, entry: SuppressedDomain) -> bool {
+        if self.domains.contains_key(&entry.domain) {
+            return false;
+        }
+        self.domains.insert(entry.domain.clone(), entry);
+        true
+    }
+
+    /// 手动加入地址或域名：`example.com` 与 `@example.com` 均按域名处理。
+    pub fn add_entries(&mut self, values: &[String], reason: &str, source: &str) -> SuppressionImportSummary {
+        self.insert_rows(manual_rows(values, reason), source, true)
+    }
+
+    /// 按关键字（子串，不区分大小写）过滤，地址与域名各最多返回 `limit` 条。
+    pub fn listing(&self, query: &str, limit: usize) -> SuppressionListing {
+        let query = query.trim().to_lowercase();
+        SuppressionListing {
+            total_addresses: self.entries.len(),
+            total_domains: self.domains.len(),
+            addresses: self
+                .entries
+                .iter()
+                .filter(|(key, _)| key.contains(&query))
+                .take(limit)
+                .map(|(_, entry)| entry.clone())
+                .collect(),
+            domains: self
+                .domains
+                .iter()
+                .filter(|(key, _)| key.contains(&query))
+                .take(limit)
+                .map(|(_, entry)| entry.clone())
+                .collect(),
+        }
+    }
+
+    /// 从文件导入：`.csv` 按表头识别邮箱 / 原因 / 时间列，其他扩展名按每行一个地址处理。
+    pub fn import_file(&mut self, path: &Path) -> Result<SuppressionImportSummary, String> {
+        let text = fs::read_to_string(path).map_err(|err| format!("读取抑制列表文件失败: {err}"))?;
+        let source = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let is_csv = path
+            .extension()
+            .and_then(|value| value.to_str())
+            .is_some_and(|value| value.eq_ignore_ascii_case("csv"));
+        let rows = if is_csv {
+            parse_csv_rows(&text)?
+        } else {
+            parse_plain_rows(&text)
+        };
+        Ok(self.insert_rows(rows, &source, true))
+    }
+
+    /// 批量加入选中的地址（如从发送记录中选中的失败收件人）；`line` 为地址在列表中的序号。
+    pub fn suppress_emails(&mut self, emails: &[String], reason: &str, source: &str) -> SuppressionImportSummary {
+        self.insert_rows(manual_rows(emails, reason), source, false)
+    }
+
+    /// `allow_domains` 时，不含 `@` 或以 `@` 开头的值按域名加入。
+    fn insert_rows(&mut self, rows: Vec<ImportRow>, source: &str, allow_domains: bool) -> SuppressionImportSummary {
+        let mut summary = SuppressionImportSummary::default();
+        for row in rows {
+            let value = row.email.trim();
+            if allow_domains && (value.starts_with('@') || !value.contains('@')) {
+                let inserted = normalize_domain(value).map(|domain| {
+                    self.insert_domain(SuppressedDomain {
+                        domain,
+                        reason: normalize_reason(row.reason.as_deref().unwrap_or_default()),
+                        source: source.to_string(),
+                        suppressed_at: normalize_timestamp(row.timestamp.as_deref().unwrap_or_default()),
+                    })
+                });
+                match inserted {
+                    Ok(true) => summary.imported += 1,
+                    Ok(false) => summary.duplicates += 1,
+                    Err(error) => summary.invalid.push(SuppressionImportError {
+                        line: row.line,
+                        value: row.email,
+                        error,
+                    }),
+                }
+                continue;
+            }
+            if let Err(error) = validate_email(&row.email, "邮箱") {
+                summary.invalid.push(SuppressionImportError {
+                    line: row.line,
+                    value: row.email,
+                    error,
+                });
+                continue;
+            }
+            let entry = SuppressionEntry {
+                email: row.email.trim().to_string(),
+                reason: normalize_reason(row.reason.as_deref().unwrap_or_default()),
+                source: source.to_string(),
+                suppressed_at: normalize_timestamp(row.timestamp.as_deref().unwrap_or_default()),
+            };
+            if self.insert(entry) {
+                summary.imported += 1;
+            } else {
+                summary.duplicates += 1;
+            }
+        }
+        summary
+    }
+
+    /// 导出为 CSV（email, reason, source, suppressed_at）或每行一个地址的纯文本；
+    /// 域名写作 `@example.com`，重新导入时仍按域名处理。
+    pub fn export_file(&self, path: &Path) -> Result<usize, String> {
+        let is_csv = path
+            .extension()
+            .and_then(|value| value.to_str())
+            .is_some_and(|value| value.eq_ignore_ascii_case("csv"));
+        let domain_rows = self.domains.values().map(|entry| SuppressionEntry {
+            email: format!("@{}", entry.domain),
+            reason: entry.reason.clone(),
+            source: entry.source.clone(),
+            suppressed_at: entry.suppressed_at.clone(),
+        });
+        let rows: Vec<SuppressionEntry> = self.entries.values().cloned().chain(domain_rows).collect();
+        if !is_csv {
+            let mut text = String::new();
+            for entry in &rows {
+                text.push_str(&entry.email);
+                text.push('\n');
+            }
+            fs::write(path, text).map_err(|err| format!("导出抑制列表失败: {err}"))?;
+            return Ok(rows.len());
+        }
+        let mut writer = csv::Writer::from_path(path).map_err(|err| format!("导出抑制列表失败: {err}"))?;
+        for entry in &rows {
+            writer
+                .serialize(entry)
+                .map_err(|err| format!("导出抑制列表失败: {err}"))?;
+        }
+        writer.flush().map_err(|err| format!("导出抑制列表失败: {err}"))?;
+        Ok(rows.len())
+    }
+}
+
+struct ImportRow {
+    line: usize,
+    email: String,
+    reason: Option<String>,
+    timestamp: Option<String>,
+}
+
+fn manual_rows(values: &[String], reason: &str) -> Vec<ImportRow> {
+    values
+        .iter()
+        .enumerate()
+        .map(|(index, value)| ImportRow {
+            line: index + 1,
+            email: value.clone(),
+            reason: Some(reason.to_string()),
+            timestamp: None,
+        })
+        .collect()
+}
+
+/// 校验并归一域名（去掉前导 `@`、转小写），至少包含两级标签。
+fn normalize_domain(raw: &str) -> Result<String, String> {
+    let domain = raw.trim().trim_start_matches('@').trim_end_matches('.').to_lowercase();
+    let labels: Vec<&str> = domain.split('.').collect();
+    let valid_label = |label: &&str| {
+        !label.is_empty()
+            && label.len() <= 63
+            && !label.starts_with('-')
+            && !label.ends_with('-')
+            && label.chars().all(|ch| ch.is_ascii_alphanumeric() || ch == '-')
+    };
+    if labels.len() < 2 || domain.len() > 253 || !labels.iter().all(valid_label) {
+        return Err(format!("不是有效的邮箱地址或域名: {}", raw.trim()));
+    }
+    Ok(domain)
+}
+
+fn parse_plain_rows(text: &str) -> Vec<ImportRow> {
+    text.lines()
+        .enumerate()
+        .filter_map(|(index, raw)| {
+            let value = raw.trim().trim_start_matches('\u{feff}');
+            if value.is_empty() || value.starts_with('#') {
+                return None;
+            }
+            Some(ImportRow {
+                line: index + 1,
+                email: value.to_string(),
+                reason: None,
+                timestamp: None,
+            })
+        })
+        .collect()
+}
+
+fn parse_csv_rows(text: &str) -> Result<Vec<ImportRow>, String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(text.trim_start_matches('\u{feff}').as_bytes());
+    let headers: Vec<String> = reader
+        .headers()
+        .map_err(|err| format!("CSV 表头解析失败: {err}"))?
+        .iter()
+        .map(|header| header.to_lowercase())
+        .collect();
+    let find = |candidates: &[&str]| headers.iter().position(|header| candidates.contains(&header.as_str()));
+    let email_column = find(&EMAIL_COLUMNS).ok_or_else(|| "CSV 中未找到邮箱列（email / address）".to_string())?;
+    let reason_column = find(&REASON_COLUMNS);
+    let time_column = find(&TIME_COLUMNS);
+
+    let mut rows = Vec::new();
+    for (index, record) in reader.records().enumerate() {
+        // 表头占第 1 行。
+        let line = index + 2;
+        let record = record.map_err(|err| format!("CSV 第 {line} 行解析失败: {err}"))?;
+        let email = record.get(email_column).unwrap_or_default().to_string();
+        if email.is_empty() {
+            continue;
+        }
+        let column = |position: Option<usize>| {
+            position
+                .and_then(|position| record.get(position))
+                .filter(|value| !value.is_empty())
+                .map(str::to_string)
+        };
+        rows.push(ImportRow {
+            line,
+            email,
+            reason: column(reason_column),
+            timestamp: column(time_column),
+        });
+    }
+    Ok(rows)
+}
+
+/// 把各家 ESP 导出中的原因归一为 bounce / complaint / unsubscribe / invalid / manual。
+pub fn normalize_reason(raw: &str) -> String {
+    let lowered = raw.trim().to_lowercase();
+    if lowered.is_empty() {
+        return "manual".to_string();
+    }
+    if lowered.contains("bounce") || lowered.starts_with('5') {
+        "bounce".to_string()
+    } else if lowered.contains("spam") || lowered.contains("complain") || lowered.contains("abuse") {
+        "complaint".to_string()
+    } else if lowered.contains("unsub") || lowered.contains("opt") {
+        "unsubscribe".to_string()
+    } else if lowered.contains("invalid") {
+        "invalid".to_string()
+    } else {
+        lowered
+    }
+}
+
+/// 识别常见时间格式（RFC3339、RFC2822、Unix 秒、日期），无法识别时使用当前时间。
+fn normalize_timestamp(raw: &str) -> String {
+    let value = raw.trim();
+    let parsed: Option<DateTime<Utc>> = DateTime::parse_from_rfc3339(value)
+        .or_else(|_| DateTime::parse_from_rfc2822(value))
+        .map(|time| time.with_timezone(&Utc))
+        .ok()
+        .or_else(|| {
+            value
+                .parse::<i64>()
+                .ok()
+                .and_then(|secs| Utc.timestamp_opt(secs, 0).single())
+        })
+        .or_else(|| {
+            NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S")
+                .ok()
+                .map(|time| time.and_utc())
+        })
+        .or_else(|| {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+                .map(|time| time.and_utc())
+        });
+    match parsed {
+        Some(time) => time.to_rfc3339_opts(SecondsFormat::Secs, true),
+        None => Local::now().to_rfc3339_opts(SecondsFormat::Secs, false),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{normalize_reason, SuppressionList};
+
+    #[test]
+    fn imports_esp_csv_with_dedup_and_validation() {
+        let dir = std::env::temp_dir().join(format!("bes-suppression-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("temp dir");
+        let path = dir.join("bounces.csv");
+        std::fs::write(
+            &path,
+            "Address,Code,Error,Created_At\n\
+             a@example.com,550,mailbox unavailable,\"Fri, 21 Oct 2011 11:02:55 +0000\"\n\
+             A@example.com,550,duplicate,1319195000\n\
+             not-an-email,550,,\n",
+        )
+        .expect("write csv");
+
+        let mut list = SuppressionList::default();
+        let summary = list.import_file(&path).expect("import");
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert_eq!(summary.imported, 1);
+        assert_eq!(summary.duplicates, 1);
+        assert_eq!(summary.invalid.len(), 1);
+        assert_eq!(summary.invalid[0].line, 4);
+        let entry = &list.entries["a@example.com"];
+        assert_eq!(entry.reason, "bounce");
+        assert_eq!(entry.suppressed_at, "2011-10-21T11:02:55Z");
+        assert!(list.is_suppressed(" A@Example.com "));
+
+        let summary = list.suppress_emails(
+            &["b@example.com".to_string(), "a@example.com".to_string(), "bad".to_string()],
+            "complaint",
+            "job-1",
+        );
+        assert_eq!((summary.imported, summary.duplicates, summary.invalid.len()), (1, 1, 1));
+        assert_eq!(list.entries["b@example.com"].source, "job-1");
+
+        let summary = list.add_entries(
+            &["@Blocked.example".to_string(), "blocked.example".to_string(), "c@example.com".to_string(), "-x".to_string()],
+            "",
+            "manual",
+        );
+        assert_eq!((summary.imported, summary.duplicates, summary.invalid.len()), (2, 1, 1));
+        assert!(list.is_suppressed("anyone@BLOCKED.example"));
+        assert!(list.is_suppressed("x@mail.blocked.example"));
+        assert!(!list.is_suppressed("x@notblocked.example"));
+        let listing = list.listing("blocked", 10);
+        assert_eq!((listing.total_addresses, listing.total_domains), (3, 1));
+        assert!(listing.addresses.is_empty());
+        assert_eq!(listing.domains[0].reason, "manual");
+    }
+
+    #[test]
+    fn normalizes_reason_codes() {
+        assert_eq!(normalize_reason("hard_bounce"), "bounce");
+        assert_eq!(normalize_reason("spamreport"), "complaint");
+        assert_eq!(normalize_reason("Unsubscribed"), "unsubscribe");
+        assert_eq!(normalize_reason(""), "manual");
+    }
+}
@@ -0,0 +1,184 @@
+use chrono::{DateTime, Datelike, TimeZone};
+use std::collections::HashMap;
+
+const SENDER_NAME_TEMPLATE_TOKEN: &str = "{sender_name}";
+const SEND_DATE_TEMPLATE_TOKEN: &str = "{send_date}";
+/// 模板内置变量，收件人的补充字段不能覆盖。
+pub const BUILTIN_VARIABLES: [&str; 5] = ["teacher_name", "teacher_email", "sender_name", "signature_name", "send_date"];
+
+/// 与 Python 端 `render_template_text` 保持一致：先把 `{{ name }}` 归一化为 `{name}`，
+/// 再按 `str.format_map` 的规则替换变量（`{{`/`}}` 为转义括号，缺失变量报错）。
+pub fn render_template_text(template: &str, variables: &HashMap<&str, String>) -> Result<String, String> {
+    let normalized = normalize_template_placeholders(template);
+    let chars: Vec<char> = normalized.chars().collect();
+    let mut output = String::with_capacity(normalized.len());
+    let mut index = 0;
+    while index < chars.len() {
+        let current = chars[index];
+        if current == '{' {
+            if chars.get(index + 1) == Some(&'{') {
+                output.push('{');
+                index += 2;
+                continue;
+            }
+            let Some(offset) = chars[index + 1..].iter().position(|c| *c == '}') else {
+                return Err("Single '{' encountered in format string".to_string());
+            };
+            let field: String = chars[index + 1..index + 1 + offset].iter().collect();
+            let key = field.split([':', '!']).next().unwrap_or_default();
+            let value = variables
+                .get(key)
+                .ok_or_else(|| format!("Missing template variable: {key}"))?;
+            output.push_str(value);
+            index += offset + 2;
+            continue;
+        }
+        if current == '}' {
+            if chars.get(index + 1) == Some(&'}') {
+                output.push('}');
+                index += 2;
+                continue;
+            }
+            return Err("Single '}' encountered in format string".to_string());
+        }
+        output.push(current);
+        index += 1;
+    }
+    Ok(output)
+}
+
+/// 模板中引用的变量名（去重，按首次出现的顺序）；格式错误的部分忽略，由渲染时报错。
+pub fn template_variables(template: &str) -> Vec<String> {
+    let normalized = normalize_template_placeholders(template);
+    let mut variables: Vec<String> = Vec::new();
+    let mut rest = normalized.as_str();
+    while let Some(start) = rest.find('{') {
+        let after = &rest[start + 1..];
+        if let Some(escaped) = after.strip_prefix('{') {
+            rest = escaped;
+            continue;
+        }
+        let Some(end) = after.find('}') else {
+            break;
+        };
+        let key = after[..end].split([':', '!']).next().unwrap_or_default();
+        if !variables.iter().any(|variable| variable == key) {
+            variables.push(key.to_string());
+        }
+        rest = &after[end + 1..];
+    }
+    variables
+}
+
+fn normalize_template_placeholders(template: &str) -> String {
+    let chars: Vec<char> = template.chars().collect();
+    let mut output = String::with_capacity(template.len());
+    let mut index = 0;
+    while index < chars.len() {
+        if chars[index] == '{' && chars.get(index + 1) == Some(&'{') {
+            if let Some((name, consumed)) = match_double_brace(&chars[index..]) {
+                output.push('{');
+                output.push_str(&name);
+                output.push('}');
+                index += consumed;
+                continue;
+            }
+        }
+        output.push(chars[index]);
+        index += 1;
+    }
+    output
+}
+
+fn match_double_brace(chars: &[char]) -> Option<(String, usize)> {
+    let mut cursor = 2;
+    while chars.get(cursor).is_some_and(|c| c.is_whitespace()) {
+        cursor += 1;
+    }
+    let start = cursor;
+    let first = *chars.get(cursor)?;
+    if !(first.is_ascii_alphabetic() || first == '_') {
+        return None;
+    }
+    while chars
+        .get(cursor)
+        .is_some_and(|c| c.is_ascii_alphanumeric() || *c == '_')
+    {
+        cursor += 1;
+    }
+    let name: String = chars[start..cursor].iter().collect();
+    while chars.get(cursor).is_some_and(|c| c.is_whitespace()) {
+        cursor += 1;
+    }
+    if chars.get(cursor) == Some(&'}') && chars.get(cursor + 1) == Some(&'}') {
+        return Some((name, cursor + 2));
+    }
+    None
+}
+
+/// 正文同时包含 `{sender_name}` 与 `{send_date}` 时，将二者移到正文末尾作为落款。
+pub fn normalize_signature_tokens_in_template(body_text_template: &str) -> String {
+    let normalized = body_text_template.replace("\r\n", "\n").replace('\r', "\n");
+    if !normalized.contains(SENDER_NAME_TEMPLATE_TOKEN) || !normalized.contains(SEND_DATE_TEMPLATE_TOKEN) {
+        return normalized;
+    }
+
+    let content_without_tokens = normalized
+        .replace(SENDER_NAME_TEMPLATE_TOKEN, "")
+        .replace(SEND_DATE_TEMPLATE_TOKEN, "");
+    let mut content_lines: Vec<String> = content_without_tokens
+        .split('\n')
+        .map(|line| line.trim_end().to_string())
+        .collect();
+
+    while content_lines.last().is_some_and(|line| line.trim().is_empty()) {
+        content_lines.pop();
+    }
+
+    if !content_lines.is_empty() {
+        content_lines.push(String::new());
+    }
+    content_lines.push(SENDER_NAME_TEMPLATE_TOKEN.to_string());
+    content_lines.push(SEND_DATE_TEMPLATE_TOKEN.to_string());
+    content_lines.join("\n")
+}
+
+pub fn format_send_date<Tz: TimeZone>(timestamp: &DateTime<Tz>) -> String {
+    format!("{}年{}月{}日", timestamp.year(), timestamp.month(), timestamp.day())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{normalize_signature_tokens_in_template, render_template_text, template_variables};
+    use std::collections::HashMap;
+
+    fn variables() -> HashMap<&'static str, String> {
+        HashMap::from([
+            ("teacher_name", "张教授".to_string()),
+            ("sender_name", "小王".to_string()),
+        ])
+    }
+
+    #[test]
+    fn renders_single_and_double_brace_placeholders() {
+        let rendered = render_template_text("{teacher_name}您好，我是{{ sender_name }}", &variables());
+        assert_eq!(rendered.unwrap(), "张教授您好，我是小王");
+        assert_eq!(
+            template_variables("{teacher_name}，{{ company }}，{{ }}，{company:>4}"),
+            ["teacher_name", "company"]
+        );
+    }
+
+    #[test]
+    fn reports_missing_template_variable() {
+        let err = render_template_text("{unknown}", &variables()).unwrap_err();
+        assert_eq!(err, "Missing template variable: unknown");
+        assert!(render_template_text("oops }", &variables()).is_err());
+    }
+
+    #[test]
+    fn moves_signature_tokens_to_the_end() {
+        let normalized = normalize_signature_tokens_in_template("正文\r\n{sender_name}\n{send_date}\n\n");
+        assert_eq!(normalized, "正文\n\n{sender_name}\n{send_date}");
+    }
+}
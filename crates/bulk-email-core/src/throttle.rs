@@ -0,0 +1,221 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const MINUTE_SEC: u64 = 60;
+const HOUR_SEC: u64 = 60 * 60;
+const DAY_SEC: u64 = 24 * 60 * 60;
+
+/// 全局发送限速配置，保存在 `AppSettings` 中；各项为空表示不限制。
+#[derive(Deserialize, Serialize, Clone, Default, PartialEq, Debug)]
+pub struct ThrottleLimits {
+    #[serde(default)]
+    pub per_minute: Option<u32>,
+    #[serde(default)]
+    pub per_hour: Option<u32>,
+    #[serde(default)]
+    pub per_day: Option<u32>,
+    /// 随机间隔下限：任务自身配置的间隔不会低于该值。
+    #[serde(default)]
+    pub min_delay_sec: Option<u64>,
+    #[serde(default)]
+    pub max_delay_sec: Option<u64>,
+}
+
+impl ThrottleLimits {
+    pub fn validate(&self) -> Result<(), String> {
+        for (value, label) in [
+            (self.per_minute, "每分钟上限"),
+            (self.per_hour, "每小时上限"),
+            (self.per_day, "每日上限"),
+        ] {
+            if value == Some(0) {
+                return Err(format!("{label}必须大于 0，不限制请留空"));
+            }
+        }
+        if let (Some(min), Some(max)) = (self.min_delay_sec, self.max_delay_sec) {
+            if min > max {
+                return Err("最小间隔不能大于最大间隔".to_string());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize)]
+pub struct ThrottleStatus {
+    pub limits: ThrottleLimits,
+    pub sent_last_minute: u32,
+    pub sent_last_hour: u32,
+    pub sent_last_day: u32,
+    pub remaining_today: Option<u32>,
+    /// 距离下一次允许发送的秒数，0 表示可以立即发送。
+    pub next_send_in_sec: u64,
+    pub blocked_by: Option<&'static str>,
+}
+
+/// 跨任务共享的限速器：记录最近 24 小时的发送时间戳，对 Python 与原生引擎统一生效。
+#[derive(Default)]
+pub struct Throttle {
+    limits: ThrottleLimits,
+    sent: VecDeque<u64>,
+}
+
+impl Throttle {
+    pub fn new(limits: ThrottleLimits, history: Vec<u64>) -> Self {
+        let mut sent: Vec<u64> = history;
+        sent.sort_unstable();
+        Throttle {
+            limits,
+            sent: sent.into(),
+        }
+    }
+
+    pub fn limits(&self) -> &ThrottleLimits {
+        &self.limits
+    }
+
+    pub fn set_limits(&mut self, limits: ThrottleLimits) {
+        self.limits = limits;
+    }
+
+    pub fn record(&mut self, now: u64) {
+        self.sent.push_back(now);
+        self.prune(now);
+    }
+
+    pub fn history(&self) -> Vec<u64> {
+        self.sent.iter().copied().collect()
+    }
+
+    /// 返回还需等待的秒数及触发的窗口；`None` 表示当前可以发送。
+    pub fn wait_secs(&mut self, now: u64) -> Option<(u64, &'static str)> {
+        self.prune(now);
+        [
+            (self.limits.per_minute, MINUTE_SEC, "per_minute"),
+            (self.limits.per_hour, HOUR_SEC, "per_hour"),
+            (self.limits.per_day, DAY_SEC, "per_day"),
+        ]
+        .into_iter()
+        .filter_map(|(limit, window, name)| {
+            let limit = limit? as usize;
+            let in_window = self.count_since(now.saturating_sub(window));
+            if in_window < limit {
+                return None;
+            }
+            // 窗口内倒数第 limit 条记录滑出窗口后即可发送。
+            let oldest = self.sent[self.sent.len() - limit];
+            Some(((oldest + window).saturating_sub(now).max(1), name))
+        })
+        .max_by_key(|(wait, _)| *wait)
+    }
+
+    pub fn remaining_today(&mut self, now: u64) -> Option<u32> {
+        self.prune(now);
+        let limit = self.limits.per_day?;
+        Some(limit.saturating_sub(self.count_since(now.saturating_sub(DAY_SEC)) as u32))
+    }
+
+    /// 把任务配置的随机间隔提升到全局限速要求的下限。
+    pub fn delay_bounds(&self, min_delay_sec: u64, max_delay_sec: u64) -> (u64, u64) {
+        let mut floor = self.limits.min_delay_sec.unwrap_or(0);
+        if let Some(per_minute) = self.limits.per_minute {
+            floor = floor.max(MINUTE_SEC.div_ceil(per_minute.into()));
+        }
+        if let Some(per_hour) = self.limits.per_hour {
+            floor = floor.max(HOUR_SEC.div_ceil(per_hour.into()));
+        }
+        let low = min_delay_sec.min(max_delay_sec).max(floor);
+        let high = min_delay_sec
+            .max(max_delay_sec)
+            .max(self.limits.max_delay_sec.unwrap_or(0))
+            .max(low);
+        (low, high)
+    }
+
+    pub fn status(&mut self, now: u64) -> ThrottleStatus {
+        let wait = self.wait_secs(now);
+        ThrottleStatus {
+            limits: self.limits.clone(),
+            sent_last_minute: self.count_since(now.saturating_sub(MINUTE_SEC)) as u32,
+            sent_last_hour: self.count_since(now.saturating_sub(HOUR_SEC)) as u32,
+            sent_last_day: self.count_since(now.saturating_sub(DAY_SEC)) as u32,
+            remaining_today: self.remaining_today(now),
+            next_send_in_sec: wait.map(|(secs, _)| secs).unwrap_or(0),
+            blocked_by: wait.map(|(_, name)| name),
+        }
+    }
+
+    fn count_since(&self, start: u64) -> usize {
+        self.sent.iter().rev().take_while(|sent_at| **sent_at > start).count()
+    }
+
+    fn prune(&mut self, now: u64) {
+        let start = now.saturating_sub(DAY_SEC);
+        while self.sent.front().is_some_and(|sent_at| *sent_at <= start) {
+            self.sent.pop_front();
+        }
+    }
+}
+
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+pub fn load_history(path: &Path) -> Vec<u64> {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_history(path: &Path, history: &[u64]) -> Result<(), String> {
+    let text = serde_json::to_string(history).map_err(|err| err.to_string())?;
+    fs::write(path, text).map_err(|err| format!("写入限速记录失败: {err}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Throttle, ThrottleLimits};
+
+    #[test]
+    fn waits_until_oldest_send_leaves_window() {
+        let limits = ThrottleLimits {
+            per_minute: Some(2),
+            ..ThrottleLimits::default()
+        };
+        let mut throttle = Throttle::new(limits, vec![1_000, 1_010]);
+        assert_eq!(throttle.wait_secs(1_020), Some((40, "per_minute")));
+        assert_eq!(throttle.wait_secs(1_060), None);
+    }
+
+    #[test]
+    fn counts_remaining_daily_budget_over_rolling_window() {
+        let limits = ThrottleLimits {
+            per_day: Some(3),
+            ..ThrottleLimits::default()
+        };
+        let mut throttle = Throttle::new(limits, vec![10, 50_000, 60_000]);
+        assert_eq!(throttle.remaining_today(90_000), Some(1));
+        throttle.record(90_000);
+        assert_eq!(throttle.remaining_today(90_000), Some(0));
+        assert_eq!(throttle.history(), vec![50_000, 60_000, 90_000]);
+    }
+
+    #[test]
+    fn raises_job_delay_to_rate_floor() {
+        let limits = ThrottleLimits {
+            per_hour: Some(120),
+            min_delay_sec: Some(5),
+            ..ThrottleLimits::default()
+        };
+        let throttle = Throttle::new(limits, Vec::new());
+        assert_eq!(throttle.delay_bounds(0, 10), (30, 30));
+        assert_eq!(throttle.delay_bounds(40, 60), (40, 60));
+    }
+}
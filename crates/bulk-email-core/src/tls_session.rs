@@ -0,0 +1,231 @@
+//! 连接测试探测服务器协商的 TLS 协议版本与加密套件。
+//!
+//! lettre 的连接不暴露这些信息，因此按同样的设置（最低版本、自定义根证书、证书校验开关）另行完成一次握手，
+//! 报告的是这次探测握手的结果，服务器为认证所用的连接协商出的参数可能不同（如负载均衡后的不同节点）：
+//! SSL 模式直接握手，STARTTLS 模式先完成明文的 EHLO 与 STARTTLS 交互。读取结果后发送 QUIT 断开。
+
+use crate::smtp_client::{MinTlsVersion, SmtpPayload};
+use lettre::transport::smtp::commands::{Ehlo, Quit, Starttls};
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::CryptoProvider;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{ClientConfig, ClientConnection, DigitallySignedStruct, ProtocolVersion, SignatureScheme, StreamOwned};
+use serde::Serialize;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// 探测握手协商出的 TLS 参数。
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct TlsSession {
+    /// 协议版本，如 `TLS 1.3`。
+    pub protocol: String,
+    /// 加密套件的 IANA 名称，如 `TLS13_AES_256_GCM_SHA384`。
+    pub cipher: String,
+}
+
+/// 与服务器另行完成一次 TLS 握手并报告协商结果；明文连接返回 `None`。
+pub fn probe_tls_session(payload: &SmtpPayload) -> Result<Option<TlsSession>, String> {
+    if !payload.use_ssl && !payload.use_starttls {
+        return Ok(None);
+    }
+    let timeout = Duration::from_secs(payload.timeout_sec.max(1).into());
+    let mut tcp = connect(&payload.host, payload.port, timeout)?;
+    if payload.use_starttls {
+        starttls(&mut tcp, payload)?;
+    }
+    let name = ServerName::try_from(payload.host.clone()).map_err(|err| format!("SMTP 主机名无效: {err}"))?;
+    let connection = ClientConnection::new(Arc::new(client_config(payload)?), name)
+        .map_err(|err| format!("初始化 TLS 失败: {err}"))?;
+    let mut stream = StreamOwned::new(connection, tcp);
+    while stream.conn.is_handshaking() {
+        stream.conn.complete_io(&mut stream.sock).map_err(|err| format!("TLS 握手失败: {err}"))?;
+    }
+    let session = TlsSession {
+        protocol: protocol_name(stream.conn.protocol_version()),
+        cipher: stream
+            .conn
+            .negotiated_cipher_suite()
+            .map(|suite| format!("{:?}", suite.suite()))
+            .unwrap_or_default(),
+    };
+    let _ = stream.write_all(Quit.to_string().as_bytes());
+    stream.conn.send_close_notify();
+    let _ = stream.conn.complete_io(&mut stream.sock);
+    Ok(Some(session))
+}
+
+fn connect(host: &str, port: u16, timeout: Duration) -> Result<TcpStream, String> {
+    let addrs = (host, port).to_socket_addrs().map_err(|err| format!("解析 SMTP 主机失败: {err}"))?;
+    let mut last_error = format!("无法解析 SMTP 主机 {host}");
+    for addr in addrs {
+        match TcpStream::connect_timeout(&addr, timeout) {
+            Ok(tcp) => {
+                tcp.set_read_timeout(Some(timeout)).map_err(|err| err.to_string())?;
+                tcp.set_write_timeout(Some(timeout)).map_err(|err| err.to_string())?;
+                return Ok(tcp);
+            }
+            Err(err) => last_error = format!("连接 SMTP 服务器失败: {err}"),
+        }
+    }
+    Err(last_error)
+}
+
+/// 读取问候语，发送 EHLO 与 STARTTLS，服务器同意（220）后连接交给 TLS 握手。
+fn starttls(tcp: &mut TcpStream, payload: &SmtpPayload) -> Result<(), String> {
+    let hello = payload.client_id()?.unwrap_or_default();
+    // 服务器回复 220 后等待客户端握手，不会多发数据，读缓冲区在这里丢弃是安全的。
+    let mut reader = BufReader::new(tcp.try_clone().map_err(|err| err.to_string())?);
+    expect_reply(&mut reader, 2)?;
+    send(tcp, &Ehlo::new(hello).to_string())?;
+    expect_reply(&mut reader, 2)?;
+    send(tcp, &Starttls.to_string())?;
+    match read_reply(&mut reader)? {
+        (220, _) => Ok(()),
+        (_, line) => Err(format!("服务器拒绝 STARTTLS: {line}")),
+    }
+}
+
+fn send(tcp: &mut TcpStream, command: &str) -> Result<(), String> {
+    tcp.write_all(command.as_bytes()).map_err(|err| format!("发送 SMTP 命令失败: {err}"))
+}
+
+fn expect_reply(reader: &mut impl BufRead, class: u16) -> Result<(), String> {
+    match read_reply(reader)? {
+        (code, _) if code / 100 == class => Ok(()),
+        (_, line) => Err(format!("SMTP 服务器返回错误: {line}")),
+    }
+}
+
+/// 读取一条（可能多行的）SMTP 响应，返回响应码与最后一行。
+fn read_reply(reader: &mut impl BufRead) -> Result<(u16, String), String> {
+    loop {
+        let mut line = String::new();
+        let read = reader.read_line(&mut line).map_err(|err| format!("读取 SMTP 响应失败: {err}"))?;
+        if read == 0 {
+            return Err("SMTP 服务器关闭了连接".to_string());
+        }
+        let line = line.trim_end().to_string();
+        let code = line
+            .get(..3)
+            .and_then(|code| code.parse().ok())
+            .ok_or_else(|| format!("无法识别的 SMTP 响应: {line}"))?;
+        if line.as_bytes().get(3) != Some(&b'-') {
+            return Ok((code, line));
+        }
+    }
+}
+
+fn client_config(payload: &SmtpPayload) -> Result<ClientConfig, String> {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let versions: &[&rustls::SupportedProtocolVersion] = match payload.min_tls_version {
+        Some(MinTlsVersion::Tls13) => &[&rustls::version::TLS13],
+        _ => &[&rustls::version::TLS13, &rustls::version::TLS12],
+    };
+    let builder = ClientConfig::builder_with_provider(provider.clone())
+        .with_protocol_versions(versions)
+        .map_err(|err| format!("初始化 TLS 失败: {err}"))?;
+    if payload.accept_invalid_certs {
+        return Ok(builder
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(AcceptAnyCertificate(provider)))
+            .with_no_client_auth());
+    }
+    let mut roots = rustls::RootCertStore {
+        roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+    };
+    if let Some(pem) = payload.ca_cert_pem.as_deref().map(str::trim).filter(|pem| !pem.is_empty()) {
+        for cert in CertificateDer::pem_slice_iter(pem.as_bytes()) {
+            let cert = cert.map_err(|err| format!("自定义根证书格式错误: {err}"))?;
+            roots.add(cert).map_err(|err| format!("自定义根证书格式错误: {err}"))?;
+        }
+    }
+    Ok(builder.with_root_certificates(roots).with_no_client_auth())
+}
+
+fn protocol_name(version: Option<ProtocolVersion>) -> String {
+    match version {
+        Some(ProtocolVersion::TLSv1_3) => "TLS 1.3".to_string(),
+        Some(ProtocolVersion::TLSv1_2) => "TLS 1.2".to_string(),
+        Some(other) => format!("{other:?}"),
+        None => String::new(),
+    }
+}
+
+/// 对应 `accept_invalid_certs`：不校验证书，但仍校验握手签名。
+#[derive(Debug)]
+struct AcceptAnyCertificate(Arc<CryptoProvider>);
+
+impl ServerCertVerifier for AcceptAnyCertificate {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls12_signature(message, cert, dss, &self.0.signature_verification_algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(message, cert, dss, &self.0.signature_verification_algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::probe_tls_session;
+    use crate::smtp_client::SmtpPayload;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+
+    #[test]
+    fn reports_nothing_for_plaintext_and_surfaces_refused_starttls() {
+        let mut payload = SmtpPayload {
+            host: "127.0.0.1".to_string(),
+            timeout_sec: 5,
+            ..SmtpPayload::default()
+        };
+        assert_eq!(probe_tls_session(&payload), Ok(None));
+
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind");
+        payload.port = listener.local_addr().expect("addr").port();
+        payload.use_starttls = true;
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().expect("accept");
+            let mut reader = BufReader::new(stream.try_clone().expect("clone"));
+            stream.write_all(b"220 mx.example ESMTP\r\n").expect("greeting");
+            let mut line = String::new();
+            reader.read_line(&mut line).expect("ehlo");
+            stream.write_all(b"250-mx.example\r\n250 STARTTLS\r\n").expect("ehlo reply");
+            line.clear();
+            reader.read_line(&mut line).expect("starttls");
+            stream.write_all(b"454 TLS not available\r\n").expect("refusal");
+            line
+        });
+        let error = probe_tls_session(&payload).expect_err("refused");
+        assert_eq!(server.join().expect("server"), "STARTTLS\r\n");
+        assert!(error.contains("454 TLS not available"), "{error}");
+    }
+}
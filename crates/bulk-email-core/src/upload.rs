@@ -0,0 +1,113 @@
+use crate::net_policy::{http_client, NetworkPolicy};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::Path;
+
+/// 附件上传目标：支持 HTTP PUT 的存储（WebDAV、对象存储网关等）。保存在本机应用设置中。
+#[derive(Deserialize, Serialize, Clone, Default, PartialEq, Debug)]
+pub struct UploadSettings {
+    /// 上传地址前缀，如 `https://files.example.edu/mail-attachments`。
+    #[serde(default)]
+    pub endpoint: Option<String>,
+    /// 以 `Authorization: Bearer` 发送的令牌。
+    #[serde(default)]
+    pub token: Option<String>,
+    /// 收件人访问用的地址前缀；为空时与上传地址相同。
+    #[serde(default)]
+    pub public_base_url: Option<String>,
+}
+
+impl UploadSettings {
+    pub fn endpoint(&self) -> Option<&str> {
+        self.endpoint.as_deref().map(str::trim).filter(|endpoint| !endpoint.is_empty())
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        for (value, label) in [(self.endpoint(), "上传地址"), (self.public_base_url.as_deref(), "下载地址前缀")] {
+            let Some(value) = value.map(str::trim).filter(|value| !value.is_empty()) else {
+                continue;
+            };
+            let url = Url::parse(value).map_err(|err| format!("{label}无效: {err}"))?;
+            if url.scheme() != "https" && url.scheme() != "http" {
+                return Err(format!("{label}仅支持 http/https"));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 替代附件发送的下载链接。
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct AttachmentLink {
+    pub name: String,
+    pub url: String,
+    pub size_bytes: u64,
+}
+
+/// 上传一个附件并返回下载链接；路径按内容摘要区分，重复上传同一文件会覆盖为相同内容。
+pub fn upload_attachment(policy: &NetworkPolicy, settings: &UploadSettings, path: &Path) -> Result<AttachmentLink, String> {
+    let endpoint = settings
+        .endpoint()
+        .ok_or_else(|| "未配置附件上传地址，无法改为下载链接".to_string())?;
+    let bytes = fs::read(path).map_err(|err| format!("读取附件失败 {}: {err}", path.display()))?;
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| "attachment".to_string());
+    let digest = format!("{:x}", Sha256::digest(&bytes));
+    let upload_url = object_url(endpoint, &digest[..16], &name)?;
+    let public_url = match settings.public_base_url.as_deref().map(str::trim) {
+        Some(base) if !base.is_empty() => object_url(base, &digest[..16], &name)?,
+        _ => upload_url.clone(),
+    };
+
+    let size_bytes = bytes.len() as u64;
+    let mut request = http_client(policy, upload_url.as_str(), "附件上传")?
+        .put(upload_url)
+        .header("Content-Type", mime_guess::from_path(path).first_or_octet_stream().essence_str())
+        .body(bytes);
+    if let Some(token) = settings.token.as_deref().map(str::trim).filter(|token| !token.is_empty()) {
+        request = request.bearer_auth(token);
+    }
+    let response = request.send().map_err(|err| format!("附件上传失败: {err}"))?;
+    if !response.status().is_success() {
+        return Err(format!("附件上传失败: {name} 返回 HTTP {}", response.status()));
+    }
+    Ok(AttachmentLink {
+        name,
+        url: public_url.to_string(),
+        size_bytes,
+    })
+}
+
+fn object_url(base: &str, digest: &str, name: &str) -> Result<Url, String> {
+    let mut url = Url::parse(base.trim()).map_err(|err| format!("上传地址无效: {err}"))?;
+    url.path_segments_mut()
+        .map_err(|_| "上传地址无效".to_string())?
+        .pop_if_empty()
+        .push(digest)
+        .push(name);
+    Ok(url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{object_url, UploadSettings};
+
+    #[test]
+    fn builds_encoded_object_urls() {
+        let url = object_url("https://files.example.edu/mail/", "0123456789abcdef", "招生 简章.pdf").expect("url");
+        assert_eq!(
+            url.as_str(),
+            "https://files.example.edu/mail/0123456789abcdef/%E6%8B%9B%E7%94%9F%20%E7%AE%80%E7%AB%A0.pdf"
+        );
+        let settings = UploadSettings {
+            endpoint: Some("ftp://files.example.edu".to_string()),
+            ..UploadSettings::default()
+        };
+        assert!(settings.validate().is_err());
+        assert!(UploadSettings::default().validate().is_ok());
+    }
+}
@@ -0,0 +1,226 @@
+//! worker 的通信通道：默认走标准输入 / 标准输出，也可以改走本地套接字（Unix 域套接字，Windows 上为命名管道）。
+//! 套接字模式下 worker 的标准输出不再承载协议，随意打印的调试信息不会被当成损坏的事件。
+
+use interprocess::local_socket::{
+    prelude::*, GenericFilePath, ListenerNonblockingMode, ListenerOptions, Stream as LocalSocketStream,
+};
+use serde::{Deserialize, Serialize};
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// 套接字模式下传给 worker 的环境变量：桌面端监听的地址（Unix 为套接字文件路径，Windows 为 `\\.\pipe\...`）。
+pub const IPC_ADDRESS_ENV: &str = "BULK_EMAIL_WORKER_IPC";
+/// 等待 worker 连接的时间：包含 Python 解释器与依赖的启动时间。
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(60);
+
+static NEXT_SOCKET_ID: AtomicU64 = AtomicU64::new(1);
+
+/// 与 worker 交换协议消息的方式。
+#[derive(Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum WorkerTransport {
+    /// 请求写入标准输入，事件从标准输出读取。
+    #[default]
+    Stdio,
+    /// worker 连接桌面端监听的本地套接字两次：第一条连接输出事件，第二条连接读取请求。
+    /// 每条连接只有一个方向，Windows 命名管道上读写不会互相阻塞。
+    Socket,
+}
+
+/// 已启动的 worker 与它的通道。
+pub struct SpawnedWorker {
+    pub child: Child,
+    /// 写入请求；丢弃后 worker 读到 EOF，处理完手头的任务后退出。
+    pub requests: Box<dyn Write + Send>,
+    /// worker 输出的协议消息（JSON Lines，握手协商后为长度前缀帧）。
+    pub events: Box<dyn Read + Send>,
+    /// 诊断输出（流名称与读取端）：标准错误，以及套接字模式下的标准输出。
+    pub diagnostics: Vec<(&'static str, Box<dyn Read + Send>)>,
+}
+
+/// 按 `transport` 启动 worker 并建立通道。套接字模式下等 worker 连上两条连接后才返回；
+/// worker 在连接前退出或超时未连接时结束进程并返回错误。
+pub fn spawn_worker(mut command: Command, transport: WorkerTransport) -> Result<SpawnedWorker, String> {
+    match transport {
+        WorkerTransport::Stdio => {
+            let mut child = command
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .spawn()
+                .map_err(|err| format!("failed to spawn worker: {err}"))?;
+            let (Some(stdin), Some(stdout), Some(stderr)) = (child.stdin.take(), child.stdout.take(), child.stderr.take())
+            else {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err("failed to open worker stdio".to_string());
+            };
+            Ok(SpawnedWorker {
+                child,
+                requests: Box::new(stdin),
+                events: Box::new(stdout),
+                diagnostics: vec![("stderr", Box::new(stderr))],
+            })
+        }
+        WorkerTransport::Socket => {
+            let listener = WorkerListener::bind().map_err(|err| format!("failed to create worker socket: {err}"))?;
+            let mut child = command
+                .env(IPC_ADDRESS_ENV, &listener.address)
+                .stdin(Stdio::null())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .spawn()
+                .map_err(|err| format!("failed to spawn worker: {err}"))?;
+            let connected = listener
+                .accept(&mut child)
+                .and_then(|events| Ok((events, listener.accept(&mut child)?)));
+            let (events, requests) = match connected {
+                Ok(streams) => streams,
+                Err(err) => {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return Err(err);
+                }
+            };
+            let mut diagnostics: Vec<(&'static str, Box<dyn Read + Send>)> = Vec::new();
+            if let Some(stdout) = child.stdout.take() {
+                diagnostics.push(("stdout", Box::new(stdout)));
+            }
+            if let Some(stderr) = child.stderr.take() {
+                diagnostics.push(("stderr", Box::new(stderr)));
+            }
+            Ok(SpawnedWorker {
+                child,
+                requests: Box::new(requests),
+                events: Box::new(events),
+                diagnostics,
+            })
+        }
+    }
+}
+
+/// 只为一个 worker 监听的本地套接字。Unix 上套接字文件放在仅当前用户可访问的临时目录中，丢弃时删除。
+struct WorkerListener {
+    listener: LocalSocketListener,
+    address: String,
+    #[cfg(unix)]
+    dir: PathBuf,
+}
+
+impl WorkerListener {
+    fn bind() -> io::Result<Self> {
+        let name = format!("bulk-email-worker-{}-{}", std::process::id(), NEXT_SOCKET_ID.fetch_add(1, Ordering::Relaxed));
+        #[cfg(unix)]
+        let (path, dir) = {
+            use std::os::unix::fs::DirBuilderExt;
+            let dir = std::env::temp_dir().join(&name);
+            std::fs::DirBuilder::new().mode(0o700).create(&dir)?;
+            (dir.join("worker.sock"), dir)
+        };
+        #[cfg(not(unix))]
+        let path = PathBuf::from(format!(r"\\.\pipe\{name}"));
+        let listener = ListenerOptions::new()
+            .name(path.as_path().to_fs_name::<GenericFilePath>()?)
+            .nonblocking(ListenerNonblockingMode::Accept)
+            .create_sync();
+        let listener = match listener {
+            Ok(listener) => listener,
+            Err(err) => {
+                #[cfg(unix)]
+                let _ = std::fs::remove_dir_all(&dir);
+                return Err(err);
+            }
+        };
+        Ok(Self {
+            listener,
+            address: path.to_string_lossy().into_owned(),
+            #[cfg(unix)]
+            dir,
+        })
+    }
+
+    /// 接受下一条连接；轮询期间 worker 退出或超过 `CONNECT_TIMEOUT` 时返回错误。
+    fn accept(&self, child: &mut Child) -> Result<LocalSocketStream, String> {
+        let started = Instant::now();
+        loop {
+            match self.listener.accept() {
+                Ok(stream) => return Ok(stream),
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => {}
+                Err(err) => return Err(format!("failed to accept worker connection: {err}")),
+            }
+            if let Ok(Some(status)) = child.try_wait() {
+                return Err(format!("worker 在连接本地套接字前退出（{status}）"));
+            }
+            if started.elapsed() >= CONNECT_TIMEOUT {
+                return Err(format!("worker 未在 {} 秒内连接本地套接字", CONNECT_TIMEOUT.as_secs()));
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        }
+    }
+}
+
+#[cfg(unix)]
+impl Drop for WorkerListener {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::{spawn_worker, WorkerListener, WorkerTransport};
+    use crate::worker_protocol::Framing;
+    use interprocess::local_socket::{prelude::*, GenericFilePath, Stream as LocalSocketStream};
+    use std::io::{BufReader, ErrorKind, Write};
+    use std::process::Command;
+    use std::time::Duration;
+
+    #[test]
+    fn carries_frames_over_the_socket_in_both_directions() {
+        let listener = WorkerListener::bind().expect("bind");
+        let address = listener.address.clone();
+        // 扮演 worker：先连上事件连接，再连上请求连接；事件帧拆成几次写入，最后一帧写到一半就断开。
+        let worker = std::thread::spawn(move || {
+            let connect = || {
+                let name = address.as_str().to_fs_name::<GenericFilePath>().expect("name");
+                LocalSocketStream::connect(name).expect("connect")
+            };
+            let mut events = connect();
+            let requests = connect();
+            for chunk in ["@16\n{\"type\":", "\"hello\"}\n@9", "\n{\"a\":\"\n\"}\n"] {
+                events.write_all(chunk.as_bytes()).expect("write");
+                events.flush().expect("flush");
+                std::thread::sleep(Duration::from_millis(20));
+            }
+            let request = Framing::LengthPrefixed.read_message(&mut BufReader::new(requests)).expect("read");
+            events.write_all(b"@40\n{\"type\":").expect("write");
+            request
+        });
+
+        let mut child = Command::new("sh").args(["-c", "sleep 30"]).spawn().expect("spawn");
+        let events = listener.accept(&mut child).expect("events connection");
+        let mut requests = listener.accept(&mut child).expect("requests connection");
+        let _ = child.kill();
+        let _ = child.wait();
+
+        let mut events = BufReader::new(events);
+        let read = |events: &mut BufReader<_>| Framing::LengthPrefixed.read_message(events);
+        assert_eq!(read(&mut events).expect("read").expect("frame"), Ok("{\"type\":\"hello\"}".to_string()));
+        assert_eq!(read(&mut events).expect("read").expect("frame"), Ok("{\"a\":\"\n\"}".to_string()));
+        Framing::LengthPrefixed.write_message(&mut requests, "{\"type\":\"cancel\"}").expect("request");
+        let received = worker.join().expect("worker");
+        assert_eq!(received, Some(Ok("{\"type\":\"cancel\"}".to_string())));
+        assert_eq!(read(&mut events).expect_err("closed mid-frame").kind(), ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn reports_a_worker_that_exits_before_connecting() {
+        let mut command = Command::new("sh");
+        command.args(["-c", "exit 3"]);
+        let error = spawn_worker(command, WorkerTransport::Socket).err().expect("worker never connects");
+        assert!(error.contains("退出"), "{error}");
+    }
+}
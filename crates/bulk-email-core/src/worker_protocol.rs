@@ -0,0 +1,1086 @@
+use crate::worker_ipc::{spawn_worker, SpawnedWorker, WorkerTransport};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fmt;
+use std::collections::VecDeque;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::process::{Child, Command};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// 桌面端与 Python worker 之间 JSON Lines 协议的版本号，随每个请求发送。
+/// 协议 2：每个请求带 `id`，worker 为该请求输出的每条消息都以 `request_id` 指回它，
+/// 同一个 worker 可以同时处理多个请求；协议 1 的 worker 不带 `request_id`。
+/// 协议 3：worker 启动后先应答 `hello` 握手，报告支持的协议范围与功能（可处理的请求类型）。
+/// 协议 4：握手时协商分帧方式，双方支持时握手之后的消息改用长度前缀帧（见 `Framing`）。
+pub const PROTOCOL_VERSION: u32 = 4;
+/// 桌面端能驱动的最旧 worker 协议：更旧的 worker 不会应答握手。协议 3 的 worker 不协商分帧，继续按行通信。
+pub const MIN_WORKER_PROTOCOL: u32 = 3;
+
+/// 桌面端发给 worker 的请求类型。
+pub const REQUEST_TYPES: [&str; 9] = [
+    "hello",
+    "load_recipients",
+    "test_smtp",
+    "start_send",
+    "cancel",
+    "pause",
+    "resume",
+    "throttle",
+    "ping",
+];
+
+/// worker 输出的事件类型，以及转发端和界面依赖的字段。
+/// worker 改名或删掉这些字段时，协议测试（Rust 与 Python 两侧）会失败。
+pub const EVENT_FIELDS: [(&str, &[&str]); 22] = [
+    ("hello", &["protocol", "min_protocol", "features"]),
+    ("recipients_loaded", &["stats", "recipients_preview"]),
+    ("smtp_test_succeeded", &[]),
+    ("pong", &["protocol"]),
+    ("job_accepted", &["job_id"]),
+    ("cancel_requested", &[]),
+    ("pause_requested", &[]),
+    ("resume_requested", &[]),
+    ("throttle_updated", &["limits"]),
+    ("job_started", &["job_id", "total"]),
+    ("recipient_started", &["job_id", "index", "email", "name"]),
+    ("recipient_sent", &["job_id", "index", "email", "name"]),
+    ("recipient_failed", &["job_id", "index", "email", "name", "error"]),
+    ("recipient_skipped", &["job_id", "index", "email", "name", "reason"]),
+    (
+        "recipient_deferred",
+        &["job_id", "index", "email", "name", "domain", "attempt", "retry_after_sec", "retry_at", "reason"],
+    ),
+    ("deferred_wait", &["job_id", "index", "email", "remaining_sec"]),
+    ("inter_send_wait", &["job_id", "index", "next_index", "delay_sec", "remaining_sec"]),
+    ("job_paused", &["job_id", "next_index"]),
+    ("job_resumed", &["job_id", "next_index"]),
+    ("job_finished", &["job_id", "success", "failed", "skipped", "total", "failures"]),
+    ("job_cancelled", &["job_id", "success", "failed", "skipped", "total"]),
+    ("error", &["error"]),
+];
+
+static NEXT_REQUEST_ID: AtomicU64 = AtomicU64::new(1);
+
+/// 发给 worker 的一个请求。线路格式为 `{"id", "protocol", "type", "payload"}`。
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct WorkerRequest {
+    pub id: u64,
+    pub protocol: u32,
+    #[serde(flatten)]
+    pub body: RequestBody,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", content = "payload", rename_all = "snake_case")]
+pub enum RequestBody {
+    /// 启动握手，worker 回复 `hello`。桌面端在写入第一个请求前发送，载荷 `{"framing": [...]}` 列出桌面端支持的分帧方式。
+    Hello(Value),
+    LoadRecipients(Value),
+    TestSmtp(Value),
+    StartSend(Value),
+    /// 以下为控制消息：`start_send` 之后标准输入保持打开，桌面端把它们写给运行中的任务。
+    /// 取消与暂停都在当前邮件发送完成、发送记录写入后生效。
+    Cancel(Value),
+    Pause,
+    Resume,
+    /// 调整限速（`ThrottleLimits` 的字段），从下一次发送间隔起生效。
+    Throttle(Value),
+    /// 确认 worker 能启动并应答协议，worker 回复 `pong`。
+    Ping,
+}
+
+impl RequestBody {
+    /// 按请求类型名构造；未知类型返回 `None`。
+    pub fn from_kind(kind: &str, payload: Value) -> Option<Self> {
+        serde_json::from_value(json!({ "type": kind, "payload": payload })).ok()
+    }
+
+    /// 线路上的请求类型名，与 `REQUEST_TYPES` 及 worker 报告的功能对应。
+    pub fn kind(&self) -> String {
+        serde_json::to_value(self)
+            .ok()
+            .and_then(|value| value["type"].as_str().map(str::to_string))
+            .unwrap_or_default()
+    }
+}
+
+/// worker 对 `hello` 的应答。
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct WorkerHello {
+    /// worker 实现的最新协议。
+    pub protocol: u32,
+    /// worker 仍接受的最旧协议。
+    pub min_protocol: u32,
+    /// worker 能处理的请求类型等功能标记。
+    pub features: Vec<String>,
+    /// worker 选定的分帧方式，握手之后的消息（双向）都按它读写；不协商分帧的 worker 为 `Lines`。
+    #[serde(default)]
+    pub framing: Framing,
+}
+
+/// 握手之后消息的分帧方式。握手本身总是一行 JSON，不认识分帧的一方照常按行通信。
+#[derive(Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Framing {
+    /// 每条消息一行 JSON（协议 1 至 3）。
+    #[default]
+    Lines,
+    /// 每条消息先写一行 `@<字节数>`，随后是恰好这么多字节的 JSON（末尾的换行只为便于阅读）。
+    /// 大载荷不受行缓冲的限制，消息内容里的换行也不会把它截断；帧之间混入的杂散行报告为错误后跳过。
+    LengthPrefixed,
+}
+
+/// 单个帧的上限，超过时视为输出已损坏、停止读取，避免按错误的长度分配内存。
+pub const MAX_FRAME_BYTES: usize = 256 * 1024 * 1024;
+
+impl Framing {
+    /// 按分帧方式写入一条消息并刷新。
+    pub fn write_message(self, writer: &mut impl Write, message: &str) -> io::Result<()> {
+        match self {
+            Framing::Lines => writeln!(writer, "{message}")?,
+            Framing::LengthPrefixed => write!(writer, "@{}\n{message}\n", message.len())?,
+        }
+        writer.flush()
+    }
+
+    /// 读取下一条消息（有损解码）；EOF 时返回 `None`。无法识别的帧头以 `Err` 返回原因，
+    /// 调用方可以继续读取，下一个帧头处重新同步。
+    pub fn read_message(self, reader: &mut impl BufRead) -> io::Result<Option<Result<String, String>>> {
+        let mut buffer = Vec::new();
+        loop {
+            buffer.clear();
+            if reader.read_until(b'\n', &mut buffer)? == 0 {
+                return Ok(None);
+            }
+            let line = String::from_utf8_lossy(&buffer);
+            let line = line.strip_suffix('\n').unwrap_or(&line);
+            if line.trim().is_empty() {
+                continue;
+            }
+            if self == Framing::Lines {
+                return Ok(Some(Ok(line.to_string())));
+            }
+            let header = line.trim_end_matches('\r');
+            let Some(length) = header.strip_prefix('@').and_then(|length| length.parse::<usize>().ok()) else {
+                let preview: String = header.chars().take(80).collect();
+                return Ok(Some(Err(format!("invalid frame header: {preview}"))));
+            };
+            if length > MAX_FRAME_BYTES {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("frame of {length} bytes exceeds the {MAX_FRAME_BYTES} byte limit"),
+                ));
+            }
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body)?;
+            return Ok(Some(Ok(String::from_utf8_lossy(&body).into_owned())));
+        }
+    }
+}
+
+impl WorkerHello {
+    /// 协议范围不兼容时返回提示用户更新哪一方的错误。
+    pub fn check_compatible(&self) -> Result<(), String> {
+        if self.protocol < MIN_WORKER_PROTOCOL {
+            return Err(format!(
+                "worker 协议版本 {} 过旧（应用需要 {MIN_WORKER_PROTOCOL} 或更新），请更新 worker / Python 运行时",
+                self.protocol
+            ));
+        }
+        if self.min_protocol > PROTOCOL_VERSION {
+            return Err(format!(
+                "应用的 worker 协议版本 {PROTOCOL_VERSION} 过旧（worker 需要 {} 或更新），请更新应用",
+                self.min_protocol
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn supports(&self, feature: &str) -> bool {
+        self.features.iter().any(|supported| supported == feature)
+    }
+
+    /// worker 不支持该请求类型时返回提示更新 worker 的错误。
+    pub fn check_supports(&self, body: &RequestBody) -> Result<(), String> {
+        let kind = body.kind();
+        if self.supports(&kind) {
+            Ok(())
+        } else {
+            Err(format!("当前 worker 不支持 `{kind}` 请求，请更新 worker / Python 运行时"))
+        }
+    }
+}
+
+impl fmt::Display for WorkerRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = serde_json::to_string(self).map_err(|_| fmt::Error)?;
+        f.write_str(&text)
+    }
+}
+
+/// 以新分配的请求 id 构造请求。
+pub fn worker_request(body: RequestBody) -> WorkerRequest {
+    WorkerRequest {
+        id: NEXT_REQUEST_ID.fetch_add(1, Ordering::Relaxed),
+        protocol: PROTOCOL_VERSION,
+        body,
+    }
+}
+
+/// worker 输出的事件，按类型校验转发端和界面依赖的字段（与 `EVENT_FIELDS` 一致）。
+/// 其余字段（进度、种子、重试次数等）不在这里建模，随 `WorkerMessage::raw` 原样转发。
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WorkerEvent {
+    RecipientsLoaded {
+        stats: Value,
+        recipients_preview: Vec<Value>,
+    },
+    SmtpTestSucceeded,
+    Hello {
+        protocol: u32,
+        min_protocol: u32,
+        features: Vec<String>,
+        #[serde(default)]
+        framing: Framing,
+    },
+    Pong {
+        protocol: u32,
+    },
+    JobAccepted {
+        job_id: String,
+    },
+    CancelRequested,
+    PauseRequested,
+    ResumeRequested,
+    ThrottleUpdated {
+        limits: Value,
+    },
+    JobStarted {
+        job_id: String,
+        total: u64,
+    },
+    RecipientStarted {
+        job_id: String,
+        index: u64,
+        email: String,
+        name: String,
+    },
+    RecipientSent {
+        job_id: String,
+        index: u64,
+        email: String,
+        name: String,
+    },
+    RecipientFailed {
+        job_id: String,
+        index: u64,
+        email: String,
+        name: String,
+        error: String,
+    },
+    RecipientSkipped {
+        job_id: String,
+        index: u64,
+        email: String,
+        name: String,
+        reason: String,
+    },
+    RecipientDeferred {
+        job_id: String,
+        index: u64,
+        email: String,
+        name: String,
+        domain: String,
+        attempt: u32,
+        retry_after_sec: u64,
+        retry_at: String,
+        reason: String,
+    },
+    DeferredWait {
+        job_id: String,
+        index: u64,
+        email: String,
+        remaining_sec: f64,
+    },
+    InterSendWait {
+        job_id: String,
+        index: u64,
+        next_index: u64,
+        delay_sec: f64,
+        remaining_sec: f64,
+    },
+    JobPaused {
+        job_id: String,
+        next_index: u64,
+    },
+    JobResumed {
+        job_id: String,
+        next_index: u64,
+    },
+    JobFinished {
+        job_id: String,
+        success: u64,
+        failed: u64,
+        skipped: u64,
+        total: u64,
+        failures: Vec<Value>,
+    },
+    JobCancelled {
+        job_id: String,
+        success: u64,
+        failed: u64,
+        skipped: u64,
+        total: u64,
+    },
+    Error {
+        error: String,
+    },
+    /// 新版 worker 的事件类型，不做校验照常转发。
+    #[serde(other)]
+    Unknown,
+}
+
+impl WorkerEvent {
+    /// 任务结束（完成、取消或出错）的事件。
+    pub fn ends_job(&self) -> bool {
+        matches!(
+            self,
+            WorkerEvent::JobFinished { .. } | WorkerEvent::JobCancelled { .. } | WorkerEvent::Error { .. }
+        )
+    }
+}
+
+/// worker 输出的一条消息。
+#[derive(Clone, Debug, PartialEq)]
+pub struct WorkerMessage {
+    /// 消息所属请求的 id；协议 1 的 worker 不带。
+    pub request_id: Option<u64>,
+    pub event: WorkerEvent,
+    /// 完整的事件 JSON，原样转发给界面。
+    pub raw: Value,
+}
+
+impl WorkerMessage {
+    /// 是否属于给定请求；不带 `request_id` 的消息（协议 1）视为属于当前唯一的请求。
+    pub fn answers(&self, request: &WorkerRequest) -> bool {
+        self.request_id.is_none_or(|id| id == request.id)
+    }
+}
+
+/// 汇总发送任务 worker 的事件，worker 进程退出后生成唯一的结束事件，界面总能进入结束状态。
+/// worker 自己的 `job_finished` / `job_cancelled` 暂缓到退出后附上退出码与耗时再发出；
+/// 没有输出这两者就退出（崩溃、被强制结束或报错）时改发 `job_failed`，计数取自已转发的收件人事件。
+#[derive(Default, Debug)]
+pub struct JobExitSummary {
+    success: u64,
+    failed: u64,
+    skipped: u64,
+    total: Option<u64>,
+    summary: Option<Value>,
+    error: Option<String>,
+}
+
+impl JobExitSummary {
+    /// 记录一条事件；返回 `false` 表示该事件应暂缓转发，由 `exit_event` 在 worker 退出后发出。
+    pub fn observe(&mut self, event: &Value) -> bool {
+        match event["type"].as_str() {
+            Some("job_started") => self.total = event["total"].as_u64(),
+            Some("recipient_sent") => self.success += 1,
+            Some("recipient_failed") => self.failed += 1,
+            Some("recipient_skipped") => self.skipped += 1,
+            Some("error") => self.error = event["error"].as_str().map(str::to_string),
+            Some("job_finished" | "job_cancelled") => {
+                self.summary = Some(event.clone());
+                return false;
+            }
+            _ => {}
+        }
+        true
+    }
+
+    /// worker 退出后的结束事件。`exit_code` 为 `None` 表示进程被信号结束。
+    pub fn exit_event(self, job_id: &str, exit_code: Option<i32>, duration: Duration) -> Value {
+        let duration_sec = (duration.as_secs_f64() * 1000.0).round() / 1000.0;
+        if let Some(mut summary) = self.summary {
+            summary["exit_code"] = json!(exit_code);
+            summary["duration_sec"] = json!(duration_sec);
+            return summary;
+        }
+        let error = self.error.unwrap_or_else(|| match exit_code {
+            Some(code) => format!("worker 未报告任务结果就退出了（退出码 {code}）"),
+            None => "worker 未报告任务结果就被终止".to_string(),
+        });
+        json!({
+            "type": "job_failed",
+            "job_id": job_id,
+            "exit_code": exit_code,
+            "duration_sec": duration_sec,
+            "success": self.success,
+            "failed": self.failed,
+            "skipped": self.skipped,
+            "total": self.total,
+            "error": error,
+        })
+    }
+}
+
+/// 已知事件缺少的字段；未知事件类型返回 `None`（新版 worker 的事件照常转发）。
+pub fn missing_fields(event: &Value) -> Option<Vec<&'static str>> {
+    let kind = event["type"].as_str()?;
+    let (_, fields) = EVENT_FIELDS.iter().find(|(name, _)| *name == kind)?;
+    Some(fields.iter().copied().filter(|field| event.get(*field).is_none()).collect())
+}
+
+/// 解析 worker 输出的一行。成功时返回消息；格式错误（不是 JSON 对象、缺少 `type`、
+/// 已知事件缺少字段或字段类型不对）时返回应转发给界面的 `error` 事件。
+pub fn parse_worker_line(raw: &str) -> Result<WorkerMessage, Value> {
+    let event: Value = serde_json::from_str(raw.trim_end_matches('\r')).map_err(|err| invalid(err.to_string()))?;
+    let Some(kind) = event.get("type").and_then(Value::as_str) else {
+        return Err(invalid("missing string field `type`".to_string()));
+    };
+    let request_id = match event.get("request_id") {
+        None | Some(Value::Null) => None,
+        Some(id) => Some(id.as_u64().ok_or_else(|| invalid(format!("{kind}: request_id must be an integer")))?),
+    };
+    let typed = WorkerEvent::deserialize(&event).map_err(|err| invalid(format!("{kind}: {err}")))?;
+    Ok(WorkerMessage {
+        request_id,
+        event: typed,
+        raw: event,
+    })
+}
+
+fn invalid(reason: String) -> Value {
+    json!({ "type": "error", "error": format!("invalid worker payload: {reason}") })
+}
+
+/// 逐行读取 worker 输出直到 EOF：合法消息以 `Ok` 回调，格式错误或读取失败以 `Err`（error 事件）回调。
+/// 按字节读取行并有损解码，单个非法 UTF-8 字节不会中断后续事件的转发。
+pub fn forward_worker_events(reader: impl std::io::Read, on_event: impl FnMut(Result<WorkerMessage, Value>)) {
+    forward_framed_events(reader, Framing::Lines, on_event)
+}
+
+/// 同 `forward_worker_events`，按握手协商的分帧方式读取。
+pub fn forward_framed_events(
+    reader: impl std::io::Read,
+    framing: Framing,
+    mut on_event: impl FnMut(Result<WorkerMessage, Value>),
+) {
+    let mut reader = BufReader::new(reader);
+    loop {
+        match framing.read_message(&mut reader) {
+            Ok(None) => break,
+            Ok(Some(Ok(message))) => on_event(parse_worker_line(&message)),
+            Ok(Some(Err(reason))) => on_event(Err(invalid(reason))),
+            Err(err) => {
+                on_event(Err(json!({ "type": "error", "error": format!("worker stdout read failure: {err}") })));
+                break;
+            }
+        }
+    }
+}
+
+/// 逐行读取 worker 的标准错误直到 EOF 或读取失败，每个非空行（有损解码、去掉行尾）回调一次。
+/// Python 的异常堆栈写在这里；打包后的应用没有终端，需要由调用方转发给界面或写入日志。
+pub fn read_stderr_lines(reader: impl std::io::Read, mut on_line: impl FnMut(String)) {
+    let mut reader = BufReader::new(reader);
+    let mut buffer = Vec::new();
+    loop {
+        buffer.clear();
+        match reader.read_until(b'\n', &mut buffer) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {
+                let line = String::from_utf8_lossy(&buffer);
+                let line = line.trim_end_matches(['\r', '\n']);
+                if !line.trim().is_empty() {
+                    on_line(line.to_string());
+                }
+            }
+        }
+    }
+}
+
+/// 与刚启动的 worker 握手，确认协议兼容且支持 `request` 后按协商的分帧方式写入这个请求，返回 worker 的握手应答。
+/// 握手只逐字节读取应答这一行，之后的输出仍完整留给调用方的事件转发（按 `WorkerHello::framing` 读取）。
+/// 超过 `HANDSHAKE_TIMEOUT` 仍未完成时结束 worker 进程并返回错误。
+pub fn send_first_request(worker: &mut SpawnedWorker, request: &WorkerRequest) -> Result<WorkerHello, String> {
+    send_first_request_within(worker, request, HANDSHAKE_TIMEOUT)
+}
+
+fn send_first_request_within(
+    worker: &mut SpawnedWorker,
+    request: &WorkerRequest,
+    timeout: Duration,
+) -> Result<WorkerHello, String> {
+    // 交给单独的线程读写，完成后把通道还给 `worker`；超时后线程阻塞在读取上，结束进程后随之退出。
+    let mut requests = std::mem::replace(&mut worker.requests, Box::new(io::sink()));
+    let mut events = std::mem::replace(&mut worker.events, Box::new(io::empty()));
+    let request = request.clone();
+    let exchange = run_with_timeout(timeout, move || {
+        let hello = handshake(&mut requests, &mut events, &request)?;
+        Ok((hello, requests, events))
+    });
+    let Some(result) = exchange else {
+        let _ = worker.child.kill();
+        let _ = worker.child.wait();
+        return Err(format!("worker 未在 {} 秒内应答启动握手，已结束 worker 进程", timeout.as_secs_f64()));
+    };
+    let (hello, requests, events) = result?;
+    worker.requests = requests;
+    worker.events = events;
+    Ok(hello)
+}
+
+fn handshake(
+    requests: &mut impl Write,
+    events: &mut impl Read,
+    request: &WorkerRequest,
+) -> Result<WorkerHello, String> {
+    let hello = worker_request(RequestBody::Hello(json!({ "framing": [Framing::LengthPrefixed] })));
+    Framing::Lines
+        .write_message(requests, &hello.to_string())
+        .map_err(|err| format!("failed to write worker request: {err}"))?;
+    let reply = loop {
+        let line = read_line_unbuffered(events)
+            .map_err(|err| format!("failed to read worker response: {err}"))?
+            .ok_or_else(|| "worker 启动后没有应答握手".to_string())?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let message = parse_worker_line(&line)
+            .map_err(|error| format!("invalid worker response: {}", error["error"]))?;
+        if message.answers(&hello) {
+            break message.event;
+        }
+    };
+    let hello = match reply {
+        WorkerEvent::Hello {
+            protocol,
+            min_protocol,
+            features,
+            framing,
+        } => WorkerHello {
+            protocol,
+            min_protocol,
+            features,
+            framing,
+        },
+        // 协议 3 之前的 worker 不认识 `hello`。
+        _ => return Err("worker 版本过旧，不支持启动握手，请更新 worker / Python 运行时".to_string()),
+    };
+    hello.check_compatible()?;
+    hello.check_supports(&request.body)?;
+    hello
+        .framing
+        .write_message(requests, &request.to_string())
+        .map_err(|err| format!("failed to write worker request: {err}"))?;
+    Ok(hello)
+}
+
+fn read_line_unbuffered(reader: &mut impl Read) -> std::io::Result<Option<String>> {
+    let mut line = Vec::new();
+    let mut byte = [0u8; 1];
+    loop {
+        match reader.read(&mut byte)? {
+            0 if line.is_empty() => return Ok(None),
+            0 => break,
+            _ if byte[0] == b'\n' => break,
+            _ => line.push(byte[0]),
+        }
+    }
+    Ok(Some(String::from_utf8_lossy(&line).into_owned()))
+}
+
+/// 一问一答的请求失败时附带的 worker 诊断输出行数（通常是异常堆栈的末尾）。
+const STDERR_TAIL_LINES: usize = 20;
+/// 一问一答请求的默认超时，包含启动 Python 解释器与读取较大的收件人文件的时间。
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
+/// 任务 worker 启动握手的超时，包含启动 Python 解释器与导入依赖的时间。
+pub const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(30);
+/// 结束 worker 后等待诊断输出读完的时间；worker 派生的子进程可能仍占用管道，不能无限等待。
+const READER_GRACE: Duration = Duration::from_secs(2);
+
+/// 一问一答请求的失败。
+#[derive(Clone, Debug, PartialEq)]
+pub enum WorkerRequestError {
+    /// worker 未在 `timeout` 内应答，进程已被结束。`partial_output` 为此前收到的、不属于该请求的消息
+    /// 与诊断输出的末尾，供排查 worker 卡在哪里。
+    Timeout {
+        kind: String,
+        timeout: Duration,
+        partial_output: String,
+    },
+    /// 启动、握手、读写失败或 worker 的输出无法解析。
+    Failed(String),
+}
+
+impl fmt::Display for WorkerRequestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WorkerRequestError::Timeout {
+                kind,
+                timeout,
+                partial_output,
+            } => {
+                write!(f, "worker 未在 {} 秒内应答 `{kind}` 请求，已结束 worker 进程", timeout.as_secs_f64())?;
+                if !partial_output.is_empty() {
+                    write!(f, "\n{partial_output}")?;
+                }
+                Ok(())
+            }
+            WorkerRequestError::Failed(message) => f.write_str(message),
+        }
+    }
+}
+
+impl From<String> for WorkerRequestError {
+    fn from(message: String) -> Self {
+        WorkerRequestError::Failed(message)
+    }
+}
+
+/// 最近的若干行输出：读取线程写入，出错或超时时由调用方取出附在错误信息后面。
+#[derive(Clone, Default)]
+struct Tail(Arc<Mutex<VecDeque<String>>>);
+
+impl Tail {
+    fn push(&self, line: String) {
+        if let Ok(mut lines) = self.0.lock() {
+            if lines.len() == STDERR_TAIL_LINES {
+                lines.pop_front();
+            }
+            lines.push_back(line);
+        }
+    }
+
+    /// 以 `worker <label>:` 开头的一段文本；没有输出时为空。
+    fn section(&self, label: &str) -> String {
+        let lines = self.0.lock().map(|lines| Vec::from(lines.clone())).unwrap_or_default();
+        if lines.is_empty() {
+            String::new()
+        } else {
+            format!("worker {label}:\n{}", lines.join("\n"))
+        }
+    }
+}
+
+/// 在单独的线程中执行与 worker 的交互，超过 `timeout` 仍未完成时返回 `None`。
+/// 超时后线程仍阻塞在读取上，调用方结束 worker 进程后它随之退出。
+fn run_with_timeout<T: Send + 'static>(
+    timeout: Duration,
+    exchange: impl FnOnce() -> Result<T, String> + Send + 'static,
+) -> Option<Result<T, String>> {
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        let _ = sender.send(exchange());
+    });
+    match receiver.recv_timeout(timeout) {
+        Ok(result) => Some(result),
+        Err(RecvTimeoutError::Timeout) => None,
+        Err(RecvTimeoutError::Disconnected) => Some(Err("worker 通信线程异常退出".to_string())),
+    }
+}
+
+/// 启动 worker、握手后写入一个请求并读取该请求的第一条响应（一问一答的请求，如 `load_recipients`）。
+/// 属于其他请求的消息被跳过；没有得到响应时，错误信息附带 worker 标准错误的末尾几行。
+/// 超过 `DEFAULT_REQUEST_TIMEOUT` 仍无响应时结束 worker 并返回超时错误。
+pub fn run_worker_request_with(command: Command, request: WorkerRequest) -> Result<Value, WorkerRequestError> {
+    run_worker_request_via(command, request, WorkerTransport::Stdio, DEFAULT_REQUEST_TIMEOUT)
+}
+
+/// 同 `run_worker_request_with`，按 `transport` 选择与 worker 通信的通道，超时时间为 `timeout`。
+pub fn run_worker_request_via(
+    command: Command,
+    request: WorkerRequest,
+    transport: WorkerTransport,
+    timeout: Duration,
+) -> Result<Value, WorkerRequestError> {
+    let mut worker = spawn_worker(command, transport)?;
+    // 在单独的线程中读取，避免诊断输出写满管道后 worker 阻塞、迟迟没有响应。
+    let diagnostics: Vec<_> = std::mem::take(&mut worker.diagnostics)
+        .into_iter()
+        .map(|(stream, reader)| {
+            let tail = Tail::default();
+            let writer = tail.clone();
+            let reader = std::thread::spawn(move || read_stderr_lines(reader, |line| writer.push(line)));
+            (stream, tail, reader)
+        })
+        .collect();
+
+    let SpawnedWorker {
+        mut child,
+        mut requests,
+        mut events,
+        ..
+    } = worker;
+    let kind = request.body.kind();
+    let unanswered = Tail::default();
+    let skipped = unanswered.clone();
+    let response = run_with_timeout(timeout, move || {
+        let hello = handshake(&mut requests, &mut events, &request);
+        // 关闭请求通道，worker 读到 EOF 后处理完这个请求即退出。
+        drop(requests);
+        read_response(&mut BufReader::new(events), hello?.framing, &request, &skipped)
+    });
+    if let Some(Ok(response)) = response {
+        let _ = child.wait();
+        return Ok(response);
+    }
+    // 出错或超时时 worker 可能仍在运行，结束它以便读完诊断输出。
+    let _ = child.kill();
+    let _ = child.wait();
+    let started = Instant::now();
+    while diagnostics.iter().any(|(_, _, reader)| !reader.is_finished()) && started.elapsed() < READER_GRACE {
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    let output: Vec<String> = diagnostics
+        .iter()
+        .map(|(stream, tail, _)| tail.section(stream))
+        .filter(|section| !section.is_empty())
+        .collect();
+    match response {
+        Some(Err(mut error)) => {
+            for section in output {
+                error.push_str(&format!("\n{section}"));
+            }
+            Err(WorkerRequestError::Failed(error))
+        }
+        _ => Err(WorkerRequestError::Timeout {
+            kind,
+            timeout,
+            partial_output: partial_output(&unanswered, output),
+        }),
+    }
+}
+
+fn partial_output(unanswered: &Tail, diagnostics: Vec<String>) -> String {
+    let mut sections = vec![unanswered.section("events")];
+    sections.extend(diagnostics);
+    sections.retain(|section| !section.is_empty());
+    sections.join("\n")
+}
+
+/// 读取 `request` 的第一条响应；属于其他请求的消息记入 `unanswered`。
+fn read_response(
+    events: &mut impl BufRead,
+    framing: Framing,
+    request: &WorkerRequest,
+    unanswered: &Tail,
+) -> Result<Value, String> {
+    while let Some(message) =
+        framing.read_message(events).map_err(|err| format!("failed to read worker response: {err}"))?
+    {
+        match message.map_err(invalid).and_then(|message| parse_worker_line(&message)) {
+            Ok(message) if message.answers(request) => return Ok(message.raw),
+            Ok(message) => unanswered.push(message.raw.to_string()),
+            Err(error) => return Err(format!("invalid worker response: {}", error["error"])),
+        }
+    }
+    Err("worker returned empty response".to_string())
+}
+
+/// 常驻 worker：握手一次后保持运行，一问一答的请求（如 `load_recipients`）依次复用同一进程，
+/// 省去每次启动 Python 解释器的时间。同一时间只处理一个请求，由调用方加锁；丢弃时结束进程。
+pub struct ResidentWorker {
+    child: Child,
+    /// 与 worker 的通道，请求期间交给通信线程；请求出错或超时后不再归还，worker 随之不可用。
+    channel: Option<ResidentChannel>,
+    timeout: Duration,
+}
+
+struct ResidentChannel {
+    requests: Box<dyn Write + Send>,
+    events: BufReader<Box<dyn Read + Send>>,
+    hello: WorkerHello,
+}
+
+impl ResidentWorker {
+    /// 与刚启动的 worker 握手并确认它应答 `ping`，之后每个请求最多等待 `timeout`。
+    /// 调用方应先取走 `diagnostics` 并持续读取，否则诊断输出写满管道后 worker 会阻塞。
+    pub fn start(worker: SpawnedWorker, timeout: Duration) -> Result<Self, WorkerRequestError> {
+        let SpawnedWorker {
+            child,
+            mut requests,
+            mut events,
+            ..
+        } = worker;
+        let mut resident = Self {
+            child,
+            channel: None,
+            timeout,
+        };
+        resident.exchange(worker_request(RequestBody::Ping), move |ping, unanswered| {
+            let hello = handshake(&mut requests, &mut events, ping)?;
+            let mut channel = ResidentChannel {
+                requests,
+                events: BufReader::new(events),
+                hello,
+            };
+            let response = read_response(&mut channel.events, channel.hello.framing, ping, unanswered)?;
+            Ok((channel, response))
+        })?;
+        Ok(resident)
+    }
+
+    /// 写入请求并读取它的第一条响应。出错或超时后 worker 不再可用，调用方应丢弃它。
+    pub fn request(&mut self, request: &WorkerRequest) -> Result<Value, WorkerRequestError> {
+        let Some(mut channel) = self.channel.take() else {
+            return Err(WorkerRequestError::Failed("常驻 worker 已不可用".to_string()));
+        };
+        if let Err(err) = channel.hello.check_supports(&request.body) {
+            self.channel = Some(channel);
+            return Err(err.into());
+        }
+        self.exchange(request.clone(), move |request, unanswered| {
+            channel
+                .hello
+                .framing
+                .write_message(&mut channel.requests, &request.to_string())
+                .map_err(|err| format!("failed to write worker request: {err}"))?;
+            let response = read_response(&mut channel.events, channel.hello.framing, request, unanswered)?;
+            Ok((channel, response))
+        })
+    }
+
+    /// 在通信线程中执行 `exchange`，成功时收回通道；超时则结束 worker 进程。
+    fn exchange(
+        &mut self,
+        request: WorkerRequest,
+        exchange: impl FnOnce(&WorkerRequest, &Tail) -> Result<(ResidentChannel, Value), String> + Send + 'static,
+    ) -> Result<Value, WorkerRequestError> {
+        let kind = request.body.kind();
+        let unanswered = Tail::default();
+        let skipped = unanswered.clone();
+        match run_with_timeout(self.timeout, move || exchange(&request, &skipped)) {
+            Some(Ok((channel, response))) => {
+                self.channel = Some(channel);
+                Ok(response)
+            }
+            Some(Err(error)) => Err(error.into()),
+            None => {
+                let _ = self.child.kill();
+                Err(WorkerRequestError::Timeout {
+                    kind,
+                    timeout: self.timeout,
+                    partial_output: partial_output(&unanswered, Vec::new()),
+                })
+            }
+        }
+    }
+
+    /// worker 进程是否仍在运行且可以处理请求。
+    pub fn is_running(&mut self) -> bool {
+        self.channel.is_some() && matches!(self.child.try_wait(), Ok(None))
+    }
+
+    pub fn id(&self) -> u32 {
+        self.child.id()
+    }
+}
+
+impl Drop for ResidentWorker {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        forward_framed_events, forward_worker_events, missing_fields, parse_worker_line, read_stderr_lines,
+        send_first_request_within, worker_request, Framing, JobExitSummary, RequestBody, WorkerEvent, WorkerHello,
+        WorkerRequest, MAX_FRAME_BYTES, PROTOCOL_VERSION,
+    };
+    use serde_json::json;
+    use std::time::Duration;
+
+    #[test]
+    fn forwards_valid_events_and_reports_malformed_lines() {
+        let output = b"{\"type\":\"job_started\",\"job_id\":\"j\",\"total\":1}\r\n\n[1,2]\n{\"type\":\"recipient_sent\",\"job_id\":\"j\",\"index\":1,\"name\":\"n\",\"email\":\"\xff\"}\n{\"job_id\":\"j\"}\nnot json";
+        let mut events = Vec::new();
+        forward_worker_events(&output[..], |event| events.push(event));
+        assert_eq!(events.len(), 5);
+        assert_eq!(events[0].as_ref().expect("started").raw["total"], 1);
+        assert!(events[1].is_err());
+        assert_eq!(events[2].as_ref().expect("lossy utf-8").raw["email"], "\u{fffd}");
+        assert!(events[3].is_err() && events[4].is_err());
+
+        assert_eq!(missing_fields(&json!({ "type": "recipient_sent", "job_id": "j" })).expect("known").len(), 3);
+        assert!(missing_fields(&json!({ "type": "future_event" })).is_none());
+        assert!(parse_worker_line("{\"type\": 3}").is_err());
+    }
+
+    #[test]
+    fn parses_typed_messages_with_request_ids() {
+        let message = parse_worker_line(r#"{"type":"job_started","job_id":"j","total":2,"seed":7,"request_id":4}"#)
+            .expect("typed event");
+        assert_eq!(message.request_id, Some(4));
+        assert_eq!(message.event, WorkerEvent::JobStarted { job_id: "j".to_string(), total: 2 });
+        assert_eq!(message.raw["seed"], 7);
+
+        let legacy = parse_worker_line(r#"{"type":"future_event","detail":1}"#).expect("unknown events pass");
+        assert_eq!((legacy.request_id, &legacy.event), (None, &WorkerEvent::Unknown));
+        // 字段缺失或类型不对的已知事件在转发前被拦下。
+        assert!(parse_worker_line(r#"{"type":"job_started","job_id":"j"}"#).is_err());
+        assert!(parse_worker_line(r#"{"type":"recipient_sent","job_id":"j","index":"1","email":"a","name":"b"}"#).is_err());
+        assert!(parse_worker_line(r#"{"type":"cancel_requested","request_id":"x"}"#).is_err());
+
+        let first = worker_request(RequestBody::Cancel(json!({})));
+        let second = worker_request(RequestBody::Ping);
+        assert!(second.id > first.id);
+        let wire: serde_json::Value = serde_json::from_str(&first.to_string()).expect("request json");
+        assert_eq!(wire, json!({ "id": first.id, "protocol": PROTOCOL_VERSION, "type": "cancel", "payload": {} }));
+        let answered = WorkerRequest { id: 4, ..first.clone() };
+        assert!(message.answers(&answered) && !message.answers(&second) && legacy.answers(&second));
+        assert_eq!(RequestBody::from_kind("ping", json!(null)), Some(RequestBody::Ping));
+        assert!(RequestBody::from_kind("unknown_request", json!({})).is_none());
+    }
+
+    #[test]
+    fn hello_checks_protocol_range_and_features() {
+        let hello = WorkerHello {
+            protocol: PROTOCOL_VERSION,
+            min_protocol: 1,
+            features: vec!["start_send".to_string(), "cancel".to_string()],
+            framing: Framing::Lines,
+        };
+        assert!(hello.check_compatible().is_ok());
+        assert!(hello.check_supports(&RequestBody::StartSend(json!({}))).is_ok());
+        let missing = hello.check_supports(&RequestBody::Pause).expect_err("pause is not advertised");
+        assert!(missing.contains("`pause`") && missing.contains("更新 worker"), "{missing}");
+
+        let old = WorkerHello { protocol: 2, ..hello.clone() };
+        assert!(old.check_compatible().expect_err("too old").contains("更新 worker"));
+        let newer = WorkerHello { min_protocol: PROTOCOL_VERSION + 1, ..hello };
+        assert!(newer.check_compatible().expect_err("too new").contains("更新应用"));
+    }
+
+    #[test]
+    fn length_prefixed_frames_carry_newlines_and_resync_after_stray_lines() {
+        let pretty = "{\n  \"type\": \"job_started\",\n  \"job_id\": \"j\",\n  \"total\": 1\n}";
+        let mut output = Vec::new();
+        Framing::LengthPrefixed.write_message(&mut output, pretty).expect("write frame");
+        output.extend_from_slice(b"debug print from a library\n\r\n");
+        Framing::LengthPrefixed
+            .write_message(&mut output, r#"{"type":"recipient_sent","job_id":"j","index":1,"email":"a","name":"教师"}"#)
+            .expect("write frame");
+        output.extend_from_slice(format!("@{}\n", MAX_FRAME_BYTES + 1).as_bytes());
+
+        let mut events = Vec::new();
+        forward_framed_events(&output[..], Framing::LengthPrefixed, |event| events.push(event));
+        assert_eq!(events.len(), 4);
+        assert_eq!(events[0].as_ref().expect("multi-line frame").raw["total"], 1);
+        let stray = events[1].as_ref().expect_err("stray line");
+        assert!(stray["error"].as_str().unwrap_or_default().contains("debug print"), "{stray}");
+        assert_eq!(events[2].as_ref().expect("next frame").raw["name"], "教师");
+        assert!(events[3].as_ref().expect_err("oversized frame")["error"].as_str().unwrap_or_default().contains("limit"));
+
+        let hello = parse_worker_line(r#"{"type":"hello","protocol":3,"min_protocol":1,"features":[]}"#).expect("hello");
+        assert!(matches!(hello.event, WorkerEvent::Hello { framing: Framing::Lines, .. }));
+        let wire: serde_json::Value = serde_json::from_str(
+            &worker_request(RequestBody::Hello(json!({ "framing": [Framing::LengthPrefixed] }))).to_string(),
+        )
+        .expect("hello json");
+        assert_eq!(wire["payload"], json!({ "framing": ["length_prefixed"] }));
+    }
+
+    /// 每次最多读出几个字节，模拟管道与套接字上被拆散的读取。
+    struct Trickle<'a>(&'a [u8]);
+
+    impl std::io::Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let count = buf.len().min(self.0.len()).min(3);
+            buf[..count].copy_from_slice(&self.0[..count]);
+            self.0 = &self.0[count..];
+            Ok(count)
+        }
+    }
+
+    #[test]
+    fn length_prefixed_frames_survive_partial_reads_and_reject_truncated_or_oversized_frames() {
+        let large = format!("{{\"type\":\"log\",\"message\":\"{}\"}}", "群发".repeat(20_000));
+        let messages = ["{\"type\":\"job_started\",\n\"total\":1}", "{\"name\":\"教师\"}", large.as_str()];
+        let mut wire = Vec::new();
+        for message in messages {
+            Framing::LengthPrefixed.write_message(&mut wire, message).expect("write");
+        }
+        let mut reader = std::io::BufReader::with_capacity(4, Trickle(&wire));
+        for message in messages {
+            let read = Framing::LengthPrefixed.read_message(&mut reader).expect("read").expect("frame");
+            assert_eq!(read.as_deref(), Ok(message));
+        }
+        assert!(Framing::LengthPrefixed.read_message(&mut reader).expect("eof").is_none());
+
+        // 连接在帧体中途或帧头之后关闭时报错，而不是返回半截消息。
+        let mut frame = Vec::new();
+        Framing::LengthPrefixed.write_message(&mut frame, &large).expect("write");
+        for cut in [frame.len() / 2, 8, 7] {
+            let mut reader = std::io::BufReader::new(Trickle(&frame[..cut]));
+            let error = Framing::LengthPrefixed.read_message(&mut reader).expect_err("truncated frame");
+            assert_eq!(error.kind(), std::io::ErrorKind::UnexpectedEof);
+        }
+
+        let oversized = format!("@{}\n{{}}\n", MAX_FRAME_BYTES + 1);
+        let error = Framing::LengthPrefixed.read_message(&mut oversized.as_bytes()).expect_err("oversized frame");
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        let exact = format!("@{MAX_FRAME_BYTES}\n");
+        let error = Framing::LengthPrefixed.read_message(&mut exact.as_bytes()).expect_err("missing body");
+        assert_eq!(error.kind(), std::io::ErrorKind::UnexpectedEof);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn gives_up_on_a_worker_that_never_answers_the_handshake() {
+        use crate::worker_ipc::{spawn_worker, WorkerTransport};
+        let mut command = std::process::Command::new("sh");
+        command.args(["-c", "sleep 30"]);
+        let mut worker = spawn_worker(command, WorkerTransport::Stdio).expect("spawn");
+        let request = worker_request(RequestBody::StartSend(json!({ "job_id": "j" })));
+        let error = send_first_request_within(&mut worker, &request, Duration::from_millis(200)).expect_err("silent");
+        assert!(error.contains("启动握手"), "{error}");
+        assert!(worker.child.try_wait().expect("reaped").is_some());
+    }
+
+    #[test]
+    fn exit_summary_holds_back_the_final_event_or_reports_a_failure() {
+        let mut finished = JobExitSummary::default();
+        assert!(finished.observe(&json!({ "type": "job_started", "job_id": "j", "total": 2 })));
+        assert!(!finished.observe(&json!({ "type": "job_finished", "job_id": "j", "success": 2, "total": 2 })));
+        let event = finished.exit_event("j", Some(0), Duration::from_millis(1500));
+        assert_eq!((&event["type"], &event["success"]), (&json!("job_finished"), &json!(2)));
+        assert_eq!((&event["exit_code"], &event["duration_sec"]), (&json!(0), &json!(1.5)));
+
+        let mut crashed = JobExitSummary::default();
+        for kind in ["job_started", "recipient_sent", "recipient_failed", "recipient_skipped", "recipient_sent"] {
+            assert!(crashed.observe(&json!({ "type": kind, "job_id": "j", "total": 9 })));
+        }
+        let event = crashed.exit_event("j", Some(1), Duration::from_secs(3));
+        assert_eq!(
+            event,
+            json!({
+                "type": "job_failed", "job_id": "j", "exit_code": 1, "duration_sec": 3.0,
+                "success": 2, "failed": 1, "skipped": 1, "total": 9,
+                "error": "worker 未报告任务结果就退出了（退出码 1）",
+            })
+        );
+
+        let mut errored = JobExitSummary::default();
+        errored.observe(&json!({ "type": "error", "error": "SMTP 登录失败" }));
+        let event = errored.exit_event("j", None, Duration::ZERO);
+        assert_eq!(event["error"], "SMTP 登录失败");
+        assert!(event["exit_code"].is_null() && event["total"].is_null());
+    }
+
+    #[test]
+    fn reads_stderr_lines_lossily() {
+        let stderr = b"Traceback (most recent call last):\r\n\n  File \"worker.py\"\nValueError: \xff bad\n";
+        let mut lines = Vec::new();
+        read_stderr_lines(&stderr[..], |line| lines.push(line));
+        assert_eq!(lines, ["Traceback (most recent call last):", "  File \"worker.py\"", "ValueError: \u{fffd} bad"]);
+    }
+}
@@ -0,0 +1,76 @@
+//! 纯 Rust 的假 worker：读取请求，按 Python worker 的格式输出事件；握手协商了分帧时之后的消息改用长度前缀帧。
+//! 设置了 `BULK_EMAIL_WORKER_IPC` 时与 Python worker 一样改走本地套接字，并在标准输出打印调试信息。
+
+use bulk_email_core::worker_ipc::IPC_ADDRESS_ENV;
+use bulk_email_core::worker_protocol::{Framing, PROTOCOL_VERSION};
+use interprocess::local_socket::{prelude::*, GenericFilePath, Stream};
+use serde_json::{json, Value};
+use std::io::{self, BufRead, BufReader, Write};
+use worker_harness::{canonical_events, malformed_output, CRASH_TRACEBACK, MODE_ENV};
+
+fn main() -> io::Result<()> {
+    match std::env::var_os(IPC_ADDRESS_ENV) {
+        Some(address) => {
+            let connect = || Stream::connect(address.as_os_str().to_fs_name::<GenericFilePath>()?);
+            // 与 Python worker 的约定一致：先连事件通道，再连请求通道。
+            let events = connect()?;
+            let requests = connect()?;
+            println!("fake worker: connected over local socket");
+            serve(BufReader::new(requests), events)
+        }
+        None => serve(io::stdin().lock(), io::stdout().lock()),
+    }
+}
+
+fn hello_events(request: &Value) -> Vec<Value> {
+    let mut events = canonical_events(request);
+    match std::env::var(MODE_ENV).as_deref() {
+        Ok("legacy") => events[0] = json!({ "type": "error", "error": "Unknown message type: hello", "request_id": request["id"] }),
+        Ok("future") => events[0]["min_protocol"] = json!(PROTOCOL_VERSION + 1),
+        Ok("lines") => {
+            events[0]["protocol"] = json!(3);
+            events[0].as_object_mut().map(|hello| hello.remove("framing"));
+        }
+        _ => {}
+    }
+    events
+}
+
+fn serve(mut input: impl BufRead, mut out: impl Write) -> io::Result<()> {
+    if std::env::var(MODE_ENV).as_deref() == Ok("crash") {
+        // 先读完握手请求，调用方写入时不会遇到已关闭的管道。
+        input.read_line(&mut String::new())?;
+        io::stderr().write_all(CRASH_TRACEBACK.as_bytes())?;
+        std::process::exit(1);
+    }
+    if std::env::var(MODE_ENV).as_deref() == Ok("malformed") {
+        out.write_all(&malformed_output())?;
+        out.flush()?;
+    }
+    let mut framing = Framing::Lines;
+    while let Some(message) = framing.read_message(&mut input)? {
+        let request =
+            message.and_then(|message| serde_json::from_str::<Value>(&message).map_err(|err| err.to_string()));
+        let events = match request {
+            Ok(request) if request["type"] == "hello" => hello_events(&request),
+            Ok(request) if std::env::var(MODE_ENV).as_deref() == Ok("hang") => {
+                let progress = json!({ "type": "loading_progress", "rows": 100, "request_id": 0 });
+                framing.write_message(&mut out, &progress.to_string())?;
+                eprintln!("fake worker: still working on {}", request["type"]);
+                loop {
+                    std::thread::sleep(std::time::Duration::from_secs(60));
+                }
+            }
+            Ok(request) => canonical_events(&request),
+            Err(err) => vec![json!({ "type": "error", "error": format!("Invalid request: {err}") })],
+        };
+        for event in &events {
+            framing.write_message(&mut out, &event.to_string())?;
+        }
+        // 与 Python worker 一样，握手应答仍按行写出，之后才切换分帧。
+        if let Some(chosen) = events.first().and_then(|event| event.get("framing")) {
+            framing = serde_json::from_value(chosen.clone()).unwrap_or_default();
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,186 @@
+//! worker 协议测试工具：纯 Rust 的假 worker（`fake-worker`）按 Python worker 的格式回放事件，
+//! 配合 golden 文件和模糊测试，保证协议变更不会悄悄破坏桌面端的事件转发。
+
+use bulk_email_core::worker_protocol::{forward_worker_events, WorkerMessage, PROTOCOL_VERSION, REQUEST_TYPES};
+use serde_json::{json, Value};
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+/// 设为 `malformed` 时，假 worker 在正常响应前先输出一批格式错误的行；
+/// 设为 `crash` 时，读到第一个请求后只向标准错误写出异常堆栈并退出，不做响应；
+/// 设为 `legacy` / `future` 时，分别像协议 3 之前（不认识 `hello`）与只接受更新协议的 worker 那样应答握手；
+/// 设为 `lines` 时像协议 3 的 worker 那样应答握手但不协商分帧，之后继续按行通信；
+/// 设为 `hang` 时正常握手，之后对请求只输出一条进度事件与一行标准错误，不再应答。
+pub const MODE_ENV: &str = "FAKE_WORKER_MODE";
+/// 设为 `1` 时，golden 测试改写而不是比对 golden 文件。
+pub const UPDATE_GOLDEN_ENV: &str = "UPDATE_GOLDEN";
+
+const FAKE_JOB_ID: &str = "fake-job";
+
+fn recipient(index: usize) -> (String, String) {
+    (format!("teacher{index}@example.com"), format!("教师{index}"))
+}
+
+/// 假 worker 对一个请求的响应事件，字段与 Python worker / 引擎保持一致；请求带 `id` 时每个事件以 `request_id` 指回它。
+pub fn canonical_events(request: &Value) -> Vec<Value> {
+    let mut events = request_events(request);
+    if let Some(id) = request.get("id").filter(|id| !id.is_null()) {
+        for event in &mut events {
+            event["request_id"] = id.clone();
+        }
+    }
+    events
+}
+
+fn request_events(request: &Value) -> Vec<Value> {
+    let kind = request["type"].as_str().unwrap_or_default();
+    let payload = &request["payload"];
+    match kind {
+        "load_recipients" => vec![json!({
+            "type": "recipients_loaded",
+            "stats": {
+                "total_rows": 2,
+                "valid_rows": 2,
+                "sendable_rows": 2,
+                "invalid_rows": 0,
+                "invalid_email_rows": 0,
+                "missing_name_rows": 0,
+                "duplicate_rows": 0,
+                "empty_rows": 0,
+            },
+            "recipients_preview": (1..=2)
+                .map(|index| {
+                    let (email, name) = recipient(index);
+                    json!({ "email": email, "name": name })
+                })
+                .collect::<Vec<_>>(),
+        })],
+        "hello" => {
+            let mut hello = json!({
+                "type": "hello",
+                "protocol": PROTOCOL_VERSION,
+                "min_protocol": 1,
+                "features": REQUEST_TYPES,
+            });
+            let offered = payload["framing"].as_array().cloned().unwrap_or_default();
+            if offered.contains(&json!("length_prefixed")) {
+                hello["framing"] = json!("length_prefixed");
+            }
+            vec![hello]
+        }
+        "test_smtp" => vec![json!({ "type": "smtp_test_succeeded" })],
+        "ping" => vec![json!({ "type": "pong", "protocol": PROTOCOL_VERSION })],
+        "start_send" => send_transcript(payload["job_id"].as_str().unwrap_or(FAKE_JOB_ID)),
+        "cancel" => vec![
+            json!({ "type": "cancel_requested" }),
+            json!({ "type": "job_cancelled", "job_id": FAKE_JOB_ID, "success": 0, "failed": 0, "skipped": 0, "total": 0 }),
+        ],
+        "pause" => vec![
+            json!({ "type": "pause_requested" }),
+            json!({ "type": "job_paused", "job_id": FAKE_JOB_ID, "next_index": 1 }),
+        ],
+        "resume" => vec![
+            json!({ "type": "resume_requested" }),
+            json!({ "type": "job_resumed", "job_id": FAKE_JOB_ID, "next_index": 1 }),
+        ],
+        "throttle" => vec![json!({ "type": "throttle_updated", "limits": payload })],
+        other => vec![json!({ "type": "error", "error": format!("Unknown message type: {other}") })],
+    }
+}
+
+/// 一次发送任务的完整事件流：成功、失败、跳过、灰名单延后各一名收件人。
+fn send_transcript(job_id: &str) -> Vec<Value> {
+    let (sent, sent_name) = recipient(1);
+    let (failed, failed_name) = recipient(2);
+    let (skipped, skipped_name) = recipient(3);
+    let (deferred, deferred_name) = recipient(4);
+    vec![
+        json!({ "type": "job_accepted", "job_id": job_id }),
+        json!({ "type": "job_started", "job_id": job_id, "total": 4 }),
+        json!({ "type": "recipient_started", "job_id": job_id, "index": 1, "email": sent, "name": sent_name }),
+        json!({ "type": "recipient_sent", "job_id": job_id, "index": 1, "email": sent, "name": sent_name }),
+        json!({ "type": "inter_send_wait", "job_id": job_id, "index": 1, "next_index": 2, "delay_sec": 1, "remaining_sec": 1 }),
+        json!({ "type": "recipient_started", "job_id": job_id, "index": 2, "email": failed, "name": failed_name }),
+        json!({
+            "type": "recipient_failed", "job_id": job_id, "index": 2, "email": failed, "name": failed_name,
+            "error": "550 5.1.1 mailbox unavailable",
+        }),
+        json!({
+            "type": "recipient_skipped", "job_id": job_id, "index": 3, "email": skipped, "name": skipped_name,
+            "reason": "already_sent",
+        }),
+        json!({ "type": "recipient_started", "job_id": job_id, "index": 4, "email": deferred, "name": deferred_name }),
+        json!({
+            "type": "recipient_deferred", "job_id": job_id, "index": 4, "email": deferred, "name": deferred_name,
+            "domain": "example.com", "attempt": 1, "retry_after_sec": 300,
+            "retry_at": "2024-01-01T00:05:00", "reason": "451 4.7.1 greylisted",
+        }),
+        json!({ "type": "deferred_wait", "job_id": job_id, "index": 4, "email": deferred, "remaining_sec": 300 }),
+        json!({ "type": "recipient_started", "job_id": job_id, "index": 4, "email": deferred, "name": deferred_name, "attempt": 2 }),
+        json!({ "type": "recipient_sent", "job_id": job_id, "index": 4, "email": deferred, "name": deferred_name }),
+        json!({
+            "type": "job_finished", "job_id": job_id, "success": 2, "failed": 1, "skipped": 1, "deferred": 0, "total": 4,
+            "failures": [{ "email": failed, "name": failed_name, "error": "550 5.1.1 mailbox unavailable" }],
+        }),
+    ]
+}
+
+/// 典型的损坏输出：截断的 JSON、非法 UTF-8、字段缺失或类型不对的事件、非对象、缺少 `type`、CRLF 与空行。
+/// 最后一行是合法事件，用来确认转发在坏行之后仍在继续。
+pub fn malformed_output() -> Vec<u8> {
+    let mut output = Vec::new();
+    output.extend_from_slice(b"{\"type\": \"job_started\", \"job_id\": \"fake-job\"\n");
+    output.extend_from_slice(b"{\"type\": \"recipient_sent\", \"job_id\": \"fake-job\", \"index\": 1, \"name\": \"\xe6\x95\x99\xe5\xb8\x88\", \"email\": \"\xff\xfe@example.com\"}\n");
+    output.extend_from_slice(b"{\"type\": \"recipient_sent\", \"job_id\": \"fake-job\", \"index\": \"one\"}\n");
+    output.extend_from_slice(b"[\"recipient_sent\"]\n");
+    output.extend_from_slice(b"{\"job_id\": \"fake-job\"}\n");
+    output.extend_from_slice(b"Traceback (most recent call last):\n");
+    output.extend_from_slice(b"\n\r\n");
+    output.extend_from_slice(b"{\"type\": \"job_finished\", \"job_id\": \"fake-job\", \"success\": 0, \"failed\": 0, \"skipped\": 0, \"total\": 0, \"failures\": []}\r\n");
+    output
+}
+
+/// `crash` 模式写到标准错误的 Python 异常堆栈。
+pub const CRASH_TRACEBACK: &str = "Traceback (most recent call last):\n  File \"worker.py\", line 9, in <module>\nModuleNotFoundError: No module named 'openpyxl'\n";
+
+/// 启动假 worker，依次写入请求后关闭 stdin，按桌面端的转发逻辑收集全部输出。
+pub fn run_fake_worker(binary: &str, requests: &[Value], mode: Option<&str>) -> Vec<Result<WorkerMessage, Value>> {
+    let mut command = Command::new(binary);
+    if let Some(mode) = mode {
+        command.env(MODE_ENV, mode);
+    }
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("spawn fake worker");
+    {
+        let mut stdin = child.stdin.take().expect("fake worker stdin");
+        for request in requests {
+            writeln!(stdin, "{request}").expect("write request");
+        }
+    }
+    let mut events = Vec::new();
+    forward_worker_events(child.stdout.take().expect("fake worker stdout"), |event| events.push(event));
+    child.wait().expect("wait fake worker");
+    events
+}
+
+pub fn golden_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden").join(format!("{name}.json"))
+}
+
+/// 与 `tests/golden/<name>.json` 比对；设置 `UPDATE_GOLDEN=1` 时改写该文件。
+pub fn assert_golden(name: &str, actual: &Value) {
+    let path = golden_path(name);
+    let rendered = format!("{}\n", serde_json::to_string_pretty(actual).expect("serialize golden"));
+    if std::env::var(UPDATE_GOLDEN_ENV).as_deref() == Ok("1") {
+        fs::write(&path, rendered).expect("write golden");
+        return;
+    }
+    let expected = fs::read_to_string(&path)
+        .unwrap_or_else(|err| panic!("读取 golden 文件 {} 失败: {err}（可用 UPDATE_GOLDEN=1 生成）", path.display()));
+    assert_eq!(rendered, expected, "协议输出与 {} 不一致；确认变更后用 UPDATE_GOLDEN=1 更新", path.display());
+}
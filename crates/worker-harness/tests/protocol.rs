@@ -0,0 +1,249 @@
+use bulk_email_core::worker_ipc::{spawn_worker, WorkerTransport};
+use bulk_email_core::worker_protocol::{
+    forward_worker_events, missing_fields, run_worker_request_via, run_worker_request_with, worker_request,
+    RequestBody, ResidentWorker, WorkerRequest, WorkerRequestError, DEFAULT_REQUEST_TIMEOUT, EVENT_FIELDS,
+    PROTOCOL_VERSION, REQUEST_TYPES,
+};
+use proptest::prelude::*;
+use serde_json::{json, Map, Value};
+use std::collections::BTreeSet;
+use std::process::Command;
+use std::time::{Duration, Instant};
+use worker_harness::{assert_golden, run_fake_worker, CRASH_TRACEBACK, MODE_ENV};
+
+const FAKE_WORKER: &str = env!("CARGO_BIN_EXE_fake-worker");
+
+fn sample_payload(kind: &str) -> Value {
+    match kind {
+        "load_recipients" => json!({ "path": "recipients.json" }),
+        "test_smtp" => json!({ "host": "smtp.example.com", "port": 465 }),
+        "start_send" => json!({ "job_id": "golden-job" }),
+        "throttle" => json!({ "per_minute": 20, "min_delay_sec": 3 }),
+        "hello" => json!({ "framing": ["length_prefixed"] }),
+        _ => json!({}),
+    }
+}
+
+/// 固定请求 id，使 golden 记录不随请求计数变化；未知类型按原始 JSON 构造。
+fn sample_request(kind: &str) -> Value {
+    let payload = sample_payload(kind);
+    match RequestBody::from_kind(kind, payload.clone()) {
+        Some(body) => json!(WorkerRequest {
+            id: 1,
+            protocol: PROTOCOL_VERSION,
+            body,
+        }),
+        None => json!({ "id": 1, "protocol": PROTOCOL_VERSION, "type": kind, "payload": payload }),
+    }
+}
+
+#[test]
+fn every_request_type_matches_golden_transcript() {
+    let mut seen = BTreeSet::new();
+    for kind in REQUEST_TYPES.into_iter().chain(["unknown_request"]) {
+        let events = run_fake_worker(FAKE_WORKER, &[sample_request(kind)], None);
+        let events: Vec<Value> = events
+            .into_iter()
+            .map(|event| event.unwrap_or_else(|err| panic!("{kind} 输出了无法解析的行: {err}")))
+            .inspect(|message| assert_eq!(message.request_id, Some(1), "{kind} 的事件应指回请求"))
+            .map(|message| message.raw)
+            .collect();
+        for event in &events {
+            let missing = missing_fields(event).unwrap_or_else(|| panic!("{kind} 输出了未登记的事件: {event}"));
+            assert!(missing.is_empty(), "{kind} 的 {} 缺少字段 {missing:?}", event["type"]);
+            seen.insert(event["type"].as_str().unwrap_or_default().to_string());
+        }
+        assert_golden(&format!("request_{kind}"), &Value::Array(events));
+    }
+    let registered: BTreeSet<String> = EVENT_FIELDS.iter().map(|(kind, _)| kind.to_string()).collect();
+    assert_eq!(seen, registered, "golden 记录应覆盖全部事件类型");
+}
+
+#[test]
+fn event_field_table_matches_golden() {
+    let table: Map<String, Value> = EVENT_FIELDS
+        .iter()
+        .map(|(kind, fields)| (kind.to_string(), json!(fields)))
+        .collect();
+    assert_golden("event_fields", &Value::Object(table));
+}
+
+#[test]
+fn malformed_worker_output_does_not_stop_forwarding() {
+    let request = json!(worker_request(RequestBody::TestSmtp(json!({}))));
+    let events = run_fake_worker(FAKE_WORKER, &[request], Some("malformed"));
+    let valid: Vec<&str> =
+        events.iter().flatten().map(|message| message.raw["type"].as_str().unwrap_or_default()).collect();
+    assert_eq!(valid, ["recipient_sent", "job_finished", "smtp_test_succeeded"]);
+    assert_eq!(events.iter().filter(|event| event.is_err()).count(), 5);
+    assert!(events.iter().filter_map(|event| event.as_ref().err()).all(|error| error["type"] == "error"));
+}
+
+#[test]
+fn one_shot_request_reports_worker_stderr_when_it_crashes() {
+    let response = run_worker_request_with(Command::new(FAKE_WORKER), worker_request(RequestBody::Ping))
+        .expect("pong from fake worker");
+    assert_eq!(response["type"], "pong");
+
+    let mut crashing = Command::new(FAKE_WORKER);
+    crashing.env(MODE_ENV, "crash");
+    let error = run_worker_request_with(crashing, worker_request(RequestBody::Ping))
+        .expect_err("no response")
+        .to_string();
+    assert!(error.starts_with("worker 启动后没有应答握手"), "{error}");
+    assert!(error.ends_with(CRASH_TRACEBACK.trim_end()), "{error}");
+}
+
+#[test]
+fn handshake_rejects_incompatible_workers() {
+    for (mode, expected) in [("legacy", "请更新 worker"), ("future", "请更新应用")] {
+        let mut command = Command::new(FAKE_WORKER);
+        command.env(MODE_ENV, mode);
+        let error = run_worker_request_with(command, worker_request(RequestBody::Ping))
+            .expect_err("incompatible")
+            .to_string();
+        assert!(error.contains(expected), "{mode}: {error}");
+    }
+}
+
+#[test]
+fn framing_carries_large_payloads_and_falls_back_to_lines() {
+    // 超过常见行缓冲的载荷，原样出现在 `throttle_updated` 的 `limits` 里。
+    let note = "教师名单\n".repeat(200_000);
+    let body = RequestBody::Throttle(json!({ "per_minute": 20, "note": note }));
+    for mode in [None, Some("lines")] {
+        let mut command = Command::new(FAKE_WORKER);
+        if let Some(mode) = mode {
+            command.env(MODE_ENV, mode);
+        }
+        let response = run_worker_request_with(command, worker_request(body.clone())).expect("throttle response");
+        assert_eq!(response["limits"]["note"], note, "{mode:?}");
+    }
+}
+
+#[test]
+fn one_shot_request_works_over_local_socket() {
+    let request = worker_request(RequestBody::Ping);
+    let command = Command::new(FAKE_WORKER);
+    let response = run_worker_request_via(command, request.clone(), WorkerTransport::Socket, DEFAULT_REQUEST_TIMEOUT)
+        .expect("pong over local socket");
+    assert_eq!(response, json!({ "type": "pong", "protocol": PROTOCOL_VERSION, "request_id": request.id }));
+
+    let mut crashing = Command::new(FAKE_WORKER);
+    crashing.env(MODE_ENV, "crash");
+    let ping = worker_request(RequestBody::Ping);
+    let error = run_worker_request_via(crashing, ping, WorkerTransport::Socket, DEFAULT_REQUEST_TIMEOUT)
+        .expect_err("no response")
+        .to_string();
+    // 标准输出只是调试信息，连同标准错误附在错误信息后面。
+    assert!(error.contains("worker stdout:\nfake worker: connected over local socket"), "{error}");
+    assert!(error.ends_with(CRASH_TRACEBACK.trim_end()), "{error}");
+}
+
+#[test]
+fn resident_worker_answers_requests_in_one_process() {
+    for transport in [WorkerTransport::Stdio, WorkerTransport::Socket] {
+        let worker = spawn_worker(Command::new(FAKE_WORKER), transport).expect("spawn fake worker");
+        let mut resident = ResidentWorker::start(worker, DEFAULT_REQUEST_TIMEOUT).expect("handshake");
+        let pid = resident.id();
+        for body in [RequestBody::LoadRecipients(json!({ "path": "a.json" })), RequestBody::Ping] {
+            let request = worker_request(body);
+            let response = resident.request(&request).expect("response");
+            assert_eq!(response["request_id"], request.id, "{transport:?}");
+        }
+        assert!(resident.is_running() && resident.id() == pid);
+    }
+
+    let mut crashing = Command::new(FAKE_WORKER);
+    crashing.env(MODE_ENV, "crash");
+    let worker = spawn_worker(crashing, WorkerTransport::Stdio).expect("spawn crashing worker");
+    let error = ResidentWorker::start(worker, DEFAULT_REQUEST_TIMEOUT).err().expect("crashes before the handshake");
+    assert!(error.to_string().starts_with("worker 启动后没有应答握手"), "{error}");
+}
+
+#[test]
+fn requests_time_out_and_keep_partial_output() {
+    let timeout = Duration::from_millis(500);
+    for transport in [WorkerTransport::Stdio, WorkerTransport::Socket] {
+        let mut hanging = Command::new(FAKE_WORKER);
+        hanging.env(MODE_ENV, "hang");
+        let started = Instant::now();
+        let error = run_worker_request_via(hanging, worker_request(RequestBody::Ping), transport, timeout)
+            .expect_err("never answers");
+        assert!(started.elapsed() < Duration::from_secs(5), "{transport:?}");
+        let WorkerRequestError::Timeout { kind, partial_output, .. } = &error else {
+            panic!("{transport:?}: {error}");
+        };
+        assert_eq!(kind, "ping");
+        assert!(partial_output.contains("\"loading_progress\""), "{partial_output}");
+        assert!(partial_output.contains("worker stderr:\nfake worker: still working on \"ping\""), "{partial_output}");
+        assert!(error.to_string().starts_with("worker 未在 0.5 秒内应答 `ping` 请求"), "{error}");
+    }
+
+    let mut hanging = Command::new(FAKE_WORKER);
+    hanging.env(MODE_ENV, "hang");
+    let worker = spawn_worker(hanging, WorkerTransport::Stdio).expect("spawn hanging worker");
+    let error = ResidentWorker::start(worker, timeout).err().expect("resident ping times out");
+    assert!(matches!(error, WorkerRequestError::Timeout { .. }), "{error}");
+}
+
+/// 按字段的协议类型生成取值：计数与秒数为非负整数，列表与统计为 JSON 数组 / 对象，其余为字符串。
+fn valid_event() -> impl Strategy<Value = Value> {
+    (0..EVENT_FIELDS.len(), any::<String>(), any::<u32>()).prop_map(|(slot, text, number)| {
+        let (kind, fields) = EVENT_FIELDS[slot];
+        let mut event = Map::new();
+        event.insert("type".to_string(), json!(kind));
+        for field in fields.iter() {
+            let value = match *field {
+                "stats" => json!({ "total_rows": number }),
+                "recipients_preview" | "failures" => json!([{ "email": text }]),
+                "features" => json!([text]),
+                "index" | "next_index" | "total" | "success" | "failed" | "skipped" | "attempt" | "protocol"
+                | "min_protocol" | "retry_after_sec" | "delay_sec" | "remaining_sec" => json!(number),
+                _ => json!(text),
+            };
+            event.insert(field.to_string(), value);
+        }
+        Value::Object(event)
+    })
+}
+
+proptest! {
+    #[test]
+    fn arbitrary_output_yields_one_item_per_non_blank_line(bytes in proptest::collection::vec(any::<u8>(), 0..512)) {
+        let expected = bytes
+            .split(|byte| *byte == b'\n')
+            .filter(|line| !String::from_utf8_lossy(line).trim().is_empty())
+            .count();
+        let mut events = Vec::new();
+        forward_worker_events(&bytes[..], |event| events.push(event));
+        prop_assert_eq!(events.len(), expected);
+        for event in events {
+            let event = event.map(|message| message.raw).unwrap_or_else(|error| error);
+            prop_assert!(event["type"].is_string());
+        }
+    }
+
+    #[test]
+    fn valid_events_survive_interleaved_garbage(
+        events in proptest::collection::vec(valid_event(), 1..16),
+        garbage in proptest::collection::vec(proptest::collection::vec(any::<u8>(), 0..64), 16),
+    ) {
+        let mut output = Vec::new();
+        for (event, junk) in events.iter().zip(&garbage) {
+            output.extend_from_slice(event.to_string().as_bytes());
+            output.extend_from_slice(b"\r\n");
+            let junk: Vec<u8> = junk.iter().copied().filter(|byte| *byte != b'\n').collect();
+            output.extend_from_slice(b"\xff");
+            output.extend_from_slice(&junk);
+            output.push(b'\n');
+        }
+        let mut forwarded = Vec::new();
+        forward_worker_events(&output[..], |event| {
+            if let Ok(message) = event {
+                forwarded.push(message.raw);
+            }
+        });
+        prop_assert_eq!(forwarded, events);
+    }
+}
@@ -19,8 +19,8 @@ if [[ -f "$HOME/.cargo/env" ]]; then
   # shellcheck disable=SC1091
   source "$HOME/.cargo/env"
 fi
-cd "$ROOT_DIR/apps/desktop/src-tauri"
-cargo test -q
+cd "$ROOT_DIR"
+cargo test -q --workspace
 
 echo "[5/5] Tauri app bundle (debug)"
 cd "$ROOT_DIR/apps/desktop"
@@ -1,8 +1,11 @@
+import json
+import smtplib
 from dataclasses import replace
 from datetime import datetime
 from pathlib import Path
+from typing import Any
 
-from bulk_email_sender.engine import SendEngine
+from bulk_email_sender.engine import JobControl, SendEngine
 from bulk_email_sender.models import (
     JobConfig,
     Recipient,
@@ -117,6 +120,24 @@ def test_send_engine_fails_fast_when_attachment_missing(tmp_path: Path) -> None:
     assert smtp_client.sent_targets == []
 
 
+def test_send_engine_rejects_messages_over_the_server_size_limit(tmp_path: Path) -> None:
+    job = _build_job(tmp_path)
+    job = replace(
+        job,
+        smtp=replace(job.smtp, size_limit=20_000),
+        recipients=[job.recipients[0], Recipient(email="teacher2@example.com", name="李" * 3000)],
+    )
+    smtp_client = FakeSMTPClient()
+    engine = SendEngine(smtp_client=smtp_client, sent_store=SentStore(job.sent_store_file))
+
+    events = list(engine.send(job))
+    failed = [event for event in events if event["type"] == "recipient_failed"]
+
+    assert smtp_client.sent_targets == ["teacher1@example.com"]
+    assert [event["email"] for event in failed] == ["teacher2@example.com"]
+    assert "超过服务器 SIZE 上限 19.5 KB" in failed[0]["error"]
+
+
 def test_send_with_retry_resets_connection_on_failure(tmp_path: Path) -> None:
     """After a send failure the engine should reset the persistent connection
     before the next retry so we don't reuse a potentially broken socket."""
@@ -147,6 +168,60 @@ def test_send_with_retry_resets_connection_on_failure(tmp_path: Path) -> None:
     assert smtp_client.reset_calls == 1
 
 
+def test_send_engine_defers_greylisted_recipients_instead_of_failing(tmp_path: Path) -> None:
+    job = _build_job(tmp_path)
+    job = replace(job, options=replace(job.options, retry_count=3, greylist_delay_sec=0))
+    calls = {"count": 0}
+
+    class GreylistOnceSMTPClient(FakeSMTPClient):
+        def send(self, recipient_email: str, message: object) -> None:
+            calls["count"] += 1
+            if calls["count"] == 1:
+                raise smtplib.SMTPRecipientsRefused(
+                    {recipient_email: (451, b"4.7.1 Greylisted, please try again later")}
+                )
+            super().send(recipient_email, message)
+
+    smtp_client = GreylistOnceSMTPClient()
+    sent_store = SentStore(job.sent_store_file)
+    engine = SendEngine(smtp_client=smtp_client, sent_store=sent_store, sleep_func=lambda _: None)
+
+    events = list(engine.send(job))
+
+    deferred = [event for event in events if event["type"] == "recipient_deferred"]
+    assert len(deferred) == 1
+    assert deferred[0]["email"] == "teacher1@example.com"
+    assert deferred[0]["domain"] == "example.com"
+    assert deferred[0]["attempt"] == 1
+    # Greylisting skips the immediate retries and the connection reset.
+    assert smtp_client.reset_calls == 0
+    retried = [event for event in events if event["type"] == "recipient_started" and "attempt" in event]
+    assert [event["email"] for event in retried] == ["teacher1@example.com"]
+    assert smtp_client.sent_targets == ["teacher2@example.com", "teacher1@example.com"]
+    finished = events[-1]
+    assert finished["type"] == "job_finished"
+    assert finished["success"] == 2
+    assert finished["failed"] == 0
+    assert finished["deferred"] == 1
+
+
+def test_send_engine_records_stable_message_ids(tmp_path: Path) -> None:
+    job = _build_job(tmp_path)
+    job = replace(job, options=replace(job.options, message_id_domain="Mail.Example.com"))
+    smtp_client = FakeSMTPClient()
+    sent_store = SentStore(job.sent_store_file)
+    engine = SendEngine(smtp_client=smtp_client, sent_store=sent_store, sleep_func=lambda _: None)
+
+    list(engine.send(job))
+
+    records = [json.loads(line) for line in job.sent_store_file.read_text(encoding="utf-8").splitlines()]
+    message_ids = [message["Message-ID"] for message in smtp_client.messages]
+    assert [record["message_id"] for record in records] == message_ids
+    assert all(message_id.startswith("<job-1.") for message_id in message_ids)
+    assert all(message_id.endswith("@mail.example.com>") for message_id in message_ids)
+    assert len(set(message_ids)) == 2
+
+
 def test_send_engine_waits_full_delay_even_when_send_is_slow(tmp_path: Path, monkeypatch) -> None:
     job = _build_job(tmp_path)
     job = replace(job, options=replace(job.options, min_delay_sec=5, max_delay_sec=5))
@@ -295,3 +370,101 @@ def test_send_engine_normalizes_signature_placeholders_to_trailing_block(tmp_pat
     assert "text-align:center" in html_text
     assert html_text.count("学生张三") == 1
     assert html_text.count(expected_date) == 1
+
+
+def test_send_engine_events_carry_fields_required_by_desktop_forwarder(tmp_path: Path) -> None:
+    # Shared with the Rust protocol harness (crates/worker-harness); regenerate with UPDATE_GOLDEN=1.
+    golden = Path(__file__).resolve().parents[1] / "crates/worker-harness/tests/golden/event_fields.json"
+    required_fields = json.loads(golden.read_text(encoding="utf-8"))
+    job = _build_job(tmp_path)
+    job = replace(job, options=replace(job.options, retry_count=3, greylist_delay_sec=0))
+    sent_store = SentStore(job.sent_store_file)
+    sent_store.append(email="teacher2@example.com", teacher_name="李教授", job_id="before")
+
+    calls = {"count": 0}
+
+    class GreylistOnceSMTPClient(FakeSMTPClient):
+        def send(self, recipient_email: str, message: object) -> None:
+            calls["count"] += 1
+            if calls["count"] == 1:
+                raise smtplib.SMTPRecipientsRefused({recipient_email: (451, b"4.7.1 Greylisted")})
+            super().send(recipient_email, message)
+
+    engine = SendEngine(smtp_client=GreylistOnceSMTPClient(), sent_store=sent_store, sleep_func=lambda _: None)
+    events = list(engine.send(job))
+
+    seen = {event["type"] for event in events}
+    assert {"job_started", "recipient_started", "recipient_skipped", "recipient_deferred", "job_finished"} <= seen
+    for event in events:
+        assert event["type"] in required_fields, event
+        missing = [field for field in required_fields[event["type"]] if field not in event]
+        assert not missing, (event["type"], missing)
+
+
+def test_send_engine_seed_replays_order_and_delays(tmp_path: Path) -> None:
+    job = _build_job(tmp_path)
+    recipients = [Recipient(email=f"t{index}@example.com", name=f"T{index}") for index in range(8)]
+    options = replace(job.options, randomize_order=True, min_delay_sec=1, max_delay_sec=30, skip_sent=False, seed=7)
+    job = replace(job, recipients=recipients, options=options)
+
+    def plan() -> list[tuple[str, Any]]:
+        sent_store = SentStore(tmp_path / "seeded.jsonl")
+        engine = SendEngine(smtp_client=FakeSMTPClient(), sent_store=sent_store, sleep_func=lambda _: None)
+        events = list(engine.send(job))
+        assert events[0]["seed"] == 7
+        return [
+            (event["type"], event.get("email") or event.get("delay_sec"))
+            for event in events
+            if event["type"] in {"recipient_sent", "inter_send_wait"}
+        ]
+
+    assert plan() == plan()
+
+
+def test_send_engine_renders_recipient_fields_without_overriding_builtins(tmp_path: Path) -> None:
+    job = _build_job(tmp_path)
+    job = replace(
+        job,
+        template=Template(subject="{company} / {teacher_name}", body_text="来自 {company}", body_html=None),
+        recipients=[
+            Recipient(
+                email="teacher@example.com",
+                name="魏中信",
+                fields={"company": "示例大学", "teacher_name": "覆盖"},
+            )
+        ],
+    )
+    smtp_client = FakeSMTPClient()
+    engine = SendEngine(smtp_client=smtp_client, sent_store=SentStore(job.sent_store_file))
+
+    list(engine.send(job))
+
+    message = smtp_client.messages[0]
+    assert message["Subject"] == "示例大学 / 魏中信"
+    plain = message.get_body(preferencelist=("plain",))
+    assert plain is not None
+    assert "来自 示例大学" in plain.get_content()
+
+
+def test_send_engine_pauses_before_next_recipient_and_applies_throttle(tmp_path: Path) -> None:
+    job = _build_job(tmp_path)
+    smtp_client = FakeSMTPClient()
+    engine = SendEngine(smtp_client=smtp_client, sent_store=SentStore(job.sent_store_file))
+    control = JobControl()
+    control.pause()
+
+    events = engine.send(job, control=control)
+    assert next(events)["type"] == "job_started"
+    assert next(events) == {"type": "job_paused", "job_id": "job-1", "next_index": 1}
+    assert smtp_client.sent_targets == []
+
+    control.resume()
+    control.set_throttle(per_minute=20)
+    assert next(events)["type"] == "job_resumed"
+    wait = next(event for event in events if event["type"] == "inter_send_wait")
+    # 20 per minute is at least 3 seconds apart, even though the job itself has no delay.
+    assert wait["delay_sec"] == 3
+
+    control.cancel()
+    assert list(events)[-1]["type"] == "job_cancelled"
+    assert smtp_client.sent_targets == ["teacher1@example.com"]
@@ -19,3 +19,14 @@ def test_sent_store_writes_human_readable_records(tmp_path: Path) -> None:
     assert "李教授" in text
     assert "job-1" in text
     assert "job-2" in text
+
+
+def test_sent_store_stamps_environment_label(tmp_path: Path) -> None:
+    jsonl_path = tmp_path / "sent_records.jsonl"
+    txt_path = tmp_path / "sent_records.txt"
+
+    store = SentStore(jsonl_path, text_path=txt_path, environment="test")
+    store.append(email="teacher@example.com", teacher_name="张教授", job_id="job-1")
+
+    assert '"environment": "test"' in jsonl_path.read_text(encoding="utf-8")
+    assert "环境: 测试" in txt_path.read_text(encoding="utf-8")
@@ -1,22 +1,33 @@
+import smtplib
+import ssl
+from dataclasses import replace
 from email.message import EmailMessage
 
 import pytest
 
+from bulk_email_sender.dsn import parse_dsn_options
 from bulk_email_sender.models import SMTPConfig
-from bulk_email_sender.smtp_client import SMTPClient
+from bulk_email_sender.smtp_client import SMTPAuthError, SMTPClient
 
 
 class FakeSMTPServer:
     def __init__(self) -> None:
         self.login_calls: list[tuple[str, str]] = []
         self.starttls_calls = 0
+        self.starttls_context: ssl.SSLContext | None = None
         self.send_calls = 0
+        self.noop_calls = 0
 
     def login(self, username: str, password: str) -> None:
         self.login_calls.append((username, password))
 
-    def starttls(self) -> None:
+    def noop(self) -> tuple[int, bytes]:
+        self.noop_calls += 1
+        return 250, b"OK"
+
+    def starttls(self, context: ssl.SSLContext | None = None) -> None:
         self.starttls_calls += 1
+        self.starttls_context = context
 
     def send_message(self, _message: EmailMessage) -> dict[str, str]:
         self.send_calls += 1
@@ -97,6 +108,70 @@ def test_smtp_client_uses_starttls_when_enabled(monkeypatch: pytest.MonkeyPatch)
 
     assert server.starttls_calls == 1
     assert server.login_calls == [("sender@example.com", "auth-code")]
+    assert server.noop_calls == 1
+
+
+def test_smtp_client_applies_custom_tls_verification(monkeypatch: pytest.MonkeyPatch) -> None:
+    server = FakeSMTPServer()
+    monkeypatch.setattr("smtplib.SMTP", lambda host, port, timeout: server)
+    config = SMTPConfig(
+        host="mail.internal.example",
+        port=587,
+        username="",
+        password="",
+        use_ssl=False,
+        use_starttls=True,
+        accept_invalid_certs=True,
+    )
+
+    SMTPClient(config).test_connection()
+
+    assert server.starttls_context is not None
+    assert server.starttls_context.verify_mode == ssl.CERT_NONE
+    assert not server.starttls_context.check_hostname
+
+    broken = replace(config, accept_invalid_certs=False, ca_cert_pem="-----BEGIN CERTIFICATE-----\nbroken\n")
+    with pytest.raises(ValueError, match="自定义根证书格式错误"):
+        SMTPClient(broken).test_connection()
+
+
+def test_smtp_client_enforces_minimum_tls_version(monkeypatch: pytest.MonkeyPatch) -> None:
+    server = FakeSMTPServer()
+    monkeypatch.setattr("smtplib.SMTP", lambda host, port, timeout: server)
+    config = SMTPConfig(
+        host="smtp.example.com",
+        port=587,
+        username="",
+        password="",
+        use_ssl=False,
+        use_starttls=True,
+        min_tls_version="1.3",
+    )
+
+    assert SMTPClient(config).test_connection() is None
+
+    assert server.starttls_context is not None
+    assert server.starttls_context.minimum_version == ssl.TLSVersion.TLSv1_3
+    assert server.starttls_context.verify_mode == ssl.CERT_REQUIRED
+
+    with pytest.raises(ValueError, match="1.2 或 1.3"):
+        SMTPClient(replace(config, min_tls_version="1.1")).test_connection()
+
+
+def test_smtp_client_test_connection_reports_rejected_credentials(monkeypatch: pytest.MonkeyPatch) -> None:
+    class RejectingServer(FakeSMTPServer):
+        def login(self, username: str, password: str) -> None:
+            raise smtplib.SMTPAuthenticationError(535, b"5.7.8 Authentication credentials invalid")
+
+    server = RejectingServer()
+    monkeypatch.setattr("smtplib.SMTP_SSL", lambda host, port, timeout: server)
+    client = SMTPClient(
+        SMTPConfig(host="smtp.example.com", port=465, username="sender@example.com", password="wrong", use_ssl=True)
+    )
+
+    with pytest.raises(SMTPAuthError, match="535"):
+        client.test_connection()
+    assert server.noop_calls == 0
 
 
 def test_smtp_client_skips_login_without_credentials(monkeypatch: pytest.MonkeyPatch) -> None:
@@ -127,3 +202,100 @@ def test_smtp_client_skips_login_without_credentials(monkeypatch: pytest.MonkeyP
     assert server.login_calls == []
     assert server.starttls_calls == 0
     assert server.send_calls == 1
+
+
+def test_smtp_client_applies_ehlo_hostname_and_return_path(monkeypatch: pytest.MonkeyPatch) -> None:
+    server = FakeSMTPServer()
+    from_addrs: list[str] = []
+
+    def fake_smtp(host: str, port: int, timeout: int, local_hostname: str):
+        assert local_hostname == "mail.example.edu"
+        return server
+
+    def send_message(_message: EmailMessage, from_addr: str) -> dict[str, str]:
+        from_addrs.append(from_addr)
+        return {}
+
+    server.send_message = send_message  # type: ignore[method-assign]
+    monkeypatch.setattr("smtplib.SMTP", fake_smtp)
+
+    client = SMTPClient(
+        SMTPConfig(
+            host="relay.example.edu",
+            port=25,
+            username="",
+            password="",
+            use_ssl=False,
+            ehlo_hostname="mail.example.edu",
+            return_path="bounces@example.edu",
+        )
+    )
+
+    client.send("teacher@example.com", _sample_message())
+
+    assert from_addrs == ["bounces@example.edu"]
+
+
+def test_smtp_client_requests_dsn_only_when_advertised(monkeypatch: pytest.MonkeyPatch) -> None:
+    server = FakeSMTPServer()
+    extensions: set[str] = {"dsn"}
+    sent_options: list[dict[str, object]] = []
+
+    def send_message(_message: EmailMessage, **options: object) -> dict[str, str]:
+        sent_options.append(options)
+        return {}
+
+    server.send_message = send_message  # type: ignore[method-assign]
+    server.ehlo_or_helo_if_needed = lambda: None  # type: ignore[attr-defined]
+    server.has_extn = lambda name: name in extensions  # type: ignore[attr-defined]
+    monkeypatch.setattr("smtplib.SMTP", lambda host, port, timeout: server)
+
+    client = SMTPClient(
+        SMTPConfig(host="relay.example.edu", port=25, username="", password="", use_ssl=False),
+        dsn=parse_dsn_options({"notify": ["failure", "success"], "ret": "headers"}),
+    )
+    message = _sample_message()
+    message["Message-ID"] = "<job-1.abc+1@example.edu>"
+
+    client.send("teacher@example.com", message)
+    extensions.clear()
+    client.send("teacher@example.com", message)
+
+    assert sent_options == [
+        {
+            "mail_options": ["RET=HDRS", "ENVID=job-1.abc+2B1@example.edu"],
+            "rcpt_options": ["NOTIFY=FAILURE,SUCCESS"],
+        },
+        {},
+    ]
+    with pytest.raises(ValueError):
+        parse_dsn_options({"notify": ["never", "failure"]})
+
+
+def test_smtp_client_uses_requested_auth_mechanism(monkeypatch: pytest.MonkeyPatch) -> None:
+    server = FakeSMTPServer()
+    auth_calls: list[tuple[str, str, str]] = []
+
+    def auth(mechanism: str, authobject) -> None:
+        auth_calls.append((mechanism, server.user, server.password))  # type: ignore[attr-defined]
+        assert authobject == server.auth_cram_md5  # type: ignore[attr-defined]
+
+    server.ehlo_or_helo_if_needed = lambda: None  # type: ignore[attr-defined]
+    server.auth = auth  # type: ignore[attr-defined]
+    server.auth_cram_md5 = lambda challenge=None: ""  # type: ignore[attr-defined]
+    monkeypatch.setattr("smtplib.SMTP", lambda host, port, timeout: server)
+
+    client = SMTPClient(
+        SMTPConfig(
+            host="relay.example.edu",
+            port=587,
+            username="sender@example.edu",
+            password="secret",
+            use_ssl=False,
+            auth_mechanism="cram_md5",
+        )
+    )
+    client.send("teacher@example.com", _sample_message())
+
+    assert auth_calls == [("CRAM-MD5", "sender@example.edu", "secret")]
+    assert server.login_calls == []
@@ -1,9 +1,14 @@
+import io
 import json
+import socket
 import threading
 from pathlib import Path
 
+import pytest
+
+from bulk_email_sender.engine import JobControl
 from bulk_email_sender.models import JobConfig, Recipient, Sender, SendOptions, SMTPConfig, Template
-from bulk_email_sender.worker import Worker, _build_job_config
+from bulk_email_sender.worker import IPC_ADDRESS_ENV, PROTOCOL_VERSION, Worker, _build_job_config, main
 
 
 class DummyWriter:
@@ -73,6 +78,127 @@ def test_worker_unknown_command_returns_error() -> None:
     assert writer.lines[-1]["type"] == "error"
 
 
+def test_worker_test_smtp_reports_auth_failures_separately(monkeypatch) -> None:
+    import smtplib
+
+    class RejectingServer:
+        def login(self, username, password):
+            raise smtplib.SMTPAuthenticationError(535, b"5.7.8 Authentication credentials invalid")
+
+        def quit(self):
+            pass
+
+    payload = {"host": "smtp.example.com", "port": 465, "username": "sender@example.com", "password": "wrong"}
+    writer = DummyWriter()
+    worker = Worker(writer=writer)
+    monkeypatch.setattr("smtplib.SMTP_SSL", lambda host, port, timeout: RejectingServer())
+    worker.handle_message({"type": "test_smtp", "protocol": PROTOCOL_VERSION, "payload": payload})
+
+    def refuse(host, port, timeout):
+        raise ConnectionRefusedError("connection refused")
+
+    monkeypatch.setattr("smtplib.SMTP_SSL", refuse)
+    worker.handle_message({"type": "test_smtp", "protocol": PROTOCOL_VERSION, "payload": payload})
+
+    assert [(line["type"], line["stage"]) for line in writer.lines] == [("error", "auth"), ("error", "connect")]
+    assert "535" in writer.lines[0]["error"]
+
+
+def test_worker_echoes_request_id_and_answers_ping() -> None:
+    writer = DummyWriter()
+    worker = Worker(writer=writer)
+    worker.handle_message({"id": 7, "type": "ping", "protocol": 2})
+    worker.handle_message({"id": 8, "type": "cancel", "protocol": 2, "payload": {}})
+    worker.handle_message({"id": 9, "type": "ping", "protocol": PROTOCOL_VERSION + 1})
+
+    assert writer.lines[0] == {"type": "pong", "protocol": PROTOCOL_VERSION, "request_id": 7}
+    assert writer.lines[1]["type"] == "error" and writer.lines[1]["request_id"] == 8
+    assert writer.lines[2]["type"] == "error" and "protocol" in writer.lines[2]["error"]
+
+
+def test_worker_hello_reports_protocol_range_and_features() -> None:
+    golden = Path(__file__).resolve().parents[1] / "crates/worker-harness/tests/golden/request_hello.json"
+    rust_hello = json.loads(golden.read_text(encoding="utf-8"))[0]
+    writer = DummyWriter()
+    Worker(writer=writer).handle_message({"id": 1, "type": "hello", "protocol": PROTOCOL_VERSION})
+
+    hello = writer.lines[0]
+    assert hello["type"] == "hello" and hello["request_id"] == 1
+    assert hello["protocol"] == rust_hello["protocol"]
+    assert hello["min_protocol"] <= 2 <= hello["protocol"]
+    # Every request type the desktop app may send is advertised.
+    assert set(rust_hello["features"]) <= set(hello["features"])
+
+
+def test_worker_control_messages_require_an_active_job_and_valid_limits() -> None:
+    writer = DummyWriter()
+    worker = Worker(writer=writer)
+    for message_type in ("pause", "resume", "throttle"):
+        worker.handle_message({"id": 1, "type": message_type, "protocol": 2, "payload": {}})
+
+    assert [line["error"] for line in writer.lines] == ["No active job"] * 3
+
+    release = threading.Event()
+    worker._job_thread = threading.Thread(target=release.wait)
+    worker._job_thread.start()
+    worker._control = JobControl()
+    worker.handle_message({"id": 2, "type": "pause", "protocol": 2})
+    worker.handle_message({"id": 3, "type": "throttle", "protocol": 2, "payload": {"per_minute": 0}})
+    worker.handle_message({"id": 4, "type": "throttle", "protocol": 2, "payload": {"min_delay_sec": 5}})
+
+    assert writer.lines[3] == {"type": "pause_requested", "request_id": 2}
+    assert worker._control.paused
+    assert writer.lines[4]["type"] == "error" and "per_minute" in writer.lines[4]["error"]
+    assert writer.lines[5]["type"] == "throttle_updated"
+    assert writer.lines[5]["limits"]["min_delay_sec"] == 5
+    release.set()
+
+
+@pytest.mark.skipif(not hasattr(socket, "AF_UNIX"), reason="unix sockets only")
+def test_worker_main_talks_over_local_socket(tmp_path: Path, monkeypatch) -> None:
+    address = str(tmp_path / "worker.sock")
+    server = socket.socket(socket.AF_UNIX, socket.SOCK_STREAM)
+    server.bind(address)
+    server.listen(2)
+    monkeypatch.setenv(IPC_ADDRESS_ENV, address)
+    thread = threading.Thread(target=main)
+    thread.start()
+
+    events, _ = server.accept()
+    requests, _ = server.accept()
+    requests.sendall(b'{"id": 5, "type": "ping", "protocol": 3}\n')
+    requests.close()
+    thread.join(timeout=5)
+
+    lines = events.makefile("r", encoding="utf-8").read().splitlines()
+    assert [json.loads(line) for line in lines] == [{"type": "pong", "protocol": PROTOCOL_VERSION, "request_id": 5}]
+    server.close()
+
+
+def _frame(payload) -> bytes:
+    body = json.dumps(payload, ensure_ascii=False).encode("utf-8")
+    return b"@%d\n" % len(body) + body + b"\n"
+
+
+def test_worker_main_switches_to_frames_after_hello(monkeypatch) -> None:
+    hello = {"id": 1, "type": "hello", "protocol": PROTOCOL_VERSION, "payload": {"framing": ["length_prefixed"]}}
+    # Far beyond a typical line buffer, with raw newlines inside the frame's JSON.
+    note = "教师名单\n" * 50_000
+    ping = json.dumps({"id": 2, "type": "ping", "protocol": PROTOCOL_VERSION, "payload": {"note": note}}, indent=2)
+    stdin = json.dumps(hello).encode("utf-8") + b"\nstray debug output\n"
+    stdin += b"@%d\n" % len(ping.encode("utf-8")) + ping.encode("utf-8")
+    stdout = io.BytesIO()
+    monkeypatch.setattr("sys.stdin", io.TextIOWrapper(io.BytesIO(stdin)))
+    monkeypatch.setattr("sys.stdout", io.TextIOWrapper(stdout))
+    main()
+
+    output = stdout.getvalue()
+    first, rest = output.split(b"\n", 1)
+    assert json.loads(first)["framing"] == "length_prefixed"
+    error = {"type": "error", "error": "Invalid frame header: stray debug output"}
+    assert rest == _frame(error) + _frame({"type": "pong", "protocol": PROTOCOL_VERSION, "request_id": 2})
+
+
 def test_worker_run_job_emits_error_on_unexpected_exception(tmp_path: Path) -> None:
     writer = DummyWriter()
     worker = Worker(writer=writer)